@@ -0,0 +1,105 @@
+//! Measures the time spent in `Host::service` receiving bursts of packets and dispatching them as
+//! `Event::Receive`, from datagrams already waiting in the socket.
+//!
+//! Run with `cargo bench --bench dispatch`.
+
+use std::{
+    convert::Infallible,
+    hint::black_box,
+    net::{Ipv4Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rusty_enet as enet;
+
+type Socket = enet::ReadWrite<SocketAddr, Infallible>;
+
+const BURST: usize = 64;
+
+fn address() -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::LOCALHOST, 1))
+}
+
+fn transfer(from: &mut enet::Host<Socket>, to: &mut enet::Host<Socket>) {
+    while let Some((_, data)) = from.socket_mut().read() {
+        to.socket_mut().write(address(), data);
+    }
+}
+
+struct Hosts {
+    server: enet::Host<Socket>,
+    client: enet::Host<Socket>,
+    peer: enet::PeerID,
+}
+
+impl Hosts {
+    fn new() -> Self {
+        let mut server = enet::Host::new(Socket::new(), enet::HostSettings::default()).unwrap();
+        let mut client = enet::Host::new(Socket::new(), enet::HostSettings::default()).unwrap();
+        let peer = client.connect(address(), 1, 0).unwrap().peer_id();
+        for _ in 0..4 {
+            while client.service().unwrap().is_some() {}
+            transfer(&mut client, &mut server);
+            while server.service().unwrap().is_some() {}
+            transfer(&mut server, &mut client);
+        }
+        Self {
+            server,
+            client,
+            peer,
+        }
+    }
+
+    /// Queue a burst of packets in the server's socket, after handing the client any
+    /// acknowledgements from the previous burst.
+    fn queue_burst(&mut self, packet: &enet::Packet) {
+        transfer(&mut self.server, &mut self.client);
+        while self.client.service().unwrap().is_some() {}
+        let peer = self.client.peer_mut(self.peer).unwrap();
+        for _ in 0..BURST {
+            peer.send(0, packet).unwrap();
+            peer.flush();
+        }
+        transfer(&mut self.client, &mut self.server);
+    }
+
+    fn receive_burst(&mut self) {
+        let mut received = 0;
+        while let Some(event) = self.server.service().unwrap() {
+            if let enet::Event::Receive { packet, .. } = event {
+                black_box(packet);
+                received += 1;
+            }
+        }
+        assert_eq!(received, BURST);
+    }
+}
+
+fn dispatch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dispatch");
+    group.throughput(Throughput::Elements(BURST as u64));
+    for (name, packet) in [
+        ("unreliable_32", enet::Packet::unreliable(&[0; 32])),
+        ("reliable_32", enet::Packet::reliable(&[0; 32])),
+        ("reliable_1024", enet::Packet::reliable(&[0; 1024])),
+    ] {
+        let mut hosts = Hosts::new();
+        group.bench_function(name, |b| {
+            b.iter_custom(|iterations| {
+                let mut elapsed = Duration::ZERO;
+                for _ in 0..iterations {
+                    hosts.queue_burst(&packet);
+                    let start = Instant::now();
+                    hosts.receive_burst();
+                    elapsed += start.elapsed();
+                }
+                elapsed
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
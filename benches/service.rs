@@ -0,0 +1,108 @@
+//! Measures the time spent in `Host::service` delivering reliable packets between in-memory
+//! hosts, including queueing acknowledgements and dispatching events.
+//!
+//! Run with `cargo bench --bench service`.
+
+use std::{
+    convert::Infallible,
+    hint::black_box,
+    net::{Ipv4Addr, SocketAddr},
+    time::Instant,
+};
+
+use rusty_enet as enet;
+
+type Socket = enet::ReadWrite<SocketAddr, Infallible>;
+
+const ROUNDS: usize = 500;
+
+fn address(port: u16) -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::LOCALHOST, port))
+}
+
+struct Hosts {
+    server: enet::Host<Socket>,
+    clients: Vec<(enet::Host<Socket>, enet::PeerID)>,
+}
+
+impl Hosts {
+    fn new(client_count: u16) -> Self {
+        let settings = || enet::HostSettings {
+            peer_limit: usize::from(client_count),
+            ..Default::default()
+        };
+        let server = enet::Host::new(Socket::new(), settings()).unwrap();
+        let clients = (0..client_count)
+            .map(|_| {
+                let mut client = enet::Host::new(Socket::new(), settings()).unwrap();
+                let peer = client.connect(address(0), 1, 0).unwrap().peer_id();
+                (client, peer)
+            })
+            .collect();
+        let mut hosts = Self { server, clients };
+        hosts.update();
+        hosts
+    }
+
+    /// Service every host and exchange datagrams until nothing is left in flight, returning the
+    /// number of packets received by the server.
+    fn update(&mut self) -> usize {
+        let mut received = 0;
+        loop {
+            let mut busy = false;
+            for (index, (client, _)) in self.clients.iter_mut().enumerate() {
+                while client.service().unwrap().is_some() {}
+                while let Some((_, data)) = client.socket_mut().read() {
+                    self.server
+                        .socket_mut()
+                        .write(address(index as u16 + 1), data);
+                    busy = true;
+                }
+            }
+            while let Some(event) = self.server.service().unwrap() {
+                if let enet::Event::Receive { packet, .. } = event {
+                    black_box(packet);
+                    received += 1;
+                }
+            }
+            while let Some((to, data)) = self.server.socket_mut().read() {
+                let (client, _) = &mut self.clients[usize::from(to.port() - 1)];
+                client.socket_mut().write(address(0), data);
+                busy = true;
+            }
+            if !busy {
+                return received;
+            }
+        }
+    }
+}
+
+fn bench(name: &str, client_count: u16, packets_per_client: usize) {
+    let mut hosts = Hosts::new(client_count);
+    let packet = enet::Packet::reliable(&[0; 32]);
+    let start = Instant::now();
+    let mut received = 0;
+    for _ in 0..ROUNDS {
+        for (client, peer) in &mut hosts.clients {
+            let peer = client.peer_mut(*peer).unwrap();
+            for _ in 0..packets_per_client {
+                peer.send(0, &packet).unwrap();
+            }
+        }
+        received += hosts.update();
+    }
+    let elapsed = start.elapsed();
+    assert_eq!(
+        received,
+        ROUNDS * usize::from(client_count) * packets_per_client
+    );
+    println!(
+        "{name:<24} {:>8.0} ns/packet",
+        elapsed.as_nanos() as f64 / received as f64
+    );
+}
+
+fn main() {
+    bench("reliable_burst", 1, 64);
+    bench("many_peers", 64, 4);
+}
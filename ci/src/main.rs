@@ -16,6 +16,7 @@ bitflags! {
         const DOC_TEST = 0b00100000;
         const DOC_CHECK = 0b01000000;
         const CLIPPY = 0b10000000;
+        const CONFORMANCE = 0b100000000;
     }
 }
 
@@ -29,6 +30,7 @@ fn main() -> anyhow::Result<()> {
         ("doc-test", Check::DOC_TEST),
         ("doc-check", Check::DOC_CHECK),
         ("clippy", Check::CLIPPY),
+        ("conformance", Check::CONFORMANCE),
     ];
 
     let what_to_run = if let Some(arg) = std::env::args().nth(1).as_deref() {
@@ -44,15 +46,42 @@ fn main() -> anyhow::Result<()> {
             );
         }
     } else {
-        Check::all()
+        // the conformance tests fetch C ENet, so they only run when asked for
+        Check::all() - Check::CONFORMANCE
     };
 
     let sh = Shell::new()?;
     if what_to_run.contains(Check::CHECK) {
-        check(&sh, Target::Default, Features(&["std", "connected"]))?;
+        check(
+            &sh,
+            Target::Default,
+            Features(&[
+                "std",
+                "connected",
+                "zstd",
+                "deflate",
+                "crypto",
+                "noise",
+                "serde",
+                "postcard",
+                "bincode",
+                "bevy",
+                "mmsg",
+                "offload",
+                "sharding",
+                "fuzzing",
+                "proptest",
+                "arbitrary",
+                "capi",
+            ]),
+        )?;
     }
     if what_to_run.contains(Check::WASM_CHECK) {
-        check(&sh, Target::Wasm, Features(&["std", "connected"]))?;
+        check(
+            &sh,
+            Target::Wasm,
+            Features(&["std", "connected", "deflate", "crypto", "serde", "postcard"]),
+        )?;
     }
     if what_to_run.contains(Check::EXAMPLE_CHECK) {
         example_check(&sh)?;
@@ -72,6 +101,9 @@ fn main() -> anyhow::Result<()> {
     if what_to_run.contains(Check::CLIPPY) {
         clippy(&sh)?;
     }
+    if what_to_run.contains(Check::CONFORMANCE) {
+        conformance(&sh)?;
+    }
     Ok(())
 }
 
@@ -124,3 +156,30 @@ fn clippy(sh: &Shell) -> anyhow::Result<()> {
     cmd!(sh, "cargo clippy --workspace --all-targets -- -D warnings").run()?;
     Ok(())
 }
+
+/// The C ENet release the conformance tests are run against.
+const ENET_VERSION: &str = "v1.3.18";
+
+fn conformance(sh: &Shell) -> anyhow::Result<()> {
+    let source = match std::env::var("ENET_SOURCE_DIR") {
+        Ok(source) => source,
+        Err(_) => {
+            let source = "target/enet";
+            if !sh.path_exists(source) {
+                cmd!(
+                    sh,
+                    "git clone --depth 1 --branch {ENET_VERSION} https://github.com/lsalzman/enet {source}"
+                )
+                .run()?;
+            }
+            sh.current_dir().join(source).display().to_string()
+        }
+    };
+    cmd!(
+        sh,
+        "cargo test --manifest-path conformance/Cargo.toml --features c-enet"
+    )
+    .env("ENET_SOURCE_DIR", source)
+    .run()?;
+    Ok(())
+}
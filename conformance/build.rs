@@ -0,0 +1,52 @@
+//! Compiles the C ENet library from `ENET_SOURCE_DIR`, a checkout of
+//! <https://github.com/lsalzman/enet>, along with the shim in `src/shim.c`.
+
+#[cfg(feature = "c-enet")]
+fn main() {
+    use std::{env, path::PathBuf};
+
+    println!("cargo:rerun-if-env-changed=ENET_SOURCE_DIR");
+    println!("cargo:rerun-if-changed=src/shim.c");
+    let source = PathBuf::from(env::var_os("ENET_SOURCE_DIR").expect(
+        "ENET_SOURCE_DIR should point to a checkout of https://github.com/lsalzman/enet.",
+    ));
+    let windows = env::var("CARGO_CFG_TARGET_OS").is_ok_and(|os| os == "windows");
+    let mut build = cc::Build::new();
+    build.include(source.join("include"));
+    for file in [
+        "callbacks.c",
+        "compress.c",
+        "host.c",
+        "list.c",
+        "packet.c",
+        "peer.c",
+        "protocol.c",
+        if windows { "win32.c" } else { "unix.c" },
+    ] {
+        println!("cargo:rerun-if-changed={}", source.join(file).display());
+        build.file(source.join(file));
+    }
+    if !windows {
+        // what ENet's configure script detects on any modern unix
+        for define in [
+            "HAS_FCNTL",
+            "HAS_POLL",
+            "HAS_GETADDRINFO",
+            "HAS_GETNAMEINFO",
+            "HAS_INET_PTON",
+            "HAS_INET_NTOP",
+            "HAS_MSGHDR_FLAGS",
+            "HAS_SOCKLEN_T",
+        ] {
+            build.define(define, "1");
+        }
+    }
+    build.file("src/shim.c").warnings(false).compile("enet");
+    if windows {
+        println!("cargo:rustc-link-lib=ws2_32");
+        println!("cargo:rustc-link-lib=winmm");
+    }
+}
+
+#[cfg(not(feature = "c-enet"))]
+fn main() {}
@@ -0,0 +1,245 @@
+use std::{
+    ffi::{c_int, c_void},
+    net::{Ipv4Addr, SocketAddr},
+    ptr, slice,
+    sync::Once,
+};
+
+/// `ENET_PACKET_FLAG_RELIABLE`.
+pub const FLAG_RELIABLE: u32 = 1 << 0;
+/// `ENET_PACKET_FLAG_UNSEQUENCED`.
+pub const FLAG_UNSEQUENCED: u32 = 1 << 1;
+/// `ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT`.
+pub const FLAG_UNRELIABLE_FRAGMENT: u32 = 1 << 3;
+
+#[repr(C)]
+struct ShimEvent {
+    kind: c_int,
+    peer: usize,
+    channel_id: u8,
+    data: u32,
+    packet: *mut c_void,
+    packet_data: *const u8,
+    packet_length: usize,
+    packet_flags: u32,
+}
+
+extern "C" {
+    fn shim_initialize() -> c_int;
+    fn shim_host_create(
+        peer_count: usize,
+        channel_limit: usize,
+        compress: c_int,
+        checksum: c_int,
+    ) -> *mut c_void;
+    fn shim_host_port(host: *mut c_void) -> u16;
+    fn shim_host_connect(
+        host: *mut c_void,
+        port: u16,
+        channel_count: usize,
+        data: u32,
+        peer: *mut usize,
+    ) -> c_int;
+    fn shim_host_service(host: *mut c_void, event: *mut ShimEvent) -> c_int;
+    fn shim_peer_send(
+        host: *mut c_void,
+        peer: usize,
+        channel_id: u8,
+        data: *const c_void,
+        length: usize,
+        flags: u32,
+    ) -> c_int;
+    fn shim_peer_disconnect(host: *mut c_void, peer: usize, data: u32);
+    fn shim_host_flush(host: *mut c_void);
+    fn shim_packet_destroy(packet: *mut c_void);
+    fn shim_host_destroy(host: *mut c_void);
+}
+
+/// An event from a [`CHost`], with peers identified by their index in the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CEvent {
+    /// `ENET_EVENT_TYPE_CONNECT`.
+    Connect {
+        /// The peer's index.
+        peer: usize,
+        /// The data sent by the remote peer when connecting.
+        data: u32,
+    },
+    /// `ENET_EVENT_TYPE_DISCONNECT`.
+    Disconnect {
+        /// The peer's index.
+        peer: usize,
+        /// The data sent by the remote peer when disconnecting.
+        data: u32,
+    },
+    /// `ENET_EVENT_TYPE_RECEIVE`.
+    Receive {
+        /// The peer's index.
+        peer: usize,
+        /// The channel the packet was received on.
+        channel_id: u8,
+        /// The packet's `ENET_PACKET_FLAG_*` flags.
+        flags: u32,
+        /// The packet's data.
+        data: Vec<u8>,
+    },
+}
+
+/// Settings for a [`CHost`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CHostSettings {
+    /// The number of peers allocated.
+    pub peer_count: usize,
+    /// The maximum channels a peer may connect with.
+    pub channel_limit: usize,
+    /// Compress with `enet_host_compress_with_range_coder`.
+    pub range_coder: bool,
+    /// Checksum datagrams with `enet_crc32`.
+    pub checksum: bool,
+}
+
+impl Default for CHostSettings {
+    fn default() -> Self {
+        Self {
+            peer_count: 4,
+            channel_limit: 8,
+            range_coder: false,
+            checksum: false,
+        }
+    }
+}
+
+/// A C ENet host, bound to `127.0.0.1` on a port chosen by the system.
+pub struct CHost {
+    host: *mut c_void,
+}
+
+impl CHost {
+    /// Create a host with `enet_host_create`, calling `enet_initialize` first if this is the
+    /// first host.
+    ///
+    /// # Panics
+    ///
+    /// Panics if C ENet fails to initialize or create the host.
+    #[must_use]
+    pub fn new(settings: CHostSettings) -> Self {
+        static INITIALIZE: Once = Once::new();
+        INITIALIZE.call_once(|| assert_eq!(unsafe { shim_initialize() }, 0));
+        let host = unsafe {
+            shim_host_create(
+                settings.peer_count,
+                settings.channel_limit,
+                c_int::from(settings.range_coder),
+                c_int::from(settings.checksum),
+            )
+        };
+        assert!(!host.is_null(), "C ENet failed to create a host.");
+        Self { host }
+    }
+
+    /// The address the host is bound to.
+    #[must_use]
+    pub fn address(&self) -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::LOCALHOST, unsafe { shim_host_port(self.host) }))
+    }
+
+    /// Connect to a host on `127.0.0.1`, returning the peer's index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address isn't on `127.0.0.1`, or no peers are available.
+    pub fn connect(&mut self, address: SocketAddr, channel_count: usize, data: u32) -> usize {
+        assert_eq!(address.ip(), Ipv4Addr::LOCALHOST);
+        let mut peer = 0;
+        let result = unsafe {
+            shim_host_connect(
+                self.host,
+                address.port(),
+                channel_count,
+                data,
+                &raw mut peer,
+            )
+        };
+        assert_eq!(result, 0, "C ENet has no available peers.");
+        peer
+    }
+
+    /// Service the host with `enet_host_service`, without waiting.
+    ///
+    /// # Panics
+    ///
+    /// Panics if servicing fails.
+    pub fn service(&mut self) -> Option<CEvent> {
+        let mut event = ShimEvent {
+            kind: 0,
+            peer: 0,
+            channel_id: 0,
+            data: 0,
+            packet: ptr::null_mut(),
+            packet_data: ptr::null(),
+            packet_length: 0,
+            packet_flags: 0,
+        };
+        let result = unsafe { shim_host_service(self.host, &raw mut event) };
+        assert!(result >= 0, "C ENet failed to service the host.");
+        if result == 0 {
+            return None;
+        }
+        Some(match event.kind {
+            1 => CEvent::Connect {
+                peer: event.peer,
+                data: event.data,
+            },
+            2 => CEvent::Disconnect {
+                peer: event.peer,
+                data: event.data,
+            },
+            3 => unsafe {
+                let data = slice::from_raw_parts(event.packet_data, event.packet_length).to_vec();
+                shim_packet_destroy(event.packet);
+                CEvent::Receive {
+                    peer: event.peer,
+                    channel_id: event.channel_id,
+                    flags: event.packet_flags,
+                    data,
+                }
+            },
+            kind => panic!("Unknown C ENet event type {kind}."),
+        })
+    }
+
+    /// Queue a packet with the `ENET_PACKET_FLAG_*` flags to a peer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the packet can't be queued.
+    pub fn send(&mut self, peer: usize, channel_id: u8, data: &[u8], flags: u32) {
+        let result = unsafe {
+            shim_peer_send(
+                self.host,
+                peer,
+                channel_id,
+                data.as_ptr().cast(),
+                data.len(),
+                flags,
+            )
+        };
+        assert_eq!(result, 0, "C ENet failed to queue a packet.");
+    }
+
+    /// Request a disconnection from a peer.
+    pub fn disconnect(&mut self, peer: usize, data: u32) {
+        unsafe { shim_peer_disconnect(self.host, peer, data) }
+    }
+
+    /// Send queued packets with `enet_host_flush`.
+    pub fn flush(&mut self) {
+        unsafe { shim_host_flush(self.host) }
+    }
+}
+
+impl Drop for CHost {
+    fn drop(&mut self) {
+        unsafe { shim_host_destroy(self.host) }
+    }
+}
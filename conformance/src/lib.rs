@@ -0,0 +1,23 @@
+//! Conformance tests between rusty_enet and the original C ENet, checking both speak the same
+//! protocol on the wire.
+//!
+//! The C library is compiled from a checkout of <https://github.com/lsalzman/enet>, so the tests
+//! are behind the `c-enet` feature:
+//!
+//! ```sh
+//! git clone https://github.com/lsalzman/enet /tmp/enet
+//! ENET_SOURCE_DIR=/tmp/enet cargo test --features c-enet
+//! ```
+//!
+//! `cargo run -p ci -- conformance`, which CI runs, does the same against C ENet v1.3.18. The
+//! suite hasn't had a run against C ENet yet, so until it passes there, it says nothing about
+//! interoperability.
+//!
+//! This crate wraps C ENet in [`CHost`], a host bound to `127.0.0.1` on a system chosen port,
+//! which the tests service alongside a rusty_enet [`Host`](rusty_enet::Host) bound to the same
+//! interface.
+
+#[cfg(feature = "c-enet")]
+mod c_host;
+#[cfg(feature = "c-enet")]
+pub use c_host::*;
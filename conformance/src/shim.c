@@ -0,0 +1,103 @@
+/* A flat interface over C ENet, so the tests don't depend on its struct layouts. */
+
+#include <enet/enet.h>
+
+typedef struct {
+    int type;
+    size_t peer;
+    enet_uint8 channel_id;
+    enet_uint32 data;
+    ENetPacket *packet;
+    const enet_uint8 *packet_data;
+    size_t packet_length;
+    enet_uint32 packet_flags;
+} shim_event;
+
+int shim_initialize(void) { return enet_initialize(); }
+
+ENetHost *shim_host_create(size_t peer_count, size_t channel_limit, int compress, int checksum) {
+    ENetAddress address;
+    ENetHost *host;
+
+    address.host = ENET_HOST_TO_NET_32(0x7F000001);
+    address.port = 0;
+    host = enet_host_create(&address, peer_count, channel_limit, 0, 0);
+    if (host == NULL) {
+        return NULL;
+    }
+    if (compress && enet_host_compress_with_range_coder(host) < 0) {
+        enet_host_destroy(host);
+        return NULL;
+    }
+    if (checksum) {
+        host->checksum = enet_crc32;
+    }
+    return host;
+}
+
+enet_uint16 shim_host_port(ENetHost *host) { return host->address.port; }
+
+int shim_host_connect(ENetHost *host, enet_uint16 port, size_t channel_count, enet_uint32 data,
+                      size_t *peer) {
+    ENetAddress address;
+    ENetPeer *connecting;
+
+    address.host = ENET_HOST_TO_NET_32(0x7F000001);
+    address.port = port;
+    connecting = enet_host_connect(host, &address, channel_count, data);
+    if (connecting == NULL) {
+        return -1;
+    }
+    *peer = (size_t)(connecting - host->peers);
+    return 0;
+}
+
+int shim_host_service(ENetHost *host, shim_event *event) {
+    ENetEvent raw;
+    int result = enet_host_service(host, &raw, 0);
+
+    if (result <= 0) {
+        return result;
+    }
+    event->type = (int)raw.type;
+    event->peer = (size_t)(raw.peer - host->peers);
+    event->channel_id = raw.channelID;
+    event->data = raw.data;
+    event->packet = raw.packet;
+    if (raw.packet != NULL) {
+        event->packet_data = raw.packet->data;
+        event->packet_length = raw.packet->dataLength;
+        event->packet_flags = raw.packet->flags;
+    } else {
+        event->packet_data = NULL;
+        event->packet_length = 0;
+        event->packet_flags = 0;
+    }
+    return result;
+}
+
+int shim_peer_send(ENetHost *host, size_t peer, enet_uint8 channel_id, const void *data,
+                   size_t length, enet_uint32 flags) {
+    ENetPacket *packet = enet_packet_create(data, length, flags);
+
+    if (packet == NULL) {
+        return -1;
+    }
+    if (enet_peer_send(&host->peers[peer], channel_id, packet) < 0) {
+        if (packet->referenceCount == 0) {
+            enet_packet_destroy(packet);
+        }
+        return -1;
+    }
+    return 0;
+}
+
+void shim_peer_disconnect(ENetHost *host, size_t peer, enet_uint32 data) {
+    enet_peer_disconnect(&host->peers[peer], data);
+}
+
+void shim_host_flush(ENetHost *host) { enet_host_flush(host); }
+
+void shim_packet_destroy(ENetPacket *packet) { enet_packet_destroy(packet); }
+
+void shim_host_destroy(ENetHost *host) { enet_host_destroy(host); }
@@ -0,0 +1,287 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    thread,
+    time::Duration,
+};
+
+use rusty_enet as enet;
+use rusty_enet_conformance::{
+    CEvent, CHost, CHostSettings, FLAG_RELIABLE, FLAG_UNRELIABLE_FRAGMENT, FLAG_UNSEQUENCED,
+};
+
+const CHANNELS: usize = 8;
+
+/// Every packet kind, with the equivalent C ENet flags.
+const KINDS: [(enet::PacketKind, u32); 5] = [
+    (enet::PacketKind::Reliable, FLAG_RELIABLE),
+    (enet::PacketKind::Unreliable { sequenced: true }, 0),
+    (
+        enet::PacketKind::Unreliable { sequenced: false },
+        FLAG_UNSEQUENCED,
+    ),
+    (
+        enet::PacketKind::AlwaysUnreliable { sequenced: true },
+        FLAG_UNRELIABLE_FRAGMENT,
+    ),
+    (
+        enet::PacketKind::AlwaysUnreliable { sequenced: false },
+        FLAG_UNRELIABLE_FRAGMENT | FLAG_UNSEQUENCED,
+    ),
+];
+
+#[derive(Clone, Copy, Default)]
+struct Options {
+    range_coder: bool,
+    checksum: bool,
+}
+
+/// A rusty_enet host and a C ENet host on `127.0.0.1`, and the events each has generated.
+struct Pair {
+    rust: enet::Host<UdpSocket>,
+    c: CHost,
+    rust_events: Vec<enet::EventNoRef>,
+    c_events: Vec<CEvent>,
+}
+
+impl Pair {
+    fn new(options: Options) -> Self {
+        let socket = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let rust = enet::Host::new(
+            socket,
+            enet::HostSettings {
+                peer_limit: 4,
+                channel_limit: CHANNELS,
+                compressor: options
+                    .range_coder
+                    .then(|| Box::new(enet::RangeCoder::new()) as Box<dyn enet::Compressor>),
+                checksum: options
+                    .checksum
+                    .then(|| Box::new(enet::crc32) as Box<dyn Fn(&[&[u8]]) -> u32 + Send + Sync>),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let c = CHost::new(CHostSettings {
+            channel_limit: CHANNELS,
+            range_coder: options.range_coder,
+            checksum: options.checksum,
+            ..CHostSettings::default()
+        });
+        Self {
+            rust,
+            c,
+            rust_events: Vec::new(),
+            c_events: Vec::new(),
+        }
+    }
+
+    /// Service both hosts until `done` returns `true`, for at most five seconds.
+    fn pump_until(&mut self, done: impl Fn(&Self) -> bool) {
+        for _ in 0..5000 {
+            while let Some(event) = self.rust.service().unwrap() {
+                self.rust_events.push(event.no_ref());
+            }
+            while let Some(event) = self.c.service() {
+                self.c_events.push(event);
+            }
+            if done(self) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!(
+            "Timed out.\nrusty_enet events: {:?}\nC ENet events: {:?}",
+            self.rust_events, self.c_events
+        );
+    }
+
+    /// Connect the rusty_enet host to the C host, returning each side's peer.
+    fn connect_from_rust(&mut self, data: u32) -> (enet::PeerID, usize) {
+        let peer = self
+            .rust
+            .connect(self.c.address(), CHANNELS, data)
+            .unwrap()
+            .peer_id();
+        self.pump_until(|pair| pair.c_connected().is_some() && pair.rust_connected().is_some());
+        assert_eq!(self.c_connected().unwrap().1, data);
+        assert_eq!(self.rust_connected().unwrap().0, peer);
+        (peer, self.c_connected().unwrap().0)
+    }
+
+    /// Connect the C host to the rusty_enet host, returning each side's peer.
+    fn connect_from_c(&mut self, data: u32) -> (enet::PeerID, usize) {
+        let address = self.rust.socket().local_addr().unwrap();
+        let peer = self.c.connect(address, CHANNELS, data);
+        self.pump_until(|pair| pair.c_connected().is_some() && pair.rust_connected().is_some());
+        assert_eq!(self.rust_connected().unwrap().1, data);
+        assert_eq!(self.c_connected().unwrap().0, peer);
+        (self.rust_connected().unwrap().0, peer)
+    }
+
+    fn rust_connected(&self) -> Option<(enet::PeerID, u32)> {
+        self.rust_events.iter().find_map(|event| match event {
+            enet::EventNoRef::Connect { peer, data, .. } => Some((*peer, *data)),
+            _ => None,
+        })
+    }
+
+    fn c_connected(&self) -> Option<(usize, u32)> {
+        self.c_events.iter().find_map(|event| match event {
+            CEvent::Connect { peer, data } => Some((*peer, *data)),
+            _ => None,
+        })
+    }
+
+    fn rust_received(&self) -> Vec<(u8, bool, Vec<u8>)> {
+        self.rust_events
+            .iter()
+            .filter_map(|event| match event {
+                enet::EventNoRef::Receive {
+                    channel_id, packet, ..
+                } => Some((*channel_id, packet.is_reliable(), packet.data().to_vec())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn c_received(&self) -> Vec<(u8, bool, Vec<u8>)> {
+        self.c_events
+            .iter()
+            .filter_map(|event| match event {
+                CEvent::Receive {
+                    channel_id,
+                    flags,
+                    data,
+                    ..
+                } => Some((*channel_id, flags & FLAG_RELIABLE != 0, data.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Send a packet of every kind in both directions, each on its own channel, and check they
+    /// all arrive intact.
+    fn exchange(&mut self, (rust_peer, c_peer): (enet::PeerID, usize), payload: &[u8]) {
+        let mut expected = Vec::new();
+        for (channel_id, (kind, flags)) in (0..).zip(KINDS) {
+            let mut data = vec![channel_id];
+            data.extend_from_slice(payload);
+            self.rust
+                .peer_mut(rust_peer)
+                .unwrap()
+                .send(channel_id, &enet::Packet::new(&data, kind))
+                .unwrap();
+            self.c.send(c_peer, channel_id, &data, flags);
+            expected.push((channel_id, kind == enet::PacketKind::Reliable, data));
+        }
+        self.rust.flush();
+        self.c.flush();
+        self.pump_until(|pair| {
+            pair.rust_received().len() == expected.len()
+                && pair.c_received().len() == expected.len()
+        });
+        let sorted = |mut received: Vec<(u8, bool, Vec<u8>)>| {
+            received.sort();
+            received
+        };
+        assert_eq!(sorted(self.rust_received()), expected);
+        assert_eq!(sorted(self.c_received()), expected);
+        self.rust_events.clear();
+        self.c_events.clear();
+    }
+
+    /// Disconnect from the rusty_enet side, and check the C host sees it.
+    fn disconnect_from_rust(&mut self, (rust_peer, c_peer): (enet::PeerID, usize), data: u32) {
+        self.rust.peer_mut(rust_peer).unwrap().disconnect(data);
+        self.pump_until(|pair| {
+            pair.c_events.contains(&CEvent::Disconnect { peer: c_peer, data })
+                && pair.rust_events.iter().any(|event| {
+                    matches!(event, enet::EventNoRef::Disconnect { peer, .. } if *peer == rust_peer)
+                })
+        });
+    }
+
+    /// Disconnect from the C side, and check the rusty_enet host sees it.
+    fn disconnect_from_c(&mut self, (rust_peer, c_peer): (enet::PeerID, usize), data: u32) {
+        self.c.disconnect(c_peer, data);
+        self.pump_until(|pair| {
+            pair.c_events
+                .iter()
+                .any(|event| matches!(event, CEvent::Disconnect { peer, .. } if *peer == c_peer))
+                && pair.rust_events.iter().any(|event| {
+                    matches!(event, enet::EventNoRef::Disconnect { peer, data: received }
+                        if *peer == rust_peer && *received == data)
+                })
+        });
+    }
+}
+
+fn text(length: usize) -> Vec<u8> {
+    b"the quick brown fox jumps over the lazy dog. "
+        .iter()
+        .copied()
+        .cycle()
+        .take(length)
+        .collect()
+}
+
+fn run(options: Options, from_rust: bool) {
+    let mut pair = Pair::new(options);
+    let peers = if from_rust {
+        pair.connect_from_rust(0xDEAD_BEEF)
+    } else {
+        pair.connect_from_c(0xDEAD_BEEF)
+    };
+    pair.rust_events.clear();
+    pair.c_events.clear();
+    pair.exchange(peers, b"");
+    pair.exchange(peers, &text(100));
+    // several fragments, reliable and unreliable
+    pair.exchange(peers, &text(10_000));
+    pair.exchange(peers, &(0..=255).cycle().take(20_000).collect::<Vec<u8>>());
+    if from_rust {
+        pair.disconnect_from_c(peers, 7);
+    } else {
+        pair.disconnect_from_rust(peers, 7);
+    }
+}
+
+#[test]
+fn rust_connects_to_c() {
+    run(Options::default(), true);
+}
+
+#[test]
+fn c_connects_to_rust() {
+    run(Options::default(), false);
+}
+
+#[test]
+fn range_coder() {
+    let options = Options {
+        range_coder: true,
+        ..Options::default()
+    };
+    run(options, true);
+    run(options, false);
+}
+
+#[test]
+fn checksum() {
+    let options = Options {
+        checksum: true,
+        ..Options::default()
+    };
+    run(options, true);
+    run(options, false);
+}
+
+#[test]
+fn range_coder_and_checksum() {
+    let options = Options {
+        range_coder: true,
+        checksum: true,
+    };
+    run(options, true);
+    run(options, false);
+}
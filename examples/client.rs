@@ -21,15 +21,16 @@ fn main() {
     )
     .unwrap();
     let address = SocketAddr::from_str("127.0.0.1:6060").unwrap();
-    let peer = host.connect(address, 2, 0).unwrap();
-    peer.set_ping_interval(100);
+    let peer = host.connect(address, 2, 0).unwrap().peer_id();
+    host.peer_mut(peer)
+        .unwrap()
+        .set_ping_interval(Duration::from_millis(100));
     loop {
         while let Some(event) = host.service().unwrap() {
             match event {
                 enet::Event::Connect { peer, .. } => {
                     println!("Connected");
-                    let packet = enet::Packet::reliable("hello world".as_bytes());
-                    _ = peer.send(0, &packet);
+                    _ = peer.send_default("hello world".as_bytes());
                 }
                 enet::Event::Disconnect { .. } => {
                     println!("Disconnected");
@@ -39,6 +40,13 @@ fn main() {
                         println!("Received packet: {:?}", message);
                     }
                 }
+                enet::Event::Acknowledge { .. }
+                | enet::Event::DeliveryFailed { .. }
+                | enet::Event::Reply { .. }
+                | enet::Event::ConnectRequest { .. }
+                | enet::Event::PeerIdle { .. }
+                | enet::Event::PacketResent { .. } => {}
+                enet::Event::ProtocolViolation { .. } => {}
             }
         }
         std::thread::sleep(Duration::from_millis(10));
@@ -0,0 +1,31 @@
+//! Regenerate the seed corpora of the `cargo fuzz` targets in `fuzz/` from a recorded session.
+//!
+//! Run with `cargo run --example fuzz_corpus --features fuzzing` from the repository root.
+
+use std::{fs, path::Path};
+
+use rusty_enet::fuzzing::record_session;
+
+fn main() {
+    let session = record_session();
+    let corpus = Path::new("fuzz/corpus");
+
+    let sessions = corpus.join("handle_session");
+    fs::create_dir_all(&sessions).unwrap();
+    fs::write(sessions.join("session"), &session).unwrap();
+
+    let datagrams = corpus.join("parse_datagram");
+    fs::create_dir_all(&datagrams).unwrap();
+    let mut remaining = session.as_slice();
+    let mut count = 0;
+    while let [high, low, rest @ ..] = remaining {
+        let (datagram, rest) = rest.split_at(usize::from(u16::from_be_bytes([*high, *low])));
+        fs::write(datagrams.join(format!("datagram-{count:03}")), datagram).unwrap();
+        remaining = rest;
+        count += 1;
+    }
+    println!(
+        "Wrote a session of {count} datagrams to {}",
+        corpus.display()
+    );
+}
@@ -24,18 +24,19 @@ fn main() {
         };
     }
 
-    host1.connect((), 255, 100).unwrap();
+    let peer = host1.connect((), 255, 100).unwrap().peer_id();
 
     update!();
 
     host1
-        .peer_mut(enet::PeerID(0))
+        .peer_mut(peer)
+        .unwrap()
         .send(0, &enet::Packet::reliable("Hello!".as_bytes()))
         .unwrap();
 
     update!();
 
-    host1.peer_mut(enet::PeerID(0)).disconnect(32);
+    host1.peer_mut(peer).unwrap().disconnect(32);
 
     update!();
 }
@@ -47,7 +48,7 @@ fn update_host(
 ) {
     while let Some(event) = host.service().unwrap() {
         match event {
-            enet::Event::Connect { peer, data } => {
+            enet::Event::Connect { peer, data, .. } => {
                 println!(
                     "[{}] Connected to {:?} with data: {}",
                     name,
@@ -67,6 +68,7 @@ fn update_host(
                 peer,
                 packet,
                 channel_id,
+                ..
             } => {
                 let message = std::str::from_utf8(packet.data()).unwrap();
                 println!(
@@ -77,6 +79,19 @@ fn update_host(
                     message
                 );
             }
+            enet::Event::Acknowledge { tag, .. } => {
+                println!("[{}] Packet {} was acknowledged", name, tag);
+            }
+            enet::Event::DeliveryFailed { tag, .. } => {
+                println!("[{}] Packet {} was not delivered", name, tag);
+            }
+            enet::Event::Reply { .. }
+            | enet::Event::ConnectRequest { .. }
+            | enet::Event::PeerIdle { .. }
+            | enet::Event::PacketResent { .. } => {}
+            enet::Event::ProtocolViolation { kind, .. } => {
+                println!("[{}] Protocol violation: {:?}", name, kind);
+            }
         }
     }
     if let Some((_, packet)) = host.socket_mut().read() {
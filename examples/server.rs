@@ -15,6 +15,7 @@ fn main() {
             channel_limit: 2,
             compressor: Some(Box::new(enet::RangeCoder::new())),
             checksum: Some(Box::new(enet::crc32)),
+            strict_validation: true,
             ..Default::default()
         },
     )
@@ -24,21 +25,31 @@ fn main() {
         while let Some(event) = host.service().unwrap() {
             match event {
                 enet::Event::Connect { peer, .. } => {
-                    println!("Peer {} connected", peer.id().0);
+                    println!("Peer {} connected", peer.id().index);
                 }
                 enet::Event::Disconnect { peer, .. } => {
-                    println!("Peer {} disconnected", peer.id().0);
+                    println!("Peer {} disconnected", peer.id().index);
                 }
                 enet::Event::Receive {
                     peer,
                     channel_id,
                     packet,
+                    ..
                 } => {
                     if let Ok(message) = str::from_utf8(packet.data()) {
                         println!("Received packet: {:?}", message);
                     }
                     _ = peer.send(channel_id, &packet);
                 }
+                enet::Event::Acknowledge { .. }
+                | enet::Event::DeliveryFailed { .. }
+                | enet::Event::Reply { .. }
+                | enet::Event::ConnectRequest { .. }
+                | enet::Event::PeerIdle { .. }
+                | enet::Event::PacketResent { .. } => {}
+                enet::Event::ProtocolViolation { address, kind } => {
+                    println!("Protocol violation from {}: {:?}", address, kind);
+                }
             }
         }
         std::thread::sleep(Duration::from_millis(10));
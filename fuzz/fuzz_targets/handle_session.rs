@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rusty_enet::fuzzing::{handle_session, FuzzState};
+
+fuzz_target!(|data: &[u8]| {
+    let mut state = FuzzState::default();
+    handle_session(&mut state, data);
+});
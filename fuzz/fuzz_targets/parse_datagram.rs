@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rusty_enet::fuzzing::parse_datagram;
+
+fuzz_target!(|data: &[u8]| {
+    _ = parse_datagram(data, false);
+    _ = parse_datagram(data, true);
+});
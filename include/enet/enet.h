@@ -0,0 +1,286 @@
+/**
+ @file  enet.h
+ @brief rusty_enet's C API, a source compatible replacement for C ENet's header.
+
+ Built with the `capi` feature, see the documentation of the `capi` module for the differences
+ from C ENet.
+*/
+#ifndef __ENET_ENET_H__
+#define __ENET_ENET_H__
+
+#ifdef __cplusplus
+extern "C"
+{
+#endif
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef _WIN32
+#include <winsock2.h>
+#else
+#include <arpa/inet.h>
+#include <sys/uio.h>
+#endif
+
+#define ENET_VERSION_MAJOR 1
+#define ENET_VERSION_MINOR 3
+#define ENET_VERSION_PATCH 18
+#define ENET_VERSION_CREATE(major, minor, patch) (((major)<<16) | ((minor)<<8) | (patch))
+#define ENET_VERSION_GET_MAJOR(version) (((version)>>16)&0xFF)
+#define ENET_VERSION_GET_MINOR(version) (((version)>>8)&0xFF)
+#define ENET_VERSION_GET_PATCH(version) ((version)&0xFF)
+#define ENET_VERSION ENET_VERSION_CREATE(ENET_VERSION_MAJOR, ENET_VERSION_MINOR, ENET_VERSION_PATCH)
+
+#define ENET_API extern
+
+typedef uint8_t enet_uint8;
+typedef uint16_t enet_uint16;
+typedef uint32_t enet_uint32;
+typedef enet_uint32 ENetVersion;
+
+#define ENET_HOST_TO_NET_16(value) (htons (value))
+#define ENET_HOST_TO_NET_32(value) (htonl (value))
+#define ENET_NET_TO_HOST_16(value) (ntohs (value))
+#define ENET_NET_TO_HOST_32(value) (ntohl (value))
+
+enum
+{
+   ENET_PROTOCOL_MINIMUM_MTU             = 576,
+   ENET_PROTOCOL_MAXIMUM_MTU             = 4096,
+   ENET_PROTOCOL_MAXIMUM_PACKET_COMMANDS = 32,
+   ENET_PROTOCOL_MINIMUM_WINDOW_SIZE     = 4096,
+   ENET_PROTOCOL_MAXIMUM_WINDOW_SIZE     = 65536,
+   ENET_PROTOCOL_MINIMUM_CHANNEL_COUNT   = 1,
+   ENET_PROTOCOL_MAXIMUM_CHANNEL_COUNT   = 255,
+   ENET_PROTOCOL_MAXIMUM_PEER_ID         = 0xFFF,
+   ENET_PROTOCOL_MAXIMUM_FRAGMENT_COUNT  = 1024 * 1024
+};
+
+enum
+{
+   ENET_HOST_DEFAULT_MTU                  = 1392,
+   ENET_HOST_DEFAULT_MAXIMUM_PACKET_SIZE  = 32 * 1024 * 1024,
+   ENET_HOST_DEFAULT_MAXIMUM_WAITING_DATA = 32 * 1024 * 1024,
+
+   ENET_PEER_DEFAULT_ROUND_TRIP_TIME      = 500,
+   ENET_PEER_DEFAULT_PACKET_THROTTLE      = 32,
+   ENET_PEER_PACKET_THROTTLE_SCALE        = 32,
+   ENET_PEER_PACKET_THROTTLE_COUNTER      = 7,
+   ENET_PEER_PACKET_THROTTLE_ACCELERATION = 2,
+   ENET_PEER_PACKET_THROTTLE_DECELERATION = 2,
+   ENET_PEER_PACKET_THROTTLE_INTERVAL     = 5000,
+   ENET_PEER_PACKET_LOSS_SCALE            = (1 << 16),
+   ENET_PEER_PACKET_LOSS_INTERVAL         = 10000,
+   ENET_PEER_WINDOW_SIZE_SCALE            = 64 * 1024,
+   ENET_PEER_TIMEOUT_LIMIT                = 32,
+   ENET_PEER_TIMEOUT_MINIMUM              = 5000,
+   ENET_PEER_TIMEOUT_MAXIMUM              = 30000,
+   ENET_PEER_PING_INTERVAL                = 500
+};
+
+#ifdef _WIN32
+typedef struct
+{
+    size_t dataLength;
+    void * data;
+} ENetBuffer;
+#else
+typedef struct
+{
+    void * data;
+    size_t dataLength;
+} ENetBuffer;
+#endif
+
+enum
+{
+   ENET_HOST_ANY       = 0,
+   ENET_HOST_BROADCAST = 0xFFFFFFFFU,
+   ENET_PORT_ANY       = 0
+};
+
+/**
+ * An IPv4 address and port. The host is in network byte order, and the port in host byte order.
+ */
+typedef struct _ENetAddress
+{
+   enet_uint32 host;
+   enet_uint16 port;
+} ENetAddress;
+
+typedef enum _ENetPacketFlag
+{
+   ENET_PACKET_FLAG_RELIABLE            = (1 << 0),
+   ENET_PACKET_FLAG_UNSEQUENCED         = (1 << 1),
+   ENET_PACKET_FLAG_NO_ALLOCATE         = (1 << 2),
+   ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT = (1 << 3),
+   ENET_PACKET_FLAG_SENT                = (1 << 8)
+} ENetPacketFlag;
+
+struct _ENetPacket;
+
+typedef void (* ENetPacketFreeCallback) (struct _ENetPacket *);
+
+/**
+ * A packet. Only created by enet_packet_create, or received in an event.
+ */
+typedef struct _ENetPacket
+{
+   size_t                 referenceCount;
+   enet_uint32            flags;
+   enet_uint8 *           data;
+   size_t                 dataLength;
+   ENetPacketFreeCallback freeCallback;
+   void *                 userData;
+   /* private fields follow */
+} ENetPacket;
+
+typedef enum _ENetPeerState
+{
+   ENET_PEER_STATE_DISCONNECTED             = 0,
+   ENET_PEER_STATE_CONNECTING               = 1,
+   ENET_PEER_STATE_ACKNOWLEDGING_CONNECT    = 2,
+   ENET_PEER_STATE_CONNECTION_PENDING       = 3,
+   ENET_PEER_STATE_CONNECTION_SUCCEEDED     = 4,
+   ENET_PEER_STATE_CONNECTED                = 5,
+   ENET_PEER_STATE_DISCONNECT_LATER         = 6,
+   ENET_PEER_STATE_DISCONNECTING            = 7,
+   ENET_PEER_STATE_ACKNOWLEDGING_DISCONNECT = 8,
+   ENET_PEER_STATE_ZOMBIE                   = 9
+} ENetPeerState;
+
+struct _ENetHost;
+
+/**
+ * A peer, in its host's peers array. Every field but data is refreshed by each call taking the
+ * host or peer, and must not be modified.
+ */
+typedef struct _ENetPeer
+{
+   struct _ENetHost * host;
+   enet_uint16   outgoingPeerID;
+   enet_uint16   incomingPeerID;
+   enet_uint32   connectID;
+   enet_uint8    outgoingSessionID;
+   enet_uint8    incomingSessionID;
+   ENetAddress   address;
+   void *        data;               /**< Application private data, may be freely modified */
+   ENetPeerState state;
+   size_t        channelCount;
+   enet_uint32   incomingBandwidth;
+   enet_uint32   outgoingBandwidth;
+   enet_uint32   incomingDataTotal;
+   enet_uint32   outgoingDataTotal;
+   enet_uint32   packetsSent;
+   enet_uint32   packetsLost;
+   enet_uint32   packetLoss;
+   enet_uint32   packetLossVariance;
+   enet_uint32   pingInterval;
+   enet_uint32   roundTripTime;
+   enet_uint32   roundTripTimeVariance;
+   enet_uint32   mtu;
+} ENetPeer;
+
+/**
+ * A compressor implemented by the application, see enet_host_compress.
+ */
+typedef struct _ENetCompressor
+{
+   void * context;
+   size_t (* compress) (void * context, const ENetBuffer * inBuffers, size_t inBufferCount, size_t inLimit, enet_uint8 * outData, size_t outLimit);
+   size_t (* decompress) (void * context, const enet_uint8 * inData, size_t inLimit, enet_uint8 * outData, size_t outLimit);
+   void (* destroy) (void * context);
+} ENetCompressor;
+
+typedef enet_uint32 (* ENetChecksumCallback) (const ENetBuffer * buffers, size_t bufferCount);
+
+/**
+ * A host. Only created by enet_host_create. Every field but checksum, which may be assigned, is
+ * refreshed by each call taking the host, and must not be modified.
+ */
+typedef struct _ENetHost
+{
+   ENetAddress          address;
+   enet_uint32          incomingBandwidth;
+   enet_uint32          outgoingBandwidth;
+   enet_uint32          mtu;
+   ENetPeer *           peers;
+   size_t               peerCount;
+   size_t               channelLimit;
+   enet_uint32          serviceTime;
+   ENetChecksumCallback checksum;
+   size_t               connectedPeers;
+   /* private fields follow */
+} ENetHost;
+
+typedef enum _ENetEventType
+{
+   ENET_EVENT_TYPE_NONE       = 0,
+   ENET_EVENT_TYPE_CONNECT    = 1,
+   ENET_EVENT_TYPE_DISCONNECT = 2,
+   ENET_EVENT_TYPE_RECEIVE    = 3
+} ENetEventType;
+
+typedef struct _ENetEvent
+{
+   ENetEventType        type;
+   ENetPeer *           peer;
+   enet_uint8           channelID;
+   enet_uint32          data;
+   ENetPacket *         packet;
+} ENetEvent;
+
+/** Allocation callbacks, accepted for compatibility and ignored. */
+typedef struct _ENetCallbacks
+{
+    void * (* malloc) (size_t size);
+    void (* free) (void * memory);
+    void (* no_memory) (void);
+} ENetCallbacks;
+
+ENET_API int        enet_initialize (void);
+ENET_API int        enet_initialize_with_callbacks (ENetVersion version, const ENetCallbacks * inits);
+ENET_API void       enet_deinitialize (void);
+ENET_API ENetVersion enet_linked_version (void);
+
+ENET_API enet_uint32 enet_time_get (void);
+ENET_API void        enet_time_set (enet_uint32);
+
+ENET_API int enet_address_set_host_ip (ENetAddress * address, const char * hostName);
+ENET_API int enet_address_set_host (ENetAddress * address, const char * hostName);
+ENET_API int enet_address_get_host_ip (const ENetAddress * address, char * hostName, size_t nameLength);
+ENET_API int enet_address_get_host (const ENetAddress * address, char * hostName, size_t nameLength);
+
+ENET_API ENetPacket * enet_packet_create (const void *, size_t, enet_uint32);
+ENET_API void         enet_packet_destroy (ENetPacket *);
+ENET_API int          enet_packet_resize  (ENetPacket *, size_t);
+ENET_API enet_uint32  enet_crc32 (const ENetBuffer *, size_t);
+
+ENET_API ENetHost * enet_host_create (const ENetAddress *, size_t, size_t, enet_uint32, enet_uint32);
+ENET_API void       enet_host_destroy (ENetHost *);
+ENET_API ENetPeer * enet_host_connect (ENetHost *, const ENetAddress *, size_t, enet_uint32);
+ENET_API int        enet_host_check_events (ENetHost *, ENetEvent *);
+ENET_API int        enet_host_service (ENetHost *, ENetEvent *, enet_uint32);
+ENET_API void       enet_host_flush (ENetHost *);
+ENET_API void       enet_host_broadcast (ENetHost *, enet_uint8, ENetPacket *);
+ENET_API void       enet_host_compress (ENetHost *, const ENetCompressor *);
+ENET_API int        enet_host_compress_with_range_coder (ENetHost * host);
+ENET_API void       enet_host_channel_limit (ENetHost *, size_t);
+ENET_API void       enet_host_bandwidth_limit (ENetHost *, enet_uint32, enet_uint32);
+
+ENET_API int  enet_peer_send (ENetPeer *, enet_uint8, ENetPacket *);
+ENET_API void enet_peer_ping (ENetPeer *);
+ENET_API void enet_peer_ping_interval (ENetPeer *, enet_uint32);
+ENET_API void enet_peer_timeout (ENetPeer *, enet_uint32, enet_uint32, enet_uint32);
+ENET_API void enet_peer_reset (ENetPeer *);
+ENET_API void enet_peer_disconnect (ENetPeer *, enet_uint32);
+ENET_API void enet_peer_disconnect_now (ENetPeer *, enet_uint32);
+ENET_API void enet_peer_disconnect_later (ENetPeer *, enet_uint32);
+ENET_API void enet_peer_throttle_configure (ENetPeer *, enet_uint32, enet_uint32, enet_uint32);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* __ENET_ENET_H__ */
@@ -1,4 +1,6 @@
-use core::net::SocketAddr;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use crate::Vec;
 
 /// An address type, for use with the [`Socket`](`crate::Socket`) trait.
 pub trait Address: Sized + Clone {
@@ -14,7 +16,31 @@ pub trait Address: Sized + Clone {
     ///
     /// For IP based addresses, checks if this is the IPv4 broadcast address.
     fn is_broadcast(&self) -> bool;
+    /// The port of this address.
+    ///
+    /// Used to derive the integrity bytes of the new protocol header, see
+    /// [`HostSettings::using_new_packet`](`crate::HostSettings::using_new_packet`). Addresses
+    /// without a notion of ports may return `0`.
     fn port(&self) -> u16;
+    /// A key identifying the host of this address, equal for all addresses which are the
+    /// [`same_host`](`Address::same_host`), used to look bans up without comparing against every
+    /// ban, see [`Host::ban`](`crate::Host::ban`).
+    ///
+    /// Returns [`None`] by default, for addresses which can't be keyed.
+    fn host_key(&self) -> Option<u128> {
+        None
+    }
+    /// Encode this address to be sent to a peer, see [`Peer::redirect`](`crate::Peer::redirect`).
+    ///
+    /// Returns [`None`] by default, for addresses which can't be sent.
+    fn to_bytes(&self) -> Option<Vec<u8>> {
+        None
+    }
+    /// Decode an address encoded with [`Address::to_bytes`], or [`None`] if it's invalid.
+    #[must_use]
+    fn from_bytes(_bytes: &[u8]) -> Option<Self> {
+        None
+    }
 }
 
 impl Address for () {
@@ -32,6 +58,10 @@ impl Address for () {
     fn port(&self) -> u16 {
         0
     }
+
+    fn host_key(&self) -> Option<u128> {
+        Some(0)
+    }
 }
 
 impl Address for SocketAddr {
@@ -53,4 +83,51 @@ impl Address for SocketAddr {
     fn port(&self) -> u16 {
         self.port()
     }
+
+    /// IPv4 addresses are keyed as their IPv4-mapped IPv6 addresses, so a ban on either covers
+    /// both.
+    fn host_key(&self) -> Option<u128> {
+        Some(match self.ip() {
+            IpAddr::V4(ip) => u128::from(ip.to_ipv6_mapped()),
+            IpAddr::V6(ip) => u128::from(ip),
+        })
+    }
+
+    fn to_bytes(&self) -> Option<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(19);
+        match self.ip() {
+            IpAddr::V4(ip) => {
+                bytes.push(4);
+                bytes.extend_from_slice(&ip.octets());
+            }
+            IpAddr::V6(ip) => {
+                bytes.push(6);
+                bytes.extend_from_slice(&ip.octets());
+            }
+        }
+        bytes.extend_from_slice(&self.port().to_be_bytes());
+        Some(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&family, rest) = bytes.split_first()?;
+        let (ip, port) = match family {
+            4 if rest.len() == 6 => {
+                let (ip, port) = rest.split_at(4);
+                (
+                    IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(ip).ok()?)),
+                    port,
+                )
+            }
+            6 if rest.len() == 18 => {
+                let (ip, port) = rest.split_at(16);
+                (
+                    IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(ip).ok()?)),
+                    port,
+                )
+            }
+            _ => return None,
+        };
+        Some(SocketAddr::new(ip, u16::from_be_bytes([port[0], port[1]])))
+    }
 }
@@ -0,0 +1,128 @@
+use core::{
+    alloc::Layout,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+#[cfg(not(feature = "std"))]
+use alloc::alloc::{alloc, dealloc};
+#[cfg(feature = "std")]
+use std::alloc::{alloc, dealloc};
+
+use crate::error::AllocatorAlreadySet;
+
+/// Functions used for ENet's internal allocations, equivalent to the callbacks passed to
+/// `enet_initialize_with_callbacks`. Installed with [`set_allocator`].
+///
+/// Hosts, peers, packets, commands and fragment buffers are allocated through these functions.
+/// Collections owned by the safe wrappers, such as queues and settings, use Rust's global
+/// allocator, which can be replaced with
+/// [`#[global_allocator]`](https://doc.rust-lang.org/std/alloc/index.html#the-global_allocator-attribute).
+#[derive(Debug, Clone, Copy)]
+pub struct Allocator {
+    /// Allocate memory for `layout`, returning null on failure, which is reported with
+    /// [`handle_alloc_error`](https://doc.rust-lang.org/std/alloc/fn.handle_alloc_error.html).
+    ///
+    /// # Safety
+    ///
+    /// Has the same contract as [`GlobalAlloc::alloc`](`core::alloc::GlobalAlloc::alloc`).
+    pub allocate: unsafe fn(layout: Layout) -> *mut u8,
+    /// Free memory previously returned by [`Allocator::allocate`] for the same `layout`.
+    ///
+    /// # Safety
+    ///
+    /// Has the same contract as [`GlobalAlloc::dealloc`](`core::alloc::GlobalAlloc::dealloc`).
+    pub deallocate: unsafe fn(ptr: *mut u8, layout: Layout),
+}
+
+impl Allocator {
+    /// The default allocator, forwarding to Rust's global allocator.
+    pub const GLOBAL: Allocator = Allocator {
+        allocate: alloc,
+        deallocate: dealloc,
+    };
+}
+
+static ALLOCATOR: AtomicPtr<Allocator> = AtomicPtr::new(ptr::null_mut());
+
+/// The default allocator at a fixed address, so it can be told apart from installed allocators.
+static GLOBAL_ALLOCATOR: Allocator = Allocator::GLOBAL;
+
+/// Install the [`Allocator`] used for all of ENet's internal allocations in this process.
+///
+/// Must be called before anything is allocated, usually at the start of `main`, as memory can
+/// only be freed by the allocator it came from. Once the first host or packet is created, the
+/// allocator is fixed for the rest of the process.
+///
+/// ```
+/// use core::{
+///     alloc::Layout,
+///     sync::atomic::{AtomicUsize, Ordering},
+/// };
+/// use std::{alloc, convert::Infallible};
+///
+/// use rusty_enet::{set_allocator, Allocator, Host, HostSettings, ReadWrite};
+///
+/// static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+///
+/// unsafe fn allocate(layout: Layout) -> *mut u8 {
+///     ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+///     alloc::alloc(layout)
+/// }
+///
+/// unsafe fn deallocate(ptr: *mut u8, layout: Layout) {
+///     ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+///     alloc::dealloc(ptr, layout);
+/// }
+///
+/// static ALLOCATOR: Allocator = Allocator {
+///     allocate,
+///     deallocate,
+/// };
+///
+/// set_allocator(&ALLOCATOR).unwrap();
+/// let host = Host::new(ReadWrite::<(), Infallible>::new(), HostSettings::default()).unwrap();
+/// assert!(ALLOCATED.load(Ordering::Relaxed) > 0);
+/// drop(host);
+/// assert_eq!(ALLOCATED.load(Ordering::Relaxed), 0);
+/// ```
+///
+/// # Errors
+///
+/// Returns [`AllocatorAlreadySet`] if an allocator was already installed, or ENet has already
+/// allocated memory with the default allocator.
+pub fn set_allocator(allocator: &'static Allocator) -> Result<(), AllocatorAlreadySet> {
+    ALLOCATOR
+        .compare_exchange(
+            ptr::null_mut(),
+            ptr::from_ref(allocator).cast_mut(),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        )
+        .map(|_| ())
+        .map_err(|_| AllocatorAlreadySet)
+}
+
+/// Get the installed allocator, fixing it to [`Allocator::GLOBAL`] if none was installed yet.
+pub(crate) fn allocator() -> &'static Allocator {
+    let mut allocator = ALLOCATOR.load(Ordering::Acquire);
+    if allocator.is_null() {
+        let global = ptr::from_ref(&GLOBAL_ALLOCATOR).cast_mut();
+        allocator = match ALLOCATOR.compare_exchange(
+            ptr::null_mut(),
+            global,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => global,
+            Err(allocator) => allocator,
+        };
+    }
+    unsafe { &*allocator }
+}
+
+/// Check if ENet's memory comes from Rust's global allocator, in which case buffers may be handed
+/// to [`Vec`](`crate::Vec`) without copying.
+pub(crate) fn uses_global_allocator() -> bool {
+    ptr::eq(allocator(), &raw const GLOBAL_ALLOCATOR)
+}
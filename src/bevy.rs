@@ -0,0 +1,156 @@
+//! Integration with the [Bevy](https://bevyengine.org) game engine.
+//!
+//! [`bevy::EnetPlugin`] takes ownership of a [`Host`], inserting it as the [`bevy::EnetHost`]
+//! resource. Every fixed timestep, the host is serviced in
+//! [`FixedPreUpdate`](`bevy_app::FixedPreUpdate`), with each event written as a
+//! [`bevy::EnetEvent`] message, and flushed in [`FixedPostUpdate`](`bevy_app::FixedPostUpdate`),
+//! so packets sent by systems in [`FixedUpdate`](`bevy_app::FixedUpdate`) go out on the same
+//! tick. The fixed schedules are run by Bevy's `TimePlugin`, part of its `DefaultPlugins` and
+//! `MinimalPlugins`.
+//!
+//! ```no_run
+//! use std::net::UdpSocket;
+//!
+//! use bevy_app::{App, FixedUpdate};
+//! use bevy_ecs::{message::MessageReader, system::ResMut};
+//! use rusty_enet::{
+//!     bevy::{EnetEvent, EnetHost, EnetPlugin},
+//!     EventNoRef, Host, HostSettings,
+//! };
+//!
+//! fn echo(mut host: ResMut<EnetHost<UdpSocket>>, mut events: MessageReader<EnetEvent>) {
+//!     for EnetEvent(event) in events.read() {
+//!         if let EventNoRef::Receive { peer, channel_id, packet, .. } = event {
+//!             if let Some(peer) = host.peer_mut(*peer) {
+//!                 _ = peer.send(*channel_id, packet);
+//!             }
+//!         }
+//!     }
+//! }
+//!
+//! let socket = UdpSocket::bind("0.0.0.0:6060").unwrap();
+//! let host = Host::new(socket, HostSettings::default()).unwrap();
+//! App::new()
+//!     .add_plugins(EnetPlugin::new(host))
+//!     .add_systems(FixedUpdate, echo)
+//!     .run();
+//! ```
+
+use core::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use bevy_app::{App, FixedPostUpdate, FixedPreUpdate, Plugin};
+use bevy_ecs::{
+    message::{Message, MessageWriter},
+    resource::Resource,
+    system::ResMut,
+};
+
+use crate::{error::ServiceError, EventNoRef, Host, Socket};
+
+#[cfg(doc)]
+use crate::bevy;
+
+/// A Bevy plugin which owns a [`Host`], servicing it every fixed timestep. See the
+/// [module documentation](`crate::bevy`).
+pub struct EnetPlugin<S: Socket> {
+    host: Mutex<Option<Host<S>>>,
+}
+
+impl<S: Socket> EnetPlugin<S> {
+    /// Create a plugin which inserts `host` as the [`EnetHost`] resource.
+    #[must_use]
+    pub fn new(host: Host<S>) -> Self {
+        Self {
+            host: Mutex::new(Some(host)),
+        }
+    }
+}
+
+impl<S> Plugin for EnetPlugin<S>
+where
+    S: Socket + Send + Sync + 'static,
+    S::Address: Send + Sync,
+    S::Error: Send + Sync,
+{
+    fn build(&self, app: &mut App) {
+        let host = self
+            .host
+            .lock()
+            .unwrap()
+            .take()
+            .expect("Expected the plugin to be built once.");
+        app.insert_resource(EnetHost(host))
+            .add_message::<EnetEvent>()
+            .add_message::<EnetServiceError<S>>()
+            .add_systems(FixedPreUpdate, service::<S>)
+            .add_systems(FixedPostUpdate, flush::<S>);
+    }
+}
+
+/// The resource holding the [`Host`] added by [`EnetPlugin`], dereferencing to the host.
+pub struct EnetHost<S: Socket>(pub Host<S>);
+
+impl<S: Socket + Send + Sync + 'static> Resource for EnetHost<S> where S::Address: Send + Sync {}
+
+impl<S: Socket> Deref for EnetHost<S> {
+    type Target = Host<S>;
+
+    fn deref(&self) -> &Host<S> {
+        &self.0
+    }
+}
+
+impl<S: Socket> DerefMut for EnetHost<S> {
+    fn deref_mut(&mut self) -> &mut Host<S> {
+        &mut self.0
+    }
+}
+
+/// A message written for each event produced by [`Host::service`].
+#[derive(Debug, Clone)]
+pub struct EnetEvent(pub EventNoRef);
+
+impl Message for EnetEvent {}
+
+/// A message written when [`Host::service`] fails. The host is serviced again on the next fixed
+/// timestep.
+pub struct EnetServiceError<S: Socket>(pub ServiceError<S>);
+
+impl<S: Socket + 'static> Message for EnetServiceError<S>
+where
+    S::Address: Send + Sync,
+    S::Error: Send + Sync,
+{
+}
+
+fn service<S>(
+    mut host: ResMut<EnetHost<S>>,
+    mut events: MessageWriter<EnetEvent>,
+    mut errors: MessageWriter<EnetServiceError<S>>,
+) where
+    S: Socket + Send + Sync + 'static,
+    S::Address: Send + Sync,
+    S::Error: Send + Sync,
+{
+    loop {
+        match host.service() {
+            Ok(Some(event)) => {
+                events.write(EnetEvent(event.no_ref()));
+            }
+            Ok(None) => break,
+            Err(err) => {
+                errors.write(EnetServiceError(err));
+                break;
+            }
+        }
+    }
+}
+
+fn flush<S>(mut host: ResMut<EnetHost<S>>)
+where
+    S: Socket + Send + Sync + 'static,
+    S::Address: Send + Sync,
+{
+    host.flush();
+}
@@ -1,4 +1,4 @@
-use core::ptr::NonNull;
+use core::{ptr::NonNull, time::Duration};
 
 use crate::Socket;
 
@@ -9,7 +9,9 @@ mod list;
 mod malloc;
 mod packet;
 mod peer;
+mod pool;
 mod protocol;
+mod reader;
 
 pub(crate) use compress::*;
 pub(crate) use event::*;
@@ -18,7 +20,9 @@ pub(crate) use list::*;
 pub(crate) use malloc::*;
 pub(crate) use packet::*;
 pub(crate) use peer::*;
+pub(crate) use pool::*;
 pub(crate) use protocol::*;
+pub(crate) use reader::*;
 
 #[derive(Copy, Clone)]
 #[repr(C)]
@@ -37,26 +41,31 @@ pub(crate) struct ENetChannel {
     pub(crate) incoming_unreliable_sequence_number: u16,
     pub(crate) incoming_reliable_commands: ENetList,
     pub(crate) incoming_unreliable_commands: ENetList,
+    pub(crate) packets_sent: u32,
+    pub(crate) packets_received: u32,
+    pub(crate) reliable_resends: u32,
 }
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub(crate) struct ENetAcknowledgement {
-    pub(crate) acknowledgement_list: ENetListNode,
     pub(crate) sent_time: u32,
     pub(crate) command: ENetProtocol,
 }
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub(crate) struct ENetOutgoingCommand {
-    pub(crate) outgoing_command_list: ENetListNode,
     pub(crate) reliable_sequence_number: u16,
     pub(crate) unreliable_sequence_number: u16,
     pub(crate) sent_time: u32,
     pub(crate) round_trip_timeout: u32,
     pub(crate) queue_time: u32,
+    pub(crate) queued_time: Duration,
     pub(crate) fragment_offset: u32,
     pub(crate) fragment_length: u16,
     pub(crate) send_attempts: u16,
+    /// How many acknowledgements arrived for reliable commands sent after this one since it was
+    /// last sent, see [`HostSettings::fast_retransmit`](`crate::HostSettings::fast_retransmit`).
+    pub(crate) later_acknowledgements: u32,
     pub(crate) command: ENetProtocol,
     pub(crate) packet: *mut ENetPacket,
 }
@@ -69,6 +78,10 @@ pub(crate) struct ENetIncomingCommand {
     pub(crate) command: ENetProtocol,
     pub(crate) fragment_count: u32,
     pub(crate) fragments_remaining: u32,
+    /// Orders incomplete unreliable fragmented packets by when they were started, see
+    /// `ENetHost::incomplete_unreliable_fragments`.
+    pub(crate) fragment_order: u64,
+    pub(crate) received_time: Duration,
     pub(crate) fragments: *mut u32,
     pub(crate) packet: *mut ENetPacket,
 }
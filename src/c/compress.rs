@@ -1,14 +1,18 @@
-use core::{alloc::Layout, cmp::Ordering, ptr::NonNull};
+//! ENet's adaptive range coder, in safe Rust.
+//!
+//! Symbols form a tree of contexts stored in a fixed array, linked by relative (`left`, `right`,
+//! `symbols`) and absolute (`parent`) indices, exactly as ENet lays them out, so the encoded
+//! output matches ENet's byte for byte.
 
-use crate::{enet_free, enet_malloc, ENetBuffer};
+use core::cmp::Ordering;
+
+use crate::Box;
 
 #[derive(Copy, Clone)]
-#[repr(C)]
 pub(crate) struct ENetRangeCoder {
     pub(crate) symbols: [ENetSymbol; 4096],
 }
-#[derive(Copy, Clone)]
-#[repr(C)]
+#[derive(Copy, Clone, Default)]
 pub(crate) struct ENetSymbol {
     pub(crate) value: u8,
     pub(crate) count: u8,
@@ -20,968 +24,579 @@ pub(crate) struct ENetSymbol {
     pub(crate) total: u16,
     pub(crate) parent: u16,
 }
-pub(crate) const ENET_CONTEXT_SYMBOL_MINIMUM: u32 = 1;
-pub(crate) const ENET_CONTEXT_ESCAPE_MINIMUM: u32 = 1;
-pub(crate) const ENET_SUBCONTEXT_ORDER: u32 = 2;
+pub(crate) const ENET_CONTEXT_SYMBOL_MINIMUM: u16 = 1;
+pub(crate) const ENET_CONTEXT_ESCAPE_MINIMUM: u16 = 1;
+pub(crate) const ENET_SUBCONTEXT_ORDER: usize = 2;
 pub(crate) const ENET_RANGE_CODER_BOTTOM: u32 = 65536;
-pub(crate) const ENET_SUBCONTEXT_SYMBOL_DELTA: u32 = 2;
-pub(crate) const ENET_SUBCONTEXT_ESCAPE_DELTA: u32 = 5;
-pub(crate) const ENET_CONTEXT_SYMBOL_DELTA: u32 = 3;
+pub(crate) const ENET_SUBCONTEXT_SYMBOL_DELTA: u8 = 2;
+pub(crate) const ENET_SUBCONTEXT_ESCAPE_DELTA: u16 = 5;
+pub(crate) const ENET_CONTEXT_SYMBOL_DELTA: u8 = 3;
 pub(crate) const ENET_RANGE_CODER_TOP: u32 = 16777216;
-pub(crate) unsafe fn enet_range_coder_create() -> *mut u8 {
-    let range_coder: *mut ENetRangeCoder = enet_malloc(Layout::new::<ENetRangeCoder>()).cast();
-    range_coder.cast()
+
+/// The root context is always the first symbol.
+const ROOT: usize = 0;
+
+pub(crate) fn enet_range_coder_create() -> Box<ENetRangeCoder> {
+    Box::new(ENetRangeCoder {
+        symbols: [ENetSymbol::default(); 4096],
+    })
 }
-pub(crate) unsafe fn enet_range_coder_destroy(context: *mut u8) {
-    let range_coder: *mut ENetRangeCoder = context.cast();
-    if range_coder.is_null() {
-        return;
-    }
-    enet_free(range_coder.cast(), Layout::new::<ENetRangeCoder>());
+
+/// Where the index of the next symbol coded is linked, either the predicted context, or the
+/// `parent` of the previous symbol.
+#[derive(Copy, Clone)]
+enum Parent {
+    Predicted,
+    Symbol(usize),
 }
-unsafe fn enet_symbol_rescale(mut symbol: *mut ENetSymbol) -> u16 {
-    let mut total: u16 = 0_i32 as u16;
-    loop {
-        (*symbol).count = ((*symbol).count as i32 - ((*symbol).count as i32 >> 1_i32)) as u8;
-        (*symbol).under = (*symbol).count as u16;
-        if (*symbol).left != 0 {
-            (*symbol).under = ((*symbol).under as i32
-                + enet_symbol_rescale(symbol.offset((*symbol).left as i32 as isize)) as i32)
-                as u16;
-        }
-        total = (total as i32 + (*symbol).under as i32) as u16;
-        if (*symbol).right == 0 {
-            break;
+
+struct Contexts<'a> {
+    symbols: &'a mut [ENetSymbol; 4096],
+    next_symbol: usize,
+    predicted: u16,
+    order: usize,
+}
+
+impl<'a> Contexts<'a> {
+    fn new(range_coder: &'a mut ENetRangeCoder) -> Self {
+        let mut contexts = Self {
+            symbols: &mut range_coder.symbols,
+            next_symbol: 0,
+            predicted: 0,
+            order: 0,
+        };
+        contexts.create_context(ENET_CONTEXT_ESCAPE_MINIMUM, ENET_CONTEXT_SYMBOL_MINIMUM);
+        contexts
+    }
+
+    fn create_symbol(&mut self, value: u8, count: u8) -> usize {
+        let symbol = self.next_symbol;
+        self.next_symbol += 1;
+        self.symbols[symbol] = ENetSymbol {
+            value,
+            count,
+            under: u16::from(count),
+            ..ENetSymbol::default()
+        };
+        symbol
+    }
+
+    fn create_context(&mut self, escapes: u16, minimum: u16) -> usize {
+        let context = self.create_symbol(0, 0);
+        self.symbols[context].escapes = escapes;
+        self.symbols[context].total = escapes.wrapping_add(256 * minimum);
+        context
+    }
+
+    fn link(&mut self, parent: Parent, symbol: usize) {
+        let symbol = symbol as u16;
+        match parent {
+            Parent::Predicted => self.predicted = symbol,
+            Parent::Symbol(parent) => self.symbols[parent].parent = symbol,
         }
-        symbol = symbol.offset((*symbol).right as i32 as isize);
     }
-    total
-}
-pub(crate) unsafe fn enet_range_coder_compress(
-    context: *mut u8,
-    mut in_buffers: *const ENetBuffer,
-    mut in_buffer_count: usize,
-    in_limit: usize,
-    mut out_data: *mut u8,
-    out_limit: usize,
-) -> usize {
-    let range_coder: *mut ENetRangeCoder = context.cast();
-    let out_start: *mut u8 = out_data;
-    let out_end: *mut u8 = out_data.add(out_limit);
-    let mut in_data: *const u8;
-    let mut in_end: *const u8;
-    let mut encode_low: u32 = 0_i32 as u32;
-    let mut encode_range: u32 = !0_i32 as u32;
-    let mut root: *mut ENetSymbol;
-    let mut predicted: u16 = 0_i32 as u16;
-    let mut order: usize = 0_i32 as usize;
-    let mut next_symbol: usize = 0_i32 as usize;
-    if range_coder.is_null() || in_buffer_count <= 0_i32 as usize || in_limit <= 0_i32 as usize {
-        return 0_i32 as usize;
+
+    /// Move to the next prediction, clearing every context once the symbols are almost full.
+    fn advance(&mut self) {
+        if self.order >= ENET_SUBCONTEXT_ORDER {
+            self.predicted = self.symbols[usize::from(self.predicted)].parent;
+        } else {
+            self.order += 1;
+        }
+        if self.next_symbol >= self.symbols.len() - ENET_SUBCONTEXT_ORDER {
+            self.next_symbol = 0;
+            self.create_context(ENET_CONTEXT_ESCAPE_MINIMUM, ENET_CONTEXT_SYMBOL_MINIMUM);
+            self.predicted = 0;
+            self.order = 0;
+        }
     }
-    in_data = (*in_buffers).data.cast_const();
-    in_end = in_data.add((*in_buffers).data_length);
-    in_buffers = in_buffers.offset(1);
-    in_buffer_count = in_buffer_count.wrapping_sub(1);
-    let fresh0 = next_symbol;
-    next_symbol = next_symbol.wrapping_add(1);
-    root = ((*range_coder).symbols).as_mut_ptr().add(fresh0);
-    (*root).value = 0_i32 as u8;
-    (*root).count = 0_i32 as u8;
-    (*root).under = 0_i32 as u16;
-    (*root).left = 0_i32 as u16;
-    (*root).right = 0_i32 as u16;
-    (*root).symbols = 0_i32 as u16;
-    (*root).escapes = 0_i32 as u16;
-    (*root).total = 0_i32 as u16;
-    (*root).parent = 0_i32 as u16;
-    (*root).escapes = ENET_CONTEXT_ESCAPE_MINIMUM as i32 as u16;
-    (*root).total =
-        (ENET_CONTEXT_ESCAPE_MINIMUM as i32 + 256_i32 * ENET_CONTEXT_SYMBOL_MINIMUM as i32) as u16;
-    (*root).symbols = 0_i32 as u16;
-    let mut current_block_237: u64;
-    loop {
-        let mut subcontext: *mut ENetSymbol;
-        let mut symbol: *mut ENetSymbol;
-        let mut count: u16;
-        let mut under: u16;
-        let mut parent: *mut u16 = &mut predicted;
-        let mut total: u16;
-        if in_data >= in_end {
-            if in_buffer_count <= 0_i32 as usize {
+
+    fn rescale_symbols(&mut self, mut symbol: usize) -> u16 {
+        let mut total: u16 = 0;
+        loop {
+            let count = self.symbols[symbol].count;
+            self.symbols[symbol].count = count - (count >> 1);
+            self.symbols[symbol].under = u16::from(self.symbols[symbol].count);
+            let left = usize::from(self.symbols[symbol].left);
+            if left != 0 {
+                let under = self.rescale_symbols(symbol + left);
+                self.symbols[symbol].under = self.symbols[symbol].under.wrapping_add(under);
+            }
+            total = total.wrapping_add(self.symbols[symbol].under);
+            let right = usize::from(self.symbols[symbol].right);
+            if right == 0 {
                 break;
             }
-            in_data = (*in_buffers).data.cast_const();
-            in_end = in_data.add((*in_buffers).data_length);
-            in_buffers = in_buffers.offset(1);
-            in_buffer_count = in_buffer_count.wrapping_sub(1);
+            symbol += right;
         }
-        let value = if in_data == NonNull::dangling().as_ptr() {
-            in_data = in_data.offset(1);
-            0
+        total
+    }
+
+    fn rescale(&mut self, context: usize, minimum: u16) {
+        let symbols = usize::from(self.symbols[context].symbols);
+        let total = if symbols != 0 {
+            self.rescale_symbols(context + symbols)
         } else {
-            let fresh1 = in_data;
-            in_data = in_data.offset(1);
-            *fresh1
+            0
         };
-        subcontext = ((*range_coder).symbols)
-            .as_mut_ptr()
-            .offset(predicted as isize);
+        let escapes = self.symbols[context].escapes;
+        let escapes = escapes - (escapes >> 1);
+        self.symbols[context].escapes = escapes;
+        self.symbols[context].total = total.wrapping_add(escapes).wrapping_add(256 * minimum);
+    }
+
+    /// Find or add `value` in a context, returning its symbol, and the `under` and `count` to
+    /// code it with. A `count` of `0` means the value was new to a subcontext, and must be
+    /// escaped.
+    fn encode(&mut self, context: usize, value: u8, update: u8, minimum: u16) -> (usize, u16, u16) {
+        let mut under = u16::from(value).wrapping_mul(minimum);
+        let mut count = minimum;
+        let symbols = usize::from(self.symbols[context].symbols);
+        if symbols == 0 {
+            let symbol = self.create_symbol(value, update);
+            self.symbols[context].symbols = (symbol - context) as u16;
+            return (symbol, under, count);
+        }
+        let mut node = context + symbols;
         loop {
-            if subcontext == root {
-                current_block_237 = 2463987395154258233;
-                break;
-            }
-            under = 0_u16;
-            count = 0_i32 as u16;
-            if (*subcontext).symbols == 0 {
-                let fresh2 = next_symbol;
-                next_symbol = next_symbol.wrapping_add(1);
-                symbol = ((*range_coder).symbols).as_mut_ptr().add(fresh2);
-                (*symbol).value = value;
-                (*symbol).count = ENET_SUBCONTEXT_SYMBOL_DELTA as i32 as u8;
-                (*symbol).under = ENET_SUBCONTEXT_SYMBOL_DELTA as i32 as u16;
-                (*symbol).left = 0_i32 as u16;
-                (*symbol).right = 0_i32 as u16;
-                (*symbol).symbols = 0_i32 as u16;
-                (*symbol).escapes = 0_i32 as u16;
-                (*symbol).total = 0_i32 as u16;
-                (*symbol).parent = 0_i32 as u16;
-                (*subcontext).symbols = symbol.offset_from(subcontext) as i64 as u16;
-            } else {
-                let mut node: *mut ENetSymbol =
-                    subcontext.offset((*subcontext).symbols as i32 as isize);
-                loop {
-                    match (value as i32).cmp(&((*node).value as i32)) {
-                        Ordering::Less => {
-                            (*node).under =
-                                ((*node).under as i32 + ENET_SUBCONTEXT_SYMBOL_DELTA as i32) as u16;
-                            if (*node).left != 0 {
-                                node = node.offset((*node).left as i32 as isize);
-                            } else {
-                                let fresh3 = next_symbol;
-                                next_symbol = next_symbol.wrapping_add(1);
-                                symbol = ((*range_coder).symbols).as_mut_ptr().add(fresh3);
-                                (*symbol).value = value;
-                                (*symbol).count = ENET_SUBCONTEXT_SYMBOL_DELTA as i32 as u8;
-                                (*symbol).under = ENET_SUBCONTEXT_SYMBOL_DELTA as i32 as u16;
-                                (*symbol).left = 0_i32 as u16;
-                                (*symbol).right = 0_i32 as u16;
-                                (*symbol).symbols = 0_i32 as u16;
-                                (*symbol).escapes = 0_i32 as u16;
-                                (*symbol).total = 0_i32 as u16;
-                                (*symbol).parent = 0_i32 as u16;
-                                (*node).left = symbol.offset_from(node) as i64 as u16;
-                                break;
-                            }
-                        }
-                        Ordering::Greater => {
-                            under = (under as i32 + (*node).under as i32) as u16;
-                            if (*node).right != 0 {
-                                node = node.offset((*node).right as i32 as isize);
-                            } else {
-                                let fresh4 = next_symbol;
-                                next_symbol = next_symbol.wrapping_add(1);
-                                symbol = ((*range_coder).symbols).as_mut_ptr().add(fresh4);
-                                (*symbol).value = value;
-                                (*symbol).count = ENET_SUBCONTEXT_SYMBOL_DELTA as i32 as u8;
-                                (*symbol).under = ENET_SUBCONTEXT_SYMBOL_DELTA as i32 as u16;
-                                (*symbol).left = 0_i32 as u16;
-                                (*symbol).right = 0_i32 as u16;
-                                (*symbol).symbols = 0_i32 as u16;
-                                (*symbol).escapes = 0_i32 as u16;
-                                (*symbol).total = 0_i32 as u16;
-                                (*symbol).parent = 0_i32 as u16;
-                                (*node).right = symbol.offset_from(node) as i64 as u16;
-                                break;
-                            }
-                        }
-                        Ordering::Equal => {
-                            count = (count as i32 + (*node).count as i32) as u16;
-                            under = (under as i32 + ((*node).under as i32 - (*node).count as i32))
-                                as u16;
-                            (*node).under =
-                                ((*node).under as i32 + ENET_SUBCONTEXT_SYMBOL_DELTA as i32) as u16;
-                            (*node).count =
-                                ((*node).count as i32 + ENET_SUBCONTEXT_SYMBOL_DELTA as i32) as u8;
-                            symbol = node;
-                            break;
-                        }
+            match value.cmp(&self.symbols[node].value) {
+                Ordering::Less => {
+                    let symbol = &mut self.symbols[node];
+                    symbol.under = symbol.under.wrapping_add(u16::from(update));
+                    if symbol.left != 0 {
+                        node += usize::from(symbol.left);
+                        continue;
                     }
+                    let symbol = self.create_symbol(value, update);
+                    self.symbols[node].left = (symbol - node) as u16;
+                    return (symbol, under, count);
                 }
-            }
-            *parent = symbol.offset_from(((*range_coder).symbols).as_mut_ptr()) as i64 as u16;
-            parent = &mut (*symbol).parent;
-            total = (*subcontext).total;
-            if count as i32 > 0_i32 {
-                encode_range = encode_range.wrapping_div(total as u32);
-                encode_low = encode_low.wrapping_add(
-                    (((*subcontext).escapes as i32 + under as i32) as u32)
-                        .wrapping_mul(encode_range),
-                );
-                encode_range = encode_range.wrapping_mul(count as u32);
-                loop {
-                    if encode_low ^ encode_low.wrapping_add(encode_range)
-                        >= ENET_RANGE_CODER_TOP as i32 as u32
-                    {
-                        if encode_range >= ENET_RANGE_CODER_BOTTOM as i32 as u32 {
-                            break;
-                        }
-                        encode_range = encode_low.wrapping_neg()
-                            & (ENET_RANGE_CODER_BOTTOM as i32 - 1_i32) as u32;
-                    }
-                    if out_data >= out_end {
-                        return 0_i32 as usize;
+                Ordering::Greater => {
+                    under = under.wrapping_add(self.symbols[node].under);
+                    if self.symbols[node].right != 0 {
+                        node += usize::from(self.symbols[node].right);
+                        continue;
                     }
-                    let fresh5 = out_data;
-                    out_data = out_data.offset(1);
-                    *fresh5 = (encode_low >> 24_i32) as u8;
-                    encode_range <<= 8_i32;
-                    encode_low <<= 8_i32;
+                    let symbol = self.create_symbol(value, update);
+                    self.symbols[node].right = (symbol - node) as u16;
+                    return (symbol, under, count);
                 }
-            } else {
-                if (*subcontext).escapes as i32 > 0_i32
-                    && ((*subcontext).escapes as i32) < total as i32
-                {
-                    encode_range = encode_range.wrapping_div(total as u32);
-                    encode_low = encode_low.wrapping_add((0_i32 as u32).wrapping_mul(encode_range));
-                    encode_range = encode_range.wrapping_mul((*subcontext).escapes as u32);
-                    loop {
-                        if encode_low ^ encode_low.wrapping_add(encode_range)
-                            >= ENET_RANGE_CODER_TOP as i32 as u32
-                        {
-                            if encode_range >= ENET_RANGE_CODER_BOTTOM as i32 as u32 {
-                                break;
-                            }
-                            encode_range = encode_low.wrapping_neg()
-                                & (ENET_RANGE_CODER_BOTTOM as i32 - 1_i32) as u32;
-                        }
-                        if out_data >= out_end {
-                            return 0_i32 as usize;
-                        }
-                        let fresh6 = out_data;
-                        out_data = out_data.offset(1);
-                        *fresh6 = (encode_low >> 24_i32) as u8;
-                        encode_range <<= 8_i32;
-                        encode_low <<= 8_i32;
-                    }
+                Ordering::Equal => {
+                    let symbol = &mut self.symbols[node];
+                    count = count.wrapping_add(u16::from(symbol.count));
+                    under = under
+                        .wrapping_add(symbol.under)
+                        .wrapping_sub(u16::from(symbol.count));
+                    symbol.under = symbol.under.wrapping_add(u16::from(update));
+                    symbol.count = symbol.count.wrapping_add(update);
+                    return (node, under, count);
                 }
-                (*subcontext).escapes =
-                    ((*subcontext).escapes as i32 + ENET_SUBCONTEXT_ESCAPE_DELTA as i32) as u16;
-                (*subcontext).total =
-                    ((*subcontext).total as i32 + ENET_SUBCONTEXT_ESCAPE_DELTA as i32) as u16;
             }
-            (*subcontext).total =
-                ((*subcontext).total as i32 + ENET_SUBCONTEXT_SYMBOL_DELTA as i32) as u16;
-            if count as i32 > 0xff_i32 - 2_i32 * ENET_SUBCONTEXT_SYMBOL_DELTA as i32
-                || (*subcontext).total as i32 > ENET_RANGE_CODER_BOTTOM as i32 - 0x100_i32
-            {
-                (*subcontext).total = (if (*subcontext).symbols as i32 != 0 {
-                    enet_symbol_rescale(subcontext.offset((*subcontext).symbols as i32 as isize))
-                        as i32
-                } else {
-                    0_i32
-                }) as u16;
-                (*subcontext).escapes =
-                    ((*subcontext).escapes as i32 - ((*subcontext).escapes as i32 >> 1_i32)) as u16;
-                (*subcontext).total =
-                    ((*subcontext).total as i32 + (*subcontext).escapes as i32) as u16;
-            }
-            if count as i32 > 0_i32 {
-                current_block_237 = 836937598693885467;
-                break;
-            }
-            subcontext = ((*range_coder).symbols)
-                .as_mut_ptr()
-                .offset((*subcontext).parent as isize);
         }
-        if let 2463987395154258233 = current_block_237 {
-            under = (value as i32 * ENET_CONTEXT_SYMBOL_MINIMUM as i32) as u16;
-            count = ENET_CONTEXT_SYMBOL_MINIMUM as i32 as u16;
-            if (*root).symbols == 0 {
-                let fresh7 = next_symbol;
-                next_symbol = next_symbol.wrapping_add(1);
-                symbol = ((*range_coder).symbols).as_mut_ptr().add(fresh7);
-                (*symbol).value = value;
-                (*symbol).count = ENET_CONTEXT_SYMBOL_DELTA as i32 as u8;
-                (*symbol).under = ENET_CONTEXT_SYMBOL_DELTA as i32 as u16;
-                (*symbol).left = 0_i32 as u16;
-                (*symbol).right = 0_i32 as u16;
-                (*symbol).symbols = 0_i32 as u16;
-                (*symbol).escapes = 0_i32 as u16;
-                (*symbol).total = 0_i32 as u16;
-                (*symbol).parent = 0_i32 as u16;
-                (*root).symbols = symbol.offset_from(root) as i64 as u16;
-            } else {
-                let mut node_0: *mut ENetSymbol = root.offset((*root).symbols as i32 as isize);
-                loop {
-                    match (value as i32).cmp(&((*node_0).value as i32)) {
-                        Ordering::Less => {
-                            (*node_0).under =
-                                ((*node_0).under as i32 + ENET_CONTEXT_SYMBOL_DELTA as i32) as u16;
-                            if (*node_0).left != 0 {
-                                node_0 = node_0.offset((*node_0).left as i32 as isize);
-                            } else {
-                                let fresh8 = next_symbol;
-                                next_symbol = next_symbol.wrapping_add(1);
-                                symbol = ((*range_coder).symbols).as_mut_ptr().add(fresh8);
-                                (*symbol).value = value;
-                                (*symbol).count = ENET_CONTEXT_SYMBOL_DELTA as i32 as u8;
-                                (*symbol).under = ENET_CONTEXT_SYMBOL_DELTA as i32 as u16;
-                                (*symbol).left = 0_i32 as u16;
-                                (*symbol).right = 0_i32 as u16;
-                                (*symbol).symbols = 0_i32 as u16;
-                                (*symbol).escapes = 0_i32 as u16;
-                                (*symbol).total = 0_i32 as u16;
-                                (*symbol).parent = 0_i32 as u16;
-                                (*node_0).left = symbol.offset_from(node_0) as i64 as u16;
-                                break;
-                            }
-                        }
-                        Ordering::Greater => {
-                            under = (under as i32 + (*node_0).under as i32) as u16;
-                            if (*node_0).right != 0 {
-                                node_0 = node_0.offset((*node_0).right as i32 as isize);
-                            } else {
-                                let fresh9 = next_symbol;
-                                next_symbol = next_symbol.wrapping_add(1);
-                                symbol = ((*range_coder).symbols).as_mut_ptr().add(fresh9);
-                                (*symbol).value = value;
-                                (*symbol).count = ENET_CONTEXT_SYMBOL_DELTA as i32 as u8;
-                                (*symbol).under = ENET_CONTEXT_SYMBOL_DELTA as i32 as u16;
-                                (*symbol).left = 0_i32 as u16;
-                                (*symbol).right = 0_i32 as u16;
-                                (*symbol).symbols = 0_i32 as u16;
-                                (*symbol).escapes = 0_i32 as u16;
-                                (*symbol).total = 0_i32 as u16;
-                                (*symbol).parent = 0_i32 as u16;
-                                (*node_0).right = symbol.offset_from(node_0) as i64 as u16;
-                                break;
-                            }
-                        }
-                        Ordering::Equal => {
-                            count = (count as i32 + (*node_0).count as i32) as u16;
-                            under = (under as i32
-                                + ((*node_0).under as i32 - (*node_0).count as i32))
-                                as u16;
-                            (*node_0).under =
-                                ((*node_0).under as i32 + ENET_CONTEXT_SYMBOL_DELTA as i32) as u16;
-                            (*node_0).count =
-                                ((*node_0).count as i32 + ENET_CONTEXT_SYMBOL_DELTA as i32) as u8;
-                            symbol = node_0;
-                            break;
-                        }
-                    }
+    }
+
+    /// Find the symbol a subcontext `code` refers to, returning its symbol, value, `under` and
+    /// `count`, or `None` if the code is invalid.
+    fn decode(&mut self, context: usize, code: u16) -> Option<(usize, u8, u16, u16)> {
+        let update = u16::from(ENET_SUBCONTEXT_SYMBOL_DELTA);
+        let mut under: u16 = 0;
+        let symbols = usize::from(self.symbols[context].symbols);
+        if symbols == 0 {
+            return None;
+        }
+        let mut node = context + symbols;
+        loop {
+            let symbol = &mut self.symbols[node];
+            let after = under.wrapping_add(symbol.under);
+            let before = u16::from(symbol.count);
+            if code >= after {
+                under = under.wrapping_add(symbol.under);
+                if symbol.right == 0 {
+                    return None;
+                }
+                node += usize::from(symbol.right);
+            } else if i32::from(code) < i32::from(after) - i32::from(before) {
+                symbol.under = symbol.under.wrapping_add(update);
+                if symbol.left == 0 {
+                    return None;
                 }
+                node += usize::from(symbol.left);
+            } else {
+                let value = symbol.value;
+                let count = u16::from(symbol.count);
+                symbol.under = symbol.under.wrapping_add(update);
+                symbol.count = symbol.count.wrapping_add(ENET_SUBCONTEXT_SYMBOL_DELTA);
+                return Some((node, value, after.wrapping_sub(before), count));
             }
-            *parent = symbol.offset_from(((*range_coder).symbols).as_mut_ptr()) as i64 as u16;
-            total = (*root).total;
-            encode_range = encode_range.wrapping_div(total as u32);
-            encode_low = encode_low.wrapping_add(
-                (((*root).escapes as i32 + under as i32) as u32).wrapping_mul(encode_range),
+        }
+    }
+
+    /// Find the symbol a root `code` refers to, adding it if it is new, returning its symbol,
+    /// value, `under` and `count`.
+    #[allow(clippy::cast_possible_truncation)]
+    fn decode_root(&mut self, code: u16) -> (usize, u8, u16, u16) {
+        let update = ENET_CONTEXT_SYMBOL_DELTA;
+        let minimum = i32::from(ENET_CONTEXT_SYMBOL_MINIMUM);
+        let code_i32 = i32::from(code);
+        let mut under: u16 = 0;
+        let count = ENET_CONTEXT_SYMBOL_MINIMUM;
+        let symbols = usize::from(self.symbols[ROOT].symbols);
+        if symbols == 0 {
+            let value = (code_i32 / minimum) as u8;
+            let under = (code_i32 - code_i32 % minimum) as u16;
+            let symbol = self.create_symbol(value, update);
+            self.symbols[ROOT].symbols = (symbol - ROOT) as u16;
+            return (symbol, value, under, count);
+        }
+        let mut node = ROOT + symbols;
+        loop {
+            let symbol = self.symbols[node];
+            let after = i32::from(
+                under
+                    .wrapping_add(symbol.under)
+                    .wrapping_add(((i32::from(symbol.value) + 1) * minimum) as u16),
             );
-            encode_range = encode_range.wrapping_mul(count as u32);
-            loop {
-                if encode_low ^ encode_low.wrapping_add(encode_range)
-                    >= ENET_RANGE_CODER_TOP as i32 as u32
-                {
-                    if encode_range >= ENET_RANGE_CODER_BOTTOM as i32 as u32 {
-                        break;
-                    }
-                    encode_range =
-                        encode_low.wrapping_neg() & (ENET_RANGE_CODER_BOTTOM as i32 - 1_i32) as u32;
+            let before = i32::from(symbol.count) + minimum;
+            if code_i32 >= after {
+                under = under.wrapping_add(symbol.under);
+                if symbol.right != 0 {
+                    node += usize::from(symbol.right);
+                    continue;
                 }
-                if out_data >= out_end {
-                    return 0_i32 as usize;
+                let value = (i32::from(symbol.value) + 1 + (code_i32 - after) / minimum) as u8;
+                let under = (code_i32 - (code_i32 - after) % minimum) as u16;
+                let created = self.create_symbol(value, update);
+                self.symbols[node].right = (created - node) as u16;
+                return (created, value, under, count);
+            } else if code_i32 < after - before {
+                self.symbols[node].under = symbol.under.wrapping_add(u16::from(update));
+                if symbol.left != 0 {
+                    node += usize::from(symbol.left);
+                    continue;
                 }
-                let fresh10 = out_data;
-                out_data = out_data.offset(1);
-                *fresh10 = (encode_low >> 24_i32) as u8;
-                encode_range <<= 8_i32;
-                encode_low <<= 8_i32;
+                let value =
+                    (i32::from(symbol.value) - 1 - (after - before - code_i32 - 1) / minimum) as u8;
+                let under = (code_i32 - (after - before - code_i32 - 1) % minimum) as u16;
+                let created = self.create_symbol(value, update);
+                self.symbols[node].left = (created - node) as u16;
+                return (created, value, under, count);
+            } else {
+                self.symbols[node].under = symbol.under.wrapping_add(u16::from(update));
+                self.symbols[node].count = symbol.count.wrapping_add(update);
+                return (
+                    node,
+                    symbol.value,
+                    (after - before) as u16,
+                    count.wrapping_add(u16::from(symbol.count)),
+                );
             }
-            (*root).total = ((*root).total as i32 + ENET_CONTEXT_SYMBOL_DELTA as i32) as u16;
-            if count as i32
-                > 0xff_i32 - 2_i32 * ENET_CONTEXT_SYMBOL_DELTA as i32
-                    + ENET_CONTEXT_SYMBOL_MINIMUM as i32
-                || (*root).total as i32 > ENET_RANGE_CODER_BOTTOM as i32 - 0x100_i32
-            {
-                (*root).total = (if (*root).symbols as i32 != 0 {
-                    enet_symbol_rescale(root.offset((*root).symbols as i32 as isize)) as i32
-                } else {
-                    0_i32
-                }) as u16;
-                (*root).escapes =
-                    ((*root).escapes as i32 - ((*root).escapes as i32 >> 1_i32)) as u16;
-                (*root).total = ((*root).total as i32
-                    + ((*root).escapes as i32 + 256_i32 * ENET_CONTEXT_SYMBOL_MINIMUM as i32))
-                    as u16;
+        }
+    }
+}
+
+struct Encoder<'a> {
+    low: u32,
+    range: u32,
+    out: &'a mut [u8],
+    position: usize,
+}
+
+impl Encoder<'_> {
+    fn output(&mut self, value: u8) -> Option<()> {
+        *self.out.get_mut(self.position)? = value;
+        self.position += 1;
+        Some(())
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn encode(&mut self, under: u32, count: u16, total: u16) -> Option<()> {
+        self.range = self.range.wrapping_div(u32::from(total));
+        self.low = self.low.wrapping_add(under.wrapping_mul(self.range));
+        self.range = self.range.wrapping_mul(u32::from(count));
+        loop {
+            if self.low ^ self.low.wrapping_add(self.range) >= ENET_RANGE_CODER_TOP {
+                if self.range >= ENET_RANGE_CODER_BOTTOM {
+                    break;
+                }
+                self.range = self.low.wrapping_neg() & (ENET_RANGE_CODER_BOTTOM - 1);
             }
+            self.output((self.low >> 24) as u8)?;
+            self.range <<= 8;
+            self.low <<= 8;
+        }
+        Some(())
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn flush(mut self) -> Option<usize> {
+        while self.low != 0 {
+            self.output((self.low >> 24) as u8)?;
+            self.low <<= 8;
         }
-        if order >= ENET_SUBCONTEXT_ORDER as i32 as usize {
-            predicted = (*range_coder).symbols[predicted as usize].parent;
+        Some(self.position)
+    }
+}
+
+struct Decoder<'a> {
+    low: u32,
+    code: u32,
+    range: u32,
+    in_data: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn next_byte(&mut self) -> u32 {
+        if let Some((&byte, rest)) = self.in_data.split_first() {
+            self.in_data = rest;
+            u32::from(byte)
         } else {
-            order = order.wrapping_add(1);
+            0
         }
-        if next_symbol
-            >= ::core::mem::size_of::<[ENetSymbol; 4096]>()
-                .wrapping_div(::core::mem::size_of::<ENetSymbol>())
-                .wrapping_sub(ENET_SUBCONTEXT_ORDER as i32 as usize)
-        {
-            next_symbol = 0_i32 as usize;
-            let fresh11 = next_symbol;
-            next_symbol = next_symbol.wrapping_add(1);
-            root = ((*range_coder).symbols).as_mut_ptr().add(fresh11);
-            (*root).value = 0_i32 as u8;
-            (*root).count = 0_i32 as u8;
-            (*root).under = 0_i32 as u16;
-            (*root).left = 0_i32 as u16;
-            (*root).right = 0_i32 as u16;
-            (*root).symbols = 0_i32 as u16;
-            (*root).escapes = 0_i32 as u16;
-            (*root).total = 0_i32 as u16;
-            (*root).parent = 0_i32 as u16;
-            (*root).escapes = ENET_CONTEXT_ESCAPE_MINIMUM as i32 as u16;
-            (*root).total = (ENET_CONTEXT_ESCAPE_MINIMUM as i32
-                + 256_i32 * ENET_CONTEXT_SYMBOL_MINIMUM as i32) as u16;
-            (*root).symbols = 0_i32 as u16;
-            predicted = 0_i32 as u16;
-            order = 0_i32 as usize;
+    }
+
+    fn seed(&mut self) {
+        for shift in [24, 16, 8, 0] {
+            if !self.in_data.is_empty() {
+                self.code |= self.next_byte() << shift;
+            }
         }
     }
-    while encode_low != 0 {
-        if out_data >= out_end {
-            return 0_i32 as usize;
+
+    /// Read the next code, or `None` if corrupt data collapsed the range, which would crash ENet.
+    #[allow(clippy::cast_possible_truncation)]
+    fn read(&mut self, total: u16) -> Option<u16> {
+        self.range = self.range.wrapping_div(u32::from(total));
+        self.code
+            .wrapping_sub(self.low)
+            .checked_div(self.range)
+            .map(|code| code as u16)
+    }
+
+    fn decode(&mut self, under: u32, count: u16) {
+        self.low = self.low.wrapping_add(under.wrapping_mul(self.range));
+        self.range = self.range.wrapping_mul(u32::from(count));
+        loop {
+            if self.low ^ self.low.wrapping_add(self.range) >= ENET_RANGE_CODER_TOP {
+                if self.range >= ENET_RANGE_CODER_BOTTOM {
+                    break;
+                }
+                self.range = self.low.wrapping_neg() & (ENET_RANGE_CODER_BOTTOM - 1);
+            }
+            self.code <<= 8;
+            self.code |= self.next_byte();
+            self.range <<= 8;
+            self.low <<= 8;
         }
-        let fresh12 = out_data;
-        out_data = out_data.offset(1);
-        *fresh12 = (encode_low >> 24_i32) as u8;
-        encode_low <<= 8_i32;
     }
-    out_data.offset_from(out_start) as i64 as usize
 }
-pub(crate) unsafe fn enet_range_coder_decompress(
-    context: *mut u8,
-    mut in_data: *const u8,
+
+/// Compress `in_buffers` into `out`, returning the compressed length, or `0` if `out` is too
+/// small.
+pub(crate) fn enet_range_coder_compress(
+    range_coder: &mut ENetRangeCoder,
+    in_buffers: &[&[u8]],
     in_limit: usize,
-    mut out_data: *mut u8,
-    out_limit: usize,
+    out: &mut [u8],
 ) -> usize {
-    let range_coder: *mut ENetRangeCoder = context.cast();
-    let out_start: *mut u8 = out_data;
-    let out_end: *mut u8 = out_data.add(out_limit);
-    let in_end: *const u8 = in_data.add(in_limit);
-    let mut decode_low: u32 = 0_i32 as u32;
-    let mut decode_code: u32 = 0_i32 as u32;
-    let mut decode_range: u32 = !0_i32 as u32;
-    let mut root: *mut ENetSymbol;
-    let mut predicted: u16 = 0_i32 as u16;
-    let mut order: usize = 0_i32 as usize;
-    let mut next_symbol: usize = 0_i32 as usize;
-    if range_coder.is_null() || in_limit <= 0_i32 as usize {
-        return 0_i32 as usize;
-    }
-    let fresh13 = next_symbol;
-    next_symbol = next_symbol.wrapping_add(1);
-    root = ((*range_coder).symbols).as_mut_ptr().add(fresh13);
-    (*root).value = 0_i32 as u8;
-    (*root).count = 0_i32 as u8;
-    (*root).under = 0_i32 as u16;
-    (*root).left = 0_i32 as u16;
-    (*root).right = 0_i32 as u16;
-    (*root).symbols = 0_i32 as u16;
-    (*root).escapes = 0_i32 as u16;
-    (*root).total = 0_i32 as u16;
-    (*root).parent = 0_i32 as u16;
-    (*root).escapes = ENET_CONTEXT_ESCAPE_MINIMUM as i32 as u16;
-    (*root).total =
-        (ENET_CONTEXT_ESCAPE_MINIMUM as i32 + 256_i32 * ENET_CONTEXT_SYMBOL_MINIMUM as i32) as u16;
-    (*root).symbols = 0_i32 as u16;
-    if in_data < in_end {
-        let fresh14 = in_data;
-        in_data = in_data.offset(1);
-        decode_code |= ((*fresh14 as i32) << 24_i32) as u32;
+    let Some((&in_data, in_buffers)) = in_buffers.split_first() else {
+        return 0;
+    };
+    if in_limit == 0 {
+        return 0;
     }
-    if in_data < in_end {
-        let fresh15 = in_data;
-        in_data = in_data.offset(1);
-        decode_code |= ((*fresh15 as i32) << 16_i32) as u32;
-    }
-    if in_data < in_end {
-        let fresh16 = in_data;
-        in_data = in_data.offset(1);
-        decode_code |= ((*fresh16 as i32) << 8_i32) as u32;
-    }
-    if in_data < in_end {
-        let fresh17 = in_data;
-        in_data = in_data.offset(1);
-        decode_code |= *fresh17 as u32;
-    }
-    let mut current_block_297: u64;
+    let mut encoder = Encoder {
+        low: 0,
+        range: !0,
+        out,
+        position: 0,
+    };
+    range_coder_compress(range_coder, in_data, in_buffers, &mut encoder)
+        .and_then(|()| encoder.flush())
+        .unwrap_or(0)
+}
+
+fn range_coder_compress<'a>(
+    range_coder: &mut ENetRangeCoder,
+    mut in_data: &'a [u8],
+    mut in_buffers: &[&'a [u8]],
+    encoder: &mut Encoder,
+) -> Option<()> {
+    let mut contexts = Contexts::new(range_coder);
+    let mut in_position = 0;
     loop {
-        let mut subcontext: *mut ENetSymbol;
-        let mut symbol: *mut ENetSymbol;
-        let mut patch: *mut ENetSymbol;
-        let mut value: u8 = 0_i32 as u8;
-        let mut code: u16;
-        let mut under: u16;
-        let mut count: u16;
-        let mut bottom: u16 = 0;
-        let mut parent: *mut u16 = &mut predicted;
-        let mut total: u16;
-        subcontext = ((*range_coder).symbols)
-            .as_mut_ptr()
-            .offset(predicted as isize);
-        loop {
-            if subcontext == root {
-                current_block_297 = 18325745679564279244;
+        if in_position >= in_data.len() {
+            let Some((&next, rest)) = in_buffers.split_first() else {
                 break;
-            }
-            if (*subcontext).escapes as i32 > 0_i32 {
-                total = (*subcontext).total;
-                if ((*subcontext).escapes as i32) < total as i32 {
-                    decode_range = decode_range.wrapping_div(total as u32);
-                    code = decode_code
-                        .wrapping_sub(decode_low)
-                        .wrapping_div(decode_range) as u16;
-                    if (code as i32) < (*subcontext).escapes as i32 {
-                        decode_low =
-                            decode_low.wrapping_add((0_i32 as u32).wrapping_mul(decode_range));
-                        decode_range = decode_range.wrapping_mul((*subcontext).escapes as u32);
-                        loop {
-                            if decode_low ^ decode_low.wrapping_add(decode_range)
-                                >= ENET_RANGE_CODER_TOP as i32 as u32
-                            {
-                                if decode_range >= ENET_RANGE_CODER_BOTTOM as i32 as u32 {
-                                    break;
-                                }
-                                decode_range = decode_low.wrapping_neg()
-                                    & (ENET_RANGE_CODER_BOTTOM as i32 - 1_i32) as u32;
-                            }
-                            decode_code <<= 8_i32;
-                            if in_data < in_end {
-                                let fresh18 = in_data;
-                                in_data = in_data.offset(1);
-                                decode_code |= *fresh18 as u32;
-                            }
-                            decode_range <<= 8_i32;
-                            decode_low <<= 8_i32;
-                        }
-                    } else {
-                        code = (code as i32 - (*subcontext).escapes as i32) as u16;
-                        under = 0_i32 as u16;
-                        count = 0_i32 as u16;
-                        if (*subcontext).symbols == 0 {
-                            return 0_i32 as usize;
-                        } else {
-                            let mut node: *mut ENetSymbol =
-                                subcontext.offset((*subcontext).symbols as i32 as isize);
-                            loop {
-                                let after: u16 = (under as i32 + (*node).under as i32) as u16;
-                                let before: u16 = (*node).count as i32 as u16;
-                                if code as i32 >= after as i32 {
-                                    under = (under as i32 + (*node).under as i32) as u16;
-                                    if (*node).right != 0 {
-                                        node = node.offset((*node).right as i32 as isize);
-                                    } else {
-                                        return 0_i32 as usize;
-                                    }
-                                } else if (code as i32) < after as i32 - before as i32 {
-                                    (*node).under = ((*node).under as i32
-                                        + ENET_SUBCONTEXT_SYMBOL_DELTA as i32)
-                                        as u16;
-                                    if (*node).left != 0 {
-                                        node = node.offset((*node).left as i32 as isize);
-                                    } else {
-                                        return 0_i32 as usize;
-                                    }
-                                } else {
-                                    value = (*node).value;
-                                    count = (count as i32 + (*node).count as i32) as u16;
-                                    under = (after as i32 - before as i32) as u16;
-                                    (*node).under = ((*node).under as i32
-                                        + ENET_SUBCONTEXT_SYMBOL_DELTA as i32)
-                                        as u16;
-                                    (*node).count = ((*node).count as i32
-                                        + ENET_SUBCONTEXT_SYMBOL_DELTA as i32)
-                                        as u8;
-                                    symbol = node;
-                                    break;
-                                }
-                            }
-                        }
-                        bottom =
-                            symbol.offset_from(((*range_coder).symbols).as_mut_ptr()) as i64 as u16;
-                        decode_low = decode_low.wrapping_add(
-                            (((*subcontext).escapes as i32 + under as i32) as u32)
-                                .wrapping_mul(decode_range),
-                        );
-                        decode_range = decode_range.wrapping_mul(count as u32);
-                        loop {
-                            if decode_low ^ decode_low.wrapping_add(decode_range)
-                                >= ENET_RANGE_CODER_TOP as i32 as u32
-                            {
-                                if decode_range >= ENET_RANGE_CODER_BOTTOM as i32 as u32 {
-                                    break;
-                                }
-                                decode_range = decode_low.wrapping_neg()
-                                    & (ENET_RANGE_CODER_BOTTOM as i32 - 1_i32) as u32;
-                            }
-                            decode_code <<= 8_i32;
-                            if in_data < in_end {
-                                let fresh19 = in_data;
-                                in_data = in_data.offset(1);
-                                decode_code |= *fresh19 as u32;
-                            }
-                            decode_range <<= 8_i32;
-                            decode_low <<= 8_i32;
-                        }
-                        (*subcontext).total = ((*subcontext).total as i32
-                            + ENET_SUBCONTEXT_SYMBOL_DELTA as i32)
-                            as u16;
-                        if count as i32 > 0xff_i32 - 2_i32 * ENET_SUBCONTEXT_SYMBOL_DELTA as i32
-                            || (*subcontext).total as i32
-                                > ENET_RANGE_CODER_BOTTOM as i32 - 0x100_i32
-                        {
-                            (*subcontext).total = (if (*subcontext).symbols as i32 != 0 {
-                                enet_symbol_rescale(
-                                    subcontext.offset((*subcontext).symbols as i32 as isize),
-                                ) as i32
-                            } else {
-                                0_i32
-                            }) as u16;
-                            (*subcontext).escapes = ((*subcontext).escapes as i32
-                                - ((*subcontext).escapes as i32 >> 1_i32))
-                                as u16;
-                            (*subcontext).total =
-                                ((*subcontext).total as i32 + (*subcontext).escapes as i32) as u16;
-                        }
-                        current_block_297 = 16234561804784670422;
-                        break;
-                    }
-                }
-            }
-            subcontext = ((*range_coder).symbols)
-                .as_mut_ptr()
-                .offset((*subcontext).parent as isize);
+            };
+            in_data = next;
+            in_buffers = rest;
+            in_position = 0;
         }
-        if let 18325745679564279244 = current_block_297 {
-            total = (*root).total;
-            decode_range = decode_range.wrapping_div(total as u32);
-            code = decode_code
-                .wrapping_sub(decode_low)
-                .wrapping_div(decode_range) as u16;
-            if (code as i32) < (*root).escapes as i32 {
-                decode_low = decode_low.wrapping_add((0_i32 as u32).wrapping_mul(decode_range));
-                decode_range = decode_range.wrapping_mul((*root).escapes as u32);
-                loop {
-                    if decode_low ^ decode_low.wrapping_add(decode_range)
-                        >= ENET_RANGE_CODER_TOP as i32 as u32
-                    {
-                        if decode_range >= ENET_RANGE_CODER_BOTTOM as i32 as u32 {
-                            break;
-                        }
-                        decode_range = decode_low.wrapping_neg()
-                            & (ENET_RANGE_CODER_BOTTOM as i32 - 1_i32) as u32;
-                    }
-                    decode_code <<= 8_i32;
-                    if in_data < in_end {
-                        let fresh20 = in_data;
-                        in_data = in_data.offset(1);
-                        decode_code |= *fresh20 as u32;
-                    }
-                    decode_range <<= 8_i32;
-                    decode_low <<= 8_i32;
+        let value = in_data.get(in_position).copied().unwrap_or(0);
+        in_position += 1;
+        let mut parent = Parent::Predicted;
+        let mut subcontext = usize::from(contexts.predicted);
+        let mut encoded = false;
+        while subcontext != ROOT {
+            let (symbol, under, count) =
+                contexts.encode(subcontext, value, ENET_SUBCONTEXT_SYMBOL_DELTA, 0);
+            contexts.link(parent, symbol);
+            parent = Parent::Symbol(symbol);
+            let context = &mut contexts.symbols[subcontext];
+            let total = context.total;
+            if count > 0 {
+                encoder.encode(u32::from(context.escapes) + u32::from(under), count, total)?;
+            } else {
+                if context.escapes > 0 && context.escapes < total {
+                    encoder.encode(0, context.escapes, total)?;
                 }
+                context.escapes = context.escapes.wrapping_add(ENET_SUBCONTEXT_ESCAPE_DELTA);
+                context.total = context.total.wrapping_add(ENET_SUBCONTEXT_ESCAPE_DELTA);
+            }
+            context.total = context
+                .total
+                .wrapping_add(u16::from(ENET_SUBCONTEXT_SYMBOL_DELTA));
+            if count > 0xff - 2 * u16::from(ENET_SUBCONTEXT_SYMBOL_DELTA)
+                || u32::from(context.total) > ENET_RANGE_CODER_BOTTOM - 0x100
+            {
+                contexts.rescale(subcontext, 0);
+            }
+            if count > 0 {
+                encoded = true;
                 break;
-            } else {
-                code = (code as i32 - (*root).escapes as i32) as u16;
-                under = 0_i32 as u16;
-                count = ENET_CONTEXT_SYMBOL_MINIMUM as i32 as u16;
-                if (*root).symbols == 0 {
-                    value = (code as i32 / ENET_CONTEXT_SYMBOL_MINIMUM as i32) as u8;
-                    under = (code as i32 - code as i32 % ENET_CONTEXT_SYMBOL_MINIMUM as i32) as u16;
-                    let fresh21 = next_symbol;
-                    next_symbol = next_symbol.wrapping_add(1);
-                    symbol = ((*range_coder).symbols).as_mut_ptr().add(fresh21);
-                    (*symbol).value = value;
-                    (*symbol).count = ENET_CONTEXT_SYMBOL_DELTA as i32 as u8;
-                    (*symbol).under = ENET_CONTEXT_SYMBOL_DELTA as i32 as u16;
-                    (*symbol).left = 0_i32 as u16;
-                    (*symbol).right = 0_i32 as u16;
-                    (*symbol).symbols = 0_i32 as u16;
-                    (*symbol).escapes = 0_i32 as u16;
-                    (*symbol).total = 0_i32 as u16;
-                    (*symbol).parent = 0_i32 as u16;
-                    (*root).symbols = symbol.offset_from(root) as i64 as u16;
+            }
+            subcontext = usize::from(contexts.symbols[subcontext].parent);
+        }
+        if !encoded {
+            let (symbol, under, count) = contexts.encode(
+                ROOT,
+                value,
+                ENET_CONTEXT_SYMBOL_DELTA,
+                ENET_CONTEXT_SYMBOL_MINIMUM,
+            );
+            contexts.link(parent, symbol);
+            let root = &mut contexts.symbols[ROOT];
+            encoder.encode(
+                u32::from(root.escapes) + u32::from(under),
+                count,
+                root.total,
+            )?;
+            root.total = root
+                .total
+                .wrapping_add(u16::from(ENET_CONTEXT_SYMBOL_DELTA));
+            if count > 0xff - 2 * u16::from(ENET_CONTEXT_SYMBOL_DELTA) + ENET_CONTEXT_SYMBOL_MINIMUM
+                || u32::from(root.total) > ENET_RANGE_CODER_BOTTOM - 0x100
+            {
+                contexts.rescale(ROOT, ENET_CONTEXT_SYMBOL_MINIMUM);
+            }
+        }
+        contexts.advance();
+    }
+    Some(())
+}
+
+/// Decompress `in_data` into `out`, returning the decompressed length, or `0` if the data is
+/// invalid or `out` is too small.
+pub(crate) fn enet_range_coder_decompress(
+    range_coder: &mut ENetRangeCoder,
+    in_data: &[u8],
+    out: &mut [u8],
+) -> usize {
+    if in_data.is_empty() {
+        return 0;
+    }
+    let mut decoder = Decoder {
+        low: 0,
+        code: 0,
+        range: !0,
+        in_data,
+    };
+    decoder.seed();
+    range_coder_decompress(range_coder, &mut decoder, out).unwrap_or(0)
+}
+
+fn range_coder_decompress(
+    range_coder: &mut ENetRangeCoder,
+    decoder: &mut Decoder,
+    out: &mut [u8],
+) -> Option<usize> {
+    let mut contexts = Contexts::new(range_coder);
+    let mut out_position = 0;
+    loop {
+        let mut decoded = None;
+        let mut subcontext = usize::from(contexts.predicted);
+        while subcontext != ROOT {
+            let context = contexts.symbols[subcontext];
+            if context.escapes > 0 && context.escapes < context.total {
+                let code = decoder.read(context.total)?;
+                if code < context.escapes {
+                    decoder.decode(0, context.escapes);
                 } else {
-                    let mut node_0: *mut ENetSymbol = root.offset((*root).symbols as i32 as isize);
-                    loop {
-                        let after_0: u16 = (under as i32
-                            + (*node_0).under as i32
-                            + ((*node_0).value as i32 + 1_i32) * ENET_CONTEXT_SYMBOL_MINIMUM as i32)
-                            as u16;
-                        let before_0: u16 =
-                            ((*node_0).count as i32 + ENET_CONTEXT_SYMBOL_MINIMUM as i32) as u16;
-                        if code as i32 >= after_0 as i32 {
-                            under = (under as i32 + (*node_0).under as i32) as u16;
-                            if (*node_0).right != 0 {
-                                node_0 = node_0.offset((*node_0).right as i32 as isize);
-                            } else {
-                                value = ((*node_0).value as i32
-                                    + 1_i32
-                                    + (code as i32 - after_0 as i32)
-                                        / ENET_CONTEXT_SYMBOL_MINIMUM as i32)
-                                    as u8;
-                                under = (code as i32
-                                    - (code as i32 - after_0 as i32)
-                                        % ENET_CONTEXT_SYMBOL_MINIMUM as i32)
-                                    as u16;
-                                let fresh22 = next_symbol;
-                                next_symbol = next_symbol.wrapping_add(1);
-                                symbol = ((*range_coder).symbols).as_mut_ptr().add(fresh22);
-                                (*symbol).value = value;
-                                (*symbol).count = ENET_CONTEXT_SYMBOL_DELTA as i32 as u8;
-                                (*symbol).under = ENET_CONTEXT_SYMBOL_DELTA as i32 as u16;
-                                (*symbol).left = 0_i32 as u16;
-                                (*symbol).right = 0_i32 as u16;
-                                (*symbol).symbols = 0_i32 as u16;
-                                (*symbol).escapes = 0_i32 as u16;
-                                (*symbol).total = 0_i32 as u16;
-                                (*symbol).parent = 0_i32 as u16;
-                                (*node_0).right = symbol.offset_from(node_0) as i64 as u16;
-                                break;
-                            }
-                        } else if (code as i32) < after_0 as i32 - before_0 as i32 {
-                            (*node_0).under =
-                                ((*node_0).under as i32 + ENET_CONTEXT_SYMBOL_DELTA as i32) as u16;
-                            if (*node_0).left != 0 {
-                                node_0 = node_0.offset((*node_0).left as i32 as isize);
-                            } else {
-                                value = ((*node_0).value as i32
-                                    - 1_i32
-                                    - (after_0 as i32 - before_0 as i32 - code as i32 - 1_i32)
-                                        / ENET_CONTEXT_SYMBOL_MINIMUM as i32)
-                                    as u8;
-                                under = (code as i32
-                                    - (after_0 as i32 - before_0 as i32 - code as i32 - 1_i32)
-                                        % ENET_CONTEXT_SYMBOL_MINIMUM as i32)
-                                    as u16;
-                                let fresh23 = next_symbol;
-                                next_symbol = next_symbol.wrapping_add(1);
-                                symbol = ((*range_coder).symbols).as_mut_ptr().add(fresh23);
-                                (*symbol).value = value;
-                                (*symbol).count = ENET_CONTEXT_SYMBOL_DELTA as i32 as u8;
-                                (*symbol).under = ENET_CONTEXT_SYMBOL_DELTA as i32 as u16;
-                                (*symbol).left = 0_i32 as u16;
-                                (*symbol).right = 0_i32 as u16;
-                                (*symbol).symbols = 0_i32 as u16;
-                                (*symbol).escapes = 0_i32 as u16;
-                                (*symbol).total = 0_i32 as u16;
-                                (*symbol).parent = 0_i32 as u16;
-                                (*node_0).left = symbol.offset_from(node_0) as i64 as u16;
-                                break;
-                            }
-                        } else {
-                            value = (*node_0).value;
-                            count = (count as i32 + (*node_0).count as i32) as u16;
-                            under = (after_0 as i32 - before_0 as i32) as u16;
-                            (*node_0).under =
-                                ((*node_0).under as i32 + ENET_CONTEXT_SYMBOL_DELTA as i32) as u16;
-                            (*node_0).count =
-                                ((*node_0).count as i32 + ENET_CONTEXT_SYMBOL_DELTA as i32) as u8;
-                            symbol = node_0;
-                            break;
-                        }
-                    }
-                }
-                bottom = symbol.offset_from(((*range_coder).symbols).as_mut_ptr()) as i64 as u16;
-                decode_low = decode_low.wrapping_add(
-                    (((*root).escapes as i32 + under as i32) as u32).wrapping_mul(decode_range),
-                );
-                decode_range = decode_range.wrapping_mul(count as u32);
-                loop {
-                    if decode_low ^ decode_low.wrapping_add(decode_range)
-                        >= ENET_RANGE_CODER_TOP as i32 as u32
+                    let (symbol, value, under, count) =
+                        contexts.decode(subcontext, code - context.escapes)?;
+                    decoder.decode(u32::from(context.escapes) + u32::from(under), count);
+                    let context = &mut contexts.symbols[subcontext];
+                    context.total = context
+                        .total
+                        .wrapping_add(u16::from(ENET_SUBCONTEXT_SYMBOL_DELTA));
+                    if count > 0xff - 2 * u16::from(ENET_SUBCONTEXT_SYMBOL_DELTA)
+                        || u32::from(context.total) > ENET_RANGE_CODER_BOTTOM - 0x100
                     {
-                        if decode_range >= ENET_RANGE_CODER_BOTTOM as i32 as u32 {
-                            break;
-                        }
-                        decode_range = decode_low.wrapping_neg()
-                            & (ENET_RANGE_CODER_BOTTOM as i32 - 1_i32) as u32;
-                    }
-                    decode_code <<= 8_i32;
-                    if in_data < in_end {
-                        let fresh24 = in_data;
-                        in_data = in_data.offset(1);
-                        decode_code |= *fresh24 as u32;
+                        contexts.rescale(subcontext, 0);
                     }
-                    decode_range <<= 8_i32;
-                    decode_low <<= 8_i32;
-                }
-                (*root).total = ((*root).total as i32 + ENET_CONTEXT_SYMBOL_DELTA as i32) as u16;
-                if count as i32
-                    > 0xff_i32 - 2_i32 * ENET_CONTEXT_SYMBOL_DELTA as i32
-                        + ENET_CONTEXT_SYMBOL_MINIMUM as i32
-                    || (*root).total as i32 > ENET_RANGE_CODER_BOTTOM as i32 - 0x100_i32
-                {
-                    (*root).total = (if (*root).symbols as i32 != 0 {
-                        enet_symbol_rescale(root.offset((*root).symbols as i32 as isize)) as i32
-                    } else {
-                        0_i32
-                    }) as u16;
-                    (*root).escapes =
-                        ((*root).escapes as i32 - ((*root).escapes as i32 >> 1_i32)) as u16;
-                    (*root).total = ((*root).total as i32
-                        + ((*root).escapes as i32 + 256_i32 * ENET_CONTEXT_SYMBOL_MINIMUM as i32))
-                        as u16;
+                    decoded = Some((symbol, value));
+                    break;
                 }
             }
+            subcontext = usize::from(contexts.symbols[subcontext].parent);
         }
-        patch = ((*range_coder).symbols)
-            .as_mut_ptr()
-            .offset(predicted as isize);
-        while patch != subcontext {
-            under = 0_u16;
-            count = 0_i32 as u16;
-            if (*patch).symbols == 0 {
-                let fresh25 = next_symbol;
-                next_symbol = next_symbol.wrapping_add(1);
-                symbol = ((*range_coder).symbols).as_mut_ptr().add(fresh25);
-                (*symbol).value = value;
-                (*symbol).count = ENET_SUBCONTEXT_SYMBOL_DELTA as i32 as u8;
-                (*symbol).under = ENET_SUBCONTEXT_SYMBOL_DELTA as i32 as u16;
-                (*symbol).left = 0_i32 as u16;
-                (*symbol).right = 0_i32 as u16;
-                (*symbol).symbols = 0_i32 as u16;
-                (*symbol).escapes = 0_i32 as u16;
-                (*symbol).total = 0_i32 as u16;
-                (*symbol).parent = 0_i32 as u16;
-                (*patch).symbols = symbol.offset_from(patch) as i64 as u16;
-            } else {
-                let mut node_1: *mut ENetSymbol = patch.offset((*patch).symbols as i32 as isize);
-                loop {
-                    match (value as i32).cmp(&((*node_1).value as i32)) {
-                        Ordering::Less => {
-                            (*node_1).under = ((*node_1).under as i32
-                                + ENET_SUBCONTEXT_SYMBOL_DELTA as i32)
-                                as u16;
-                            if (*node_1).left != 0 {
-                                node_1 = node_1.offset((*node_1).left as i32 as isize);
-                            } else {
-                                let fresh26 = next_symbol;
-                                next_symbol = next_symbol.wrapping_add(1);
-                                symbol = ((*range_coder).symbols).as_mut_ptr().add(fresh26);
-                                (*symbol).value = value;
-                                (*symbol).count = ENET_SUBCONTEXT_SYMBOL_DELTA as i32 as u8;
-                                (*symbol).under = ENET_SUBCONTEXT_SYMBOL_DELTA as i32 as u16;
-                                (*symbol).left = 0_i32 as u16;
-                                (*symbol).right = 0_i32 as u16;
-                                (*symbol).symbols = 0_i32 as u16;
-                                (*symbol).escapes = 0_i32 as u16;
-                                (*symbol).total = 0_i32 as u16;
-                                (*symbol).parent = 0_i32 as u16;
-                                (*node_1).left = symbol.offset_from(node_1) as i64 as u16;
-                                break;
-                            }
-                        }
-                        Ordering::Greater => {
-                            under = (under as i32 + (*node_1).under as i32) as u16;
-                            if (*node_1).right != 0 {
-                                node_1 = node_1.offset((*node_1).right as i32 as isize);
-                            } else {
-                                let fresh27 = next_symbol;
-                                next_symbol = next_symbol.wrapping_add(1);
-                                symbol = ((*range_coder).symbols).as_mut_ptr().add(fresh27);
-                                (*symbol).value = value;
-                                (*symbol).count = ENET_SUBCONTEXT_SYMBOL_DELTA as i32 as u8;
-                                (*symbol).under = ENET_SUBCONTEXT_SYMBOL_DELTA as i32 as u16;
-                                (*symbol).left = 0_i32 as u16;
-                                (*symbol).right = 0_i32 as u16;
-                                (*symbol).symbols = 0_i32 as u16;
-                                (*symbol).escapes = 0_i32 as u16;
-                                (*symbol).total = 0_i32 as u16;
-                                (*symbol).parent = 0_i32 as u16;
-                                (*node_1).right = symbol.offset_from(node_1) as i64 as u16;
-                                break;
-                            }
-                        }
-                        Ordering::Equal => {
-                            count = (count as i32 + (*node_1).count as i32) as u16;
-                            (*node_1).under = ((*node_1).under as i32
-                                + ENET_SUBCONTEXT_SYMBOL_DELTA as i32)
-                                as u16;
-                            (*node_1).count = ((*node_1).count as i32
-                                + ENET_SUBCONTEXT_SYMBOL_DELTA as i32)
-                                as u8;
-                            symbol = node_1;
-                            break;
-                        }
-                    }
-                }
+        let (bottom, value) = if let Some(decoded) = decoded {
+            decoded
+        } else {
+            let root = contexts.symbols[ROOT];
+            let code = decoder.read(root.total)?;
+            if code < root.escapes {
+                decoder.decode(0, root.escapes);
+                break;
             }
-            *parent = symbol.offset_from(((*range_coder).symbols).as_mut_ptr()) as i64 as u16;
-            parent = &mut (*symbol).parent;
-            if count as i32 <= 0_i32 {
-                (*patch).escapes =
-                    ((*patch).escapes as i32 + ENET_SUBCONTEXT_ESCAPE_DELTA as i32) as u16;
-                (*patch).total =
-                    ((*patch).total as i32 + ENET_SUBCONTEXT_ESCAPE_DELTA as i32) as u16;
+            let (symbol, value, under, count) = contexts.decode_root(code - root.escapes);
+            decoder.decode(u32::from(root.escapes) + u32::from(under), count);
+            let root = &mut contexts.symbols[ROOT];
+            root.total = root
+                .total
+                .wrapping_add(u16::from(ENET_CONTEXT_SYMBOL_DELTA));
+            if count > 0xff - 2 * u16::from(ENET_CONTEXT_SYMBOL_DELTA) + ENET_CONTEXT_SYMBOL_MINIMUM
+                || u32::from(root.total) > ENET_RANGE_CODER_BOTTOM - 0x100
+            {
+                contexts.rescale(ROOT, ENET_CONTEXT_SYMBOL_MINIMUM);
             }
-            (*patch).total = ((*patch).total as i32 + ENET_SUBCONTEXT_SYMBOL_DELTA as i32) as u16;
-            if count as i32 > 0xff_i32 - 2_i32 * ENET_SUBCONTEXT_SYMBOL_DELTA as i32
-                || (*patch).total as i32 > ENET_RANGE_CODER_BOTTOM as i32 - 0x100_i32
+            (symbol, value)
+        };
+        let mut parent = Parent::Predicted;
+        let mut patch = usize::from(contexts.predicted);
+        while patch != subcontext {
+            let (symbol, _, count) = contexts.encode(patch, value, ENET_SUBCONTEXT_SYMBOL_DELTA, 0);
+            contexts.link(parent, symbol);
+            parent = Parent::Symbol(symbol);
+            let context = &mut contexts.symbols[patch];
+            if count == 0 {
+                context.escapes = context.escapes.wrapping_add(ENET_SUBCONTEXT_ESCAPE_DELTA);
+                context.total = context.total.wrapping_add(ENET_SUBCONTEXT_ESCAPE_DELTA);
+            }
+            context.total = context
+                .total
+                .wrapping_add(u16::from(ENET_SUBCONTEXT_SYMBOL_DELTA));
+            if count > 0xff - 2 * u16::from(ENET_SUBCONTEXT_SYMBOL_DELTA)
+                || u32::from(context.total) > ENET_RANGE_CODER_BOTTOM - 0x100
             {
-                (*patch).total = (if (*patch).symbols as i32 != 0 {
-                    enet_symbol_rescale(patch.offset((*patch).symbols as i32 as isize)) as i32
-                } else {
-                    0_i32
-                }) as u16;
-                (*patch).escapes =
-                    ((*patch).escapes as i32 - ((*patch).escapes as i32 >> 1_i32)) as u16;
-                (*patch).total = ((*patch).total as i32 + (*patch).escapes as i32) as u16;
+                contexts.rescale(patch, 0);
             }
-            patch = ((*range_coder).symbols)
-                .as_mut_ptr()
-                .offset((*patch).parent as isize);
-        }
-        *parent = bottom;
-        if out_data >= out_end {
-            return 0_i32 as usize;
-        }
-        let fresh28 = out_data;
-        out_data = out_data.offset(1);
-        *fresh28 = value;
-        if order >= ENET_SUBCONTEXT_ORDER as i32 as usize {
-            predicted = (*range_coder).symbols[predicted as usize].parent;
-        } else {
-            order = order.wrapping_add(1);
-        }
-        if next_symbol
-            >= ::core::mem::size_of::<[ENetSymbol; 4096]>()
-                .wrapping_div(::core::mem::size_of::<ENetSymbol>())
-                .wrapping_sub(ENET_SUBCONTEXT_ORDER as i32 as usize)
-        {
-            next_symbol = 0_i32 as usize;
-            let fresh29 = next_symbol;
-            next_symbol = next_symbol.wrapping_add(1);
-            root = ((*range_coder).symbols).as_mut_ptr().add(fresh29);
-            (*root).value = 0_i32 as u8;
-            (*root).count = 0_i32 as u8;
-            (*root).under = 0_i32 as u16;
-            (*root).left = 0_i32 as u16;
-            (*root).right = 0_i32 as u16;
-            (*root).symbols = 0_i32 as u16;
-            (*root).escapes = 0_i32 as u16;
-            (*root).total = 0_i32 as u16;
-            (*root).parent = 0_i32 as u16;
-            (*root).escapes = ENET_CONTEXT_ESCAPE_MINIMUM as i32 as u16;
-            (*root).total = (ENET_CONTEXT_ESCAPE_MINIMUM as i32
-                + 256_i32 * ENET_CONTEXT_SYMBOL_MINIMUM as i32) as u16;
-            (*root).symbols = 0_i32 as u16;
-            predicted = 0_i32 as u16;
-            order = 0_i32 as usize;
+            patch = usize::from(contexts.symbols[patch].parent);
         }
+        contexts.link(parent, bottom);
+        *out.get_mut(out_position)? = value;
+        out_position += 1;
+        contexts.advance();
     }
-    out_data.offset_from(out_start) as i64 as usize
+    Some(out_position)
 }
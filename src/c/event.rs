@@ -1,6 +1,14 @@
+use core::time::Duration;
+
 use crate::{ENetPacket, ENetPeer, Socket};
 
 pub(crate) type ENetEventType = u32;
+pub(crate) const ENET_EVENT_TYPE_CONNECT_REQUEST: ENetEventType = 9;
+pub(crate) const ENET_EVENT_TYPE_PACKET_RESENT: ENetEventType = 8;
+pub(crate) const ENET_EVENT_TYPE_PEER_IDLE: ENetEventType = 7;
+pub(crate) const ENET_EVENT_TYPE_DELIVERY_FAILED: ENetEventType = 6;
+pub(crate) const ENET_EVENT_TYPE_ACKNOWLEDGE: ENetEventType = 5;
+pub(crate) const ENET_EVENT_TYPE_PROTOCOL_VIOLATION: ENetEventType = 4;
 pub(crate) const ENET_EVENT_TYPE_RECEIVE: ENetEventType = 3;
 pub(crate) const ENET_EVENT_TYPE_DISCONNECT: ENetEventType = 2;
 pub(crate) const ENET_EVENT_TYPE_CONNECT: ENetEventType = 1;
@@ -20,4 +28,6 @@ pub(crate) struct ENetEvent<S: Socket> {
     pub(crate) channel_id: u8,
     pub(crate) data: u32,
     pub(crate) packet: *mut ENetPacket,
+    pub(crate) tag: u64,
+    pub(crate) time: Duration,
 }
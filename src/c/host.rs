@@ -1,15 +1,55 @@
-use core::{alloc::Layout, mem::MaybeUninit, ptr::write_bytes, time::Duration};
+use core::{
+    alloc::Layout,
+    mem::{take, MaybeUninit},
+    ptr::write_bytes,
+    time::Duration,
+};
 
 use crate::{
-    consts::*, enet_free, enet_list_clear, enet_malloc, enet_packet_destroy,
-    enet_peer_queue_outgoing_command, enet_peer_reset, enet_peer_send, enet_time_get, Box,
-    Compressor, ENetBuffer, ENetChannel, ENetList, ENetPacket, ENetPeer, ENetProtocol,
-    ENetProtocolCommandHeader, Socket, SocketOptions, ENET_PEER_STATE_CONNECTED,
-    ENET_PEER_STATE_CONNECTING, ENET_PEER_STATE_DISCONNECTED, ENET_PEER_STATE_DISCONNECT_LATER,
+    consts::*,
+    enet_free, enet_host_flush, enet_list_clear, enet_list_size, enet_malloc, enet_packet_destroy,
+    enet_packet_reference_count, enet_peer_queue_outgoing_command, enet_peer_reset,
+    enet_peer_reset_queues, enet_peer_send, enet_peer_send_resume_ticket, enet_time_get,
+    error::{ServiceError, ServiceStage},
+    AcknowledgementMode, Address, BTreeMap, Box, ChannelMode, Compressor, CongestionController,
+    ConnectOutcome, ENetBuffer, ENetChannel, ENetIncomingCommand, ENetOutgoingCommand, ENetPacket,
+    ENetPeer, ENetPool, ENetProtocol, ENetProtocolCommandHeader, ENetResumableSession,
+    EnetThrottle, FecState, MemoryBudgetPolicy, PacketKind, PacketReceived, PacketTransform,
+    ProtocolVersion, ProtocolViolationKind, RaceState, ReceiveMetadata, RpcState, Socket,
+    SocketOptions, UnreliableDropPolicy, Vec, VecDeque, ENET_PEER_STATE_CONNECTED,
+    ENET_PEER_STATE_CONNECTING, ENET_PEER_STATE_CONNECTION_PENDING,
+    ENET_PEER_STATE_CONNECTION_SUCCEEDED, ENET_PEER_STATE_DISCONNECTED,
+    ENET_PEER_STATE_DISCONNECTING, ENET_PEER_STATE_DISCONNECT_LATER, ENET_PEER_STATE_ZOMBIE,
     ENET_PROTOCOL_COMMAND_BANDWIDTH_LIMIT, ENET_PROTOCOL_COMMAND_CONNECT,
-    ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE,
+    ENET_PROTOCOL_COMMAND_DISCONNECT, ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE,
+    ENET_PROTOCOL_COMMAND_FLAG_UNSEQUENCED, ENET_PROTOCOL_HEADER_EXTENDED_PEER_ID,
 };
 
+/// Datagrams received with [`Socket::receive_batch_with_metadata`], processed one at a time.
+pub(crate) struct ENetReceiveBatch<A> {
+    pub(crate) buffers: Vec<[u8; PROTOCOL_MAXIMUM_MTU]>,
+    pub(crate) received: Vec<(A, PacketReceived, ReceiveMetadata<A>)>,
+    pub(crate) next: usize,
+    /// When the batch was read from the socket.
+    pub(crate) time: Duration,
+    /// Whether the batch holds datagrams between loopback peers rather than from the socket.
+    pub(crate) loopback: bool,
+}
+
+impl<A> ENetReceiveBatch<A> {
+    pub(crate) fn new(size: usize) -> Self {
+        let mut buffers = Vec::new();
+        buffers.resize(size, [0; PROTOCOL_MAXIMUM_MTU]);
+        Self {
+            buffers,
+            received: Vec::new(),
+            next: 0,
+            time: Duration::ZERO,
+            loopback: false,
+        }
+    }
+}
+
 #[allow(clippy::type_complexity)]
 pub(crate) struct ENetHost<S: Socket> {
     pub(crate) socket: MaybeUninit<S>,
@@ -23,7 +63,10 @@ pub(crate) struct ENetHost<S: Socket> {
     pub(crate) peer_count: usize,
     pub(crate) channel_limit: usize,
     pub(crate) service_time: u32,
-    pub(crate) dispatch_queue: ENetList,
+    /// Indices of the peers with events to dispatch, in order.
+    pub(crate) dispatch_queue: MaybeUninit<VecDeque<usize>>,
+    pub(crate) outgoing_command_pool: MaybeUninit<ENetPool<ENetOutgoingCommand>>,
+    pub(crate) incoming_command_pool: MaybeUninit<ENetPool<ENetIncomingCommand>>,
     pub(crate) total_queued: u32,
     pub(crate) packet_size: usize,
     pub(crate) header_flags: u16,
@@ -31,11 +74,41 @@ pub(crate) struct ENetHost<S: Socket> {
     pub(crate) command_count: usize,
     pub(crate) buffers: [ENetBuffer; BUFFER_MAXIMUM as usize],
     pub(crate) buffer_count: usize,
-    pub(crate) checksum: MaybeUninit<Option<Box<dyn Fn(&[&[u8]]) -> u32>>>,
-    pub(crate) time: MaybeUninit<Box<dyn Fn() -> Duration>>,
+    pub(crate) checksum: MaybeUninit<Option<Box<dyn Fn(&[&[u8]]) -> u32 + Send + Sync>>>,
+    pub(crate) time: MaybeUninit<Box<dyn Fn() -> Duration + Send + Sync>>,
+    pub(crate) secure_random: MaybeUninit<Box<dyn Fn(&mut [u8]) + Send + Sync>>,
     pub(crate) compressor: MaybeUninit<Option<Box<dyn Compressor>>>,
+    pub(crate) transforms: MaybeUninit<Vec<Box<dyn PacketTransform>>>,
     pub(crate) packet_data: [[u8; PROTOCOL_MAXIMUM_MTU]; 2],
     pub(crate) received_address: MaybeUninit<Option<S::Address>>,
+    pub(crate) receive_batch: MaybeUninit<ENetReceiveBatch<S::Address>>,
+    pub(crate) outgoing_datagrams: MaybeUninit<Vec<(S::Address, Vec<u8>)>>,
+    /// Datagrams which failed to send, reported by the next service or
+    /// [`Host::flush`](`crate::Host::flush`), so one failure doesn't stop the rest being sent.
+    pub(crate) send_errors: MaybeUninit<VecDeque<ServiceError<S>>>,
+    pub(crate) socket_batch_size: usize,
+    /// See [`HostSettings::raw_datagram_rate`](`crate::HostSettings::raw_datagram_rate`).
+    pub(crate) raw_datagram_rate: u32,
+    /// See [`HostSettings::default_channel`](`crate::HostSettings::default_channel`).
+    pub(crate) default_channel: u8,
+    /// See [`HostSettings::default_packet_kind`](`crate::HostSettings::default_packet_kind`).
+    pub(crate) default_packet_kind: PacketKind,
+    /// Datagrams sent between peers connected with
+    /// [`Host::connect_loopback`](`crate::Host::connect_loopback`), received without the socket.
+    pub(crate) loopback_datagrams: MaybeUninit<VecDeque<(S::Address, Vec<u8>)>>,
+    /// Whether the datagram being handled came from a loopback peer.
+    pub(crate) receiving_loopback: bool,
+    /// Datagrams to peers simulating latency with [`Peer::simulate`](`crate::Peer::simulate`),
+    /// with the host time they are due to be sent.
+    pub(crate) simulated_datagrams: MaybeUninit<Vec<(u32, S::Address, Vec<u8>)>>,
+    /// The datagrams queued with [`Host::broadcast_raw`](`crate::Host::broadcast_raw`).
+    pub(crate) raw_datagrams: MaybeUninit<VecDeque<(S::Address, Vec<u8>)>>,
+    /// How many raw datagrams can be sent before waiting, refilled at the rate limit.
+    pub(crate) raw_datagram_credit: u32,
+    pub(crate) raw_datagram_epoch: u32,
+    /// The number of received datagrams left to process before returning, see
+    /// [`Host::service_budget`](`crate::Host::service_budget`).
+    pub(crate) datagram_budget: usize,
     pub(crate) received_data: *mut u8,
     pub(crate) received_data_length: usize,
     pub(crate) total_sent_data: u32,
@@ -46,18 +119,126 @@ pub(crate) struct ENetHost<S: Socket> {
     pub(crate) bandwidth_limited_peers: usize,
     pub(crate) duplicate_peers: usize,
     pub(crate) maximum_packet_size: usize,
+    pub(crate) coalesce_delay: u32,
+    pub(crate) acknowledgement_mode: AcknowledgementMode,
+    /// The peer which queued acknowledgements while handling the current datagram, sent right away
+    /// with [`AcknowledgementMode::Immediate`].
+    pub(crate) acknowledging_peer: *mut ENetPeer<S>,
+    pub(crate) protocol_version: ProtocolVersion,
+    pub(crate) connection_migration: bool,
+    /// How long peers can go without sending data before [`ENET_EVENT_TYPE_PEER_IDLE`] is
+    /// generated, in milliseconds, see
+    /// [`HostSettings::idle_timeout`](`crate::HostSettings::idle_timeout`).
+    pub(crate) idle_timeout: Option<u32>,
+    /// How many unsequenced groups each peer's incoming unsequenced window covers, see
+    /// [`HostSettings::unsequenced_window_size`](`crate::HostSettings::unsequenced_window_size`).
+    pub(crate) unsequenced_window_size: u32,
+    /// How many unsequenced packets are remembered per peer to drop duplicates, see
+    /// [`HostSettings::unsequenced_dedup_window`](`crate::HostSettings::unsequenced_dedup_window`).
+    pub(crate) unsequenced_dedup_window: usize,
+    /// The default [`ENetPeer::missed_ping_limit`].
+    pub(crate) missed_ping_limit: Option<u32>,
+    /// How many times a reliable packet can be resent before each further resend generates
+    /// [`ENET_EVENT_TYPE_PACKET_RESENT`], see
+    /// [`HostSettings::resend_event_threshold`](`crate::HostSettings::resend_event_threshold`).
+    pub(crate) resend_event_threshold: Option<u32>,
+    /// How many acknowledgements for later reliable commands make a reliable command be resent
+    /// early, see [`HostSettings::fast_retransmit`](`crate::HostSettings::fast_retransmit`).
+    pub(crate) fast_retransmit: Option<u32>,
+    /// The data to disconnect peers with when the host is dropped, see
+    /// [`HostSettings::disconnect_on_drop`](`crate::HostSettings::disconnect_on_drop`).
+    pub(crate) disconnect_on_drop: Option<u32>,
+    /// How long sessions can be resumed after a graceful disconnect, in milliseconds, see
+    /// [`HostSettings::session_resumption`](`crate::HostSettings::session_resumption`).
+    pub(crate) session_resumption: Option<u32>,
+    /// Whether connection requests wait for [`Peer::accept`](`crate::Peer::accept`), see
+    /// [`HostSettings::connect_approval`](`crate::HostSettings::connect_approval`).
+    pub(crate) connect_approval: bool,
+    /// Whether peers redirected by the host connected to are connected to the new address, see
+    /// [`HostSettings::follow_redirects`](`crate::HostSettings::follow_redirects`).
+    pub(crate) follow_redirects: bool,
+    /// The addresses, channel counts and data of redirects to follow during the next service.
+    pub(crate) redirects: MaybeUninit<Vec<(S::Address, usize, u32)>>,
+    /// The host time bans expire at, by the [`Address::host_key`] of the banned address, see
+    /// [`Host::ban`](`crate::Host::ban`).
+    pub(crate) bans: MaybeUninit<BTreeMap<u128, Duration>>,
+    /// Banned addresses without a [`Address::host_key`], and the host time their bans expire at.
+    pub(crate) unkeyed_bans: MaybeUninit<Vec<(S::Address, Duration)>>,
+    /// See [`Host::set_connect_audit`](`crate::Host::set_connect_audit`).
+    #[allow(clippy::type_complexity)]
+    pub(crate) connect_audit:
+        MaybeUninit<Option<Box<dyn FnMut(&S::Address, ConnectOutcome) + Send + Sync>>>,
+    pub(crate) next_session_id: u64,
+    pub(crate) resumable_sessions: MaybeUninit<Vec<ENetResumableSession>>,
+    /// Tickets received from the hosts connected to, presented when connecting to them again.
+    pub(crate) session_tickets: MaybeUninit<Vec<(S::Address, [u8; 16])>>,
+    pub(crate) ping_payloads: bool,
+    pub(crate) clock_sync: bool,
+    pub(crate) ecn: bool,
+    /// Whether the datagram being processed was marked Congestion Experienced.
+    pub(crate) received_congestion_experienced: bool,
+    /// The TTL and local address supplied with the datagram being handled, see
+    /// [`ReceiveMetadata`].
+    pub(crate) received_hop_limit: Option<u8>,
+    pub(crate) received_destination: MaybeUninit<Option<S::Address>>,
+    pub(crate) receive_time: Duration,
     pub(crate) maximum_waiting_data: usize,
+    pub(crate) maximum_incomplete_fragments: usize,
+    pub(crate) maximum_fragment_data: usize,
+    pub(crate) incomplete_fragment_data: usize,
+    pub(crate) maximum_peer_fragment_data: usize,
+    /// Every peer's incomplete unreliable fragmented packets by `fragment_order`, oldest first,
+    /// so the oldest can be dropped without scanning every peer's channels.
+    pub(crate) incomplete_unreliable_fragments:
+        MaybeUninit<BTreeMap<u64, (*mut ENetPeer<S>, *mut ENetIncomingCommand)>>,
+    pub(crate) next_fragment_order: u64,
+    pub(crate) peer_memory_budget: usize,
+    pub(crate) memory_budget_policy: MemoryBudgetPolicy,
     pub(crate) using_new_packet: bool,
+    pub(crate) strict_validation: bool,
+    pub(crate) report_malformed: bool,
+    pub(crate) protocol_violation: MaybeUninit<Option<(S::Address, ProtocolViolationKind)>>,
+    pub(crate) packet_reports: MaybeUninit<VecDeque<ENetPacketReport<S>>>,
+    pub(crate) resend_reports: MaybeUninit<VecDeque<ENetResendReport<S>>>,
+    pub(crate) rpc: MaybeUninit<RpcState>,
+    pub(crate) fec: MaybeUninit<FecState>,
+    pub(crate) races: MaybeUninit<RaceState<S::Address>>,
+    pub(crate) channel_modes: MaybeUninit<Vec<ChannelMode>>,
+    pub(crate) channel_priorities: MaybeUninit<Vec<u8>>,
+    pub(crate) channel_maximum_packet_sizes: MaybeUninit<Vec<usize>>,
+    pub(crate) channel_drop_policies: MaybeUninit<Vec<UnreliableDropPolicy>>,
+    pub(crate) congestion_controller: MaybeUninit<Box<dyn CongestionController>>,
+    #[cfg(feature = "crypto")]
+    pub(crate) connect_authentication: Option<crate::ConnectAuthentication>,
+    #[cfg(feature = "crypto")]
+    pub(crate) connect_replays: MaybeUninit<crate::crypto::ConnectReplays>,
+}
+pub(crate) struct ENetPacketReport<S: Socket> {
+    pub(crate) peer: *mut ENetPeer<S>,
+    pub(crate) channel_id: u8,
+    pub(crate) tag: u64,
+    pub(crate) delivered: bool,
+    pub(crate) queued_time: Duration,
 }
+/// A reliable packet resent more than [`ENetHost::resend_event_threshold`] times, waiting to be
+/// returned as [`ENET_EVENT_TYPE_PACKET_RESENT`].
+pub(crate) struct ENetResendReport<S: Socket> {
+    pub(crate) peer: *mut ENetPeer<S>,
+    pub(crate) channel_id: u8,
+    pub(crate) attempts: u32,
+}
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub(crate) unsafe fn enet_host_create<S: Socket>(
     mut socket: S,
     peer_count: usize,
     mut channel_limit: usize,
     incoming_bandwidth: u32,
     outgoing_bandwidth: u32,
-    time: Box<dyn Fn() -> Duration>,
+    time: Box<dyn Fn() -> Duration + Send + Sync>,
     seed: Option<u32>,
+    secure_random: Box<dyn Fn(&mut [u8]) + Send + Sync>,
     using_new_packet: bool,
+    ecn: bool,
 ) -> Result<*mut ENetHost<S>, S::Error> {
     let mut current_peer: *mut ENetPeer<S>;
     let host: *mut ENetHost<S> = enet_malloc(Layout::new::<ENetHost<S>>()).cast();
@@ -67,6 +248,7 @@ pub(crate) unsafe fn enet_host_create<S: Socket>(
     socket.init(SocketOptions {
         receive_buffer: HOST_RECEIVE_BUFFER_SIZE as usize,
         send_buffer: HOST_SEND_BUFFER_SIZE as usize,
+        ecn,
     })?;
     (*host).socket.write(socket);
     if channel_limit == 0 || channel_limit > PROTOCOL_MAXIMUM_CHANNEL_COUNT as i32 as usize {
@@ -75,13 +257,13 @@ pub(crate) unsafe fn enet_host_create<S: Socket>(
         channel_limit = PROTOCOL_MINIMUM_CHANNEL_COUNT as i32 as usize;
     }
     (*host).time.write(time);
+    (*host).secure_random.write(secure_random);
     if let Some(seed) = seed {
         (*host).random_seed = seed;
     } else {
         (*host).random_seed = host as usize as u32;
-        (*host).random_seed =
-            ((*host).random_seed as u32).wrapping_add(enet_time_get(host)) as u32 as u32;
-        (*host).random_seed = (*host).random_seed << 16_i32 | (*host).random_seed >> 16_i32;
+        (*host).random_seed = (*host).random_seed.wrapping_add(enet_time_get(host));
+        (*host).random_seed = (*host).random_seed.rotate_right(16);
     }
     (*host).channel_limit = channel_limit;
     (*host).incoming_bandwidth = incoming_bandwidth;
@@ -94,6 +276,20 @@ pub(crate) unsafe fn enet_host_create<S: Socket>(
     (*host).buffer_count = 0_i32 as usize;
     (*host).checksum.write(None);
     (*host).received_address.write(None);
+    (*host).receive_batch.write(ENetReceiveBatch::new(1));
+    (*host).outgoing_datagrams.write(Vec::new());
+    (*host).simulated_datagrams.write(Vec::new());
+    (*host).loopback_datagrams.write(VecDeque::new());
+    (*host).receiving_loopback = false;
+    (*host).send_errors.write(VecDeque::new());
+    (*host).socket_batch_size = 1;
+    (*host).raw_datagram_rate = 100;
+    (*host).default_channel = 0;
+    (*host).default_packet_kind = PacketKind::Reliable;
+    (*host).raw_datagrams.write(VecDeque::new());
+    (*host).raw_datagram_credit = 100;
+    (*host).raw_datagram_epoch = enet_time_get(host);
+    (*host).datagram_budget = usize::MAX;
     (*host).received_data = core::ptr::null_mut();
     (*host).received_data_length = 0_i32 as usize;
     (*host).total_sent_data = 0_i32 as u32;
@@ -106,9 +302,75 @@ pub(crate) unsafe fn enet_host_create<S: Socket>(
     (*host).duplicate_peers = PROTOCOL_MAXIMUM_PEER_ID as i32 as usize;
     (*host).maximum_packet_size = HOST_DEFAULT_MAXIMUM_PACKET_SIZE as i32 as usize;
     (*host).maximum_waiting_data = HOST_DEFAULT_MAXIMUM_WAITING_DATA as i32 as usize;
+    (*host).maximum_incomplete_fragments = usize::MAX;
+    (*host).maximum_fragment_data = usize::MAX;
+    (*host).incomplete_fragment_data = 0_i32 as usize;
+    (*host).maximum_peer_fragment_data = usize::MAX;
+    (*host)
+        .incomplete_unreliable_fragments
+        .write(BTreeMap::new());
+    (*host).next_fragment_order = 0;
+    (*host).peer_memory_budget = usize::MAX;
+    (*host).coalesce_delay = 0;
+    (*host).acknowledgement_mode = AcknowledgementMode::PerService;
+    (*host).acknowledging_peer = core::ptr::null_mut();
+    (*host).protocol_version = ProtocolVersion::default();
+    (*host).connection_migration = false;
+    (*host).idle_timeout = None;
+    (*host).unsequenced_window_size = PEER_UNSEQUENCED_WINDOW_SIZE;
+    (*host).unsequenced_dedup_window = 0;
+    (*host).missed_ping_limit = None;
+    (*host).resend_event_threshold = None;
+    (*host).fast_retransmit = None;
+    (*host).disconnect_on_drop = None;
+    (*host).session_resumption = None;
+    (*host).connect_approval = false;
+    (*host).follow_redirects = false;
+    (*host).redirects.write(Vec::new());
+    (*host).bans.write(BTreeMap::new());
+    (*host).unkeyed_bans.write(Vec::new());
+    (*host).connect_audit.write(None);
+    (*host).next_session_id = 0;
+    (*host).resumable_sessions.write(Vec::new());
+    (*host).session_tickets.write(Vec::new());
+    (*host).ping_payloads = false;
+    (*host).clock_sync = false;
+    (*host).ecn = ecn;
+    (*host).received_congestion_experienced = false;
+    (*host).received_hop_limit = None;
+    (*host).received_destination.write(None);
+    (*host).receive_time = Duration::ZERO;
+    (*host).memory_budget_policy = MemoryBudgetPolicy::Disconnect;
     (*host).compressor.write(None);
+    (*host).transforms.write(Vec::new());
     (*host).using_new_packet = using_new_packet;
-    enet_list_clear(&mut (*host).dispatch_queue);
+    (*host).strict_validation = false;
+    (*host).report_malformed = false;
+    (*host).protocol_violation.write(None);
+    (*host).packet_reports.write(VecDeque::new());
+    (*host).resend_reports.write(VecDeque::new());
+    (*host).rpc.write(RpcState::new());
+    (*host).fec.write(FecState::new());
+    (*host).races.write(RaceState::new());
+    (*host).channel_modes.write(Vec::new());
+    (*host).channel_priorities.write(Vec::new());
+    (*host).channel_maximum_packet_sizes.write(Vec::new());
+    (*host).channel_drop_policies.write(Vec::new());
+    (*host).congestion_controller.write(Box::new(EnetThrottle));
+    #[cfg(feature = "crypto")]
+    {
+        (*host).connect_authentication = None;
+        (*host)
+            .connect_replays
+            .write(crate::crypto::ConnectReplays::default());
+    }
+    (*host).dispatch_queue.write(VecDeque::new());
+    (*host)
+        .outgoing_command_pool
+        .write(ENetPool::new(HOST_DEFAULT_COMMAND_POOL_LIMIT));
+    (*host)
+        .incoming_command_pool
+        .write(ENetPool::new(HOST_DEFAULT_COMMAND_POOL_LIMIT));
     current_peer = (*host).peers;
     while current_peer < ((*host).peers).add((*host).peer_count) {
         (*current_peer).host = host;
@@ -116,12 +378,34 @@ pub(crate) unsafe fn enet_host_create<S: Socket>(
         (*current_peer).incoming_session_id = 0xff_i32 as u8;
         (*current_peer).outgoing_session_id = (*current_peer).incoming_session_id;
         (*current_peer).address.write(None);
+        (*current_peer).generation = 0;
+        (*current_peer).ping_payload.write(Vec::new());
+        (*current_peer).remote_ping_payload.write(None);
+        (*current_peer).connect_payload.write(Vec::new());
+        (*current_peer).migration_challenge.write(None);
+        (*current_peer).paths.write(Vec::new());
         (*current_peer).data = core::ptr::null_mut();
-        enet_list_clear(&mut (*current_peer).acknowledgements);
-        enet_list_clear(&mut (*current_peer).sent_reliable_commands);
-        enet_list_clear(&mut (*current_peer).outgoing_commands);
-        enet_list_clear(&mut (*current_peer).outgoing_send_reliable_commands);
-        enet_list_clear(&mut (*current_peer).dispatched_commands);
+        (*current_peer).acknowledgements.write(VecDeque::new());
+        (*current_peer).unsequenced_window.write(Vec::new());
+        (*current_peer)
+            .incomplete_unreliable_fragments
+            .write(BTreeMap::new());
+        (*current_peer)
+            .reported_packet_commands
+            .write(BTreeMap::new());
+        (*current_peer).unsequenced_hashes.write(VecDeque::new());
+        (*current_peer).redirect.write(Vec::new());
+        (*current_peer).pending_redirect.write(None);
+        (*current_peer).redirected_to.write(None);
+        (*current_peer).local_address.write(None);
+        (*current_peer)
+            .sent_reliable_commands
+            .write(VecDeque::new());
+        (*current_peer).outgoing_commands.write(VecDeque::new());
+        (*current_peer)
+            .outgoing_send_reliable_commands
+            .write(VecDeque::new());
+        enet_list_clear(&raw mut (*current_peer).dispatched_commands);
         enet_peer_reset(current_peer);
         current_peer = current_peer.offset(1);
     }
@@ -137,12 +421,65 @@ pub(crate) unsafe fn enet_host_destroy<S: Socket>(host: *mut ENetHost<S>) {
     while current_peer < ((*host).peers).add((*host).peer_count) {
         enet_peer_reset(current_peer);
         (*current_peer).address.assume_init_drop();
+        (*current_peer).ping_payload.assume_init_drop();
+        (*current_peer).remote_ping_payload.assume_init_drop();
+        (*current_peer).connect_payload.assume_init_drop();
+        (*current_peer).migration_challenge.assume_init_drop();
+        (*current_peer).paths.assume_init_drop();
+        (*current_peer).acknowledgements.assume_init_drop();
+        (*current_peer).sent_reliable_commands.assume_init_drop();
+        (*current_peer).outgoing_commands.assume_init_drop();
+        (*current_peer)
+            .outgoing_send_reliable_commands
+            .assume_init_drop();
+        (*current_peer).unsequenced_window.assume_init_drop();
+        (*current_peer)
+            .incomplete_unreliable_fragments
+            .assume_init_drop();
+        (*current_peer).reported_packet_commands.assume_init_drop();
+        (*current_peer).unsequenced_hashes.assume_init_drop();
+        (*current_peer).redirect.assume_init_drop();
+        (*current_peer).pending_redirect.assume_init_drop();
+        (*current_peer).redirected_to.assume_init_drop();
+        (*current_peer).local_address.assume_init_drop();
         current_peer = current_peer.offset(1);
     }
+    (*host).dispatch_queue.assume_init_drop();
+    (*host).outgoing_command_pool.assume_init_drop();
+    (*host).incoming_command_pool.assume_init_drop();
+    (*host).incomplete_unreliable_fragments.assume_init_drop();
     (*host).checksum.assume_init_drop();
     (*host).time.assume_init_drop();
+    (*host).secure_random.assume_init_drop();
     (*host).compressor.assume_init_drop();
+    (*host).transforms.assume_init_drop();
     (*host).received_address.assume_init_drop();
+    (*host).receive_batch.assume_init_drop();
+    (*host).received_destination.assume_init_drop();
+    (*host).outgoing_datagrams.assume_init_drop();
+    (*host).simulated_datagrams.assume_init_drop();
+    (*host).loopback_datagrams.assume_init_drop();
+    (*host).send_errors.assume_init_drop();
+    (*host).raw_datagrams.assume_init_drop();
+    (*host).protocol_violation.assume_init_drop();
+    (*host).packet_reports.assume_init_drop();
+    (*host).resend_reports.assume_init_drop();
+    (*host).rpc.assume_init_drop();
+    (*host).fec.assume_init_drop();
+    (*host).races.assume_init_drop();
+    (*host).channel_modes.assume_init_drop();
+    (*host).channel_priorities.assume_init_drop();
+    (*host).channel_maximum_packet_sizes.assume_init_drop();
+    (*host).channel_drop_policies.assume_init_drop();
+    (*host).resumable_sessions.assume_init_drop();
+    (*host).session_tickets.assume_init_drop();
+    (*host).redirects.assume_init_drop();
+    (*host).bans.assume_init_drop();
+    (*host).unkeyed_bans.assume_init_drop();
+    (*host).connect_audit.assume_init_drop();
+    (*host).congestion_controller.assume_init_drop();
+    #[cfg(feature = "crypto")]
+    (*host).connect_replays.assume_init_drop();
     enet_free(
         (*host).peers.cast(),
         Layout::array::<ENetPeer<S>>((*host).peer_count).unwrap(),
@@ -156,6 +493,11 @@ pub(crate) unsafe fn enet_host_random<S: Socket>(host: *mut ENetHost<S>) -> u32
     n ^= n.wrapping_add((n ^ n >> 7_i32).wrapping_mul(n | 61_u32));
     n ^ n >> 14_i32
 }
+/// Fill `bytes` with the host's [`HostSettings::secure_random`](`crate::HostSettings::secure_random`),
+/// for values other hosts must not be able to guess, unlike those from [`enet_host_random`].
+pub(crate) unsafe fn enet_host_secure_random<S: Socket>(host: *mut ENetHost<S>, bytes: &mut [u8]) {
+    (*host).secure_random.assume_init_ref()(bytes);
+}
 pub(crate) unsafe fn enet_host_connect<S: Socket>(
     host: *mut ENetHost<S>,
     address: S::Address,
@@ -178,7 +520,9 @@ pub(crate) unsafe fn enet_host_connect<S: Socket>(
     }
     current_peer = (*host).peers;
     while current_peer < ((*host).peers).add((*host).peer_count) {
-        if (*current_peer).state == ENET_PEER_STATE_DISCONNECTED as i32 as u32 {
+        if (*current_peer).state == ENET_PEER_STATE_DISCONNECTED as i32 as u32
+            && enet_host_peer_slot_allowed(host, current_peer, ProtocolVersion::Enet)
+        {
             break;
         }
         current_peer = current_peer.offset(1);
@@ -190,8 +534,10 @@ pub(crate) unsafe fn enet_host_connect<S: Socket>(
         enet_malloc(Layout::array::<ENetChannel>(channel_count).unwrap()).cast();
     (*current_peer).channel_count = channel_count;
     (*current_peer).state = ENET_PEER_STATE_CONNECTING;
+    (*current_peer).generation = (*current_peer).generation.wrapping_add(1);
     *(*current_peer).address.assume_init_mut() = Some(address);
     (*current_peer).connect_id = enet_host_random(host);
+    (*current_peer).outgoing = true;
     (*current_peer).mtu = (*host).mtu;
     if (*host).outgoing_bandwidth == 0_i32 as u32 {
         (*current_peer).window_size = PROTOCOL_MAXIMUM_WINDOW_SIZE as i32 as u32;
@@ -211,10 +557,13 @@ pub(crate) unsafe fn enet_host_connect<S: Socket>(
         (*channel).outgoing_unreliable_sequence_number = 0_i32 as u16;
         (*channel).incoming_reliable_sequence_number = 0_i32 as u16;
         (*channel).incoming_unreliable_sequence_number = 0_i32 as u16;
-        enet_list_clear(&mut (*channel).incoming_reliable_commands);
-        enet_list_clear(&mut (*channel).incoming_unreliable_commands);
+        enet_list_clear(&raw mut (*channel).incoming_reliable_commands);
+        enet_list_clear(&raw mut (*channel).incoming_unreliable_commands);
         (*channel).used_reliable_windows = 0_i32 as u16;
         write_bytes(((*channel).reliable_windows).as_mut_ptr(), 0, 16);
+        (*channel).packets_sent = 0;
+        (*channel).packets_received = 0;
+        (*channel).reliable_resends = 0;
         channel = channel.offset(1);
     }
     command.header.command = (ENET_PROTOCOL_COMMAND_CONNECT as i32
@@ -223,6 +572,11 @@ pub(crate) unsafe fn enet_host_connect<S: Socket>(
     command.connect.outgoing_peer_id = (*current_peer).incoming_peer_id.to_be();
     command.connect.incoming_session_id = (*current_peer).incoming_session_id;
     command.connect.outgoing_session_id = (*current_peer).outgoing_session_id;
+    if (*host).protocol_version > ProtocolVersion::Enet {
+        command.connect.outgoing_session_id = (*host)
+            .protocol_version
+            .mark_session_id(command.connect.outgoing_session_id);
+    }
     command.connect.mtu = (*current_peer).mtu.to_be();
     command.connect.window_size = (*current_peer).window_size.to_be();
     command.connect.channel_count = (channel_count as u32).to_be();
@@ -235,15 +589,59 @@ pub(crate) unsafe fn enet_host_connect<S: Socket>(
         (*current_peer).packet_throttle_deceleration.to_be();
     command.connect.connect_id = (*current_peer).connect_id;
     command.connect.data = data.to_be();
+    (*current_peer).connect_data = data;
     enet_peer_queue_outgoing_command(
         current_peer,
-        &command,
+        &raw const command,
         core::ptr::null_mut(),
         0_i32 as u32,
         0_i32 as u16,
     );
+    if (*host).protocol_version > ProtocolVersion::Enet {
+        let address = (*current_peer).address.assume_init_ref().as_ref().unwrap();
+        let tickets = (*host).session_tickets.assume_init_mut();
+        if let Some(index) = tickets
+            .iter()
+            .position(|(ticket_address, _)| ticket_address.same(address))
+        {
+            let (_, ticket) = tickets.remove(index);
+            (*current_peer).resume_ticket = Some(ticket);
+            enet_peer_send_resume_ticket(current_peer);
+        }
+    }
     current_peer
 }
+/// Disconnect every peer immediately, like [`enet_peer_disconnect_now`], sending all of their
+/// disconnect commands with a single flush.
+pub(crate) unsafe fn enet_host_disconnect_now<S: Socket>(host: *mut ENetHost<S>, data: u32) {
+    let mut command: ENetProtocol = ENetProtocol {
+        header: ENetProtocolCommandHeader {
+            command: (ENET_PROTOCOL_COMMAND_DISCONNECT as i32
+                | ENET_PROTOCOL_COMMAND_FLAG_UNSEQUENCED as i32) as u8,
+            channel_id: 0xff,
+            reliable_sequence_number: 0,
+        },
+    };
+    command.disconnect.data = data.to_be();
+    let peers = (*host).peers;
+    for index in 0..(*host).peer_count {
+        let peer = peers.add(index);
+        if (*peer).state != ENET_PEER_STATE_DISCONNECTED
+            && (*peer).state != ENET_PEER_STATE_ZOMBIE
+            && (*peer).state != ENET_PEER_STATE_DISCONNECTING
+        {
+            enet_peer_reset_queues(peer);
+            enet_peer_queue_outgoing_command(peer, &raw const command, core::ptr::null_mut(), 0, 0);
+        }
+    }
+    enet_host_flush(host);
+    for index in 0..(*host).peer_count {
+        let peer = peers.add(index);
+        if (*peer).state != ENET_PEER_STATE_DISCONNECTED {
+            enet_peer_reset(peer);
+        }
+    }
+}
 pub(crate) unsafe fn enet_host_broadcast<S: Socket>(
     host: *mut ENetHost<S>,
     channel_id: u8,
@@ -258,7 +656,7 @@ pub(crate) unsafe fn enet_host_broadcast<S: Socket>(
         }
         current_peer = current_peer.offset(1);
     }
-    if (*packet).reference_count == 0_i32 as usize {
+    if enet_packet_reference_count(packet) == 0 {
         enet_packet_destroy(packet);
     }
 }
@@ -442,7 +840,7 @@ pub(crate) unsafe fn enet_host_bandwidth_throttle<S: Socket>(host: *mut ENetHost
                 }
                 enet_peer_queue_outgoing_command(
                     peer,
-                    &command,
+                    &raw const command,
                     core::ptr::null_mut(),
                     0_i32 as u32,
                     0_i32 as u16,
@@ -452,3 +850,223 @@ pub(crate) unsafe fn enet_host_bandwidth_throttle<S: Socket>(host: *mut ENetHost
         }
     }
 }
+/// Count the events waiting to be returned by [`enet_host_service`](`crate::enet_host_service`)
+/// without receiving anything, including packets which may turn out to be superseded.
+pub(crate) unsafe fn enet_host_pending_events<S: Socket>(host: *mut ENetHost<S>) -> usize {
+    let mut count = (*host).packet_reports.assume_init_ref().len()
+        + (*host).resend_reports.assume_init_ref().len();
+    for &peer_index in (*host).dispatch_queue.assume_init_ref() {
+        let peer = (*host).peers.add(peer_index);
+        match (*peer).state {
+            ENET_PEER_STATE_CONNECTION_PENDING
+            | ENET_PEER_STATE_CONNECTION_SUCCEEDED
+            | ENET_PEER_STATE_ZOMBIE => count += 1,
+            ENET_PEER_STATE_CONNECTED => {
+                count += enet_list_size(&raw mut (*peer).dispatched_commands);
+            }
+            _ => {}
+        }
+    }
+    count
+}
+pub(crate) unsafe fn enet_host_channel_mode<S: Socket>(
+    host: *mut ENetHost<S>,
+    channel_id: u8,
+) -> ChannelMode {
+    (*host)
+        .channel_modes
+        .assume_init_ref()
+        .get(channel_id as usize)
+        .copied()
+        .unwrap_or_default()
+}
+pub(crate) unsafe fn enet_host_channel_maximum_packet_size<S: Socket>(
+    host: *mut ENetHost<S>,
+    channel_id: u8,
+) -> usize {
+    // protocol messages are only bounded by the host and peer limits
+    if channel_id == 0xff {
+        return usize::MAX;
+    }
+    (*host)
+        .channel_maximum_packet_sizes
+        .assume_init_ref()
+        .get(channel_id as usize)
+        .copied()
+        .unwrap_or(usize::MAX)
+}
+pub(crate) unsafe fn enet_host_channel_priority<S: Socket>(
+    host: *mut ENetHost<S>,
+    channel_id: u8,
+) -> u8 {
+    // protocol commands, like pings and disconnects, are never delayed by packets
+    if channel_id == 0xff {
+        return u8::MAX;
+    }
+    (*host)
+        .channel_priorities
+        .assume_init_ref()
+        .get(channel_id as usize)
+        .copied()
+        .unwrap_or(0)
+}
+pub(crate) unsafe fn enet_host_unreliable_drop_policy<S: Socket>(
+    host: *mut ENetHost<S>,
+    channel_id: u8,
+) -> UnreliableDropPolicy {
+    (*host)
+        .channel_drop_policies
+        .assume_init_ref()
+        .get(channel_id as usize)
+        .copied()
+        .unwrap_or_default()
+}
+/// Whether a free peer slot can be given to a connection speaking `version`. Hosts with more
+/// peers than [`PROTOCOL_MAXIMUM_PEER_ID`] never use the slots the datagram header reserves, and
+/// only give the slots past them to peers speaking [`ProtocolVersion::ExtendedPeerIds`], as
+/// other hosts can't address them.
+pub(crate) unsafe fn enet_host_peer_slot_allowed<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+    version: ProtocolVersion,
+) -> bool {
+    let index = (*peer).incoming_peer_id as u32;
+    (*host).peer_count <= PROTOCOL_MAXIMUM_PEER_ID as usize
+        || index < ENET_PROTOCOL_HEADER_EXTENDED_PEER_ID as u32
+        || index > PROTOCOL_MAXIMUM_PEER_ID && version >= ProtocolVersion::ExtendedPeerIds
+}
+/// Forget the sessions which can no longer be resumed.
+pub(crate) unsafe fn enet_host_expire_sessions<S: Socket>(host: *mut ENetHost<S>) {
+    let lifetime = (*host).session_resumption.unwrap_or(0);
+    let service_time = (*host).service_time;
+    (*host)
+        .resumable_sessions
+        .assume_init_mut()
+        .retain(|resumable| service_time.wrapping_sub(resumable.end_time) < lifetime);
+}
+/// Connect to the addresses peers were redirected to, once their disconnect events have been
+/// dispatched.
+pub(crate) unsafe fn enet_host_follow_redirects<S: Socket>(host: *mut ENetHost<S>) {
+    for (address, channel_count, data) in take((*host).redirects.assume_init_mut()) {
+        enet_host_connect(host, address, channel_count, data);
+    }
+}
+/// Drop every datagram from the host at `address` until `duration` has passed, replacing any
+/// existing ban.
+pub(crate) unsafe fn enet_host_ban<S: Socket>(
+    host: *mut ENetHost<S>,
+    address: S::Address,
+    duration: Duration,
+) {
+    let expires = (*host).time.assume_init_ref()().saturating_add(duration);
+    if let Some(key) = address.host_key() {
+        (*host).bans.assume_init_mut().insert(key, expires);
+    } else {
+        enet_host_unban(host, &address);
+        (*host)
+            .unkeyed_bans
+            .assume_init_mut()
+            .push((address, expires));
+    }
+}
+pub(crate) unsafe fn enet_host_unban<S: Socket>(
+    host: *mut ENetHost<S>,
+    address: &S::Address,
+) -> bool {
+    if let Some(key) = address.host_key() {
+        return (*host).bans.assume_init_mut().remove(&key).is_some();
+    }
+    let bans = (*host).unkeyed_bans.assume_init_mut();
+    let count = bans.len();
+    bans.retain(|(banned, _)| !banned.same_host(address));
+    bans.len() != count
+}
+/// The host time the ban on `address` expires at, if it's banned, including expired bans which
+/// haven't been forgotten yet.
+pub(crate) unsafe fn enet_host_ban_expiry<S: Socket>(
+    host: *mut ENetHost<S>,
+    address: &S::Address,
+) -> Option<Duration> {
+    if let Some(key) = address.host_key() {
+        return (*host).bans.assume_init_ref().get(&key).copied();
+    }
+    (*host)
+        .unkeyed_bans
+        .assume_init_ref()
+        .iter()
+        .find(|(banned, _)| banned.same_host(address))
+        .map(|(_, expires)| *expires)
+}
+pub(crate) unsafe fn enet_host_is_banned<S: Socket>(
+    host: *mut ENetHost<S>,
+    address: &S::Address,
+) -> bool {
+    let now = (*host).time.assume_init_ref()();
+    enet_host_ban_expiry(host, address).is_some_and(|expires| expires > now)
+}
+/// Send the datagrams queued with [`Host::broadcast_raw`](`crate::Host::broadcast_raw`), as far
+/// as the rate limit allows at the host time `now`.
+pub(crate) unsafe fn enet_host_send_raw_datagrams<S: Socket>(host: *mut ENetHost<S>, now: u32) {
+    if (*host).raw_datagrams.assume_init_ref().is_empty() {
+        return;
+    }
+    let rate = (*host).raw_datagram_rate;
+    let elapsed = now.wrapping_sub((*host).raw_datagram_epoch);
+    let refill = u64::from(elapsed) * u64::from(rate) / 1000;
+    if refill > 0 {
+        (*host).raw_datagram_credit = u64::from((*host).raw_datagram_credit)
+            .saturating_add(refill)
+            .min(u64::from(rate)) as u32;
+        (*host).raw_datagram_epoch = now;
+    }
+    while (*host).raw_datagram_credit > 0 {
+        let Some((address, data)) = (*host).raw_datagrams.assume_init_mut().pop_front() else {
+            break;
+        };
+        (*host).raw_datagram_credit -= 1;
+        if let Err(error) = (*host)
+            .socket
+            .assume_init_mut()
+            .send(address.clone(), &data)
+        {
+            (*host)
+                .send_errors
+                .assume_init_mut()
+                .push_back(ServiceError {
+                    stage: ServiceStage::Send,
+                    address: Some(address),
+                    error,
+                });
+        }
+    }
+}
+/// Report the outcome of a connection attempt to the callback set with
+/// [`Host::set_connect_audit`](`crate::Host::set_connect_audit`).
+pub(crate) unsafe fn enet_host_audit_connect<S: Socket>(
+    host: *mut ENetHost<S>,
+    address: &S::Address,
+    outcome: ConnectOutcome,
+) {
+    if let Some(audit) = (*host).connect_audit.assume_init_mut() {
+        audit(address, outcome);
+    }
+}
+/// Forget the bans which have expired.
+pub(crate) unsafe fn enet_host_expire_bans<S: Socket>(host: *mut ENetHost<S>) {
+    if !(*host).bans.assume_init_ref().is_empty()
+        || !(*host).unkeyed_bans.assume_init_ref().is_empty()
+    {
+        let now = (*host).time.assume_init_ref()();
+        (*host)
+            .bans
+            .assume_init_mut()
+            .retain(|_, expires| *expires > now);
+        (*host)
+            .unkeyed_bans
+            .assume_init_mut()
+            .retain(|(_, expires)| *expires > now);
+    }
+}
+pub(crate) unsafe fn enet_host_protocol_messages<S: Socket>(host: *mut ENetHost<S>) -> bool {
+    (*host).protocol_version > ProtocolVersion::Enet
+}
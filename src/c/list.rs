@@ -11,8 +11,8 @@ pub(crate) struct ENetList {
     pub(crate) sentinel: ENetListNode,
 }
 pub(crate) unsafe fn enet_list_clear(list: *mut ENetList) {
-    (*list).sentinel.next = &mut (*list).sentinel;
-    (*list).sentinel.previous = &mut (*list).sentinel;
+    (*list).sentinel.next = &raw mut (*list).sentinel;
+    (*list).sentinel.previous = &raw mut (*list).sentinel;
 }
 pub(crate) unsafe fn enet_list_insert(
     position: ENetListIterator,
@@ -30,6 +30,15 @@ pub(crate) unsafe fn enet_list_remove(position: ENetListIterator) -> *mut u8 {
     (*(*position).next).previous = (*position).previous;
     position.cast()
 }
+pub(crate) unsafe fn enet_list_size(list: *mut ENetList) -> usize {
+    let mut size: usize = 0;
+    let mut position: ENetListIterator = (*list).sentinel.next;
+    while position != &raw mut (*list).sentinel {
+        size = size.wrapping_add(1);
+        position = (*position).next;
+    }
+    size
+}
 pub(crate) unsafe fn enet_list_move(
     position: ENetListIterator,
     data_first: *mut u8,
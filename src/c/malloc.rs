@@ -1,12 +1,14 @@
 use core::alloc::Layout;
 
 #[cfg(not(feature = "std"))]
-use alloc::alloc::{alloc, dealloc, handle_alloc_error};
+use alloc::alloc::handle_alloc_error;
 #[cfg(feature = "std")]
-use std::alloc::{alloc, dealloc, handle_alloc_error};
+use std::alloc::handle_alloc_error;
+
+use crate::allocator;
 
 pub(crate) unsafe fn enet_malloc(layout: Layout) -> *mut u8 {
-    let ptr = unsafe { alloc(layout) };
+    let ptr = unsafe { (allocator().allocate)(layout) };
     if ptr.is_null() {
         handle_alloc_error(layout);
     }
@@ -14,5 +16,5 @@ pub(crate) unsafe fn enet_malloc(layout: Layout) -> *mut u8 {
 }
 
 pub(crate) unsafe fn enet_free(ptr: *mut u8, layout: Layout) {
-    dealloc(ptr, layout);
+    (allocator().deallocate)(ptr, layout);
 }
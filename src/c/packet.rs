@@ -1,14 +1,20 @@
-use core::{alloc::Layout, ptr::copy_nonoverlapping};
+use core::{
+    alloc::Layout,
+    ptr::copy_nonoverlapping,
+    sync::atomic::{fence, AtomicU32, AtomicUsize, Ordering},
+};
 
 use crate::{enet_free, enet_malloc, ENET_PACKET_FLAG_NO_ALLOCATE};
 
-#[derive(Copy, Clone)]
 #[repr(C)]
 pub(crate) struct ENetPacket {
-    pub(crate) reference_count: usize,
-    pub(crate) flags: u32,
+    pub(crate) reference_count: AtomicUsize,
+    pub(crate) flags: AtomicU32,
     pub(crate) data: *mut u8,
     pub(crate) data_length: usize,
+    pub(crate) tag: Option<u64>,
+    pub(crate) owner: *mut u8,
+    pub(crate) free_owner: Option<unsafe fn(*mut u8)>,
 }
 pub(crate) unsafe fn enet_packet_create(
     data: *const u8,
@@ -26,16 +32,19 @@ pub(crate) unsafe fn enet_packet_create(
             copy_nonoverlapping(data, (*packet).data, data_length);
         }
     }
-    (*packet).reference_count = 0_i32 as usize;
-    (*packet).flags = flags;
+    (*packet).reference_count = AtomicUsize::new(0);
+    (*packet).flags = AtomicU32::new(flags);
     (*packet).data_length = data_length;
+    (*packet).tag = None;
+    (*packet).owner = core::ptr::null_mut();
+    (*packet).free_owner = None;
     packet
 }
 pub(crate) unsafe fn enet_packet_destroy(packet: *mut ENetPacket) {
     if packet.is_null() {
         return;
     }
-    if (*packet).flags & ENET_PACKET_FLAG_NO_ALLOCATE as i32 as u32 == 0
+    if (*packet).flags.load(Ordering::Relaxed) & ENET_PACKET_FLAG_NO_ALLOCATE as i32 as u32 == 0
         && !((*packet).data).is_null()
     {
         enet_free(
@@ -43,5 +52,30 @@ pub(crate) unsafe fn enet_packet_destroy(packet: *mut ENetPacket) {
             Layout::array::<u8>((*packet).data_length).unwrap(),
         );
     }
+    if let Some(free_owner) = (*packet).free_owner {
+        free_owner((*packet).owner);
+    }
     enet_free(packet.cast(), Layout::new::<ENetPacket>());
 }
+/// Add `count` references to a packet.
+pub(crate) unsafe fn enet_packet_acquire(packet: *mut ENetPacket, count: usize) {
+    (*packet)
+        .reference_count
+        .fetch_add(count, Ordering::Relaxed);
+}
+/// Remove a reference from a packet, returning `true` if it was the last one, in which case the
+/// caller is responsible for destroying the packet.
+///
+/// Packets may be shared with [`Packet`](`crate::Packet`)s on other threads, so the count is
+/// atomic, and only the thread removing the last reference may destroy the packet.
+pub(crate) unsafe fn enet_packet_release(packet: *mut ENetPacket) -> bool {
+    if (*packet).reference_count.fetch_sub(1, Ordering::Release) == 1 {
+        fence(Ordering::Acquire);
+        true
+    } else {
+        false
+    }
+}
+pub(crate) unsafe fn enet_packet_reference_count(packet: *mut ENetPacket) -> usize {
+    (*packet).reference_count.load(Ordering::Acquire)
+}
@@ -2,24 +2,35 @@ use core::{
     alloc::Layout,
     mem::MaybeUninit,
     ptr::{addr_of_mut, write_bytes},
+    sync::atomic::Ordering,
+    time::Duration,
 };
 
 use crate::{
-    consts::*, enet_free, enet_host_flush, enet_list_clear, enet_list_insert, enet_list_move,
-    enet_list_remove, enet_malloc, enet_packet_create, enet_packet_destroy,
-    enet_protocol_command_size, error::PeerSendError, ENetAcknowledgement, ENetChannel,
-    ENetIncomingCommand, ENetList, ENetListIterator, ENetListNode, ENetOutgoingCommand, ENetPacket,
-    ENetProtocol, ENetProtocolAcknowledge, ENetProtocolCommandHeader, ENetProtocolHeader,
-    ENetProtocolSendFragment, Socket, ENET_PACKET_FLAG_RELIABLE,
-    ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT, ENET_PACKET_FLAG_UNSEQUENCED,
+    consts::*, enet_free, enet_host_audit_connect, enet_host_channel_maximum_packet_size,
+    enet_host_channel_mode, enet_host_channel_priority, enet_host_expire_sessions, enet_host_flush,
+    enet_host_secure_random, enet_host_unreliable_drop_policy, enet_list_clear, enet_list_insert,
+    enet_list_move, enet_list_remove, enet_malloc, enet_packet_acquire, enet_packet_create,
+    enet_packet_destroy, enet_packet_reference_count, enet_packet_release,
+    enet_protocol_command_size, error::PeerSendError, AcknowledgementMode, Address, BTreeMap,
+    ChannelMode, ConnectOutcome, ENetAcknowledgement, ENetChannel, ENetIncomingCommand, ENetList,
+    ENetListIterator, ENetListNode, ENetOutgoingCommand, ENetPacket, ENetProtocol,
+    ENetProtocolAcknowledge, ENetProtocolCommandHeader, ENetProtocolHeader,
+    ENetProtocolSendFragment, MemoryBudgetPolicy, PacketKind, PeerID, PeerSimulation,
+    ProtocolVersion, Socket, ThrottleSample, UnreliableDropPolicy, Vec, VecDeque,
+    ENET_PACKET_FLAG_RELIABLE, ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT, ENET_PACKET_FLAG_UNSEQUENCED,
     ENET_PROTOCOL_COMMAND_DISCONNECT, ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE,
     ENET_PROTOCOL_COMMAND_FLAG_UNSEQUENCED, ENET_PROTOCOL_COMMAND_MASK, ENET_PROTOCOL_COMMAND_PING,
     ENET_PROTOCOL_COMMAND_SEND_FRAGMENT, ENET_PROTOCOL_COMMAND_SEND_RELIABLE,
     ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE, ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE_FRAGMENT,
     ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED, ENET_PROTOCOL_COMMAND_THROTTLE_CONFIGURE,
+    ENET_PROTOCOL_MESSAGE_ACCEPT_DATA, ENET_PROTOCOL_MESSAGE_CLOCK_REQUEST,
+    ENET_PROTOCOL_MESSAGE_CONGESTION_ECHO, ENET_PROTOCOL_MESSAGE_CONNECT_PAYLOAD,
+    ENET_PROTOCOL_MESSAGE_PING_PAYLOAD, ENET_PROTOCOL_MESSAGE_REDIRECT,
+    ENET_PROTOCOL_MESSAGE_RESUME_SESSION, ENET_PROTOCOL_MESSAGE_SESSION_TICKET,
 };
 
-use super::{ENetHost, ENetNewProtocolHeader};
+use super::{ENetHost, ENetNewProtocolHeader, ENetPacketReport};
 
 pub(crate) type ENetPeerState = _ENetPeerState;
 pub(crate) type _ENetPeerState = u32;
@@ -36,13 +47,41 @@ pub(crate) const ENET_PEER_STATE_DISCONNECTED: _ENetPeerState = 0;
 pub(crate) type _ENetPeerFlag = u32;
 pub(crate) const ENET_PEER_FLAG_CONTINUE_SENDING: _ENetPeerFlag = 2;
 pub(crate) const ENET_PEER_FLAG_NEEDS_DISPATCH: _ENetPeerFlag = 1;
+/// The last challenge sent to a new address a peer's datagrams arrived from, see
+/// [`HostSettings::connection_migration`](`crate::HostSettings::connection_migration`).
+pub(crate) struct ENetMigrationChallenge<A> {
+    pub(crate) address: A,
+    pub(crate) nonce: [u8; 8],
+    pub(crate) sent_time: u32,
+    /// The number of wrong answers received, after which the challenge can't be answered.
+    pub(crate) failures: u8,
+}
+/// An address a peer can be reached at, see [`Peer::add_address`](`crate::Peer::add_address`).
+pub(crate) struct ENetPath<A> {
+    pub(crate) address: A,
+    /// The nonce of the probe last sent along this path, if it hasn't been answered yet.
+    pub(crate) nonce: Option<[u8; 8]>,
+    pub(crate) round_trip_time: Option<u32>,
+}
+/// The session a connection belongs to, see
+/// [`HostSettings::session_resumption`](`crate::HostSettings::session_resumption`).
+#[derive(Clone, Copy)]
+pub(crate) struct ENetSession {
+    pub(crate) id: u64,
+    pub(crate) token: [u8; 8],
+}
+/// A session ended by a graceful disconnect, which can be resumed until its lifetime passes.
+pub(crate) struct ENetResumableSession {
+    pub(crate) session: ENetSession,
+    pub(crate) end_time: u32,
+}
 #[repr(C)]
 pub(crate) struct ENetPeer<S: Socket> {
-    pub(crate) dispatch_list: ENetListNode,
     pub(crate) host: *mut ENetHost<S>,
     pub(crate) outgoing_peer_id: u16,
     pub(crate) incoming_peer_id: u16,
     pub(crate) connect_id: u32,
+    pub(crate) generation: u32,
     pub(crate) outgoing_session_id: u8,
     pub(crate) incoming_session_id: u8,
     pub(crate) address: MaybeUninit<Option<S::Address>>,
@@ -63,6 +102,11 @@ pub(crate) struct ENetPeer<S: Socket> {
     pub(crate) packet_loss_epoch: u32,
     pub(crate) packets_sent: u32,
     pub(crate) packets_lost: u32,
+    pub(crate) reliable_resends: u32,
+    /// Hashes of the latest unsequenced packets received, see
+    /// [`HostSettings::unsequenced_dedup_window`](`crate::HostSettings::unsequenced_dedup_window`).
+    pub(crate) unsequenced_hashes: MaybeUninit<VecDeque<u64>>,
+    pub(crate) unsequenced_duplicates: u32,
     pub(crate) packet_loss: u32,
     pub(crate) packet_loss_variance: u32,
     pub(crate) packet_throttle: u32,
@@ -76,6 +120,9 @@ pub(crate) struct ENetPeer<S: Socket> {
     pub(crate) timeout_limit: u32,
     pub(crate) timeout_minimum: u32,
     pub(crate) timeout_maximum: u32,
+    /// How many ping intervals can pass without an acknowledgement before the peer times out,
+    /// see [`HostSettings::missed_ping_limit`](`crate::HostSettings::missed_ping_limit`).
+    pub(crate) missed_ping_limit: Option<u32>,
     pub(crate) last_round_trip_time: u32,
     pub(crate) lowest_round_trip_time: u32,
     pub(crate) last_round_trip_time_variance: u32,
@@ -86,18 +133,99 @@ pub(crate) struct ENetPeer<S: Socket> {
     pub(crate) window_size: u32,
     pub(crate) reliable_data_in_transit: u32,
     pub(crate) outgoing_reliable_sequence_number: u16,
-    pub(crate) acknowledgements: ENetList,
-    pub(crate) sent_reliable_commands: ENetList,
-    pub(crate) outgoing_send_reliable_commands: ENetList,
-    pub(crate) outgoing_commands: ENetList,
+    pub(crate) acknowledgements: MaybeUninit<VecDeque<ENetAcknowledgement>>,
+    pub(crate) sent_reliable_commands: MaybeUninit<VecDeque<*mut ENetOutgoingCommand>>,
+    pub(crate) outgoing_send_reliable_commands: MaybeUninit<VecDeque<*mut ENetOutgoingCommand>>,
+    pub(crate) outgoing_commands: MaybeUninit<VecDeque<*mut ENetOutgoingCommand>>,
     pub(crate) dispatched_commands: ENetList,
     pub(crate) flags: u16,
     pub(crate) reserved: u16,
     pub(crate) incoming_unsequenced_group: u16,
     pub(crate) outgoing_unsequenced_group: u16,
-    pub(crate) unsequenced_window: [u32; 32],
+    pub(crate) unsequenced_window: MaybeUninit<Vec<u32>>,
     pub(crate) event_data: u32,
+    /// The data the connection was started with, passed to
+    /// [`Host::connect`](`crate::Host::connect`) by whichever side connected.
+    pub(crate) connect_data: u32,
     pub(crate) total_waiting_data: usize,
+    pub(crate) incomplete_fragments: usize,
+    /// Bytes buffered for this peer's incomplete reliable fragmented packets, bounded by
+    /// [`HostSettings::maximum_peer_fragment_data`](`crate::HostSettings::maximum_peer_fragment_data`).
+    pub(crate) incomplete_reliable_fragment_data: usize,
+    /// Set once the peer went past `incomplete_reliable_fragment_data`'s limit, to be
+    /// disconnected on the next service.
+    pub(crate) fragment_data_exceeded: bool,
+    /// This peer's incomplete unreliable fragmented packets by `fragment_order`, oldest first.
+    pub(crate) incomplete_unreliable_fragments:
+        MaybeUninit<BTreeMap<u64, *mut ENetIncomingCommand>>,
+    pub(crate) maximum_packet_size: usize,
+    pub(crate) memory_usage: usize,
+    /// How many of the peer's commands still hold each tagged reliable packet, by address, so its
+    /// delivery is reported once the last of them is acknowledged or dropped.
+    pub(crate) reported_packet_commands: MaybeUninit<BTreeMap<usize, usize>>,
+    pub(crate) coalesce_delay: Option<u32>,
+    /// The DSCP datagrams to this peer are marked with, see
+    /// [`Peer::set_dscp`](`crate::Peer::set_dscp`).
+    pub(crate) dscp: Option<u8>,
+    /// The channel and kind of packets sent with [`Peer::send_default`](`crate::Peer::send_default`),
+    /// overriding the host's, see [`Peer::set_defaults`](`crate::Peer::set_defaults`).
+    pub(crate) defaults: Option<(u8, PacketKind)>,
+    /// See [`Peer::simulate`](`crate::Peer::simulate`).
+    pub(crate) simulation: Option<PeerSimulation>,
+    /// See [`Peer::is_loopback`](`crate::Peer::is_loopback`).
+    pub(crate) loopback: bool,
+    /// The TTL and local address of the last datagram received from the peer, if the socket
+    /// supplied them, see [`ReceiveMetadata`](`crate::ReceiveMetadata`).
+    pub(crate) hop_limit: Option<u8>,
+    pub(crate) local_address: MaybeUninit<Option<S::Address>>,
+    /// When application data was last received, excluding pings and protocol messages.
+    pub(crate) last_data_time: u32,
+    /// Whether the peer has been reported idle since it last sent data.
+    pub(crate) idle_reported: bool,
+    pub(crate) window_size_override: Option<u32>,
+    pub(crate) ping_payload: MaybeUninit<Vec<u8>>,
+    pub(crate) remote_ping_payload: MaybeUninit<Option<Vec<u8>>>,
+    /// The payload to send with the connect command, or the payload received with it until the
+    /// connect event takes it, see [`Host::connect_with_payload`](`crate::Host::connect_with_payload`).
+    pub(crate) connect_payload: MaybeUninit<Vec<u8>>,
+    /// The verify connect command held back until the connection is accepted, see
+    /// [`HostSettings::connect_approval`](`crate::HostSettings::connect_approval`).
+    pub(crate) pending_verify: Option<ENetProtocol>,
+    /// The data given to [`Peer::accept`](`crate::Peer::accept`), sent along with the verify
+    /// connect command.
+    pub(crate) accept_data: Option<u32>,
+    /// The encoded address to send ahead of the disconnect command, see
+    /// [`Peer::redirect`](`crate::Peer::redirect`).
+    pub(crate) redirect: MaybeUninit<Vec<u8>>,
+    /// The address received in a redirect, which only applies if the next command in the same
+    /// datagram is the disconnect command.
+    pub(crate) pending_redirect: MaybeUninit<Option<S::Address>>,
+    /// The address received ahead of the disconnect command, to connect to once disconnected.
+    pub(crate) redirected_to: MaybeUninit<Option<S::Address>>,
+    /// Whether this host connected to the peer, rather than the peer to this host.
+    pub(crate) outgoing: bool,
+    pub(crate) migration_challenge: MaybeUninit<Option<ENetMigrationChallenge<S::Address>>>,
+    pub(crate) paths: MaybeUninit<Vec<ENetPath<S::Address>>>,
+    pub(crate) path_probe_time: u32,
+    pub(crate) clock_offset: Option<i64>,
+    pub(crate) protocol_version: ProtocolVersion,
+    pub(crate) session: Option<ENetSession>,
+    /// The number of datagrams from the peer marked Congestion Experienced.
+    pub(crate) incoming_congestion_marks: u32,
+    pub(crate) congestion_echo_time: Option<u32>,
+    /// The number of datagrams to the peer it reported were marked Congestion Experienced.
+    pub(crate) outgoing_congestion_marks: u32,
+    pub(crate) resumed: bool,
+    /// The session ticket presented to the host connected to, resent along with the connect
+    /// command until it's acknowledged.
+    pub(crate) resume_ticket: Option<[u8; 16]>,
+    /// Whether unreliable packets are held in the outgoing queue instead of being sent, see
+    /// [`Peer::pause_unreliable`](`crate::Peer::pause_unreliable`).
+    pub(crate) unreliable_paused: bool,
+    pub(crate) coalesce_start: Option<u32>,
+    /// When the oldest queued acknowledgement was queued, see
+    /// [`AcknowledgementMode::Delayed`](`crate::AcknowledgementMode::Delayed`).
+    pub(crate) acknowledgement_time: Option<u32>,
 }
 pub(crate) unsafe fn enet_peer_throttle_configure<S: Socket>(
     peer: *mut ENetPeer<S>,
@@ -123,94 +251,358 @@ pub(crate) unsafe fn enet_peer_throttle_configure<S: Socket>(
     command.throttle_configure.packet_throttle_deceleration = deceleration.to_be();
     enet_peer_queue_outgoing_command(
         peer,
-        &command,
+        &raw const command,
         core::ptr::null_mut(),
         0_i32 as u32,
         0_i32 as u16,
     );
 }
-pub(crate) unsafe fn enet_peer_throttle<S: Socket>(peer: *mut ENetPeer<S>, rtt: u32) -> i32 {
-    if (*peer).last_round_trip_time <= (*peer).last_round_trip_time_variance {
-        (*peer).packet_throttle = (*peer).packet_throttle_limit;
-    } else if rtt <= (*peer).last_round_trip_time {
-        (*peer).packet_throttle = (*peer)
-            .packet_throttle
-            .wrapping_add((*peer).packet_throttle_acceleration);
-        if (*peer).packet_throttle > (*peer).packet_throttle_limit {
-            (*peer).packet_throttle = (*peer).packet_throttle_limit;
-        }
-        return 1_i32;
-    } else if rtt
-        > ((*peer).last_round_trip_time)
-            .wrapping_add((2_i32 as u32).wrapping_mul((*peer).last_round_trip_time_variance))
+pub(crate) unsafe fn enet_peer_throttle<S: Socket>(peer: *mut ENetPeer<S>, rtt: u32) {
+    let host = (*peer).host;
+    let sample = ThrottleSample {
+        round_trip_time: Duration::from_millis(u64::from(rtt)),
+        last_round_trip_time: Duration::from_millis(u64::from((*peer).last_round_trip_time)),
+        last_round_trip_time_variance: Duration::from_millis(u64::from(
+            (*peer).last_round_trip_time_variance,
+        )),
+        throttle: (*peer).packet_throttle,
+        limit: (*peer).packet_throttle_limit,
+        acceleration: (*peer).packet_throttle_acceleration,
+        deceleration: (*peer).packet_throttle_deceleration,
+    };
+    let peer_id = enet_peer_id(peer);
+    (*peer).packet_throttle = (*host)
+        .congestion_controller
+        .assume_init_mut()
+        .throttle(peer_id, sample)
+        .min((*peer).packet_throttle_limit);
+}
+/// Count a datagram from the peer marked Congestion Experienced, echoing the count back to the
+/// peer at most once per round trip.
+pub(crate) unsafe fn enet_peer_congestion_experienced<S: Socket>(peer: *mut ENetPeer<S>) {
+    let host = (*peer).host;
+    (*peer).incoming_congestion_marks = (*peer).incoming_congestion_marks.wrapping_add(1);
+    if (*peer).protocol_version == ProtocolVersion::Enet
+        || (*peer).state != ENET_PEER_STATE_CONNECTED
+            && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER
+        || (*peer).congestion_echo_time.is_some_and(|echo_time| {
+            (*host).service_time.wrapping_sub(echo_time) < (*peer).round_trip_time
+        })
     {
-        if (*peer).packet_throttle > (*peer).packet_throttle_deceleration {
-            (*peer).packet_throttle = (*peer)
-                .packet_throttle
-                .wrapping_sub((*peer).packet_throttle_deceleration);
-        } else {
-            (*peer).packet_throttle = 0_i32 as u32;
-        }
-        return -1_i32;
+        return;
     }
-    0_i32
+    (*peer).congestion_echo_time = Some((*host).service_time);
+    enet_peer_send_protocol_message(
+        peer,
+        ENET_PROTOCOL_MESSAGE_CONGESTION_ECHO,
+        &(*peer).incoming_congestion_marks.to_be_bytes(),
+    );
+}
+/// Slow the peer's throttle when it reports more datagrams were marked Congestion Experienced.
+pub(crate) unsafe fn enet_peer_congestion_echo<S: Socket>(peer: *mut ENetPeer<S>, marks: u32) {
+    let host = (*peer).host;
+    // counts wrap, so newer counts are ahead by less than half the range
+    if !(*host).ecn || marks.wrapping_sub((*peer).outgoing_congestion_marks) as i32 <= 0 {
+        return;
+    }
+    (*peer).outgoing_congestion_marks = marks;
+    let sample = ThrottleSample {
+        round_trip_time: Duration::from_millis(u64::from((*peer).round_trip_time)),
+        last_round_trip_time: Duration::from_millis(u64::from((*peer).last_round_trip_time)),
+        last_round_trip_time_variance: Duration::from_millis(u64::from(
+            (*peer).last_round_trip_time_variance,
+        )),
+        throttle: (*peer).packet_throttle,
+        limit: (*peer).packet_throttle_limit,
+        acceleration: (*peer).packet_throttle_acceleration,
+        deceleration: (*peer).packet_throttle_deceleration,
+    };
+    let peer_id = enet_peer_id(peer);
+    (*peer).packet_throttle = (*host)
+        .congestion_controller
+        .assume_init_mut()
+        .congestion_experienced(peer_id, sample)
+        .min((*peer).packet_throttle_limit);
 }
 pub(crate) unsafe fn enet_peer_send<S: Socket>(
     peer: *mut ENetPeer<S>,
     channel_id: u8,
     packet: *mut ENetPacket,
 ) -> Result<(), PeerSendError> {
-    let mut command: ENetProtocol = ENetProtocol {
-        header: ENetProtocolCommandHeader {
-            command: 0,
-            channel_id: 0,
-            reliable_sequence_number: 0,
-        },
+    let fec = (*(*peer).host).fec.assume_init_mut();
+    let Some(group_size) = fec.group_size(channel_id) else {
+        return enet_peer_send_packet(peer, channel_id, packet);
+    };
+    let peer_index = enet_peer_id(peer).index;
+    let flags = (*packet).flags.load(Ordering::Relaxed)
+        & (ENET_PACKET_FLAG_RELIABLE
+            | ENET_PACKET_FLAG_UNSEQUENCED
+            | ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT);
+    let reliable = flags & ENET_PACKET_FLAG_RELIABLE != 0;
+    let data: &[u8] = if (*packet).data_length == 0 {
+        &[]
+    } else {
+        core::slice::from_raw_parts((*packet).data, (*packet).data_length)
+    };
+    let wrapped = fec.wrap(peer_index, channel_id, reliable, data);
+    let wrapped_packet = enet_packet_create(wrapped.as_ptr(), wrapped.len(), flags);
+    (*wrapped_packet).tag = (*packet).tag;
+    let result = enet_peer_send_packet(peer, channel_id, wrapped_packet);
+    if enet_packet_reference_count(wrapped_packet) == 0 {
+        enet_packet_destroy(wrapped_packet);
+    }
+    result?;
+    if !reliable {
+        if let Some(parity) = fec.commit(peer_index, channel_id, group_size, data) {
+            let parity_packet = enet_packet_create(parity.as_ptr(), parity.len(), flags);
+            _ = enet_peer_send_packet(peer, channel_id, parity_packet);
+            if enet_packet_reference_count(parity_packet) == 0 {
+                enet_packet_destroy(parity_packet);
+            }
+        }
+    }
+    Ok(())
+}
+/// Queue several packets on one channel, with the checks shared by the packets done once, and
+/// every command acquired from the pool at once. Packets are checked before any is queued, so on
+/// failure, only those before the first failing packet are queued, and its index is returned.
+pub(crate) unsafe fn enet_peer_send_batch<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    channel_id: u8,
+    packets: &[*mut ENetPacket],
+) -> Result<(), (usize, PeerSendError)> {
+    let (count, failure) = match enet_peer_check_batch(peer, channel_id, packets) {
+        Ok(()) => (packets.len(), None),
+        Err((index, error)) => (index, Some((index, error))),
     };
-    let mut fragment_length: usize;
+    enet_peer_queue_batch(peer, channel_id, &packets[..count])?;
+    failure.map_or(Ok(()), Err)
+}
+/// Check a batch of packets could be queued with [`enet_peer_queue_batch`], returning the index
+/// of the first packet which can't be.
+pub(crate) unsafe fn enet_peer_check_batch<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    channel_id: u8,
+    packets: &[*mut ENetPacket],
+) -> Result<(), (usize, PeerSendError)> {
+    if packets.is_empty() {
+        return Ok(());
+    }
+    let (_, fragment_length) =
+        enet_peer_check_channel(peer, channel_id).map_err(|error| (0, error))?;
+    // packets on channels using forward error correction are checked as they're wrapped
+    if (*(*peer).host)
+        .fec
+        .assume_init_ref()
+        .group_size(channel_id)
+        .is_some()
+    {
+        return Ok(());
+    }
+    let mode = enet_host_channel_mode((*peer).host, channel_id);
+    for (index, &packet) in packets.iter().enumerate() {
+        enet_peer_check_packet(peer, mode, packet, fragment_length)
+            .map_err(|error| (index, error))?;
+    }
+    Ok(())
+}
+/// Queue a batch of packets checked with [`enet_peer_check_batch`] in a single pass.
+pub(crate) unsafe fn enet_peer_queue_batch<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    channel_id: u8,
+    packets: &[*mut ENetPacket],
+) -> Result<(), (usize, PeerSendError)> {
+    if packets.is_empty() {
+        return Ok(());
+    }
+    if (*(*peer).host)
+        .fec
+        .assume_init_ref()
+        .group_size(channel_id)
+        .is_some()
+    {
+        for (index, &packet) in packets.iter().enumerate() {
+            enet_peer_send(peer, channel_id, packet).map_err(|error| (index, error))?;
+        }
+        return Ok(());
+    }
+    let (channel, fragment_length) =
+        enet_peer_check_channel(peer, channel_id).map_err(|error| (0, error))?;
+    let count = packets
+        .iter()
+        .map(|&packet| enet_peer_command_count(packet, fragment_length))
+        .sum();
+    let mut commands = Vec::with_capacity(count);
+    (*(*peer).host)
+        .outgoing_command_pool
+        .assume_init_mut()
+        .acquire_many(count, &mut commands);
+    let mut commands = commands.into_iter();
+    for (index, &packet) in packets.iter().enumerate() {
+        let command_count = enet_peer_command_count(packet, fragment_length);
+        if !enet_peer_make_room(peer, channel_id, packet) {
+            // the unused commands go back to the pool
+            for command in commands.by_ref().take(command_count) {
+                (*(*peer).host)
+                    .outgoing_command_pool
+                    .assume_init_mut()
+                    .release(command);
+            }
+            continue;
+        }
+        if !enet_peer_queue_packet(
+            peer,
+            channel,
+            channel_id,
+            packet,
+            fragment_length,
+            &mut commands,
+        ) {
+            return Err((index, PeerSendError::FailedToQueue));
+        }
+    }
+    Ok(())
+}
+/// Queue a packet as is, without the header of channels using forward error correction.
+unsafe fn enet_peer_send_packet<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    channel_id: u8,
+    packet: *mut ENetPacket,
+) -> Result<(), PeerSendError> {
+    let (channel, fragment_length) = enet_peer_check_channel(peer, channel_id)?;
+    let mode = enet_host_channel_mode((*peer).host, channel_id);
+    enet_peer_check_packet(peer, mode, packet, fragment_length)?;
+    if !enet_peer_make_room(peer, channel_id, packet) {
+        return Ok(());
+    }
+    let command_count = enet_peer_command_count(packet, fragment_length);
+    let mut commands = (0..command_count).map(|_| {
+        (*(*peer).host)
+            .outgoing_command_pool
+            .assume_init_mut()
+            .acquire()
+    });
+    if !enet_peer_queue_packet(
+        peer,
+        channel,
+        channel_id,
+        packet,
+        fragment_length,
+        &mut commands,
+    ) {
+        return Err(PeerSendError::FailedToQueue);
+    }
+    Ok(())
+}
+/// The checks shared by every packet sent to a peer on a channel, returning the channel and the
+/// length of the fragments packets too large for one datagram are split into.
+unsafe fn enet_peer_check_channel<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    channel_id: u8,
+) -> Result<(*mut ENetChannel, usize), PeerSendError> {
     if (*peer).state != ENET_PEER_STATE_CONNECTED as i32 as u32 {
         return Err(PeerSendError::NotConnected);
     }
     if channel_id as usize >= (*peer).channel_count {
         return Err(PeerSendError::InvalidChannel);
     }
-    if (*packet).data_length > (*(*peer).host).maximum_packet_size {
-        return Err(PeerSendError::PacketTooLarge);
+    if (*(*peer).host).memory_budget_policy == MemoryBudgetPolicy::Throttle
+        && enet_peer_over_memory_budget(peer)
+    {
+        return Err(PeerSendError::MemoryBudgetExceeded);
     }
     let channel = ((*peer).channels).offset(channel_id as isize);
-    if (*(*peer).host).using_new_packet {
-        fragment_length = ((*peer).mtu as usize)
+    let mut fragment_length = if (*(*peer).host).using_new_packet {
+        ((*peer).mtu as usize)
             .wrapping_sub(::core::mem::size_of::<ENetNewProtocolHeader>())
-            .wrapping_sub(::core::mem::size_of::<ENetProtocolSendFragment>());
+            .wrapping_sub(::core::mem::size_of::<ENetProtocolSendFragment>())
     } else {
-        fragment_length = ((*peer).mtu as usize)
+        ((*peer).mtu as usize)
             .wrapping_sub(::core::mem::size_of::<ENetProtocolHeader>())
-            .wrapping_sub(::core::mem::size_of::<ENetProtocolSendFragment>());
-    }
+            .wrapping_sub(::core::mem::size_of::<ENetProtocolSendFragment>())
+    };
     if ((*(*peer).host).checksum.assume_init_ref()).is_some() {
         fragment_length =
             (fragment_length as u64).wrapping_sub(::core::mem::size_of::<u32>() as u64) as usize;
     }
+    Ok((channel, fragment_length))
+}
+/// Check a packet can be sent on a channel in `mode`.
+unsafe fn enet_peer_check_packet<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    mode: ChannelMode,
+    packet: *mut ENetPacket,
+    fragment_length: usize,
+) -> Result<(), PeerSendError> {
+    if !mode.allows((*packet).flags.load(Ordering::Relaxed)) {
+        return Err(PeerSendError::ChannelModeMismatch);
+    }
+    if (*packet).data_length > (*(*peer).host).maximum_packet_size {
+        return Err(PeerSendError::PacketTooLarge);
+    }
+    if enet_peer_command_count(packet, fragment_length) > PROTOCOL_MAXIMUM_FRAGMENT_COUNT as usize {
+        return Err(PeerSendError::FragmentsExceeded);
+    }
+    Ok(())
+}
+/// The number of commands a packet is sent as, one per fragment.
+unsafe fn enet_peer_command_count(packet: *mut ENetPacket, fragment_length: usize) -> usize {
     if (*packet).data_length > fragment_length {
-        let fragment_count: u32 = ((*packet).data_length)
-            .wrapping_add(fragment_length)
-            .wrapping_sub(1_i32 as usize)
-            .wrapping_div(fragment_length) as u32;
+        (*packet).data_length.div_ceil(fragment_length)
+    } else {
+        1
+    }
+}
+/// Apply the channel's [`UnreliableDropPolicy`] before an unreliable packet is queued, returning
+/// `false` if the packet itself is dropped.
+unsafe fn enet_peer_make_room<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    channel_id: u8,
+    packet: *mut ENetPacket,
+) -> bool {
+    if (*packet).flags.load(Ordering::Relaxed) & ENET_PACKET_FLAG_RELIABLE != 0 {
+        return true;
+    }
+    match enet_host_unreliable_drop_policy((*peer).host, channel_id) {
+        UnreliableDropPolicy::Unbounded => {}
+        UnreliableDropPolicy::DropNewest(limit) => {
+            if enet_peer_queued_unreliable_packets(peer, channel_id) >= limit {
+                return false;
+            }
+        }
+        UnreliableDropPolicy::DropOldest(limit) => {
+            while enet_peer_queued_unreliable_packets(peer, channel_id) >= limit.max(1) {
+                enet_peer_drop_oldest_unreliable_packet(peer, channel_id);
+            }
+        }
+        UnreliableDropPolicy::KeepLatest => {
+            while enet_peer_queued_unreliable_packets(peer, channel_id) > 0 {
+                enet_peer_drop_oldest_unreliable_packet(peer, channel_id);
+            }
+        }
+    }
+    true
+}
+/// Queue a checked packet, as one command or a command per fragment taken from `commands`,
+/// returning `false` if a command couldn't be allocated.
+unsafe fn enet_peer_queue_packet<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    channel: *mut ENetChannel,
+    channel_id: u8,
+    packet: *mut ENetPacket,
+    mut fragment_length: usize,
+    commands: &mut impl Iterator<Item = *mut ENetOutgoingCommand>,
+) -> bool {
+    if (*packet).data_length > fragment_length {
+        let fragment_count = enet_peer_command_count(packet, fragment_length) as u32;
         let mut fragment_number: u32;
         let mut fragment_offset: u32;
         let command_number: u8;
         let start_sequence_number: u16;
-        let mut fragments: ENetList = ENetList {
-            sentinel: ENetListNode {
-                next: core::ptr::null_mut(),
-                previous: core::ptr::null_mut(),
-            },
-        };
+        let mut fragments: Vec<*mut ENetOutgoingCommand> =
+            Vec::with_capacity(fragment_count as usize);
         let mut fragment: *mut ENetOutgoingCommand;
-        if fragment_count > PROTOCOL_MAXIMUM_FRAGMENT_COUNT as i32 as u32 {
-            return Err(PeerSendError::FragmentsExceeded);
-        }
-        if (*packet).flags
+        if (*packet).flags.load(Ordering::Relaxed)
             & (ENET_PACKET_FLAG_RELIABLE as i32 | ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT as i32)
                 as u32
             == ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT as i32 as u32
@@ -225,14 +617,22 @@ pub(crate) unsafe fn enet_peer_send<S: Socket>(
             start_sequence_number =
                 (((*channel).outgoing_reliable_sequence_number as i32 + 1_i32) as u16).to_be();
         }
-        enet_list_clear(&mut fragments);
         fragment_number = 0_i32 as u32;
         fragment_offset = 0_i32 as u32;
         while (fragment_offset as usize) < (*packet).data_length {
             if ((*packet).data_length).wrapping_sub(fragment_offset as usize) < fragment_length {
                 fragment_length = ((*packet).data_length).wrapping_sub(fragment_offset as usize);
             }
-            fragment = enet_malloc(Layout::new::<ENetOutgoingCommand>()).cast();
+            fragment = commands.next().unwrap_or(core::ptr::null_mut());
+            if fragment.is_null() {
+                for fragment in fragments {
+                    (*(*peer).host)
+                        .outgoing_command_pool
+                        .assume_init_mut()
+                        .release(fragment);
+                }
+                return false;
+            }
             (*fragment).fragment_offset = fragment_offset;
             (*fragment).fragment_length = fragment_length as u16;
             (*fragment).packet = packet;
@@ -244,102 +644,204 @@ pub(crate) unsafe fn enet_peer_send<S: Socket>(
             (*fragment).command.send_fragment.fragment_number = fragment_number.to_be();
             (*fragment).command.send_fragment.total_length = ((*packet).data_length as u32).to_be();
             (*fragment).command.send_fragment.fragment_offset = u32::from_be(fragment_offset);
-            enet_list_insert(&mut fragments.sentinel, fragment.cast());
+            fragments.push(fragment);
             fragment_number = fragment_number.wrapping_add(1);
             fragment_offset = (fragment_offset as usize).wrapping_add(fragment_length) as u32;
         }
-        (*packet).reference_count =
-            ((*packet).reference_count as u64).wrapping_add(fragment_number as u64) as usize;
-        while fragments.sentinel.next != core::ptr::addr_of_mut!(fragments.sentinel) {
-            fragment = enet_list_remove(fragments.sentinel.next).cast();
+        enet_packet_acquire(packet, fragment_number as usize);
+        for fragment in fragments {
             enet_peer_setup_outgoing_command(peer, fragment);
         }
-        return Ok(());
+        (*channel).packets_sent = (*channel).packets_sent.wrapping_add(1);
+        return true;
     }
-    command.header.channel_id = channel_id;
-    if (*packet).flags
+    let outgoing_command = commands.next().unwrap_or(core::ptr::null_mut());
+    if outgoing_command.is_null() {
+        return false;
+    }
+    (*outgoing_command).command.header.channel_id = channel_id;
+    if (*packet).flags.load(Ordering::Relaxed)
         & (ENET_PACKET_FLAG_RELIABLE as i32 | ENET_PACKET_FLAG_UNSEQUENCED as i32) as u32
         == ENET_PACKET_FLAG_UNSEQUENCED as i32 as u32
     {
-        command.header.command = (ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED as i32
-            | ENET_PROTOCOL_COMMAND_FLAG_UNSEQUENCED as i32) as u8;
-        command.send_unsequenced.data_length = ((*packet).data_length as u16).to_be();
-    } else if (*packet).flags & ENET_PACKET_FLAG_RELIABLE as i32 as u32 != 0
+        (*outgoing_command).command.header.command = (ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED as i32
+            | ENET_PROTOCOL_COMMAND_FLAG_UNSEQUENCED as i32)
+            as u8;
+        (*outgoing_command).command.send_unsequenced.data_length =
+            ((*packet).data_length as u16).to_be();
+    } else if (*packet).flags.load(Ordering::Relaxed) & ENET_PACKET_FLAG_RELIABLE as i32 as u32 != 0
         || (*channel).outgoing_unreliable_sequence_number as i32 >= 0xffff_i32
     {
-        command.header.command = (ENET_PROTOCOL_COMMAND_SEND_RELIABLE as i32
-            | ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE as i32) as u8;
-        command.send_reliable.data_length = ((*packet).data_length as u16).to_be();
+        (*outgoing_command).command.header.command = (ENET_PROTOCOL_COMMAND_SEND_RELIABLE as i32
+            | ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE as i32)
+            as u8;
+        (*outgoing_command).command.send_reliable.data_length =
+            ((*packet).data_length as u16).to_be();
     } else {
-        command.header.command = ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE as i32 as u8;
-        command.send_unreliable.data_length = ((*packet).data_length as u16).to_be();
-    }
-    if (enet_peer_queue_outgoing_command(
-        peer,
-        &command,
-        packet,
-        0_i32 as u32,
-        (*packet).data_length as u16,
-    ))
-    .is_null()
-    {
-        return Err(PeerSendError::FailedToQueue);
+        (*outgoing_command).command.header.command =
+            ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE as i32 as u8;
+        (*outgoing_command).command.send_unreliable.data_length =
+            ((*packet).data_length as u16).to_be();
     }
-    Ok(())
+    (*outgoing_command).fragment_offset = 0;
+    (*outgoing_command).fragment_length = (*packet).data_length as u16;
+    (*outgoing_command).packet = packet;
+    enet_packet_acquire(packet, 1);
+    enet_peer_setup_outgoing_command(peer, outgoing_command);
+    (*channel).packets_sent = (*channel).packets_sent.wrapping_add(1);
+    true
 }
 pub(crate) unsafe fn enet_peer_receive<S: Socket>(
     peer: *mut ENetPeer<S>,
     channel_id: *mut u8,
+    received_time: *mut Duration,
 ) -> *mut ENetPacket {
-    if (*peer).dispatched_commands.sentinel.next
-        == core::ptr::addr_of_mut!((*peer).dispatched_commands.sentinel)
-    {
-        return core::ptr::null_mut();
+    loop {
+        if (*peer).dispatched_commands.sentinel.next
+            == core::ptr::addr_of_mut!((*peer).dispatched_commands.sentinel)
+        {
+            return core::ptr::null_mut();
+        }
+        let incoming_command: *mut ENetIncomingCommand =
+            enet_list_remove((*peer).dispatched_commands.sentinel.next).cast();
+        let superseded = enet_peer_dispatched_command_superseded(peer, incoming_command);
+        let received_channel_id = (*incoming_command).command.header.channel_id;
+        if !channel_id.is_null() {
+            *channel_id = received_channel_id;
+        }
+        if !received_time.is_null() {
+            *received_time = (*incoming_command).received_time;
+        }
+        let packet = (*incoming_command).packet;
+        (*peer).memory_usage = ((*peer).memory_usage)
+            .wrapping_sub(enet_peer_incoming_command_memory(incoming_command));
+        if !((*incoming_command).fragments).is_null() {
+            let count = (*incoming_command)
+                .fragment_count
+                .wrapping_add(31_i32 as u32)
+                .wrapping_div(32_i32 as u32) as usize;
+            enet_free(
+                (*incoming_command).fragments.cast(),
+                Layout::array::<u32>(count).unwrap(),
+            );
+        }
+        (*(*peer).host)
+            .incoming_command_pool
+            .assume_init_mut()
+            .release(incoming_command);
+        (*peer).total_waiting_data = (*peer)
+            .total_waiting_data
+            .wrapping_sub((*packet).data_length);
+        if superseded {
+            if enet_packet_release(packet) {
+                enet_packet_destroy(packet);
+            }
+            continue;
+        }
+        if usize::from(received_channel_id) < (*peer).channel_count {
+            let channel = (*peer).channels.add(usize::from(received_channel_id));
+            (*channel).packets_received = (*channel).packets_received.wrapping_add(1);
+        }
+        // the dispatch queue's reference is handed to the caller
+        return packet;
     }
-    let incoming_command: *mut ENetIncomingCommand =
-        enet_list_remove((*peer).dispatched_commands.sentinel.next).cast();
-    if !channel_id.is_null() {
-        *channel_id = (*incoming_command).command.header.channel_id;
-    }
-    let packet = (*incoming_command).packet;
-    (*packet).reference_count = ((*packet).reference_count).wrapping_sub(1);
-    if !((*incoming_command).fragments).is_null() {
-        let count = (*incoming_command)
-            .fragment_count
-            .wrapping_add(31_i32 as u32)
-            .wrapping_div(32_i32 as u32) as usize;
-        enet_free(
-            (*incoming_command).fragments.cast(),
-            Layout::array::<u32>(count).unwrap(),
-        );
+}
+/// Check if a command removed from the dispatch queue is followed by a newer packet on a
+/// [`ChannelMode::Latest`] channel, in which case only the newer packet is delivered.
+unsafe fn enet_peer_dispatched_command_superseded<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    incoming_command: *mut ENetIncomingCommand,
+) -> bool {
+    let channel_id = (*incoming_command).command.header.channel_id;
+    if enet_host_channel_mode((*peer).host, channel_id) != ChannelMode::Latest {
+        return false;
     }
-    enet_free(
-        incoming_command.cast(),
-        Layout::new::<ENetIncomingCommand>(),
-    );
-    (*peer).total_waiting_data = (*peer)
-        .total_waiting_data
-        .wrapping_sub((*packet).data_length) as usize as usize;
-    packet
-}
-unsafe fn enet_peer_reset_outgoing_commands(queue: *mut ENetList) {
-    let mut outgoing_command: *mut ENetOutgoingCommand;
-    while (*queue).sentinel.next != core::ptr::addr_of_mut!((*queue).sentinel) {
-        outgoing_command = enet_list_remove((*queue).sentinel.next).cast();
-        if !((*outgoing_command).packet).is_null() {
-            (*(*outgoing_command).packet).reference_count =
-                ((*(*outgoing_command).packet).reference_count).wrapping_sub(1);
-            if (*(*outgoing_command).packet).reference_count == 0_i32 as usize {
-                enet_packet_destroy((*outgoing_command).packet);
-            }
+    let mut current_command = (*peer).dispatched_commands.sentinel.next;
+    while current_command != core::ptr::addr_of_mut!((*peer).dispatched_commands.sentinel) {
+        let command: *mut ENetIncomingCommand = current_command.cast();
+        if (*command).command.header.channel_id == channel_id {
+            return true;
         }
-        enet_free(
-            outgoing_command.cast(),
-            Layout::new::<ENetOutgoingCommand>(),
-        );
+        current_command = (*current_command).next;
     }
+    false
 }
-unsafe fn enet_peer_remove_incoming_commands(
+unsafe fn enet_peer_is_queued_unreliable_command(
+    outgoing_command: *mut ENetOutgoingCommand,
+    channel_id: u8,
+) -> bool {
+    (*outgoing_command).command.header.channel_id == channel_id
+        && !(*outgoing_command).packet.is_null()
+        && matches!(
+            (*outgoing_command).command.header.command as u32 & ENET_PROTOCOL_COMMAND_MASK,
+            ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE
+                | ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED
+                | ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE_FRAGMENT
+        )
+}
+unsafe fn enet_peer_queued_unreliable_packets<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    channel_id: u8,
+) -> usize {
+    (*peer)
+        .outgoing_commands
+        .assume_init_ref()
+        .iter()
+        .filter(|&&outgoing_command| {
+            enet_peer_is_queued_unreliable_command(outgoing_command, channel_id)
+                && (*outgoing_command).fragment_offset == 0
+        })
+        .count()
+}
+/// Remove every queued command of the oldest unreliable packet waiting to be sent on a channel.
+unsafe fn enet_peer_drop_oldest_unreliable_packet<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    channel_id: u8,
+) {
+    let mut packet: *mut ENetPacket = core::ptr::null_mut();
+    let mut released = false;
+    let mut index = 0;
+    while index < (*peer).outgoing_commands.assume_init_ref().len() {
+        let outgoing_command = (*peer).outgoing_commands.assume_init_ref()[index];
+        if !enet_peer_is_queued_unreliable_command(outgoing_command, channel_id)
+            || (!packet.is_null() && (*outgoing_command).packet != packet)
+        {
+            index += 1;
+            continue;
+        }
+        packet = (*outgoing_command).packet;
+        (*peer).outgoing_commands.assume_init_mut().remove(index);
+        enet_peer_release_outgoing_command(peer, outgoing_command);
+        released = enet_packet_release(packet);
+        (*(*peer).host)
+            .outgoing_command_pool
+            .assume_init_mut()
+            .release(outgoing_command);
+    }
+    if released {
+        enet_packet_destroy(packet);
+    }
+}
+unsafe fn enet_peer_reset_outgoing_commands<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    queue: *mut MaybeUninit<VecDeque<*mut ENetOutgoingCommand>>,
+) {
+    while let Some(outgoing_command) = (*queue).assume_init_mut().pop_front() {
+        enet_peer_release_outgoing_command(peer, outgoing_command);
+        enet_peer_report_packet(peer, outgoing_command, false);
+        if !((*outgoing_command).packet).is_null()
+            && enet_packet_release((*outgoing_command).packet)
+        {
+            enet_packet_destroy((*outgoing_command).packet);
+        }
+        (*(*peer).host)
+            .outgoing_command_pool
+            .assume_init_mut()
+            .release(outgoing_command);
+    }
+}
+unsafe fn enet_peer_remove_incoming_commands<S: Socket>(
+    peer: *mut ENetPeer<S>,
     mut _queue: *mut ENetList,
     start_command: ENetListIterator,
     end_command: ENetListIterator,
@@ -353,13 +855,18 @@ unsafe fn enet_peer_remove_incoming_commands(
         if incoming_command == exclude_command {
             continue;
         }
-        enet_list_remove(&mut (*incoming_command).incoming_command_list);
-        if !((*incoming_command).packet).is_null() {
-            (*(*incoming_command).packet).reference_count =
-                ((*(*incoming_command).packet).reference_count).wrapping_sub(1);
-            if (*(*incoming_command).packet).reference_count == 0_i32 as usize {
-                enet_packet_destroy((*incoming_command).packet);
-            }
+        enet_list_remove(&raw mut (*incoming_command).incoming_command_list);
+        if !((*incoming_command).fragments).is_null()
+            && (*incoming_command).fragments_remaining > 0_i32 as u32
+        {
+            enet_peer_forget_incomplete_fragments(peer, incoming_command);
+        }
+        (*peer).memory_usage = ((*peer).memory_usage)
+            .wrapping_sub(enet_peer_incoming_command_memory(incoming_command));
+        if !((*incoming_command).packet).is_null()
+            && enet_packet_release((*incoming_command).packet)
+        {
+            enet_packet_destroy((*incoming_command).packet);
         }
         if !((*incoming_command).fragments).is_null() {
             let count = (*incoming_command)
@@ -371,43 +878,237 @@ unsafe fn enet_peer_remove_incoming_commands(
                 Layout::array::<u32>(count).unwrap(),
             );
         }
-        enet_free(
-            incoming_command.cast(),
-            Layout::new::<ENetIncomingCommand>(),
-        );
+        (*(*peer).host)
+            .incoming_command_pool
+            .assume_init_mut()
+            .release(incoming_command);
     }
 }
-unsafe fn enet_peer_reset_incoming_commands(queue: *mut ENetList) {
+unsafe fn enet_peer_reset_incoming_commands<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    queue: *mut ENetList,
+) {
     enet_peer_remove_incoming_commands(
+        peer,
         queue,
         (*queue).sentinel.next,
-        &mut (*queue).sentinel,
+        &raw mut (*queue).sentinel,
+        core::ptr::null_mut(),
+    );
+}
+unsafe fn enet_peer_incoming_command_memory(incoming_command: *mut ENetIncomingCommand) -> usize {
+    ::core::mem::size_of::<ENetIncomingCommand>()
+        .wrapping_add((*(*incoming_command).packet).data_length)
+}
+unsafe fn enet_peer_outgoing_command_memory(outgoing_command: *mut ENetOutgoingCommand) -> usize {
+    ::core::mem::size_of::<ENetOutgoingCommand>()
+        .wrapping_add((*outgoing_command).fragment_length as usize)
+}
+pub(crate) unsafe fn enet_peer_release_outgoing_command<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    outgoing_command: *mut ENetOutgoingCommand,
+) {
+    (*peer).memory_usage =
+        ((*peer).memory_usage).wrapping_sub(enet_peer_outgoing_command_memory(outgoing_command));
+    if enet_peer_reports_packet(outgoing_command) {
+        let outstanding = (*peer).reported_packet_commands.assume_init_mut();
+        let key = (*outgoing_command).packet as usize;
+        if let Some(count) = outstanding.get_mut(&key) {
+            *count -= 1;
+            if *count == 0 {
+                outstanding.remove(&key);
+            }
+        }
+    }
+}
+/// Whether the command's packet is reported with [`enet_peer_report_packet`] once the peer has no
+/// other commands for it.
+unsafe fn enet_peer_reports_packet(outgoing_command: *mut ENetOutgoingCommand) -> bool {
+    // unreliable commands may be released after their packet was destroyed, so check the flag
+    // before the packet
+    (*outgoing_command).command.header.command as i32
+        & ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE as i32
+        != 0
+        && !((*outgoing_command).packet).is_null()
+        && (*(*outgoing_command).packet).tag.is_some()
+}
+pub(crate) unsafe fn enet_peer_report_packet<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    outgoing_command: *mut ENetOutgoingCommand,
+    delivered: bool,
+) {
+    let packet = (*outgoing_command).packet;
+    if packet.is_null()
+        || (*outgoing_command).command.header.command as i32
+            & ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE as i32
+            == 0
+    {
+        return;
+    }
+    let Some(tag) = (*packet).tag else {
+        return;
+    };
+    if (*peer)
+        .reported_packet_commands
+        .assume_init_ref()
+        .contains_key(&(packet as usize))
+    {
+        return;
+    }
+    (*(*peer).host)
+        .packet_reports
+        .assume_init_mut()
+        .push_back(ENetPacketReport {
+            peer,
+            channel_id: (*outgoing_command).command.header.channel_id,
+            tag,
+            delivered,
+            queued_time: (*outgoing_command).queued_time,
+        });
+}
+pub(crate) unsafe fn enet_peer_over_memory_budget<S: Socket>(peer: *mut ENetPeer<S>) -> bool {
+    (*peer).memory_usage > (*(*peer).host).peer_memory_budget
+}
+pub(crate) unsafe fn enet_peer_maximum_packet_size<S: Socket>(peer: *mut ENetPeer<S>) -> usize {
+    ((*peer).maximum_packet_size).min((*(*peer).host).maximum_packet_size)
+}
+/// The maximum size of packets the peer may send on a channel, which also accounts for
+/// [`HostSettings::channel_maximum_packet_sizes`](`crate::HostSettings::channel_maximum_packet_sizes`).
+pub(crate) unsafe fn enet_peer_channel_maximum_packet_size<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    channel_id: u8,
+) -> usize {
+    enet_peer_maximum_packet_size(peer).min(enet_host_channel_maximum_packet_size(
+        (*peer).host,
+        channel_id,
+    ))
+}
+pub(crate) unsafe fn enet_peer_window_size<S: Socket>(peer: *mut ENetPeer<S>) -> u32 {
+    (*peer).window_size_override.unwrap_or((*peer).window_size)
+}
+/// Whether the remote host assigned the peer an ID past [`PROTOCOL_MAXIMUM_PEER_ID`], which is
+/// sent after the datagram header rather than in it.
+pub(crate) unsafe fn enet_peer_extended_peer_id<S: Socket>(peer: *mut ENetPeer<S>) -> bool {
+    (*peer).outgoing_peer_id as u32 > PROTOCOL_MAXIMUM_PEER_ID
+}
+pub(crate) unsafe fn enet_peer_coalesce_delay<S: Socket>(peer: *mut ENetPeer<S>) -> u32 {
+    (*peer)
+        .coalesce_delay
+        .unwrap_or((*(*peer).host).coalesce_delay)
+}
+/// Check if the acknowledgements queued for a peer should be held back during this service, see
+/// [`AcknowledgementMode::Delayed`](`crate::AcknowledgementMode::Delayed`).
+pub(crate) unsafe fn enet_peer_holds_acknowledgements<S: Socket>(peer: *mut ENetPeer<S>) -> bool {
+    let AcknowledgementMode::Delayed(delay) = (*(*peer).host).acknowledgement_mode else {
+        return false;
+    };
+    let Some(acknowledgement_time) = (*peer).acknowledgement_time else {
+        return false;
+    };
+    (*peer).state == ENET_PEER_STATE_CONNECTED
+        && (*peer).outgoing_commands.assume_init_ref().is_empty()
+        && (*peer)
+            .outgoing_send_reliable_commands
+            .assume_init_ref()
+            .is_empty()
+        && ((*(*peer).host).service_time).wrapping_sub(acknowledgement_time)
+            < u32::try_from(delay.as_millis()).unwrap_or(u32::MAX)
+}
+/// Check if the commands queued for a peer should be held back during this service, to be sent
+/// together with commands queued later.
+pub(crate) unsafe fn enet_peer_holds_outgoing_commands<S: Socket>(peer: *mut ENetPeer<S>) -> bool {
+    let Some(coalesce_start) = (*peer).coalesce_start else {
+        return false;
+    };
+    if ((*(*peer).host).service_time).wrapping_sub(coalesce_start) >= enet_peer_coalesce_delay(peer)
+    {
+        return false;
+    }
+    let mut queued_size = 0;
+    for queue in [
+        (*peer).outgoing_commands.assume_init_ref(),
+        (*peer).outgoing_send_reliable_commands.assume_init_ref(),
+    ] {
+        for &outgoing_command in queue {
+            queued_size += enet_protocol_command_size((*outgoing_command).command.header.command)
+                + (*outgoing_command).fragment_length as usize;
+            if queued_size >= (*peer).mtu as usize {
+                return false;
+            }
+        }
+    }
+    true
+}
+pub(crate) unsafe fn enet_peer_forget_incomplete_fragments<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    incoming_command: *mut ENetIncomingCommand,
+) {
+    let host = (*peer).host;
+    let data_length = (*(*incoming_command).packet).data_length;
+    (*peer).incomplete_fragments = ((*peer).incomplete_fragments).wrapping_sub(1);
+    if (*incoming_command).command.header.command as i32 & ENET_PROTOCOL_COMMAND_MASK as i32
+        == ENET_PROTOCOL_COMMAND_SEND_FRAGMENT as i32
+    {
+        (*peer).incomplete_reliable_fragment_data =
+            ((*peer).incomplete_reliable_fragment_data).wrapping_sub(data_length);
+    } else {
+        (*host).incomplete_fragment_data =
+            ((*host).incomplete_fragment_data).wrapping_sub(data_length);
+        (*peer)
+            .incomplete_unreliable_fragments
+            .assume_init_mut()
+            .remove(&(*incoming_command).fragment_order);
+        (*host)
+            .incomplete_unreliable_fragments
+            .assume_init_mut()
+            .remove(&(*incoming_command).fragment_order);
+    }
+}
+pub(crate) unsafe fn enet_peer_drop_incoming_command<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    channel: *mut ENetChannel,
+    incoming_command: *mut ENetIncomingCommand,
+) {
+    enet_peer_remove_incoming_commands(
+        peer,
+        &raw mut (*channel).incoming_unreliable_commands,
+        incoming_command.cast(),
+        (*incoming_command).incoming_command_list.next,
         core::ptr::null_mut(),
     );
 }
 pub(crate) unsafe fn enet_peer_reset_queues<S: Socket>(peer: *mut ENetPeer<S>) {
     let mut channel: *mut ENetChannel;
     if (*peer).flags as i32 & ENET_PEER_FLAG_NEEDS_DISPATCH as i32 != 0 {
-        enet_list_remove(&mut (*peer).dispatch_list);
+        let peer_index = usize::from((*peer).incoming_peer_id);
+        (*(*peer).host)
+            .dispatch_queue
+            .assume_init_mut()
+            .retain(|&queued| queued != peer_index);
         (*peer).flags = ((*peer).flags as i32 & !(ENET_PEER_FLAG_NEEDS_DISPATCH as i32)) as u16;
     }
-    while (*peer).acknowledgements.sentinel.next
-        != core::ptr::addr_of_mut!((*peer).acknowledgements.sentinel)
-    {
-        enet_free(
-            enet_list_remove((*peer).acknowledgements.sentinel.next),
-            Layout::new::<ENetAcknowledgement>(),
-        );
-    }
-    enet_peer_reset_outgoing_commands(&mut (*peer).sent_reliable_commands);
-    enet_peer_reset_outgoing_commands(&mut (*peer).outgoing_commands);
-    enet_peer_reset_outgoing_commands(&mut (*peer).outgoing_send_reliable_commands);
-    enet_peer_reset_incoming_commands(&mut (*peer).dispatched_commands);
+    (*peer).acknowledgements.assume_init_mut().clear();
+    (*(*peer).host)
+        .fec
+        .assume_init_mut()
+        .reset_peer(enet_peer_id(peer).index);
+    (*peer).acknowledgement_time = None;
+    (*(*peer).host)
+        .resend_reports
+        .assume_init_mut()
+        .retain(|report| report.peer != peer);
+    enet_peer_reset_outgoing_commands(peer, &raw mut (*peer).sent_reliable_commands);
+    enet_peer_reset_outgoing_commands(peer, &raw mut (*peer).outgoing_commands);
+    enet_peer_reset_outgoing_commands(peer, &raw mut (*peer).outgoing_send_reliable_commands);
+    enet_peer_reset_incoming_commands(peer, &raw mut (*peer).dispatched_commands);
     if !((*peer).channels).is_null() && (*peer).channel_count > 0_i32 as usize {
         channel = (*peer).channels;
         while channel < ((*peer).channels).add((*peer).channel_count) {
-            enet_peer_reset_incoming_commands(&mut (*channel).incoming_reliable_commands);
-            enet_peer_reset_incoming_commands(&mut (*channel).incoming_unreliable_commands);
+            enet_peer_reset_incoming_commands(peer, &raw mut (*channel).incoming_reliable_commands);
+            enet_peer_reset_incoming_commands(
+                peer,
+                &raw mut (*channel).incoming_unreliable_commands,
+            );
             channel = channel.offset(1);
         }
         enet_free(
@@ -427,6 +1128,8 @@ pub(crate) unsafe fn enet_peer_on_connect<S: Socket>(peer: *mut ENetPeer<S>) {
                 ((*(*peer).host).bandwidth_limited_peers).wrapping_add(1);
         }
         (*(*peer).host).connected_peers = ((*(*peer).host).connected_peers).wrapping_add(1);
+        (*peer).last_data_time = (*(*peer).host).service_time;
+        (*peer).idle_reported = false;
     }
 }
 pub(crate) unsafe fn enet_peer_on_disconnect<S: Socket>(peer: *mut ENetPeer<S>) {
@@ -442,9 +1145,15 @@ pub(crate) unsafe fn enet_peer_on_disconnect<S: Socket>(peer: *mut ENetPeer<S>)
 }
 pub(crate) unsafe fn enet_peer_reset<S: Socket>(peer: *mut ENetPeer<S>) {
     enet_peer_on_disconnect(peer);
+    let host = (*peer).host;
+    (*host)
+        .congestion_controller
+        .assume_init_mut()
+        .reset(enet_peer_id(peer));
     (*peer).outgoing_peer_id = PROTOCOL_MAXIMUM_PEER_ID as i32 as u16;
     (*peer).connect_id = 0_i32 as u32;
     (*peer).state = ENET_PEER_STATE_DISCONNECTED;
+    (*peer).fragment_data_exceeded = false;
     (*peer).incoming_bandwidth = 0_i32 as u32;
     (*peer).outgoing_bandwidth = 0_i32 as u32;
     (*peer).incoming_bandwidth_throttle_epoch = 0_i32 as u32;
@@ -458,6 +1167,9 @@ pub(crate) unsafe fn enet_peer_reset<S: Socket>(peer: *mut ENetPeer<S>) {
     (*peer).packet_loss_epoch = 0_i32 as u32;
     (*peer).packets_sent = 0_i32 as u32;
     (*peer).packets_lost = 0_i32 as u32;
+    (*peer).reliable_resends = 0;
+    (*peer).unsequenced_hashes.assume_init_mut().clear();
+    (*peer).unsequenced_duplicates = 0;
     (*peer).packet_loss = 0_i32 as u32;
     (*peer).packet_loss_variance = 0_i32 as u32;
     (*peer).packet_throttle = PEER_DEFAULT_PACKET_THROTTLE as i32 as u32;
@@ -468,6 +1180,7 @@ pub(crate) unsafe fn enet_peer_reset<S: Socket>(peer: *mut ENetPeer<S>) {
     (*peer).packet_throttle_deceleration = PEER_PACKET_THROTTLE_DECELERATION as i32 as u32;
     (*peer).packet_throttle_interval = PEER_PACKET_THROTTLE_INTERVAL as i32 as u32;
     (*peer).ping_interval = PEER_PING_INTERVAL as i32 as u32;
+    (*peer).missed_ping_limit = (*host).missed_ping_limit;
     (*peer).timeout_limit = PEER_TIMEOUT_LIMIT as i32 as u32;
     (*peer).timeout_minimum = PEER_TIMEOUT_MINIMUM as i32 as u32;
     (*peer).timeout_maximum = PEER_TIMEOUT_MAXIMUM as i32 as u32;
@@ -484,9 +1197,45 @@ pub(crate) unsafe fn enet_peer_reset<S: Socket>(peer: *mut ENetPeer<S>) {
     (*peer).incoming_unsequenced_group = 0_i32 as u16;
     (*peer).outgoing_unsequenced_group = 0_i32 as u16;
     (*peer).event_data = 0_i32 as u32;
+    (*peer).connect_data = 0;
     (*peer).total_waiting_data = 0_i32 as usize;
+    (*peer).maximum_packet_size = usize::MAX;
+    (*peer).coalesce_delay = None;
+    (*peer).dscp = None;
+    (*peer).defaults = None;
+    (*peer).simulation = None;
+    (*peer).loopback = false;
+    (*peer).hop_limit = None;
+    *(*peer).local_address.assume_init_mut() = None;
+    (*peer).last_data_time = 0;
+    (*peer).idle_reported = false;
+    (*peer).window_size_override = None;
+    (*peer).ping_payload.assume_init_mut().clear();
+    *(*peer).remote_ping_payload.assume_init_mut() = None;
+    (*peer).connect_payload.assume_init_mut().clear();
+    (*peer).pending_verify = None;
+    (*peer).accept_data = None;
+    (*peer).redirect.assume_init_mut().clear();
+    *(*peer).pending_redirect.assume_init_mut() = None;
+    *(*peer).redirected_to.assume_init_mut() = None;
+    (*peer).outgoing = false;
+    *(*peer).migration_challenge.assume_init_mut() = None;
+    (*peer).paths.assume_init_mut().clear();
+    (*peer).path_probe_time = 0;
+    (*peer).clock_offset = None;
+    (*peer).protocol_version = ProtocolVersion::Enet;
+    (*peer).session = None;
+    (*peer).incoming_congestion_marks = 0;
+    (*peer).congestion_echo_time = None;
+    (*peer).outgoing_congestion_marks = 0;
+    (*peer).resumed = false;
+    (*peer).resume_ticket = None;
+    (*peer).unreliable_paused = false;
+    (*peer).coalesce_start = None;
     (*peer).flags = 0_i32 as u16;
-    write_bytes(((*peer).unsequenced_window).as_mut_ptr(), 0, 32);
+    let unsequenced_window = (*peer).unsequenced_window.assume_init_mut();
+    unsequenced_window.clear();
+    unsequenced_window.resize((*host).unsequenced_window_size as usize / 32, 0);
     enet_peer_reset_queues(peer);
 }
 pub(crate) unsafe fn enet_peer_ping<S: Socket>(peer: *mut ENetPeer<S>) {
@@ -505,11 +1254,335 @@ pub(crate) unsafe fn enet_peer_ping<S: Socket>(peer: *mut ENetPeer<S>) {
     command.header.channel_id = 0xff_i32 as u8;
     enet_peer_queue_outgoing_command(
         peer,
-        &command,
+        &raw const command,
+        core::ptr::null_mut(),
+        0_i32 as u32,
+        0_i32 as u16,
+    );
+    let host = (*peer).host;
+    if (*peer).protocol_version == ProtocolVersion::Enet {
+        return;
+    }
+    if (*host).ping_payloads && !(*peer).ping_payload.assume_init_ref().is_empty() {
+        let payload = (*peer).ping_payload.assume_init_ref().clone();
+        enet_peer_send_protocol_message(peer, ENET_PROTOCOL_MESSAGE_PING_PAYLOAD, &payload);
+    }
+    if (*host).clock_sync {
+        enet_peer_send_protocol_message(
+            peer,
+            ENET_PROTOCOL_MESSAGE_CLOCK_REQUEST,
+            &(*host).service_time.to_be_bytes(),
+        );
+    }
+}
+/// Queue a message for the protocol itself as an unsequenced command on the protocol's own
+/// channel, which hosts speaking [`ProtocolVersion::Extended`] handle instead of
+/// dispatching. The first byte of the command's data identifies the kind of message. Returns the
+/// queued command, or null if it couldn't be queued.
+pub(crate) unsafe fn enet_peer_send_protocol_message<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    kind: u8,
+    data: &[u8],
+) -> *mut ENetOutgoingCommand {
+    let packet = enet_packet_create(
+        core::ptr::null(),
+        data.len() + 1,
+        ENET_PACKET_FLAG_UNSEQUENCED,
+    );
+    *(*packet).data = kind;
+    core::ptr::copy_nonoverlapping(data.as_ptr(), (*packet).data.add(1), data.len());
+    let mut command: ENetProtocol = ENetProtocol {
+        header: ENetProtocolCommandHeader {
+            command: (ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED as i32
+                | ENET_PROTOCOL_COMMAND_FLAG_UNSEQUENCED as i32) as u8,
+            channel_id: 0xff,
+            reliable_sequence_number: 0,
+        },
+    };
+    command.send_unsequenced.data_length = ((data.len() + 1) as u16).to_be();
+    let outgoing_command = enet_peer_queue_outgoing_command(
+        peer,
+        &raw const command,
+        packet,
+        0,
+        (data.len() + 1) as u16,
+    );
+    if outgoing_command.is_null() {
+        enet_packet_destroy(packet);
+    }
+    outgoing_command
+}
+/// Queue a protocol message like [`enet_peer_send_protocol_message`], moved ahead of an outgoing
+/// command so the message is sent before it.
+pub(crate) unsafe fn enet_peer_send_protocol_message_before<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    kind: u8,
+    data: &[u8],
+    outgoing_command: *mut ENetOutgoingCommand,
+) {
+    let message = enet_peer_send_protocol_message(peer, kind, data);
+    if message.is_null() {
+        return;
+    }
+    (*peer)
+        .outgoing_commands
+        .assume_init_mut()
+        .retain(|&queued| queued != message);
+    for queue in [
+        (*peer).outgoing_commands.assume_init_mut(),
+        (*peer).outgoing_send_reliable_commands.assume_init_mut(),
+    ] {
+        if let Some(position) = queue.iter().position(|&queued| queued == outgoing_command) {
+            queue.insert(position, message);
+            return;
+        }
+    }
+    // the command isn't queued, so queue the message as usual
+    enet_peer_insert_outgoing_command(peer, &raw mut (*peer).outgoing_commands, message);
+}
+/// Send the payload given to [`Host::connect_with_payload`](`crate::Host::connect_with_payload`)
+/// after the connect command, each time the command is sent.
+pub(crate) unsafe fn enet_peer_send_connect_payload<S: Socket>(peer: *mut ENetPeer<S>) {
+    if (*peer).state != ENET_PEER_STATE_CONNECTING
+        || (*(*peer).host).protocol_version == ProtocolVersion::Enet
+        || (*peer).connect_payload.assume_init_ref().is_empty()
+    {
+        return;
+    }
+    let payload = (*peer).connect_payload.assume_init_ref().clone();
+    enet_peer_send_protocol_message(peer, ENET_PROTOCOL_MESSAGE_CONNECT_PAYLOAD, &payload);
+}
+/// Present the session ticket for resuming a session with the host connected to, after the
+/// connect command, each time the command is sent.
+pub(crate) unsafe fn enet_peer_send_resume_ticket<S: Socket>(peer: *mut ENetPeer<S>) {
+    if (*peer).state != ENET_PEER_STATE_CONNECTING {
+        return;
+    }
+    if let Some(ticket) = (*peer).resume_ticket {
+        enet_peer_send_protocol_message(peer, ENET_PROTOCOL_MESSAGE_RESUME_SESSION, &ticket);
+    }
+}
+/// Accept a connection held back by
+/// [`HostSettings::connect_approval`](`crate::HostSettings::connect_approval`), sending the verify
+/// connect command with at most `channel_count` channels, preceded by `data` for peers which
+/// speak [`ProtocolVersion::Extended`].
+pub(crate) unsafe fn enet_peer_accept<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    channel_count: usize,
+    data: u32,
+) {
+    let Some(mut verify_command) = (*peer).pending_verify.take() else {
+        return;
+    };
+    if let Some(address) = (*peer).address.assume_init_ref() {
+        enet_host_audit_connect((*peer).host, address, ConnectOutcome::Accepted);
+    }
+    let channel_count = channel_count.clamp(
+        PROTOCOL_MINIMUM_CHANNEL_COUNT as usize,
+        (*peer).channel_count,
+    );
+    if channel_count < (*peer).channel_count {
+        let channels: *mut ENetChannel =
+            enet_malloc(Layout::array::<ENetChannel>(channel_count).unwrap()).cast();
+        core::ptr::copy_nonoverlapping((*peer).channels, channels, channel_count);
+        enet_free(
+            (*peer).channels.cast(),
+            Layout::array::<ENetChannel>((*peer).channel_count).unwrap(),
+        );
+        for index in 0..channel_count {
+            let channel = channels.add(index);
+            enet_list_clear(&raw mut (*channel).incoming_reliable_commands);
+            enet_list_clear(&raw mut (*channel).incoming_unreliable_commands);
+        }
+        (*peer).channels = channels;
+        (*peer).channel_count = channel_count;
+        verify_command.verify_connect.channel_count = (channel_count as u32).to_be();
+    }
+    let verify = enet_peer_queue_outgoing_command(
+        peer,
+        &raw const verify_command,
         core::ptr::null_mut(),
         0_i32 as u32,
         0_i32 as u16,
     );
+    if (*peer).protocol_version > ProtocolVersion::Enet {
+        (*peer).accept_data = Some(data);
+        enet_peer_send_accept_data(peer, verify);
+    }
+}
+/// Send the data given to [`Peer::accept`](`crate::Peer::accept`) ahead of the verify connect
+/// command, as it must reach the connecting peer first.
+pub(crate) unsafe fn enet_peer_send_accept_data<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    verify: *mut ENetOutgoingCommand,
+) {
+    if let Some(data) = (*peer).accept_data {
+        if (*peer).state == ENET_PEER_STATE_ACKNOWLEDGING_CONNECT && !verify.is_null() {
+            enet_peer_send_protocol_message_before(
+                peer,
+                ENET_PROTOCOL_MESSAGE_ACCEPT_DATA,
+                &data.to_be_bytes(),
+                verify,
+            );
+        }
+    }
+}
+/// Disconnect a peer like [`enet_peer_disconnect`], sending it an encoded address to connect to
+/// instead, returning `false` if the peer isn't connected.
+pub(crate) unsafe fn enet_peer_redirect<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    address: Vec<u8>,
+    data: u32,
+) -> bool {
+    if (*peer).state != ENET_PEER_STATE_CONNECTED
+        && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER
+    {
+        return false;
+    }
+    enet_peer_disconnect(peer, data);
+    *(*peer).redirect.assume_init_mut() = address;
+    // the disconnect command is the only command left after resetting the queues
+    let disconnect = (*peer).outgoing_commands.assume_init_ref().front().copied();
+    enet_peer_send_redirect(peer, disconnect.unwrap_or(core::ptr::null_mut()));
+    true
+}
+/// Send the address given to [`Peer::redirect`](`crate::Peer::redirect`) ahead of the disconnect
+/// command, as it must reach the peer first.
+pub(crate) unsafe fn enet_peer_send_redirect<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    disconnect: *mut ENetOutgoingCommand,
+) {
+    if (*peer).state == ENET_PEER_STATE_DISCONNECTING
+        && !(*peer).redirect.assume_init_ref().is_empty()
+    {
+        let address = (*peer).redirect.assume_init_ref().clone();
+        enet_peer_send_protocol_message_before(
+            peer,
+            ENET_PROTOCOL_MESSAGE_REDIRECT,
+            &address,
+            disconnect,
+        );
+    }
+}
+/// Add an address the peer can be reached at, along with the address it's connected with.
+pub(crate) unsafe fn enet_peer_add_path<S: Socket>(peer: *mut ENetPeer<S>, address: S::Address) {
+    let paths = (*peer).paths.assume_init_mut();
+    if paths.is_empty() {
+        if let Some(current) = (*peer).address.assume_init_ref() {
+            paths.push(ENetPath {
+                address: current.clone(),
+                nonce: None,
+                round_trip_time: None,
+            });
+        }
+    }
+    if !paths.iter().any(|path| path.address.same(&address)) {
+        paths.push(ENetPath {
+            address,
+            nonce: None,
+            round_trip_time: None,
+        });
+    }
+}
+/// Update a path's round trip time from the answer to a probe sent at
+/// [`ENetPeer::path_probe_time`].
+pub(crate) unsafe fn enet_peer_path_response<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    nonce: &[u8],
+    receive_time: u32,
+) {
+    let sample = receive_time.wrapping_sub((*peer).path_probe_time);
+    if let Some(path) = (*peer)
+        .paths
+        .assume_init_mut()
+        .iter_mut()
+        .find(|path| path.nonce.is_some_and(|path_nonce| path_nonce == nonce))
+    {
+        path.nonce = None;
+        path.round_trip_time = Some(path.round_trip_time.map_or(sample, |round_trip_time| {
+            round_trip_time - round_trip_time / 4 + sample / 4
+        }));
+    }
+}
+/// Send the peer a ticket for resuming its session, starting a new session unless it resumed
+/// one. Tokens are secure random bytes replaced each time, so a ticket can only be presented
+/// once, and can't be guessed from another.
+pub(crate) unsafe fn enet_peer_issue_session_ticket<S: Socket>(peer: *mut ENetPeer<S>) {
+    let host = (*peer).host;
+    if (*host).session_resumption.is_none() {
+        return;
+    }
+    let id = (*peer).session.map_or_else(
+        || {
+            let id = (*host).next_session_id;
+            (*host).next_session_id = id.wrapping_add(1);
+            id
+        },
+        |session| session.id,
+    );
+    let mut token = [0; 8];
+    enet_host_secure_random(host, &mut token);
+    (*peer).session = Some(ENetSession { id, token });
+    let mut ticket = [0; 16];
+    ticket[..8].copy_from_slice(&id.to_be_bytes());
+    ticket[8..].copy_from_slice(&token);
+    enet_peer_send_protocol_message(peer, ENET_PROTOCOL_MESSAGE_SESSION_TICKET, &ticket);
+}
+/// Make the peer's session resumable after a graceful disconnect.
+pub(crate) unsafe fn enet_peer_end_session<S: Socket>(peer: *mut ENetPeer<S>) {
+    let host = (*peer).host;
+    let Some(session) = (*peer).session.take() else {
+        return;
+    };
+    enet_host_expire_sessions(host);
+    let sessions = (*host).resumable_sessions.assume_init_mut();
+    if sessions.len() >= (*host).peer_count {
+        sessions.remove(0);
+    }
+    sessions.push(ENetResumableSession {
+        session,
+        end_time: (*host).service_time,
+    });
+}
+/// Compare two byte strings in time depending only on their lengths, so comparing against a secret
+/// doesn't reveal how much of it was guessed.
+fn enet_constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && core::hint::black_box(
+            a.iter()
+                .zip(b)
+                .fold(0, |difference, (a, b)| difference | (a ^ b)),
+        ) == 0
+}
+/// Resume the session a connecting peer presented a ticket for, if it's still resumable.
+pub(crate) unsafe fn enet_peer_resume_session<S: Socket>(peer: *mut ENetPeer<S>, ticket: &[u8]) {
+    let host = (*peer).host;
+    let Ok(id) = ticket[..8].try_into().map(u64::from_be_bytes) else {
+        return;
+    };
+    enet_host_expire_sessions(host);
+    let sessions = (*host).resumable_sessions.assume_init_mut();
+    if let Some(index) = sessions.iter().position(|resumable| {
+        resumable.session.id == id && enet_constant_time_eq(&resumable.session.token, &ticket[8..])
+    }) {
+        (*peer).session = Some(sessions.remove(index).session);
+        (*peer).resumed = true;
+    }
+}
+/// Update the peer's estimated clock offset from a clock response, received at `receive_time` for
+/// a request sent at `request_time`, which the peer handled at `remote_time`.
+pub(crate) unsafe fn enet_peer_clock_sample<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    request_time: u32,
+    remote_time: u32,
+    receive_time: u32,
+) {
+    let round_trip_time = receive_time.wrapping_sub(request_time);
+    let sample =
+        i64::from(remote_time.wrapping_sub(request_time.wrapping_add(round_trip_time / 2)) as i32);
+    (*peer).clock_offset = Some((*peer).clock_offset.map_or(sample, |clock_offset| {
+        clock_offset + (sample - clock_offset) / 8
+    }));
 }
 pub(crate) unsafe fn enet_peer_ping_interval<S: Socket>(
     peer: *mut ENetPeer<S>,
@@ -564,7 +1637,7 @@ pub(crate) unsafe fn enet_peer_disconnect_now<S: Socket>(peer: *mut ENetPeer<S>,
         command.disconnect.data = data.to_be();
         enet_peer_queue_outgoing_command(
             peer,
-            &command,
+            &raw const command,
             core::ptr::null_mut(),
             0_i32 as u32,
             0_i32 as u16,
@@ -603,7 +1676,7 @@ pub(crate) unsafe fn enet_peer_disconnect<S: Socket>(peer: *mut ENetPeer<S>, dat
     }
     enet_peer_queue_outgoing_command(
         peer,
-        &command,
+        &raw const command,
         core::ptr::null_mut(),
         0_i32 as u32,
         0_i32 as u16,
@@ -619,12 +1692,12 @@ pub(crate) unsafe fn enet_peer_disconnect<S: Socket>(peer: *mut ENetPeer<S>, dat
     };
 }
 pub(crate) unsafe fn enet_peer_has_outgoing_commands<S: Socket>(peer: *mut ENetPeer<S>) -> i32 {
-    if (*peer).outgoing_commands.sentinel.next
-        == core::ptr::addr_of_mut!((*peer).outgoing_commands.sentinel)
-        && (*peer).outgoing_send_reliable_commands.sentinel.next
-            == core::ptr::addr_of_mut!((*peer).outgoing_send_reliable_commands.sentinel)
-        && (*peer).sent_reliable_commands.sentinel.next
-            == core::ptr::addr_of_mut!((*peer).sent_reliable_commands.sentinel)
+    if (*peer).outgoing_commands.assume_init_ref().is_empty()
+        && (*peer)
+            .outgoing_send_reliable_commands
+            .assume_init_ref()
+            .is_empty()
+        && (*peer).sent_reliable_commands.assume_init_ref().is_empty()
     {
         return 0_i32;
     }
@@ -645,7 +1718,7 @@ pub(crate) unsafe fn enet_peer_queue_acknowledgement<S: Socket>(
     peer: *mut ENetPeer<S>,
     command: *const ENetProtocol,
     sent_time: u16,
-) -> *mut ENetAcknowledgement {
+) {
     if ((*command).header.channel_id as usize) < (*peer).channel_count {
         let channel: *mut ENetChannel =
             ((*peer).channels).offset((*command).header.channel_id as isize);
@@ -662,21 +1735,23 @@ pub(crate) unsafe fn enet_peer_queue_acknowledgement<S: Socket>(
             >= current_window as i32 + PEER_FREE_RELIABLE_WINDOWS as i32 - 1_i32
             && reliable_window as i32 <= current_window as i32 + PEER_FREE_RELIABLE_WINDOWS as i32
         {
-            return core::ptr::null_mut();
+            return;
         }
     }
-    let acknowledgement: *mut ENetAcknowledgement =
-        enet_malloc(Layout::new::<ENetAcknowledgement>()).cast();
     (*peer).outgoing_data_total = ((*peer).outgoing_data_total as u64)
         .wrapping_add(::core::mem::size_of::<ENetProtocolAcknowledge>() as u64)
         as u32;
-    (*acknowledgement).sent_time = sent_time as u32;
-    (*acknowledgement).command = *command;
-    enet_list_insert(
-        &mut (*peer).acknowledgements.sentinel,
-        acknowledgement.cast(),
-    );
-    acknowledgement
+    if (*peer).acknowledgement_time.is_none() {
+        (*peer).acknowledgement_time = Some((*(*peer).host).service_time);
+    }
+    (*(*peer).host).acknowledging_peer = peer;
+    (*peer)
+        .acknowledgements
+        .assume_init_mut()
+        .push_back(ENetAcknowledgement {
+            sent_time: sent_time as u32,
+            command: *command,
+        });
 }
 pub(crate) unsafe fn enet_peer_setup_outgoing_command<S: Socket>(
     peer: *mut ENetPeer<S>,
@@ -685,7 +1760,16 @@ pub(crate) unsafe fn enet_peer_setup_outgoing_command<S: Socket>(
     (*peer).outgoing_data_total = ((*peer).outgoing_data_total as usize).wrapping_add(
         (enet_protocol_command_size((*outgoing_command).command.header.command))
             .wrapping_add((*outgoing_command).fragment_length as usize),
-    ) as u32 as u32;
+    ) as u32;
+    (*peer).memory_usage =
+        ((*peer).memory_usage).wrapping_add(enet_peer_outgoing_command_memory(outgoing_command));
+    if enet_peer_reports_packet(outgoing_command) {
+        *(*peer)
+            .reported_packet_commands
+            .assume_init_mut()
+            .entry((*outgoing_command).packet as usize)
+            .or_insert(0) += 1;
+    }
     if (*outgoing_command).command.header.channel_id as i32 == 0xff_i32 {
         (*peer).outgoing_reliable_sequence_number =
             ((*peer).outgoing_reliable_sequence_number).wrapping_add(1);
@@ -724,12 +1808,20 @@ pub(crate) unsafe fn enet_peer_setup_outgoing_command<S: Socket>(
         }
     }
     (*outgoing_command).send_attempts = 0_i32 as u16;
+    (*outgoing_command).later_acknowledgements = 0;
     (*outgoing_command).sent_time = 0_i32 as u32;
     (*outgoing_command).round_trip_timeout = 0_i32 as u32;
     (*outgoing_command).command.header.reliable_sequence_number =
         (*outgoing_command).reliable_sequence_number.to_be();
     (*(*peer).host).total_queued = ((*(*peer).host).total_queued).wrapping_add(1);
     (*outgoing_command).queue_time = (*(*peer).host).total_queued;
+    // only tagged packets report back when they were queued
+    (*outgoing_command).queued_time =
+        if !(*outgoing_command).packet.is_null() && (*(*outgoing_command).packet).tag.is_some() {
+            (*(*peer).host).time.assume_init_ref()()
+        } else {
+            Duration::ZERO
+        };
     match (*outgoing_command).command.header.command as i32 & ENET_PROTOCOL_COMMAND_MASK as i32 {
         7 => {
             (*outgoing_command)
@@ -746,22 +1838,48 @@ pub(crate) unsafe fn enet_peer_setup_outgoing_command<S: Socket>(
         }
         _ => {}
     }
+    if (*peer).coalesce_start.is_none() && enet_peer_coalesce_delay(peer) > 0 {
+        (*peer).coalesce_start = Some((*(*peer).host).service_time);
+    }
     if (*outgoing_command).command.header.command as i32
         & ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE as i32
         != 0_i32
         && !((*outgoing_command).packet).is_null()
     {
-        enet_list_insert(
-            &mut (*peer).outgoing_send_reliable_commands.sentinel,
-            outgoing_command.cast(),
+        enet_peer_insert_outgoing_command(
+            peer,
+            &raw mut (*peer).outgoing_send_reliable_commands,
+            outgoing_command,
         );
     } else {
-        enet_list_insert(
-            &mut (*peer).outgoing_commands.sentinel,
-            outgoing_command.cast(),
+        enet_peer_insert_outgoing_command(
+            peer,
+            &raw mut (*peer).outgoing_commands,
+            outgoing_command,
         );
     };
 }
+/// Queue an outgoing command behind every command of an equal or higher channel priority, so
+/// commands are sent in priority order, and in the order they were queued within a priority.
+unsafe fn enet_peer_insert_outgoing_command<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    queue: *mut MaybeUninit<VecDeque<*mut ENetOutgoingCommand>>,
+    outgoing_command: *mut ENetOutgoingCommand,
+) {
+    let priority =
+        enet_host_channel_priority((*peer).host, (*outgoing_command).command.header.channel_id);
+    let queue = (*queue).assume_init_mut();
+    let mut position = queue.len();
+    while position > 0
+        && enet_host_channel_priority(
+            (*peer).host,
+            (*queue[position - 1]).command.header.channel_id,
+        ) < priority
+    {
+        position -= 1;
+    }
+    queue.insert(position, outgoing_command);
+}
 pub(crate) unsafe fn enet_peer_queue_outgoing_command<S: Socket>(
     peer: *mut ENetPeer<S>,
     command: *const ENetProtocol,
@@ -769,8 +1887,10 @@ pub(crate) unsafe fn enet_peer_queue_outgoing_command<S: Socket>(
     offset: u32,
     length: u16,
 ) -> *mut ENetOutgoingCommand {
-    let outgoing_command: *mut ENetOutgoingCommand =
-        enet_malloc(Layout::new::<ENetOutgoingCommand>()).cast();
+    let outgoing_command: *mut ENetOutgoingCommand = (*(*peer).host)
+        .outgoing_command_pool
+        .assume_init_mut()
+        .acquire();
     if outgoing_command.is_null() {
         return core::ptr::null_mut();
     }
@@ -779,7 +1899,7 @@ pub(crate) unsafe fn enet_peer_queue_outgoing_command<S: Socket>(
     (*outgoing_command).fragment_length = length;
     (*outgoing_command).packet = packet;
     if !packet.is_null() {
-        (*packet).reference_count = ((*packet).reference_count).wrapping_add(1);
+        enet_packet_acquire(packet, 1);
     }
     enet_peer_setup_outgoing_command(peer, outgoing_command);
     outgoing_command
@@ -813,19 +1933,11 @@ pub(crate) unsafe fn enet_peer_dispatch_incoming_unreliable_commands<S: Socket>(
                 } else {
                     if start_command != current_command {
                         enet_list_move(
-                            &mut (*peer).dispatched_commands.sentinel,
+                            &raw mut (*peer).dispatched_commands.sentinel,
                             start_command.cast(),
                             (*current_command).previous.cast(),
                         );
-                        if (*peer).flags as i32 & ENET_PEER_FLAG_NEEDS_DISPATCH as i32 == 0 {
-                            enet_list_insert(
-                                &mut (*(*peer).host).dispatch_queue.sentinel,
-                                core::ptr::addr_of_mut!((*peer).dispatch_list).cast(),
-                            );
-                            (*peer).flags = ((*peer).flags as i32
-                                | ENET_PEER_FLAG_NEEDS_DISPATCH as i32)
-                                as u16;
-                        }
+                        enet_peer_queue_dispatch(peer);
                         dropped_command = current_command;
                     } else if dropped_command != current_command {
                         dropped_command = (*current_command).previous;
@@ -854,18 +1966,11 @@ pub(crate) unsafe fn enet_peer_dispatch_incoming_unreliable_commands<S: Socket>(
                 dropped_command = (*current_command).next;
                 if start_command != current_command {
                     enet_list_move(
-                        &mut (*peer).dispatched_commands.sentinel,
+                        &raw mut (*peer).dispatched_commands.sentinel,
                         start_command.cast(),
                         (*current_command).previous.cast(),
                     );
-                    if (*peer).flags as i32 & ENET_PEER_FLAG_NEEDS_DISPATCH as i32 == 0 {
-                        enet_list_insert(
-                            &mut (*(*peer).host).dispatch_queue.sentinel,
-                            core::ptr::addr_of_mut!((*peer).dispatch_list).cast(),
-                        );
-                        (*peer).flags =
-                            ((*peer).flags as i32 | ENET_PEER_FLAG_NEEDS_DISPATCH as i32) as u16;
-                    }
+                    enet_peer_queue_dispatch(peer);
                 }
                 current_block_22 = 13472856163611868459;
             }
@@ -880,21 +1985,16 @@ pub(crate) unsafe fn enet_peer_dispatch_incoming_unreliable_commands<S: Socket>(
     }
     if start_command != current_command {
         enet_list_move(
-            &mut (*peer).dispatched_commands.sentinel,
+            &raw mut (*peer).dispatched_commands.sentinel,
             start_command.cast(),
             (*current_command).previous.cast(),
         );
-        if (*peer).flags as i32 & ENET_PEER_FLAG_NEEDS_DISPATCH as i32 == 0 {
-            enet_list_insert(
-                &mut (*(*peer).host).dispatch_queue.sentinel,
-                core::ptr::addr_of_mut!((*peer).dispatch_list).cast(),
-            );
-            (*peer).flags = ((*peer).flags as i32 | ENET_PEER_FLAG_NEEDS_DISPATCH as i32) as u16;
-        }
+        enet_peer_queue_dispatch(peer);
         dropped_command = current_command;
     }
     enet_peer_remove_incoming_commands(
-        &mut (*channel).incoming_unreliable_commands,
+        peer,
+        &raw mut (*channel).incoming_unreliable_commands,
         (*channel).incoming_unreliable_commands.sentinel.next,
         dropped_command,
         queued_command,
@@ -930,17 +2030,11 @@ pub(crate) unsafe fn enet_peer_dispatch_incoming_reliable_commands<S: Socket>(
     }
     (*channel).incoming_unreliable_sequence_number = 0_i32 as u16;
     enet_list_move(
-        &mut (*peer).dispatched_commands.sentinel,
+        &raw mut (*peer).dispatched_commands.sentinel,
         ((*channel).incoming_reliable_commands.sentinel.next).cast(),
         ((*current_command).previous).cast(),
     );
-    if (*peer).flags as i32 & ENET_PEER_FLAG_NEEDS_DISPATCH as i32 == 0 {
-        enet_list_insert(
-            &mut (*(*peer).host).dispatch_queue.sentinel,
-            core::ptr::addr_of_mut!((*peer).dispatch_list).cast(),
-        );
-        (*peer).flags = ((*peer).flags as i32 | ENET_PEER_FLAG_NEEDS_DISPATCH as i32) as u16;
-    }
+    enet_peer_queue_dispatch(peer);
     if (*channel).incoming_unreliable_commands.sentinel.next
         != core::ptr::addr_of_mut!((*channel).incoming_unreliable_commands.sentinel)
     {
@@ -957,8 +2051,8 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
 ) -> *mut ENetIncomingCommand {
     static mut DUMMY_COMMAND: ENetIncomingCommand = ENetIncomingCommand {
         incoming_command_list: ENetListNode {
-            next: 0 as *mut ENetListNode,
-            previous: 0 as *mut ENetListNode,
+            next: core::ptr::null_mut::<ENetListNode>(),
+            previous: core::ptr::null_mut::<ENetListNode>(),
         },
         reliable_sequence_number: 0,
         unreliable_sequence_number: 0,
@@ -971,6 +2065,8 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
         },
         fragment_count: 0,
         fragments_remaining: 0,
+        fragment_order: 0,
+        received_time: Duration::ZERO,
         fragments: core::ptr::null_mut(),
         packet: core::ptr::null_mut(),
     };
@@ -1017,7 +2113,8 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                     current_block = 4379360700607281851;
                     match current_block {
                         10107555224945550073 => {
-                            current_command = &mut (*channel).incoming_unreliable_commands.sentinel;
+                            current_command =
+                                &raw mut (*channel).incoming_unreliable_commands.sentinel;
                             current_block = 7746103178988627676;
                         }
                         4379360700607281851 => {
@@ -1158,15 +2255,21 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                     match current_block {
                         9207730764507465628 => {}
                         _ => {
-                            if (*peer).total_waiting_data >= (*(*peer).host).maximum_waiting_data {
+                            if (*peer).total_waiting_data >= (*(*peer).host).maximum_waiting_data
+                                || (*(*peer).host).memory_budget_policy
+                                    == MemoryBudgetPolicy::Throttle
+                                    && enet_peer_over_memory_budget(peer)
+                            {
                                 current_block = 15492018734234176694;
                             } else {
                                 packet = enet_packet_create(data, data_length, flags);
                                 if packet.is_null() {
                                     current_block = 15492018734234176694;
                                 } else {
-                                    incoming_command =
-                                        enet_malloc(Layout::new::<ENetIncomingCommand>()).cast();
+                                    incoming_command = (*(*peer).host)
+                                        .incoming_command_pool
+                                        .assume_init_mut()
+                                        .acquire();
                                     if incoming_command.is_null() {
                                         current_block = 15492018734234176694;
                                     } else {
@@ -1177,6 +2280,9 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                                         (*incoming_command).command = *command;
                                         (*incoming_command).fragment_count = fragment_count;
                                         (*incoming_command).fragments_remaining = fragment_count;
+                                        (*incoming_command).fragment_order = 0;
+                                        (*incoming_command).received_time =
+                                            (*(*peer).host).receive_time;
                                         (*incoming_command).packet = packet;
                                         (*incoming_command).fragments = core::ptr::null_mut();
                                         if fragment_count > 0_i32 as u32 {
@@ -1193,10 +2299,10 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                                                 .cast();
                                             }
                                             if ((*incoming_command).fragments).is_null() {
-                                                enet_free(
-                                                    incoming_command.cast(),
-                                                    Layout::new::<ENetIncomingCommand>(),
-                                                );
+                                                (*(*peer).host)
+                                                    .incoming_command_pool
+                                                    .assume_init_mut()
+                                                    .release(incoming_command);
                                                 current_block = 15492018734234176694;
                                             } else {
                                                 write_bytes(
@@ -1217,12 +2323,15 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                                         }
                                         if current_block != 15492018734234176694 {
                                             if !packet.is_null() {
-                                                (*packet).reference_count =
-                                                    ((*packet).reference_count).wrapping_add(1);
+                                                enet_packet_acquire(packet, 1);
                                                 (*peer).total_waiting_data = (*peer)
                                                     .total_waiting_data
                                                     .wrapping_add((*packet).data_length);
                                             }
+                                            (*peer).memory_usage = ((*peer).memory_usage)
+                                                .wrapping_add(enet_peer_incoming_command_memory(
+                                                    incoming_command,
+                                                ));
                                             enet_list_insert(
                                                 (*current_command).next,
                                                 incoming_command.cast(),
@@ -1257,7 +2366,8 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                     current_block = 1195130990526578986;
                     match current_block {
                         10107555224945550073 => {
-                            current_command = &mut (*channel).incoming_unreliable_commands.sentinel;
+                            current_command =
+                                &raw mut (*channel).incoming_unreliable_commands.sentinel;
                             current_block = 7746103178988627676;
                         }
                         4379360700607281851 => {
@@ -1398,15 +2508,21 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                     match current_block {
                         9207730764507465628 => {}
                         _ => {
-                            if (*peer).total_waiting_data >= (*(*peer).host).maximum_waiting_data {
+                            if (*peer).total_waiting_data >= (*(*peer).host).maximum_waiting_data
+                                || (*(*peer).host).memory_budget_policy
+                                    == MemoryBudgetPolicy::Throttle
+                                    && enet_peer_over_memory_budget(peer)
+                            {
                                 current_block = 15492018734234176694;
                             } else {
                                 packet = enet_packet_create(data, data_length, flags);
                                 if packet.is_null() {
                                     current_block = 15492018734234176694;
                                 } else {
-                                    incoming_command =
-                                        enet_malloc(Layout::new::<ENetIncomingCommand>()).cast();
+                                    incoming_command = (*(*peer).host)
+                                        .incoming_command_pool
+                                        .assume_init_mut()
+                                        .acquire();
                                     if incoming_command.is_null() {
                                         current_block = 15492018734234176694;
                                     } else {
@@ -1417,6 +2533,9 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                                         (*incoming_command).command = *command;
                                         (*incoming_command).fragment_count = fragment_count;
                                         (*incoming_command).fragments_remaining = fragment_count;
+                                        (*incoming_command).fragment_order = 0;
+                                        (*incoming_command).received_time =
+                                            (*(*peer).host).receive_time;
                                         (*incoming_command).packet = packet;
                                         (*incoming_command).fragments = core::ptr::null_mut();
                                         if fragment_count > 0_i32 as u32 {
@@ -1433,10 +2552,10 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                                                 .cast();
                                             }
                                             if ((*incoming_command).fragments).is_null() {
-                                                enet_free(
-                                                    incoming_command.cast(),
-                                                    Layout::new::<ENetIncomingCommand>(),
-                                                );
+                                                (*(*peer).host)
+                                                    .incoming_command_pool
+                                                    .assume_init_mut()
+                                                    .release(incoming_command);
                                                 current_block = 15492018734234176694;
                                             } else {
                                                 write_bytes(
@@ -1457,12 +2576,15 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                                         }
                                         if current_block != 15492018734234176694 {
                                             if !packet.is_null() {
-                                                (*packet).reference_count =
-                                                    ((*packet).reference_count).wrapping_add(1);
+                                                enet_packet_acquire(packet, 1);
                                                 (*peer).total_waiting_data = (*peer)
                                                     .total_waiting_data
                                                     .wrapping_add((*packet).data_length);
                                             }
+                                            (*peer).memory_usage = ((*peer).memory_usage)
+                                                .wrapping_add(enet_peer_incoming_command_memory(
+                                                    incoming_command,
+                                                ));
                                             enet_list_insert(
                                                 (*current_command).next,
                                                 incoming_command.cast(),
@@ -1497,7 +2619,8 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                     current_block = 10107555224945550073;
                     match current_block {
                         10107555224945550073 => {
-                            current_command = &mut (*channel).incoming_unreliable_commands.sentinel;
+                            current_command =
+                                &raw mut (*channel).incoming_unreliable_commands.sentinel;
                             current_block = 7746103178988627676;
                         }
                         4379360700607281851 => {
@@ -1638,15 +2761,21 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                     match current_block {
                         9207730764507465628 => {}
                         _ => {
-                            if (*peer).total_waiting_data >= (*(*peer).host).maximum_waiting_data {
+                            if (*peer).total_waiting_data >= (*(*peer).host).maximum_waiting_data
+                                || (*(*peer).host).memory_budget_policy
+                                    == MemoryBudgetPolicy::Throttle
+                                    && enet_peer_over_memory_budget(peer)
+                            {
                                 current_block = 15492018734234176694;
                             } else {
                                 packet = enet_packet_create(data, data_length, flags);
                                 if packet.is_null() {
                                     current_block = 15492018734234176694;
                                 } else {
-                                    incoming_command =
-                                        enet_malloc(Layout::new::<ENetIncomingCommand>()).cast();
+                                    incoming_command = (*(*peer).host)
+                                        .incoming_command_pool
+                                        .assume_init_mut()
+                                        .acquire();
                                     if incoming_command.is_null() {
                                         current_block = 15492018734234176694;
                                     } else {
@@ -1657,6 +2786,9 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                                         (*incoming_command).command = *command;
                                         (*incoming_command).fragment_count = fragment_count;
                                         (*incoming_command).fragments_remaining = fragment_count;
+                                        (*incoming_command).fragment_order = 0;
+                                        (*incoming_command).received_time =
+                                            (*(*peer).host).receive_time;
                                         (*incoming_command).packet = packet;
                                         (*incoming_command).fragments = core::ptr::null_mut();
                                         if fragment_count > 0_i32 as u32 {
@@ -1673,10 +2805,10 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                                                 .cast();
                                             }
                                             if ((*incoming_command).fragments).is_null() {
-                                                enet_free(
-                                                    incoming_command.cast(),
-                                                    Layout::new::<ENetIncomingCommand>(),
-                                                );
+                                                (*(*peer).host)
+                                                    .incoming_command_pool
+                                                    .assume_init_mut()
+                                                    .release(incoming_command);
                                                 current_block = 15492018734234176694;
                                             } else {
                                                 write_bytes(
@@ -1697,12 +2829,15 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
                                         }
                                         if current_block != 15492018734234176694 {
                                             if !packet.is_null() {
-                                                (*packet).reference_count =
-                                                    ((*packet).reference_count).wrapping_add(1);
+                                                enet_packet_acquire(packet, 1);
                                                 (*peer).total_waiting_data = (*peer)
                                                     .total_waiting_data
                                                     .wrapping_add((*packet).data_length);
                                             }
+                                            (*peer).memory_usage = ((*peer).memory_usage)
+                                                .wrapping_add(enet_peer_incoming_command_memory(
+                                                    incoming_command,
+                                                ));
                                             enet_list_insert(
                                                 (*current_command).next,
                                                 incoming_command.cast(),
@@ -1741,14 +2876,30 @@ pub(crate) unsafe fn enet_peer_queue_incoming_command<S: Socket>(
     }
     if let 9207730764507465628 = current_block {
         if fragment_count <= 0_i32 as u32 {
-            if !packet.is_null() && (*packet).reference_count == 0_i32 as usize {
+            if !packet.is_null() && enet_packet_reference_count(packet) == 0 {
                 enet_packet_destroy(packet);
             }
             return addr_of_mut!(DUMMY_COMMAND);
         }
     }
-    if !packet.is_null() && (*packet).reference_count == 0_i32 as usize {
+    if !packet.is_null() && enet_packet_reference_count(packet) == 0 {
         enet_packet_destroy(packet);
     }
     core::ptr::null_mut()
 }
+pub(crate) unsafe fn enet_peer_id<S: Socket>(peer: *mut ENetPeer<S>) -> PeerID {
+    PeerID {
+        index: peer.offset_from((*(*peer).host).peers) as usize,
+        generation: (*peer).generation,
+    }
+}
+/// Queue a peer to have its events dispatched by its host, if it isn't queued already.
+pub(crate) unsafe fn enet_peer_queue_dispatch<S: Socket>(peer: *mut ENetPeer<S>) {
+    if (*peer).flags as i32 & ENET_PEER_FLAG_NEEDS_DISPATCH as i32 == 0 {
+        (*(*peer).host)
+            .dispatch_queue
+            .assume_init_mut()
+            .push_back(usize::from((*peer).incoming_peer_id));
+        (*peer).flags = ((*peer).flags as i32 | ENET_PEER_FLAG_NEEDS_DISPATCH as i32) as u16;
+    }
+}
@@ -0,0 +1,82 @@
+use core::alloc::Layout;
+
+use crate::{enet_free, enet_malloc, PoolStats, Vec};
+
+/// A free list of command allocations, so steady traffic reuses commands instead of allocating
+/// one per packet.
+pub(crate) struct ENetPool<T> {
+    free: Vec<*mut T>,
+    limit: usize,
+    allocated: usize,
+    allocations: u64,
+    reuses: u64,
+}
+
+impl<T> ENetPool<T> {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            free: Vec::new(),
+            limit,
+            allocated: 0,
+            allocations: 0,
+            reuses: 0,
+        }
+    }
+
+    pub(crate) unsafe fn acquire(&mut self) -> *mut T {
+        if let Some(ptr) = self.free.pop() {
+            self.reuses += 1;
+            ptr
+        } else {
+            self.allocated += 1;
+            self.allocations += 1;
+            enet_malloc(Layout::new::<T>()).cast()
+        }
+    }
+
+    /// Acquire `count` allocations at once, appending them to `acquired`.
+    pub(crate) unsafe fn acquire_many(&mut self, count: usize, acquired: &mut Vec<*mut T>) {
+        let reused = count.min(self.free.len());
+        acquired.extend(self.free.drain(self.free.len() - reused..));
+        self.reuses += reused as u64;
+        let allocated = count - reused;
+        self.allocated += allocated;
+        self.allocations += allocated as u64;
+        acquired.extend((0..allocated).map(|_| enet_malloc(Layout::new::<T>()).cast::<T>()));
+    }
+
+    pub(crate) unsafe fn release(&mut self, ptr: *mut T) {
+        if self.free.len() < self.limit {
+            self.free.push(ptr);
+        } else {
+            self.allocated -= 1;
+            enet_free(ptr.cast(), Layout::new::<T>());
+        }
+    }
+
+    pub(crate) unsafe fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        while self.free.len() > limit {
+            let ptr = self.free.pop().unwrap();
+            self.allocated -= 1;
+            enet_free(ptr.cast(), Layout::new::<T>());
+        }
+    }
+
+    pub(crate) fn stats(&self) -> PoolStats {
+        PoolStats {
+            allocated: self.allocated,
+            available: self.free.len(),
+            allocations: self.allocations,
+            reuses: self.reuses,
+        }
+    }
+}
+
+impl<T> Drop for ENetPool<T> {
+    fn drop(&mut self) {
+        for ptr in self.free.drain(..) {
+            unsafe { enet_free(ptr.cast(), Layout::new::<T>()) };
+        }
+    }
+}
@@ -1,38 +1,57 @@
-use core::{
-    alloc::Layout,
-    ptr::{copy_nonoverlapping, write_bytes},
-};
-use std::{mem, ptr};
-use std::mem::offset_of;
+use crate::c::{enet_host_random, enet_host_secure_random};
 use crate::{
     consts::{
         BUFFER_MAXIMUM, HOST_BANDWIDTH_THROTTLE_INTERVAL, PEER_FREE_RELIABLE_WINDOWS,
-        PEER_FREE_UNSEQUENCED_WINDOWS, PEER_PACKET_LOSS_INTERVAL, PEER_PACKET_LOSS_SCALE,
-        PEER_PACKET_THROTTLE_COUNTER, PEER_PACKET_THROTTLE_SCALE, PEER_RELIABLE_WINDOWS,
-        PEER_RELIABLE_WINDOW_SIZE, PEER_UNSEQUENCED_WINDOW_SIZE, PEER_WINDOW_SIZE_SCALE,
+        PEER_FREE_UNSEQUENCED_WINDOWS, PEER_MAXIMUM_CONNECT_PAYLOAD, PEER_MAXIMUM_PING_PAYLOAD,
+        PEER_MIGRATION_CHALLENGE_INTERVAL, PEER_MIGRATION_CHALLENGE_MAXIMUM_FAILURES,
+        PEER_PACKET_LOSS_INTERVAL, PEER_PACKET_LOSS_SCALE, PEER_PACKET_THROTTLE_COUNTER,
+        PEER_PACKET_THROTTLE_SCALE, PEER_PATH_PROBE_INTERVAL, PEER_PATH_SWITCH_THRESHOLD,
+        PEER_RELIABLE_WINDOWS, PEER_RELIABLE_WINDOW_SIZE, PEER_WINDOW_SIZE_SCALE,
         PROTOCOL_MAXIMUM_CHANNEL_COUNT, PROTOCOL_MAXIMUM_FRAGMENT_COUNT, PROTOCOL_MAXIMUM_MTU,
-        PROTOCOL_MAXIMUM_PEER_ID, PROTOCOL_MAXIMUM_WINDOW_SIZE, PROTOCOL_MINIMUM_CHANNEL_COUNT,
-        PROTOCOL_MINIMUM_MTU, PROTOCOL_MINIMUM_WINDOW_SIZE,
+        PROTOCOL_MAXIMUM_PACKET_COMMANDS, PROTOCOL_MAXIMUM_PEER_ID, PROTOCOL_MAXIMUM_WINDOW_SIZE,
+        PROTOCOL_MINIMUM_CHANNEL_COUNT, PROTOCOL_MINIMUM_MTU, PROTOCOL_MINIMUM_WINDOW_SIZE,
     },
-    enet_free, enet_host_bandwidth_throttle, enet_list_clear, enet_list_insert, enet_list_remove,
-    enet_malloc, enet_packet_destroy, enet_peer_disconnect,
+    enet_host_audit_connect, enet_host_ban_expiry, enet_host_bandwidth_throttle,
+    enet_host_channel_mode, enet_host_expire_bans, enet_host_follow_redirects,
+    enet_host_peer_slot_allowed, enet_host_protocol_messages, enet_host_send_raw_datagrams,
+    enet_list_clear, enet_malloc, enet_packet_destroy, enet_packet_release,
+    enet_peer_channel_maximum_packet_size, enet_peer_clock_sample, enet_peer_congestion_echo,
+    enet_peer_congestion_experienced, enet_peer_disconnect,
     enet_peer_dispatch_incoming_reliable_commands, enet_peer_dispatch_incoming_unreliable_commands,
-    enet_peer_has_outgoing_commands, enet_peer_on_connect, enet_peer_on_disconnect, enet_peer_ping,
-    enet_peer_queue_acknowledgement, enet_peer_queue_incoming_command,
-    enet_peer_queue_outgoing_command, enet_peer_receive, enet_peer_reset, enet_peer_reset_queues,
-    enet_peer_throttle, enet_time_get, from_raw_parts_or_empty, Address, ENetAcknowledgement,
-    ENetBuffer, ENetChannel, ENetEvent, ENetHost, ENetIncomingCommand, ENetList, ENetListIterator,
-    ENetListNode, ENetOutgoingCommand, ENetPeer, ENetPeerState, PacketReceived, Socket, Vec,
-    ENET_EVENT_TYPE_CONNECT, ENET_EVENT_TYPE_DISCONNECT, ENET_EVENT_TYPE_NONE,
-    ENET_EVENT_TYPE_RECEIVE, ENET_PACKET_FLAG_RELIABLE, ENET_PACKET_FLAG_SENT,
-    ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT, ENET_PACKET_FLAG_UNSEQUENCED,
-    ENET_PEER_FLAG_CONTINUE_SENDING, ENET_PEER_FLAG_NEEDS_DISPATCH,
+    enet_peer_drop_incoming_command, enet_peer_end_session, enet_peer_extended_peer_id,
+    enet_peer_forget_incomplete_fragments, enet_peer_has_outgoing_commands,
+    enet_peer_holds_acknowledgements, enet_peer_holds_outgoing_commands,
+    enet_peer_issue_session_ticket, enet_peer_on_connect, enet_peer_on_disconnect,
+    enet_peer_over_memory_budget, enet_peer_path_response, enet_peer_ping,
+    enet_peer_queue_acknowledgement, enet_peer_queue_dispatch, enet_peer_queue_incoming_command,
+    enet_peer_queue_outgoing_command, enet_peer_receive, enet_peer_release_outgoing_command,
+    enet_peer_report_packet, enet_peer_reset, enet_peer_reset_queues, enet_peer_resume_session,
+    enet_peer_send_accept_data, enet_peer_send_connect_payload, enet_peer_send_protocol_message,
+    enet_peer_send_redirect, enet_peer_send_resume_ticket, enet_peer_throttle,
+    enet_peer_window_size, enet_time_get,
+    error::{ServiceError, ServiceStage},
+    from_raw_parts_or_empty, AcknowledgementMode, Address, ChannelMode, ConnectOutcome, ENetBuffer,
+    ENetChannel, ENetEvent, ENetHost, ENetIncomingCommand, ENetListIterator,
+    ENetMigrationChallenge, ENetOutgoingCommand, ENetPeer, ENetPeerState, ENetReader,
+    ENetResendReport, MemoryBudgetPolicy, PacketReceived, PeerSimulation, ProtocolVersion,
+    ProtocolViolationKind, ReceiveMetadata, Socket, Vec, VecDeque, ENET_EVENT_TYPE_ACKNOWLEDGE,
+    ENET_EVENT_TYPE_CONNECT, ENET_EVENT_TYPE_CONNECT_REQUEST, ENET_EVENT_TYPE_DELIVERY_FAILED,
+    ENET_EVENT_TYPE_DISCONNECT, ENET_EVENT_TYPE_NONE, ENET_EVENT_TYPE_PACKET_RESENT,
+    ENET_EVENT_TYPE_PEER_IDLE, ENET_EVENT_TYPE_PROTOCOL_VIOLATION, ENET_EVENT_TYPE_RECEIVE,
+    ENET_PACKET_FLAG_RELIABLE, ENET_PACKET_FLAG_SENT, ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT,
+    ENET_PACKET_FLAG_UNSEQUENCED, ENET_PEER_FLAG_CONTINUE_SENDING, ENET_PEER_FLAG_NEEDS_DISPATCH,
     ENET_PEER_STATE_ACKNOWLEDGING_CONNECT, ENET_PEER_STATE_ACKNOWLEDGING_DISCONNECT,
     ENET_PEER_STATE_CONNECTED, ENET_PEER_STATE_CONNECTING, ENET_PEER_STATE_CONNECTION_PENDING,
     ENET_PEER_STATE_CONNECTION_SUCCEEDED, ENET_PEER_STATE_DISCONNECTED,
     ENET_PEER_STATE_DISCONNECTING, ENET_PEER_STATE_DISCONNECT_LATER, ENET_PEER_STATE_ZOMBIE,
 };
-use crate::c::enet_host_random;
+use core::mem::{offset_of, size_of, take, zeroed, MaybeUninit};
+use core::{
+    alloc::Layout,
+    ptr::{copy_nonoverlapping, write_bytes},
+    sync::atomic::Ordering,
+    time::Duration,
+};
 
 pub(crate) type _ENetProtocolCommand = u32;
 pub(crate) const ENET_PROTOCOL_COMMAND_MASK: _ENetProtocolCommand = 15;
@@ -57,8 +76,27 @@ pub(crate) const ENET_PROTOCOL_HEADER_SESSION_MASK: _ENetProtocolFlag = 12288;
 pub(crate) const ENET_PROTOCOL_HEADER_FLAG_MASK: _ENetProtocolFlag = 49152;
 pub(crate) const ENET_PROTOCOL_HEADER_FLAG_SENT_TIME: _ENetProtocolFlag = 32768;
 pub(crate) const ENET_PROTOCOL_HEADER_FLAG_COMPRESSED: _ENetProtocolFlag = 16384;
+pub(crate) const ENET_PROTOCOL_HEADER_EXTENDED_PEER_ID: u16 = 4094;
 pub(crate) const ENET_PROTOCOL_COMMAND_FLAG_UNSEQUENCED: _ENetProtocolFlag = 64;
 pub(crate) const ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE: _ENetProtocolFlag = 128;
+pub(crate) const ENET_PROTOCOL_MESSAGE_PING_PAYLOAD: u8 = 0;
+pub(crate) const ENET_PROTOCOL_MESSAGE_CLOCK_REQUEST: u8 = 1;
+pub(crate) const ENET_PROTOCOL_MESSAGE_CLOCK_RESPONSE: u8 = 2;
+pub(crate) const ENET_PROTOCOL_MESSAGE_VERSION: u8 = 3;
+pub(crate) const ENET_PROTOCOL_MESSAGE_MIGRATION_CHALLENGE: u8 = 4;
+pub(crate) const ENET_PROTOCOL_MESSAGE_MIGRATION_RESPONSE: u8 = 5;
+pub(crate) const ENET_PROTOCOL_MESSAGE_SESSION_TICKET: u8 = 6;
+pub(crate) const ENET_PROTOCOL_MESSAGE_RESUME_SESSION: u8 = 7;
+pub(crate) const ENET_PROTOCOL_MESSAGE_PATH_PROBE: u8 = 8;
+pub(crate) const ENET_PROTOCOL_MESSAGE_PATH_RESPONSE: u8 = 9;
+pub(crate) const ENET_PROTOCOL_MESSAGE_CONGESTION_ECHO: u8 = 10;
+pub(crate) const ENET_PROTOCOL_MESSAGE_CONNECT_PAYLOAD: u8 = 11;
+pub(crate) const ENET_PROTOCOL_MESSAGE_ACCEPT_DATA: u8 = 12;
+pub(crate) const ENET_PROTOCOL_MESSAGE_REDIRECT: u8 = 13;
+/// The ECN codepoint for ECN-Capable Transport, `ECT(0)`, in a traffic class.
+const ECN_CAPABLE: u8 = 0b10;
+/// The ECN codepoint for Congestion Experienced, `CE`.
+const ECN_CONGESTION_EXPERIENCED: u8 = 0b11;
 #[derive(Copy, Clone)]
 #[repr(C, packed)]
 pub(crate) struct ENetProtocolHeader {
@@ -194,7 +232,7 @@ pub(crate) union ENetProtocol {
     pub(crate) bandwidth_limit: ENetProtocolBandwidthLimit,
     pub(crate) throttle_configure: ENetProtocolThrottleConfigure,
 }
-static mut COMMAND_SIZES: [usize; 13] = [
+static COMMAND_SIZES: [usize; 13] = [
     0_i32 as usize,
     ::core::mem::size_of::<ENetProtocolAcknowledge>(),
     ::core::mem::size_of::<ENetProtocolConnect>(),
@@ -212,6 +250,140 @@ static mut COMMAND_SIZES: [usize; 13] = [
 pub(crate) unsafe fn enet_protocol_command_size(command_number: u8) -> usize {
     COMMAND_SIZES[(command_number as i32 & ENET_PROTOCOL_COMMAND_MASK as i32) as usize]
 }
+/// A datagram's protocol header, parsed by [`enet_protocol_parse_header`].
+pub(crate) struct ENetDatagramHeader {
+    pub(crate) peer_id: u16,
+    pub(crate) session_id: u8,
+    pub(crate) flags: u16,
+    pub(crate) sent_time: Option<u16>,
+    /// The checksum, in the byte order it was written with.
+    pub(crate) checksum: Option<[u8; 4]>,
+    /// The length of the header, which the datagram's commands follow.
+    pub(crate) size: usize,
+}
+/// Parse the header at the start of a datagram, checking the datagram is long enough for the
+/// sent time and checksum called for by its flags and the host's settings.
+///
+/// Hosts with more peers than [`PROTOCOL_MAXIMUM_PEER_ID`] take the peer ID
+/// [`ENET_PROTOCOL_HEADER_EXTENDED_PEER_ID`] to mean the full peer ID follows the sent time.
+pub(crate) fn enet_protocol_parse_header(
+    data: &[u8],
+    has_checksum: bool,
+    extended_peer_ids: bool,
+) -> Result<ENetDatagramHeader, ProtocolViolationKind> {
+    let mut reader = ENetReader::new(data);
+    let peer_id = reader
+        .read_u16()
+        .ok_or(ProtocolViolationKind::TruncatedHeader)?;
+    let flags = peer_id & ENET_PROTOCOL_HEADER_FLAG_MASK as u16;
+    let sent_time = if flags & ENET_PROTOCOL_HEADER_FLAG_SENT_TIME as u16 != 0 {
+        Some(
+            reader
+                .read_u16()
+                .ok_or(ProtocolViolationKind::TruncatedHeader)?,
+        )
+    } else {
+        None
+    };
+    let mut header_peer_id =
+        peer_id & !(ENET_PROTOCOL_HEADER_FLAG_MASK | ENET_PROTOCOL_HEADER_SESSION_MASK) as u16;
+    if extended_peer_ids && header_peer_id == ENET_PROTOCOL_HEADER_EXTENDED_PEER_ID {
+        header_peer_id = reader
+            .read_u16()
+            .ok_or(ProtocolViolationKind::TruncatedHeader)?;
+        if header_peer_id as u32 <= PROTOCOL_MAXIMUM_PEER_ID {
+            return Err(ProtocolViolationKind::UnknownPeer);
+        }
+    }
+    let checksum = if has_checksum {
+        Some(
+            reader
+                .read_array()
+                .ok_or(ProtocolViolationKind::TruncatedHeader)?,
+        )
+    } else {
+        None
+    };
+    Ok(ENetDatagramHeader {
+        peer_id: header_peer_id,
+        session_id: ((peer_id & ENET_PROTOCOL_HEADER_SESSION_MASK as u16)
+            >> ENET_PROTOCOL_HEADER_SESSION_SHIFT) as u8,
+        flags,
+        sent_time,
+        checksum,
+        size: reader.position(),
+    })
+}
+/// A command located by [`enet_protocol_parse_command`].
+pub(crate) struct ENetCommandFrame<'a> {
+    pub(crate) command_number: u8,
+    /// The command's fixed size fields, in network byte order.
+    pub(crate) command: &'a [u8],
+    /// The packet data following a send command, or empty for other commands.
+    pub(crate) data: &'a [u8],
+}
+/// Read the next command from a datagram, checking both the command and the packet data its
+/// length field describes are within the datagram.
+pub(crate) fn enet_protocol_parse_command<'a>(
+    reader: &mut ENetReader<'a>,
+) -> Result<ENetCommandFrame<'a>, ProtocolViolationKind> {
+    if reader.remaining() < size_of::<ENetProtocolCommandHeader>() {
+        return Err(ProtocolViolationKind::TruncatedCommand);
+    }
+    let command_number = reader.peek_u8().unwrap_or_default() & ENET_PROTOCOL_COMMAND_MASK as u8;
+    if u32::from(command_number) >= ENET_PROTOCOL_COMMAND_COUNT {
+        return Err(ProtocolViolationKind::UnknownCommand);
+    }
+    let command_size = COMMAND_SIZES[usize::from(command_number)];
+    if command_size == 0 {
+        return Err(ProtocolViolationKind::UnknownCommand);
+    }
+    let command = reader
+        .read_bytes(command_size)
+        .ok_or(ProtocolViolationKind::TruncatedCommand)?;
+    let data_length_offset = match u32::from(command_number) {
+        ENET_PROTOCOL_COMMAND_SEND_RELIABLE => {
+            Some(offset_of!(ENetProtocolSendReliable, data_length))
+        }
+        ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE => {
+            Some(offset_of!(ENetProtocolSendUnreliable, data_length))
+        }
+        ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED => {
+            Some(offset_of!(ENetProtocolSendUnsequenced, data_length))
+        }
+        ENET_PROTOCOL_COMMAND_SEND_FRAGMENT | ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE_FRAGMENT => {
+            Some(offset_of!(ENetProtocolSendFragment, data_length))
+        }
+        _ => None,
+    };
+    let data_length = data_length_offset
+        .and_then(|offset| ENetReader::new(command.get(offset..)?).read_u16())
+        .unwrap_or_default();
+    let data = reader
+        .read_bytes(usize::from(data_length))
+        .ok_or(ProtocolViolationKind::TruncatedCommand)?;
+    Ok(ENetCommandFrame {
+        command_number,
+        command,
+        data,
+    })
+}
+/// Check if a datagram is a connection request, without decompressing it: it's addressed to no
+/// peer, and its first command is a connect unless the rest is compressed.
+fn enet_protocol_is_connect(data: &[u8], has_checksum: bool, extended_peer_ids: bool) -> bool {
+    let Ok(header) = enet_protocol_parse_header(data, has_checksum, extended_peer_ids) else {
+        return false;
+    };
+    if u32::from(header.peer_id) != PROTOCOL_MAXIMUM_PEER_ID {
+        return false;
+    }
+    if header.flags & ENET_PROTOCOL_HEADER_FLAG_COMPRESSED as u16 != 0 {
+        return true;
+    }
+    let mut reader = ENetReader::new(data.get(header.size..).unwrap_or_default());
+    enet_protocol_parse_command(&mut reader)
+        .is_ok_and(|frame| u32::from(frame.command_number) == ENET_PROTOCOL_COMMAND_CONNECT)
+}
 unsafe fn enet_protocol_change_state<S: Socket>(
     mut _host: *mut ENetHost<S>,
     peer: *mut ENetPeer<S>,
@@ -232,24 +404,35 @@ unsafe fn enet_protocol_dispatch_state<S: Socket>(
     state: ENetPeerState,
 ) {
     enet_protocol_change_state(host, peer, state);
-    if (*peer).flags as i32 & ENET_PEER_FLAG_NEEDS_DISPATCH as i32 == 0 {
-        enet_list_insert(
-            &mut (*host).dispatch_queue.sentinel,
-            core::ptr::addr_of_mut!((*peer).dispatch_list).cast(),
-        );
-        (*peer).flags = ((*peer).flags as i32 | ENET_PEER_FLAG_NEEDS_DISPATCH as i32) as u16;
-    }
+    enet_peer_queue_dispatch(peer);
 }
 unsafe fn enet_protocol_dispatch_incoming_commands<S: Socket>(
     host: *mut ENetHost<S>,
     event: *mut ENetEvent<S>, // SAFETY: should not be null
 ) -> bool {
-    while (*host).dispatch_queue.sentinel.next
-        != core::ptr::addr_of_mut!((*host).dispatch_queue.sentinel)
-    {
-        let peer: *mut ENetPeer<S> = enet_list_remove((*host).dispatch_queue.sentinel.next).cast();
+    if let Some(report) = (*host).packet_reports.assume_init_mut().pop_front() {
+        (*event).type_0 = if report.delivered {
+            ENET_EVENT_TYPE_ACKNOWLEDGE
+        } else {
+            ENET_EVENT_TYPE_DELIVERY_FAILED
+        };
+        (*event).peer = report.peer;
+        (*event).channel_id = report.channel_id;
+        (*event).tag = report.tag;
+        (*event).time = report.queued_time;
+        return true;
+    }
+    if let Some(report) = (*host).resend_reports.assume_init_mut().pop_front() {
+        (*event).type_0 = ENET_EVENT_TYPE_PACKET_RESENT;
+        (*event).peer = report.peer;
+        (*event).channel_id = report.channel_id;
+        (*event).data = report.attempts;
+        return true;
+    }
+    while let Some(peer_index) = (*host).dispatch_queue.assume_init_mut().pop_front() {
+        let peer: *mut ENetPeer<S> = (*host).peers.add(peer_index);
         (*peer).flags = ((*peer).flags as i32 & !(ENET_PEER_FLAG_NEEDS_DISPATCH as i32)) as u16;
-        match (*peer).state as u32 {
+        match (*peer).state {
             3 | 4 => {
                 enet_protocol_change_state(host, peer, ENET_PEER_STATE_CONNECTED);
                 (*event).type_0 = ENET_EVENT_TYPE_CONNECT;
@@ -257,11 +440,24 @@ unsafe fn enet_protocol_dispatch_incoming_commands<S: Socket>(
                 (*event).data = (*peer).event_data;
                 return true;
             }
+            2 if (*peer).pending_verify.is_some() => {
+                (*event).type_0 = ENET_EVENT_TYPE_CONNECT_REQUEST;
+                (*event).peer = peer;
+                (*event).data = (*peer).event_data;
+                return true;
+            }
             9 => {
                 (*host).recalculate_bandwidth_limits = 1_i32;
                 (*event).type_0 = ENET_EVENT_TYPE_DISCONNECT;
                 (*event).peer = peer;
                 (*event).data = (*peer).event_data;
+                if let Some(address) = (*peer).redirected_to.assume_init_mut().take() {
+                    (*host).redirects.assume_init_mut().push((
+                        address,
+                        (*peer).channel_count,
+                        (*peer).event_data,
+                    ));
+                }
                 enet_peer_reset(peer);
                 return true;
             }
@@ -271,7 +467,8 @@ unsafe fn enet_protocol_dispatch_incoming_commands<S: Socket>(
                 {
                     continue;
                 }
-                (*event).packet = enet_peer_receive(peer, &mut (*event).channel_id);
+                (*event).packet =
+                    enet_peer_receive(peer, &raw mut (*event).channel_id, &raw mut (*event).time);
                 if ((*event).packet).is_null() {
                     continue;
                 }
@@ -280,12 +477,7 @@ unsafe fn enet_protocol_dispatch_incoming_commands<S: Socket>(
                 if (*peer).dispatched_commands.sentinel.next
                     != core::ptr::addr_of_mut!((*peer).dispatched_commands.sentinel)
                 {
-                    (*peer).flags =
-                        ((*peer).flags as i32 | ENET_PEER_FLAG_NEEDS_DISPATCH as i32) as u16;
-                    enet_list_insert(
-                        &mut (*host).dispatch_queue.sentinel,
-                        core::ptr::addr_of_mut!((*peer).dispatch_list).cast::<u8>(),
-                    );
+                    enet_peer_queue_dispatch(peer);
                 }
                 return true;
             }
@@ -300,6 +492,16 @@ unsafe fn enet_protocol_notify_connect<S: Socket>(
     event: *mut ENetEvent<S>,
 ) {
     (*host).recalculate_bandwidth_limits = 1_i32;
+    if (*peer).state == ENET_PEER_STATE_ACKNOWLEDGING_CONNECT as i32 as u32
+        && (*peer).protocol_version > ProtocolVersion::Enet
+    {
+        enet_peer_send_protocol_message(
+            peer,
+            ENET_PROTOCOL_MESSAGE_VERSION,
+            &[(*peer).protocol_version as u8],
+        );
+        enet_peer_issue_session_ticket(peer);
+    }
     if !event.is_null() {
         enet_protocol_change_state(host, peer, ENET_PEER_STATE_CONNECTED);
         (*event).type_0 = ENET_EVENT_TYPE_CONNECT;
@@ -341,38 +543,25 @@ unsafe fn enet_protocol_notify_disconnect<S: Socket>(
 }
 unsafe fn enet_protocol_remove_sent_unreliable_commands<S: Socket>(
     peer: *mut ENetPeer<S>,
-    sent_unreliable_commands: *mut ENetList,
+    sent_unreliable_commands: &mut Vec<*mut ENetOutgoingCommand>,
 ) {
-    let mut outgoing_command: *mut ENetOutgoingCommand;
-    if (*sent_unreliable_commands).sentinel.next
-        == core::ptr::addr_of_mut!((*sent_unreliable_commands).sentinel)
-    {
+    if sent_unreliable_commands.is_empty() {
         return;
     }
-    loop {
-        outgoing_command = (*sent_unreliable_commands)
-            .sentinel
-            .next
-            .cast::<u8>()
-            .cast::<ENetOutgoingCommand>();
-        enet_list_remove(&mut (*outgoing_command).outgoing_command_list);
-        if !((*outgoing_command).packet).is_null() {
-            (*(*outgoing_command).packet).reference_count =
-                ((*(*outgoing_command).packet).reference_count).wrapping_sub(1);
-            if (*(*outgoing_command).packet).reference_count == 0_i32 as usize {
-                (*(*outgoing_command).packet).flags |= ENET_PACKET_FLAG_SENT as i32 as u32;
-                enet_packet_destroy((*outgoing_command).packet);
-            }
-        }
-        enet_free(
-            outgoing_command.cast(),
-            Layout::new::<ENetOutgoingCommand>(),
-        );
-        if (*sent_unreliable_commands).sentinel.next
-            == core::ptr::addr_of_mut!((*sent_unreliable_commands).sentinel)
+    for outgoing_command in sent_unreliable_commands.drain(..) {
+        if !((*outgoing_command).packet).is_null()
+            && enet_packet_release((*outgoing_command).packet)
         {
-            break;
+            (*(*outgoing_command).packet)
+                .flags
+                .fetch_or(ENET_PACKET_FLAG_SENT as i32 as u32, Ordering::Relaxed);
+            enet_packet_destroy((*outgoing_command).packet);
         }
+        enet_peer_release_outgoing_command(peer, outgoing_command);
+        (*(*peer).host)
+            .outgoing_command_pool
+            .assume_init_mut()
+            .release(outgoing_command);
     }
     if (*peer).state == ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
         && enet_peer_has_outgoing_commands(peer) == 0
@@ -380,15 +569,14 @@ unsafe fn enet_protocol_remove_sent_unreliable_commands<S: Socket>(
         enet_peer_disconnect(peer, (*peer).event_data);
     }
 }
-unsafe fn enet_protocol_find_sent_reliable_command(
-    list: *mut ENetList,
+/// Remove a reliable command which was sent before, but queued to be sent again, from an
+/// outgoing queue.
+unsafe fn enet_protocol_remove_resent_reliable_command(
+    queue: &mut VecDeque<*mut ENetOutgoingCommand>,
     reliable_sequence_number: u16,
     channel_id: u8,
-) -> *mut ENetOutgoingCommand {
-    let mut current_command: ENetListIterator;
-    current_command = (*list).sentinel.next;
-    while current_command != core::ptr::addr_of_mut!((*list).sentinel) {
-        let outgoing_command: *mut ENetOutgoingCommand = current_command.cast();
+) -> Option<*mut ENetOutgoingCommand> {
+    for (position, &outgoing_command) in queue.iter().enumerate() {
         if (*outgoing_command).command.header.command as i32
             & ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE as i32
             != 0
@@ -400,49 +588,44 @@ unsafe fn enet_protocol_find_sent_reliable_command(
                 == reliable_sequence_number as i32
                 && (*outgoing_command).command.header.channel_id as i32 == channel_id as i32
             {
-                return outgoing_command;
+                return queue.remove(position);
             }
         }
-        current_command = (*current_command).next;
     }
-    core::ptr::null_mut()
+    None
 }
 unsafe fn enet_protocol_remove_sent_reliable_command<S: Socket>(
     peer: *mut ENetPeer<S>,
     reliable_sequence_number: u16,
     channel_id: u8,
 ) -> ENetProtocolCommand {
-    let mut outgoing_command: *mut ENetOutgoingCommand = core::ptr::null_mut();
-    let mut current_command: ENetListIterator;
     let mut was_sent: i32 = 1_i32;
-    current_command = (*peer).sent_reliable_commands.sentinel.next;
-    while current_command != core::ptr::addr_of_mut!((*peer).sent_reliable_commands.sentinel) {
-        outgoing_command = current_command.cast();
-        if (*outgoing_command).reliable_sequence_number as i32 == reliable_sequence_number as i32
-            && (*outgoing_command).command.header.channel_id as i32 == channel_id as i32
-        {
-            break;
-        }
-        current_command = (*current_command).next;
-    }
-    if current_command == core::ptr::addr_of_mut!((*peer).sent_reliable_commands.sentinel) {
-        outgoing_command = enet_protocol_find_sent_reliable_command(
-            &mut (*peer).outgoing_commands,
+    let sent_reliable_commands = (*peer).sent_reliable_commands.assume_init_mut();
+    let mut outgoing_command = sent_reliable_commands
+        .iter()
+        .position(|&outgoing_command| {
+            (*outgoing_command).reliable_sequence_number as i32 == reliable_sequence_number as i32
+                && (*outgoing_command).command.header.channel_id as i32 == channel_id as i32
+        })
+        .and_then(|position| sent_reliable_commands.remove(position));
+    if outgoing_command.is_none() {
+        outgoing_command = enet_protocol_remove_resent_reliable_command(
+            (*peer).outgoing_commands.assume_init_mut(),
             reliable_sequence_number,
             channel_id,
-        );
-        if outgoing_command.is_null() {
-            outgoing_command = enet_protocol_find_sent_reliable_command(
-                &mut (*peer).outgoing_send_reliable_commands,
+        )
+        .or_else(|| {
+            enet_protocol_remove_resent_reliable_command(
+                (*peer).outgoing_send_reliable_commands.assume_init_mut(),
                 reliable_sequence_number,
                 channel_id,
-            );
-        }
+            )
+        });
         was_sent = 0_i32;
     }
-    if outgoing_command.is_null() {
+    let Some(outgoing_command) = outgoing_command else {
         return ENET_PROTOCOL_COMMAND_NONE;
-    }
+    };
     if (channel_id as usize) < (*peer).channel_count {
         let channel: *mut ENetChannel = ((*peer).channels).offset(channel_id as isize);
         let reliable_window: u16 =
@@ -459,43 +642,35 @@ unsafe fn enet_protocol_remove_sent_reliable_command<S: Socket>(
     }
     let command_number = ((*outgoing_command).command.header.command as i32
         & ENET_PROTOCOL_COMMAND_MASK as i32) as ENetProtocolCommand;
-    enet_list_remove(&mut (*outgoing_command).outgoing_command_list);
+    enet_peer_release_outgoing_command(peer, outgoing_command);
+    enet_peer_report_packet(peer, outgoing_command, true);
     if !((*outgoing_command).packet).is_null() {
         if was_sent != 0 {
             (*peer).reliable_data_in_transit = (*peer)
                 .reliable_data_in_transit
                 .wrapping_sub((*outgoing_command).fragment_length as u32);
         }
-        (*(*outgoing_command).packet).reference_count =
-            ((*(*outgoing_command).packet).reference_count).wrapping_sub(1);
-        if (*(*outgoing_command).packet).reference_count == 0_i32 as usize {
-            (*(*outgoing_command).packet).flags |= ENET_PACKET_FLAG_SENT as i32 as u32;
+        if enet_packet_release((*outgoing_command).packet) {
+            (*(*outgoing_command).packet)
+                .flags
+                .fetch_or(ENET_PACKET_FLAG_SENT as i32 as u32, Ordering::Relaxed);
             enet_packet_destroy((*outgoing_command).packet);
         }
     }
-    enet_free(
-        outgoing_command.cast(),
-        Layout::new::<ENetOutgoingCommand>(),
-    );
-    if (*peer).sent_reliable_commands.sentinel.next
-        == core::ptr::addr_of_mut!((*peer).sent_reliable_commands.sentinel)
-    {
+    (*(*peer).host)
+        .outgoing_command_pool
+        .assume_init_mut()
+        .release(outgoing_command);
+    let Some(&outgoing_command) = (*peer).sent_reliable_commands.assume_init_ref().front() else {
         return command_number;
-    }
-    outgoing_command = (*peer)
-        .sent_reliable_commands
-        .sentinel
-        .next
-        .cast::<u8>()
-        .cast::<ENetOutgoingCommand>();
+    };
     (*peer).next_timeout =
         ((*outgoing_command).sent_time).wrapping_add((*outgoing_command).round_trip_timeout);
     command_number
 }
 unsafe fn enet_protocol_handle_connect<S: Socket>(
     host: *mut ENetHost<S>,
-    mut _header: *mut ENetProtocolHeader,
-    command: *mut ENetProtocol,
+    command: *const ENetProtocol,
 ) -> *mut ENetPeer<S> {
     let mut incoming_session_id: u8;
     let mut outgoing_session_id: u8;
@@ -517,21 +692,31 @@ unsafe fn enet_protocol_handle_connect<S: Socket>(
     if channel_count < PROTOCOL_MINIMUM_CHANNEL_COUNT as i32 as usize
         || channel_count > PROTOCOL_MAXIMUM_CHANNEL_COUNT as i32 as usize
     {
+        enet_host_audit_connect(
+            host,
+            (*host).received_address.assume_init_ref().as_ref().unwrap(),
+            ConnectOutcome::InvalidRequest,
+        );
         return core::ptr::null_mut();
     }
+    let protocol_version = (*host)
+        .protocol_version
+        .min(ProtocolVersion::from_session_id(
+            (*command).connect.outgoing_session_id,
+        ));
     current_peer = (*host).peers;
     while current_peer < ((*host).peers).add((*host).peer_count) {
         if (*current_peer).state == ENET_PEER_STATE_DISCONNECTED as i32 as u32 {
-            if peer.is_null() {
+            if peer.is_null() && enet_host_peer_slot_allowed(host, current_peer, protocol_version) {
                 peer = current_peer;
             }
         } else if (*current_peer).state != ENET_PEER_STATE_CONNECTING as i32 as u32
             && (*current_peer)
-            .address
-            .assume_init_ref()
-            .as_ref()
-            .unwrap()
-            .same_host((*host).received_address.assume_init_ref().as_ref().unwrap())
+                .address
+                .assume_init_ref()
+                .as_ref()
+                .unwrap()
+                .same_host((*host).received_address.assume_init_ref().as_ref().unwrap())
         {
             if (*current_peer)
                 .address
@@ -548,6 +733,11 @@ unsafe fn enet_protocol_handle_connect<S: Socket>(
         current_peer = current_peer.offset(1);
     }
     if peer.is_null() || duplicate_peers >= (*host).duplicate_peers {
+        enet_host_audit_connect(
+            host,
+            (*host).received_address.assume_init_ref().as_ref().unwrap(),
+            ConnectOutcome::PeerLimit,
+        );
         return core::ptr::null_mut();
     }
     if channel_count > (*host).channel_limit {
@@ -556,7 +746,9 @@ unsafe fn enet_protocol_handle_connect<S: Socket>(
     (*peer).channels = enet_malloc(Layout::array::<ENetChannel>(channel_count).unwrap()).cast();
     (*peer).channel_count = channel_count;
     (*peer).state = ENET_PEER_STATE_ACKNOWLEDGING_CONNECT;
+    (*peer).generation = (*peer).generation.wrapping_add(1);
     (*peer).connect_id = (*command).connect.connect_id;
+    (*peer).protocol_version = protocol_version;
     *(*peer).address.assume_init_mut() = Some(
         (*host)
             .received_address
@@ -575,6 +767,8 @@ unsafe fn enet_protocol_handle_connect<S: Socket>(
     (*peer).packet_throttle_deceleration =
         u32::from_be((*command).connect.packet_throttle_deceleration);
     (*peer).event_data = u32::from_be((*command).connect.data);
+    (*peer).connect_data = (*peer).event_data;
+    (*peer).loopback = (*host).receiving_loopback;
     incoming_session_id = (if (*command).connect.incoming_session_id as i32 == 0xff_i32 {
         (*peer).outgoing_session_id as i32
     } else {
@@ -609,10 +803,13 @@ unsafe fn enet_protocol_handle_connect<S: Socket>(
         (*channel).outgoing_unreliable_sequence_number = 0_i32 as u16;
         (*channel).incoming_reliable_sequence_number = 0_i32 as u16;
         (*channel).incoming_unreliable_sequence_number = 0_i32 as u16;
-        enet_list_clear(&mut (*channel).incoming_reliable_commands);
-        enet_list_clear(&mut (*channel).incoming_unreliable_commands);
+        enet_list_clear(&raw mut (*channel).incoming_reliable_commands);
+        enet_list_clear(&raw mut (*channel).incoming_unreliable_commands);
         (*channel).used_reliable_windows = 0_i32 as u16;
         write_bytes(((*channel).reliable_windows).as_mut_ptr(), 0, 16);
+        (*channel).packets_sent = 0;
+        (*channel).packets_received = 0;
+        (*channel).reliable_resends = 0;
         channel = channel.offset(1);
     }
     mtu = u32::from_be((*command).connect.mtu);
@@ -634,16 +831,16 @@ unsafe fn enet_protocol_handle_connect<S: Socket>(
         } else {
             (*peer).incoming_bandwidth
         })
-            .wrapping_div(PEER_WINDOW_SIZE_SCALE as i32 as u32)
-            .wrapping_mul(PROTOCOL_MINIMUM_WINDOW_SIZE as i32 as u32);
+        .wrapping_div(PEER_WINDOW_SIZE_SCALE as i32 as u32)
+        .wrapping_mul(PROTOCOL_MINIMUM_WINDOW_SIZE as i32 as u32);
     } else {
         (*peer).window_size = (if (*host).outgoing_bandwidth < (*peer).incoming_bandwidth {
             (*host).outgoing_bandwidth
         } else {
             (*peer).incoming_bandwidth
         })
-            .wrapping_div(PEER_WINDOW_SIZE_SCALE as i32 as u32)
-            .wrapping_mul(PROTOCOL_MINIMUM_WINDOW_SIZE as i32 as u32);
+        .wrapping_div(PEER_WINDOW_SIZE_SCALE as i32 as u32)
+        .wrapping_mul(PROTOCOL_MINIMUM_WINDOW_SIZE as i32 as u32);
     }
     if (*peer).window_size < PROTOCOL_MINIMUM_WINDOW_SIZE as i32 as u32 {
         (*peer).window_size = PROTOCOL_MINIMUM_WINDOW_SIZE as i32 as u32;
@@ -683,153 +880,352 @@ unsafe fn enet_protocol_handle_connect<S: Socket>(
     verify_command.verify_connect.packet_throttle_deceleration =
         (*peer).packet_throttle_deceleration.to_be();
     verify_command.verify_connect.connect_id = (*peer).connect_id;
+    if (*host).connect_approval {
+        // the connecting peer keeps resending its connect command until it's verified
+        (*peer).pending_verify = Some(verify_command);
+        enet_peer_queue_dispatch(peer);
+        return peer;
+    }
     enet_peer_queue_outgoing_command(
         peer,
-        &verify_command,
+        &raw const verify_command,
         core::ptr::null_mut(),
         0_i32 as u32,
         0_i32 as u16,
     );
+    enet_host_audit_connect(
+        host,
+        (*host).received_address.assume_init_ref().as_ref().unwrap(),
+        ConnectOutcome::Accepted,
+    );
     peer
 }
 unsafe fn enet_protocol_handle_send_reliable<S: Socket>(
     host: *mut ENetHost<S>,
     peer: *mut ENetPeer<S>,
     command: *const ENetProtocol,
-    current_data: *mut *mut u8,
+    data: &[u8],
 ) -> i32 {
     if (*command).header.channel_id as usize >= (*peer).channel_count
         || (*peer).state != ENET_PEER_STATE_CONNECTED as i32 as u32
-        && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
+            && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
     {
         return -1_i32;
     }
-    let data_length = u16::from_be((*command).send_reliable.data_length) as usize;
-    *current_data = (*current_data).add(data_length);
-    if data_length > (*host).maximum_packet_size
-        || *current_data < (*host).received_data
-        || *current_data > ((*host).received_data).add((*host).received_data_length)
-    {
+    if data.len() > (*host).maximum_packet_size {
         return -1_i32;
     }
     if (enet_peer_queue_incoming_command(
         peer,
         command,
-        command
-            .cast::<u8>()
-            .offset(::core::mem::size_of::<ENetProtocolSendReliable>() as u64 as isize),
-        data_length,
+        data.as_ptr(),
+        data.len(),
         ENET_PACKET_FLAG_RELIABLE as i32 as u32,
         0_i32 as u32,
     ))
-        .is_null()
+    .is_null()
+    {
+        return -1_i32;
+    }
+    0_i32
+}
+unsafe fn enet_protocol_handle_protocol_message<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+    data: &[u8],
+) -> i32 {
+    // tickets and payloads are presented alongside the connect command, before the connection is
+    // established
+    let connecting = matches!(
+        data.first(),
+        Some(&(ENET_PROTOCOL_MESSAGE_RESUME_SESSION | ENET_PROTOCOL_MESSAGE_CONNECT_PAYLOAD))
+    ) && (*peer).state == ENET_PEER_STATE_ACKNOWLEDGING_CONNECT as i32 as u32;
+    // and accept data before the verify connect command
+    let accepting = data.first() == Some(&ENET_PROTOCOL_MESSAGE_ACCEPT_DATA)
+        && (*peer).state == ENET_PEER_STATE_CONNECTING as i32 as u32;
+    if !connecting
+        && !accepting
+        && (*peer).state != ENET_PEER_STATE_CONNECTED as i32 as u32
+        && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
     {
         return -1_i32;
     }
+    if data.len() > PEER_MAXIMUM_PING_PAYLOAD.max(PEER_MAXIMUM_CONNECT_PAYLOAD) + 1 {
+        return -1_i32;
+    }
+    let Some((&kind, data)) = data.split_first() else {
+        return 0_i32;
+    };
+    match kind {
+        ENET_PROTOCOL_MESSAGE_PING_PAYLOAD if (*host).ping_payloads => {
+            *(*peer).remote_ping_payload.assume_init_mut() = Some(data.to_vec());
+        }
+        ENET_PROTOCOL_MESSAGE_CLOCK_REQUEST if (*host).clock_sync && data.len() == 4 => {
+            let mut response = [0; 8];
+            response[..4].copy_from_slice(data);
+            response[4..].copy_from_slice(&(*host).service_time.to_be_bytes());
+            enet_peer_send_protocol_message(peer, ENET_PROTOCOL_MESSAGE_CLOCK_RESPONSE, &response);
+        }
+        ENET_PROTOCOL_MESSAGE_CLOCK_RESPONSE if (*host).clock_sync && data.len() == 8 => {
+            let request_time = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+            let remote_time = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+            enet_peer_clock_sample(peer, request_time, remote_time, (*host).service_time);
+        }
+        ENET_PROTOCOL_MESSAGE_MIGRATION_CHALLENGE if data.len() == 8 => {
+            let mut response = [0; 12];
+            response[..8].copy_from_slice(data);
+            response[8..].copy_from_slice(&(*peer).connect_id.to_be_bytes());
+            enet_peer_send_protocol_message(
+                peer,
+                ENET_PROTOCOL_MESSAGE_MIGRATION_RESPONSE,
+                &response,
+            );
+        }
+        ENET_PROTOCOL_MESSAGE_VERSION if data.len() == 1 => {
+            if let Some(version) = ProtocolVersion::from_u8(data[0]) {
+                (*peer).protocol_version = (*host).protocol_version.min(version);
+            }
+        }
+        ENET_PROTOCOL_MESSAGE_SESSION_TICKET if data.len() == 16 => {
+            let address = (*peer).address.assume_init_ref().as_ref().unwrap();
+            let tickets = (*host).session_tickets.assume_init_mut();
+            tickets.retain(|(ticket_address, _)| !ticket_address.same(address));
+            if tickets.len() >= (*host).peer_count {
+                tickets.remove(0);
+            }
+            let mut ticket = [0; 16];
+            ticket.copy_from_slice(data);
+            tickets.push((address.clone(), ticket));
+        }
+        ENET_PROTOCOL_MESSAGE_PATH_PROBE if data.len() == 8 => {
+            let address = (*host)
+                .received_address
+                .assume_init_ref()
+                .as_ref()
+                .cloned()
+                .unwrap();
+            enet_protocol_queue_protocol_message_to(
+                host,
+                peer,
+                address,
+                ENET_PROTOCOL_MESSAGE_PATH_RESPONSE,
+                data,
+            );
+        }
+        ENET_PROTOCOL_MESSAGE_PATH_RESPONSE if data.len() == 8 => {
+            enet_peer_path_response(peer, data, (*host).service_time);
+        }
+        ENET_PROTOCOL_MESSAGE_CONGESTION_ECHO if data.len() == 4 => {
+            enet_peer_congestion_echo(
+                peer,
+                u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+            );
+        }
+        ENET_PROTOCOL_MESSAGE_RESUME_SESSION
+            if connecting && (*host).session_resumption.is_some() && data.len() == 16 =>
+        {
+            enet_peer_resume_session(peer, data);
+        }
+        ENET_PROTOCOL_MESSAGE_CONNECT_PAYLOAD
+            if connecting && data.len() <= PEER_MAXIMUM_CONNECT_PAYLOAD =>
+        {
+            *(*peer).connect_payload.assume_init_mut() = data.to_vec();
+        }
+        // only the hosts this host connected to can send it elsewhere
+        ENET_PROTOCOL_MESSAGE_REDIRECT if (*host).follow_redirects && (*peer).outgoing => {
+            *(*peer).pending_redirect.assume_init_mut() = S::Address::from_bytes(data);
+        }
+        ENET_PROTOCOL_MESSAGE_ACCEPT_DATA if accepting && data.len() == 4 => {
+            (*peer).event_data = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        }
+        _ => {}
+    }
     0_i32
 }
 unsafe fn enet_protocol_handle_send_unsequenced<S: Socket>(
     host: *mut ENetHost<S>,
     peer: *mut ENetPeer<S>,
     command: *const ENetProtocol,
-    current_data: *mut *mut u8,
+    data: &[u8],
 ) -> i32 {
     let mut unsequenced_group: u32;
+    if (*command).header.channel_id == 0xff && enet_host_protocol_messages(host) {
+        return enet_protocol_handle_protocol_message(host, peer, data);
+    }
     if (*command).header.channel_id as usize >= (*peer).channel_count
         || (*peer).state != ENET_PEER_STATE_CONNECTED as i32 as u32
-        && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
+            && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
     {
         return -1_i32;
     }
-    let data_length = u16::from_be((*command).send_unsequenced.data_length) as usize;
-    *current_data = (*current_data).add(data_length);
-    if data_length > (*host).maximum_packet_size
-        || *current_data < (*host).received_data
-        || *current_data > ((*host).received_data).add((*host).received_data_length)
-    {
+    if data.len() > (*host).maximum_packet_size {
         return -1_i32;
     }
     unsequenced_group = u16::from_be((*command).send_unsequenced.unsequenced_group) as u32;
-    let index = unsequenced_group.wrapping_rem(PEER_UNSEQUENCED_WINDOW_SIZE as i32 as u32);
+    let window_size = (*host).unsequenced_window_size;
+    let index = unsequenced_group.wrapping_rem(window_size);
     if unsequenced_group < (*peer).incoming_unsequenced_group as u32 {
         unsequenced_group = unsequenced_group.wrapping_add(0x10000_i32 as u32);
     }
     if unsequenced_group
-        >= ((*peer).incoming_unsequenced_group as u32).wrapping_add(
-        (PEER_FREE_UNSEQUENCED_WINDOWS as i32 * PEER_UNSEQUENCED_WINDOW_SIZE as i32) as u32,
-    )
+        >= ((*peer).incoming_unsequenced_group as u32)
+            .wrapping_add((PEER_FREE_UNSEQUENCED_WINDOWS * window_size).min(0x8000))
     {
         return 0_i32;
     }
     unsequenced_group &= 0xffff_i32 as u32;
     if unsequenced_group.wrapping_sub(index) != (*peer).incoming_unsequenced_group as u32 {
         (*peer).incoming_unsequenced_group = unsequenced_group.wrapping_sub(index) as u16;
-        write_bytes((*peer).unsequenced_window.as_mut_ptr(), 0, 32);
-    } else if (*peer).unsequenced_window[index.wrapping_div(32_i32 as u32) as usize]
+        (*peer).unsequenced_window.assume_init_mut().fill(0);
+    } else if (*peer).unsequenced_window.assume_init_ref()
+        [index.wrapping_div(32_i32 as u32) as usize]
         & (1_i32 << index.wrapping_rem(32_i32 as u32)) as u32
         != 0
     {
         return 0_i32;
     }
+    if enet_protocol_unsequenced_duplicate(host, peer, (*command).header.channel_id, data) {
+        return 0_i32;
+    }
     if (enet_peer_queue_incoming_command(
         peer,
         command,
-        command
-            .cast::<u8>()
-            .offset(::core::mem::size_of::<ENetProtocolSendUnsequenced>() as u64 as isize),
-        data_length,
+        data.as_ptr(),
+        data.len(),
         ENET_PACKET_FLAG_UNSEQUENCED as i32 as u32,
         0_i32 as u32,
     ))
-        .is_null()
+    .is_null()
     {
         return -1_i32;
     }
-    (*peer).unsequenced_window[index.wrapping_div(32_i32 as u32) as usize] |=
+    (*peer).unsequenced_window.assume_init_mut()[index.wrapping_div(32_i32 as u32) as usize] |=
         (1_i32 << index.wrapping_rem(32_i32 as u32)) as u32;
     0_i32
 }
+/// Remember the hash of an unsequenced packet's channel and data, returning `true` if it matches
+/// one of the last [`ENetHost::unsequenced_dedup_window`] packets from the peer.
+unsafe fn enet_protocol_unsequenced_duplicate<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+    channel_id: u8,
+    data: &[u8],
+) -> bool {
+    if (*host).unsequenced_dedup_window == 0 {
+        return false;
+    }
+    // FNV-1a
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in core::iter::once(&channel_id).chain(data) {
+        hash = (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+    }
+    let hashes = (*peer).unsequenced_hashes.assume_init_mut();
+    if hashes.contains(&hash) {
+        (*peer).unsequenced_duplicates = (*peer).unsequenced_duplicates.wrapping_add(1);
+        return true;
+    }
+    while hashes.len() >= (*host).unsequenced_dedup_window {
+        hashes.pop_front();
+    }
+    hashes.push_back(hash);
+    false
+}
 unsafe fn enet_protocol_handle_send_unreliable<S: Socket>(
     host: *mut ENetHost<S>,
     peer: *mut ENetPeer<S>,
     command: *const ENetProtocol,
-    current_data: *mut *mut u8,
+    data: &[u8],
 ) -> i32 {
     if (*command).header.channel_id as usize >= (*peer).channel_count
         || (*peer).state != ENET_PEER_STATE_CONNECTED as i32 as u32
-        && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
+            && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
     {
         return -1_i32;
     }
-    let data_length = u16::from_be((*command).send_unreliable.data_length) as usize;
-    *current_data = (*current_data).add(data_length);
-    if data_length > (*host).maximum_packet_size
-        || *current_data < (*host).received_data
-        || *current_data > ((*host).received_data).add((*host).received_data_length)
-    {
+    if data.len() > (*host).maximum_packet_size {
         return -1_i32;
     }
     if (enet_peer_queue_incoming_command(
         peer,
         command,
-        command
-            .cast::<u8>()
-            .offset(::core::mem::size_of::<ENetProtocolSendUnreliable>() as u64 as isize),
-        data_length,
+        data.as_ptr(),
+        data.len(),
         0_i32 as u32,
         0_i32 as u32,
     ))
-        .is_null()
+    .is_null()
     {
         return -1_i32;
     }
     0_i32
 }
+unsafe fn enet_protocol_reserve_fragments<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+    total_length: usize,
+) -> bool {
+    loop {
+        let peer_limited = (*peer).incomplete_fragments >= (*host).maximum_incomplete_fragments;
+        if !peer_limited
+            && ((*host).incomplete_fragment_data).saturating_add(total_length)
+                <= (*host).maximum_fragment_data
+        {
+            return true;
+        }
+        let oldest = if peer_limited {
+            (*peer)
+                .incomplete_unreliable_fragments
+                .assume_init_ref()
+                .first_key_value()
+                .map(|(_, &command)| (peer, command))
+        } else {
+            (*host)
+                .incomplete_unreliable_fragments
+                .assume_init_ref()
+                .first_key_value()
+                .map(|(_, &oldest)| oldest)
+        };
+        let Some((oldest_peer, oldest_command)) = oldest else {
+            return false;
+        };
+        let oldest_channel =
+            ((*oldest_peer).channels).add((*oldest_command).command.header.channel_id as usize);
+        enet_peer_drop_incoming_command(oldest_peer, oldest_channel, oldest_command);
+    }
+}
+unsafe fn enet_protocol_track_fragments<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+    start_command: *mut ENetIncomingCommand,
+) {
+    let data_length = (*(*start_command).packet).data_length;
+    (*peer).incomplete_fragments = ((*peer).incomplete_fragments).wrapping_add(1);
+    if (*start_command).command.header.command as i32 & ENET_PROTOCOL_COMMAND_MASK as i32
+        == ENET_PROTOCOL_COMMAND_SEND_FRAGMENT as i32
+    {
+        (*peer).incomplete_reliable_fragment_data =
+            ((*peer).incomplete_reliable_fragment_data).wrapping_add(data_length);
+    } else {
+        (*host).incomplete_fragment_data =
+            ((*host).incomplete_fragment_data).wrapping_add(data_length);
+        let order = (*host).next_fragment_order;
+        (*host).next_fragment_order = order.wrapping_add(1);
+        (*start_command).fragment_order = order;
+        (*peer)
+            .incomplete_unreliable_fragments
+            .assume_init_mut()
+            .insert(order, start_command);
+        (*host)
+            .incomplete_unreliable_fragments
+            .assume_init_mut()
+            .insert(order, (peer, start_command));
+    }
+}
 unsafe fn enet_protocol_handle_send_fragment<S: Socket>(
     host: *mut ENetHost<S>,
     peer: *mut ENetPeer<S>,
     command: *const ENetProtocol,
-    current_data: *mut *mut u8,
+    data: &[u8],
 ) -> i32 {
     let mut fragment_length: u32;
     let mut start_window: u16;
@@ -837,17 +1233,12 @@ unsafe fn enet_protocol_handle_send_fragment<S: Socket>(
     let mut start_command: *mut ENetIncomingCommand = core::ptr::null_mut();
     if (*command).header.channel_id as usize >= (*peer).channel_count
         || (*peer).state != ENET_PEER_STATE_CONNECTED as i32 as u32
-        && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
+            && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
     {
         return -1_i32;
     }
-    fragment_length = u16::from_be((*command).send_fragment.data_length) as u32;
-    *current_data = (*current_data).offset(fragment_length as isize);
-    if fragment_length <= 0_i32 as u32
-        || fragment_length as usize > (*host).maximum_packet_size
-        || *current_data < (*host).received_data
-        || *current_data > ((*host).received_data).add((*host).received_data_length)
-    {
+    fragment_length = data.len() as u32;
+    if fragment_length <= 0_i32 as u32 || data.len() > (*host).maximum_packet_size {
         return -1_i32;
     }
     let channel = ((*peer).channels).offset((*command).header.channel_id as isize);
@@ -918,11 +1309,22 @@ unsafe fn enet_protocol_handle_send_fragment<S: Socket>(
         current_command = (*current_command).previous;
     }
     if start_command.is_null() {
+        if ((*peer).incomplete_reliable_fragment_data).saturating_add(total_length as usize)
+            > (*host).maximum_peer_fragment_data
+        {
+            (*peer).fragment_data_exceeded = true;
+            return -2_i32;
+        }
+        // reliable fragments only count toward the peer's own limit, but may still drop the
+        // peer's unreliable ones to stay within HostSettings::maximum_incomplete_fragments
+        if !enet_protocol_reserve_fragments(host, peer, 0) {
+            return -1_i32;
+        }
         let mut host_command: ENetProtocol = *command;
         host_command.header.reliable_sequence_number = start_sequence_number as u16;
         start_command = enet_peer_queue_incoming_command(
             peer,
-            &host_command,
+            &raw const host_command,
             core::ptr::null(),
             total_length as usize,
             ENET_PACKET_FLAG_RELIABLE as i32 as u32,
@@ -931,6 +1333,7 @@ unsafe fn enet_protocol_handle_send_fragment<S: Socket>(
         if start_command.is_null() {
             return -1_i32;
         }
+        enet_protocol_track_fragments(host, peer, start_command);
     }
     if *((*start_command).fragments).offset(fragment_number.wrapping_div(32_i32 as u32) as isize)
         & (1_i32 << fragment_number.wrapping_rem(32_i32 as u32)) as u32
@@ -938,6 +1341,7 @@ unsafe fn enet_protocol_handle_send_fragment<S: Socket>(
     {
         (*start_command).fragments_remaining =
             ((*start_command).fragments_remaining).wrapping_sub(1);
+        (*start_command).received_time = (*host).receive_time;
         let fresh32 = ((*start_command).fragments)
             .offset(fragment_number.wrapping_div(32_i32 as u32) as isize);
         *fresh32 |= (1_i32 << fragment_number.wrapping_rem(32_i32 as u32)) as u32;
@@ -948,13 +1352,12 @@ unsafe fn enet_protocol_handle_send_fragment<S: Socket>(
                 .wrapping_sub(fragment_offset as usize) as u32;
         }
         copy_nonoverlapping(
-            (command as *mut u8)
-                .offset(::core::mem::size_of::<ENetProtocolSendFragment>() as u64 as isize)
-                .cast_const(),
+            data.as_ptr(),
             ((*(*start_command).packet).data).offset(fragment_offset as isize),
             fragment_length as usize,
         );
         if (*start_command).fragments_remaining <= 0_i32 as u32 {
+            enet_peer_forget_incomplete_fragments(peer, start_command);
             enet_peer_dispatch_incoming_reliable_commands(peer, channel, core::ptr::null_mut());
         }
     }
@@ -964,7 +1367,7 @@ unsafe fn enet_protocol_handle_send_unreliable_fragment<S: Socket>(
     host: *mut ENetHost<S>,
     peer: *mut ENetPeer<S>,
     command: *const ENetProtocol,
-    current_data: *mut *mut u8,
+    data: &[u8],
 ) -> i32 {
     let mut fragment_length: u32;
     let mut reliable_window: u16;
@@ -972,16 +1375,12 @@ unsafe fn enet_protocol_handle_send_unreliable_fragment<S: Socket>(
     let mut start_command: *mut ENetIncomingCommand = core::ptr::null_mut();
     if (*command).header.channel_id as usize >= (*peer).channel_count
         || (*peer).state != ENET_PEER_STATE_CONNECTED as i32 as u32
-        && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
+            && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
     {
         return -1_i32;
     }
-    fragment_length = u16::from_be((*command).send_fragment.data_length) as u32;
-    *current_data = (*current_data).offset(fragment_length as isize);
-    if fragment_length as usize > (*host).maximum_packet_size
-        || *current_data < (*host).received_data
-        || *current_data > ((*host).received_data).add((*host).received_data_length)
-    {
+    fragment_length = data.len() as u32;
+    if data.len() > (*host).maximum_packet_size {
         return -1_i32;
     }
     let channel = ((*peer).channels).offset((*command).header.channel_id as isize);
@@ -996,7 +1395,7 @@ unsafe fn enet_protocol_handle_send_unreliable_fragment<S: Socket>(
     }
     if (reliable_window as i32) < current_window as i32
         || reliable_window as i32
-        >= current_window as i32 + PEER_FREE_RELIABLE_WINDOWS as i32 - 1_i32
+            >= current_window as i32 + PEER_FREE_RELIABLE_WINDOWS as i32 - 1_i32
     {
         return 0_i32;
     }
@@ -1064,6 +1463,9 @@ unsafe fn enet_protocol_handle_send_unreliable_fragment<S: Socket>(
         current_command = (*current_command).previous;
     }
     if start_command.is_null() {
+        if !enet_protocol_reserve_fragments(host, peer, total_length as usize) {
+            return 0_i32;
+        }
         start_command = enet_peer_queue_incoming_command(
             peer,
             command,
@@ -1075,6 +1477,7 @@ unsafe fn enet_protocol_handle_send_unreliable_fragment<S: Socket>(
         if start_command.is_null() {
             return -1_i32;
         }
+        enet_protocol_track_fragments(host, peer, start_command);
     }
     if *((*start_command).fragments).offset(fragment_number.wrapping_div(32_i32 as u32) as isize)
         & (1_i32 << fragment_number.wrapping_rem(32_i32 as u32)) as u32
@@ -1082,6 +1485,7 @@ unsafe fn enet_protocol_handle_send_unreliable_fragment<S: Socket>(
     {
         (*start_command).fragments_remaining =
             ((*start_command).fragments_remaining).wrapping_sub(1);
+        (*start_command).received_time = (*host).receive_time;
         let fresh33 = ((*start_command).fragments)
             .offset(fragment_number.wrapping_div(32_i32 as u32) as isize);
         *fresh33 |= (1_i32 << fragment_number.wrapping_rem(32_i32 as u32)) as u32;
@@ -1092,13 +1496,12 @@ unsafe fn enet_protocol_handle_send_unreliable_fragment<S: Socket>(
                 .wrapping_sub(fragment_offset as usize) as u32;
         }
         copy_nonoverlapping(
-            (command as *mut u8)
-                .offset(::core::mem::size_of::<ENetProtocolSendFragment>() as u64 as isize)
-                .cast_const(),
+            data.as_ptr(),
             ((*(*start_command).packet).data).offset(fragment_offset as isize),
             fragment_length as usize,
         );
         if (*start_command).fragments_remaining <= 0_i32 as u32 {
+            enet_peer_forget_incomplete_fragments(peer, start_command);
             enet_peer_dispatch_incoming_unreliable_commands(peer, channel, core::ptr::null_mut());
         }
     }
@@ -1144,16 +1547,16 @@ unsafe fn enet_protocol_handle_bandwidth_limit<S: Socket>(
         } else {
             (*host).outgoing_bandwidth
         })
-            .wrapping_div(PEER_WINDOW_SIZE_SCALE as i32 as u32)
-            .wrapping_mul(PROTOCOL_MINIMUM_WINDOW_SIZE as i32 as u32);
+        .wrapping_div(PEER_WINDOW_SIZE_SCALE as i32 as u32)
+        .wrapping_mul(PROTOCOL_MINIMUM_WINDOW_SIZE as i32 as u32);
     } else {
         (*peer).window_size = (if (*peer).incoming_bandwidth < (*host).outgoing_bandwidth {
             (*peer).incoming_bandwidth
         } else {
             (*host).outgoing_bandwidth
         })
-            .wrapping_div(PEER_WINDOW_SIZE_SCALE as i32 as u32)
-            .wrapping_mul(PROTOCOL_MINIMUM_WINDOW_SIZE as i32 as u32);
+        .wrapping_div(PEER_WINDOW_SIZE_SCALE as i32 as u32)
+        .wrapping_mul(PROTOCOL_MINIMUM_WINDOW_SIZE as i32 as u32);
     }
     if (*peer).window_size < PROTOCOL_MINIMUM_WINDOW_SIZE as i32 as u32 {
         (*peer).window_size = PROTOCOL_MINIMUM_WINDOW_SIZE as i32 as u32;
@@ -1184,6 +1587,7 @@ unsafe fn enet_protocol_handle_disconnect<S: Socket>(
     host: *mut ENetHost<S>,
     peer: *mut ENetPeer<S>,
     command: *const ENetProtocol,
+    redirect: Option<S::Address>,
 ) -> i32 {
     if (*peer).state == ENET_PEER_STATE_DISCONNECTED as i32 as u32
         || (*peer).state == ENET_PEER_STATE_ZOMBIE as i32 as u32
@@ -1191,6 +1595,13 @@ unsafe fn enet_protocol_handle_disconnect<S: Socket>(
     {
         return 0_i32;
     }
+    *(*peer).redirected_to.assume_init_mut() = redirect;
+    if (*peer).state == ENET_PEER_STATE_CONNECTED as i32 as u32
+        || (*peer).state == ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
+        || (*peer).state == ENET_PEER_STATE_DISCONNECTING as i32 as u32
+    {
+        enet_peer_end_session(peer);
+    }
     enet_peer_reset_queues(peer);
     if (*peer).state == ENET_PEER_STATE_CONNECTION_SUCCEEDED as i32 as u32
         || (*peer).state == ENET_PEER_STATE_DISCONNECTING as i32 as u32
@@ -1283,12 +1694,12 @@ unsafe fn enet_protocol_handle_acknowledge<S: Socket>(
     }
     if (*peer).packet_throttle_epoch == 0_i32 as u32
         || (if ((*host).service_time).wrapping_sub((*peer).packet_throttle_epoch)
-        >= 86400000_i32 as u32
-    {
-        ((*peer).packet_throttle_epoch).wrapping_sub((*host).service_time)
-    } else {
-        ((*host).service_time).wrapping_sub((*peer).packet_throttle_epoch)
-    }) >= (*peer).packet_throttle_interval
+            >= 86400000_i32 as u32
+        {
+            ((*peer).packet_throttle_epoch).wrapping_sub((*host).service_time)
+        } else {
+            ((*host).service_time).wrapping_sub((*peer).packet_throttle_epoch)
+        }) >= (*peer).packet_throttle_interval
     {
         (*peer).last_round_trip_time = (*peer).lowest_round_trip_time;
         (*peer).last_round_trip_time_variance =
@@ -1314,6 +1725,11 @@ unsafe fn enet_protocol_handle_acknowledge<S: Socket>(
         received_reliable_sequence_number as u16,
         (*command).header.channel_id,
     );
+    if command_number != ENET_PROTOCOL_COMMAND_NONE
+        && (*peer).state == ENET_PEER_STATE_CONNECTED as i32 as u32
+    {
+        enet_protocol_fast_retransmit(host, peer, received_sent_time);
+    }
     match (*peer).state {
         2 => {
             if command_number as u32 != ENET_PROTOCOL_COMMAND_VERIFY_CONNECT as i32 as u32 {
@@ -1325,12 +1741,11 @@ unsafe fn enet_protocol_handle_acknowledge<S: Socket>(
             if command_number as u32 != ENET_PROTOCOL_COMMAND_DISCONNECT as i32 as u32 {
                 return -1_i32;
             }
+            enet_peer_end_session(peer);
             enet_protocol_notify_disconnect(host, peer, event);
         }
-        6 => {
-            if enet_peer_has_outgoing_commands(peer) == 0 {
-                enet_peer_disconnect(peer, (*peer).event_data);
-            }
+        6 if enet_peer_has_outgoing_commands(peer) == 0 => {
+            enet_peer_disconnect(peer, (*peer).event_data);
         }
         _ => {}
     }
@@ -1351,11 +1766,11 @@ unsafe fn enet_protocol_handle_verify_connect<S: Socket>(
     if channel_count < PROTOCOL_MINIMUM_CHANNEL_COUNT as i32 as usize
         || channel_count > PROTOCOL_MAXIMUM_CHANNEL_COUNT as i32 as usize
         || u32::from_be((*command).verify_connect.packet_throttle_interval)
-        != (*peer).packet_throttle_interval
+            != (*peer).packet_throttle_interval
         || u32::from_be((*command).verify_connect.packet_throttle_acceleration)
-        != (*peer).packet_throttle_acceleration
+            != (*peer).packet_throttle_acceleration
         || u32::from_be((*command).verify_connect.packet_throttle_deceleration)
-        != (*peer).packet_throttle_deceleration
+            != (*peer).packet_throttle_deceleration
         || (*command).verify_connect.connect_id != (*peer).connect_id
     {
         (*peer).event_data = 0_i32 as u32;
@@ -1363,6 +1778,9 @@ unsafe fn enet_protocol_handle_verify_connect<S: Socket>(
         return -1_i32;
     }
     enet_protocol_remove_sent_reliable_command(peer, 1_i32 as u16, 0xff_i32 as u8);
+    // the payload sent with the connect command has arrived, and isn't for the connect event here
+    (*peer).connect_payload.assume_init_mut().clear();
+    (*peer).resume_ticket = None;
     if channel_count < (*peer).channel_count {
         (*peer).channel_count = channel_count;
     }
@@ -1393,161 +1811,340 @@ unsafe fn enet_protocol_handle_verify_connect<S: Socket>(
     enet_protocol_notify_connect(host, peer, event);
     0_i32
 }
-unsafe fn enet_protocol_handle_incoming_commands<S: Socket>(
+unsafe fn enet_protocol_report_violation<S: Socket>(
     host: *mut ENetHost<S>,
     event: *mut ENetEvent<S>,
+    kind: ProtocolViolationKind,
 ) -> bool {
-    let mut command: *mut ENetProtocol;
-    let mut peer: *mut ENetPeer<S>;
-    let mut current_data: *mut u8;
-    let mut header_size: usize;
-    let mut peer_id: u16;
-    if (*host).received_data_length < 2_usize {
+    if !((*host).strict_validation || (*host).report_malformed)
+        || event.is_null()
+        || (*event).type_0 != ENET_EVENT_TYPE_NONE as i32 as u32
+    {
         return false;
     }
-    let header: *mut ENetProtocolHeader = (*host).received_data.cast();
-    peer_id = u16::from_be((*header).peer_id);
-    let session_id = ((peer_id as i32 & ENET_PROTOCOL_HEADER_SESSION_MASK as i32)
-        >> ENET_PROTOCOL_HEADER_SESSION_SHIFT as i32) as u8;
-    let flags = (peer_id as i32 & ENET_PROTOCOL_HEADER_FLAG_MASK as i32) as u16;
-    peer_id = (peer_id as i32
-        & !(ENET_PROTOCOL_HEADER_FLAG_MASK as i32 | ENET_PROTOCOL_HEADER_SESSION_MASK as i32))
-        as u16;
-    header_size = if flags as i32 & ENET_PROTOCOL_HEADER_FLAG_SENT_TIME as i32 != 0 {
-        ::core::mem::size_of::<ENetProtocolHeader>()
-    } else {
-        2_usize
+    (*event).type_0 = ENET_EVENT_TYPE_PROTOCOL_VIOLATION;
+    (*event).peer = core::ptr::null_mut();
+    *(*host).protocol_violation.assume_init_mut() = Some((
+        (*host)
+            .received_address
+            .assume_init_ref()
+            .as_ref()
+            .cloned()
+            .unwrap(),
+        kind,
+    ));
+    true
+}
+unsafe fn enet_protocol_channel_mode_allows<S: Socket>(
+    host: *mut ENetHost<S>,
+    command: *const ENetProtocol,
+    command_number: u8,
+) -> bool {
+    let mode = enet_host_channel_mode(host, (*command).header.channel_id);
+    match command_number as u32 {
+        // sequenced packets are sent reliably every 65535 packets to restart the sequence numbers
+        ENET_PROTOCOL_COMMAND_SEND_RELIABLE => mode != ChannelMode::Unsequenced,
+        ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE => mode.allows(0),
+        ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED => mode.allows(ENET_PACKET_FLAG_UNSEQUENCED),
+        // unreliable packets too large for the MTU may be sent as reliable fragments
+        ENET_PROTOCOL_COMMAND_SEND_FRAGMENT => true,
+        ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE_FRAGMENT => mode != ChannelMode::Reliable,
+        _ => true,
+    }
+}
+unsafe fn enet_protocol_validate_command<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+    command: *const ENetProtocol,
+    command_number: u8,
+    data_length: usize,
+) -> Option<ProtocolViolationKind> {
+    let (channel_count, window_size, mtu) = match command_number as u32 {
+        ENET_PROTOCOL_COMMAND_CONNECT => (
+            u32::from_be((*command).connect.channel_count),
+            u32::from_be((*command).connect.window_size),
+            u32::from_be((*command).connect.mtu),
+        ),
+        ENET_PROTOCOL_COMMAND_VERIFY_CONNECT => (
+            u32::from_be((*command).verify_connect.channel_count),
+            u32::from_be((*command).verify_connect.window_size),
+            u32::from_be((*command).verify_connect.mtu),
+        ),
+        ENET_PROTOCOL_COMMAND_SEND_RELIABLE
+        | ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE
+        | ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED
+        | ENET_PROTOCOL_COMMAND_SEND_FRAGMENT
+        | ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE_FRAGMENT => {
+            if (*command).header.channel_id as usize >= (*peer).channel_count
+                && !((*command).header.channel_id == 0xff
+                    && command_number as u32 == ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED
+                    && enet_host_protocol_messages(host))
+            {
+                return Some(ProtocolViolationKind::InvalidChannel);
+            }
+            if command_number as u32 == ENET_PROTOCOL_COMMAND_SEND_FRAGMENT
+                || command_number as u32 == ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE_FRAGMENT
+            {
+                let fragment_count = u32::from_be((*command).send_fragment.fragment_count);
+                let fragment_number = u32::from_be((*command).send_fragment.fragment_number);
+                let fragment_offset = u32::from_be((*command).send_fragment.fragment_offset);
+                let total_length = u32::from_be((*command).send_fragment.total_length);
+                if data_length == 0
+                    || fragment_count == 0
+                    || fragment_count > PROTOCOL_MAXIMUM_FRAGMENT_COUNT
+                    || fragment_number >= fragment_count
+                    || total_length < fragment_count
+                    || fragment_offset >= total_length
+                    || data_length as u32 > total_length - fragment_offset
+                {
+                    return Some(ProtocolViolationKind::InvalidFragment);
+                }
+            }
+            return None;
+        }
+        _ => return None,
     };
-    if ((*host).checksum.assume_init_ref()).is_some() {
-        header_size =
-            (header_size as u64).wrapping_add(::core::mem::size_of::<u32>() as u64) as usize;
+    if !(PROTOCOL_MINIMUM_CHANNEL_COUNT..=PROTOCOL_MAXIMUM_CHANNEL_COUNT).contains(&channel_count) {
+        Some(ProtocolViolationKind::InvalidChannelCount)
+    } else if !(PROTOCOL_MINIMUM_WINDOW_SIZE..=PROTOCOL_MAXIMUM_WINDOW_SIZE).contains(&window_size)
+    {
+        Some(ProtocolViolationKind::InvalidWindowSize)
+    } else if !(PROTOCOL_MINIMUM_MTU..=PROTOCOL_MAXIMUM_MTU).contains(&(mtu as usize)) {
+        Some(ProtocolViolationKind::InvalidMtu)
+    } else {
+        None
     }
-    if peer_id as i32 == PROTOCOL_MAXIMUM_PEER_ID as i32 {
+}
+unsafe fn enet_protocol_handle_incoming_commands<S: Socket>(
+    host: *mut ENetHost<S>,
+    event: *mut ENetEvent<S>,
+) -> bool {
+    let mut peer: *mut ENetPeer<S>;
+    let mut migrating = false;
+    let mut alternate_path = false;
+    let header = match enet_protocol_parse_header(
+        from_raw_parts_or_empty((*host).received_data, (*host).received_data_length),
+        (*host).checksum.assume_init_ref().is_some(),
+        (*host).peer_count > PROTOCOL_MAXIMUM_PEER_ID as usize,
+    ) {
+        Ok(header) => header,
+        Err(kind) => return enet_protocol_report_violation(host, event, kind),
+    };
+    if header.peer_id as i32 == PROTOCOL_MAXIMUM_PEER_ID as i32 {
         peer = core::ptr::null_mut();
-    } else if peer_id as usize >= (*host).peer_count {
-        return false;
+    } else if header.peer_id as usize >= (*host).peer_count {
+        return enet_protocol_report_violation(host, event, ProtocolViolationKind::UnknownPeer);
     } else {
-        peer = ((*host).peers).offset(peer_id as isize);
+        peer = ((*host).peers).offset(header.peer_id as isize);
         if (*peer).state == ENET_PEER_STATE_DISCONNECTED as i32 as u32
             || (*peer).state == ENET_PEER_STATE_ZOMBIE as i32 as u32
-            || !(*host)
-            .received_address
-            .assume_init_ref()
-            .as_ref()
-            .unwrap()
-            .same((*peer).address.assume_init_ref().as_ref().unwrap())
-            && !(*peer)
-            .address
-            .assume_init_ref()
-            .as_ref()
-            .unwrap()
-            .is_broadcast()
-            || ((*peer).outgoing_peer_id as i32) < PROTOCOL_MAXIMUM_PEER_ID as i32
-            && session_id as i32 != (*peer).incoming_session_id as i32
+            || (*peer).outgoing_peer_id as u32 != PROTOCOL_MAXIMUM_PEER_ID
+                && header.session_id as i32 != (*peer).incoming_session_id as i32
         {
             return false;
         }
-    }
-    if flags as i32 & ENET_PROTOCOL_HEADER_FLAG_COMPRESSED as i32 != 0 {
-        let Some(compressor) = (*host).compressor.assume_init_mut() else {
+        if (*peer)
+            .simulation
+            .is_some_and(|simulation| enet_protocol_simulate_loss(host, simulation))
+        {
             return false;
-        };
-        let in_data = super::from_raw_parts_or_empty(
-            ((*host).received_data).add(header_size),
-            ((*host).received_data_length).wrapping_sub(header_size),
-        );
-        let out = super::from_raw_parts_or_empty_mut(
-            ((*host).packet_data[1_i32 as usize])
-                .as_mut_ptr()
-                .add(header_size),
-            ::core::mem::size_of::<[u8; 4096]>().wrapping_sub(header_size),
-        );
-        let original_size = compressor.decompress(in_data, out);
-        if original_size <= 0_i32 as usize
-            || original_size > ::core::mem::size_of::<[u8; 4096]>().wrapping_sub(header_size)
+        }
+        let received_address = (*host).received_address.assume_init_ref().as_ref().unwrap();
+        let peer_address = (*peer).address.assume_init_ref().as_ref().unwrap();
+        let moved = !received_address.same(peer_address) && !peer_address.is_broadcast();
+        // datagrams along the peer's other paths don't move it, see `enet_protocol_probe_paths`
+        alternate_path = moved
+            && (*peer)
+                .paths
+                .assume_init_ref()
+                .iter()
+                .any(|path| path.address.same(received_address));
+        migrating = moved && !alternate_path;
+        if migrating
+            && !((*host).connection_migration
+                && (*peer).protocol_version > ProtocolVersion::Enet
+                && ((*peer).state == ENET_PEER_STATE_CONNECTED as i32 as u32
+                    || (*peer).state == ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32))
         {
             return false;
         }
-        copy_nonoverlapping(
-            header as *const u8,
-            ((*host).packet_data[1_i32 as usize]).as_mut_ptr(),
-            header_size,
-        );
-        (*host).received_data = ((*host).packet_data[1_i32 as usize]).as_mut_ptr();
-        (*host).received_data_length = header_size.wrapping_add(original_size);
     }
-    if let Some(checksum_fn) = (*host).checksum.assume_init_ref() {
-        let checksum_addr: *mut u8 =
-            ((*host).received_data).add(header_size.wrapping_sub(::core::mem::size_of::<u32>()));
-        let mut desired_checksum: u32 = 0;
+    #[cfg(feature = "crypto")]
+    if peer.is_null() {
+        if let Some(connect_authentication) = (*host).connect_authentication {
+            if !connect_authentication.verify(
+                from_raw_parts_or_empty((*host).received_data, (*host).received_data_length),
+                (*host).time.assume_init_ref()(),
+                (*host).connect_replays.assume_init_mut(),
+            ) {
+                enet_host_audit_connect(
+                    host,
+                    (*host).received_address.assume_init_ref().as_ref().unwrap(),
+                    ConnectOutcome::AuthenticationFailed,
+                );
+                return false;
+            }
+            (*host).received_data_length =
+                ((*host).received_data_length).wrapping_sub(crate::CONNECT_AUTHENTICATION_OVERHEAD);
+        }
+    }
+    if (*host).received_data_length < header.size {
+        return enet_protocol_report_violation(host, event, ProtocolViolationKind::TruncatedHeader);
+    }
+    if header.flags as i32 & ENET_PROTOCOL_HEADER_FLAG_COMPRESSED as i32 != 0 {
+        let Some(compressor) = (*host).compressor.assume_init_mut() else {
+            return enet_protocol_report_violation(
+                host,
+                event,
+                ProtocolViolationKind::DecompressionFailed,
+            );
+        };
+        let in_data = from_raw_parts_or_empty(
+            ((*host).received_data).add(header.size),
+            (*host).received_data_length - header.size,
+        );
+        let packet_data = &mut (*host).packet_data[1];
+        let out = &mut packet_data[header.size..];
+        let original_size = compressor.decompress(in_data, out);
+        if original_size == 0 || original_size > out.len() {
+            return enet_protocol_report_violation(
+                host,
+                event,
+                ProtocolViolationKind::DecompressionFailed,
+            );
+        }
         copy_nonoverlapping(
-            checksum_addr.cast_const(),
-            core::ptr::addr_of_mut!(desired_checksum).cast(),
-            ::core::mem::size_of::<u32>(),
+            (*host).received_data.cast_const(),
+            ((*host).packet_data[1]).as_mut_ptr(),
+            header.size,
         );
-        let mut buffer: ENetBuffer = ENetBuffer {
-            data: core::ptr::null_mut(),
-            data_length: 0,
-        };
-        let checksum = if !peer.is_null() {
-            (*peer).connect_id
+        (*host).received_data = ((*host).packet_data[1]).as_mut_ptr();
+        (*host).received_data_length = header.size + original_size;
+    }
+    if let (Some(checksum_fn), Some(desired_checksum)) =
+        ((*host).checksum.assume_init_ref(), header.checksum)
+    {
+        // the checksum is calculated with the connect ID in place of the checksum
+        let checksum = if peer.is_null() {
+            0_u32
         } else {
-            0_i32 as u32
+            (*peer).connect_id
         };
         copy_nonoverlapping(
-            core::ptr::addr_of!(checksum).cast(),
-            checksum_addr,
-            ::core::mem::size_of::<u32>(),
+            checksum.to_ne_bytes().as_ptr(),
+            ((*host).received_data).add(header.size - size_of::<u32>()),
+            size_of::<u32>(),
         );
-        buffer.data = (*host).received_data;
-        buffer.data_length = (*host).received_data_length;
-        let in_buffers = [super::from_raw_parts_or_empty(
-            buffer.data,
-            buffer.data_length,
+        let in_buffers = [from_raw_parts_or_empty(
+            (*host).received_data,
+            (*host).received_data_length,
         )];
-        if checksum_fn(&in_buffers) != desired_checksum {
-            return false;
+        if checksum_fn(&in_buffers) != u32::from_ne_bytes(desired_checksum) {
+            return enet_protocol_report_violation(host, event, ProtocolViolationKind::BadChecksum);
         }
     }
+    if migrating && !enet_protocol_handle_migration(host, peer, header.size) {
+        return false;
+    }
     if !peer.is_null() {
-        *(*peer).address.assume_init_mut() = Some(
-            (*host)
-                .received_address
-                .assume_init_ref()
-                .as_ref()
-                .cloned()
-                .unwrap(),
-        );
+        if !alternate_path {
+            *(*peer).address.assume_init_mut() = Some(
+                (*host)
+                    .received_address
+                    .assume_init_ref()
+                    .as_ref()
+                    .cloned()
+                    .unwrap(),
+            );
+        }
+        if (*host).received_congestion_experienced {
+            enet_peer_congestion_experienced(peer);
+        }
+        if let Some(hop_limit) = (*host).received_hop_limit {
+            (*peer).hop_limit = Some(hop_limit);
+        }
+        if let Some(destination) = (*host).received_destination.assume_init_mut().take() {
+            *(*peer).local_address.assume_init_mut() = Some(destination);
+        }
         (*peer).incoming_data_total = ((*peer).incoming_data_total as usize)
             .wrapping_add((*host).received_data_length)
             as u32;
     }
-    current_data = ((*host).received_data).add(header_size);
-    while current_data < ((*host).received_data).add((*host).received_data_length) {
-        command = current_data.cast();
-        if current_data.offset(::core::mem::size_of::<ENetProtocolCommandHeader>() as u64 as isize)
-            > ((*host).received_data).add((*host).received_data_length)
-        {
+    let mut reader = ENetReader::new(from_raw_parts_or_empty(
+        (*host).received_data,
+        (*host).received_data_length,
+    ));
+    _ = reader.read_bytes(header.size);
+    let mut command_count = 0;
+    while !reader.is_empty() {
+        command_count += 1;
+        if (*host).strict_validation && command_count > PROTOCOL_MAXIMUM_PACKET_COMMANDS {
+            enet_protocol_report_violation(host, event, ProtocolViolationKind::TooManyCommands);
             break;
         }
-        let command_number =
-            ((*command).header.command as i32 & ENET_PROTOCOL_COMMAND_MASK as i32) as u8;
-        if command_number as i32 >= ENET_PROTOCOL_COMMAND_COUNT as i32 {
+        let frame = match enet_protocol_parse_command(&mut reader) {
+            Ok(frame) => frame,
+            Err(kind) => {
+                enet_protocol_report_violation(host, event, kind);
+                break;
+            }
+        };
+        let command_number = frame.command_number;
+        // a redirect only applies to the disconnect command right after it
+        let redirect = if peer.is_null() {
+            None
+        } else {
+            (*peer).pending_redirect.assume_init_mut().take()
+        };
+        if peer.is_null() == (command_number as i32 != ENET_PROTOCOL_COMMAND_CONNECT as i32) {
+            enet_protocol_report_violation(host, event, ProtocolViolationKind::UnexpectedCommand);
             break;
         }
-        let command_size = COMMAND_SIZES[command_number as usize];
-        if command_size == 0_i32 as usize
-            || current_data.add(command_size)
-            > ((*host).received_data).add((*host).received_data_length)
+        // decode the command into an aligned copy, leaving the received datagram untouched
+        let mut command: ENetProtocol = zeroed();
+        copy_nonoverlapping(
+            frame.command.as_ptr(),
+            (&raw mut command).cast::<u8>(),
+            frame.command.len(),
+        );
+        command.header.reliable_sequence_number =
+            u16::from_be(command.header.reliable_sequence_number);
+        let command: *const ENetProtocol = &raw const command;
+        if (*host).strict_validation {
+            if let Some(kind) = enet_protocol_validate_command(
+                host,
+                peer,
+                command,
+                command_number,
+                frame.data.len(),
+            ) {
+                enet_protocol_report_violation(host, event, kind);
+                break;
+            }
+        }
+        let packet_length = match command_number as u32 {
+            ENET_PROTOCOL_COMMAND_SEND_FRAGMENT
+            | ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE_FRAGMENT => {
+                Some(u32::from_be((*command).send_fragment.total_length) as usize)
+            }
+            ENET_PROTOCOL_COMMAND_SEND_RELIABLE
+            | ENET_PROTOCOL_COMMAND_SEND_UNRELIABLE
+            | ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED => Some(frame.data.len()),
+            _ => None,
+        };
+        if !peer.is_null()
+            && packet_length.is_some_and(|packet_length| {
+                packet_length
+                    > enet_peer_channel_maximum_packet_size(peer, (*command).header.channel_id)
+            })
         {
+            enet_protocol_report_violation(host, event, ProtocolViolationKind::PacketTooLarge);
             break;
         }
-        current_data = current_data.add(command_size);
-        if peer.is_null() && command_number as i32 != ENET_PROTOCOL_COMMAND_CONNECT as i32 {
+        if !peer.is_null() && !enet_protocol_channel_mode_allows(host, command, command_number) {
+            enet_protocol_report_violation(host, event, ProtocolViolationKind::ChannelModeMismatch);
             break;
         }
-        (*command).header.reliable_sequence_number =
-            u16::from_be((*command).header.reliable_sequence_number);
         match command_number as i32 {
             1 => {
                 if enet_protocol_handle_acknowledge(host, event, peer, command) != 0 {
@@ -1555,10 +2152,7 @@ unsafe fn enet_protocol_handle_incoming_commands<S: Socket>(
                 }
             }
             2 => {
-                if !peer.is_null() {
-                    break;
-                }
-                peer = enet_protocol_handle_connect(host, header, command);
+                peer = enet_protocol_handle_connect(host, command);
                 if peer.is_null() {
                     break;
                 }
@@ -1569,7 +2163,7 @@ unsafe fn enet_protocol_handle_incoming_commands<S: Socket>(
                 }
             }
             4 => {
-                if enet_protocol_handle_disconnect(host, peer, command) != 0 {
+                if enet_protocol_handle_disconnect(host, peer, command, redirect) != 0 {
                     break;
                 }
             }
@@ -1579,25 +2173,30 @@ unsafe fn enet_protocol_handle_incoming_commands<S: Socket>(
                 }
             }
             6 => {
-                if enet_protocol_handle_send_reliable(host, peer, command, &mut current_data) != 0 {
+                if enet_protocol_handle_send_reliable(host, peer, command, frame.data) != 0 {
                     break;
                 }
             }
             7 => {
-                if enet_protocol_handle_send_unreliable(host, peer, command, &mut current_data) != 0
-                {
+                if enet_protocol_handle_send_unreliable(host, peer, command, frame.data) != 0 {
                     break;
                 }
             }
             9 => {
-                if enet_protocol_handle_send_unsequenced(host, peer, command, &mut current_data)
-                    != 0
-                {
+                if enet_protocol_handle_send_unsequenced(host, peer, command, frame.data) != 0 {
                     break;
                 }
             }
             8 => {
-                if enet_protocol_handle_send_fragment(host, peer, command, &mut current_data) != 0 {
+                let result = enet_protocol_handle_send_fragment(host, peer, command, frame.data);
+                if result == -2_i32 {
+                    enet_protocol_report_violation(
+                        host,
+                        event,
+                        ProtocolViolationKind::FragmentDataExceeded,
+                    );
+                }
+                if result != 0 {
                     break;
                 }
             }
@@ -1612,12 +2211,8 @@ unsafe fn enet_protocol_handle_incoming_commands<S: Socket>(
                 }
             }
             12 => {
-                if enet_protocol_handle_send_unreliable_fragment(
-                    host,
-                    peer,
-                    command,
-                    &mut current_data,
-                ) != 0
+                if enet_protocol_handle_send_unreliable_fragment(host, peer, command, frame.data)
+                    != 0
                 {
                     break;
                 }
@@ -1626,16 +2221,20 @@ unsafe fn enet_protocol_handle_incoming_commands<S: Socket>(
                 break;
             }
         }
+        // pings and protocol messages don't keep a peer from going idle
+        if matches!(command_number, 6..=9 | 12) && (*command).header.channel_id != 0xff {
+            (*peer).last_data_time = (*host).service_time;
+            (*peer).idle_reported = false;
+        }
         if peer.is_null()
             || (*command).header.command as i32 & ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE as i32
-            == 0_i32
+                == 0_i32
         {
             continue;
         }
-        if flags as i32 & ENET_PROTOCOL_HEADER_FLAG_SENT_TIME as i32 == 0 {
+        let Some(sent_time) = header.sent_time else {
             break;
-        }
-        let sent_time = u16::from_be((*header).sent_time);
+        };
         match (*peer).state {
             7 | 2 | 0 | 9 => {}
             8 => {
@@ -1650,6 +2249,9 @@ unsafe fn enet_protocol_handle_incoming_commands<S: Socket>(
             }
         }
     }
+    if !peer.is_null() {
+        *(*peer).pending_redirect.assume_init_mut() = None;
+    }
     if !event.is_null() && (*event).type_0 != ENET_EVENT_TYPE_NONE as i32 as u32 {
         return true;
     }
@@ -1658,42 +2260,120 @@ unsafe fn enet_protocol_handle_incoming_commands<S: Socket>(
 unsafe fn enet_protocol_receive_incoming_commands<S: Socket>(
     host: *mut ENetHost<S>,
     event: *mut ENetEvent<S>,
-) -> Result<bool, S::Error> {
+) -> Result<bool, ServiceError<S>> {
     let mut packets: i32;
     packets = 0_i32;
     while packets < 256_i32 {
-        let mut buffer: ENetBuffer = ENetBuffer {
-            data: core::ptr::null_mut(),
-            data_length: 0,
-        };
-        buffer.data = ((*host).packet_data[0_i32 as usize]).as_mut_ptr();
-        buffer.data_length = ::core::mem::size_of::<[u8; PROTOCOL_MAXIMUM_MTU]>();
-        let received_length = match (*host)
-            .socket
-            .assume_init_mut()
-            .receive(&mut *buffer.data.cast::<[u8; 4096]>())
-        {
-            Ok(Some((received_address, PacketReceived::Complete(received_length)))) => {
-                *(*host).received_address.assume_init_mut() = Some(received_address);
-                received_length
+        if (*host).datagram_budget == 0 {
+            return Ok(false);
+        }
+        let batch = (*host).receive_batch.assume_init_mut();
+        if batch.next == batch.received.len() {
+            batch.received.clear();
+            batch.next = 0;
+            let loopback = (*host).loopback_datagrams.assume_init_mut();
+            batch.loopback = !loopback.is_empty();
+            if batch.loopback {
+                // datagrams between loopback peers skip the socket, see `Host::connect_loopback`
+                for buffer in &mut batch.buffers {
+                    let Some((address, datagram)) = loopback.pop_front() else {
+                        break;
+                    };
+                    let length = datagram.len().min(PROTOCOL_MAXIMUM_MTU);
+                    buffer[..length].copy_from_slice(&datagram[..length]);
+                    batch.received.push((
+                        address,
+                        PacketReceived::Complete(length),
+                        ReceiveMetadata::default(),
+                    ));
+                }
+            } else {
+                (*host)
+                    .socket
+                    .assume_init_mut()
+                    .receive_batch_with_metadata(&mut batch.buffers, &mut batch.received)
+                    .map_err(|error| ServiceError {
+                        stage: ServiceStage::Receive,
+                        address: None,
+                        error,
+                    })?;
+                if batch.received.is_empty() {
+                    return Ok(false);
+                }
             }
-            Ok(Some((_, PacketReceived::Partial))) => {
-                continue;
+            batch.time = (*host).time.assume_init_ref()();
+        }
+        let index = batch.next;
+        batch.next += 1;
+        (*host).datagram_budget -= 1;
+        let (received_address, received, metadata) = &mut batch.received[index];
+        let Some(received_length) = received.length() else {
+            continue;
+        };
+        if enet_host_ban_expiry(host, received_address).is_some() {
+            // transformed datagrams can't be checked without decoding them, so aren't audited
+            if (*host).transforms.assume_init_ref().is_empty()
+                && enet_protocol_is_connect(
+                    &batch.buffers[index][..received_length.min(PROTOCOL_MAXIMUM_MTU)],
+                    (*host).checksum.assume_init_ref().is_some(),
+                    (*host).peer_count > PROTOCOL_MAXIMUM_PEER_ID as usize,
+                )
+            {
+                enet_host_audit_connect(host, received_address, ConnectOutcome::Banned);
             }
-            Ok(None) => {
-                return Ok(false);
+            packets += 1;
+            continue;
+        }
+        (*host).receiving_loopback = batch.loopback;
+        (*host).received_congestion_experienced =
+            matches!(received, PacketReceived::CongestionExperienced(_))
+                || metadata.ecn == Some(ECN_CONGESTION_EXPERIENCED);
+        (*host).receive_time = metadata.timestamp.unwrap_or(batch.time);
+        (*host).received_hop_limit = metadata.hop_limit;
+        *(*host).received_destination.assume_init_mut() = metadata.destination.take();
+        *(*host).received_address.assume_init_mut() = Some(received_address.clone());
+        let buffer = ENetBuffer {
+            data: batch.buffers[index].as_mut_ptr(),
+            data_length: PROTOCOL_MAXIMUM_MTU,
+        };
+        let mut received_length = received_length.min(buffer.data_length);
+        if !(*host).transforms.assume_init_ref().is_empty() {
+            let mut data = super::from_raw_parts_or_empty(buffer.data, received_length).to_vec();
+            let mut transformed = true;
+            let mut connect_id = || enet_protocol_connect_id(host, received_address);
+            for transform in (*host).transforms.assume_init_mut().iter_mut().rev() {
+                if !transform.decode_connection(&mut data, &mut connect_id) {
+                    transformed = false;
+                    break;
+                }
             }
-            Err(err) => {
-                return Err(err);
+            if !transformed || data.len() > PROTOCOL_MAXIMUM_MTU {
+                packets += 1;
+                continue;
             }
-        };
-        (*host).received_data = ((*host).packet_data[0_i32 as usize]).as_mut_ptr();
+            copy_nonoverlapping(data.as_ptr(), buffer.data, data.len());
+            received_length = data.len();
+        }
+        (*host).received_data = buffer.data;
         (*host).received_data_length = received_length;
         (*host).total_received_data = (*host)
             .total_received_data
             .wrapping_add(received_length as u32);
         (*host).total_received_packets = ((*host).total_received_packets).wrapping_add(1);
-        if enet_protocol_handle_incoming_commands(host, event) {
+        let handled_event = enet_protocol_handle_incoming_commands(host, event);
+        let acknowledging_peer =
+            core::mem::replace(&mut (*host).acknowledging_peer, core::ptr::null_mut());
+        if (*host).acknowledgement_mode == AcknowledgementMode::Immediate
+            && !acknowledging_peer.is_null()
+        {
+            enet_protocol_send_outgoing_commands(
+                host,
+                core::ptr::null_mut(),
+                0_i32,
+                acknowledging_peer,
+            );
+        }
+        if handled_event {
             return Ok(true);
         }
         packets += 1;
@@ -1706,55 +2386,50 @@ unsafe fn enet_protocol_send_acknowledgements<S: Socket>(
 ) {
     let mut command: *mut ENetProtocol = ((*host).commands).as_mut_ptr().add((*host).command_count);
     let mut buffer: *mut ENetBuffer = ((*host).buffers).as_mut_ptr().add((*host).buffer_count);
-    let mut acknowledgement: *mut ENetAcknowledgement;
-    let mut current_acknowledgement: ENetListIterator;
     let mut reliable_sequence_number: u16;
-    current_acknowledgement = (*peer).acknowledgements.sentinel.next;
-    while current_acknowledgement != core::ptr::addr_of_mut!((*peer).acknowledgements.sentinel) {
+    while let Some(acknowledgement) = (*peer).acknowledgements.assume_init_ref().front().copied() {
         if command
             >= ((*host).commands).as_mut_ptr().offset(
-            (::core::mem::size_of::<[ENetProtocol; 32]>() as u64)
-                .wrapping_div(::core::mem::size_of::<ENetProtocol>() as u64)
-                as isize,
-        )
+                (::core::mem::size_of::<[ENetProtocol; 32]>() as u64)
+                    .wrapping_div(::core::mem::size_of::<ENetProtocol>() as u64)
+                    as isize,
+            )
             || buffer
-            >= ((*host).buffers).as_mut_ptr().offset(
-            (::core::mem::size_of::<[ENetBuffer; 65]>() as u64)
-                .wrapping_div(::core::mem::size_of::<ENetBuffer>() as u64)
-                as isize,
-        )
+                >= ((*host).buffers).as_mut_ptr().offset(
+                    (::core::mem::size_of::<[ENetBuffer; 65]>() as u64)
+                        .wrapping_div(::core::mem::size_of::<ENetBuffer>() as u64)
+                        as isize,
+                )
             || ((*peer).mtu as usize).wrapping_sub((*host).packet_size)
-            < ::core::mem::size_of::<ENetProtocolAcknowledge>()
+                < ::core::mem::size_of::<ENetProtocolAcknowledge>()
         {
             (*peer).flags = ((*peer).flags as i32 | ENET_PEER_FLAG_CONTINUE_SENDING as i32) as u16;
             break;
-        } else {
-            acknowledgement = current_acknowledgement.cast();
-            current_acknowledgement = (*current_acknowledgement).next;
-            (*buffer).data = command.cast();
-            (*buffer).data_length = ::core::mem::size_of::<ENetProtocolAcknowledge>();
-            (*host).packet_size = (*host).packet_size.wrapping_add((*buffer).data_length);
-            reliable_sequence_number = (*acknowledgement)
-                .command
-                .header
-                .reliable_sequence_number
-                .to_be();
-            (*command).header.command = ENET_PROTOCOL_COMMAND_ACKNOWLEDGE as i32 as u8;
-            (*command).header.channel_id = (*acknowledgement).command.header.channel_id;
-            (*command).header.reliable_sequence_number = reliable_sequence_number;
-            (*command).acknowledge.received_reliable_sequence_number = reliable_sequence_number;
-            (*command).acknowledge.received_sent_time =
-                ((*acknowledgement).sent_time as u16).to_be();
-            if (*acknowledgement).command.header.command as i32 & ENET_PROTOCOL_COMMAND_MASK as i32
-                == ENET_PROTOCOL_COMMAND_DISCONNECT as i32
-            {
-                enet_protocol_dispatch_state(host, peer, ENET_PEER_STATE_ZOMBIE);
-            }
-            enet_list_remove(&mut (*acknowledgement).acknowledgement_list);
-            enet_free(acknowledgement.cast(), Layout::new::<ENetAcknowledgement>());
-            command = command.offset(1);
-            buffer = buffer.offset(1);
         }
+        (*peer).acknowledgements.assume_init_mut().pop_front();
+        if (*peer).acknowledgements.assume_init_ref().is_empty() {
+            (*peer).acknowledgement_time = None;
+        }
+        (*buffer).data = command.cast();
+        (*buffer).data_length = ::core::mem::size_of::<ENetProtocolAcknowledge>();
+        (*host).packet_size = (*host).packet_size.wrapping_add((*buffer).data_length);
+        reliable_sequence_number = acknowledgement
+            .command
+            .header
+            .reliable_sequence_number
+            .to_be();
+        (*command).header.command = ENET_PROTOCOL_COMMAND_ACKNOWLEDGE as i32 as u8;
+        (*command).header.channel_id = acknowledgement.command.header.channel_id;
+        (*command).header.reliable_sequence_number = reliable_sequence_number;
+        (*command).acknowledge.received_reliable_sequence_number = reliable_sequence_number;
+        (*command).acknowledge.received_sent_time = (acknowledgement.sent_time as u16).to_be();
+        if acknowledgement.command.header.command as i32 & ENET_PROTOCOL_COMMAND_MASK as i32
+            == ENET_PROTOCOL_COMMAND_DISCONNECT as i32
+        {
+            enet_protocol_dispatch_state(host, peer, ENET_PEER_STATE_ZOMBIE);
+        }
+        command = command.offset(1);
+        buffer = buffer.offset(1);
     }
     (*host).command_count = command.offset_from(((*host).commands).as_mut_ptr()) as i64 as usize;
     (*host).buffer_count = buffer.offset_from(((*host).buffers).as_mut_ptr()) as i64 as usize;
@@ -1765,13 +2440,13 @@ unsafe fn enet_protocol_check_timeouts<S: Socket>(
     event: *mut ENetEvent<S>,
 ) -> i32 {
     let mut outgoing_command: *mut ENetOutgoingCommand;
-    let mut current_command: ENetListIterator;
-    current_command = (*peer).sent_reliable_commands.sentinel.next;
-    let insert_position = (*peer).outgoing_commands.sentinel.next;
-    let insert_send_reliable_position = (*peer).outgoing_send_reliable_commands.sentinel.next;
-    while current_command != core::ptr::addr_of_mut!((*peer).sent_reliable_commands.sentinel) {
-        outgoing_command = current_command.cast();
-        current_command = (*current_command).next;
+    let mut current_command: usize = 0;
+    let insert_before = enet_protocol_first_command(&raw mut (*peer).outgoing_commands);
+    let insert_send_reliable_before =
+        enet_protocol_first_command(&raw mut (*peer).outgoing_send_reliable_commands);
+    while current_command < (*peer).sent_reliable_commands.assume_init_ref().len() {
+        outgoing_command = (*peer).sent_reliable_commands.assume_init_ref()[current_command];
+        current_command += 1;
         if (if ((*host).service_time).wrapping_sub((*outgoing_command).sent_time)
             >= 86400000_i32 as u32
         {
@@ -1784,103 +2459,246 @@ unsafe fn enet_protocol_check_timeouts<S: Socket>(
         }
         if (*peer).earliest_timeout == 0_i32 as u32
             || ((*outgoing_command).sent_time).wrapping_sub((*peer).earliest_timeout)
-            >= 86400000_i32 as u32
+                >= 86400000_i32 as u32
         {
             (*peer).earliest_timeout = (*outgoing_command).sent_time;
         }
         if (*peer).earliest_timeout != 0_i32 as u32
             && ((if ((*host).service_time).wrapping_sub((*peer).earliest_timeout)
-            >= 86400000_i32 as u32
-        {
-            ((*peer).earliest_timeout).wrapping_sub((*host).service_time)
-        } else {
-            ((*host).service_time).wrapping_sub((*peer).earliest_timeout)
-        }) >= (*peer).timeout_maximum
-            || (1_i32 << ((*outgoing_command).send_attempts as i32 - 1_i32)) as u32
-            >= (*peer).timeout_limit
-            && (if ((*host).service_time).wrapping_sub((*peer).earliest_timeout)
-            >= 86400000_i32 as u32
-        {
-            ((*peer).earliest_timeout).wrapping_sub((*host).service_time)
-        } else {
-            ((*host).service_time).wrapping_sub((*peer).earliest_timeout)
-        }) >= (*peer).timeout_minimum)
+                >= 86400000_i32 as u32
+            {
+                ((*peer).earliest_timeout).wrapping_sub((*host).service_time)
+            } else {
+                ((*host).service_time).wrapping_sub((*peer).earliest_timeout)
+            }) >= (*peer).timeout_maximum
+                || (1_i32 << ((*outgoing_command).send_attempts as i32 - 1_i32)) as u32
+                    >= (*peer).timeout_limit
+                    && (if ((*host).service_time).wrapping_sub((*peer).earliest_timeout)
+                        >= 86400000_i32 as u32
+                    {
+                        ((*peer).earliest_timeout).wrapping_sub((*host).service_time)
+                    } else {
+                        ((*host).service_time).wrapping_sub((*peer).earliest_timeout)
+                    }) >= (*peer).timeout_minimum)
         {
             enet_protocol_notify_disconnect(host, peer, event);
             return 1_i32;
         }
-        (*peer).packets_lost = ((*peer).packets_lost).wrapping_add(1);
         (*outgoing_command).round_trip_timeout = (*outgoing_command)
             .round_trip_timeout
             .wrapping_mul(2_i32 as u32);
-        if !((*outgoing_command).packet).is_null() {
-            (*peer).reliable_data_in_transit = (*peer)
-                .reliable_data_in_transit
-                .wrapping_sub((*outgoing_command).fragment_length as u32);
-            enet_list_insert(
-                insert_send_reliable_position,
-                enet_list_remove(&mut (*outgoing_command).outgoing_command_list),
-            );
-        } else {
-            enet_list_insert(
-                insert_position,
-                enet_list_remove(&mut (*outgoing_command).outgoing_command_list),
-            );
-        }
-        if current_command == (*peer).sent_reliable_commands.sentinel.next
-            && ((*peer).sent_reliable_commands.sentinel.next
-            != core::ptr::addr_of_mut!((*peer).sent_reliable_commands.sentinel))
-        {
-            outgoing_command = current_command.cast();
-            (*peer).next_timeout = ((*outgoing_command).sent_time)
-                .wrapping_add((*outgoing_command).round_trip_timeout);
+        current_command -= 1;
+        (*peer)
+            .sent_reliable_commands
+            .assume_init_mut()
+            .remove(current_command);
+        enet_protocol_resend_command(
+            host,
+            peer,
+            outgoing_command,
+            insert_before,
+            insert_send_reliable_before,
+        );
+        if current_command == 0 {
+            if let Some(&outgoing_command) =
+                (*peer).sent_reliable_commands.assume_init_ref().front()
+            {
+                (*peer).next_timeout = ((*outgoing_command).sent_time)
+                    .wrapping_add((*outgoing_command).round_trip_timeout);
+            }
         }
     }
     0_i32
 }
+/// The command at the front of an outgoing queue, or null if it's empty.
+unsafe fn enet_protocol_first_command(
+    queue: *mut MaybeUninit<VecDeque<*mut ENetOutgoingCommand>>,
+) -> *mut ENetOutgoingCommand {
+    (*queue)
+        .assume_init_ref()
+        .front()
+        .copied()
+        .unwrap_or(core::ptr::null_mut())
+}
+/// Queue a reliable command, just removed from the sent commands, to be sent again ahead of the
+/// given commands, or at the end of the outgoing queues if they're null or no longer queued.
+unsafe fn enet_protocol_resend_command<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+    outgoing_command: *mut ENetOutgoingCommand,
+    insert_before: *mut ENetOutgoingCommand,
+    insert_send_reliable_before: *mut ENetOutgoingCommand,
+) {
+    (*peer).packets_lost = ((*peer).packets_lost).wrapping_add(1);
+    (*peer).reliable_resends = ((*peer).reliable_resends).wrapping_add(1);
+    let channel_id = usize::from((*outgoing_command).command.header.channel_id);
+    if channel_id < (*peer).channel_count {
+        let channel = (*peer).channels.add(channel_id);
+        (*channel).reliable_resends = (*channel).reliable_resends.wrapping_add(1);
+        enet_protocol_report_resend(host, peer, outgoing_command);
+    }
+    (*outgoing_command).later_acknowledgements = 0;
+    if !((*outgoing_command).packet).is_null() {
+        (*peer).reliable_data_in_transit = (*peer)
+            .reliable_data_in_transit
+            .wrapping_sub((*outgoing_command).fragment_length as u32);
+        let queue = (*peer).outgoing_send_reliable_commands.assume_init_mut();
+        let position = queue
+            .iter()
+            .position(|&queued| queued == insert_send_reliable_before)
+            .unwrap_or(queue.len());
+        queue.insert(position, outgoing_command);
+    } else {
+        let queue = (*peer).outgoing_commands.assume_init_mut();
+        let position = queue
+            .iter()
+            .position(|&queued| queued == insert_before)
+            .unwrap_or(queue.len());
+        queue.insert(position, outgoing_command);
+    }
+    match (*outgoing_command).command.header.command & ENET_PROTOCOL_COMMAND_MASK as u8 {
+        // the payload must follow the connect command, which is the only command a host accepts
+        // from an unknown peer
+        command if command == ENET_PROTOCOL_COMMAND_CONNECT as u8 => {
+            enet_peer_send_resume_ticket(peer);
+            enet_peer_send_connect_payload(peer);
+        }
+        // while the accept data must precede the verify connect command
+        command if command == ENET_PROTOCOL_COMMAND_VERIFY_CONNECT as u8 => {
+            enet_peer_send_accept_data(peer, outgoing_command);
+        }
+        // and the redirect the disconnect command
+        command if command == ENET_PROTOCOL_COMMAND_DISCONNECT as u8 => {
+            enet_peer_send_redirect(peer, outgoing_command);
+        }
+        _ => {}
+    }
+}
+/// Count an acknowledgement against the reliable commands sent before the acknowledged one, and
+/// resend those acknowledgements have skipped [`ENetHost::fast_retransmit`] times without waiting
+/// for them to time out.
+unsafe fn enet_protocol_fast_retransmit<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+    received_sent_time: u32,
+) {
+    let Some(threshold) = (*host).fast_retransmit else {
+        return;
+    };
+    let insert_before = enet_protocol_first_command(&raw mut (*peer).outgoing_commands);
+    let insert_send_reliable_before =
+        enet_protocol_first_command(&raw mut (*peer).outgoing_send_reliable_commands);
+    let mut current_command: usize = 0;
+    while current_command < (*peer).sent_reliable_commands.assume_init_ref().len() {
+        let outgoing_command = (*peer).sent_reliable_commands.assume_init_ref()[current_command];
+        current_command += 1;
+        let sent_before = received_sent_time.wrapping_sub((*outgoing_command).sent_time);
+        if sent_before == 0 || sent_before >= 86400000_i32 as u32 {
+            continue;
+        }
+        (*outgoing_command).later_acknowledgements += 1;
+        if (*outgoing_command).later_acknowledgements >= threshold.max(1) {
+            current_command -= 1;
+            (*peer)
+                .sent_reliable_commands
+                .assume_init_mut()
+                .remove(current_command);
+            enet_protocol_resend_command(
+                host,
+                peer,
+                outgoing_command,
+                insert_before,
+                insert_send_reliable_before,
+            );
+        }
+    }
+}
+/// Queue [`ENET_EVENT_TYPE_PACKET_RESENT`] for a timed out reliable packet which has been resent
+/// more than [`ENetHost::resend_event_threshold`] times. Fragmented packets are reported by their
+/// first fragment, so each resend is reported once.
+unsafe fn enet_protocol_report_resend<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+    outgoing_command: *mut ENetOutgoingCommand,
+) {
+    let Some(threshold) = (*host).resend_event_threshold else {
+        return;
+    };
+    let attempts = u32::from((*outgoing_command).send_attempts);
+    if ((*outgoing_command).packet).is_null()
+        || (*outgoing_command).fragment_offset != 0
+        || attempts <= threshold
+    {
+        return;
+    }
+    (*host)
+        .resend_reports
+        .assume_init_mut()
+        .push_back(ENetResendReport {
+            peer,
+            channel_id: (*outgoing_command).command.header.channel_id,
+            attempts,
+        });
+}
+/// Remove the command [`enet_protocol_check_outgoing_commands`] just took from one of a peer's
+/// outgoing queues, stepping back that queue's position over it.
+unsafe fn enet_protocol_remove_taken_command<S: Socket>(
+    peer: *mut ENetPeer<S>,
+    send_reliable: bool,
+    current_command: &mut usize,
+    current_send_reliable_command: &mut usize,
+) {
+    if send_reliable {
+        *current_send_reliable_command -= 1;
+        (*peer)
+            .outgoing_send_reliable_commands
+            .assume_init_mut()
+            .remove(*current_send_reliable_command);
+    } else {
+        *current_command -= 1;
+        (*peer)
+            .outgoing_commands
+            .assume_init_mut()
+            .remove(*current_command);
+    }
+}
 unsafe fn enet_protocol_check_outgoing_commands<S: Socket>(
     host: *mut ENetHost<S>,
     peer: *mut ENetPeer<S>,
-    sent_unreliable_commands: *mut ENetList,
+    sent_unreliable_commands: &mut Vec<*mut ENetOutgoingCommand>,
 ) -> i32 {
     let mut command: *mut ENetProtocol = ((*host).commands).as_mut_ptr().add((*host).command_count);
     let mut buffer: *mut ENetBuffer = ((*host).buffers).as_mut_ptr().add((*host).buffer_count);
-    let mut outgoing_command: *mut ENetOutgoingCommand = core::ptr::null_mut();
-    let mut current_command: ENetListIterator;
-    let mut current_send_reliable_command: ENetListIterator;
+    let mut outgoing_command: *mut ENetOutgoingCommand;
+    let mut current_command: usize = 0;
+    let mut current_send_reliable_command: usize = 0;
+    let mut send_reliable: bool;
     let mut channel: *mut ENetChannel = core::ptr::null_mut();
     let mut reliable_window: u16 = 0_i32 as u16;
     let mut command_size: usize;
     let mut window_wrap: i32 = 0_i32;
     let mut can_ping: i32 = 1_i32;
-    current_command = (*peer).outgoing_commands.sentinel.next;
-    current_send_reliable_command = (*peer).outgoing_send_reliable_commands.sentinel.next;
-    let mut current_block_55: u64;
     loop {
-        if current_command != core::ptr::addr_of_mut!((*peer).outgoing_commands.sentinel) {
-            outgoing_command = current_command.cast();
-            if current_send_reliable_command
-                != core::ptr::addr_of_mut!((*peer).outgoing_send_reliable_commands.sentinel)
-                && ((*(current_send_reliable_command.cast::<ENetOutgoingCommand>())).queue_time)
-                .wrapping_sub((*outgoing_command).queue_time)
-                >= 86400000_i32 as u32
-            {
-                current_block_55 = 13678975718891345113;
-            } else {
-                current_command = (*current_command).next;
-                current_block_55 = 1856101646708284338;
-            }
+        let outgoing_commands = (*peer).outgoing_commands.assume_init_ref();
+        let outgoing_send_reliable_commands =
+            (*peer).outgoing_send_reliable_commands.assume_init_ref();
+        if let Some(&next_command) = outgoing_commands.get(current_command) {
+            send_reliable = current_send_reliable_command < outgoing_send_reliable_commands.len()
+                && ((*outgoing_send_reliable_commands[current_send_reliable_command]).queue_time)
+                    .wrapping_sub((*next_command).queue_time)
+                    >= 86400000_i32 as u32;
         } else {
-            if current_send_reliable_command
-                == core::ptr::addr_of_mut!((*peer).outgoing_send_reliable_commands.sentinel)
-            {
+            if current_send_reliable_command >= outgoing_send_reliable_commands.len() {
                 break;
             }
-            current_block_55 = 13678975718891345113;
+            send_reliable = true;
         }
-        if let 13678975718891345113 = current_block_55 {
-            outgoing_command = current_send_reliable_command.cast();
-            current_send_reliable_command = (*current_send_reliable_command).next;
+        if send_reliable {
+            outgoing_command = outgoing_send_reliable_commands[current_send_reliable_command];
+            current_send_reliable_command += 1;
+        } else {
+            outgoing_command = outgoing_commands[current_command];
+            current_command += 1;
         }
         if (*outgoing_command).command.header.command as i32
             & ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE as i32
@@ -1901,65 +2719,75 @@ unsafe fn enet_protocol_check_outgoing_commands<S: Socket>(
                 }
                 if ((*outgoing_command).send_attempts as i32) < 1_i32
                     && (*outgoing_command).reliable_sequence_number as i32
-                    % PEER_RELIABLE_WINDOW_SIZE as i32
-                    == 0
+                        % PEER_RELIABLE_WINDOW_SIZE as i32
+                        == 0
                     && ((*channel).reliable_windows[((reliable_window as i32
-                    + PEER_RELIABLE_WINDOWS as i32
-                    - 1_i32)
-                    % PEER_RELIABLE_WINDOWS as i32)
-                    as usize] as i32
-                    >= PEER_RELIABLE_WINDOW_SIZE as i32
-                    || (*channel).used_reliable_windows as i32
-                    & (((1_i32 << (PEER_FREE_RELIABLE_WINDOWS as i32 + 2_i32)) - 1_i32)
-                    << reliable_window as i32
-                    | ((1_i32 << (PEER_FREE_RELIABLE_WINDOWS as i32 + 2_i32)) - 1_i32)
-                    >> (PEER_RELIABLE_WINDOWS as i32 - reliable_window as i32))
-                    != 0)
+                        + PEER_RELIABLE_WINDOWS as i32
+                        - 1_i32)
+                        % PEER_RELIABLE_WINDOWS as i32)
+                        as usize] as i32
+                        >= PEER_RELIABLE_WINDOW_SIZE as i32
+                        || (*channel).used_reliable_windows as i32
+                            & (((1_i32 << (PEER_FREE_RELIABLE_WINDOWS as i32 + 2_i32)) - 1_i32)
+                                << reliable_window as i32
+                                | ((1_i32 << (PEER_FREE_RELIABLE_WINDOWS as i32 + 2_i32)) - 1_i32)
+                                    >> (PEER_RELIABLE_WINDOWS as i32 - reliable_window as i32))
+                            != 0)
                 {
                     window_wrap = 1_i32;
-                    current_send_reliable_command =
-                        &mut (*peer).outgoing_send_reliable_commands.sentinel;
+                    current_send_reliable_command = (*peer)
+                        .outgoing_send_reliable_commands
+                        .assume_init_ref()
+                        .len();
                     continue;
                 }
             }
             if !((*outgoing_command).packet).is_null() {
                 let window_size: u32 = ((*peer).packet_throttle)
-                    .wrapping_mul((*peer).window_size)
+                    .wrapping_mul(enet_peer_window_size(peer))
                     .wrapping_div(PEER_PACKET_THROTTLE_SCALE as i32 as u32);
                 if ((*peer).reliable_data_in_transit)
                     .wrapping_add((*outgoing_command).fragment_length as u32)
                     > (if window_size > (*peer).mtu {
-                    window_size
-                } else {
-                    (*peer).mtu
-                })
+                        window_size
+                    } else {
+                        (*peer).mtu
+                    })
                 {
-                    current_send_reliable_command =
-                        &mut (*peer).outgoing_send_reliable_commands.sentinel;
+                    current_send_reliable_command = (*peer)
+                        .outgoing_send_reliable_commands
+                        .assume_init_ref()
+                        .len();
                     continue;
                 }
             }
             can_ping = 0_i32;
+        } else if (*peer).unreliable_paused
+            && (*peer).state == ENET_PEER_STATE_CONNECTED as i32 as u32
+            && !((*outgoing_command).packet).is_null()
+            && ((*outgoing_command).command.header.channel_id as usize) < (*peer).channel_count
+        {
+            continue;
         }
         command_size = COMMAND_SIZES[((*outgoing_command).command.header.command as i32
             & ENET_PROTOCOL_COMMAND_MASK as i32) as usize];
         if command
             >= ((*host).commands).as_mut_ptr().offset(
-            (::core::mem::size_of::<[ENetProtocol; 32]>() as u64)
-                .wrapping_div(::core::mem::size_of::<ENetProtocol>() as u64)
-                as isize,
-        )
+                (::core::mem::size_of::<[ENetProtocol; 32]>() as u64)
+                    .wrapping_div(::core::mem::size_of::<ENetProtocol>() as u64)
+                    as isize,
+            )
             || buffer.offset(1_i32 as isize)
-            >= ((*host).buffers).as_mut_ptr().offset(
-            (::core::mem::size_of::<[ENetBuffer; 65]>() as u64)
-                .wrapping_div(::core::mem::size_of::<ENetBuffer>() as u64)
-                as isize,
-        )
+                >= ((*host).buffers).as_mut_ptr().offset(
+                    (::core::mem::size_of::<[ENetBuffer; 65]>() as u64)
+                        .wrapping_div(::core::mem::size_of::<ENetBuffer>() as u64)
+                        as isize,
+                )
             || ((*peer).mtu as usize).wrapping_sub((*host).packet_size) < command_size
             || !((*outgoing_command).packet).is_null()
-            && (((*peer).mtu as usize).wrapping_sub((*host).packet_size) as u16 as i32)
-            < command_size.wrapping_add((*outgoing_command).fragment_length as usize) as u16
-            as i32
+                && (((*peer).mtu as usize).wrapping_sub((*host).packet_size) as u16 as i32)
+                    < command_size.wrapping_add((*outgoing_command).fragment_length as usize) as u16
+                        as i32
         {
             (*peer).flags = ((*peer).flags as i32 | ENET_PEER_FLAG_CONTINUE_SENDING as i32) as u16;
             break;
@@ -1983,16 +2811,20 @@ unsafe fn enet_protocol_check_outgoing_commands<S: Socket>(
                             (4_i32 as u32).wrapping_mul((*peer).round_trip_time_variance),
                         );
                 }
-                if (*peer).sent_reliable_commands.sentinel.next
-                    == core::ptr::addr_of_mut!((*peer).sent_reliable_commands.sentinel)
-                {
+                if (*peer).sent_reliable_commands.assume_init_ref().is_empty() {
                     (*peer).next_timeout =
                         ((*host).service_time).wrapping_add((*outgoing_command).round_trip_timeout);
                 }
-                enet_list_insert(
-                    &mut (*peer).sent_reliable_commands.sentinel,
-                    enet_list_remove(&mut (*outgoing_command).outgoing_command_list),
+                enet_protocol_remove_taken_command(
+                    peer,
+                    send_reliable,
+                    &mut current_command,
+                    &mut current_send_reliable_command,
                 );
+                (*peer)
+                    .sent_reliable_commands
+                    .assume_init_mut()
+                    .push_back(outgoing_command);
                 (*outgoing_command).sent_time = (*host).service_time;
                 (*host).header_flags = ((*host).header_flags as i32
                     | ENET_PROTOCOL_HEADER_FLAG_SENT_TIME as i32)
@@ -2016,40 +2848,49 @@ unsafe fn enet_protocol_check_outgoing_commands<S: Socket>(
                         let unreliable_sequence_number: u16 =
                             (*outgoing_command).unreliable_sequence_number;
                         loop {
-                            (*(*outgoing_command).packet).reference_count =
-                                ((*(*outgoing_command).packet).reference_count).wrapping_sub(1);
-                            if (*(*outgoing_command).packet).reference_count == 0_i32 as usize {
+                            if enet_packet_release((*outgoing_command).packet) {
                                 enet_packet_destroy((*outgoing_command).packet);
                             }
-                            enet_list_remove(&mut (*outgoing_command).outgoing_command_list);
-                            enet_free(
-                                outgoing_command.cast(),
-                                Layout::new::<ENetOutgoingCommand>(),
+                            enet_protocol_remove_taken_command(
+                                peer,
+                                send_reliable,
+                                &mut current_command,
+                                &mut current_send_reliable_command,
                             );
-                            if current_command
-                                == core::ptr::addr_of_mut!((*peer).outgoing_commands.sentinel)
-                            {
+                            enet_peer_release_outgoing_command(peer, outgoing_command);
+                            (*(*peer).host)
+                                .outgoing_command_pool
+                                .assume_init_mut()
+                                .release(outgoing_command);
+                            let Some(&next_command) = (*peer)
+                                .outgoing_commands
+                                .assume_init_ref()
+                                .get(current_command)
+                            else {
                                 break;
-                            }
-                            outgoing_command = current_command.cast();
+                            };
+                            outgoing_command = next_command;
                             if (*outgoing_command).reliable_sequence_number as i32
                                 != reliable_sequence_number as i32
                                 || (*outgoing_command).unreliable_sequence_number as i32
-                                != unreliable_sequence_number as i32
+                                    != unreliable_sequence_number as i32
                             {
                                 break;
                             }
-                            current_command = (*current_command).next;
+                            current_command += 1;
+                            send_reliable = false;
                         }
                         continue;
                     }
                 }
-                enet_list_remove(&mut (*outgoing_command).outgoing_command_list);
+                enet_protocol_remove_taken_command(
+                    peer,
+                    send_reliable,
+                    &mut current_command,
+                    &mut current_send_reliable_command,
+                );
                 if !((*outgoing_command).packet).is_null() {
-                    enet_list_insert(
-                        &mut (*sent_unreliable_commands).sentinel,
-                        outgoing_command.cast(),
-                    );
+                    sent_unreliable_commands.push(outgoing_command);
                 }
             }
             (*buffer).data = command.cast();
@@ -2068,10 +2909,11 @@ unsafe fn enet_protocol_check_outgoing_commands<S: Socket>(
                 & ENET_PROTOCOL_COMMAND_FLAG_ACKNOWLEDGE as i32
                 == 0
             {
-                enet_free(
-                    outgoing_command.cast(),
-                    Layout::new::<ENetOutgoingCommand>(),
-                );
+                enet_peer_release_outgoing_command(peer, outgoing_command);
+                (*(*peer).host)
+                    .outgoing_command_pool
+                    .assume_init_mut()
+                    .release(outgoing_command);
             }
             (*peer).packets_sent = ((*peer).packets_sent).wrapping_add(1);
             command = command.offset(1);
@@ -2082,8 +2924,7 @@ unsafe fn enet_protocol_check_outgoing_commands<S: Socket>(
     (*host).buffer_count = buffer.offset_from(((*host).buffers).as_mut_ptr()) as i64 as usize;
     if (*peer).state == ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
         && enet_peer_has_outgoing_commands(peer) == 0
-        && (*sent_unreliable_commands).sentinel.next
-        == core::ptr::addr_of_mut!((*sent_unreliable_commands).sentinel)
+        && sent_unreliable_commands.is_empty()
     {
         enet_peer_disconnect(peer, (*peer).event_data);
     }
@@ -2093,7 +2934,8 @@ unsafe fn enet_protocol_send_outgoing_commands<S: Socket>(
     host: *mut ENetHost<S>,
     event: *mut ENetEvent<S>,
     check_for_timeouts: i32,
-) -> Result<bool, S::Error> {
+    only_peer: *mut ENetPeer<S>,
+) -> bool {
     let packet_size = {
         if (*host).using_new_packet {
             ::core::mem::size_of::<ENetNewProtocolHeader>()
@@ -2101,32 +2943,18 @@ unsafe fn enet_protocol_send_outgoing_commands<S: Socket>(
             ::core::mem::size_of::<ENetProtocolHeader>()
         }
     };
-    let mut header_data: [u8; std::mem::size_of::<ENetNewProtocolHeader>() + std::mem::size_of::<u32>()] = [0; std::mem::size_of::<ENetNewProtocolHeader>() + std::mem::size_of::<u32>()];
+    let mut header_data: [u8; core::mem::size_of::<ENetNewProtocolHeader>()
+        + core::mem::size_of::<u16>()
+        + core::mem::size_of::<u32>()] = [0; core::mem::size_of::<ENetNewProtocolHeader>()
+        + core::mem::size_of::<u16>()
+        + core::mem::size_of::<u32>()];
     let header: *mut ENetProtocolHeader = header_data.as_mut_ptr().cast();
     let new_header: *mut ENetNewProtocolHeader = header_data.as_mut_ptr().cast();
     let mut should_compress: usize;
-    let mut sent_unreliable_commands: ENetList = ENetList {
-        sentinel: ENetListNode {
-            next: core::ptr::null_mut(),
-            previous: core::ptr::null_mut(),
-        },
-    };
-    enet_list_clear(&mut sent_unreliable_commands);
+    let mut sent_unreliable_commands: Vec<*mut ENetOutgoingCommand> = Vec::new();
 
     if (*host).using_new_packet {
-        let port: u16 = (*(*host).peers)
-            .address
-            .assume_init_ref()
-            .as_ref()
-            .unwrap()
-            .port();
-        let rand1: u16 = (enet_host_random(host) as u16) % (port + 1);
-
-        unsafe {
-            (*new_header).integrity[0] = rand1.to_be();
-            (*new_header).integrity[1] = (rand1 ^ port).to_be();
-            (*new_header).integrity[2] = ((enet_host_random(host) as u16) & 0x61D2 | 0x920D).to_be();
-        }
+        (*new_header).integrity = enet_protocol_header_integrity(host).map(u16::to_be);
     }
 
     let mut send_pass: i32 = 0_i32;
@@ -2136,8 +2964,9 @@ unsafe fn enet_protocol_send_outgoing_commands<S: Socket>(
         while current_peer < ((*host).peers).add((*host).peer_count) {
             if !((*current_peer).state == ENET_PEER_STATE_DISCONNECTED as i32 as u32
                 || (*current_peer).state == ENET_PEER_STATE_ZOMBIE as i32 as u32
+                || !only_peer.is_null() && current_peer != only_peer
                 || send_pass > 0_i32
-                && (*current_peer).flags as i32 & ENET_PEER_FLAG_CONTINUE_SENDING as i32 == 0)
+                    && (*current_peer).flags as i32 & ENET_PEER_FLAG_CONTINUE_SENDING as i32 == 0)
             {
                 (*current_peer).flags = ((*current_peer).flags as i32
                     & !(ENET_PEER_FLAG_CONTINUE_SENDING as i32))
@@ -2146,50 +2975,84 @@ unsafe fn enet_protocol_send_outgoing_commands<S: Socket>(
                 (*host).command_count = 0_i32 as usize;
                 (*host).buffer_count = 1_i32 as usize;
                 (*host).packet_size = packet_size;
-                if (*current_peer).acknowledgements.sentinel.next
-                    != core::ptr::addr_of_mut!((*current_peer).acknowledgements.sentinel)
+                if enet_peer_extended_peer_id(current_peer) {
+                    (*host).packet_size += core::mem::size_of::<u16>();
+                }
+                if !(*current_peer)
+                    .acknowledgements
+                    .assume_init_ref()
+                    .is_empty()
+                    && (check_for_timeouts == 0_i32
+                        || !enet_peer_holds_acknowledgements(current_peer))
                 {
                     enet_protocol_send_acknowledgements(host, current_peer);
                 }
                 if check_for_timeouts != 0_i32
-                    && ((*current_peer).sent_reliable_commands.sentinel.next
-                    != core::ptr::addr_of_mut!((*current_peer).sent_reliable_commands.sentinel))
+                    && ((*host).memory_budget_policy == MemoryBudgetPolicy::Disconnect
+                        && enet_peer_over_memory_budget(current_peer)
+                        || (*current_peer).fragment_data_exceeded
+                        || enet_protocol_missed_pings(host, current_peer))
+                {
+                    enet_protocol_notify_disconnect(host, current_peer, event);
+                    if !event.is_null() && (*event).type_0 != ENET_EVENT_TYPE_NONE as i32 as u32 {
+                        // queued datagrams are sent once the event is returned and the host is serviced again
+                        return true;
+                    }
+                } else if check_for_timeouts != 0_i32
+                    && !(*current_peer)
+                        .sent_reliable_commands
+                        .assume_init_ref()
+                        .is_empty()
                     && (((*host).service_time).wrapping_sub((*current_peer).next_timeout)
-                    < 86400000_i32 as u32)
+                        < 86400000_i32 as u32)
                     && enet_protocol_check_timeouts(host, current_peer, event) == 1_i32
                 {
                     if !event.is_null() && (*event).type_0 != ENET_EVENT_TYPE_NONE as i32 as u32 {
-                        return Ok(true);
+                        return true;
                     }
+                } else if check_for_timeouts != 0_i32
+                    && !event.is_null()
+                    && enet_protocol_check_idle(host, current_peer, event)
+                {
+                    return true;
                 } else {
-                    if ((*current_peer).outgoing_commands.sentinel.next
-                        == core::ptr::addr_of_mut!((*current_peer).outgoing_commands.sentinel)
+                    if check_for_timeouts != 0_i32 {
+                        enet_protocol_probe_paths(host, current_peer);
+                    }
+                    // commands held to coalesce are only sent by service, flushing sends them
+                    let hold = check_for_timeouts != 0_i32
+                        && enet_peer_holds_outgoing_commands(current_peer);
+                    if !hold {
+                        (*current_peer).coalesce_start = None;
+                    }
+                    if ((*current_peer)
+                        .outgoing_commands
+                        .assume_init_ref()
+                        .is_empty()
                         && (*current_peer)
-                        .outgoing_send_reliable_commands
-                        .sentinel
-                        .next
-                        == core::ptr::addr_of_mut!(
-                                (*current_peer).outgoing_send_reliable_commands.sentinel
-                            )
-                        || enet_protocol_check_outgoing_commands(
-                        host,
-                        current_peer,
-                        &mut sent_unreliable_commands,
-                    ) != 0)
-                        && (*current_peer).sent_reliable_commands.sentinel.next
-                        == core::ptr::addr_of_mut!(
-                                (*current_peer).sent_reliable_commands.sentinel
-                            )
+                            .outgoing_send_reliable_commands
+                            .assume_init_ref()
+                            .is_empty()
+                        || !hold
+                            && enet_protocol_check_outgoing_commands(
+                                host,
+                                current_peer,
+                                &mut sent_unreliable_commands,
+                            ) != 0)
+                        && (*current_peer)
+                            .sent_reliable_commands
+                            .assume_init_ref()
+                            .is_empty()
                         && (if ((*host).service_time)
-                        .wrapping_sub((*current_peer).last_receive_time)
-                        >= 86400000_i32 as u32
-                    {
-                        ((*current_peer).last_receive_time).wrapping_sub((*host).service_time)
-                    } else {
-                        ((*host).service_time).wrapping_sub((*current_peer).last_receive_time)
-                    }) >= (*current_peer).ping_interval
+                            .wrapping_sub((*current_peer).last_receive_time)
+                            >= 86400000_i32 as u32
+                        {
+                            ((*current_peer).last_receive_time).wrapping_sub((*host).service_time)
+                        } else {
+                            ((*host).service_time).wrapping_sub((*current_peer).last_receive_time)
+                        }) >= (*current_peer).ping_interval
                         && ((*current_peer).mtu as usize).wrapping_sub((*host).packet_size)
-                        >= ::core::mem::size_of::<ENetProtocolPing>()
+                            >= ::core::mem::size_of::<ENetProtocolPing>()
                     {
                         enet_peer_ping(current_peer);
                         enet_protocol_check_outgoing_commands(
@@ -2234,11 +3097,15 @@ unsafe fn enet_protocol_send_outgoing_commands<S: Socket>(
                         let fresh34 = &mut (*((*host).buffers).as_mut_ptr()).data;
                         *fresh34 = header_data.as_mut_ptr();
 
-                        if (*host).header_flags as i32 & ENET_PROTOCOL_HEADER_FLAG_SENT_TIME as i32 != 0 {
+                        if (*host).header_flags as i32 & ENET_PROTOCOL_HEADER_FLAG_SENT_TIME as i32
+                            != 0
+                        {
                             if (*host).using_new_packet {
-                                (*new_header).sent_time = (((*host).service_time & 0xffff_i32 as u32) as u16).to_be();
+                                (*new_header).sent_time =
+                                    (((*host).service_time & 0xffff_i32 as u32) as u16).to_be();
                             } else {
-                                (*header).sent_time = (((*host).service_time & 0xffff_i32 as u32) as u16).to_be();
+                                (*header).sent_time =
+                                    (((*host).service_time & 0xffff_i32 as u32) as u16).to_be();
                             }
                             (*((*host).buffers).as_mut_ptr()).data_length = packet_size;
                         } else {
@@ -2253,8 +3120,11 @@ unsafe fn enet_protocol_send_outgoing_commands<S: Socket>(
 
                         should_compress = 0_i32 as usize;
                         if let Some(compressor) = (*host).compressor.assume_init_mut() {
-                            let original_size: usize = ((*host).packet_size)
-                                .wrapping_sub(packet_size);
+                            let mut original_size: usize =
+                                ((*host).packet_size).wrapping_sub(packet_size);
+                            if enet_peer_extended_peer_id(current_peer) {
+                                original_size -= ::core::mem::size_of::<u16>();
+                            }
                             let mut in_buffers: [&[u8]; BUFFER_MAXIMUM as usize] =
                                 core::array::from_fn(|_| {
                                     from_raw_parts_or_empty::<u8>(core::ptr::null(), 0)
@@ -2282,33 +3152,43 @@ unsafe fn enet_protocol_send_outgoing_commands<S: Socket>(
                                 should_compress = compressed_size;
                             }
                         }
-                        if ((*current_peer).outgoing_peer_id as i32)
-                            < PROTOCOL_MAXIMUM_PEER_ID as i32
-                        {
+                        if (*current_peer).outgoing_peer_id as u32 != PROTOCOL_MAXIMUM_PEER_ID {
                             (*host).header_flags = ((*host).header_flags as i32
                                 | ((*current_peer).outgoing_session_id as i32)
-                                << ENET_PROTOCOL_HEADER_SESSION_SHIFT as i32)
+                                    << ENET_PROTOCOL_HEADER_SESSION_SHIFT as i32)
                                 as u16;
                         }
+                        let mut header_peer_id = (*current_peer).outgoing_peer_id;
+                        if enet_peer_extended_peer_id(current_peer) {
+                            let extended_addr: *mut u8 = header_data
+                                .as_mut_ptr()
+                                .add((*((*host).buffers).as_mut_ptr()).data_length);
+                            copy_nonoverlapping(
+                                header_peer_id.to_be_bytes().as_ptr(),
+                                extended_addr,
+                                ::core::mem::size_of::<u16>(),
+                            );
+                            (*((*host).buffers).as_mut_ptr()).data_length +=
+                                ::core::mem::size_of::<u16>();
+                            header_peer_id = ENET_PROTOCOL_HEADER_EXTENDED_PEER_ID;
+                        }
 
                         if (*host).using_new_packet {
-                            (*new_header).peer_id = (((*current_peer).outgoing_peer_id as i32
-                                | (*host).header_flags as i32)
-                                as u16)
-                                .to_be();
+                            (*new_header).peer_id =
+                                ((header_peer_id as i32 | (*host).header_flags as i32) as u16)
+                                    .to_be();
                         } else {
-                            (*header).peer_id = (((*current_peer).outgoing_peer_id as i32
-                                | (*host).header_flags as i32)
-                                as u16)
-                                .to_be();
+                            (*header).peer_id =
+                                ((header_peer_id as i32 | (*host).header_flags as i32) as u16)
+                                    .to_be();
                         }
 
                         if let Some(checksum_fn) = (*host).checksum.assume_init_ref() {
                             let checksum_addr: *mut u8 = header_data
                                 .as_mut_ptr()
                                 .add((*((*host).buffers).as_mut_ptr()).data_length);
-                            let mut checksum = if ((*current_peer).outgoing_peer_id as i32)
-                                < PROTOCOL_MAXIMUM_PEER_ID as i32
+                            let mut checksum = if (*current_peer).outgoing_peer_id as u32
+                                != PROTOCOL_MAXIMUM_PEER_ID
                             {
                                 (*current_peer).connect_id
                             } else {
@@ -2358,25 +3238,74 @@ unsafe fn enet_protocol_send_outgoing_commands<S: Socket>(
                                 buffer.data_length,
                             ));
                         }
-                        let sent_length = (*host).socket.assume_init_mut().send(
-                            (*current_peer)
+                        #[cfg(feature = "crypto")]
+                        if let Some(connect_authentication) = (*host).connect_authentication {
+                            if (*current_peer).outgoing_peer_id as u32 == PROTOCOL_MAXIMUM_PEER_ID {
+                                let trailer = connect_authentication
+                                    .sign(&conglomerate_buffer, (*host).time.assume_init_ref()());
+                                conglomerate_buffer.extend_from_slice(&trailer);
+                            }
+                        }
+                        // until the remote host answers a connect, it doesn't know the
+                        // connect ID yet
+                        let connect_id = ((*current_peer).state
+                            != ENET_PEER_STATE_CONNECTING as i32 as u32)
+                            .then_some((*current_peer).connect_id);
+                        let mut transformed = true;
+                        for transform in (*host).transforms.assume_init_mut() {
+                            let encoded = match connect_id {
+                                Some(connect_id) => transform
+                                    .encode_connection(&mut conglomerate_buffer, connect_id),
+                                None => transform.encode(&mut conglomerate_buffer),
+                            };
+                            if !encoded {
+                                transformed = false;
+                                break;
+                            }
+                        }
+                        if transformed {
+                            let address = (*current_peer)
                                 .address
                                 .assume_init_ref()
                                 .as_ref()
                                 .cloned()
-                                .unwrap(),
-                            &conglomerate_buffer,
-                        );
+                                .unwrap();
+                            if (*current_peer).loopback {
+                                (*host)
+                                    .loopback_datagrams
+                                    .assume_init_mut()
+                                    .push_back((address, conglomerate_buffer));
+                            } else if let Some(simulation) = (*current_peer).simulation {
+                                enet_protocol_simulate_send(
+                                    host,
+                                    simulation,
+                                    address,
+                                    conglomerate_buffer,
+                                );
+                            } else if let Some(dscp) = (*current_peer).dscp {
+                                // marked datagrams are sent alone, after those queued before them
+                                enet_protocol_send_datagrams(host);
+                                enet_protocol_send_marked_datagram(
+                                    host,
+                                    address,
+                                    &conglomerate_buffer,
+                                    dscp,
+                                );
+                            } else {
+                                (*host)
+                                    .outgoing_datagrams
+                                    .assume_init_mut()
+                                    .push((address, conglomerate_buffer));
+                            }
+                        }
                         enet_protocol_remove_sent_unreliable_commands(
                             current_peer,
                             &mut sent_unreliable_commands,
                         );
-                        match sent_length {
-                            Err(err) => return Err(err),
-                            Ok(sent_length) => {
-                                (*host).total_sent_data =
-                                    (*host).total_sent_data.wrapping_add(sent_length as u32);
-                            }
+                        if (*host).outgoing_datagrams.assume_init_ref().len()
+                            >= (*host).socket_batch_size
+                        {
+                            enet_protocol_send_datagrams(host);
                         }
                         (*host).total_sent_packets = ((*host).total_sent_packets).wrapping_add(1);
                     }
@@ -2389,13 +3318,432 @@ unsafe fn enet_protocol_send_outgoing_commands<S: Socket>(
         }
         send_pass += 1;
     }
+    enet_protocol_send_datagrams(host);
+    false
+}
+/// The integrity fields of the header sent when
+/// [`HostSettings::using_new_packet`](`crate::HostSettings::using_new_packet`) is enabled.
+unsafe fn enet_protocol_header_integrity<S: Socket>(host: *mut ENetHost<S>) -> [u16; 3] {
+    let port: u16 = (*(*host).peers)
+        .address
+        .assume_init_ref()
+        .as_ref()
+        .unwrap()
+        .port();
+    let rand1: u16 = (enet_host_random(host) as u16) % (port + 1);
+    [
+        rand1,
+        rand1 ^ port,
+        (enet_host_random(host) as u16) & 0x61D2 | 0x920D,
+    ]
+}
+/// Queue a datagram holding a single protocol message for `peer` to `address`, apart from the
+/// peer's outgoing commands, so it can be sent somewhere other than the peer's address.
+unsafe fn enet_protocol_queue_protocol_message_to<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+    address: S::Address,
+    kind: u8,
+    data: &[u8],
+) {
+    let mut datagram = Vec::new();
+    if (*host).using_new_packet {
+        for integrity in enet_protocol_header_integrity(host) {
+            datagram.extend_from_slice(&integrity.to_be_bytes());
+        }
+    }
+    let mut header_peer_id = (*peer).outgoing_peer_id;
+    if enet_peer_extended_peer_id(peer) {
+        header_peer_id = ENET_PROTOCOL_HEADER_EXTENDED_PEER_ID;
+    }
+    header_peer_id |= u16::from((*peer).outgoing_session_id) << ENET_PROTOCOL_HEADER_SESSION_SHIFT;
+    datagram.extend_from_slice(&header_peer_id.to_be_bytes());
+    if enet_peer_extended_peer_id(peer) {
+        datagram.extend_from_slice(&(*peer).outgoing_peer_id.to_be_bytes());
+    }
+    let checksum_offset = datagram.len();
+    if (*host).checksum.assume_init_ref().is_some() {
+        datagram.extend_from_slice(&(*peer).connect_id.to_ne_bytes());
+    }
+    let mut command: ENetProtocol = zeroed();
+    command.header.command = (ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED as i32
+        | ENET_PROTOCOL_COMMAND_FLAG_UNSEQUENCED as i32) as u8;
+    command.header.channel_id = 0xff;
+    command.send_unsequenced.data_length = ((data.len() + 1) as u16).to_be();
+    datagram.extend_from_slice(from_raw_parts_or_empty(
+        (&raw const command).cast::<u8>(),
+        size_of::<ENetProtocolSendUnsequenced>(),
+    ));
+    datagram.push(kind);
+    datagram.extend_from_slice(data);
+    if let Some(checksum_fn) = (*host).checksum.assume_init_ref() {
+        let checksum = checksum_fn(&[&datagram]);
+        datagram[checksum_offset..checksum_offset + size_of::<u32>()]
+            .copy_from_slice(&checksum.to_ne_bytes());
+    }
+    for transform in (*host).transforms.assume_init_mut() {
+        if !transform.encode(&mut datagram) {
+            return;
+        }
+    }
+    (*host)
+        .outgoing_datagrams
+        .assume_init_mut()
+        .push((address, datagram));
+}
+/// The connect ID of the first peer connected or connecting to `address`, for transforms which
+/// derive their keys from it.
+unsafe fn enet_protocol_connect_id<S: Socket>(
+    host: *mut ENetHost<S>,
+    address: &S::Address,
+) -> Option<u32> {
+    let mut current_peer = (*host).peers;
+    while current_peer < ((*host).peers).add((*host).peer_count) {
+        if (*current_peer).state != ENET_PEER_STATE_DISCONNECTED as i32 as u32
+            && (*current_peer)
+                .address
+                .assume_init_ref()
+                .as_ref()
+                .is_some_and(|peer_address| peer_address.same(address))
+        {
+            return Some((*current_peer).connect_id);
+        }
+        current_peer = current_peer.offset(1);
+    }
+    None
+}
+/// Check a datagram received for a connected peer from a new address answers the peer's
+/// migration challenge for that address, and challenge the address otherwise. Returns `true` if
+/// the peer should move to the new address.
+///
+/// The challenge's nonce is only sent to the new address, and the answer must carry the
+/// connection's connect ID, so only the peer itself, receiving at the new address, can answer.
+unsafe fn enet_protocol_handle_migration<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+    header_size: usize,
+) -> bool {
+    let address = (*host)
+        .received_address
+        .assume_init_ref()
+        .as_ref()
+        .cloned()
+        .unwrap();
+    let mut reader = ENetReader::new(from_raw_parts_or_empty(
+        (*host).received_data,
+        (*host).received_data_length,
+    ));
+    _ = reader.read_bytes(header_size);
+    let challenge = (*peer).migration_challenge.assume_init_mut();
+    let mut probed = false;
+    while let Ok(frame) = enet_protocol_parse_command(&mut reader) {
+        // probes are answered along the path they came from, without moving the peer
+        if frame.command_number as u32 == ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED
+            && frame.command[1] == 0xff
+            && frame.data.len() == 9
+            && frame.data[0] == ENET_PROTOCOL_MESSAGE_PATH_PROBE
+        {
+            enet_protocol_queue_protocol_message_to(
+                host,
+                peer,
+                address.clone(),
+                ENET_PROTOCOL_MESSAGE_PATH_RESPONSE,
+                &frame.data[1..],
+            );
+            probed = true;
+            continue;
+        }
+        if frame.command_number as u32 != ENET_PROTOCOL_COMMAND_SEND_UNSEQUENCED
+            || frame.command[1] != 0xff
+            || frame.data.len() != 13
+            || frame.data[0] != ENET_PROTOCOL_MESSAGE_MIGRATION_RESPONSE
+        {
+            continue;
+        }
+        if let Some(current) = challenge.as_mut() {
+            // a challenge is spent after too many wrong answers, so it can't be brute forced
+            if current.address.same(&address)
+                && current.failures < PEER_MIGRATION_CHALLENGE_MAXIMUM_FAILURES
+            {
+                if frame.data[1..9] == current.nonce
+                    && frame.data[9..] == (*peer).connect_id.to_be_bytes()
+                {
+                    *challenge = None;
+                    return true;
+                }
+                current.failures += 1;
+            }
+        }
+    }
+    if probed {
+        return false;
+    }
+    if let Some(current) = challenge.as_ref() {
+        if current.address.same(&address)
+            && (*host).service_time.wrapping_sub(current.sent_time)
+                < PEER_MIGRATION_CHALLENGE_INTERVAL
+        {
+            return false;
+        }
+    }
+    let mut nonce = [0; 8];
+    enet_host_secure_random(host, &mut nonce);
+    *challenge = Some(ENetMigrationChallenge {
+        address: address.clone(),
+        nonce,
+        sent_time: (*host).service_time,
+        failures: 0,
+    });
+    enet_protocol_queue_protocol_message_to(
+        host,
+        peer,
+        address,
+        ENET_PROTOCOL_MESSAGE_MIGRATION_CHALLENGE,
+        &nonce,
+    );
+    false
+}
+/// Probe each of a connected peer's paths, see [`Peer::add_address`](`crate::Peer::add_address`),
+/// moving it to the fastest path which answered the last probe.
+unsafe fn enet_protocol_probe_paths<S: Socket>(host: *mut ENetHost<S>, peer: *mut ENetPeer<S>) {
+    if (*peer).state != ENET_PEER_STATE_CONNECTED as i32 as u32
+        || (*peer).protocol_version == ProtocolVersion::Enet
+        || (*peer).paths.assume_init_ref().is_empty()
+        || (*host).service_time.wrapping_sub((*peer).path_probe_time) < PEER_PATH_PROBE_INTERVAL
+    {
+        return;
+    }
+    let paths = (*peer).paths.assume_init_mut();
+    for path in paths.iter_mut() {
+        if path.nonce.take().is_some() {
+            path.round_trip_time = None;
+        }
+    }
+    let address = (*peer).address.assume_init_mut().as_mut().unwrap();
+    let current = paths
+        .iter()
+        .find(|path| path.address.same(address))
+        .and_then(|path| path.round_trip_time);
+    if let Some((best, round_trip_time)) = paths
+        .iter()
+        .filter_map(|path| Some((&path.address, path.round_trip_time?)))
+        .min_by_key(|(_, round_trip_time)| *round_trip_time)
+    {
+        if current.is_none_or(|current| round_trip_time + PEER_PATH_SWITCH_THRESHOLD < current) {
+            *address = best.clone();
+        }
+    }
+    (*peer).path_probe_time = (*host).service_time;
+    let mut probes = Vec::new();
+    for path in paths.iter_mut() {
+        let mut nonce = [0; 8];
+        enet_host_secure_random(host, &mut nonce);
+        path.nonce = Some(nonce);
+        probes.push((path.address.clone(), nonce));
+    }
+    for (address, nonce) in probes {
+        enet_protocol_queue_protocol_message_to(
+            host,
+            peer,
+            address,
+            ENET_PROTOCOL_MESSAGE_PATH_PROBE,
+            &nonce,
+        );
+    }
+}
+/// Whether a connected peer has gone [`ENetPeer::missed_ping_limit`] ping intervals without
+/// acknowledging anything, regardless of the reliable command timeouts.
+unsafe fn enet_protocol_missed_pings<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+) -> bool {
+    let Some(missed_ping_limit) = (*peer).missed_ping_limit else {
+        return false;
+    };
+    if (*peer).state != ENET_PEER_STATE_CONNECTED as i32 as u32
+        && (*peer).state != ENET_PEER_STATE_DISCONNECT_LATER as i32 as u32
+        || (*peer).last_receive_time == 0
+    {
+        return false;
+    }
+    let silence = ((*host).service_time).wrapping_sub((*peer).last_receive_time);
+    silence < 86400000_i32 as u32
+        && silence >= missed_ping_limit.saturating_mul((*peer).ping_interval)
+}
+/// Generate [`ENET_EVENT_TYPE_PEER_IDLE`] once a connected peer has sent no data for
+/// [`ENetHost::idle_timeout`], returning `true` if the event was generated.
+unsafe fn enet_protocol_check_idle<S: Socket>(
+    host: *mut ENetHost<S>,
+    peer: *mut ENetPeer<S>,
+    event: *mut ENetEvent<S>, // SAFETY: should not be null
+) -> bool {
+    let Some(idle_timeout) = (*host).idle_timeout else {
+        return false;
+    };
+    if (*peer).state != ENET_PEER_STATE_CONNECTED as i32 as u32 || (*peer).idle_reported {
+        return false;
+    }
+    let idle_for = ((*host).service_time).wrapping_sub((*peer).last_data_time);
+    if idle_for >= 86400000_i32 as u32 || idle_for < idle_timeout {
+        return false;
+    }
+    (*peer).idle_reported = true;
+    (*event).type_0 = ENET_EVENT_TYPE_PEER_IDLE;
+    (*event).peer = peer;
+    (*event).time = Duration::from_millis(u64::from(idle_for));
+    true
+}
+/// Fail connections being made to addresses the socket reports are unreachable, see
+/// [`Socket::receive_unreachable`].
+unsafe fn enet_protocol_receive_unreachable<S: Socket>(
+    host: *mut ENetHost<S>,
+    event: *mut ENetEvent<S>,
+) -> Result<bool, ServiceError<S>> {
+    while let Some(address) = (*host)
+        .socket
+        .assume_init_mut()
+        .receive_unreachable()
+        .map_err(|error| ServiceError {
+            stage: ServiceStage::Receive,
+            address: None,
+            error,
+        })?
+    {
+        let mut current_peer = (*host).peers;
+        while current_peer < ((*host).peers).add((*host).peer_count) {
+            if (*current_peer).state == ENET_PEER_STATE_CONNECTING as i32 as u32
+                && (*current_peer)
+                    .address
+                    .assume_init_ref()
+                    .as_ref()
+                    .is_some_and(|peer_address| peer_address.same(&address))
+            {
+                enet_protocol_notify_disconnect(host, current_peer, event);
+                if !event.is_null() && (*event).type_0 != ENET_EVENT_TYPE_NONE as i32 as u32 {
+                    return Ok(true);
+                }
+            }
+            current_peer = current_peer.offset(1);
+        }
+    }
     Ok(false)
 }
+/// Send the datagrams queued by [`enet_protocol_send_outgoing_commands`] with
+/// [`Socket::send_batch`], queueing any failure in [`ENetHost::send_errors`] so the remaining
+/// peers are still sent to.
+unsafe fn enet_protocol_send_datagrams<S: Socket>(host: *mut ENetHost<S>) {
+    let datagrams = (*host).outgoing_datagrams.assume_init_mut();
+    if datagrams.is_empty() {
+        return;
+    }
+    let sent_length = (*host).socket.assume_init_mut().send_batch(datagrams);
+    // the failed datagram is only known when it was sent alone
+    let address = (datagrams.len() == 1).then(|| datagrams[0].0.clone());
+    datagrams.clear();
+    match sent_length {
+        Ok(sent_length) => {
+            (*host).total_sent_data = (*host).total_sent_data.wrapping_add(sent_length as u32);
+        }
+        Err(error) => (*host)
+            .send_errors
+            .assume_init_mut()
+            .push_back(ServiceError {
+                stage: ServiceStage::Send,
+                address,
+                error,
+            }),
+    }
+}
+/// Whether to drop a datagram to or from a peer simulating loss with
+/// [`Peer::simulate`](`crate::Peer::simulate`).
+unsafe fn enet_protocol_simulate_loss<S: Socket>(
+    host: *mut ENetHost<S>,
+    simulation: PeerSimulation,
+) -> bool {
+    simulation.loss_percent > 0 && enet_host_random(host) % 100 < u32::from(simulation.loss_percent)
+}
+/// Queue a datagram to a peer simulating a poor connection with
+/// [`Peer::simulate`](`crate::Peer::simulate`), dropping it at the simulated loss rate, or
+/// holding it back until its simulated latency has passed.
+unsafe fn enet_protocol_simulate_send<S: Socket>(
+    host: *mut ENetHost<S>,
+    simulation: PeerSimulation,
+    address: S::Address,
+    datagram: Vec<u8>,
+) {
+    if enet_protocol_simulate_loss(host, simulation) {
+        return;
+    }
+    let jitter = u32::try_from(simulation.jitter.as_millis()).unwrap_or(u32::MAX);
+    let latency = u32::try_from(simulation.min_latency.as_millis())
+        .unwrap_or(u32::MAX)
+        .saturating_add(enet_host_random(host) % jitter.saturating_add(1));
+    if latency == 0 {
+        (*host)
+            .outgoing_datagrams
+            .assume_init_mut()
+            .push((address, datagram));
+    } else {
+        (*host).simulated_datagrams.assume_init_mut().push((
+            (*host).service_time.wrapping_add(latency),
+            address,
+            datagram,
+        ));
+    }
+}
+/// Send the datagrams held back by [`enet_protocol_simulate_send`] whose simulated latency has
+/// passed.
+unsafe fn enet_protocol_send_simulated_datagrams<S: Socket>(host: *mut ENetHost<S>) {
+    let service_time = (*host).service_time;
+    let simulated = (*host).simulated_datagrams.assume_init_mut();
+    if simulated.is_empty() {
+        return;
+    }
+    let outgoing = (*host).outgoing_datagrams.assume_init_mut();
+    simulated.retain_mut(|(due, address, datagram)| {
+        if service_time.wrapping_sub(*due) >= 86400000_i32 as u32 {
+            return true;
+        }
+        outgoing.push((address.clone(), take(datagram)));
+        false
+    });
+    enet_protocol_send_datagrams(host);
+}
+/// Send a datagram to a peer with a DSCP set with [`Peer::set_dscp`](`crate::Peer::set_dscp`),
+/// with [`Socket::send_with_traffic_class`].
+unsafe fn enet_protocol_send_marked_datagram<S: Socket>(
+    host: *mut ENetHost<S>,
+    address: S::Address,
+    datagram: &[u8],
+    dscp: u8,
+) {
+    let ecn = if (*host).ecn { ECN_CAPABLE } else { 0 };
+    match (*host).socket.assume_init_mut().send_with_traffic_class(
+        address.clone(),
+        datagram,
+        dscp << 2 | ecn,
+    ) {
+        Ok(sent_length) => {
+            (*host).total_sent_data = (*host).total_sent_data.wrapping_add(sent_length as u32);
+        }
+        Err(error) => (*host)
+            .send_errors
+            .assume_init_mut()
+            .push_back(ServiceError {
+                stage: ServiceStage::Send,
+                address: Some(address),
+                error,
+            }),
+    }
+}
 pub(crate) unsafe fn enet_host_flush<S: Socket>(host: *mut ENetHost<S>) {
     (*host).service_time = enet_time_get(host);
-    // TODO: enet ignores the error here, but is that really what we want?
-    // a socket's send could error and no one would know
-    _ = enet_protocol_send_outgoing_commands(host, core::ptr::null_mut(), 0_i32);
+    enet_protocol_send_outgoing_commands(host, core::ptr::null_mut(), 0_i32, core::ptr::null_mut());
+}
+pub(crate) unsafe fn enet_peer_flush<S: Socket>(peer: *mut ENetPeer<S>) {
+    let host = (*peer).host;
+    (*host).service_time = enet_time_get(host);
+    enet_protocol_send_outgoing_commands(host, core::ptr::null_mut(), 0_i32, peer);
 }
 pub(crate) unsafe fn enet_host_check_events<S: Socket>(
     host: *mut ENetHost<S>,
@@ -2409,7 +3757,7 @@ pub(crate) unsafe fn enet_host_check_events<S: Socket>(
 pub(crate) unsafe fn enet_host_service<S: Socket>(
     host: *mut ENetHost<S>,
     event: *mut ENetEvent<S>, // SAFETY: should not be null
-) -> Result<bool, S::Error> {
+) -> Result<bool, ServiceError<S>> {
     (*event).type_0 = ENET_EVENT_TYPE_NONE;
     (*event).peer = core::ptr::null_mut();
     (*event).packet = core::ptr::null_mut();
@@ -2417,6 +3765,10 @@ pub(crate) unsafe fn enet_host_service<S: Socket>(
         return Ok(true);
     }
     (*host).service_time = enet_time_get(host);
+    enet_host_follow_redirects(host);
+    enet_host_expire_bans(host);
+    enet_host_send_raw_datagrams(host, (*host).service_time);
+    enet_protocol_send_simulated_datagrams(host);
     if (if ((*host).service_time).wrapping_sub((*host).bandwidth_throttle_epoch)
         >= 86400000_i32 as u32
     {
@@ -2427,21 +3779,28 @@ pub(crate) unsafe fn enet_host_service<S: Socket>(
     {
         enet_host_bandwidth_throttle(host);
     }
-    match enet_protocol_send_outgoing_commands(host, event, 1_i32) {
-        Ok(true) => return Ok(true),
-        Ok(false) => {}
-        Err(err) => return Err(err),
+    if enet_protocol_send_outgoing_commands(host, event, 1_i32, core::ptr::null_mut()) {
+        return Ok(true);
+    }
+    if let Some(err) = (*host).send_errors.assume_init_mut().pop_front() {
+        return Err(err);
     }
     match enet_protocol_receive_incoming_commands(host, event) {
         Ok(true) => return Ok(true),
         Ok(false) => {}
         Err(err) => return Err(err),
     }
-    match enet_protocol_send_outgoing_commands(host, event, 1_i32) {
+    match enet_protocol_receive_unreachable(host, event) {
         Ok(true) => return Ok(true),
         Ok(false) => {}
         Err(err) => return Err(err),
     }
+    if enet_protocol_send_outgoing_commands(host, event, 1_i32, core::ptr::null_mut()) {
+        return Ok(true);
+    }
+    if let Some(err) = (*host).send_errors.assume_init_mut().pop_front() {
+        return Err(err);
+    }
     if enet_protocol_dispatch_incoming_commands(host, event) {
         return Ok(true);
     }
@@ -0,0 +1,49 @@
+//! A bounds checked cursor over received datagrams, in safe Rust.
+//!
+//! Every read checks the remaining length first and returns `None` rather than reading past the
+//! end, so crafted length fields can only make parsing fail, never read out of bounds.
+
+#[derive(Clone)]
+pub(crate) struct ENetReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ENetReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    /// The number of bytes read so far.
+    pub(crate) fn position(&self) -> usize {
+        self.position
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    pub(crate) fn peek_u8(&self) -> Option<u8> {
+        self.data.get(self.position).copied()
+    }
+
+    pub(crate) fn read_bytes(&mut self, length: usize) -> Option<&'a [u8]> {
+        let end = self.position.checked_add(length)?;
+        let bytes = self.data.get(self.position..end)?;
+        self.position = end;
+        Some(bytes)
+    }
+
+    pub(crate) fn read_array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.read_bytes(N)?.try_into().ok()
+    }
+
+    /// Read a big endian (network order) `u16`.
+    pub(crate) fn read_u16(&mut self) -> Option<u16> {
+        self.read_array().map(u16::from_be_bytes)
+    }
+}
@@ -0,0 +1,1348 @@
+//! A C API mirroring C ENet's `enet_*` functions, enabled with the `capi` feature, so C and C++
+//! programs written against C ENet can use rusty_enet instead.
+//!
+//! Build the crate as a C library, then compile against `include/enet/enet.h` from the repository
+//! in place of C ENet's header, and link it in place of `libenet`:
+//!
+//! ```sh
+//! cargo rustc --release --features capi --crate-type cdylib
+//! cc game.c -Ipath/to/rusty_enet/include -Lpath/to/rusty_enet/target/release -lrusty_enet
+//! ```
+//!
+//! Hosts use [`UdpSocket`]s over IPv4, and the host, peer, packet and address functions are
+//! source compatible with C ENet's, with these differences:
+//!
+//! - [`ENetHost`] only exposes the fields `address`, `incomingBandwidth`, `outgoingBandwidth`,
+//!   `mtu`, `peers`, `peerCount`, `channelLimit`, `serviceTime`, `checksum` and `connectedPeers`.
+//!   Assigning `checksum` takes effect on the next call taking the host, every other field is
+//!   read only.
+//! - [`ENetPeer`] only exposes the fields declared in the header. Every field but `data`, which
+//!   belongs to the application, is refreshed after each call taking the host or peer.
+//! - `enet_socket_*`, `enet_peer_receive`, `enet_host_random_seed` and host intercepts aren't
+//!   provided, so programs using them can't switch without changes.
+//! - `enet_address_get_host` returns the IP address, as reverse lookups aren't supported.
+//!
+//! Packets passed to `enet_peer_send` or `enet_host_broadcast` are copied when queued. As in C
+//! ENet, they stay valid until the host has been serviced or flushed, so a packet may be sent to
+//! several peers, and are destroyed then if no other references remain.
+//!
+//! Like C ENet, the API isn't thread safe. A host and its peers must only be used by one thread
+//! at a time.
+
+use core::{
+    alloc::Layout,
+    ffi::{c_char, c_int, c_void, CStr},
+    mem::size_of,
+    ptr, slice,
+    time::Duration,
+};
+use std::{
+    collections::VecDeque,
+    net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+    string::ToString,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::Instant,
+};
+
+use crate::{
+    consts::PROTOCOL_MAXIMUM_CHANNEL_COUNT, crc32, set_allocator, time_since_epoch, Allocator, Box,
+    Compressor, Event, EventNoRef, Host, HostSettings, Packet, PacketBuilder, PacketFlags, Peer,
+    PeerState, ProtocolVersion, RangeCoder, Vec, Version,
+};
+
+/// `ENET_PACKET_FLAG_RELIABLE`.
+pub const ENET_PACKET_FLAG_RELIABLE: u32 = 1 << 0;
+/// `ENET_PACKET_FLAG_UNSEQUENCED`.
+pub const ENET_PACKET_FLAG_UNSEQUENCED: u32 = 1 << 1;
+/// `ENET_PACKET_FLAG_NO_ALLOCATE`.
+pub const ENET_PACKET_FLAG_NO_ALLOCATE: u32 = 1 << 2;
+/// `ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT`.
+pub const ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT: u32 = 1 << 3;
+
+/// `ENET_EVENT_TYPE_NONE`.
+pub const ENET_EVENT_TYPE_NONE: c_int = 0;
+/// `ENET_EVENT_TYPE_CONNECT`.
+pub const ENET_EVENT_TYPE_CONNECT: c_int = 1;
+/// `ENET_EVENT_TYPE_DISCONNECT`.
+pub const ENET_EVENT_TYPE_DISCONNECT: c_int = 2;
+/// `ENET_EVENT_TYPE_RECEIVE`.
+pub const ENET_EVENT_TYPE_RECEIVE: c_int = 3;
+
+/// A checksum function, assigned to [`ENetHost::checksum`].
+pub type ENetChecksumCallback =
+    Option<unsafe extern "C" fn(buffers: *const ENetBuffer, buffer_count: usize) -> u32>;
+
+/// A function called when a packet is destroyed, assigned to [`ENetPacket::free_callback`].
+pub type ENetPacketFreeCallback = Option<unsafe extern "C" fn(packet: *mut ENetPacket)>;
+
+/// A buffer passed to checksum and compression callbacks, laid out like the platform's
+/// scatter/gather buffers.
+#[cfg(not(windows))]
+#[repr(C)]
+pub struct ENetBuffer {
+    /// The buffer's data.
+    pub data: *mut c_void,
+    /// The buffer's length.
+    pub data_length: usize,
+}
+
+/// A buffer passed to checksum and compression callbacks, laid out like the platform's
+/// scatter/gather buffers.
+#[cfg(windows)]
+#[repr(C)]
+pub struct ENetBuffer {
+    /// The buffer's length.
+    pub data_length: usize,
+    /// The buffer's data.
+    pub data: *mut c_void,
+}
+
+impl ENetBuffer {
+    fn from_slice(data: &[u8]) -> Self {
+        Self {
+            data: data.as_ptr().cast_mut().cast(),
+            data_length: data.len(),
+        }
+    }
+
+    unsafe fn as_slice<'a>(&self) -> &'a [u8] {
+        if self.data_length == 0 {
+            &[]
+        } else {
+            slice::from_raw_parts(self.data.cast(), self.data_length)
+        }
+    }
+}
+
+/// An IPv4 address and port.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct ENetAddress {
+    /// The IP address, in network byte order.
+    pub host: u32,
+    /// The port, in host byte order.
+    pub port: u16,
+}
+
+impl ENetAddress {
+    fn to_socket_addr(self) -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::from(self.host.to_ne_bytes()), self.port))
+    }
+
+    fn from_socket_addr(address: SocketAddr) -> Self {
+        let ip = match address {
+            SocketAddr::V4(address) => *address.ip(),
+            SocketAddr::V6(address) => address
+                .ip()
+                .to_ipv4_mapped()
+                .unwrap_or(Ipv4Addr::UNSPECIFIED),
+        };
+        Self {
+            host: u32::from_ne_bytes(ip.octets()),
+            port: address.port(),
+        }
+    }
+}
+
+/// A packet, created with [`enet_packet_create`].
+#[repr(C)]
+pub struct ENetPacket {
+    /// The number of queued sends referencing the packet.
+    pub reference_count: usize,
+    /// The packet's `ENET_PACKET_FLAG_*` flags.
+    pub flags: u32,
+    /// The packet's data.
+    pub data: *mut u8,
+    /// The length of the packet's data.
+    pub data_length: usize,
+    /// Called when the packet is destroyed.
+    pub free_callback: ENetPacketFreeCallback,
+    /// Application private data.
+    pub user_data: *mut c_void,
+    buffer: Vec<u8>,
+}
+
+impl ENetPacket {
+    fn allocate(mut buffer: Vec<u8>, flags: u32) -> *mut ENetPacket {
+        Box::into_raw(Box::new(ENetPacket {
+            reference_count: 0,
+            flags,
+            data: buffer.as_mut_ptr(),
+            data_length: buffer.len(),
+            free_callback: None,
+            user_data: ptr::null_mut(),
+            buffer,
+        }))
+    }
+
+    unsafe fn to_packet(&self) -> Packet {
+        let data = if self.data_length == 0 {
+            &[]
+        } else {
+            slice::from_raw_parts(self.data, self.data_length)
+        };
+        PacketBuilder::new(data)
+            .flags(PacketFlags {
+                reliable: self.flags & ENET_PACKET_FLAG_RELIABLE != 0,
+                unsequenced: self.flags & ENET_PACKET_FLAG_UNSEQUENCED != 0,
+                unreliable_fragment: self.flags & ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT != 0,
+            })
+            .build()
+    }
+}
+
+/// `ENET_PEER_STATE_*`, the state of an [`ENetPeer`].
+pub type ENetPeerState = c_int;
+
+fn peer_state(state: PeerState) -> ENetPeerState {
+    match state {
+        PeerState::Disconnected => 0,
+        PeerState::Connecting => 1,
+        PeerState::AcknowledgingConnect => 2,
+        PeerState::ConnectionPending => 3,
+        PeerState::ConnectionSucceeded => 4,
+        PeerState::Connected => 5,
+        PeerState::DisconnectLater => 6,
+        PeerState::Disconnecting => 7,
+        PeerState::AcknowledgingDisconnect => 8,
+        PeerState::Zombie => 9,
+    }
+}
+
+/// A peer of an [`ENetHost`], in its `peers` array.
+#[repr(C)]
+pub struct ENetPeer {
+    /// The host the peer belongs to.
+    pub host: *mut ENetHost,
+    /// The peer's ID in the remote host.
+    pub outgoing_peer_id: u16,
+    /// The peer's index in its host's `peers` array.
+    pub incoming_peer_id: u16,
+    /// The ID of the peer's current connection.
+    pub connect_id: u32,
+    /// The session ID of outgoing datagrams.
+    pub outgoing_session_id: u8,
+    /// The session ID of incoming datagrams.
+    pub incoming_session_id: u8,
+    /// The remote peer's address.
+    pub address: ENetAddress,
+    /// Application private data.
+    pub data: *mut c_void,
+    /// The peer's `ENET_PEER_STATE_*`.
+    pub state: ENetPeerState,
+    /// The number of channels allocated for the connection.
+    pub channel_count: usize,
+    /// Downstream bandwidth of the remote peer in bytes/second.
+    pub incoming_bandwidth: u32,
+    /// Upstream bandwidth of the remote peer in bytes/second.
+    pub outgoing_bandwidth: u32,
+    /// Total downstream data received.
+    pub incoming_data_total: u32,
+    /// Total upstream data sent.
+    pub outgoing_data_total: u32,
+    /// Total packets sent.
+    pub packets_sent: u32,
+    /// Total packets lost.
+    pub packets_lost: u32,
+    /// Mean packet loss of reliable packets, as a ratio of `ENET_PEER_PACKET_LOSS_SCALE`.
+    pub packet_loss: u32,
+    /// Variance of the mean packet loss.
+    pub packet_loss_variance: u32,
+    /// The interval between pings, in milliseconds.
+    pub ping_interval: u32,
+    /// Mean round trip time, in milliseconds.
+    pub round_trip_time: u32,
+    /// Round trip time variance, in milliseconds.
+    pub round_trip_time_variance: u32,
+    /// The peer's maximum transmission unit.
+    pub mtu: u32,
+}
+
+impl ENetPeer {
+    fn new(host: *mut ENetHost, index: usize) -> Self {
+        Self {
+            host,
+            outgoing_peer_id: 0,
+            incoming_peer_id: index as u16,
+            connect_id: 0,
+            outgoing_session_id: 0,
+            incoming_session_id: 0,
+            address: ENetAddress::default(),
+            data: ptr::null_mut(),
+            state: 0,
+            channel_count: 0,
+            incoming_bandwidth: 0,
+            outgoing_bandwidth: 0,
+            incoming_data_total: 0,
+            outgoing_data_total: 0,
+            packets_sent: 0,
+            packets_lost: 0,
+            packet_loss: 0,
+            packet_loss_variance: 0,
+            ping_interval: 0,
+            round_trip_time: 0,
+            round_trip_time_variance: 0,
+            mtu: 0,
+        }
+    }
+
+    fn refresh(&mut self, peer: &Peer<UdpSocket>) {
+        unsafe {
+            self.outgoing_peer_id = (*peer.0).outgoing_peer_id;
+            self.connect_id = (*peer.0).connect_id;
+            self.outgoing_session_id = (*peer.0).outgoing_session_id;
+            self.incoming_session_id = (*peer.0).incoming_session_id;
+        }
+        self.address = peer
+            .address()
+            .map(ENetAddress::from_socket_addr)
+            .unwrap_or_default();
+        self.state = peer_state(peer.state());
+        self.channel_count = peer.channel_count();
+        self.incoming_bandwidth = peer.incoming_bandwidth();
+        self.outgoing_bandwidth = peer.outgoing_bandwidth();
+        self.incoming_data_total = peer.incoming_data_total();
+        self.outgoing_data_total = peer.outgoing_data_total();
+        self.packets_sent = peer.packets_sent();
+        self.packets_lost = peer.packets_lost();
+        self.packet_loss = peer.packet_loss();
+        self.packet_loss_variance = peer.packet_loss_variance();
+        self.ping_interval = peer.ping_interval().as_millis() as u32;
+        self.round_trip_time = peer.round_trip_time().as_millis() as u32;
+        self.round_trip_time_variance = peer.round_trip_time_variance().as_millis() as u32;
+        self.mtu = u32::from(peer.mtu());
+    }
+}
+
+type ChecksumFunction = Box<dyn Fn(&[&[u8]]) -> u32 + Send + Sync>;
+
+/// A host, created with [`enet_host_create`].
+#[repr(C)]
+pub struct ENetHost {
+    /// The address the host is bound to.
+    pub address: ENetAddress,
+    /// Downstream bandwidth of the host in bytes/second, or `0` if unlimited.
+    pub incoming_bandwidth: u32,
+    /// Upstream bandwidth of the host in bytes/second, or `0` if unlimited.
+    pub outgoing_bandwidth: u32,
+    /// The host's maximum transmission unit.
+    pub mtu: u32,
+    /// The host's peers.
+    pub peers: *mut ENetPeer,
+    /// The number of peers.
+    pub peer_count: usize,
+    /// The maximum channels of incoming connections.
+    pub channel_limit: usize,
+    /// The time the host was last serviced, see [`enet_time_get`].
+    pub service_time: u32,
+    /// The checksum function, or `NULL`. May be assigned.
+    pub checksum: ENetChecksumCallback,
+    /// The number of connected peers.
+    pub connected_peers: usize,
+    host: Host<UdpSocket>,
+    events: VecDeque<EventNoRef>,
+    sent_packets: Vec<*mut ENetPacket>,
+    applied_checksum: ENetChecksumCallback,
+}
+
+impl ENetHost {
+    /// Apply fields the application may have assigned.
+    fn apply(&mut self) {
+        let address = |checksum: ENetChecksumCallback| checksum.map(|checksum| checksum as usize);
+        if address(self.checksum) == address(self.applied_checksum) {
+            return;
+        }
+        self.applied_checksum = self.checksum;
+        self.host
+            .set_checksum(self.checksum.map(|checksum| -> ChecksumFunction {
+                if checksum as usize == enet_crc32 as *const () as usize {
+                    Box::new(crc32)
+                } else {
+                    Box::new(move |in_buffers: &[&[u8]]| {
+                        let buffers: Vec<ENetBuffer> = in_buffers
+                            .iter()
+                            .map(|buffer| ENetBuffer::from_slice(buffer))
+                            .collect();
+                        unsafe { checksum(buffers.as_ptr(), buffers.len()) }
+                    })
+                }
+            }));
+    }
+
+    /// Refresh the fields read by the application.
+    fn refresh(&mut self) {
+        let peers = unsafe { slice::from_raw_parts_mut(self.peers, self.peer_count) };
+        for (mirror, peer) in peers.iter_mut().zip(self.host.peers_mut()) {
+            mirror.refresh(peer);
+        }
+        let (incoming_bandwidth, outgoing_bandwidth) = self.host.bandwidth_limit();
+        self.incoming_bandwidth = incoming_bandwidth.unwrap_or(0);
+        self.outgoing_bandwidth = outgoing_bandwidth.unwrap_or(0);
+        self.mtu = u32::from(self.host.mtu());
+        self.channel_limit = self.host.channel_limit();
+        self.connected_peers = self.host.connected_peers().count();
+    }
+
+    /// Release the references held by packets queued since the last call, destroying those
+    /// without any left.
+    fn release_sent_packets(&mut self) {
+        for packet in self.sent_packets.drain(..) {
+            unsafe {
+                (*packet).reference_count -= 1;
+                if (*packet).reference_count == 0 {
+                    enet_packet_destroy(packet);
+                }
+            }
+        }
+    }
+
+    fn peer_index(&self, peer: *const ENetPeer) -> usize {
+        (peer as usize - self.peers as usize) / size_of::<ENetPeer>()
+    }
+
+    fn peer_mut(&mut self, index: usize) -> &mut Peer<UdpSocket> {
+        self.host
+            .peers_mut()
+            .nth(index)
+            .expect("Expected the peer to belong to the host.")
+    }
+
+    /// Service the host until it has an event for the application, returning `1` and filling
+    /// `event`, or `0` if it has none. With a null `event`, events are queued for later calls.
+    unsafe fn poll(&mut self, event: *mut ENetEvent) -> c_int {
+        loop {
+            if !event.is_null() {
+                if let Some(next) = self.events.pop_front() {
+                    self.fill_event(next, event);
+                    return 1;
+                }
+            }
+            match self.host.service().map(|next| next.map(Event::no_ref)) {
+                Ok(Some(next)) => self.queue_event(next),
+                Ok(None) => return 0,
+                Err(_) => return -1,
+            }
+        }
+    }
+
+    fn queue_event(&mut self, event: EventNoRef) {
+        if matches!(
+            event,
+            EventNoRef::Connect { .. } | EventNoRef::Disconnect { .. } | EventNoRef::Receive { .. }
+        ) {
+            self.events.push_back(event);
+        }
+    }
+
+    unsafe fn fill_event(&mut self, next: EventNoRef, event: *mut ENetEvent) {
+        self.refresh();
+        let (kind, peer, channel_id, data, packet) = match next {
+            EventNoRef::Connect { peer, data, .. } => {
+                (ENET_EVENT_TYPE_CONNECT, peer, 0, data, ptr::null_mut())
+            }
+            EventNoRef::Disconnect { peer, data } => {
+                (ENET_EVENT_TYPE_DISCONNECT, peer, 0, data, ptr::null_mut())
+            }
+            EventNoRef::Receive {
+                peer,
+                channel_id,
+                packet,
+                ..
+            } => {
+                let flags = packet.flags();
+                let mut raw_flags = 0;
+                if flags.reliable {
+                    raw_flags |= ENET_PACKET_FLAG_RELIABLE;
+                }
+                if flags.unsequenced {
+                    raw_flags |= ENET_PACKET_FLAG_UNSEQUENCED;
+                }
+                if flags.unreliable_fragment {
+                    raw_flags |= ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT;
+                }
+                let packet = ENetPacket::allocate(packet.data().to_vec(), raw_flags);
+                (ENET_EVENT_TYPE_RECEIVE, peer, channel_id, 0, packet)
+            }
+            _ => unreachable!(),
+        };
+        *event = ENetEvent {
+            kind,
+            peer: self.peers.add(peer.index),
+            channel_id,
+            data,
+            packet,
+        };
+    }
+}
+
+/// An event returned by [`enet_host_service`] or [`enet_host_check_events`].
+#[repr(C)]
+pub struct ENetEvent {
+    /// The `ENET_EVENT_TYPE_*` of the event.
+    pub kind: c_int,
+    /// The peer the event is for.
+    pub peer: *mut ENetPeer,
+    /// The channel a packet was received on.
+    pub channel_id: u8,
+    /// The data sent with a connection or disconnection.
+    pub data: u32,
+    /// The packet received, which the application must destroy.
+    pub packet: *mut ENetPacket,
+}
+
+/// A compressor implemented by the application, passed to [`enet_host_compress`].
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct ENetCompressor {
+    /// Passed to each callback.
+    pub context: *mut c_void,
+    /// Compress the buffers, returning the compressed length, or `0` on failure.
+    pub compress: Option<
+        unsafe extern "C" fn(
+            context: *mut c_void,
+            in_buffers: *const ENetBuffer,
+            in_buffer_count: usize,
+            in_limit: usize,
+            out_data: *mut u8,
+            out_limit: usize,
+        ) -> usize,
+    >,
+    /// Decompress the data, returning the decompressed length, or `0` on failure.
+    pub decompress: Option<
+        unsafe extern "C" fn(
+            context: *mut c_void,
+            in_data: *const u8,
+            in_limit: usize,
+            out_data: *mut u8,
+            out_limit: usize,
+        ) -> usize,
+    >,
+    /// Called when the compressor is replaced, or the host destroyed.
+    pub destroy: Option<unsafe extern "C" fn(context: *mut c_void)>,
+}
+
+struct CallbackCompressor(ENetCompressor);
+
+// the C API is used from one thread at a time
+unsafe impl Send for CallbackCompressor {}
+
+impl Compressor for CallbackCompressor {
+    fn compress(&mut self, in_buffers: &[&[u8]], in_limit: usize, out: &mut [u8]) -> usize {
+        let Some(compress) = self.0.compress else {
+            return 0;
+        };
+        let buffers: Vec<ENetBuffer> = in_buffers
+            .iter()
+            .map(|buffer| ENetBuffer::from_slice(buffer))
+            .collect();
+        unsafe {
+            compress(
+                self.0.context,
+                buffers.as_ptr(),
+                buffers.len(),
+                in_limit,
+                out.as_mut_ptr(),
+                out.len(),
+            )
+        }
+    }
+
+    fn decompress(&mut self, in_data: &[u8], out: &mut [u8]) -> usize {
+        let Some(decompress) = self.0.decompress else {
+            return 0;
+        };
+        unsafe {
+            decompress(
+                self.0.context,
+                in_data.as_ptr(),
+                in_data.len(),
+                out.as_mut_ptr(),
+                out.len(),
+            )
+        }
+    }
+}
+
+impl Drop for CallbackCompressor {
+    fn drop(&mut self) {
+        if let Some(destroy) = self.0.destroy {
+            unsafe { destroy(self.0.context) }
+        }
+    }
+}
+
+/// The allocation callbacks passed to [`enet_initialize_with_callbacks`].
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct ENetCallbacks {
+    /// Allocate `size` bytes, like `malloc`, returning null on failure.
+    pub malloc: Option<unsafe extern "C" fn(size: usize) -> *mut c_void>,
+    /// Free memory returned by `malloc`, like `free`.
+    pub free: Option<unsafe extern "C" fn(memory: *mut c_void)>,
+    /// Called when `malloc` fails, before the process is aborted.
+    pub no_memory: Option<unsafe extern "C" fn()>,
+}
+
+/// The callbacks installed by [`enet_initialize_with_callbacks`].
+static CALLBACKS: OnceLock<ENetCallbacks> = OnceLock::new();
+
+/// Forwards ENet's allocations to the installed [`CALLBACKS`].
+static CALLBACK_ALLOCATOR: Allocator = Allocator {
+    allocate: callback_allocate,
+    deallocate: callback_deallocate,
+};
+
+/// `malloc` only guarantees fundamental alignment, so allocations are padded to align them, with
+/// the pointer returned by `malloc` stored just before the aligned memory, to be freed.
+unsafe fn callback_allocate(layout: Layout) -> *mut u8 {
+    let Some(callbacks) = CALLBACKS.get() else {
+        return ptr::null_mut();
+    };
+    let (Some(malloc), Some(size)) = (
+        callbacks.malloc,
+        (layout.size() + size_of::<*mut c_void>()).checked_add(layout.align()),
+    ) else {
+        return ptr::null_mut();
+    };
+    let memory = malloc(size).cast::<u8>();
+    if memory.is_null() {
+        if let Some(no_memory) = callbacks.no_memory {
+            no_memory();
+        }
+        return ptr::null_mut();
+    }
+    let header = memory.add(size_of::<*mut c_void>());
+    let aligned = header.add(header.align_offset(layout.align()));
+    aligned
+        .sub(size_of::<*mut c_void>())
+        .cast::<*mut u8>()
+        .write_unaligned(memory);
+    aligned
+}
+
+unsafe fn callback_deallocate(ptr: *mut u8, _layout: Layout) {
+    let memory = ptr
+        .sub(size_of::<*mut c_void>())
+        .cast::<*mut u8>()
+        .read_unaligned();
+    if let Some(free) = CALLBACKS.get().and_then(|callbacks| callbacks.free) {
+        free(memory.cast());
+    }
+}
+
+/// The base of [`enet_time_get`], in milliseconds since the Unix epoch, or `u64::MAX` before the
+/// first call.
+static TIME_BASE: AtomicU64 = AtomicU64::new(u64::MAX);
+
+fn epoch_millis() -> u64 {
+    time_since_epoch().as_millis() as u64
+}
+
+fn time_millis() -> u64 {
+    let now = epoch_millis();
+    let base = match TIME_BASE.compare_exchange(u64::MAX, now, Ordering::Relaxed, Ordering::Relaxed)
+    {
+        Ok(_) => now,
+        Err(base) => base,
+    };
+    now.wrapping_sub(base)
+}
+
+fn wait_readable(socket: &UdpSocket, timeout: Duration) {
+    if socket.set_nonblocking(false).is_ok() && socket.set_read_timeout(Some(timeout)).is_ok() {
+        _ = socket.peek(&mut [0]);
+    }
+    _ = socket.set_nonblocking(true);
+}
+
+unsafe fn write_c_string(value: &str, name: *mut c_char, name_length: usize) -> c_int {
+    if name.is_null() || value.len() >= name_length {
+        return -1;
+    }
+    ptr::copy_nonoverlapping(value.as_ptr().cast(), name, value.len());
+    *name.add(value.len()) = 0;
+    0
+}
+
+/// Initialize ENet. Does nothing, and returns `0`.
+#[no_mangle]
+pub extern "C" fn enet_initialize() -> c_int {
+    0
+}
+
+/// Initialize ENet, if `version` is at least 1.3.0, allocating its memory with the `malloc` and
+/// `free` callbacks of `inits`, if given. `no_memory` is called before aborting when `malloc`
+/// fails.
+///
+/// The callbacks are installed with [`set_allocator`](`crate::set_allocator`), so they apply to
+/// the whole process, and must be passed before ENet has allocated any memory. Returns `-1` if
+/// only one of `malloc` and `free` is given, or ENet already allocates with other functions.
+///
+/// ```
+/// use core::{
+///     ffi::c_void,
+///     ptr,
+///     sync::atomic::{AtomicUsize, Ordering},
+/// };
+///
+/// use rusty_enet::capi::*;
+///
+/// static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+///
+/// extern "C" {
+///     fn malloc(size: usize) -> *mut c_void;
+///     fn free(memory: *mut c_void);
+/// }
+///
+/// unsafe extern "C" fn counting_malloc(size: usize) -> *mut c_void {
+///     ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+///     malloc(size)
+/// }
+///
+/// unsafe extern "C" fn counting_free(memory: *mut c_void) {
+///     ALLOCATIONS.fetch_sub(1, Ordering::Relaxed);
+///     free(memory);
+/// }
+///
+/// let callbacks = ENetCallbacks {
+///     malloc: Some(counting_malloc),
+///     free: Some(counting_free),
+///     no_memory: None,
+/// };
+/// unsafe {
+///     assert_eq!(enet_initialize_with_callbacks(1 << 16 | 3 << 8, &callbacks), 0);
+///     let host = enet_host_create(ptr::null(), 1, 1, 0, 0);
+///     assert!(ALLOCATIONS.load(Ordering::Relaxed) > 0);
+///     enet_host_destroy(host);
+/// }
+/// assert_eq!(ALLOCATIONS.load(Ordering::Relaxed), 0);
+/// ```
+///
+/// # Safety
+///
+/// `inits` must be null, or point to a valid [`ENetCallbacks`].
+#[no_mangle]
+pub unsafe extern "C" fn enet_initialize_with_callbacks(
+    version: u32,
+    inits: *const ENetCallbacks,
+) -> c_int {
+    if version < (1 << 16) | (3 << 8) {
+        return -1;
+    }
+    let Some(&inits) = inits.as_ref() else {
+        return 0;
+    };
+    match (inits.malloc, inits.free) {
+        (None, None) => 0,
+        (Some(_), Some(_)) => {
+            if CALLBACKS.set(inits).is_err() || set_allocator(&CALLBACK_ALLOCATOR).is_err() {
+                return -1;
+            }
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Deinitialize ENet. Does nothing.
+#[no_mangle]
+pub extern "C" fn enet_deinitialize() {}
+
+/// The version of ENet implemented, as created by `ENET_VERSION_CREATE`.
+#[no_mangle]
+pub extern "C" fn enet_linked_version() -> u32 {
+    let version = Version::current();
+    (u32::from(version.major) << 16) | (u32::from(version.minor) << 8) | u32::from(version.patch)
+}
+
+/// The time in milliseconds since the first call, or since the time set with
+/// [`enet_time_set`]. Wraps around.
+#[no_mangle]
+pub extern "C" fn enet_time_get() -> u32 {
+    time_millis() as u32
+}
+
+/// Set the time returned by [`enet_time_get`].
+#[no_mangle]
+pub extern "C" fn enet_time_set(time: u32) {
+    TIME_BASE.store(
+        epoch_millis().wrapping_sub(u64::from(time)),
+        Ordering::Relaxed,
+    );
+}
+
+/// ENet's CRC32 checksum, which may be assigned to [`ENetHost::checksum`].
+///
+/// # Safety
+///
+/// `buffers` must point to `buffer_count` valid buffers.
+#[no_mangle]
+pub unsafe extern "C" fn enet_crc32(buffers: *const ENetBuffer, buffer_count: usize) -> u32 {
+    let buffers = if buffer_count == 0 {
+        Vec::new()
+    } else {
+        slice::from_raw_parts(buffers, buffer_count)
+            .iter()
+            .map(|buffer| buffer.as_slice())
+            .collect()
+    };
+    crc32(&buffers)
+}
+
+/// Parse an IPv4 address in dotted decimal notation into `address`. Returns `0` on success, or
+/// `-1` if `name` isn't an IPv4 address.
+///
+/// # Safety
+///
+/// `address` must be valid, and `name` must be a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn enet_address_set_host_ip(
+    address: *mut ENetAddress,
+    name: *const c_char,
+) -> c_int {
+    match CStr::from_ptr(name).to_str().map(str::parse::<Ipv4Addr>) {
+        Ok(Ok(ip)) => {
+            (*address).host = u32::from_ne_bytes(ip.octets());
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Resolve a host name or IPv4 address into `address`. Returns `0` on success, or `-1` if it
+/// doesn't resolve to an IPv4 address.
+///
+/// # Safety
+///
+/// `address` must be valid, and `name` must be a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn enet_address_set_host(
+    address: *mut ENetAddress,
+    name: *const c_char,
+) -> c_int {
+    let Ok(name) = CStr::from_ptr(name).to_str() else {
+        return -1;
+    };
+    let resolved = (name, 0)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.find(SocketAddr::is_ipv4));
+    let Some(resolved) = resolved else {
+        return -1;
+    };
+    (*address).host = ENetAddress::from_socket_addr(resolved).host;
+    0
+}
+
+/// Write the IP address of `address` to `name`, nul terminated. Returns `0` on success, or `-1`
+/// if `name_length` is too short.
+///
+/// # Safety
+///
+/// `address` must be valid, and `name` must point to `name_length` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn enet_address_get_host_ip(
+    address: *const ENetAddress,
+    name: *mut c_char,
+    name_length: usize,
+) -> c_int {
+    let ip = Ipv4Addr::from((*address).host.to_ne_bytes());
+    write_c_string(&ip.to_string(), name, name_length)
+}
+
+/// Write the IP address of `address` to `name`, as reverse lookups aren't supported. See
+/// [`enet_address_get_host_ip`].
+///
+/// # Safety
+///
+/// See [`enet_address_get_host_ip`].
+#[no_mangle]
+pub unsafe extern "C" fn enet_address_get_host(
+    address: *const ENetAddress,
+    name: *mut c_char,
+    name_length: usize,
+) -> c_int {
+    enet_address_get_host_ip(address, name, name_length)
+}
+
+/// Create a packet holding a copy of `data`, or `length` zeroed bytes if `data` is null. With
+/// `ENET_PACKET_FLAG_NO_ALLOCATE`, the packet refers to `data` instead, which must outlive it.
+///
+/// # Safety
+///
+/// `data` must be null, or point to `length` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn enet_packet_create(
+    data: *const c_void,
+    length: usize,
+    flags: u32,
+) -> *mut ENetPacket {
+    if flags & ENET_PACKET_FLAG_NO_ALLOCATE != 0 {
+        let packet = ENetPacket::allocate(Vec::new(), flags);
+        (*packet).data = data.cast_mut().cast();
+        (*packet).data_length = length;
+        return packet;
+    }
+    let buffer = if data.is_null() {
+        vec![0; length]
+    } else {
+        slice::from_raw_parts(data.cast::<u8>(), length).to_vec()
+    };
+    ENetPacket::allocate(buffer, flags)
+}
+
+/// Destroy a packet, calling its `freeCallback`. Does nothing if `packet` is null.
+///
+/// # Safety
+///
+/// `packet` must be null, or a packet which hasn't been destroyed, and isn't queued.
+#[no_mangle]
+pub unsafe extern "C" fn enet_packet_destroy(packet: *mut ENetPacket) {
+    if packet.is_null() {
+        return;
+    }
+    if let Some(free_callback) = (*packet).free_callback {
+        free_callback(packet);
+    }
+    drop(Box::from_raw(packet));
+}
+
+/// Resize a packet's data, keeping its contents. Returns `0`.
+///
+/// # Safety
+///
+/// `packet` must be a valid packet. With `ENET_PACKET_FLAG_NO_ALLOCATE`, its data must be at
+/// least `length` bytes long.
+#[no_mangle]
+pub unsafe extern "C" fn enet_packet_resize(packet: *mut ENetPacket, length: usize) -> c_int {
+    let packet = &mut *packet;
+    if length <= packet.data_length || packet.flags & ENET_PACKET_FLAG_NO_ALLOCATE != 0 {
+        packet.data_length = length;
+        return 0;
+    }
+    packet.buffer.resize(length, 0);
+    packet.data = packet.buffer.as_mut_ptr();
+    packet.data_length = length;
+    0
+}
+
+/// Create a host bound to `address`, or to any address and port if it is null. Bandwidth limits
+/// of `0` are unlimited, as is a `channel_limit` of `0`. Returns null on failure.
+///
+/// # Safety
+///
+/// `address` must be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_host_create(
+    address: *const ENetAddress,
+    peer_count: usize,
+    channel_limit: usize,
+    incoming_bandwidth: u32,
+    outgoing_bandwidth: u32,
+) -> *mut ENetHost {
+    let bind_address = if address.is_null() {
+        SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))
+    } else {
+        (*address).to_socket_addr()
+    };
+    let Ok(socket) = UdpSocket::bind(bind_address) else {
+        return ptr::null_mut();
+    };
+    let local_address = socket.local_addr().map_or(
+        ENetAddress::from_socket_addr(bind_address),
+        ENetAddress::from_socket_addr,
+    );
+    let maximum_channels = PROTOCOL_MAXIMUM_CHANNEL_COUNT as usize;
+    let Ok(host) = Host::new(
+        socket,
+        HostSettings {
+            peer_limit: peer_count,
+            channel_limit: match channel_limit {
+                0 => maximum_channels,
+                limit => limit.min(maximum_channels),
+            },
+            incoming_bandwidth_limit: (incoming_bandwidth != 0).then_some(incoming_bandwidth),
+            outgoing_bandwidth_limit: (outgoing_bandwidth != 0).then_some(outgoing_bandwidth),
+            time: Box::new(|| Duration::from_millis(time_millis())),
+            protocol_version: ProtocolVersion::Enet,
+            ..HostSettings::default()
+        },
+    ) else {
+        return ptr::null_mut();
+    };
+    let host = Box::into_raw(Box::new(ENetHost {
+        address: local_address,
+        incoming_bandwidth,
+        outgoing_bandwidth,
+        mtu: 0,
+        peers: ptr::null_mut(),
+        peer_count,
+        channel_limit,
+        service_time: enet_time_get(),
+        checksum: None,
+        connected_peers: 0,
+        host,
+        events: VecDeque::new(),
+        sent_packets: Vec::new(),
+        applied_checksum: None,
+    }));
+    let peers: Box<[ENetPeer]> = (0..peer_count)
+        .map(|index| ENetPeer::new(host, index))
+        .collect();
+    (*host).peers = Box::into_raw(peers).cast();
+    (*host).refresh();
+    host
+}
+
+/// Destroy a host, resetting its peers without notifying them, and destroying packets queued
+/// since it was last serviced.
+///
+/// # Safety
+///
+/// `host` must be null, or a host which hasn't been destroyed. Its peers must not be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn enet_host_destroy(host: *mut ENetHost) {
+    if host.is_null() {
+        return;
+    }
+    let mut host = Box::from_raw(host);
+    host.release_sent_packets();
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+        host.peers,
+        host.peer_count,
+    )));
+}
+
+/// Start connecting to `address` with `channel_count` channels, clamped to those ENet supports.
+/// Returns the peer, which is connected once an `ENET_EVENT_TYPE_CONNECT` event arrives, or null
+/// if no peers are available.
+///
+/// # Safety
+///
+/// `host` and `address` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_host_connect(
+    host: *mut ENetHost,
+    address: *const ENetAddress,
+    channel_count: usize,
+    data: u32,
+) -> *mut ENetPeer {
+    let host = &mut *host;
+    host.apply();
+    let channel_count = channel_count.clamp(1, PROTOCOL_MAXIMUM_CHANNEL_COUNT as usize);
+    let Ok(connection) = host
+        .host
+        .connect((*address).to_socket_addr(), channel_count, data)
+    else {
+        return ptr::null_mut();
+    };
+    host.refresh();
+    host.peers.add(connection.peer_id().index)
+}
+
+/// Service the host, waiting up to `timeout` milliseconds for an event. Returns `1` and fills
+/// `event` if an event occurred, `0` if none did, or `-1` on failure. With a null `event`, events
+/// are queued for later calls.
+///
+/// # Safety
+///
+/// `host` must be valid, and `event` null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_host_service(
+    host: *mut ENetHost,
+    event: *mut ENetEvent,
+    timeout: u32,
+) -> c_int {
+    let host = &mut *host;
+    host.apply();
+    if !event.is_null() {
+        (*event).kind = ENET_EVENT_TYPE_NONE;
+        (*event).peer = ptr::null_mut();
+        (*event).packet = ptr::null_mut();
+    }
+    let deadline = Instant::now() + Duration::from_millis(u64::from(timeout));
+    loop {
+        host.service_time = enet_time_get();
+        let result = host.poll(event);
+        host.release_sent_packets();
+        host.refresh();
+        if result != 0 {
+            return result;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return 0;
+        }
+        wait_readable(host.host.socket(), deadline - now);
+    }
+}
+
+/// Dispatch an event which has already been received, without servicing the host. Returns `1`
+/// and fills `event` if there was one, `0` if not, or `-1` if `event` is null.
+///
+/// # Safety
+///
+/// `host` must be valid, and `event` null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_host_check_events(
+    host: *mut ENetHost,
+    event: *mut ENetEvent,
+) -> c_int {
+    if event.is_null() {
+        return -1;
+    }
+    let host = &mut *host;
+    (*event).kind = ENET_EVENT_TYPE_NONE;
+    (*event).peer = ptr::null_mut();
+    (*event).packet = ptr::null_mut();
+    loop {
+        if let Some(next) = host.events.pop_front() {
+            host.fill_event(next, event);
+            return 1;
+        }
+        let Some(next) = host.host.check_events().map(Event::no_ref) else {
+            return 0;
+        };
+        host.queue_event(next);
+    }
+}
+
+/// Send any queued packets without servicing the host.
+///
+/// # Safety
+///
+/// `host` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_host_flush(host: *mut ENetHost) {
+    let host = &mut *host;
+    host.apply();
+    host.host.flush();
+    host.release_sent_packets();
+    host.refresh();
+}
+
+/// Queue a packet to be sent to every connected peer. See [`enet_peer_send`].
+///
+/// # Safety
+///
+/// `host` and `packet` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_host_broadcast(
+    host: *mut ENetHost,
+    channel_id: u8,
+    packet: *mut ENetPacket,
+) {
+    let host = &mut *host;
+    host.host.broadcast(channel_id, &(*packet).to_packet());
+    (*packet).reference_count += 1;
+    host.sent_packets.push(packet);
+}
+
+/// Set the maximum channels of future incoming connections, or the most ENet supports if `0`.
+///
+/// # Safety
+///
+/// `host` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_host_channel_limit(host: *mut ENetHost, channel_limit: usize) {
+    let host = &mut *host;
+    let maximum_channels = PROTOCOL_MAXIMUM_CHANNEL_COUNT as usize;
+    let channel_limit = match channel_limit {
+        0 => maximum_channels,
+        limit => limit.min(maximum_channels),
+    };
+    _ = host.host.set_channel_limit(channel_limit);
+    host.refresh();
+}
+
+/// Set the host's bandwidth limits in bytes/second, or `0` for unlimited.
+///
+/// # Safety
+///
+/// `host` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_host_bandwidth_limit(
+    host: *mut ENetHost,
+    incoming_bandwidth: u32,
+    outgoing_bandwidth: u32,
+) {
+    let host = &mut *host;
+    _ = host.host.set_bandwidth_limit(
+        (incoming_bandwidth != 0).then_some(incoming_bandwidth),
+        (outgoing_bandwidth != 0).then_some(outgoing_bandwidth),
+    );
+    host.refresh();
+}
+
+/// Compress datagrams with ENet's range coder. Returns `0`.
+///
+/// # Safety
+///
+/// `host` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_host_compress_with_range_coder(host: *mut ENetHost) -> c_int {
+    (*host)
+        .host
+        .set_compressor(Some(Box::new(RangeCoder::new())));
+    0
+}
+
+/// Compress datagrams with a copy of `compressor`, or disable compression if it is null. The
+/// previous compressor's `destroy` callback is called.
+///
+/// # Safety
+///
+/// `host` must be valid, and `compressor` null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_host_compress(
+    host: *mut ENetHost,
+    compressor: *const ENetCompressor,
+) {
+    let compressor = if compressor.is_null() {
+        None
+    } else {
+        Some(Box::new(CallbackCompressor(*compressor)) as Box<dyn Compressor>)
+    };
+    (*host).host.set_compressor(compressor);
+}
+
+/// Queue a packet to be sent to `peer`. Returns `0` on success, or `-1` if the peer isn't
+/// connected, the channel is invalid or the packet is too large.
+///
+/// On success the packet is referenced until the host is next serviced or flushed, and
+/// destroyed then if no references remain, so it must not be destroyed by the application.
+///
+/// # Safety
+///
+/// `peer` and `packet` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_peer_send(
+    peer: *mut ENetPeer,
+    channel_id: u8,
+    packet: *mut ENetPacket,
+) -> c_int {
+    let host = &mut *(*peer).host;
+    let index = host.peer_index(peer);
+    if host
+        .peer_mut(index)
+        .send(channel_id, &(*packet).to_packet())
+        .is_err()
+    {
+        return -1;
+    }
+    (*packet).reference_count += 1;
+    host.sent_packets.push(packet);
+    0
+}
+
+/// Call `f` with the peer, then refresh the host's fields.
+unsafe fn with_peer(peer: *mut ENetPeer, f: impl FnOnce(&mut Peer<UdpSocket>)) {
+    let host = &mut *(*peer).host;
+    let index = host.peer_index(peer);
+    f(host.peer_mut(index));
+    host.refresh();
+}
+
+/// Send a ping to `peer`.
+///
+/// # Safety
+///
+/// `peer` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_peer_ping(peer: *mut ENetPeer) {
+    with_peer(peer, Peer::ping);
+}
+
+/// Set the interval between pings to `peer`, in milliseconds.
+///
+/// # Safety
+///
+/// `peer` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_peer_ping_interval(peer: *mut ENetPeer, ping_interval: u32) {
+    with_peer(peer, |peer| {
+        peer.set_ping_interval(Duration::from_millis(u64::from(ping_interval)));
+    });
+}
+
+/// Set the timeout parameters of `peer`, in milliseconds. See [`Peer::set_timeout_durations`].
+///
+/// # Safety
+///
+/// `peer` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_peer_timeout(
+    peer: *mut ENetPeer,
+    timeout_limit: u32,
+    timeout_minimum: u32,
+    timeout_maximum: u32,
+) {
+    with_peer(peer, |peer| {
+        peer.set_timeout_durations(
+            timeout_limit,
+            Duration::from_millis(u64::from(timeout_minimum)),
+            Duration::from_millis(u64::from(timeout_maximum)),
+        );
+    });
+}
+
+/// Configure the throttle of `peer`. See [`Peer::set_throttle`].
+///
+/// # Safety
+///
+/// `peer` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_peer_throttle_configure(
+    peer: *mut ENetPeer,
+    interval: u32,
+    acceleration: u32,
+    deceleration: u32,
+) {
+    with_peer(peer, |peer| {
+        peer.set_throttle(interval, acceleration, deceleration);
+    });
+}
+
+/// Reset `peer` without notifying the remote peer.
+///
+/// # Safety
+///
+/// `peer` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_peer_reset(peer: *mut ENetPeer) {
+    with_peer(peer, Peer::reset);
+}
+
+/// Request a disconnection from `peer`.
+///
+/// # Safety
+///
+/// `peer` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_peer_disconnect(peer: *mut ENetPeer, data: u32) {
+    with_peer(peer, |peer| peer.disconnect(data));
+}
+
+/// Disconnect from `peer` immediately, without an `ENET_EVENT_TYPE_DISCONNECT` event.
+///
+/// # Safety
+///
+/// `peer` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_peer_disconnect_now(peer: *mut ENetPeer, data: u32) {
+    with_peer(peer, |peer| peer.disconnect_now(data));
+}
+
+/// Request a disconnection from `peer` once its queued packets are sent.
+///
+/// # Safety
+///
+/// `peer` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn enet_peer_disconnect_later(peer: *mut ENetPeer, data: u32) {
+    with_peer(peer, |peer| peer.disconnect_later(data));
+}
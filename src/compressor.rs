@@ -1,12 +1,12 @@
-use core::mem::zeroed;
-
+#[cfg(any(feature = "zstd", feature = "deflate"))]
+use crate::Vec;
 use crate::{
-    consts::BUFFER_MAXIMUM, enet_range_coder_compress, enet_range_coder_create,
-    enet_range_coder_decompress, enet_range_coder_destroy, ENetBuffer, ENetRangeCoder,
+    enet_range_coder_compress, enet_range_coder_create, enet_range_coder_decompress, Box,
+    ENetRangeCoder,
 };
 
 /// An interface for compressing ENet packets.
-pub trait Compressor {
+pub trait Compressor: Send {
     /// Compress the incoming buffers.
     fn compress(&mut self, in_buffers: &[&[u8]], in_limit: usize, out: &mut [u8]) -> usize;
     /// Decompress the buffer.
@@ -14,16 +14,13 @@ pub trait Compressor {
 }
 
 /// The built-in range coder compression provided by ENet.
-pub struct RangeCoder(*mut ENetRangeCoder);
-
-unsafe impl Send for RangeCoder {}
-unsafe impl Sync for RangeCoder {}
+pub struct RangeCoder(Box<ENetRangeCoder>);
 
 impl RangeCoder {
     /// Create a new range coder compressor.
     #[must_use]
     pub fn new() -> Self {
-        Self(unsafe { enet_range_coder_create().cast() })
+        Self(enet_range_coder_create())
     }
 }
 
@@ -35,41 +32,270 @@ impl Default for RangeCoder {
 
 impl Compressor for RangeCoder {
     fn compress(&mut self, in_buffers: &[&[u8]], in_limit: usize, out: &mut [u8]) -> usize {
-        unsafe {
-            let mut buffers: [ENetBuffer; BUFFER_MAXIMUM as usize] =
-                core::array::from_fn(|_| zeroed());
-            for (i, in_buffer) in in_buffers.iter().enumerate() {
-                buffers[i] = ENetBuffer {
-                    data: in_buffer.as_ptr().cast_mut(),
-                    data_length: in_buffer.len(),
-                };
-            }
-            enet_range_coder_compress(
-                self.0.cast(),
-                buffers.as_ptr(),
-                in_buffers.len(),
-                in_limit,
-                out.as_mut_ptr(),
-                out.len(),
-            )
+        enet_range_coder_compress(&mut self.0, in_buffers, in_limit, out)
+    }
+
+    fn decompress(&mut self, in_data: &[u8], out: &mut [u8]) -> usize {
+        enet_range_coder_decompress(&mut self.0, in_data, out)
+    }
+}
+
+/// [Zstandard](https://facebook.github.io/zstd/) compression, as an alternative to
+/// [`RangeCoder`].
+///
+/// Both hosts must use the same kind of compressor.
+#[cfg(feature = "zstd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zstd")))]
+pub struct Zstd {
+    compressor: zstd::bulk::Compressor<'static>,
+    decompressor: zstd::bulk::Decompressor<'static>,
+    buffer: Vec<u8>,
+}
+
+#[cfg(feature = "zstd")]
+impl Zstd {
+    /// The compression level used by [`Zstd::new`].
+    pub const DEFAULT_LEVEL: i32 = zstd::DEFAULT_COMPRESSION_LEVEL;
+
+    /// Create a new zstd compressor with [`Zstd::DEFAULT_LEVEL`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_level(Self::DEFAULT_LEVEL)
+    }
+
+    /// Create a new zstd compressor with a specific compression level, usually between `1` and
+    /// `22`. Levels outside of the range supported by zstd are clamped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if zstd fails to allocate its compression context.
+    #[must_use]
+    pub fn with_level(level: i32) -> Self {
+        let level = level.clamp(
+            *zstd::compression_level_range().start(),
+            *zstd::compression_level_range().end(),
+        );
+        Self {
+            compressor: zstd::bulk::Compressor::new(level)
+                .expect("Expected zstd compression context to be created."),
+            decompressor: zstd::bulk::Decompressor::new()
+                .expect("Expected zstd decompression context to be created."),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Default for Zstd {
+    fn default() -> Self {
+        Zstd::new()
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Compressor for Zstd {
+    fn compress(&mut self, in_buffers: &[&[u8]], in_limit: usize, out: &mut [u8]) -> usize {
+        join_buffers(&mut self.buffer, in_buffers, in_limit);
+        self.compressor
+            .compress_to_buffer(&self.buffer[..], out)
+            .unwrap_or(0)
+    }
+
+    fn decompress(&mut self, in_data: &[u8], out: &mut [u8]) -> usize {
+        self.decompressor
+            .decompress_to_buffer(in_data, out)
+            .unwrap_or(0)
+    }
+}
+
+/// Raw [DEFLATE](https://www.rfc-editor.org/rfc/rfc1951) compression, as an alternative to
+/// [`RangeCoder`].
+///
+/// Both hosts must use the same kind of compressor.
+#[cfg(feature = "deflate")]
+#[cfg_attr(docsrs, doc(cfg(feature = "deflate")))]
+pub struct Deflate {
+    compressor: Box<miniz_oxide::deflate::core::CompressorOxide>,
+    decompressor: Box<miniz_oxide::inflate::core::DecompressorOxide>,
+    buffer: Vec<u8>,
+}
+
+#[cfg(feature = "deflate")]
+impl Deflate {
+    /// The compression level used by [`Deflate::new`].
+    pub const DEFAULT_LEVEL: u8 = 6;
+
+    /// Create a new DEFLATE compressor with [`Deflate::DEFAULT_LEVEL`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_level(Self::DEFAULT_LEVEL)
+    }
+
+    /// Create a new DEFLATE compressor with a specific compression level, between `0` (no
+    /// compression) and `10` (best compression). Higher levels are clamped.
+    #[must_use]
+    pub fn with_level(level: u8) -> Self {
+        let mut compressor = Box::<miniz_oxide::deflate::core::CompressorOxide>::default();
+        compressor.set_format_and_level(miniz_oxide::DataFormat::Raw, level.min(10));
+        Self {
+            compressor,
+            decompressor: Box::default(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "deflate")]
+impl Default for Deflate {
+    fn default() -> Self {
+        Deflate::new()
+    }
+}
+
+#[cfg(feature = "deflate")]
+impl Compressor for Deflate {
+    fn compress(&mut self, in_buffers: &[&[u8]], in_limit: usize, out: &mut [u8]) -> usize {
+        use miniz_oxide::deflate::core::{compress, TDEFLFlush, TDEFLStatus};
+        join_buffers(&mut self.buffer, in_buffers, in_limit);
+        self.compressor.reset();
+        match compress(&mut self.compressor, &self.buffer, out, TDEFLFlush::Finish) {
+            (TDEFLStatus::Done, _, out_length) => out_length,
+            _ => 0,
         }
     }
 
     fn decompress(&mut self, in_data: &[u8], out: &mut [u8]) -> usize {
-        unsafe {
-            enet_range_coder_decompress(
-                self.0.cast(),
-                in_data.as_ptr(),
-                in_data.len(),
-                out.as_mut_ptr(),
-                out.len(),
-            )
+        use miniz_oxide::inflate::{
+            core::{decompress, inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF},
+            TINFLStatus,
+        };
+        self.decompressor.init();
+        match decompress(
+            &mut self.decompressor,
+            in_data,
+            out,
+            0,
+            TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
+        ) {
+            (TINFLStatus::Done, _, out_length) => out_length,
+            _ => 0,
         }
     }
 }
 
-impl Drop for RangeCoder {
-    fn drop(&mut self) {
-        unsafe { enet_range_coder_destroy(self.0.cast::<u8>()) };
+#[cfg(any(feature = "zstd", feature = "deflate"))]
+fn join_buffers(buffer: &mut Vec<u8>, in_buffers: &[&[u8]], in_limit: usize) {
+    buffer.clear();
+    for in_buffer in in_buffers {
+        let remaining = in_limit - buffer.len();
+        buffer.extend_from_slice(&in_buffer[..in_buffer.len().min(remaining)]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn range_coder_round_trip() {
+        use super::{Compressor, RangeCoder};
+
+        let data = "hello hello hello hello hello hello hello hello".as_bytes();
+        let mut compressed = [0; 4096];
+        let mut decompressed = [0; 4096];
+        let mut range_coder = RangeCoder::new();
+        let compressed_length =
+            range_coder.compress(&[&data[..10], &data[10..]], data.len(), &mut compressed);
+        assert!(compressed_length > 0 && compressed_length < data.len());
+        let decompressed_length =
+            range_coder.decompress(&compressed[..compressed_length], &mut decompressed);
+        assert_eq!(&decompressed[..decompressed_length], data);
+    }
+
+    /// Compressed output recorded from the C range coder, to keep the implementation wire
+    /// compatible with ENet.
+    #[test]
+    fn range_coder_compatibility() {
+        use super::{Compressor, RangeCoder};
+        use crate::{crc32, Vec};
+
+        let mut seed = 1_u32;
+        let mut random = |modulus: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            ((seed >> 16) % modulus) as u8
+        };
+        let inputs: [(Vec<u8>, usize, u32); 4] = [
+            (
+                "hello hello hello hello hello hello hello hello".into(),
+                15,
+                0x399c_6355,
+            ),
+            ((0..1400).map(|_| random(256)).collect(), 1514, 0x2ef2_0acf),
+            ((0..8000).map(|i| (i % 7) as u8).collect(), 33, 0xb747_3982),
+            (
+                (0..20000).map(|_| random(16) * random(16)).collect(),
+                17211,
+                0x99d1_43d6,
+            ),
+        ];
+        let mut range_coder = RangeCoder::new();
+        let mut compressed = Vec::from([0; 65536]);
+        let mut decompressed = Vec::from([0; 65536]);
+        for (data, length, checksum) in &inputs {
+            let (head, tail) = data.split_at(data.len() / 3);
+            let compressed_length =
+                range_coder.compress(&[head, tail], data.len(), &mut compressed);
+            assert_eq!(compressed_length, *length);
+            assert_eq!(crc32(&[&compressed[..compressed_length]]), *checksum);
+            let decompressed_length =
+                range_coder.decompress(&compressed[..compressed_length], &mut decompressed);
+            assert_eq!(&decompressed[..decompressed_length], &data[..]);
+        }
+    }
+
+    #[test]
+    fn range_coder_corrupt_data() {
+        use super::{Compressor, RangeCoder};
+
+        let mut seed = 7_u32;
+        let mut range_coder = RangeCoder::new();
+        let mut decompressed = [0; 4096];
+        for length in 1..2000 {
+            let data: crate::Vec<u8> = (0..length % 200)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    (seed >> 16) as u8
+                })
+                .collect();
+            let decompressed_length = range_coder.decompress(&data, &mut decompressed);
+            assert!(decompressed_length <= decompressed.len());
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_round_trip() {
+        round_trip(&mut super::Zstd::new());
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn deflate_round_trip() {
+        round_trip(&mut super::Deflate::new());
+    }
+
+    #[cfg(any(feature = "zstd", feature = "deflate"))]
+    fn round_trip(compressor: &mut dyn super::Compressor) {
+        let data = "hello hello hello hello hello hello hello hello".as_bytes();
+        let mut compressed = [0; 4096];
+        let mut decompressed = [0; 4096];
+        let compressed_length =
+            compressor.compress(&[&data[..10], &data[10..]], data.len(), &mut compressed);
+        assert!(compressed_length > 0 && compressed_length < data.len());
+        let decompressed_length =
+            compressor.decompress(&compressed[..compressed_length], &mut decompressed);
+        assert_eq!(&decompressed[..decompressed_length], data);
+        assert_eq!(
+            compressor.compress(&[data], data.len(), &mut compressed[..4]),
+            0
+        );
     }
 }
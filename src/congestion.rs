@@ -0,0 +1,131 @@
+use core::time::Duration;
+
+use crate::{PeerID, Vec};
+
+/// The state passed to a [`CongestionController`] with each round trip time sample.
+///
+/// Throttle values are between `0` and
+/// [`PEER_PACKET_THROTTLE_SCALE`](`crate::consts::PEER_PACKET_THROTTLE_SCALE`). See
+/// [`Peer::set_throttle`](`crate::Peer::set_throttle`) for what the throttle controls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThrottleSample {
+    /// The round trip time of the reliable command just acknowledged.
+    pub round_trip_time: Duration,
+    /// The lowest mean round trip time measured during the last throttle interval.
+    pub last_round_trip_time: Duration,
+    /// The round trip time variance at the time of [`ThrottleSample::last_round_trip_time`].
+    pub last_round_trip_time_variance: Duration,
+    /// The peer's current throttle.
+    pub throttle: u32,
+    /// The highest throttle allowed for the peer.
+    pub limit: u32,
+    /// How much the throttle should increase when conditions are good.
+    pub acceleration: u32,
+    /// How much the throttle should decrease when conditions are bad.
+    pub deceleration: u32,
+}
+
+/// An interface for adjusting a peer's packet throttle as round trip time samples arrive, set
+/// with [`HostSettings::congestion_controller`](`crate::HostSettings::congestion_controller`).
+///
+/// The throttle is the probability that an unreliable packet is sent rather than dropped, and
+/// also scales how much reliable data may be in flight to the peer. ENet's own algorithm is
+/// provided as [`EnetThrottle`], and a delay based alternative as [`DelayGradient`].
+pub trait CongestionController: Send {
+    /// Calculate a peer's new throttle from a round trip time sample. Values above
+    /// [`ThrottleSample::limit`] are clamped.
+    fn throttle(&mut self, peer: PeerID, sample: ThrottleSample) -> u32;
+    /// Calculate a peer's new throttle after it reported datagrams marked Congestion
+    /// Experienced, see [`HostSettings::ecn`](`crate::HostSettings::ecn`). Called at most once
+    /// per round trip, with [`ThrottleSample::round_trip_time`] set to the mean round trip time.
+    /// Values above [`ThrottleSample::limit`] are clamped.
+    ///
+    /// Defaults to slowing the throttle down by [`ThrottleSample::deceleration`], like a round
+    /// trip time spike would.
+    fn congestion_experienced(&mut self, _peer: PeerID, sample: ThrottleSample) -> u32 {
+        sample.throttle.saturating_sub(sample.deceleration)
+    }
+    /// Clear any state kept for a peer, called whenever the peer is reset.
+    fn reset(&mut self, _peer: PeerID) {}
+}
+
+/// ENet's default throttle, which speeds up when round trip times fall, and slows down when
+/// they rise more than twice the variance above the last interval's lowest round trip time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnetThrottle;
+
+impl CongestionController for EnetThrottle {
+    fn throttle(&mut self, _peer: PeerID, sample: ThrottleSample) -> u32 {
+        if sample.last_round_trip_time <= sample.last_round_trip_time_variance {
+            sample.limit
+        } else if sample.round_trip_time <= sample.last_round_trip_time {
+            (sample.throttle + sample.acceleration).min(sample.limit)
+        } else if sample.round_trip_time
+            > sample.last_round_trip_time + 2 * sample.last_round_trip_time_variance
+        {
+            sample.throttle.saturating_sub(sample.deceleration)
+        } else {
+            sample.throttle
+        }
+    }
+}
+
+/// A delay based throttle in the style of LEDBAT, which slows down as soon as queueing delay
+/// builds up on the path, rather than waiting for round trip times to spike.
+///
+/// The lowest round trip time seen for each peer is taken as the path's base delay. While the
+/// delay above it stays under the target, the throttle speeds up, and past the target it slows
+/// down in proportion to how far over the target the delay is. This keeps latency low on
+/// congested mobile links, while still reaching full speed on fast, quiet networks.
+#[derive(Debug, Clone)]
+pub struct DelayGradient {
+    target_delay: Duration,
+    base_delays: Vec<Option<Duration>>,
+}
+
+impl DelayGradient {
+    /// Create a controller keeping queueing delay under `target_delay`.
+    #[must_use]
+    pub fn new(target_delay: Duration) -> Self {
+        Self {
+            target_delay,
+            base_delays: Vec::new(),
+        }
+    }
+}
+
+impl Default for DelayGradient {
+    /// Create a controller with a target delay of 25ms.
+    fn default() -> Self {
+        Self::new(Duration::from_millis(25))
+    }
+}
+
+impl CongestionController for DelayGradient {
+    fn throttle(&mut self, peer: PeerID, sample: ThrottleSample) -> u32 {
+        if self.base_delays.len() <= peer.index {
+            self.base_delays.resize(peer.index + 1, None);
+        }
+        let base_delay = self.base_delays[peer.index]
+            .map_or(sample.round_trip_time, |base_delay| {
+                base_delay.min(sample.round_trip_time)
+            });
+        self.base_delays[peer.index] = Some(base_delay);
+        let queueing_delay = sample.round_trip_time - base_delay;
+        if queueing_delay <= self.target_delay {
+            (sample.throttle + sample.acceleration).min(sample.limit)
+        } else {
+            let target = self.target_delay.as_millis().max(1);
+            let excess = u32::try_from(queueing_delay.as_millis() / target).unwrap_or(u32::MAX);
+            sample
+                .throttle
+                .saturating_sub(sample.deceleration.saturating_mul(excess))
+        }
+    }
+
+    fn reset(&mut self, peer: PeerID) {
+        if let Some(base_delay) = self.base_delays.get_mut(peer.index) {
+            *base_delay = None;
+        }
+    }
+}
@@ -25,6 +25,7 @@ use crate::connected;
 
 /// Errors for [`connected::Host::add_connection`].
 #[derive(Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum AddConnectionError<C: Connection> {
     /// Failed to add connection because there were no available ENet connection slots.
     NoAvailablePeers,
@@ -44,6 +45,19 @@ impl<C: Connection> core::fmt::Debug for AddConnectionError<C> {
     }
 }
 
+#[cfg(feature = "std")]
+impl<C: Connection> std::error::Error for AddConnectionError<C>
+where
+    C::Error: 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AddConnectionError::NoAvailablePeers => None,
+            AddConnectionError::FailedToInitializeConnection(err) => Some(err),
+        }
+    }
+}
+
 impl<C: Connection> core::fmt::Display for AddConnectionError<C> {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
@@ -94,6 +108,10 @@ impl<A: crate::Address> crate::Address for Address<A> {
     fn is_broadcast(&self) -> bool {
         A::is_broadcast(&self.address)
     }
+
+    fn port(&self) -> u16 {
+        A::port(&self.address)
+    }
 }
 
 /// A trait for implementing connection based sockets, similar to [`Socket`](`crate::Socket`).
@@ -192,16 +210,17 @@ pub struct ConnectionID(pub usize);
 
 #[derive(Default)]
 struct PeerSettings {
-    timeout: Option<(u32, u32, u32)>,
-    ping_interval: Option<u32>,
+    timeout: Option<(u32, Duration, Duration)>,
+    ping_interval: Option<Duration>,
     throttle: Option<(u32, u32, u32)>,
     mtu: Option<u16>,
+    unreliable_paused: Option<bool>,
 }
 
 impl PeerSettings {
     fn apply<C: Connection + 'static>(&self, peer: &mut crate::Peer<SocketInterface<C>>) {
         if let Some((limit, minimum, maximum)) = self.timeout {
-            peer.set_timeout(limit, minimum, maximum);
+            peer.set_timeout_durations(limit, minimum, maximum);
         }
         if let Some(ping_interval) = self.ping_interval {
             peer.set_ping_interval(ping_interval);
@@ -212,12 +231,15 @@ impl PeerSettings {
         if let Some(mtu) = self.mtu {
             _ = peer.set_mtu(mtu);
         }
+        if let Some(paused) = self.unreliable_paused {
+            peer.pause_unreliable(paused);
+        }
     }
 }
 
 enum PeerState<C: Connection + 'static> {
     Disconnected {
-        last_peer_ptr: Option<*mut crate::Peer<SocketInterface<C>>>,
+        last_peer: Option<crate::PeerID>,
     },
     AwaitingPeer {
         connection: C,
@@ -229,71 +251,265 @@ enum PeerState<C: Connection + 'static> {
     HasPeer {
         connection: C,
         address: Address<C::Address>,
-        peer_ptr: *mut crate::Peer<SocketInterface<C>>,
+        peer: crate::PeerID,
     },
     Disconnecting {
         connection: C,
         address: Address<C::Address>,
-        last_peer_ptr: Option<*mut crate::Peer<SocketInterface<C>>>,
+        last_peer: Option<crate::PeerID>,
         last_send: Duration,
     },
 }
 
-/// A peer, associated with a [`connected::Host`], which may or may not be connected.
-///
-/// To check on the connectivity of a peer, see [`connected::Peer::state`].
-pub struct Peer<C: Connection + 'static> {
+impl<C: Connection> PeerState<C> {
+    fn peer_id(&self) -> Option<crate::PeerID> {
+        match self {
+            PeerState::Disconnected { last_peer } | PeerState::Disconnecting { last_peer, .. } => {
+                *last_peer
+            }
+            PeerState::HasPeer { peer, .. } => Some(*peer),
+            PeerState::AwaitingPeer { .. } => None,
+        }
+    }
+}
+
+struct PeerSlot<C: Connection + 'static> {
     id: ConnectionID,
     state: PeerState<C>,
 }
 
-impl<C: Connection> Peer<C> {
-    fn peer(&self) -> Option<&mut crate::Peer<SocketInterface<C>>> {
-        match self.state {
-            PeerState::HasPeer { peer_ptr, .. } => unsafe { Some(&mut *peer_ptr) },
-            _ => None,
+// getters shared by `Peer` and `PeerRef`, which both have `slot` and `peer` fields
+macro_rules! peer_getters {
+    () => {
+        fn peer_or_last_peer(&self) -> Option<&crate::Peer<SocketInterface<C>>> {
+            match self.slot.state {
+                PeerState::AwaitingPeer { .. } => None,
+                _ => self.peer.as_deref(),
+            }
         }
-    }
 
-    fn peer_or_last_peer(&self) -> Option<&mut crate::Peer<SocketInterface<C>>> {
-        match self.state {
-            PeerState::Disconnected { last_peer_ptr, .. } => unsafe {
-                last_peer_ptr.map(|peer_ptr| &mut *peer_ptr)
-            },
-            PeerState::HasPeer { peer_ptr, .. } => unsafe { Some(&mut *peer_ptr) },
-            PeerState::Disconnecting { last_peer_ptr, .. } => unsafe {
-                last_peer_ptr.map(|peer_ptr| &mut *peer_ptr)
-            },
+        /// Get the [`connected::ConnectionID`] of this peer.
+        #[must_use]
+        pub fn id(&self) -> ConnectionID {
+            self.slot.id
+        }
+
+        /// Get a reference to the underlying connection.
+        pub fn connection(&self) -> Option<&C> {
+            match &self.slot.state {
+                PeerState::Disconnected { .. } => None,
+                PeerState::AwaitingPeer { connection, .. } => Some(connection),
+                PeerState::HasPeer { connection, .. } => Some(connection),
+                PeerState::Disconnecting { connection, .. } => Some(connection),
+            }
+        }
+
+        /// See [`Peer::unreliable_paused`](`crate::Peer::unreliable_paused`).
+        #[must_use]
+        pub fn unreliable_paused(&self) -> bool {
+            self.peer_or_last_peer()
+                .is_some_and(|peer| peer.unreliable_paused())
+        }
+
+        /// See [`Peer::packet_throttle`](`crate::Peer::packet_throttle`).
+        #[must_use]
+        pub fn packet_throttle(&self) -> u32 {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.packet_throttle())
+        }
+
+        /// See [`Peer::packet_throttle_limit`](`crate::Peer::packet_throttle_limit`).
+        #[must_use]
+        pub fn packet_throttle_limit(&self) -> u32 {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.packet_throttle_limit())
+        }
+
+        /// See [`Peer::bandwidth_limited`](`crate::Peer::bandwidth_limited`).
+        #[must_use]
+        pub fn bandwidth_limited(&self) -> bool {
+            self.peer_or_last_peer()
+                .is_some_and(|peer| peer.bandwidth_limited())
+        }
+
+        /// See [`Peer::mtu`](`crate::Peer::mtu`).
+        #[must_use]
+        pub fn mtu(&self) -> u16 {
+            self.peer_or_last_peer().map_or(0, |peer| peer.mtu())
+        }
+
+        /// See [`Peer::state`](`crate::Peer::state`).
+        #[must_use]
+        pub fn state(&self) -> crate::PeerState {
+            self.peer_or_last_peer()
+                .map_or(crate::PeerState::Disconnected, |peer| peer.state())
+        }
+
+        /// See [`Peer::connected`](`crate::Peer::connected`).
+        #[must_use]
+        pub fn connected(&self) -> bool {
+            self.state() == crate::PeerState::Connected
+        }
+
+        /// See [`Peer::channel_count`](`crate::Peer::channel_count`).
+        #[must_use]
+        pub fn channel_count(&self) -> usize {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.channel_count())
+        }
+
+        /// See [`Peer::incoming_bandwidth`](`crate::Peer::incoming_bandwidth`).
+        #[must_use]
+        pub fn incoming_bandwidth(&self) -> u32 {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.incoming_bandwidth())
+        }
+
+        /// See [`Peer::outgoing_bandwidth`](`crate::Peer::outgoing_bandwidth`).
+        #[must_use]
+        pub fn outgoing_bandwidth(&self) -> u32 {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.outgoing_bandwidth())
+        }
+
+        /// See [`Peer::incoming_data_total`](`crate::Peer::incoming_data_total`).
+        #[must_use]
+        pub fn incoming_data_total(&self) -> u32 {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.incoming_data_total())
+        }
+
+        /// See [`Peer::outgoing_data_total`](`crate::Peer::outgoing_data_total`).
+        #[must_use]
+        pub fn outgoing_data_total(&self) -> u32 {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.outgoing_data_total())
+        }
+
+        /// See [`Peer::packets_sent`](`crate::Peer::packets_sent`).
+        #[must_use]
+        pub fn packets_sent(&self) -> u32 {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.packets_sent())
+        }
+
+        /// See [`Peer::packets_lost`](`crate::Peer::packets_lost`).
+        #[must_use]
+        pub fn packets_lost(&self) -> u32 {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.packets_lost())
+        }
+
+        /// See [`Peer::channel_stats`](`crate::Peer::channel_stats`).
+        #[must_use]
+        pub fn channel_stats(&self, channel_id: u8) -> Option<crate::ChannelStats> {
+            self.peer_or_last_peer()
+                .and_then(|peer| peer.channel_stats(channel_id))
+        }
+
+        /// See [`Peer::incoming_waiting_bytes`](`crate::Peer::incoming_waiting_bytes`).
+        #[must_use]
+        pub fn incoming_waiting_bytes(&self) -> usize {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.incoming_waiting_bytes())
+        }
+
+        /// See [`Peer::reliable_resends`](`crate::Peer::reliable_resends`).
+        #[must_use]
+        pub fn reliable_resends(&self) -> u32 {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.reliable_resends())
+        }
+
+        /// See [`Peer::unsequenced_duplicates`](`crate::Peer::unsequenced_duplicates`).
+        #[must_use]
+        pub fn unsequenced_duplicates(&self) -> u32 {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.unsequenced_duplicates())
+        }
+
+        /// See [`Peer::packet_loss`](`crate::Peer::packet_loss`).
+        #[must_use]
+        pub fn packet_loss(&self) -> u32 {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.packet_loss())
+        }
+
+        /// See [`Peer::packet_loss_variance`](`crate::Peer::packet_loss_variance`).
+        #[must_use]
+        pub fn packet_loss_variance(&self) -> u32 {
+            self.peer_or_last_peer()
+                .map_or(0, |peer| peer.packet_loss_variance())
+        }
+
+        /// See [`Peer::ping_interval`](`crate::Peer::ping_interval`).
+        #[must_use]
+        pub fn ping_interval(&self) -> Duration {
+            self.peer_or_last_peer()
+                .map_or(Duration::ZERO, |peer| peer.ping_interval())
+        }
+
+        /// See [`Peer::round_trip_time`](`crate::Peer::round_trip_time`).
+        #[must_use]
+        pub fn round_trip_time(&self) -> Duration {
+            self.peer_or_last_peer()
+                .map_or(Duration::ZERO, |peer| peer.round_trip_time())
+        }
+
+        /// See [`Peer::round_trip_time_variance`](`crate::Peer::round_trip_time_variance`).
+        #[must_use]
+        pub fn round_trip_time_variance(&self) -> Duration {
+            self.peer_or_last_peer()
+                .map_or(Duration::ZERO, |peer| peer.round_trip_time_variance())
+        }
+
+        /// See [`Peer::address`](`crate::Peer::address`).
+        #[must_use]
+        pub fn address(&self) -> Option<C::Address> {
+            match self.slot.state {
+                PeerState::HasPeer { .. } => self
+                    .peer
+                    .as_deref()
+                    .and_then(|peer| peer.address())
+                    .map(|address| address.address),
+                _ => None,
+            }
+        }
+    };
+}
+
+/// A peer, associated with a [`connected::Host`], which may or may not be connected.
+///
+/// Borrows the host it was acquired from, which looks up the underlying
+/// [`Peer`](`crate::Peer`) by its [`PeerID`](`crate::PeerID`) each time, so a connection never
+/// refers to a peer slot that has since been reused.
+///
+/// To check on the connectivity of a peer, see [`connected::Peer::state`].
+pub struct Peer<'a, C: Connection + 'static> {
+    slot: &'a mut PeerSlot<C>,
+    peer: Option<&'a mut crate::Peer<SocketInterface<C>>>,
+}
+
+impl<C: Connection> Peer<'_, C> {
+    peer_getters!();
+
+    fn peer(&mut self) -> Option<&mut crate::Peer<SocketInterface<C>>> {
+        match self.slot.state {
+            PeerState::HasPeer { .. } => self.peer.as_deref_mut(),
             _ => None,
         }
     }
 
     fn settings(&mut self) -> Option<&mut PeerSettings> {
-        match &mut self.state {
+        match &mut self.slot.state {
             PeerState::AwaitingPeer { settings, .. } => Some(settings),
             _ => None,
         }
     }
 
-    /// Get the [`connected::ConnectionID`] of this peer.
-    #[must_use]
-    pub fn id(&self) -> ConnectionID {
-        self.id
-    }
-
-    /// Get a reference to the underlying connection.
-    pub fn connection(&self) -> Option<&C> {
-        match &self.state {
-            PeerState::Disconnected { .. } => None,
-            PeerState::AwaitingPeer { connection, .. } => Some(connection),
-            PeerState::HasPeer { connection, .. } => Some(connection),
-            PeerState::Disconnecting { connection, .. } => Some(connection),
-        }
-    }
-
     /// Get a mutable reference to the underlying connection.
     pub fn connection_mut(&mut self) -> Option<&mut C> {
-        match &mut self.state {
+        match &mut self.slot.state {
             PeerState::Disconnected { .. } => None,
             PeerState::AwaitingPeer { connection, .. } => Some(connection),
             PeerState::HasPeer { connection, .. } => Some(connection),
@@ -330,9 +546,7 @@ impl<C: Connection> Peer<C> {
         if let Some(peer) = self.peer() {
             peer.disconnect(data);
         } else {
-            self.state = PeerState::Disconnected {
-                last_peer_ptr: None,
-            };
+            self.slot.state = PeerState::Disconnected { last_peer: None };
         }
     }
 
@@ -341,9 +555,7 @@ impl<C: Connection> Peer<C> {
         if let Some(peer) = self.peer() {
             peer.disconnect_now(data);
         } else {
-            self.state = PeerState::Disconnected {
-                last_peer_ptr: None,
-            };
+            self.slot.state = PeerState::Disconnected { last_peer: None };
         }
     }
 
@@ -352,9 +564,7 @@ impl<C: Connection> Peer<C> {
         if let Some(peer) = self.peer() {
             peer.disconnect_later(data);
         } else {
-            self.state = PeerState::Disconnected {
-                last_peer_ptr: None,
-            };
+            self.slot.state = PeerState::Disconnected { last_peer: None };
         }
     }
 
@@ -363,22 +573,30 @@ impl<C: Connection> Peer<C> {
         if let Some(peer) = self.peer() {
             peer.reset();
         }
-        self.state = PeerState::Disconnected {
-            last_peer_ptr: None,
-        };
+        self.slot.state = PeerState::Disconnected { last_peer: None };
     }
 
-    /// See [`Peer::set_timeout`](`crate::Peer::set_timeout`).
-    pub fn set_timeout(&mut self, limit: u32, minimum: u32, maximum: u32) {
+    /// See [`Peer::set_timeout_durations`](`crate::Peer::set_timeout_durations`).
+    pub fn set_timeout_durations(&mut self, limit: u32, minimum: Duration, maximum: Duration) {
         if let Some(peer) = self.peer() {
-            peer.set_timeout(limit, minimum, maximum);
+            peer.set_timeout_durations(limit, minimum, maximum);
         } else if let Some(settings) = self.settings() {
             settings.timeout = Some((limit, minimum, maximum));
         }
     }
 
+    /// See [`Peer::set_timeout`](`crate::Peer::set_timeout`).
+    #[deprecated(note = "use `Peer::set_timeout_durations`, which takes `Duration`s")]
+    pub fn set_timeout(&mut self, limit: u32, minimum: u32, maximum: u32) {
+        self.set_timeout_durations(
+            limit,
+            Duration::from_millis(u64::from(minimum)),
+            Duration::from_millis(u64::from(maximum)),
+        );
+    }
+
     /// See [`Peer::set_ping_interval`](`crate::Peer::set_ping_interval`).
-    pub fn set_ping_interval(&mut self, ping_interval: u32) {
+    pub fn set_ping_interval(&mut self, ping_interval: Duration) {
         if let Some(peer) = self.peer() {
             peer.set_ping_interval(ping_interval);
         } else if let Some(settings) = self.settings() {
@@ -395,10 +613,13 @@ impl<C: Connection> Peer<C> {
         }
     }
 
-    /// See [`Peer::mtu`](`crate::Peer::mtu`).
-    #[must_use]
-    pub fn mtu(&self) -> u16 {
-        self.peer_or_last_peer().map_or(0, |peer| peer.mtu())
+    /// See [`Peer::pause_unreliable`](`crate::Peer::pause_unreliable`).
+    pub fn pause_unreliable(&mut self, paused: bool) {
+        if let Some(peer) = self.peer() {
+            peer.pause_unreliable(paused);
+        } else if let Some(settings) = self.settings() {
+            settings.unreliable_paused = Some(paused);
+        }
     }
 
     /// See [`Peer::set_mtu`](`crate::Peer::set_mtu`).
@@ -427,112 +648,16 @@ impl<C: Connection> Peer<C> {
             Ok(())
         }
     }
+}
 
-    /// See [`Peer::state`](`crate::Peer::state`).
-    #[must_use]
-    pub fn state(&self) -> crate::PeerState {
-        self.peer_or_last_peer()
-            .map_or(crate::PeerState::Disconnected, |peer| peer.state())
-    }
-
-    /// See [`Peer::connected`](`crate::Peer::connected`).
-    #[must_use]
-    pub fn connected(&self) -> bool {
-        self.state() == crate::PeerState::Connected
-    }
-
-    /// See [`Peer::channel_count`](`crate::Peer::channel_count`).
-    #[must_use]
-    pub fn channel_count(&self) -> usize {
-        self.peer_or_last_peer()
-            .map_or(0, |peer| peer.channel_count())
-    }
-
-    /// See [`Peer::incoming_bandwidth`](`crate::Peer::incoming_bandwidth`).
-    #[must_use]
-    pub fn incoming_bandwidth(&self) -> u32 {
-        self.peer_or_last_peer()
-            .map_or(0, |peer| peer.incoming_bandwidth())
-    }
-
-    /// See [`Peer::outgoing_bandwidth`](`crate::Peer::outgoing_bandwidth`).
-    #[must_use]
-    pub fn outgoing_bandwidth(&self) -> u32 {
-        self.peer_or_last_peer()
-            .map_or(0, |peer| peer.outgoing_bandwidth())
-    }
-
-    /// See [`Peer::incoming_data_total`](`crate::Peer::incoming_data_total`).
-    #[must_use]
-    pub fn incoming_data_total(&self) -> u32 {
-        self.peer_or_last_peer()
-            .map_or(0, |peer| peer.incoming_data_total())
-    }
-
-    /// See [`Peer::outgoing_data_total`](`crate::Peer::outgoing_data_total`).
-    #[must_use]
-    pub fn outgoing_data_total(&self) -> u32 {
-        self.peer_or_last_peer()
-            .map_or(0, |peer| peer.outgoing_data_total())
-    }
-
-    /// See [`Peer::packets_sent`](`crate::Peer::packets_sent`).
-    #[must_use]
-    pub fn packets_sent(&self) -> u32 {
-        self.peer_or_last_peer()
-            .map_or(0, |peer| peer.packets_sent())
-    }
-
-    /// See [`Peer::packets_lost`](`crate::Peer::packets_lost`).
-    #[must_use]
-    pub fn packets_lost(&self) -> u32 {
-        self.peer_or_last_peer()
-            .map_or(0, |peer| peer.packets_lost())
-    }
-
-    /// See [`Peer::packet_loss`](`crate::Peer::packet_loss`).
-    #[must_use]
-    pub fn packet_loss(&self) -> u32 {
-        self.peer_or_last_peer()
-            .map_or(0, |peer| peer.packet_loss())
-    }
-
-    /// See [`Peer::packet_loss_variance`](`crate::Peer::packet_loss_variance`).
-    #[must_use]
-    pub fn packet_loss_variance(&self) -> u32 {
-        self.peer_or_last_peer()
-            .map_or(0, |peer| peer.packet_loss_variance())
-    }
-
-    /// See [`Peer::ping_interval`](`crate::Peer::ping_interval`).
-    #[must_use]
-    pub fn ping_interval(&self) -> Duration {
-        self.peer_or_last_peer()
-            .map_or(Duration::ZERO, |peer| peer.ping_interval())
-    }
-
-    /// See [`Peer::round_trip_time`](`crate::Peer::round_trip_time`).
-    #[must_use]
-    pub fn round_trip_time(&self) -> Duration {
-        self.peer_or_last_peer()
-            .map_or(Duration::ZERO, |peer| peer.round_trip_time())
-    }
-
-    /// See [`Peer::round_trip_time_variance`](`crate::Peer::round_trip_time_variance`).
-    #[must_use]
-    pub fn round_trip_time_variance(&self) -> Duration {
-        self.peer_or_last_peer()
-            .map_or(Duration::ZERO, |peer| peer.round_trip_time_variance())
-    }
+/// A shared reference to a [`connected::Peer`], acquired with [`connected::Host::peer`].
+pub struct PeerRef<'a, C: Connection + 'static> {
+    slot: &'a PeerSlot<C>,
+    peer: Option<&'a crate::Peer<SocketInterface<C>>>,
+}
 
-    /// See [`Peer::address`](`crate::Peer::address`).
-    #[must_use]
-    pub fn address(&self) -> Option<C::Address> {
-        self.peer().map_or_else(
-            || None,
-            |peer| peer.address().map(|address| address.address),
-        )
-    }
+impl<C: Connection> PeerRef<'_, C> {
+    peer_getters!();
 }
 
 /// An ENet event returned by [`connected::Host::service`].
@@ -540,25 +665,83 @@ pub enum Event<'a, C: Connection + 'static> {
     /// A new peer has connected.
     Connect {
         /// Peer that generated the event.
-        peer: &'a mut Peer<C>,
+        peer: Peer<'a, C>,
         /// Data associated with the event, sent by the peer on connect.
         data: u32,
+        /// The data the connection was started with, passed to
+        /// [`Host::connect`](`crate::Host::connect`) by whichever side connected, so both sides
+        /// can correlate the connection. The same as `data` for incoming connections.
+        connect_data: u32,
+        /// The payload sent by the peer with
+        /// [`Host::connect_with_payload`](`crate::Host::connect_with_payload`), or empty.
+        payload: Vec<u8>,
+        /// The number of channels negotiated with the peer.
+        channel_count: usize,
+        /// The peer's address.
+        address: C::Address,
     },
     /// A peer has disconnected.
     Disconnect {
         /// Peer that generated the event.
-        peer: &'a mut Peer<C>,
+        peer: Peer<'a, C>,
         /// Data associated with the event, sent by the peer on disconnect.
         data: u32,
     },
     /// A peer sent a packet to us.
     Receive {
         /// Peer that generated the event.
-        peer: &'a mut Peer<C>,
+        peer: Peer<'a, C>,
         /// Channel the peer sent the packet on.
         channel_id: u8,
         /// The actual packet data.
         packet: crate::Packet,
+        /// When the packet was received. See [`crate::Event::Receive`].
+        received_at: Duration,
+    },
+    /// A reliable packet sent with a tag was acknowledged. See [`crate::Event::Acknowledge`].
+    Acknowledge {
+        /// Peer that generated the event.
+        peer: Peer<'a, C>,
+        /// Channel the packet was sent on.
+        channel_id: u8,
+        /// The tag the packet was sent with.
+        tag: u64,
+        /// When the packet was queued. See [`crate::Event::Acknowledge`].
+        queued_at: Duration,
+    },
+    /// A reliable packet sent with a tag will never be acknowledged. See
+    /// [`crate::Event::DeliveryFailed`].
+    DeliveryFailed {
+        /// Peer that generated the event.
+        peer: Peer<'a, C>,
+        /// Channel the packet was sent on.
+        channel_id: u8,
+        /// The tag the packet was sent with.
+        tag: u64,
+    },
+    /// A peer has sent no packets for a while. See [`crate::Event::PeerIdle`].
+    PeerIdle {
+        /// Peer that generated the event.
+        peer: Peer<'a, C>,
+        /// How long the peer has been idle.
+        idle_for: Duration,
+    },
+    /// A reliable packet is being resent again. See [`crate::Event::PacketResent`].
+    PacketResent {
+        /// Peer the packet is being resent to.
+        peer: Peer<'a, C>,
+        /// Channel the packet was sent on.
+        channel_id: u8,
+        /// How many times the packet has been resent, including this resend.
+        attempts: u32,
+    },
+    /// A peer sent a datagram which failed validation. See
+    /// [`crate::Event::ProtocolViolation`].
+    ProtocolViolation {
+        /// Peer that generated the event.
+        peer: Peer<'a, C>,
+        /// What was wrong with the datagram.
+        kind: crate::ProtocolViolationKind,
     },
 }
 
@@ -568,9 +751,19 @@ impl<'a, C: Connection + 'static> Event<'a, C> {
     #[must_use]
     pub fn no_ref(self) -> EventNoRef {
         match self {
-            Self::Connect { peer, data } => EventNoRef::Connect {
+            Self::Connect {
+                peer,
+                data,
+                connect_data,
+                payload,
+                channel_count,
+                ..
+            } => EventNoRef::Connect {
                 peer: peer.id(),
                 data,
+                connect_data,
+                payload,
+                channel_count,
             },
             Self::Disconnect { peer, data } => EventNoRef::Disconnect {
                 peer: peer.id(),
@@ -580,10 +773,49 @@ impl<'a, C: Connection + 'static> Event<'a, C> {
                 peer,
                 channel_id,
                 packet,
+                received_at,
             } => EventNoRef::Receive {
                 peer: peer.id(),
                 channel_id,
                 packet,
+                received_at,
+            },
+            Self::Acknowledge {
+                peer,
+                channel_id,
+                tag,
+                queued_at,
+            } => EventNoRef::Acknowledge {
+                peer: peer.id(),
+                channel_id,
+                tag,
+                queued_at,
+            },
+            Self::DeliveryFailed {
+                peer,
+                channel_id,
+                tag,
+            } => EventNoRef::DeliveryFailed {
+                peer: peer.id(),
+                channel_id,
+                tag,
+            },
+            Self::PeerIdle { peer, idle_for } => EventNoRef::PeerIdle {
+                peer: peer.id(),
+                idle_for,
+            },
+            Self::PacketResent {
+                peer,
+                channel_id,
+                attempts,
+            } => EventNoRef::PacketResent {
+                peer: peer.id(),
+                channel_id,
+                attempts,
+            },
+            Self::ProtocolViolation { peer, kind } => EventNoRef::ProtocolViolation {
+                peer: peer.id(),
+                kind,
             },
         }
     }
@@ -600,6 +832,15 @@ pub enum EventNoRef {
         peer: ConnectionID,
         /// Data associated with the event, sent by the peer on connect.
         data: u32,
+        /// The data the connection was started with, passed to
+        /// [`Host::connect`](`crate::Host::connect`) by whichever side connected, so both sides
+        /// can correlate the connection. The same as `data` for incoming connections.
+        connect_data: u32,
+        /// The payload sent by the peer with
+        /// [`Host::connect_with_payload`](`crate::Host::connect_with_payload`), or empty.
+        payload: Vec<u8>,
+        /// The number of channels negotiated with the peer.
+        channel_count: usize,
     },
     /// A peer has disconnected.
     Disconnect {
@@ -616,6 +857,52 @@ pub enum EventNoRef {
         channel_id: u8,
         /// The actual packet data.
         packet: crate::Packet,
+        /// When the packet was received. See [`crate::Event::Receive`].
+        received_at: Duration,
+    },
+    /// A reliable packet sent with a tag was acknowledged. See [`crate::Event::Acknowledge`].
+    Acknowledge {
+        /// Peer that generated the event.
+        peer: ConnectionID,
+        /// Channel the packet was sent on.
+        channel_id: u8,
+        /// The tag the packet was sent with.
+        tag: u64,
+        /// When the packet was queued. See [`crate::Event::Acknowledge`].
+        queued_at: Duration,
+    },
+    /// A reliable packet sent with a tag will never be acknowledged. See
+    /// [`crate::Event::DeliveryFailed`].
+    DeliveryFailed {
+        /// Peer that generated the event.
+        peer: ConnectionID,
+        /// Channel the packet was sent on.
+        channel_id: u8,
+        /// The tag the packet was sent with.
+        tag: u64,
+    },
+    /// A peer has sent no packets for a while. See [`crate::Event::PeerIdle`].
+    PeerIdle {
+        /// Peer that generated the event.
+        peer: ConnectionID,
+        /// How long the peer has been idle.
+        idle_for: Duration,
+    },
+    /// A reliable packet is being resent again. See [`crate::Event::PacketResent`].
+    PacketResent {
+        /// Peer the packet is being resent to.
+        peer: ConnectionID,
+        /// Channel the packet was sent on.
+        channel_id: u8,
+        /// How many times the packet has been resent, including this resend.
+        attempts: u32,
+    },
+    /// A peer sent a datagram which failed validation.
+    ProtocolViolation {
+        /// Peer that generated the event.
+        peer: ConnectionID,
+        /// What was wrong with the datagram.
+        kind: crate::ProtocolViolationKind,
     },
 }
 
@@ -627,7 +914,7 @@ pub enum EventNoRef {
 /// Requires a [`connected::Connection`] implementation.
 pub struct Host<C: Connection + 'static> {
     host: crate::Host<SocketInterface<C>>,
-    peers: Vec<Peer<C>>,
+    peers: Vec<PeerSlot<C>>,
     next_generation: usize,
 }
 
@@ -647,20 +934,28 @@ impl<C: Connection> Host<C> {
     /// - If [`HostSettings::outgoing_bandwidth_limit`](`crate::HostSettings::outgoing_bandwidth_limit`)
     ///   is equal to `Some(0)`.
     /// - If [`HostSettings::peer_limit`](`crate::HostSettings::peer_limit`) is equal to `0` or
-    ///   greater than [`consts::PROTOCOL_MAXIMUM_PEER_ID`](`crate::consts::PROTOCOL_MAXIMUM_PEER_ID`).
+    ///   greater than [`consts::PROTOCOL_MAXIMUM_PEER_ID`](`crate::consts::PROTOCOL_MAXIMUM_PEER_ID`),
+    ///   or [`consts::PROTOCOL_EXTENDED_MAXIMUM_PEER_ID`](`crate::consts::PROTOCOL_EXTENDED_MAXIMUM_PEER_ID`)
+    ///   with [`ProtocolVersion::ExtendedPeerIds`](`crate::ProtocolVersion::ExtendedPeerIds`).
     pub fn new(settings: crate::HostSettings) -> Result<Self, crate::error::BadParameter> {
         let mut peers = Vec::new();
         peers.reserve_exact(settings.peer_limit);
         for index in 0..settings.peer_limit {
-            peers.push(Peer {
+            peers.push(PeerSlot {
                 id: ConnectionID(index),
-                state: PeerState::Disconnected {
-                    last_peer_ptr: None,
-                },
+                state: PeerState::Disconnected { last_peer: None },
             });
         }
         Ok(Self {
-            host: crate::Host::new(crate::ReadWrite::new(), settings).map_err(|err| match err {
+            host: crate::Host::new(
+                crate::ReadWrite::new(),
+                crate::HostSettings {
+                    connect_approval: false,
+                    follow_redirects: false,
+                    ..settings
+                },
+            )
+            .map_err(|err| match err {
                 crate::error::HostNewError::BadParameter(err) => err,
                 crate::error::HostNewError::FailedToInitializeSocket(..) => unreachable!(),
             })?,
@@ -685,7 +980,7 @@ impl<C: Connection> Host<C> {
     pub fn add_connection(
         &mut self,
         kind: ConnectionKind<C>,
-    ) -> Result<&mut Peer<C>, AddConnectionError<C>> {
+    ) -> Result<Peer<'_, C>, AddConnectionError<C>> {
         let mut connection_id = None;
         for (index, peer) in self.peers.iter().enumerate() {
             if let PeerState::Disconnected { .. } = &peer.state {
@@ -711,11 +1006,16 @@ impl<C: Connection> Host<C> {
                     connection_id,
                     generation,
                 );
-                if let Ok(peer_ptr) = self.host.connect(address.clone(), channel_count, data) {
+                let peer = self
+                    .host
+                    .connect(address.clone(), channel_count, data)
+                    .ok()
+                    .map(|pending| pending.peer_id());
+                if let Some(peer) = peer {
                     self.peers[connection_id.0].state = PeerState::HasPeer {
                         connection,
                         address,
-                        peer_ptr,
+                        peer,
                     };
                 } else {
                     return Err(AddConnectionError::NoAvailablePeers);
@@ -746,88 +1046,93 @@ impl<C: Connection> Host<C> {
         Ok(self.peer_mut(connection_id))
     }
 
-    fn handle_event(&mut self, event: crate::EventNoRef) -> Event<C> {
+    fn handle_event(&mut self, event: crate::EventNoRef) -> Event<'_, C> {
         let now = self.host.now();
         match event {
-            crate::EventNoRef::Connect { peer, data } => {
-                let peer = self.host.peer_mut(peer);
-                let peer_ptr = peer as *mut _;
-                let connection = peer
-                    .address()
-                    .expect("Peer should have an address.")
-                    .connection_id();
-                let connection_peer = self.peer_mut(connection);
-                connection_peer.state = match replace(
-                    &mut connection_peer.state,
-                    PeerState::Disconnected {
-                        last_peer_ptr: None,
-                    },
-                ) {
-                    PeerState::AwaitingPeer {
-                        connection,
-                        address,
-                        settings,
-                        ..
-                    } => {
-                        unsafe { settings.apply::<C>(&mut *peer_ptr) };
-                        PeerState::HasPeer {
+            crate::EventNoRef::Connect {
+                peer,
+                data,
+                connect_data,
+                payload,
+                channel_count,
+            } => {
+                let enet_peer = self
+                    .host
+                    .peer_mut(peer)
+                    .expect("Event peers should be current.");
+                let peer_address = enet_peer.address().expect("Peer should have an address.");
+                let connection = peer_address.connection_id();
+                let slot = &mut self.peers[connection.0];
+                slot.state =
+                    match replace(&mut slot.state, PeerState::Disconnected { last_peer: None }) {
+                        PeerState::AwaitingPeer {
                             connection,
                             address,
-                            peer_ptr,
+                            settings,
+                            ..
+                        } => {
+                            settings.apply::<C>(enet_peer);
+                            PeerState::HasPeer {
+                                connection,
+                                address,
+                                peer,
+                            }
                         }
-                    }
-                    PeerState::HasPeer {
-                        connection,
-                        address,
-                        peer_ptr,
-                    } => PeerState::HasPeer {
-                        connection,
-                        address,
-                        peer_ptr,
-                    },
-                    _ => unreachable!(),
-                };
+                        PeerState::HasPeer {
+                            connection,
+                            address,
+                            peer,
+                        } => PeerState::HasPeer {
+                            connection,
+                            address,
+                            peer,
+                        },
+                        _ => unreachable!(),
+                    };
                 Event::Connect {
                     peer: self.peer_mut(connection),
                     data,
+                    connect_data,
+                    payload,
+                    channel_count,
+                    address: peer_address.address,
                 }
             }
             crate::EventNoRef::Disconnect { peer, data } => {
-                let peer = self.host.peer_mut(peer);
+                let peer = self
+                    .host
+                    .peer_mut(peer)
+                    .expect("Event peers should be current.");
                 let connection_id = peer
                     .address()
                     .expect("Peer should have an address.")
                     .connection_id();
-                let peer = self.peer_mut(connection_id);
-                peer.state = match replace(
-                    &mut peer.state,
-                    PeerState::Disconnected {
-                        last_peer_ptr: None,
-                    },
-                ) {
-                    PeerState::AwaitingPeer {
-                        connection,
-                        address,
-                        ..
-                    } => PeerState::Disconnecting {
-                        connection,
-                        address,
-                        last_peer_ptr: None,
-                        last_send: now,
-                    },
-                    PeerState::HasPeer {
-                        connection,
-                        address,
-                        peer_ptr,
-                        ..
-                    } => PeerState::Disconnecting {
-                        connection,
-                        address,
-                        last_peer_ptr: Some(peer_ptr),
-                        last_send: now,
-                    },
-                    _ => unreachable!(),
-                };
+                let slot = &mut self.peers[connection_id.0];
+                slot.state =
+                    match replace(&mut slot.state, PeerState::Disconnected { last_peer: None }) {
+                        PeerState::AwaitingPeer {
+                            connection,
+                            address,
+                            ..
+                        } => PeerState::Disconnecting {
+                            connection,
+                            address,
+                            last_peer: None,
+                            last_send: now,
+                        },
+                        PeerState::HasPeer {
+                            connection,
+                            address,
+                            peer,
+                            ..
+                        } => PeerState::Disconnecting {
+                            connection,
+                            address,
+                            last_peer: Some(peer),
+                            last_send: now,
+                        },
+                        _ => unreachable!(),
+                    };
                 Event::Disconnect {
                     peer: self.peer_mut(connection_id),
                     data,
@@ -837,8 +1142,12 @@ impl<C: Connection> Host<C> {
                 peer,
                 channel_id,
                 packet,
+                received_at,
             } => {
-                let peer = self.host.peer_mut(peer);
+                let peer = self
+                    .host
+                    .peer_mut(peer)
+                    .expect("Event peers should be current.");
                 let connection = peer
                     .address()
                     .expect("Peer should have an address.")
@@ -847,15 +1156,104 @@ impl<C: Connection> Host<C> {
                     peer: self.peer_mut(connection),
                     channel_id,
                     packet,
+                    received_at,
+                }
+            }
+            crate::EventNoRef::Acknowledge {
+                peer,
+                channel_id,
+                tag,
+                queued_at,
+            } => {
+                let peer = self
+                    .host
+                    .peer_mut(peer)
+                    .expect("Event peers should be current.");
+                let connection = peer
+                    .address()
+                    .expect("Peer should have an address.")
+                    .connection_id();
+                Event::Acknowledge {
+                    peer: self.peer_mut(connection),
+                    channel_id,
+                    tag,
+                    queued_at,
+                }
+            }
+            crate::EventNoRef::DeliveryFailed {
+                peer,
+                channel_id,
+                tag,
+            } => {
+                let peer = self
+                    .host
+                    .peer_mut(peer)
+                    .expect("Event peers should be current.");
+                let connection = peer
+                    .address()
+                    .expect("Peer should have an address.")
+                    .connection_id();
+                Event::DeliveryFailed {
+                    peer: self.peer_mut(connection),
+                    channel_id,
+                    tag,
+                }
+            }
+            crate::EventNoRef::PeerIdle { peer, idle_for } => {
+                let peer = self
+                    .host
+                    .peer_mut(peer)
+                    .expect("Event peers should be current.");
+                let connection = peer
+                    .address()
+                    .expect("Peer should have an address.")
+                    .connection_id();
+                Event::PeerIdle {
+                    peer: self.peer_mut(connection),
+                    idle_for,
                 }
             }
+            crate::EventNoRef::PacketResent {
+                peer,
+                channel_id,
+                attempts,
+            } => {
+                let peer = self
+                    .host
+                    .peer_mut(peer)
+                    .expect("Event peers should be current.");
+                let connection = peer
+                    .address()
+                    .expect("Peer should have an address.")
+                    .connection_id();
+                Event::PacketResent {
+                    peer: self.peer_mut(connection),
+                    channel_id,
+                    attempts,
+                }
+            }
+            crate::EventNoRef::ConnectRequest { .. } => {
+                unreachable!("Connected hosts don't wait for connection approval.")
+            }
+            crate::EventNoRef::Reply { .. } => {
+                unreachable!("Connected hosts can't make remote calls.")
+            }
+            crate::EventNoRef::ProtocolViolation { .. } => {
+                unreachable!("Protocol violations are handled before removing references.")
+            }
         }
     }
 
     /// Checks for any queued events on the host and dispatches one if available.
-    pub fn check_events(&mut self) -> Option<Event<C>> {
+    pub fn check_events(&mut self) -> Option<Event<'_, C>> {
         #[allow(clippy::option_if_let_else)]
         match self.host.check_events() {
+            Some(crate::Event::ProtocolViolation { address, kind }) => {
+                Some(Event::ProtocolViolation {
+                    peer: self.peer_mut(address.connection_id()),
+                    kind,
+                })
+            }
             Some(event) => {
                 let event = event.no_ref();
                 Some(self.handle_event(event))
@@ -867,28 +1265,26 @@ impl<C: Connection> Host<C> {
     /// Checks for events on the host and shuttles packets between the host and its peers.
     ///
     /// Should be called fairly regularly for adequate performance.
-    pub fn service(&mut self) -> Option<Event<C>> {
+    pub fn service(&mut self) -> Option<Event<'_, C>> {
         let now = self.host.now();
         let mut disconnect_event = None;
         for peer in &mut self.peers {
             if let PeerState::AwaitingPeer { since, timeout, .. } = &mut peer.state {
                 if *since + *timeout < now {
-                    peer.state = PeerState::Disconnected {
-                        last_peer_ptr: None,
-                    };
+                    peer.state = PeerState::Disconnected { last_peer: None };
                     disconnect_event = Some(peer.id);
                     break;
                 }
             }
             if let PeerState::Disconnecting {
                 last_send,
-                last_peer_ptr,
+                last_peer,
                 ..
             } = &mut peer.state
             {
                 if *last_send + Duration::from_secs(2) < now {
                     peer.state = PeerState::Disconnected {
-                        last_peer_ptr: last_peer_ptr.take(),
+                        last_peer: last_peer.take(),
                     };
                 }
             }
@@ -911,13 +1307,14 @@ impl<C: Connection> Host<C> {
                 } => {
                     let mut buffer = [0; crate::MTU_MAX];
                     match connection.receive(&mut buffer) {
-                        Ok(Some(crate::PacketReceived::Complete(size))) => {
-                            self.host
-                                .socket_mut()
-                                .write(address.clone(), (buffer[0..size]).to_vec());
+                        Ok(Some(received)) => {
+                            if let Some(size) = received.length() {
+                                self.host
+                                    .socket_mut()
+                                    .write(address.clone(), (buffer[0..size]).to_vec());
+                            }
                         }
                         Err(_) => {
-                            peer.reset();
                             disconnect_event = Some(peer.id);
                             break;
                         }
@@ -927,15 +1324,14 @@ impl<C: Connection> Host<C> {
             }
         }
         if let Some(connection) = disconnect_event {
-            return Some(Event::Disconnect {
-                peer: self.peer_mut(connection),
-                data: 0,
-            });
+            let mut peer = self.peer_mut(connection);
+            peer.reset();
+            return Some(Event::Disconnect { peer, data: 0 });
         }
         while let Some((packet_address, packet)) = self.host.socket_mut().read() {
             if let Some(peer) = self.peers.get_mut(packet_address.id.0) {
                 let mut disconnect = false;
-                if let Peer {
+                if let PeerSlot {
                     state:
                         PeerState::AwaitingPeer {
                             connection,
@@ -956,7 +1352,7 @@ impl<C: Connection> Host<C> {
                         disconnect = true;
                     }
                 }
-                if let Peer {
+                if let PeerSlot {
                     state:
                         PeerState::Disconnecting {
                             connection,
@@ -974,11 +1370,17 @@ impl<C: Connection> Host<C> {
                     }
                 }
                 if disconnect {
-                    peer.disconnect(0);
+                    self.peer_mut(packet_address.id).disconnect(0);
                 }
             }
         }
         match self.host.service() {
+            Ok(Some(crate::Event::ProtocolViolation { address, kind })) => {
+                Some(Event::ProtocolViolation {
+                    peer: self.peer_mut(address.connection_id()),
+                    kind,
+                })
+            }
             Ok(Some(event)) => {
                 let event = event.no_ref();
                 Some(self.handle_event(event))
@@ -1016,9 +1418,8 @@ impl<C: Connection> Host<C> {
     /// Panics if the connection ID is outside the bounds of peers allocated for this host. Use
     /// [`connected::Host::get_peer`] for a non-panicking version.
     #[must_use]
-    pub fn peer(&self, connection: ConnectionID) -> &Peer<C> {
-        self.peers
-            .get(connection.0)
+    pub fn peer(&self, connection: ConnectionID) -> PeerRef<'_, C> {
+        self.get_peer(connection)
             .expect("Expected the connection id to be in bounds.")
     }
 
@@ -1028,8 +1429,12 @@ impl<C: Connection> Host<C> {
     ///
     /// Acquires the peer object, even if the peer is not in a connected state.
     #[must_use]
-    pub fn get_peer(&self, connection: ConnectionID) -> Option<&Peer<C>> {
-        self.peers.get(connection.0)
+    pub fn get_peer(&self, connection: ConnectionID) -> Option<PeerRef<'_, C>> {
+        let slot = self.peers.get(connection.0)?;
+        Some(PeerRef {
+            slot,
+            peer: slot.state.peer_id().and_then(|id| self.host.peer(id)),
+        })
     }
 
     /// Get a mutable reference to a single peer.
@@ -1043,9 +1448,8 @@ impl<C: Connection> Host<C> {
     /// Panics if the connection ID is outside the bounds of peers allocated for this host. Use
     /// [`connected::Host::get_peer_mut`] for a non-panicking version.
     #[must_use]
-    pub fn peer_mut(&mut self, connection: ConnectionID) -> &mut Peer<C> {
-        self.peers
-            .get_mut(connection.0)
+    pub fn peer_mut(&mut self, connection: ConnectionID) -> Peer<'_, C> {
+        self.get_peer_mut(connection)
             .expect("Expected the connection id to be in bounds.")
     }
 
@@ -1055,8 +1459,13 @@ impl<C: Connection> Host<C> {
     ///
     /// Acquires the peer object, even if the peer is not in a connected state.
     #[must_use]
-    pub fn get_peer_mut(&mut self, connection: ConnectionID) -> Option<&mut Peer<C>> {
-        self.peers.get_mut(connection.0)
+    pub fn get_peer_mut(&mut self, connection: ConnectionID) -> Option<Peer<'_, C>> {
+        let slot = self.peers.get_mut(connection.0)?;
+        let peer = match slot.state.peer_id() {
+            Some(id) => self.host.peer_mut(id),
+            None => None,
+        };
+        Some(Peer { slot, peer })
     }
 
     /// Iterate over all peer objects.
@@ -1065,8 +1474,12 @@ impl<C: Connection> Host<C> {
     ///
     /// Acquires the peer objects, even if the peers are not in a connected state. Use
     /// [`connected::Host::connected_peers`] for only connected peers.
-    pub fn peers(&mut self) -> impl Iterator<Item = &Peer<C>> {
-        self.peers.iter()
+    pub fn peers(&mut self) -> impl Iterator<Item = PeerRef<'_, C>> {
+        let host = &self.host;
+        self.peers.iter().map(|slot| PeerRef {
+            slot,
+            peer: slot.state.peer_id().and_then(|id| host.peer(id)),
+        })
     }
 
     /// Mutably iterate over all peer objects.
@@ -1075,18 +1488,28 @@ impl<C: Connection> Host<C> {
     ///
     /// Acquires the peer objects, even if the peers are not in a connected state. Use
     /// [`connected::Host::connected_peers_mut`] for only connected peers.
-    pub fn peers_mut(&mut self) -> impl Iterator<Item = &mut Peer<C>> {
-        self.peers.iter_mut()
+    pub fn peers_mut(&mut self) -> impl Iterator<Item = Peer<'_, C>> {
+        // each ENet peer is current for at most one connection, so each is lent out at most once
+        let mut enet_peers: Vec<_> = self.host.peers_mut().map(Some).collect();
+        self.peers.iter_mut().map(move |slot| {
+            let peer = slot.state.peer_id().and_then(|id| {
+                enet_peers
+                    .get_mut(id.index)
+                    .filter(|peer| peer.as_ref().is_some_and(|peer| peer.id() == id))?
+                    .take()
+            });
+            Peer { slot, peer }
+        })
     }
 
     /// Iterate over all connected peers.
-    pub fn connected_peers(&mut self) -> impl Iterator<Item = &Peer<C>> {
-        self.peers.iter().filter(|peer| peer.connected())
+    pub fn connected_peers(&mut self) -> impl Iterator<Item = PeerRef<'_, C>> {
+        self.peers().filter(|peer| peer.connected())
     }
 
     /// Mutably iterate over all connected peers.
-    pub fn connected_peers_mut(&mut self) -> impl Iterator<Item = &mut Peer<C>> {
-        self.peers.iter_mut().filter(|peer| peer.connected())
+    pub fn connected_peers_mut(&mut self) -> impl Iterator<Item = Peer<'_, C>> {
+        self.peers_mut().filter(|peer| peer.connected())
     }
 
     /// See [`Host::broadcast`](`crate::Host::broadcast`).
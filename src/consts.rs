@@ -1,5 +1,6 @@
 pub const PROTOCOL_MAXIMUM_FRAGMENT_COUNT: u32 = 1024 * 1024;
 pub const PROTOCOL_MAXIMUM_PEER_ID: u32 = 4095;
+pub const PROTOCOL_EXTENDED_MAXIMUM_PEER_ID: u32 = 65535;
 pub const PROTOCOL_MAXIMUM_CHANNEL_COUNT: u32 = 255;
 pub const PROTOCOL_MINIMUM_CHANNEL_COUNT: u32 = 1;
 pub const PROTOCOL_MAXIMUM_WINDOW_SIZE: u32 = 65536;
@@ -13,7 +14,15 @@ pub const PEER_RELIABLE_WINDOWS: u32 = 16;
 pub const PEER_FREE_UNSEQUENCED_WINDOWS: u32 = 32;
 pub const PEER_UNSEQUENCED_WINDOW_SIZE: u32 = 1024;
 pub const PEER_UNSEQUENCED_WINDOWS: u32 = 64;
+pub const PEER_MINIMUM_UNSEQUENCED_WINDOW_SIZE: u32 = 32;
+pub const PEER_MAXIMUM_UNSEQUENCED_WINDOW_SIZE: u32 = 16384;
 pub const PEER_PING_INTERVAL: u32 = 500;
+pub const PEER_MAXIMUM_PING_PAYLOAD: usize = 256;
+pub const PEER_MAXIMUM_CONNECT_PAYLOAD: usize = 400;
+pub const PEER_MIGRATION_CHALLENGE_INTERVAL: u32 = 500;
+pub const PEER_MIGRATION_CHALLENGE_MAXIMUM_FAILURES: u8 = 4;
+pub const PEER_PATH_PROBE_INTERVAL: u32 = 1000;
+pub const PEER_PATH_SWITCH_THRESHOLD: u32 = 10;
 pub const PEER_TIMEOUT_MAXIMUM: u32 = 30000;
 pub const PEER_TIMEOUT_MINIMUM: u32 = 5000;
 pub const PEER_TIMEOUT_LIMIT: u32 = 32;
@@ -30,8 +39,10 @@ pub const PEER_DEFAULT_ROUND_TRIP_TIME: u32 = 500;
 pub const HOST_DEFAULT_MAXIMUM_WAITING_DATA: u32 = 32 * 1024 * 1024;
 pub const HOST_DEFAULT_MAXIMUM_PACKET_SIZE: u32 = 32 * 1024 * 1024;
 pub const HOST_DEFAULT_MTU: u32 = 1392;
+pub const HOST_DEFAULT_COMMAND_POOL_LIMIT: usize = 1024;
 pub const HOST_BANDWIDTH_THROTTLE_INTERVAL: u32 = 1000;
 pub const HOST_SEND_BUFFER_SIZE: u32 = 256 * 1024;
 pub const HOST_RECEIVE_BUFFER_SIZE: u32 = 256 * 1024;
+pub const HOST_CONNECT_RACE_DELAY: u32 = 250;
 
 pub const BUFFER_MAXIMUM: u32 = PROTOCOL_MAXIMUM_PACKET_COMMANDS * 2 + 1;
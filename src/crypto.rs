@@ -0,0 +1,909 @@
+use core::time::Duration;
+
+use chacha20poly1305::{
+    aead::{AeadInPlace, KeyInit},
+    ChaCha20Poly1305, Nonce, Tag,
+};
+use curve25519_dalek::montgomery::MontgomeryPoint;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeSet;
+#[cfg(feature = "std")]
+use std::collections::BTreeSet;
+
+use crate::{Address, Box, PacketReceived, Socket, SocketOptions, Vec, MTU_MAX};
+
+/// Bytes added to every datagram sent through an [`EncryptedSocket`] session: a 1 byte message
+/// type, an 8 byte counter and a 16 byte authentication tag.
+///
+/// Hosts using an [`EncryptedSocket`] should keep their MTU at or below
+/// `MTU_MAX - ENCRYPTION_OVERHEAD`, see [`Host::set_mtu`](`crate::Host::set_mtu`).
+pub const ENCRYPTION_OVERHEAD: usize = 1 + 8 + TAG_SIZE;
+
+const TAG_SIZE: usize = 16;
+const KEY_SIZE: usize = 32;
+const MAC_SIZE: usize = 32;
+const HANDSHAKE_SIZE: usize = 1 + KEY_SIZE + MAC_SIZE;
+const HELLO: u8 = 0;
+const REPLY: u8 = 1;
+const DATA: u8 = 2;
+const REPLAY_WINDOW_SIZE: u64 = 64;
+const MAXIMUM_SESSIONS: usize = 4096;
+const MAXIMUM_HANDSHAKES: usize = 256;
+const MAXIMUM_QUEUED_DATAGRAMS: usize = 8;
+
+/// A 256-bit pre-shared key, shared by every host which should be able to communicate through an
+/// [`EncryptedSocket`].
+///
+/// The key only authenticates the X25519 key exchange with each remote host. Traffic is encrypted
+/// with session keys derived from that exchange, which are never reused between sessions.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct EncryptionKey(pub [u8; 32]);
+
+impl core::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// A [`Socket`] wrapper which encrypts and authenticates every datagram with ChaCha20-Poly1305,
+/// under session keys agreed with each remote address through an X25519 key exchange.
+///
+/// The first datagram ENet sends to an address (its connect command) starts the key exchange, and
+/// is held back until it completes one round trip later. Both hosts prove they know the same
+/// [`EncryptionKey`] during the exchange, so hosts without it can neither read traffic nor
+/// establish a session. Each session has its own replay window: datagrams which fail
+/// authentication, were already received, or arrive from an address without a session are
+/// silently dropped before ENet ever sees them.
+///
+/// Sessions whose key exchange hasn't completed are bounded separately from established sessions,
+/// and never take their place.
+///
+/// ```
+/// use std::net::UdpSocket;
+///
+/// use rusty_enet::{EncryptedSocket, EncryptionKey, Host, HostSettings};
+///
+/// let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+/// let socket = EncryptedSocket::new(socket, EncryptionKey([7; 32]));
+/// let host = Host::new(socket, HostSettings::default()).unwrap();
+/// ```
+pub struct EncryptedSocket<S: Socket> {
+    socket: S,
+    key: EncryptionKey,
+    /// Sessions whose key exchange completed, at most one per address.
+    sessions: Vec<Session<S::Address>>,
+    /// Sessions created in reply to a remote host's hello, which become established once the
+    /// remote host proves it derived the same keys by sending a datagram with them.
+    replies: Vec<Session<S::Address>>,
+    /// Key exchanges started by this socket, waiting for a reply.
+    hellos: Vec<Hello<S::Address>>,
+    /// Counts received datagrams, to find the least recently active session.
+    clock: u64,
+    buffer: Box<[u8; MTU_MAX]>,
+}
+
+struct Session<A: Address> {
+    address: A,
+    /// The remote host's ephemeral public key, identifying the key exchange.
+    remote_public: [u8; KEY_SIZE],
+    /// Our reply, if the remote host started the key exchange, repeated if its hello is.
+    reply: Option<[u8; HANDSHAKE_SIZE]>,
+    send: ChaCha20Poly1305,
+    receive: ChaCha20Poly1305,
+    counter: u64,
+    highest: u64,
+    received: u64,
+    last_active: u64,
+}
+
+impl<A: Address> Session<A> {
+    fn accept_counter(&mut self, counter: u64) -> bool {
+        if counter > self.highest {
+            let shift = counter - self.highest;
+            self.received = if shift >= REPLAY_WINDOW_SIZE {
+                1
+            } else {
+                (self.received << shift) | 1
+            };
+            self.highest = counter;
+            true
+        } else {
+            let offset = self.highest - counter;
+            if offset >= REPLAY_WINDOW_SIZE || self.received & (1 << offset) != 0 {
+                false
+            } else {
+                self.received |= 1 << offset;
+                true
+            }
+        }
+    }
+}
+
+struct Hello<A: Address> {
+    address: A,
+    secret: [u8; KEY_SIZE],
+    message: [u8; HANDSHAKE_SIZE],
+    /// Datagrams sent before the key exchange completed, with their traffic class.
+    queued: Vec<(Vec<u8>, Option<u8>)>,
+}
+
+fn mac(key: &[u8], parts: &[&[u8]]) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key)
+        .expect("Expected HMAC to accept any key length.");
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+fn handshake_message(kind: u8, public: &[u8; KEY_SIZE], mac: Hmac<Sha256>) -> [u8; HANDSHAKE_SIZE] {
+    let mut message = [0; HANDSHAKE_SIZE];
+    message[0] = kind;
+    message[1..1 + KEY_SIZE].copy_from_slice(public);
+    message[1 + KEY_SIZE..].copy_from_slice(&mac.finalize().into_bytes());
+    message
+}
+
+impl<S: Socket> EncryptedSocket<S> {
+    /// Wrap a socket, authenticating key exchanges with `key`.
+    ///
+    /// Ephemeral keys are generated with the operating system's random number generator. If it
+    /// fails, key exchanges are not started, and ENet will retry them later.
+    #[must_use]
+    pub fn new(socket: S, key: EncryptionKey) -> Self {
+        Self {
+            socket,
+            key,
+            sessions: Vec::new(),
+            replies: Vec::new(),
+            hellos: Vec::new(),
+            clock: 0,
+            buffer: Box::new([0; MTU_MAX]),
+        }
+    }
+
+    /// Get a reference to the wrapped socket.
+    #[must_use]
+    pub fn socket(&self) -> &S {
+        &self.socket
+    }
+
+    /// Get a mutable reference to the wrapped socket.
+    pub fn socket_mut(&mut self) -> &mut S {
+        &mut self.socket
+    }
+
+    /// Check if the key exchange with `address` completed.
+    #[must_use]
+    pub fn has_session(&self, address: &S::Address) -> bool {
+        self.sessions
+            .iter()
+            .any(|session| session.address.same(address))
+    }
+
+    /// Derive the session keys of a key exchange, for the initiator and responder respectively.
+    fn session_keys(
+        &self,
+        shared: &MontgomeryPoint,
+        initiator: &[u8; KEY_SIZE],
+        responder: &[u8; KEY_SIZE],
+    ) -> Option<(ChaCha20Poly1305, ChaCha20Poly1305)> {
+        // a low order point from the remote host would leave nothing secret
+        if shared.as_bytes() == &[0; KEY_SIZE] {
+            return None;
+        }
+        let secret = mac(
+            &self.key.0,
+            &[
+                b"rusty_enet session",
+                shared.as_bytes(),
+                initiator,
+                responder,
+            ],
+        )
+        .finalize()
+        .into_bytes();
+        let initiator_key = mac(&secret, &[&[1]]).finalize().into_bytes();
+        let responder_key = mac(&secret, &[&[2]]).finalize().into_bytes();
+        Some((
+            ChaCha20Poly1305::new(&initiator_key),
+            ChaCha20Poly1305::new(&responder_key),
+        ))
+    }
+
+    fn establish(&mut self, mut session: Session<S::Address>) -> usize {
+        session.last_active = self.clock;
+        if let Some(index) = self
+            .sessions
+            .iter()
+            .position(|existing| existing.address.same(&session.address))
+        {
+            self.sessions[index] = session;
+            return index;
+        }
+        if self.sessions.len() >= MAXIMUM_SESSIONS {
+            // only hosts knowing the key can complete a key exchange, so the least recently
+            // active session is the one most likely to be abandoned
+            if let Some(index) = self
+                .sessions
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, session)| session.last_active)
+                .map(|(index, _)| index)
+            {
+                self.sessions.swap_remove(index);
+            }
+        }
+        self.sessions.push(session);
+        self.sessions.len() - 1
+    }
+
+    fn send_session(
+        socket: &mut S,
+        buffer: &mut [u8; MTU_MAX],
+        session: &mut Session<S::Address>,
+        data: &[u8],
+        traffic_class: Option<u8>,
+    ) -> Result<usize, S::Error> {
+        let length = data.len() + ENCRYPTION_OVERHEAD;
+        let Some(datagram) = buffer.get_mut(..length) else {
+            return Ok(0);
+        };
+        let (header, rest) = datagram.split_at_mut(9);
+        let (payload, tag) = rest.split_at_mut(data.len());
+        header[0] = DATA;
+        header[1..].copy_from_slice(&session.counter.to_be_bytes());
+        payload.copy_from_slice(data);
+        let mut nonce = Nonce::default();
+        nonce[4..].copy_from_slice(&session.counter.to_be_bytes());
+        session.counter += 1;
+        let Ok(computed_tag) = session
+            .send
+            .encrypt_in_place_detached(&nonce, header, payload)
+        else {
+            return Ok(0);
+        };
+        tag.copy_from_slice(&computed_tag);
+        let sent_length = match traffic_class {
+            Some(traffic_class) => socket.send_with_traffic_class(
+                session.address.clone(),
+                &buffer[..length],
+                traffic_class,
+            )?,
+            None => socket.send(session.address.clone(), &buffer[..length])?,
+        };
+        Ok(sent_length.saturating_sub(ENCRYPTION_OVERHEAD))
+    }
+
+    fn send_queued(
+        &mut self,
+        session: usize,
+        established: bool,
+        queued: Vec<(Vec<u8>, Option<u8>)>,
+    ) -> Result<(), S::Error> {
+        let session = if established {
+            &mut self.sessions[session]
+        } else {
+            &mut self.replies[session]
+        };
+        for (data, traffic_class) in queued {
+            Self::send_session(
+                &mut self.socket,
+                &mut self.buffer,
+                session,
+                &data,
+                traffic_class,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn send_datagram(
+        &mut self,
+        address: S::Address,
+        data: &[u8],
+        traffic_class: Option<u8>,
+    ) -> Result<usize, S::Error> {
+        if let Some(session) = self
+            .sessions
+            .iter_mut()
+            .chain(self.replies.iter_mut())
+            .find(|session| session.address.same(&address))
+        {
+            return Self::send_session(
+                &mut self.socket,
+                &mut self.buffer,
+                session,
+                data,
+                traffic_class,
+            );
+        }
+        let index = if let Some(index) = self
+            .hellos
+            .iter()
+            .position(|hello| hello.address.same(&address))
+        {
+            // ENet is retrying, so our hello may have been lost
+            let hello = &self.hellos[index];
+            self.socket.send(address, &hello.message)?;
+            index
+        } else {
+            let mut secret = [0; KEY_SIZE];
+            if getrandom::getrandom(&mut secret).is_err() {
+                return Ok(0);
+            }
+            let public = MontgomeryPoint::mul_base_clamped(secret).to_bytes();
+            let message = handshake_message(
+                HELLO,
+                &public,
+                mac(&self.key.0, &[b"rusty_enet hello", &public]),
+            );
+            if self.hellos.len() >= MAXIMUM_HANDSHAKES {
+                self.hellos.remove(0);
+            }
+            self.socket.send(address.clone(), &message)?;
+            self.hellos.push(Hello {
+                address,
+                secret,
+                message,
+                queued: Vec::new(),
+            });
+            self.hellos.len() - 1
+        };
+        let queued = &mut self.hellos[index].queued;
+        if queued.len() >= MAXIMUM_QUEUED_DATAGRAMS {
+            queued.remove(0);
+        }
+        queued.push((data.to_vec(), traffic_class));
+        Ok(data.len())
+    }
+
+    fn receive_hello(&mut self, address: &S::Address, length: usize) -> Result<(), S::Error> {
+        if length != HANDSHAKE_SIZE {
+            return Ok(());
+        }
+        let mut remote_public = [0; KEY_SIZE];
+        remote_public.copy_from_slice(&self.buffer[1..1 + KEY_SIZE]);
+        if mac(&self.key.0, &[b"rusty_enet hello", &remote_public])
+            .verify_slice(&self.buffer[1 + KEY_SIZE..length])
+            .is_err()
+        {
+            return Ok(());
+        }
+        if let Some(session) = self
+            .sessions
+            .iter()
+            .chain(self.replies.iter())
+            .find(|session| session.remote_public == remote_public)
+        {
+            // the remote host did not receive our reply, so repeat it, but only to the address
+            // which sent the original hello
+            if let (true, Some(reply)) = (session.address.same(address), session.reply) {
+                self.socket.send(address.clone(), &reply)?;
+            }
+            return Ok(());
+        }
+        let mut queued = Vec::new();
+        if let Some(index) = self
+            .hellos
+            .iter()
+            .position(|hello| hello.address.same(address))
+        {
+            // both hosts started a key exchange, the larger public key wins
+            if self.hellos[index].message[1..1 + KEY_SIZE] > remote_public[..] {
+                return Ok(());
+            }
+            queued = self.hellos.remove(index).queued;
+        }
+        let mut secret = [0; KEY_SIZE];
+        if getrandom::getrandom(&mut secret).is_err() {
+            return Ok(());
+        }
+        let public = MontgomeryPoint::mul_base_clamped(secret).to_bytes();
+        let shared = MontgomeryPoint(remote_public).mul_clamped(secret);
+        let Some((initiator, responder)) = self.session_keys(&shared, &remote_public, &public)
+        else {
+            return Ok(());
+        };
+        let reply = handshake_message(
+            REPLY,
+            &public,
+            mac(&self.key.0, &[b"rusty_enet reply", &remote_public, &public]),
+        );
+        self.replies
+            .retain(|session| !session.address.same(address));
+        if self.replies.len() >= MAXIMUM_HANDSHAKES {
+            self.replies.remove(0);
+        }
+        self.replies.push(Session {
+            address: address.clone(),
+            remote_public,
+            reply: Some(reply),
+            send: responder,
+            receive: initiator,
+            counter: 0,
+            highest: 0,
+            received: 0,
+            last_active: self.clock,
+        });
+        self.socket.send(address.clone(), &reply)?;
+        self.send_queued(self.replies.len() - 1, false, queued)
+    }
+
+    fn receive_reply(&mut self, address: &S::Address, length: usize) -> Result<(), S::Error> {
+        if length != HANDSHAKE_SIZE {
+            return Ok(());
+        }
+        let Some(index) = self
+            .hellos
+            .iter()
+            .position(|hello| hello.address.same(address))
+        else {
+            return Ok(());
+        };
+        let hello = &self.hellos[index];
+        let mut remote_public = [0; KEY_SIZE];
+        remote_public.copy_from_slice(&self.buffer[1..1 + KEY_SIZE]);
+        if mac(
+            &self.key.0,
+            &[
+                b"rusty_enet reply",
+                &hello.message[1..1 + KEY_SIZE],
+                &remote_public,
+            ],
+        )
+        .verify_slice(&self.buffer[1 + KEY_SIZE..length])
+        .is_err()
+        {
+            return Ok(());
+        }
+        let mut public = [0; KEY_SIZE];
+        public.copy_from_slice(&hello.message[1..1 + KEY_SIZE]);
+        let shared = MontgomeryPoint(remote_public).mul_clamped(hello.secret);
+        let Some((initiator, responder)) = self.session_keys(&shared, &public, &remote_public)
+        else {
+            return Ok(());
+        };
+        let hello = self.hellos.remove(index);
+        let session = self.establish(Session {
+            address: hello.address,
+            remote_public,
+            reply: None,
+            send: initiator,
+            receive: responder,
+            counter: 0,
+            highest: 0,
+            received: 0,
+            last_active: 0,
+        });
+        self.send_queued(session, true, hello.queued)
+    }
+
+    fn receive_data(
+        &mut self,
+        address: &S::Address,
+        length: usize,
+        buffer: &mut [u8; MTU_MAX],
+    ) -> Option<usize> {
+        if length < ENCRYPTION_OVERHEAD {
+            return None;
+        }
+        let payload_length = length - ENCRYPTION_OVERHEAD;
+        let header = &self.buffer[..9];
+        let tag = Tag::from_slice(&self.buffer[9 + payload_length..length]);
+        let mut counter = [0; 8];
+        counter.copy_from_slice(&header[1..]);
+        let counter = u64::from_be_bytes(counter);
+        let mut nonce = Nonce::default();
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        // the tag is verified before anything is decrypted, so the ciphertext stays intact for
+        // the next session to try
+        let payload = &mut buffer[..payload_length];
+        payload.copy_from_slice(&self.buffer[9..9 + payload_length]);
+        let decrypt = |session: &Session<S::Address>, payload: &mut [u8]| {
+            session.address.same(address)
+                && session
+                    .receive
+                    .decrypt_in_place_detached(&nonce, header, payload, tag)
+                    .is_ok()
+        };
+        let session = if let Some(index) = self
+            .sessions
+            .iter()
+            .position(|session| decrypt(session, payload))
+        {
+            index
+        } else {
+            // the remote host proved it derived the keys of our reply, so the key exchange is
+            // complete
+            let index = self
+                .replies
+                .iter()
+                .position(|session| decrypt(session, payload))?;
+            let session = self.replies.remove(index);
+            self.establish(session)
+        };
+        let session = &mut self.sessions[session];
+        if !session.accept_counter(counter) {
+            return None;
+        }
+        session.last_active = self.clock;
+        Some(payload_length)
+    }
+}
+
+impl<S: Socket> Socket for EncryptedSocket<S> {
+    type Address = S::Address;
+    type Error = S::Error;
+
+    fn init(&mut self, socket_options: SocketOptions) -> Result<(), Self::Error> {
+        self.socket.init(socket_options)
+    }
+
+    fn send(&mut self, address: Self::Address, buffer: &[u8]) -> Result<usize, Self::Error> {
+        self.send_datagram(address, buffer, None)
+    }
+
+    fn send_with_traffic_class(
+        &mut self,
+        address: Self::Address,
+        buffer: &[u8],
+        traffic_class: u8,
+    ) -> Result<usize, Self::Error> {
+        self.send_datagram(address, buffer, Some(traffic_class))
+    }
+
+    fn receive_unreachable(&mut self) -> Result<Option<Self::Address>, Self::Error> {
+        self.socket.receive_unreachable()
+    }
+
+    fn receive(
+        &mut self,
+        buffer: &mut [u8; MTU_MAX],
+    ) -> Result<Option<(Self::Address, PacketReceived)>, Self::Error> {
+        loop {
+            let (address, received, length) = match self.socket.receive(&mut self.buffer)? {
+                Some((address, received)) => match received.length() {
+                    Some(length) => (address, received, length),
+                    None => return Ok(Some((address, PacketReceived::Partial))),
+                },
+                None => return Ok(None),
+            };
+            self.clock += 1;
+            match self.buffer[..length].first() {
+                Some(&HELLO) => self.receive_hello(&address, length)?,
+                Some(&REPLY) => self.receive_reply(&address, length)?,
+                Some(&DATA) => {
+                    if let Some(payload_length) = self.receive_data(&address, length, buffer) {
+                        return Ok(Some((address, received.with_length(payload_length))));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<S: Socket + core::fmt::Debug> core::fmt::Debug for EncryptedSocket<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EncryptedSocket")
+            .field("socket", &self.socket)
+            .field("sessions", &self.sessions.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Bytes appended to every connect datagram by a host using [`ConnectAuthentication`]: an 8 byte
+/// timestamp and a 32 byte HMAC-SHA256.
+pub const CONNECT_AUTHENTICATION_OVERHEAD: usize = 8 + 32;
+
+/// The number of authenticated connect datagrams a host remembers to reject replays of, see
+/// [`ConnectAuthentication`].
+const CONNECT_AUTHENTICATION_REPLAY_CAPACITY: usize = 4096;
+
+/// Pre-shared key authentication of incoming connections, set with
+/// [`HostSettings::connect_authentication`](`crate::HostSettings::connect_authentication`).
+///
+/// Datagrams sent by a peer which is still connecting carry a timestamp and an HMAC-SHA256 over
+/// the datagram, keyed with a pre-shared key. Hosts silently drop connection requests that fail
+/// verification, or whose timestamp is further than [`ConnectAuthentication::max_clock_skew`]
+/// from their own clock, so they never consume a peer slot. Hosts also remember the datagrams
+/// they accepted until their timestamps are too old to pass that check, and drop repeats of them,
+/// so a captured connection request can't be replayed, from the same address or any other.
+///
+/// The source address isn't covered by the HMAC, as peers behind a NAT don't know the address
+/// the host sees them at.
+///
+/// This is much lighter than [`EncryptedSocket`], but only protects the connection handshake.
+/// Both hosts must use the same key, and their
+/// [`HostSettings::time`](`crate::HostSettings::time`) functions must roughly agree.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ConnectAuthentication {
+    /// The pre-shared key.
+    pub key: [u8; 32],
+    /// The maximum difference between the timestamp of a connection request and the time of the
+    /// receiving host.
+    pub max_clock_skew: Duration,
+}
+
+impl ConnectAuthentication {
+    /// The clock skew used by [`ConnectAuthentication::new`].
+    pub const DEFAULT_MAX_CLOCK_SKEW: Duration = Duration::from_secs(30);
+
+    /// Authenticate connections with `key`, allowing [`Self::DEFAULT_MAX_CLOCK_SKEW`].
+    #[must_use]
+    pub const fn new(key: [u8; 32]) -> Self {
+        Self {
+            key,
+            max_clock_skew: Self::DEFAULT_MAX_CLOCK_SKEW,
+        }
+    }
+
+    pub(crate) fn sign(
+        &self,
+        datagram: &[u8],
+        now: Duration,
+    ) -> [u8; CONNECT_AUTHENTICATION_OVERHEAD] {
+        let timestamp = (now.as_millis() as u64).to_be_bytes();
+        let mut trailer = [0; CONNECT_AUTHENTICATION_OVERHEAD];
+        trailer[..8].copy_from_slice(&timestamp);
+        trailer[8..].copy_from_slice(&self.mac(datagram, timestamp).finalize().into_bytes());
+        trailer
+    }
+
+    pub(crate) fn verify(
+        &self,
+        datagram: &[u8],
+        now: Duration,
+        replays: &mut ConnectReplays,
+    ) -> bool {
+        let Some(data_length) = datagram.len().checked_sub(CONNECT_AUTHENTICATION_OVERHEAD) else {
+            return false;
+        };
+        let (data, trailer) = datagram.split_at(data_length);
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&trailer[..8]);
+        let sent_time = Duration::from_millis(u64::from_be_bytes(timestamp));
+        let skew = sent_time.abs_diff(now);
+        if skew > self.max_clock_skew
+            || self
+                .mac(data, timestamp)
+                .verify_slice(&trailer[8..])
+                .is_err()
+        {
+            return false;
+        }
+        let mut tag = [0; 32];
+        tag.copy_from_slice(&trailer[8..]);
+        let oldest = now.saturating_sub(self.max_clock_skew).as_millis() as u64;
+        replays.insert(u64::from_be_bytes(timestamp), tag, oldest)
+    }
+
+    fn mac(&self, data: &[u8], timestamp: [u8; 8]) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.key)
+            .expect("Expected HMAC to accept any key length.");
+        mac.update(&timestamp);
+        mac.update(data);
+        mac
+    }
+}
+
+/// The timestamps and HMACs of the connect datagrams accepted by [`ConnectAuthentication::verify`].
+#[derive(Default)]
+pub(crate) struct ConnectReplays {
+    seen: BTreeSet<(u64, [u8; 32])>,
+    /// The newest timestamp forgotten to stay within capacity, at or before which datagrams are
+    /// rejected, as they may have been seen.
+    floor: Option<u64>,
+}
+
+impl ConnectReplays {
+    /// Remember a datagram, returning `false` if it may have been seen before. Datagrams with
+    /// timestamps before `oldest` fail the clock skew check, and are forgotten.
+    fn insert(&mut self, timestamp: u64, tag: [u8; 32], oldest: u64) -> bool {
+        self.seen = self.seen.split_off(&(oldest, [0; 32]));
+        if self.floor.is_some_and(|floor| timestamp <= floor) || !self.seen.insert((timestamp, tag))
+        {
+            return false;
+        }
+        if self.seen.len() > CONNECT_AUTHENTICATION_REPLAY_CAPACITY {
+            self.floor = self.seen.pop_first().map(|(timestamp, _)| timestamp);
+        }
+        true
+    }
+}
+
+impl core::fmt::Debug for ConnectAuthentication {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ConnectAuthentication")
+            .field("max_clock_skew", &self.max_clock_skew)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{convert::Infallible, net::SocketAddr};
+
+    use core::time::Duration;
+
+    use super::{
+        ConnectAuthentication, ConnectReplays, EncryptedSocket, EncryptionKey,
+        CONNECT_AUTHENTICATION_REPLAY_CAPACITY,
+    };
+    use crate::{
+        Event, Host, HostSettings, Packet, PacketReceived, ReadWrite, Socket, Vec, MTU_MAX,
+    };
+
+    type TestSocket = EncryptedSocket<ReadWrite<SocketAddr, Infallible>>;
+
+    fn address(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    fn receive(socket: &mut TestSocket) -> Option<(SocketAddr, Vec<u8>)> {
+        let mut buffer = [0; MTU_MAX];
+        match socket.receive(&mut buffer).unwrap() {
+            Some((address, PacketReceived::Complete(length))) => {
+                Some((address, buffer[..length].to_vec()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Move every datagram sent by `from` to `to`, as if sent from `address`.
+    fn transfer(from: &mut TestSocket, to: &mut TestSocket, address: SocketAddr) {
+        while let Some((_, datagram)) = from.socket_mut().read() {
+            to.socket_mut().write(address, datagram);
+        }
+    }
+
+    #[test]
+    fn encrypted_socket() {
+        let mut client = TestSocket::new(ReadWrite::new(), EncryptionKey([1; 32]));
+        let mut server = TestSocket::new(ReadWrite::new(), EncryptionKey([1; 32]));
+
+        // the first datagram waits for the key exchange
+        assert_eq!(client.send(address(2), b"secret").unwrap(), 6);
+        transfer(&mut client, &mut server, address(1));
+        assert_eq!(receive(&mut server), None);
+        transfer(&mut server, &mut client, address(2));
+        assert_eq!(receive(&mut client), None);
+        assert!(client.has_session(&address(2)));
+        let (_, datagram) = client.socket_mut().read().unwrap();
+        assert!(!datagram.windows(6).any(|window| window == b"secret"));
+        server.socket_mut().write(address(1), datagram.clone());
+        assert_eq!(receive(&mut server), Some((address(1), b"secret".to_vec())));
+        assert!(server.has_session(&address(1)));
+
+        server.send(address(1), b"reply").unwrap();
+        transfer(&mut server, &mut client, address(2));
+        assert_eq!(receive(&mut client), Some((address(2), b"reply".to_vec())));
+
+        // replays are dropped, from the original address or any other
+        server.socket_mut().write(address(1), datagram.clone());
+        server.socket_mut().write(address(3), datagram.clone());
+        assert_eq!(receive(&mut server), None);
+
+        // tampered datagrams are dropped
+        let mut tampered = datagram;
+        tampered[12] ^= 1;
+        server.socket_mut().write(address(1), tampered);
+        assert_eq!(receive(&mut server), None);
+
+        // out of order datagrams inside the replay window are accepted once
+        client.send(address(2), b"first").unwrap();
+        client.send(address(2), b"second").unwrap();
+        let (_, first) = client.socket_mut().read().unwrap();
+        let (_, second) = client.socket_mut().read().unwrap();
+        server.socket_mut().write(address(1), second);
+        server.socket_mut().write(address(1), first.clone());
+        server.socket_mut().write(address(1), first);
+        assert_eq!(receive(&mut server), Some((address(1), b"second".to_vec())));
+        assert_eq!(receive(&mut server), Some((address(1), b"first".to_vec())));
+        assert_eq!(receive(&mut server), None);
+    }
+
+    #[test]
+    fn encrypted_socket_wrong_key() {
+        let mut server = TestSocket::new(ReadWrite::new(), EncryptionKey([1; 32]));
+        let mut intruder = TestSocket::new(ReadWrite::new(), EncryptionKey([4; 32]));
+
+        // hellos authenticated with another key are ignored
+        intruder.send(address(2), b"secret").unwrap();
+        transfer(&mut intruder, &mut server, address(4));
+        assert_eq!(receive(&mut server), None);
+        assert_eq!(server.socket_mut().read(), None);
+        assert!(!server.has_session(&address(4)));
+    }
+
+    #[test]
+    fn encrypted_socket_replayed_hello() {
+        let mut client = TestSocket::new(ReadWrite::new(), EncryptionKey([1; 32]));
+        let mut server = TestSocket::new(ReadWrite::new(), EncryptionKey([1; 32]));
+
+        client.send(address(2), b"secret").unwrap();
+        let (_, hello) = client.socket_mut().read().unwrap();
+        server.socket_mut().write(address(1), hello.clone());
+        assert_eq!(receive(&mut server), None);
+        transfer(&mut server, &mut client, address(2));
+        assert_eq!(receive(&mut client), None);
+        transfer(&mut client, &mut server, address(1));
+        assert_eq!(receive(&mut server), Some((address(1), b"secret".to_vec())));
+
+        // a replayed hello from another address is ignored, and doesn't disturb the session
+        server.socket_mut().write(address(3), hello.clone());
+        assert_eq!(receive(&mut server), None);
+        assert_eq!(server.socket_mut().read(), None);
+        // from the same address, it only repeats the reply
+        server.socket_mut().write(address(1), hello);
+        assert_eq!(receive(&mut server), None);
+        transfer(&mut server, &mut client, address(2));
+        assert_eq!(receive(&mut client), None);
+        client.send(address(2), b"still here").unwrap();
+        transfer(&mut client, &mut server, address(1));
+        assert_eq!(
+            receive(&mut server),
+            Some((address(1), b"still here".to_vec()))
+        );
+    }
+
+    #[test]
+    fn encrypted_hosts() {
+        let mut hosts = [1, 2].map(|_| {
+            let socket = TestSocket::new(ReadWrite::new(), EncryptionKey([1; 32]));
+            Host::new(socket, HostSettings::default()).unwrap()
+        });
+        let peer = hosts[0].connect(address(2), 1, 0).unwrap().peer_id();
+        let mut received = Vec::new();
+        for step in 0..10 {
+            if step == 5 {
+                hosts[0]
+                    .peer_mut(peer)
+                    .unwrap()
+                    .send(0, &Packet::reliable(&b"secret"[..]))
+                    .unwrap();
+            }
+            for host in &mut hosts {
+                while let Some(event) = host.service().unwrap() {
+                    if let Event::Receive { packet, .. } = event {
+                        received.push(packet.data().to_vec());
+                    }
+                }
+            }
+            let [client, server] = &mut hosts;
+            transfer(client.socket_mut(), server.socket_mut(), address(1));
+            transfer(server.socket_mut(), client.socket_mut(), address(2));
+        }
+        assert_eq!(received, [b"secret".to_vec()]);
+    }
+
+    #[test]
+    fn connect_authentication_replay() {
+        let authentication = ConnectAuthentication::new([1; 32]);
+        let mut replays = ConnectReplays::default();
+        let sign = |data: &[u8], now| {
+            let mut datagram = data.to_vec();
+            datagram.extend_from_slice(&authentication.sign(data, now));
+            datagram
+        };
+        let now = Duration::from_secs(100);
+        let datagram = sign(b"connect", now);
+        assert!(authentication.verify(&datagram, now, &mut replays));
+        // repeats are rejected for as long as their timestamp is accepted
+        assert!(!authentication.verify(&datagram, now, &mut replays));
+        let later = now + authentication.max_clock_skew;
+        assert!(!authentication.verify(&datagram, later, &mut replays));
+        assert!(authentication.verify(&sign(b"connect", later), later, &mut replays));
+
+        // datagrams forgotten to stay within capacity are rejected too
+        for millis in 0..CONNECT_AUTHENTICATION_REPLAY_CAPACITY as u64 {
+            let now = later + Duration::from_millis(millis + 1);
+            assert!(authentication.verify(&sign(b"connect", now), now, &mut replays));
+        }
+        assert!(!authentication.verify(&sign(b"other", later), later, &mut replays));
+    }
+}
@@ -1,9 +1,14 @@
 //! Error types.
+//!
+//! Error enums are `#[non_exhaustive]`, so new failure modes can be reported without breaking
+//! matches on them. With the `std` feature, every error implements [`std::error::Error`], with
+//! [`source`](`std::error::Error::source`) returning the underlying error if there is one.
 
 use crate::Socket;
 
 /// Error for [`Host::new`](`crate::Host::new`).
 #[derive(Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum HostNewError<S: Socket> {
     /// Failed to create a new ENet host due to a bad parameter.
     BadParameter(BadParameter),
@@ -23,6 +28,19 @@ impl<S: Socket> core::fmt::Debug for HostNewError<S> {
     }
 }
 
+#[cfg(feature = "std")]
+impl<S: Socket> std::error::Error for HostNewError<S>
+where
+    S::Error: 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HostNewError::BadParameter(err) => Some(err),
+            HostNewError::FailedToInitializeSocket(err) => Some(err),
+        }
+    }
+}
+
 impl<S: Socket> core::fmt::Display for HostNewError<S> {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
@@ -36,8 +54,73 @@ impl<S: Socket> core::fmt::Display for HostNewError<S> {
     }
 }
 
+/// The stage of [`Host::service`](`crate::Host::service`) which failed, see [`ServiceError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ServiceStage {
+    /// Receiving datagrams, with [`Socket::receive`], [`Socket::receive_batch`],
+    /// [`Socket::receive_batch_with_metadata`] or [`Socket::receive_unreachable`].
+    Receive,
+    /// Sending datagrams, with [`Socket::send`] or [`Socket::send_batch`].
+    Send,
+}
+
+/// Error for [`Host::service`](`crate::Host::service`), when the socket fails.
+///
+/// Only socket errors fail servicing. Malformed datagrams are dropped, or reported with
+/// [`Event::ProtocolViolation`](`crate::Event::ProtocolViolation`), and dispatching events can't
+/// fail.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ServiceError<S: Socket> {
+    /// What the host was doing when the socket failed.
+    pub stage: ServiceStage,
+    /// The address a failed datagram was being sent to. Only known when sending, and when the
+    /// datagram wasn't part of a larger batch, see
+    /// [`HostSettings::socket_batch_size`](`crate::HostSettings::socket_batch_size`).
+    pub address: Option<S::Address>,
+    /// The error reported by the socket.
+    pub error: S::Error,
+}
+
+impl<S: Socket> core::fmt::Debug for ServiceError<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("ServiceError")
+            .field("stage", &self.stage)
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Socket> std::error::Error for ServiceError<S>
+where
+    S::Error: 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<S: Socket> core::fmt::Display for ServiceError<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self.stage {
+            ServiceStage::Receive => {
+                f.write_str("Failed to receive datagrams while servicing an ENet host.")
+            }
+            ServiceStage::Send => {
+                f.write_str("Failed to send datagrams while servicing an ENet host.")
+            }
+        }
+    }
+}
+
 /// Error for [`Peer::send`](`crate::Peer::send`).
+///
+/// Packets are never rejected because the peer's reliable window is full. They are queued, and
+/// sent once the peer acknowledges enough of the packets in flight.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum PeerSendError {
     /// Cannot send to peer because it is not connected.
     NotConnected,
@@ -47,8 +130,19 @@ pub enum PeerSendError {
     PacketTooLarge,
     /// Cannot send to peer because the fragment count was exceeded.
     FragmentsExceeded,
-    /// Cannot send to peer because the packet failed to queue.
+    /// Cannot send to peer because no outgoing command could be allocated for the packet.
     FailedToQueue,
+    /// Cannot send to peer because it is over its memory budget. See
+    /// [`MemoryBudgetPolicy::Throttle`](`crate::MemoryBudgetPolicy::Throttle`).
+    MemoryBudgetExceeded,
+    /// Cannot send to peer because the packet's kind doesn't match the channel's
+    /// [`ChannelMode`](`crate::ChannelMode`).
+    ChannelModeMismatch,
+    /// Cannot send to peer because the peer ID is out of range or stale. Only returned by
+    /// [`Host::send_bytes`](`crate::Host::send_bytes`),
+    /// [`Host::send_many`](`crate::Host::send_many`) and `HostHandle::send`, see
+    /// [`Host::peer_mut`](`crate::Host::peer_mut`).
+    InvalidPeer,
 }
 
 #[cfg(feature = "std")]
@@ -72,6 +166,106 @@ impl core::fmt::Display for PeerSendError {
             PeerSendError::FailedToQueue => {
                 f.write_str("Cannot send to an ENet peer because the packet failed to queue.")
             }
+            PeerSendError::MemoryBudgetExceeded => {
+                f.write_str("Cannot send to an ENet peer because it is over its memory budget.")
+            }
+            PeerSendError::ChannelModeMismatch => f.write_str(
+                "Cannot send to an ENet peer because the packet doesn't match the channel's mode.",
+            ),
+            PeerSendError::InvalidPeer => {
+                f.write_str("Cannot send to an ENet peer because its peer ID is invalid.")
+            }
+        }
+    }
+}
+
+/// Error for [`Peer::send_batch`](`crate::Peer::send_batch`) and
+/// [`Host::send_many`](`crate::Host::send_many`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchSendError {
+    /// Number of packets queued before the failure. Packets after the failed one are not queued.
+    pub sent: usize,
+    /// The error for the packet which failed to queue.
+    pub error: PeerSendError,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BatchSendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl core::fmt::Display for BatchSendError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "Failed to queue a batch of packets after queueing {} of them.",
+            self.sent
+        )
+    }
+}
+
+/// Error for [`Peer::send_serialized`](`crate::Peer::send_serialized`).
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SerializedSendError<E> {
+    /// The message failed to serialize.
+    Serialize(E),
+    /// The serialized packet failed to queue.
+    Send(PeerSendError),
+}
+
+#[cfg(all(feature = "serde", feature = "std"))]
+impl<E: core::fmt::Debug> std::error::Error for SerializedSendError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SerializedSendError::Serialize(_) => None,
+            SerializedSendError::Send(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<E> core::fmt::Display for SerializedSendError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            SerializedSendError::Serialize(_) => {
+                f.write_str("Failed to serialize a message for an ENet peer.")
+            }
+            SerializedSendError::Send(err) => core::fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+/// The reason a call made with [`Peer::call`](`crate::Peer::call`) failed, reported in
+/// [`Event::Reply`](`crate::Event::Reply`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RpcError {
+    /// No reply was received within [`HostSettings::rpc_timeout`](`crate::HostSettings::rpc_timeout`).
+    Timeout,
+    /// The peer disconnected before replying.
+    Disconnected,
+    /// The peer has no handler registered for the method.
+    UnknownMethod,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RpcError {}
+
+impl core::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            RpcError::Timeout => f.write_str("An ENet remote call timed out."),
+            RpcError::Disconnected => {
+                f.write_str("An ENet remote call failed because the peer disconnected.")
+            }
+            RpcError::UnknownMethod => {
+                f.write_str("An ENet remote call failed because the peer has no such method.")
+            }
         }
     }
 }
@@ -109,3 +303,59 @@ impl core::fmt::Display for NoAvailablePeers {
         f.write_str("Failed to connect because there were no available ENet peer slots.")
     }
 }
+
+/// Failed to connect with [`Host::connect_with_payload`](`crate::Host::connect_with_payload`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectError {
+    /// There were no available ENet peer slots.
+    NoAvailablePeers,
+    /// The payload was longer than
+    /// [`PEER_MAXIMUM_CONNECT_PAYLOAD`](`crate::consts::PEER_MAXIMUM_CONNECT_PAYLOAD`) bytes.
+    PayloadTooLarge,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConnectError {}
+
+impl core::fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ConnectError::NoAvailablePeers => core::fmt::Display::fmt(&NoAvailablePeers, f),
+            ConnectError::PayloadTooLarge => {
+                f.write_str("Failed to connect because the connect payload was too large.")
+            }
+        }
+    }
+}
+
+/// The thread running a [`HostHandle`](`crate::HostHandle`)'s host has stopped, because
+/// [`Host::service`](`crate::Host::service`) failed.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HostThreadStopped;
+
+#[cfg(feature = "std")]
+impl std::error::Error for HostThreadStopped {}
+
+#[cfg(feature = "std")]
+impl core::fmt::Display for HostThreadStopped {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("The host thread has stopped.")
+    }
+}
+
+/// Error for [`set_allocator`](`crate::set_allocator`), when an allocator was already installed or
+/// ENet has already allocated memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocatorAlreadySet;
+
+#[cfg(feature = "std")]
+impl std::error::Error for AllocatorAlreadySet {}
+
+impl core::fmt::Display for AllocatorAlreadySet {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("An allocator was already set, or memory was already allocated.")
+    }
+}
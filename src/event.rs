@@ -1,4 +1,63 @@
-use crate::{Packet, Peer, PeerID, Socket};
+use core::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+use crate::{error::RpcError, CallID, Packet, Peer, PeerID, Socket, Vec};
+#[cfg(feature = "serde")]
+use crate::{MessageCodec, TypedChannel};
+
+/// The reason a datagram was rejected, reported by hosts using
+/// [`HostSettings::report_malformed`](`crate::HostSettings::report_malformed`) or
+/// [`HostSettings::strict_validation`](`crate::HostSettings::strict_validation`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolViolationKind {
+    /// The datagram was too short to contain a protocol header.
+    TruncatedHeader,
+    /// The datagram failed checksum verification.
+    BadChecksum,
+    /// The datagram was marked as compressed, but could not be decompressed.
+    DecompressionFailed,
+    /// The datagram referenced a peer ID this host does not have.
+    UnknownPeer,
+    /// A command, or the data following it, extended past the end of the datagram.
+    TruncatedCommand,
+    /// A command had an unknown command number.
+    UnknownCommand,
+    /// A command was not valid in its context, such as a non-connect command from an address with
+    /// no peer, or a connect command from a connected peer.
+    UnexpectedCommand,
+    /// The datagram contained more commands than ENet ever sends in one datagram. Only reported
+    /// with strict validation.
+    TooManyCommands,
+    /// A command referenced a channel the peer does not have. Only reported with strict validation.
+    InvalidChannel,
+    /// A connect command requested an invalid number of channels. Only reported with strict
+    /// validation.
+    InvalidChannelCount,
+    /// A connect command requested a window size outside of the protocol limits. Only reported
+    /// with strict validation.
+    InvalidWindowSize,
+    /// A connect command requested an MTU outside of the protocol limits. Only reported with
+    /// strict validation.
+    InvalidMtu,
+    /// A fragment had an invalid fragment count, number, offset, or total length. Only reported
+    /// with strict validation.
+    InvalidFragment,
+    /// A packet was larger than the maximum packet size accepted from the peer on its channel. See
+    /// [`HostSettings::maximum_packet_size`](`crate::HostSettings::maximum_packet_size`),
+    /// [`HostSettings::channel_maximum_packet_sizes`](`crate::HostSettings::channel_maximum_packet_sizes`),
+    /// and [`Peer::set_maximum_packet_size`](`crate::Peer::set_maximum_packet_size`).
+    PacketTooLarge,
+    /// A packet was received on a channel with a different
+    /// [`ChannelMode`](`crate::ChannelMode`), see
+    /// [`HostSettings::channel_modes`](`crate::HostSettings::channel_modes`).
+    ChannelModeMismatch,
+    /// A peer's incomplete reliable fragmented packets went past
+    /// [`HostSettings::maximum_peer_fragment_data`](`crate::HostSettings::maximum_peer_fragment_data`).
+    /// The peer is disconnected.
+    FragmentDataExceeded,
+}
 
 /// An ENet event returned by [`Host::service`](`crate::Host::service`).
 #[derive(Debug)]
@@ -7,8 +66,38 @@ pub enum Event<'a, S: Socket> {
     Connect {
         /// Peer that generated the event.
         peer: &'a mut Peer<S>,
-        /// Data associated with the event, sent by the peer on connect.
+        /// Data associated with the event, sent by the peer on connect, or passed to
+        /// [`Peer::accept`] by the host connected to.
+        data: u32,
+        /// The data the connection was started with, passed to
+        /// [`Host::connect`](`crate::Host::connect`) by whichever side connected, so both sides
+        /// can correlate the connection. The same as `data` for incoming connections.
+        connect_data: u32,
+        /// The payload sent by the peer with
+        /// [`Host::connect_with_payload`](`crate::Host::connect_with_payload`), or empty.
+        payload: Vec<u8>,
+        /// The number of channels negotiated with the peer.
+        channel_count: usize,
+        /// The peer's address.
+        address: S::Address,
+    },
+    /// A peer is requesting to connect, and must be answered with [`Peer::accept`] or
+    /// [`Peer::reject`]. Only generated by hosts using
+    /// [`HostSettings::connect_approval`](`crate::HostSettings::connect_approval`).
+    ConnectRequest {
+        /// Peer requesting to connect.
+        peer: &'a mut Peer<S>,
+        /// Data sent by the peer with its request.
         data: u32,
+        /// The payload sent by the peer with
+        /// [`Host::connect_with_payload`](`crate::Host::connect_with_payload`), or empty. Also
+        /// returned by [`Event::Connect`] once accepted.
+        payload: Vec<u8>,
+        /// The number of channels requested by the peer, up to
+        /// [`HostSettings::channel_limit`](`crate::HostSettings::channel_limit`).
+        channel_count: usize,
+        /// The peer's address.
+        address: S::Address,
     },
     /// A peer has disconnected.
     Disconnect {
@@ -24,18 +113,145 @@ pub enum Event<'a, S: Socket> {
         /// Channel the peer sent the packet on.
         channel_id: u8,
         /// The actual packet data.
+        ///
+        /// Packets are reference counted, so keeping or cloning one never copies its data. Use
+        /// [`Packet::into_vec`] to take ownership of the buffer, or [`Packet::from_shared`] to
+        /// forward it to other peers.
         packet: Packet,
+        /// When the packet was received from the socket, according to
+        /// [`HostSettings::time`](`crate::HostSettings::time`), regardless of when the event was
+        /// serviced. For fragmented packets, this is when the last fragment arrived.
+        received_at: Duration,
+    },
+    /// A reliable packet sent with a [`PacketBuilder::tag`](`crate::PacketBuilder::tag`) was
+    /// acknowledged by the peer. For fragmented packets, this is generated once every fragment has
+    /// been acknowledged.
+    Acknowledge {
+        /// Peer that generated the event.
+        peer: &'a mut Peer<S>,
+        /// Channel the packet was sent on.
+        channel_id: u8,
+        /// The tag the packet was sent with.
+        tag: u64,
+        /// When the packet was queued to the peer, according to
+        /// [`HostSettings::time`](`crate::HostSettings::time`).
+        queued_at: Duration,
+    },
+    /// A reliable packet sent with a [`PacketBuilder::tag`](`crate::PacketBuilder::tag`) will never
+    /// be acknowledged, because the peer disconnected, timed out or was reset first. When a peer
+    /// disconnects, these events follow its [`Event::Disconnect`].
+    DeliveryFailed {
+        /// Peer that generated the event.
+        peer: &'a mut Peer<S>,
+        /// Channel the packet was sent on.
+        channel_id: u8,
+        /// The tag the packet was sent with.
+        tag: u64,
+    },
+    /// A call made with [`Peer::call`](`crate::Peer::call`) was answered by the peer, or failed.
+    Reply {
+        /// Peer the call was made to.
+        peer: &'a mut Peer<S>,
+        /// The call being answered, as returned by [`Peer::call`](`crate::Peer::call`).
+        call: CallID,
+        /// The reply's payload, or why the call failed.
+        result: Result<Packet, RpcError>,
+    },
+    /// A connected peer has sent no packets for
+    /// [`HostSettings::idle_timeout`](`crate::HostSettings::idle_timeout`). Pings and other
+    /// protocol traffic don't count. Generated once, until the peer sends another packet.
+    PeerIdle {
+        /// Peer that generated the event.
+        peer: &'a mut Peer<S>,
+        /// How long the peer has been idle.
+        idle_for: Duration,
+    },
+    /// A reliable packet timed out waiting for an acknowledgement and is being resent, more than
+    /// [`HostSettings::resend_event_threshold`](`crate::HostSettings::resend_event_threshold`)
+    /// times. Generated for each further resend, as an early warning that the connection is
+    /// degrading before the peer times out.
+    PacketResent {
+        /// Peer the packet is being resent to.
+        peer: &'a mut Peer<S>,
+        /// Channel the packet was sent on.
+        channel_id: u8,
+        /// How many times the packet has been resent, including this resend.
+        attempts: u32,
+    },
+    /// A datagram failed validation and was discarded. Only generated by hosts using
+    /// [`HostSettings::report_malformed`](`crate::HostSettings::report_malformed`) or
+    /// [`HostSettings::strict_validation`](`crate::HostSettings::strict_validation`).
+    ProtocolViolation {
+        /// Address the datagram was received from.
+        address: S::Address,
+        /// What was wrong with the datagram.
+        kind: ProtocolViolationKind,
     },
 }
 
 impl<'a, S: Socket> Event<'a, S> {
+    /// Deserialize the packet of an [`Event::Receive`] with the [`MessageCodec`] `C`, or return
+    /// [`None`] for any other event. See [`Packet::deserialize`].
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    #[must_use]
+    pub fn deserialize<'b, C: MessageCodec, T: Deserialize<'b>>(
+        &'b self,
+    ) -> Option<Result<T, C::Error>> {
+        match self {
+            Self::Receive { packet, .. } => Some(packet.deserialize::<C, T>()),
+            _ => None,
+        }
+    }
+
+    /// Deserialize the packet of an [`Event::Receive`] on the [`TypedChannel`] `T` with the
+    /// [`MessageCodec`] `C`, or return [`None`] for any other event or channel.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    #[must_use]
+    pub fn receive_typed<'b, T: TypedChannel, C: MessageCodec>(
+        &'b self,
+    ) -> Option<Result<T::Message, C::Error>>
+    where
+        T::Message: Deserialize<'b>,
+    {
+        match self {
+            Self::Receive {
+                channel_id, packet, ..
+            } if *channel_id == T::ID => Some(packet.deserialize::<C, T::Message>()),
+            _ => None,
+        }
+    }
+
     /// Remove the peer reference from this event, converting into an [`EventNoRef`].
     #[must_use]
     pub fn no_ref(self) -> EventNoRef {
         match self {
-            Self::Connect { peer, data } => EventNoRef::Connect {
+            Self::Connect {
+                peer,
+                data,
+                connect_data,
+                payload,
+                channel_count,
+                ..
+            } => EventNoRef::Connect {
+                peer: peer.id(),
+                data,
+                connect_data,
+                payload,
+                channel_count,
+            },
+            Self::ConnectRequest {
+                peer,
+                data,
+                payload,
+                channel_count,
+                ..
+            } => EventNoRef::ConnectRequest {
                 peer: peer.id(),
                 data,
+                payload,
+                channel_count,
             },
             Self::Disconnect { peer, data } => EventNoRef::Disconnect {
                 peer: peer.id(),
@@ -45,11 +261,52 @@ impl<'a, S: Socket> Event<'a, S> {
                 peer,
                 channel_id,
                 packet,
+                received_at,
             } => EventNoRef::Receive {
                 peer: peer.id(),
                 channel_id,
                 packet,
+                received_at,
+            },
+            Self::Acknowledge {
+                peer,
+                channel_id,
+                tag,
+                queued_at,
+            } => EventNoRef::Acknowledge {
+                peer: peer.id(),
+                channel_id,
+                tag,
+                queued_at,
             },
+            Self::DeliveryFailed {
+                peer,
+                channel_id,
+                tag,
+            } => EventNoRef::DeliveryFailed {
+                peer: peer.id(),
+                channel_id,
+                tag,
+            },
+            Self::Reply { peer, call, result } => EventNoRef::Reply {
+                peer: peer.id(),
+                call,
+                result,
+            },
+            Self::PeerIdle { peer, idle_for } => EventNoRef::PeerIdle {
+                peer: peer.id(),
+                idle_for,
+            },
+            Self::PacketResent {
+                peer,
+                channel_id,
+                attempts,
+            } => EventNoRef::PacketResent {
+                peer: peer.id(),
+                channel_id,
+                attempts,
+            },
+            Self::ProtocolViolation { kind, .. } => EventNoRef::ProtocolViolation { kind },
         }
     }
 }
@@ -63,8 +320,30 @@ pub enum EventNoRef {
     Connect {
         /// Peer that generated the event.
         peer: PeerID,
-        /// Data associated with the event, sent by the peer on connect.
+        /// Data associated with the event, sent by the peer on connect, or passed to
+        /// [`Peer::accept`] by the host connected to.
+        data: u32,
+        /// The data the connection was started with, passed to
+        /// [`Host::connect`](`crate::Host::connect`) by whichever side connected, so both sides
+        /// can correlate the connection. The same as `data` for incoming connections.
+        connect_data: u32,
+        /// The payload sent by the peer with
+        /// [`Host::connect_with_payload`](`crate::Host::connect_with_payload`), or empty.
+        payload: Vec<u8>,
+        /// The number of channels negotiated with the peer.
+        channel_count: usize,
+    },
+    /// A peer is requesting to connect. See [`Event::ConnectRequest`].
+    ConnectRequest {
+        /// Peer requesting to connect.
+        peer: PeerID,
+        /// Data sent by the peer with its request.
         data: u32,
+        /// The payload sent by the peer with
+        /// [`Host::connect_with_payload`](`crate::Host::connect_with_payload`), or empty.
+        payload: Vec<u8>,
+        /// The number of channels requested by the peer.
+        channel_count: usize,
     },
     /// A peer has disconnected.
     Disconnect {
@@ -80,6 +359,109 @@ pub enum EventNoRef {
         /// Channel the peer sent the packet on.
         channel_id: u8,
         /// The actual packet data.
+        ///
+        /// Packets are reference counted, so keeping or cloning one never copies its data. Use
+        /// [`Packet::into_vec`] to take ownership of the buffer, or [`Packet::from_shared`] to
+        /// forward it to other peers.
         packet: Packet,
+        /// When the packet was received from the socket, according to
+        /// [`HostSettings::time`](`crate::HostSettings::time`), regardless of when the event was
+        /// serviced. For fragmented packets, this is when the last fragment arrived.
+        received_at: Duration,
+    },
+    /// A reliable packet sent with a [`PacketBuilder::tag`](`crate::PacketBuilder::tag`) was
+    /// acknowledged by the peer. For fragmented packets, this is generated once every fragment has
+    /// been acknowledged.
+    Acknowledge {
+        /// Peer that generated the event.
+        peer: PeerID,
+        /// Channel the packet was sent on.
+        channel_id: u8,
+        /// The tag the packet was sent with.
+        tag: u64,
+        /// When the packet was queued to the peer, according to
+        /// [`HostSettings::time`](`crate::HostSettings::time`).
+        queued_at: Duration,
+    },
+    /// A reliable packet sent with a [`PacketBuilder::tag`](`crate::PacketBuilder::tag`) will never
+    /// be acknowledged, because the peer disconnected, timed out or was reset first. When a peer
+    /// disconnects, these events follow its [`EventNoRef::Disconnect`].
+    DeliveryFailed {
+        /// Peer that generated the event.
+        peer: PeerID,
+        /// Channel the packet was sent on.
+        channel_id: u8,
+        /// The tag the packet was sent with.
+        tag: u64,
+    },
+    /// A call made with [`Peer::call`](`crate::Peer::call`) was answered by the peer, or failed.
+    Reply {
+        /// Peer the call was made to.
+        peer: PeerID,
+        /// The call being answered, as returned by [`Peer::call`](`crate::Peer::call`).
+        call: CallID,
+        /// The reply's payload, or why the call failed.
+        result: Result<Packet, RpcError>,
+    },
+    /// A connected peer has sent no packets for
+    /// [`HostSettings::idle_timeout`](`crate::HostSettings::idle_timeout`). Pings and other
+    /// protocol traffic don't count. Generated once, until the peer sends another packet.
+    PeerIdle {
+        /// Peer that generated the event.
+        peer: PeerID,
+        /// How long the peer has been idle.
+        idle_for: Duration,
+    },
+    /// A reliable packet timed out waiting for an acknowledgement and is being resent, more than
+    /// [`HostSettings::resend_event_threshold`](`crate::HostSettings::resend_event_threshold`)
+    /// times.
+    PacketResent {
+        /// Peer the packet is being resent to.
+        peer: PeerID,
+        /// Channel the packet was sent on.
+        channel_id: u8,
+        /// How many times the packet has been resent, including this resend.
+        attempts: u32,
+    },
+    /// A datagram failed validation and was discarded. The address it was received from is only
+    /// available on [`Event::ProtocolViolation`].
+    ProtocolViolation {
+        /// What was wrong with the datagram.
+        kind: ProtocolViolationKind,
     },
 }
+
+impl EventNoRef {
+    /// Deserialize the packet of an [`EventNoRef::Receive`] with the [`MessageCodec`] `C`, or
+    /// return [`None`] for any other event. See [`Packet::deserialize`].
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    #[must_use]
+    pub fn deserialize<'b, C: MessageCodec, T: Deserialize<'b>>(
+        &'b self,
+    ) -> Option<Result<T, C::Error>> {
+        match self {
+            Self::Receive { packet, .. } => Some(packet.deserialize::<C, T>()),
+            _ => None,
+        }
+    }
+
+    /// Deserialize the packet of an [`EventNoRef::Receive`] on the [`TypedChannel`] `T` with the
+    /// [`MessageCodec`] `C`, or return [`None`] for any other event or channel.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    #[must_use]
+    pub fn receive_typed<'b, T: TypedChannel, C: MessageCodec>(
+        &'b self,
+    ) -> Option<Result<T::Message, C::Error>>
+    where
+        T::Message: Deserialize<'b>,
+    {
+        match self {
+            Self::Receive {
+                channel_id, packet, ..
+            } if *channel_id == T::ID => Some(packet.deserialize::<C, T::Message>()),
+            _ => None,
+        }
+    }
+}
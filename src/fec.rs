@@ -0,0 +1,288 @@
+use core::time::Duration;
+
+use crate::{Packet, PeerID, Vec, VecDeque};
+
+const FEC_PLAIN: u8 = 0;
+const FEC_DATA: u8 = 1;
+const FEC_PARITY: u8 = 2;
+const FEC_HEADER_SIZE: usize = 4;
+
+/// How many recent groups are kept for each peer and channel while waiting for their parity
+/// packets. Packets of older groups can no longer be recovered.
+const FEC_GROUP_HISTORY: usize = 4;
+
+/// The parity of the packets sent so far in the current group, on one channel to one peer.
+struct FecEncoder {
+    peer: usize,
+    channel_id: u8,
+    group: u16,
+    count: u8,
+    parity: Vec<u8>,
+}
+
+/// A group of packets received on one channel from one peer, waiting for its parity packet or its
+/// last missing packet.
+struct FecGroup {
+    peer: usize,
+    channel_id: u8,
+    group: u16,
+    received: Vec<u8>,
+    parity: Vec<u8>,
+    parity_count: Option<u8>,
+    complete: bool,
+}
+
+pub(crate) struct FecDelivery {
+    pub(crate) peer: PeerID,
+    pub(crate) channel_id: u8,
+    pub(crate) packet: Packet,
+    pub(crate) received_at: Duration,
+}
+
+pub(crate) struct FecState {
+    /// The number of packets covered by each parity packet, indexed by channel ID, see
+    /// [`HostSettings::channel_parity_groups`](`crate::HostSettings::channel_parity_groups`).
+    pub(crate) channel_groups: Vec<u8>,
+    encoders: Vec<FecEncoder>,
+    groups: Vec<FecGroup>,
+    deliveries: VecDeque<FecDelivery>,
+}
+
+/// XOR a length prefixed block of `data` into `parity`, growing it as needed.
+fn xor_block(parity: &mut Vec<u8>, data: &[u8]) {
+    let length = (data.len() as u16).to_be_bytes();
+    if parity.len() < data.len() + 2 {
+        parity.resize(data.len() + 2, 0);
+    }
+    for (byte, value) in parity.iter_mut().zip(length.iter().chain(data)) {
+        *byte ^= value;
+    }
+}
+
+fn header(kind: u8, group: u16, index: u8, capacity: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(FEC_HEADER_SIZE + capacity);
+    data.push(kind);
+    data.extend_from_slice(&group.to_be_bytes());
+    data.push(index);
+    data
+}
+
+impl FecState {
+    pub(crate) fn new() -> Self {
+        Self {
+            channel_groups: Vec::new(),
+            encoders: Vec::new(),
+            groups: Vec::new(),
+            deliveries: VecDeque::new(),
+        }
+    }
+
+    /// The number of packets covered by each parity packet on a channel, or [`None`] if it
+    /// doesn't use forward error correction.
+    pub(crate) fn group_size(&self, channel_id: u8) -> Option<u8> {
+        self.channel_groups
+            .get(usize::from(channel_id))
+            .copied()
+            .filter(|&size| size > 0)
+    }
+
+    fn encoder(&mut self, peer: usize, channel_id: u8) -> &mut FecEncoder {
+        let index = if let Some(index) = self
+            .encoders
+            .iter()
+            .position(|encoder| encoder.peer == peer && encoder.channel_id == channel_id)
+        {
+            index
+        } else {
+            self.encoders.push(FecEncoder {
+                peer,
+                channel_id,
+                group: 0,
+                count: 0,
+                parity: Vec::new(),
+            });
+            self.encoders.len() - 1
+        };
+        &mut self.encoders[index]
+    }
+
+    /// Prefix a packet's data with its header. Reliable packets are only marked as such, and
+    /// unreliable packets take the next place in the current group, which should be claimed with
+    /// [`FecState::commit`] once the packet is queued.
+    pub(crate) fn wrap(
+        &mut self,
+        peer: usize,
+        channel_id: u8,
+        reliable: bool,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut wrapped = if reliable {
+            let mut wrapped = Vec::with_capacity(1 + data.len());
+            wrapped.push(FEC_PLAIN);
+            wrapped
+        } else {
+            let encoder = self.encoder(peer, channel_id);
+            header(FEC_DATA, encoder.group, encoder.count, data.len())
+        };
+        wrapped.extend_from_slice(data);
+        wrapped
+    }
+
+    /// Add a queued unreliable packet to the current group, returning the group's parity packet
+    /// once it is full.
+    pub(crate) fn commit(
+        &mut self,
+        peer: usize,
+        channel_id: u8,
+        group_size: u8,
+        data: &[u8],
+    ) -> Option<Vec<u8>> {
+        let encoder = self.encoder(peer, channel_id);
+        xor_block(&mut encoder.parity, data);
+        encoder.count += 1;
+        if encoder.count < group_size {
+            return None;
+        }
+        let mut parity = header(
+            FEC_PARITY,
+            encoder.group,
+            encoder.count,
+            encoder.parity.len(),
+        );
+        parity.append(&mut encoder.parity);
+        encoder.group = encoder.group.wrapping_add(1);
+        encoder.count = 0;
+        Some(parity)
+    }
+
+    /// Handle a packet received on a channel using forward error correction, queueing its data,
+    /// and any packet its group's parity recovers, to be delivered with
+    /// [`FecState::next_delivery`]. Malformed packets are dropped.
+    pub(crate) fn receive(
+        &mut self,
+        peer: PeerID,
+        channel_id: u8,
+        packet: &Packet,
+        received_at: Duration,
+    ) {
+        let data = packet.data();
+        let kind = packet.kind();
+        if data.first() == Some(&FEC_PLAIN) {
+            self.deliver(peer, channel_id, Packet::new(&data[1..], kind), received_at);
+            return;
+        }
+        if data.len() < FEC_HEADER_SIZE {
+            return;
+        }
+        let group_id = u16::from_be_bytes([data[1], data[2]]);
+        let index = data[3];
+        let payload = &data[FEC_HEADER_SIZE..];
+        let group = self.group(peer.index, channel_id, group_id);
+        if group.complete {
+            return;
+        }
+        match data[0] {
+            FEC_DATA => {
+                if group.received.contains(&index) {
+                    return;
+                }
+                group.received.push(index);
+                xor_block(&mut group.parity, payload);
+            }
+            FEC_PARITY => {
+                if group.parity_count.is_some() || index == 0 {
+                    return;
+                }
+                group.parity_count = Some(index);
+                if group.parity.len() < payload.len() {
+                    group.parity.resize(payload.len(), 0);
+                }
+                for (byte, value) in group.parity.iter_mut().zip(payload) {
+                    *byte ^= value;
+                }
+            }
+            _ => return,
+        }
+        let recovered = match group.parity_count {
+            Some(count) if group.received.len() + 1 == usize::from(count) => {
+                group.complete = true;
+                let length = match group.parity.get(..2) {
+                    Some(&[high, low]) => usize::from(u16::from_be_bytes([high, low])),
+                    _ => usize::MAX,
+                };
+                // every other block cancels out, leaving the missing one
+                group
+                    .parity
+                    .get(2..)
+                    .and_then(|block| block.get(..length))
+                    .map(|block| Packet::new(block, kind))
+            }
+            Some(count) if group.received.len() >= usize::from(count) => {
+                group.complete = true;
+                None
+            }
+            _ => None,
+        };
+        if data[0] == FEC_DATA {
+            self.deliver(peer, channel_id, Packet::new(payload, kind), received_at);
+        }
+        if let Some(packet) = recovered {
+            self.deliver(peer, channel_id, packet, received_at);
+        }
+    }
+
+    fn group(&mut self, peer: usize, channel_id: u8, group: u16) -> &mut FecGroup {
+        if let Some(index) = self.groups.iter().position(|existing| {
+            existing.peer == peer && existing.channel_id == channel_id && existing.group == group
+        }) {
+            return &mut self.groups[index];
+        }
+        let mut history = self
+            .groups
+            .iter()
+            .filter(|existing| existing.peer == peer && existing.channel_id == channel_id);
+        if history.nth(FEC_GROUP_HISTORY - 1).is_some() {
+            let oldest = self
+                .groups
+                .iter()
+                .position(|existing| existing.peer == peer && existing.channel_id == channel_id)
+                .unwrap();
+            self.groups.remove(oldest);
+        }
+        self.groups.push(FecGroup {
+            peer,
+            channel_id,
+            group,
+            received: Vec::new(),
+            parity: Vec::new(),
+            parity_count: None,
+            complete: false,
+        });
+        self.groups.last_mut().unwrap()
+    }
+
+    fn deliver(&mut self, peer: PeerID, channel_id: u8, packet: Packet, received_at: Duration) {
+        self.deliveries.push_back(FecDelivery {
+            peer,
+            channel_id,
+            packet,
+            received_at,
+        });
+    }
+
+    pub(crate) fn next_delivery(&mut self) -> Option<FecDelivery> {
+        self.deliveries.pop_front()
+    }
+
+    pub(crate) fn pending_deliveries(&self) -> usize {
+        self.deliveries.len()
+    }
+
+    /// Forget the groups and undelivered packets of a peer slot which was reset.
+    pub(crate) fn reset_peer(&mut self, peer: usize) {
+        self.encoders.retain(|encoder| encoder.peer != peer);
+        self.groups.retain(|group| group.peer != peer);
+        self.deliveries
+            .retain(|delivery| delivery.peer.index != peer);
+    }
+}
@@ -0,0 +1,220 @@
+//! Entry points for fuzzing ENet's protocol handling directly, enabled with the `fuzzing`
+//! feature.
+//!
+//! [`parse_datagram`] drives only the header and command parser, while [`handle_datagram`] and
+//! [`handle_session`] feed datagrams to a [`FuzzState`], a host with a manual clock and a fixed
+//! random seed, so runs are reproducible. Datagrams sent by the host are discarded.
+//!
+//! The `fuzz` directory of the repository contains `cargo fuzz` targets for these functions,
+//! with seed corpora generated by [`record_session`] (see the `fuzz_corpus` example).
+//!
+//! ```
+//! use rusty_enet::{
+//!     fuzzing::{handle_session, record_session, FuzzState},
+//!     EventNoRef,
+//! };
+//!
+//! // replaying a recorded session reaches the same states as the real one
+//! let mut state = FuzzState::default();
+//! let events = handle_session(&mut state, &record_session());
+//! assert!(matches!(events[0], EventNoRef::Connect { .. }));
+//! ```
+
+use core::{convert::Infallible, time::Duration};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    enet_protocol_parse_command, enet_protocol_parse_header, Box, ENetReader, EventNoRef, Host,
+    HostSettings, Packet, PacketKind, ReadWrite, Vec,
+};
+
+/// The socket used by a [`FuzzState`]'s host.
+pub type FuzzSocket = ReadWrite<SocketAddr, Infallible>;
+
+/// The milliseconds the clock advances before each datagram of a session, both when recording
+/// and replaying, so replayed sessions see the same timing.
+const SESSION_STEP: u64 = 10;
+
+/// A host receiving fuzzed datagrams. Created with [`FuzzState::new`].
+pub struct FuzzState {
+    host: Host<FuzzSocket>,
+    time: Arc<AtomicU64>,
+}
+
+impl FuzzState {
+    /// Create a host for fuzzing with `settings`, replacing the time function with a manual
+    /// clock, advanced with [`FuzzState::advance`], and the seed and secure random bytes with
+    /// fixed ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the settings are invalid, see [`Host::new`].
+    #[must_use]
+    pub fn new(settings: HostSettings) -> Self {
+        let time = Arc::new(AtomicU64::new(1000));
+        let host_time = time.clone();
+        let host = Host::new(
+            FuzzSocket::new(),
+            HostSettings {
+                time: Box::new(move || Duration::from_millis(host_time.load(Ordering::Relaxed))),
+                seed: Some(0),
+                secure_random: Box::new(|bytes| bytes.fill(0)),
+                ..settings
+            },
+        )
+        .expect("Fuzzing host settings should be valid.");
+        Self { host, time }
+    }
+
+    /// Get the host.
+    #[must_use]
+    pub fn host(&self) -> &Host<FuzzSocket> {
+        &self.host
+    }
+
+    /// Get the host mutably, to change settings or act on peers between datagrams.
+    pub fn host_mut(&mut self) -> &mut Host<FuzzSocket> {
+        &mut self.host
+    }
+
+    /// Advance the host's clock, then service it, so timeouts and retransmissions run. Returns
+    /// the events generated.
+    pub fn advance(&mut self, duration: Duration) -> Vec<EventNoRef> {
+        let millis = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+        self.time.fetch_add(millis, Ordering::Relaxed);
+        self.service().0
+    }
+
+    fn step(&self) {
+        self.time.fetch_add(SESSION_STEP, Ordering::Relaxed);
+    }
+
+    /// The address fuzzed datagrams are received from.
+    #[must_use]
+    pub fn source_address() -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 1))
+    }
+
+    /// Service the host until it has no more events, returning the events, and the datagrams it
+    /// sent.
+    fn service(&mut self) -> (Vec<EventNoRef>, Vec<Vec<u8>>) {
+        let mut events = Vec::new();
+        while let Some(event) = self.host.service().unwrap() {
+            events.push(event.no_ref());
+        }
+        let mut sent = Vec::new();
+        while let Some((_, datagram)) = self.host.socket_mut().read() {
+            sent.push(datagram);
+        }
+        (events, sent)
+    }
+}
+
+impl Default for FuzzState {
+    fn default() -> Self {
+        Self::new(HostSettings {
+            peer_limit: 4,
+            ..HostSettings::default()
+        })
+    }
+}
+
+/// Parse a datagram's header and commands as the host would, without handling them. Returns
+/// `true` if the whole datagram parsed.
+#[must_use]
+pub fn parse_datagram(bytes: &[u8], has_checksum: bool) -> bool {
+    let Ok(header) = enet_protocol_parse_header(bytes, has_checksum, false) else {
+        return false;
+    };
+    let mut reader = ENetReader::new(bytes);
+    _ = reader.read_bytes(header.size);
+    while !reader.is_empty() {
+        if enet_protocol_parse_command(&mut reader).is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Handle one datagram from [`FuzzState::source_address`], servicing the host until it has no
+/// more events. Returns the events generated.
+pub fn handle_datagram(state: &mut FuzzState, bytes: &[u8]) -> Vec<EventNoRef> {
+    state
+        .host
+        .socket_mut()
+        .write(FuzzState::source_address(), bytes.to_vec());
+    state.service().0
+}
+
+/// Handle a session of datagrams, each prefixed with its length as a big endian `u16`, advancing
+/// the clock by 10 milliseconds before each one. A truncated final datagram is handled as is.
+/// Returns the events generated.
+pub fn handle_session(state: &mut FuzzState, mut bytes: &[u8]) -> Vec<EventNoRef> {
+    let mut events = Vec::new();
+    while let [high, low, rest @ ..] = bytes {
+        let length = usize::from(u16::from_be_bytes([*high, *low])).min(rest.len());
+        let (datagram, rest) = rest.split_at(length);
+        state.step();
+        events.extend(handle_datagram(state, datagram));
+        bytes = rest;
+    }
+    events
+}
+
+/// Record a session between a client and a [`FuzzState::default`] host, in the format read by
+/// [`handle_session`], for use as a seed. The client connects, sends reliable, unreliable,
+/// unsequenced and fragmented packets, and disconnects.
+///
+/// # Panics
+///
+/// Panics if the session doesn't complete, which would be a bug.
+#[must_use]
+pub fn record_session() -> Vec<u8> {
+    let mut server = FuzzState::default();
+    let mut client = FuzzState::default();
+    let mut session = Vec::new();
+    let address = FuzzState::source_address();
+    let peer = client.host.connect(address, 2, 0).unwrap().peer_id();
+    let mut exchange = |client: &mut FuzzState, server: &mut FuzzState| {
+        for _ in 0..8 {
+            client.step();
+            for datagram in client.service().1 {
+                session.extend_from_slice(&u16::try_from(datagram.len()).unwrap().to_be_bytes());
+                session.extend_from_slice(&datagram);
+                server.step();
+                server.host.socket_mut().write(address, datagram);
+                for datagram in server.service().1 {
+                    client.host.socket_mut().write(address, datagram);
+                }
+            }
+        }
+    };
+    exchange(&mut client, &mut server);
+    let packets = [
+        Packet::reliable(b"reliable"),
+        Packet::unreliable(b"unreliable"),
+        Packet::unreliable_unsequenced(b"unsequenced"),
+        Packet::reliable(&[7; 3000]),
+        Packet::new(&[9; 3000], PacketKind::AlwaysUnreliable { sequenced: true }),
+    ];
+    for (index, packet) in packets.iter().enumerate() {
+        let channel_id = u8::try_from(index % 2).unwrap();
+        client
+            .host
+            .peer_mut(peer)
+            .unwrap()
+            .send(channel_id, packet)
+            .unwrap();
+    }
+    exchange(&mut client, &mut server);
+    client.host.peer_mut(peer).unwrap().disconnect(0);
+    exchange(&mut client, &mut server);
+    assert_eq!(server.host.connected_peers().count(), 0);
+    session
+}
@@ -0,0 +1,202 @@
+use core::time::Duration;
+use std::{
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+};
+
+use crate::{
+    error::{HostThreadStopped, PeerSendError, ServiceError},
+    EventNoRef, Host, Packet, PeerID, Socket,
+};
+
+enum HostCommand {
+    Send {
+        peer: PeerID,
+        channel_id: u8,
+        packet: Packet,
+        result: Sender<Result<(), PeerSendError>>,
+    },
+    Disconnect {
+        peer: PeerID,
+        data: u32,
+    },
+    Broadcast {
+        channel_id: u8,
+        packet: Packet,
+    },
+}
+
+/// A cloneable handle to a [`Host`] running on its own thread, created with
+/// [`HostHandle::spawn`].
+///
+/// The host thread calls [`Host::service`] in a loop, forwarding every event to the receiver
+/// returned alongside the handle, and wakes up as soon as a command is sent through any handle.
+/// The outcome of each send is reported through the receiver returned by [`HostHandle::send`].
+///
+/// # Shutdown
+///
+/// **The thread stops as soon as every handle is dropped**, flushing any queued packets first,
+/// even if the event receiver is still alive. Discarding the handle, as in
+/// `let (_, events) = HostHandle::spawn(host);`, stops the host immediately, so keep a handle
+/// alive for as long as the host should run. The thread also stops when [`Host::service`] fails,
+/// in which case the error is sent as the last event.
+///
+/// ```no_run
+/// use std::net::UdpSocket;
+///
+/// use rusty_enet::{EventNoRef, Host, HostHandle, HostSettings};
+///
+/// let socket = UdpSocket::bind("0.0.0.0:6060").unwrap();
+/// let host = Host::new(socket, HostSettings::default()).unwrap();
+/// let (handle, events) = HostHandle::spawn(host);
+/// while let Ok(event) = events.recv() {
+///     if let EventNoRef::Receive { peer, channel_id, packet, .. } = event.unwrap() {
+///         handle.send(peer, channel_id, packet).unwrap();
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HostHandle {
+    commands: Sender<HostCommand>,
+}
+
+impl HostHandle {
+    /// The default interval between calls to [`Host::service`] when no commands arrive, 1ms.
+    pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+    /// Move `host` onto its own thread, polling it every
+    /// [`HostHandle::DEFAULT_POLL_INTERVAL`].
+    ///
+    /// Outgoing connections can be started with [`Host::connect`] before spawning.
+    ///
+    /// The host stops once the returned handle and all of its clones are dropped, see
+    /// [Shutdown](HostHandle#shutdown).
+    #[must_use]
+    pub fn spawn<S>(host: Host<S>) -> (HostHandle, Receiver<Result<EventNoRef, ServiceError<S>>>)
+    where
+        S: Socket + Send + 'static,
+        S::Address: Send,
+        S::Error: Send,
+    {
+        Self::spawn_with_poll_interval(host, Self::DEFAULT_POLL_INTERVAL)
+    }
+
+    /// Move `host` onto its own thread, waiting at most `poll_interval` between calls to
+    /// [`Host::service`] when no commands arrive.
+    ///
+    /// The host stops once the returned handle and all of its clones are dropped, see
+    /// [Shutdown](HostHandle#shutdown).
+    #[must_use]
+    pub fn spawn_with_poll_interval<S>(
+        mut host: Host<S>,
+        poll_interval: Duration,
+    ) -> (HostHandle, Receiver<Result<EventNoRef, ServiceError<S>>>)
+    where
+        S: Socket + Send + 'static,
+        S::Address: Send,
+        S::Error: Send,
+    {
+        let (commands, command_receiver) = mpsc::channel();
+        let (event_sender, events) = mpsc::channel();
+        thread::spawn(move || loop {
+            loop {
+                match host.service() {
+                    Ok(Some(event)) => {
+                        _ = event_sender.send(Ok(event.no_ref()));
+                    }
+                    Ok(None) => break,
+                    Err(err) => {
+                        _ = event_sender.send(Err(err));
+                        return;
+                    }
+                }
+            }
+            match command_receiver.recv_timeout(poll_interval) {
+                Ok(command) => {
+                    run_command(&mut host, command);
+                    while let Ok(command) = command_receiver.try_recv() {
+                        run_command(&mut host, command);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    host.flush();
+                    return;
+                }
+            }
+        });
+        (HostHandle { commands }, events)
+    }
+
+    /// Queue a packet to be sent to a peer. See [`Peer::send`](`crate::Peer::send`).
+    ///
+    /// The returned receiver yields the result of [`Peer::send`](`crate::Peer::send`) once the
+    /// host thread has run the command, or [`PeerSendError::InvalidPeer`] if `peer` is stale. It
+    /// can be dropped if the outcome isn't needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostThreadStopped`] if the host thread has stopped.
+    pub fn send(
+        &self,
+        peer: PeerID,
+        channel_id: u8,
+        packet: Packet,
+    ) -> Result<Receiver<Result<(), PeerSendError>>, HostThreadStopped> {
+        let (result, receiver) = mpsc::channel();
+        self.command(HostCommand::Send {
+            peer,
+            channel_id,
+            packet,
+            result,
+        })?;
+        Ok(receiver)
+    }
+
+    /// Request a disconnection from a peer. See [`Peer::disconnect`](`crate::Peer::disconnect`).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostThreadStopped`] if the host thread has stopped.
+    pub fn disconnect(&self, peer: PeerID, data: u32) -> Result<(), HostThreadStopped> {
+        self.command(HostCommand::Disconnect { peer, data })
+    }
+
+    /// Queue a packet to be sent to all connected peers. See [`Host::broadcast`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostThreadStopped`] if the host thread has stopped.
+    pub fn broadcast(&self, channel_id: u8, packet: Packet) -> Result<(), HostThreadStopped> {
+        self.command(HostCommand::Broadcast { channel_id, packet })
+    }
+
+    fn command(&self, command: HostCommand) -> Result<(), HostThreadStopped> {
+        self.commands.send(command).map_err(|_| HostThreadStopped)
+    }
+}
+
+fn run_command<S: Socket>(host: &mut Host<S>, command: HostCommand) {
+    match command {
+        HostCommand::Send {
+            peer,
+            channel_id,
+            packet,
+            result,
+        } => {
+            let sent = host
+                .peer_mut(peer)
+                .ok_or(PeerSendError::InvalidPeer)
+                .and_then(|peer| peer.send(channel_id, &packet));
+            _ = result.send(sent);
+        }
+        HostCommand::Disconnect { peer, data } => {
+            if let Some(peer) = host.peer_mut(peer) {
+                peer.disconnect(data);
+            }
+        }
+        HostCommand::Broadcast { channel_id, packet } => {
+            host.broadcast(channel_id, &packet);
+        }
+    }
+}
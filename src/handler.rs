@@ -0,0 +1,95 @@
+use core::{ops::ControlFlow, time::Duration};
+use std::thread;
+
+use crate::{error::ServiceError, Event, Host, Packet, Peer, Socket};
+
+/// Callbacks for the events of a host run with [`Host::run_with_handler`], for applications
+/// which would rather be called back than poll [`Host::service`] themselves.
+///
+/// Every method has a default which does nothing, so handlers only implement the events they
+/// care about. [`EventHandler::on_event`] receives every event, and passes connections, packets
+/// and disconnections on to the matching method unless overridden.
+///
+/// ```no_run
+/// use std::{net::UdpSocket, ops::ControlFlow};
+///
+/// use rusty_enet::{EventHandler, Host, HostSettings, Packet, Peer};
+///
+/// struct Echo;
+///
+/// impl EventHandler<UdpSocket> for Echo {
+///     fn on_receive(&mut self, peer: &mut Peer<UdpSocket>, channel_id: u8, packet: Packet) {
+///         _ = peer.send(channel_id, &packet);
+///     }
+/// }
+///
+/// let socket = UdpSocket::bind("0.0.0.0:6060").unwrap();
+/// let mut host = Host::new(socket, HostSettings::default()).unwrap();
+/// host.run_with_handler(&mut Echo).unwrap();
+/// ```
+pub trait EventHandler<S: Socket> {
+    /// Called for every event. By default, calls [`EventHandler::on_connect`],
+    /// [`EventHandler::on_receive`] or [`EventHandler::on_disconnect`], and ignores other events.
+    fn on_event(&mut self, event: Event<'_, S>) {
+        match event {
+            Event::Connect { peer, data, .. } => self.on_connect(peer, data),
+            Event::Receive {
+                peer,
+                channel_id,
+                packet,
+                ..
+            } => self.on_receive(peer, channel_id, packet),
+            Event::Disconnect { peer, data } => self.on_disconnect(peer, data),
+            _ => {}
+        }
+    }
+
+    /// Called when a peer connects, see [`Event::Connect`].
+    fn on_connect(&mut self, _peer: &mut Peer<S>, _data: u32) {}
+
+    /// Called when a peer sends a packet, see [`Event::Receive`].
+    fn on_receive(&mut self, _peer: &mut Peer<S>, _channel_id: u8, _packet: Packet) {}
+
+    /// Called when a peer disconnects, see [`Event::Disconnect`].
+    fn on_disconnect(&mut self, _peer: &mut Peer<S>, _data: u32) {}
+
+    /// Called once no events remain, before waiting for more, with access to the whole host to
+    /// connect or broadcast. Return [`ControlFlow::Break`] to stop
+    /// [`Host::run_with_handler`].
+    fn on_idle(&mut self, _host: &mut Host<S>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// How long to wait between services of the host while there are no events. Defaults to
+    /// 1ms.
+    fn poll_interval(&self) -> Duration {
+        Duration::from_millis(1)
+    }
+}
+
+impl<S: Socket> Host<S> {
+    /// Service the host in a loop, passing each event to `handler`, until
+    /// [`EventHandler::on_idle`] returns [`ControlFlow::Break`].
+    ///
+    /// Events are handled as soon as they are available. Once none remain, the thread sleeps for
+    /// [`EventHandler::poll_interval`] before servicing the host again.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reported by the socket implementation, as with [`Host::service`],
+    /// stopping the loop.
+    pub fn run_with_handler(
+        &mut self,
+        handler: &mut impl EventHandler<S>,
+    ) -> Result<(), ServiceError<S>> {
+        loop {
+            while let Some(event) = self.service()? {
+                handler.on_event(event);
+            }
+            if handler.on_idle(self).is_break() {
+                return Ok(());
+            }
+            thread::sleep(handler.poll_interval());
+        }
+    }
+}
@@ -1,25 +1,150 @@
-use core::{fmt::Debug, mem::zeroed, time::Duration};
+use core::{
+    fmt::Debug,
+    mem::{take, zeroed},
+    time::Duration,
+};
 
-use crate::{Box, Vec};
+use crate::{BTreeMap, Box, Vec, VecDeque};
 
 use crate::{
     consts::{
-        PROTOCOL_MAXIMUM_CHANNEL_COUNT, PROTOCOL_MAXIMUM_MTU, PROTOCOL_MAXIMUM_PEER_ID,
-        PROTOCOL_MINIMUM_MTU,
+        HOST_CONNECT_RACE_DELAY, HOST_DEFAULT_COMMAND_POOL_LIMIT, HOST_DEFAULT_MAXIMUM_PACKET_SIZE,
+        PEER_MAXIMUM_CONNECT_PAYLOAD, PEER_MAXIMUM_UNSEQUENCED_WINDOW_SIZE,
+        PEER_MINIMUM_UNSEQUENCED_WINDOW_SIZE, PEER_UNSEQUENCED_WINDOW_SIZE,
+        PROTOCOL_EXTENDED_MAXIMUM_PEER_ID, PROTOCOL_MAXIMUM_CHANNEL_COUNT, PROTOCOL_MAXIMUM_MTU,
+        PROTOCOL_MAXIMUM_PEER_ID, PROTOCOL_MINIMUM_MTU,
     },
-    enet_host_bandwidth_limit, enet_host_broadcast, enet_host_channel_limit,
+    enet_host_ban, enet_host_bandwidth_limit, enet_host_broadcast, enet_host_channel_limit,
     enet_host_check_events, enet_host_compress, enet_host_connect, enet_host_create,
-    enet_host_destroy, enet_host_flush, enet_host_service,
-    error::{BadParameter, HostNewError, NoAvailablePeers},
-    time_since_epoch, Compressor, ENetEvent, ENetHost, ENetPeer, Event, Packet, Peer, PeerID,
-    PeerState, Socket, ENET_EVENT_TYPE_CONNECT, ENET_EVENT_TYPE_DISCONNECT,
-    ENET_EVENT_TYPE_RECEIVE,
+    enet_host_destroy, enet_host_disconnect_now, enet_host_flush, enet_host_is_banned,
+    enet_host_pending_events, enet_host_send_raw_datagrams, enet_host_service, enet_host_unban,
+    enet_peer_check_batch, enet_peer_id, enet_peer_queue_batch, enet_peer_send_connect_payload,
+    enet_time_get,
+    error::{
+        BadParameter, BatchSendError, ConnectError, HostNewError, NoAvailablePeers, PeerSendError,
+        ServiceError,
+    },
+    os_random, time_since_epoch, ChannelMode, Compressor, CongestionController, ENetEvent,
+    ENetHost, ENetPacket, ENetPeer, ENetReceiveBatch, EnetThrottle, Event, EventNoRef, FecDelivery,
+    FecState, Packet, PacketKind, PacketTransform, Peer, PeerID, PeerState, PendingConnection,
+    ProtocolVersion, RaceState, RacingConnection, RpcReply, RpcState, Socket,
+    ENET_EVENT_TYPE_ACKNOWLEDGE, ENET_EVENT_TYPE_CONNECT, ENET_EVENT_TYPE_CONNECT_REQUEST,
+    ENET_EVENT_TYPE_DELIVERY_FAILED, ENET_EVENT_TYPE_DISCONNECT, ENET_EVENT_TYPE_PACKET_RESENT,
+    ENET_EVENT_TYPE_PEER_IDLE, ENET_EVENT_TYPE_PROTOCOL_VIOLATION, ENET_EVENT_TYPE_RECEIVE,
 };
 
+/// What a host does with peers over
+/// [`HostSettings::peer_memory_budget`]. See [`Peer::memory_usage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MemoryBudgetPolicy {
+    /// Disconnect the peer during the next [`Host::service`], generating an
+    /// [`Event::Disconnect`].
+    #[default]
+    Disconnect,
+    /// Stop queueing packets to and from the peer until its usage drops back under the budget.
+    ///
+    /// Incoming reliable packets are left unacknowledged so the peer retransmits them later,
+    /// incoming unreliable packets are dropped, and [`Peer::send`] returns
+    /// [`PeerSendError::MemoryBudgetExceeded`](`crate::error::PeerSendError::MemoryBudgetExceeded`).
+    Throttle,
+}
+
+/// When a host sends the acknowledgements for reliable packets it receives, configured with
+/// [`HostSettings::acknowledgement_mode`].
+///
+/// Acknowledgements are always sent along with any other commands queued for the peer, so
+/// batching them only costs a round trip when there is nothing else to send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AcknowledgementMode {
+    /// Send acknowledgements once per [`Host::service`], after all received datagrams are
+    /// handled. This is ENet's behavior.
+    #[default]
+    PerService,
+    /// Send acknowledgements as soon as each datagram is handled, without waiting for the rest of
+    /// the service. Suits request/response workloads, at the cost of a datagram per received
+    /// datagram.
+    Immediate,
+    /// Hold acknowledgements for up to this long during [`Host::service`], to send them together
+    /// with later acknowledgements or replies. Held acknowledgements are sent as soon as other
+    /// commands are sent to the peer, and by [`Host::flush`] and [`Peer::flush`].
+    ///
+    /// The delay counts towards the round trip time measured by the peer, so it should stay well
+    /// under the peer's resend timeout.
+    Delayed(Duration),
+}
+
+/// What a channel does with unreliable packets queued faster than they can be sent, configured
+/// with [`HostSettings::channel_drop_policies`].
+///
+/// Only unreliable packets which haven't been sent yet are counted or dropped. Dropped packets are
+/// never sent, and [`Peer::send`] still returns `Ok` for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnreliableDropPolicy {
+    /// Queue every packet, leaving them to ENet's packet throttle.
+    #[default]
+    Unbounded,
+    /// Allow this many packets to wait, dropping the oldest to make room for new ones. A limit of
+    /// `0` is treated as `1`.
+    DropOldest(usize),
+    /// Allow this many packets to wait, dropping new packets until there is room.
+    DropNewest(usize),
+    /// Only keep the latest packet waiting, replacing it with each new packet. Suits snapshot
+    /// channels, where older state is useless once newer state is available.
+    KeepLatest,
+}
+
+/// Statistics for one of a host's command pools, returned by
+/// [`Host::outgoing_command_pool_stats`] and [`Host::incoming_command_pool_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoolStats {
+    /// The number of commands currently allocated, whether in use or available for reuse.
+    pub allocated: usize,
+    /// The number of freed commands available for reuse.
+    pub available: usize,
+    /// The total number of commands allocated from the heap.
+    pub allocations: u64,
+    /// The total number of commands reused from the pool instead of being allocated.
+    pub reuses: u64,
+}
+
+/// The outcome of a connection attempt, reported to the callback set with
+/// [`Host::set_connect_audit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectOutcome {
+    /// The connection was accepted, immediately or with [`Peer::accept`] if
+    /// [`HostSettings::connect_approval`] is enabled.
+    Accepted,
+    /// The request was dropped because no peer slot was available, or the address already has
+    /// too many connections.
+    PeerLimit,
+    /// The request was rejected with [`Peer::reject`].
+    Rejected,
+    /// The request asked for an invalid number of channels.
+    InvalidRequest,
+    /// A datagram from an unknown peer failed `HostSettings::connect_authentication`.
+    AuthenticationFailed,
+    /// A connection request from an address banned with [`Host::ban`] was dropped. Requests
+    /// altered by [`Host::add_transform`] can't be recognized before they're dropped, so aren't
+    /// reported.
+    Banned,
+}
+
+/// The result of [`Host::service_budget`].
+#[derive(Debug, Clone)]
+pub struct BudgetedService {
+    /// The events generated, in order.
+    pub events: Vec<EventNoRef>,
+    /// Whether servicing stopped because the budget ran out, rather than because there was
+    /// nothing left to do. Can be `true` when the budget ran out just as the last work was done.
+    pub work_remaining: bool,
+}
+
 /// Settings for a newly created host, passed into [`Host::new`].
 #[allow(clippy::type_complexity)]
 pub struct HostSettings {
-    /// The maximum number of peers that should be allocated for the host.
+    /// The maximum number of peers that should be allocated for the host, up to
+    /// [`PROTOCOL_MAXIMUM_PEER_ID`], or [`PROTOCOL_EXTENDED_MAXIMUM_PEER_ID`] with
+    /// [`ProtocolVersion::ExtendedPeerIds`].
     pub peer_limit: usize,
     /// The maximum number of channels allowed. Cannot be 0.
     pub channel_limit: usize,
@@ -37,13 +162,295 @@ pub struct HostSettings {
     pub compressor: Option<Box<dyn Compressor>>,
     /// The checksum function to use when sending and receiving packets, or [`None`] for no
     /// checksum.
-    pub checksum: Option<Box<dyn Fn(&[&[u8]]) -> u32>>,
+    ///
+    /// See [`Host::set_checksum`] for more info.
+    pub checksum: Option<Box<dyn Fn(&[&[u8]]) -> u32 + Send + Sync>>,
     /// A custom time function to use, or [`None`] to use the default one. Should return an
     /// an accurate, incrementally increasing [`Duration`]. Defaults to [`time_since_epoch`].
-    pub time: Box<dyn Fn() -> Duration>,
+    pub time: Box<dyn Fn() -> Duration + Send + Sync>,
     /// Seed the host with a specific random seed, or set to [`None`] to use a random seed.
     pub seed: Option<u32>,
+    /// A function filling a buffer with cryptographically secure random bytes, used for the
+    /// values other hosts must not be able to guess, such as migration challenges and session
+    /// tickets. Unlike [`HostSettings::seed`], these are never derived from the host's seed.
+    /// Defaults to [`os_random`].
+    pub secure_random: Box<dyn Fn(&mut [u8]) + Send + Sync>,
+    /// Use the extended protocol header, which prefixes every outgoing datagram with integrity
+    /// bytes derived from the peer's port. Only hosts with the same setting can communicate.
     pub using_new_packet: bool,
+    /// Validate incoming datagrams strictly, reporting malformed or out of range commands with
+    /// [`Event::ProtocolViolation`] instead of silently ignoring them.
+    ///
+    /// Datagrams which fail validation are discarded entirely. Implies
+    /// [`HostSettings::report_malformed`]. Defaults to `false`.
+    pub strict_validation: bool,
+    /// Report datagrams which ENet fails to parse, such as those with an unknown peer ID, a bad
+    /// checksum, or truncated commands, with [`Event::ProtocolViolation`]. Unlike
+    /// [`HostSettings::strict_validation`], this does not change which datagrams are accepted.
+    ///
+    /// Useful for detecting attack traffic and misbehaving clients. Defaults to `false`.
+    pub report_malformed: bool,
+    /// The maximum number of fragmented packets each peer may have partially reassembled at
+    /// once, or [`None`] for no limit.
+    ///
+    /// When a peer starts a new fragmented packet past this limit, its oldest incomplete
+    /// unreliable fragmented packet is dropped. If there are none to drop, new unreliable
+    /// fragments are discarded, and new reliable fragments are left unacknowledged so the peer
+    /// retransmits them later. Defaults to [`None`].
+    pub maximum_incomplete_fragments: Option<usize>,
+    /// The maximum size of packets this host will send or reassemble. Can be lowered for
+    /// individual peers with [`Peer::set_maximum_packet_size`].
+    ///
+    /// Fragmented packets larger than this are rejected before any memory is allocated for them,
+    /// and reported as
+    /// [`ProtocolViolationKind::PacketTooLarge`](`crate::ProtocolViolationKind::PacketTooLarge`).
+    /// Defaults to
+    /// [`HOST_DEFAULT_MAXIMUM_PACKET_SIZE`](`crate::consts::HOST_DEFAULT_MAXIMUM_PACKET_SIZE`).
+    pub maximum_packet_size: usize,
+    /// The maximum number of bytes each peer may hold in queued incoming and outgoing commands,
+    /// or [`None`] for no limit. See [`Peer::memory_usage`].
+    ///
+    /// Peers over the budget are handled according to [`HostSettings::memory_budget_policy`].
+    /// Defaults to [`None`].
+    pub peer_memory_budget: Option<usize>,
+    /// What to do with peers over [`HostSettings::peer_memory_budget`]. Defaults to
+    /// [`MemoryBudgetPolicy::Disconnect`].
+    pub memory_budget_policy: MemoryBudgetPolicy,
+    /// The maximum number of bytes the host may buffer for partially reassembled unreliable
+    /// fragmented packets across all peers, or [`None`] for no limit.
+    ///
+    /// When exceeded, the oldest incomplete unreliable fragmented packets of any peer are dropped
+    /// first, as with [`HostSettings::maximum_incomplete_fragments`]. Reliable fragmented packets
+    /// can't be dropped, so they are bounded per peer by
+    /// [`HostSettings::maximum_peer_fragment_data`] instead. Defaults to [`None`].
+    pub maximum_fragment_data: Option<usize>,
+    /// The maximum number of bytes each peer may have buffered for partially reassembled reliable
+    /// fragmented packets, or [`None`] for no limit.
+    ///
+    /// A peer starting a reliable fragmented packet past this limit is reported with
+    /// [`ProtocolViolationKind::FragmentDataExceeded`](`crate::ProtocolViolationKind::FragmentDataExceeded`)
+    /// and disconnected, leaving other peers unaffected. Defaults to [`None`].
+    pub maximum_peer_fragment_data: Option<usize>,
+    /// The maximum number of freed commands of each kind the host keeps for reuse. See
+    /// [`Host::outgoing_command_pool_stats`].
+    ///
+    /// Every queued packet needs an outgoing command until it is acknowledged, and every received
+    /// packet an incoming command until it is dispatched. Pooling them means steady traffic
+    /// doesn't allocate a command per packet, while the limit bounds the memory kept after a
+    /// burst. Defaults to
+    /// [`HOST_DEFAULT_COMMAND_POOL_LIMIT`](`crate::consts::HOST_DEFAULT_COMMAND_POOL_LIMIT`).
+    pub command_pool_limit: usize,
+    /// The maximum number of datagrams passed to [`Socket::send_batch`] and
+    /// [`Socket::receive_batch`] at once. Cannot be 0.
+    ///
+    /// Outgoing datagrams are queued until this many are ready, or the host is done sending, so
+    /// sockets with batched system calls like `sendmmsg` and `recvmmsg` can handle many
+    /// datagrams per call. A buffer of [`MTU_MAX`](`crate::MTU_MAX`) bytes is allocated for each
+    /// datagram received at once. Defaults to `1`.
+    pub socket_batch_size: usize,
+    /// The maximum number of datagrams sent by [`Host::broadcast_raw`] per second, so
+    /// announcements to many addresses don't crowd out traffic to peers. Datagrams over the
+    /// limit wait in a queue, and up to a second's worth can be sent at once. Defaults to `100`.
+    pub raw_datagram_rate: u32,
+    /// The channel packets are sent on by [`Peer::send_default`], unless the peer overrides it
+    /// with [`Peer::set_defaults`]. Defaults to `0`.
+    pub default_channel: u8,
+    /// The kind of packets sent by [`Peer::send_default`], unless the peer overrides it with
+    /// [`Peer::set_defaults`]. Defaults to [`PacketKind::Reliable`].
+    pub default_packet_kind: PacketKind,
+    /// The channel reserved for remote calls made with [`Peer::call`], or [`None`] to disable
+    /// them. Packets received on this channel are handled by the host, and never generate
+    /// [`Event::Receive`]. Both hosts must use the same channel. Defaults to [`None`].
+    pub rpc_channel: Option<u8>,
+    /// How long to wait for the reply to a [`Peer::call`] before failing it with
+    /// [`RpcError::Timeout`](`crate::error::RpcError::Timeout`). Defaults to 5 seconds.
+    pub rpc_timeout: Duration,
+    /// The delivery mode of each channel, indexed by channel ID. Channels past the end of the list
+    /// accept packets of any kind.
+    ///
+    /// Packets sent on a channel with a mode must match it, or [`Peer::send`] returns
+    /// [`PeerSendError::ChannelModeMismatch`], and [`Peer::send_on`] sends packets of the
+    /// channel's kind without repeating it. Packets received on the channel which don't match are
+    /// rejected as
+    /// [`ProtocolViolationKind::ChannelModeMismatch`](`crate::ProtocolViolationKind::ChannelModeMismatch`),
+    /// so both hosts should use the same modes. Defaults to an empty list.
+    pub channel_modes: Vec<ChannelMode>,
+    /// The priority of each channel, indexed by channel ID. Channels past the end of the list have
+    /// a priority of `0`.
+    ///
+    /// Packets queued on a channel are sent before any packets still waiting on channels of a
+    /// lower priority, so latency sensitive channels like input or chat aren't stuck behind bulk
+    /// transfers when bandwidth is limited. Packets of the same priority are sent in the order
+    /// they were queued. Defaults to an empty list.
+    pub channel_priorities: Vec<u8>,
+    /// The maximum size of packets accepted on each channel, indexed by channel ID. Channels past
+    /// the end of the list are only limited by [`HostSettings::maximum_packet_size`] and
+    /// [`Peer::set_maximum_packet_size`].
+    ///
+    /// Larger packets received on the channel are rejected as
+    /// [`ProtocolViolationKind::PacketTooLarge`](`crate::ProtocolViolationKind::PacketTooLarge`),
+    /// fragmented ones before any memory is allocated for them. This lets bulk transfer channels
+    /// accept large packets while keeping input or chat channels to small ones. Defaults to an
+    /// empty list.
+    pub channel_maximum_packet_sizes: Vec<usize>,
+    /// What each channel does with unreliable packets queued faster than they can be sent, indexed
+    /// by channel ID. Channels past the end of the list use [`UnreliableDropPolicy::Unbounded`].
+    /// Defaults to an empty list.
+    pub channel_drop_policies: Vec<UnreliableDropPolicy>,
+    /// How many unreliable packets each parity packet protects, indexed by channel ID. Channels
+    /// past the end of the list, or with a group of `0`, don't use forward error correction.
+    ///
+    /// After every group of this many unreliable packets sent on the channel, an extra unreliable
+    /// packet holding their XOR parity is sent, which lets the peer rebuild any single packet of
+    /// the group that was lost, without waiting for a resend. This suits voice and state streams,
+    /// trading bandwidth for surviving moderate loss: a group of `4` sends a fifth more data, and
+    /// a group of `1` sends everything twice. Recovered packets are received once the group's
+    /// parity arrives, so may come after later packets of their group.
+    ///
+    /// Every packet sent on these channels carries a small header, so both hosts must use the same
+    /// groups. Defaults to an empty list.
+    pub channel_parity_groups: Vec<u8>,
+    /// How long packets queued for a peer may be held during [`Host::service`] to coalesce into
+    /// fewer datagrams, trading latency for a lower packet rate. Can be overridden for individual
+    /// peers with [`Peer::set_coalesce_delay`].
+    ///
+    /// Packets are held until the first of them has waited this long, or enough are queued to
+    /// fill a datagram. [`Host::flush`], [`Peer::flush`] and [`Peer::send_now`] never hold
+    /// packets. Defaults to [`Duration::ZERO`], which never holds packets.
+    pub coalesce_delay: Duration,
+    /// When acknowledgements for received reliable packets are sent. Defaults to
+    /// [`AcknowledgementMode::PerService`].
+    pub acknowledgement_mode: AcknowledgementMode,
+    /// The variant of ENet's protocol to speak, downgraded for each connection to what the peer's
+    /// host speaks. See [`ProtocolVersion`] for more info. Defaults to
+    /// [`ProtocolVersion::Extended`].
+    pub protocol_version: ProtocolVersion,
+    /// Keep connected peers whose datagrams start arriving from a new address, such as mobile
+    /// clients rebinding their NAT or switching networks, rather than dropping their datagrams
+    /// until they time out.
+    ///
+    /// Before moving the peer, the new address is sent a challenge, which the peer must answer
+    /// from that address along with the connection's connect ID, so spoofed datagrams can't
+    /// redirect a peer. Datagrams from the new address are dropped until then. Only peers
+    /// speaking [`ProtocolVersion::Extended`] can migrate. Defaults to `false`.
+    pub connection_migration: bool,
+    /// Generate [`Event::PeerIdle`] when a connected peer sends no packets for this long, or
+    /// [`None`] to never consider peers idle. Pings and other protocol traffic don't count, so
+    /// this finds peers which are still connected, but inactive. Defaults to [`None`].
+    pub idle_timeout: Option<Duration>,
+    /// Time peers out once this many ping intervals pass without them acknowledging anything, or
+    /// [`None`] to only time out unacknowledged reliable commands, see
+    /// [`Peer::set_timeout_durations`].
+    ///
+    /// Peers are pinged while no other reliable traffic is being sent, so this bounds how long a
+    /// dead peer lingers no matter how much traffic there is, which reliable command timeouts only
+    /// do loosely. Can be changed per peer with [`Peer::set_missed_ping_limit`]. Defaults to
+    /// [`None`].
+    pub missed_ping_limit: Option<u32>,
+    /// How many unsequenced groups each peer's incoming unsequenced window covers. Unsequenced
+    /// packets arriving more than this many groups behind the newest are discarded, so peers
+    /// sending unsequenced packets at a high rate may need a larger window to avoid spurious
+    /// drops. See [`Sequencing`](`crate#sequencing`).
+    ///
+    /// Must be a power of two between
+    /// [`PEER_MINIMUM_UNSEQUENCED_WINDOW_SIZE`](`crate::consts::PEER_MINIMUM_UNSEQUENCED_WINDOW_SIZE`)
+    /// and
+    /// [`PEER_MAXIMUM_UNSEQUENCED_WINDOW_SIZE`](`crate::consts::PEER_MAXIMUM_UNSEQUENCED_WINDOW_SIZE`).
+    /// Only affects packets received, so peers don't need to agree. Defaults to
+    /// [`PEER_UNSEQUENCED_WINDOW_SIZE`](`crate::consts::PEER_UNSEQUENCED_WINDOW_SIZE`).
+    pub unsequenced_window_size: u32,
+    /// Generate [`Event::PacketResent`] each time a reliable packet is resent after being resent
+    /// this many times already, or [`None`] to not generate them. `Some(0)` reports every resend.
+    /// Resends are counted regardless, see [`Peer::reliable_resends`]. Defaults to [`None`].
+    pub resend_event_threshold: Option<u32>,
+    /// Drop unsequenced packets identical to one of the last this many received from the same peer
+    /// on the same channel, or `0` to never compare them. See [`Peer::unsequenced_duplicates`].
+    ///
+    /// Packets are compared by a 64 bit hash of their channel and data, so duplicated datagrams,
+    /// common on some mobile networks, are caught even once they fall outside the unsequenced
+    /// window. Identical packets sent on purpose are dropped too, so this only suits traffic where
+    /// each packet is unique, like packets carrying a sequence number or timestamp. Fragmented
+    /// unsequenced packets are never compared. Defaults to `0`.
+    pub unsequenced_dedup_window: usize,
+    /// Resend a reliable packet once this many acknowledgements arrive for reliable packets sent
+    /// after it, instead of waiting for it to time out, or [`None`] to only resend packets which
+    /// time out. `Some(0)` is treated as `Some(1)`.
+    ///
+    /// Acknowledgements for later packets imply an earlier one was lost, so this cuts the stall
+    /// after a lost packet on lossy links from a full resend timeout to about a round trip. Lower
+    /// values resend sooner, but may resend packets which were only reordered. Fast resends count
+    /// as resends in [`Peer::reliable_resends`] and [`Event::PacketResent`]. Defaults to
+    /// [`None`].
+    pub fast_retransmit: Option<u32>,
+    /// When the host is dropped, disconnect every peer immediately with this data, like
+    /// [`Peer::disconnect_now`], so they don't wait to time out. Or [`None`] to drop peers
+    /// without telling them.
+    ///
+    /// Disconnections are sent on a best-effort basis, unreliably, in a single flush before the
+    /// socket is released. See also [`Host::close`]. Defaults to [`None`].
+    pub disconnect_on_drop: Option<u32>,
+    /// How long a session can be resumed after a graceful disconnect, or [`None`] to not track
+    /// sessions.
+    ///
+    /// Each connection accepted from a peer speaking [`ProtocolVersion::Extended`] is given a
+    /// session, identified by [`Peer::session`], and the connecting host is sent a ticket for it.
+    /// When that host next connects to this address, it presents the ticket along with its
+    /// connection request, and again with each retransmission of the request, and if the session ended gracefully less than this long ago, the new
+    /// connection keeps its session and [`Peer::resumed`] returns `true`. The connection itself,
+    /// including its channels, starts fresh, but applications can carry their own state over,
+    /// like authentication, without another round trip. Tickets can only be presented once.
+    /// Defaults to [`None`].
+    pub session_resumption: Option<Duration>,
+    /// Hold incoming connections until the application approves them, so peers can be
+    /// authenticated before they count as connected.
+    ///
+    /// Each connection request generates [`Event::ConnectRequest`], and the connecting host keeps
+    /// resending its request until [`Peer::accept`] or [`Peer::reject`] is called, or it times
+    /// out. Requests should always be answered, as the peer keeps its slot until then. Ignored by
+    /// [`connected::Host`](`crate::connected::Host`). Defaults to `false`.
+    pub connect_approval: bool,
+    /// Follow redirects sent by peers with [`Peer::redirect`], connecting to the address they
+    /// name once the disconnection is complete.
+    ///
+    /// Only redirects from peers this host connected to with [`Host::connect`] are followed, and
+    /// only when the disconnect command comes right after the redirect, so a redirect is never
+    /// followed after a timeout or a disconnect for another reason.
+    ///
+    /// The new connection is started by the next call to [`Host::service`], with the same
+    /// number of channels, and the redirect's data as its connect data. Peers which don't follow
+    /// redirects just disconnect. Ignored by [`connected::Host`](`crate::connected::Host`).
+    /// Defaults to `false`.
+    pub follow_redirects: bool,
+    /// Send the payloads set with [`Peer::set_ping_payload`] along with pings, and accept them
+    /// from peers, surfaced with [`Peer::remote_ping_payload`]. Both hosts must enable this, and
+    /// payloads are only sent to peers speaking [`ProtocolVersion::Extended`]. Defaults to
+    /// `false`.
+    pub ping_payloads: bool,
+    /// Estimate each peer's clock offset from the round trips of its pings, surfaced with
+    /// [`Peer::estimated_clock_offset`]. Both hosts must enable this, and timestamps are only
+    /// sent to peers speaking [`ProtocolVersion::Extended`]. Defaults to `false`.
+    pub clock_sync: bool,
+    /// Mark outgoing datagrams ECN-Capable Transport, letting routers signal congestion by
+    /// marking them Congestion Experienced rather than dropping them.
+    ///
+    /// Peers echo the marks they receive back with a protocol extension message, at most once
+    /// per round trip, and each echo is passed to
+    /// [`CongestionController::congestion_experienced`](`crate::CongestionController::congestion_experienced`),
+    /// slowing the peer's throttle before packets are lost. Requires a socket which can read and
+    /// write the IP header's ECN field, see [`SocketOptions::ecn`](`crate::SocketOptions::ecn`),
+    /// such as [`std::net::UdpSocket`] on Linux with the `mmsg` feature. Both peers must speak
+    /// [`ProtocolVersion::Extended`]. Defaults to `false`.
+    pub ecn: bool,
+    /// The algorithm adjusting each peer's packet throttle as round trip times are measured.
+    /// Defaults to [`EnetThrottle`](`crate::EnetThrottle`), ENet's own algorithm.
+    pub congestion_controller: Box<dyn CongestionController>,
+    /// Require connection requests to be authenticated with a pre-shared key, or [`None`] to
+    /// accept any connection request.
+    ///
+    /// See [`ConnectAuthentication`](`crate::ConnectAuthentication`) for more info.
+    #[cfg(feature = "crypto")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+    pub connect_authentication: Option<crate::ConnectAuthentication>,
 }
 
 impl Default for HostSettings {
@@ -57,7 +464,48 @@ impl Default for HostSettings {
             checksum: None,
             time: Box::new(time_since_epoch),
             seed: None,
+            secure_random: Box::new(os_random),
             using_new_packet: false,
+            strict_validation: false,
+            report_malformed: false,
+            maximum_incomplete_fragments: None,
+            maximum_packet_size: HOST_DEFAULT_MAXIMUM_PACKET_SIZE as usize,
+            peer_memory_budget: None,
+            memory_budget_policy: MemoryBudgetPolicy::default(),
+            maximum_fragment_data: None,
+            maximum_peer_fragment_data: None,
+            command_pool_limit: HOST_DEFAULT_COMMAND_POOL_LIMIT,
+            socket_batch_size: 1,
+            raw_datagram_rate: 100,
+            default_channel: 0,
+            default_packet_kind: PacketKind::Reliable,
+            rpc_channel: None,
+            rpc_timeout: Duration::from_secs(5),
+            channel_modes: Vec::new(),
+            channel_priorities: Vec::new(),
+            channel_maximum_packet_sizes: Vec::new(),
+            channel_drop_policies: Vec::new(),
+            channel_parity_groups: Vec::new(),
+            coalesce_delay: Duration::ZERO,
+            acknowledgement_mode: AcknowledgementMode::default(),
+            protocol_version: ProtocolVersion::default(),
+            connection_migration: false,
+            idle_timeout: None,
+            missed_ping_limit: None,
+            unsequenced_window_size: PEER_UNSEQUENCED_WINDOW_SIZE,
+            resend_event_threshold: None,
+            unsequenced_dedup_window: 0,
+            fast_retransmit: None,
+            disconnect_on_drop: None,
+            session_resumption: None,
+            connect_approval: false,
+            follow_redirects: false,
+            ping_payloads: false,
+            clock_sync: false,
+            ecn: false,
+            congestion_controller: Box::new(EnetThrottle),
+            #[cfg(feature = "crypto")]
+            connect_authentication: None,
         }
     }
 }
@@ -70,8 +518,9 @@ pub struct Host<S: Socket> {
     peers: Vec<Peer<S>>,
 }
 
-unsafe impl<S: Socket> Send for Host<S> {}
-unsafe impl<S: Socket> Sync for Host<S> {}
+// the ENet structures behind the pointers hold the socket and its addresses
+unsafe impl<S: Socket + Send> Send for Host<S> where S::Address: Send {}
+unsafe impl<S: Socket + Sync> Sync for Host<S> where S::Address: Sync {}
 
 impl<S: Socket> Host<S> {
     /// Creates a host for communicating to peers, using the socket provided as a transport layer.
@@ -86,7 +535,11 @@ impl<S: Socket> Host<S> {
     /// - If [`HostSettings::incoming_bandwidth_limit`] is equal to `Some(0)`.
     /// - If [`HostSettings::outgoing_bandwidth_limit`] is equal to `Some(0)`.
     /// - If [`HostSettings::peer_limit`] is equal to `0` or greater than
-    ///   [`PROTOCOL_MAXIMUM_PEER_ID`].
+    ///   [`PROTOCOL_MAXIMUM_PEER_ID`], or [`PROTOCOL_EXTENDED_MAXIMUM_PEER_ID`] with
+    ///   [`ProtocolVersion::ExtendedPeerIds`].
+    /// - If [`HostSettings::socket_batch_size`] is equal to `0`.
+    /// - If [`HostSettings::unsequenced_window_size`] isn't a power of two between
+    ///   [`PEER_MINIMUM_UNSEQUENCED_WINDOW_SIZE`] and [`PEER_MAXIMUM_UNSEQUENCED_WINDOW_SIZE`].
     ///
     /// Returns [`HostNewError::FailedToInitializeSocket`] if the call to [`Socket::init`] fails.
     pub fn new(socket: S, settings: HostSettings) -> Result<Host<S>, HostNewError<S>> {
@@ -108,12 +561,38 @@ impl<S: Socket> Host<S> {
                 parameter: "settings.outgoing_bandwidth_limit",
             }));
         }
-        if settings.peer_limit == 0 || settings.peer_limit > PROTOCOL_MAXIMUM_PEER_ID as usize {
+        let maximum_peer_id = if settings.protocol_version >= ProtocolVersion::ExtendedPeerIds {
+            PROTOCOL_EXTENDED_MAXIMUM_PEER_ID
+        } else {
+            PROTOCOL_MAXIMUM_PEER_ID
+        };
+        if settings.peer_limit == 0 || settings.peer_limit > maximum_peer_id as usize {
             return Err(HostNewError::BadParameter(BadParameter {
                 method: "Host::new",
                 parameter: "settings.peer_limit",
             }));
         }
+        if settings.socket_batch_size == 0 {
+            return Err(HostNewError::BadParameter(BadParameter {
+                method: "Host::new",
+                parameter: "settings.socket_batch_size",
+            }));
+        }
+        if !settings.unsequenced_window_size.is_power_of_two()
+            || !(PEER_MINIMUM_UNSEQUENCED_WINDOW_SIZE..=PEER_MAXIMUM_UNSEQUENCED_WINDOW_SIZE)
+                .contains(&settings.unsequenced_window_size)
+        {
+            return Err(HostNewError::BadParameter(BadParameter {
+                method: "Host::new",
+                parameter: "settings.unsequenced_window_size",
+            }));
+        }
+        if settings.maximum_packet_size == 0 {
+            return Err(HostNewError::BadParameter(BadParameter {
+                method: "Host::new",
+                parameter: "settings.maximum_packet_size",
+            }));
+        }
         unsafe {
             let host = enet_host_create::<S>(
                 socket,
@@ -123,7 +602,9 @@ impl<S: Socket> Host<S> {
                 settings.outgoing_bandwidth_limit.unwrap_or(0),
                 settings.time,
                 settings.seed,
+                settings.secure_random,
                 settings.using_new_packet,
+                settings.ecn,
             )
             .map_err(|err| HostNewError::FailedToInitializeSocket(err))?;
             let mut peers = Vec::new();
@@ -137,6 +618,75 @@ impl<S: Socket> Host<S> {
             if let Some(checksum) = settings.checksum {
                 *(*host).checksum.assume_init_mut() = Some(checksum);
             }
+            (*host).socket_batch_size = settings.socket_batch_size;
+            if settings.socket_batch_size > 1 {
+                *(*host).receive_batch.assume_init_mut() =
+                    ENetReceiveBatch::new(settings.socket_batch_size);
+            }
+            (*host).strict_validation = settings.strict_validation;
+            (*host).report_malformed = settings.report_malformed;
+            (*host).maximum_incomplete_fragments =
+                settings.maximum_incomplete_fragments.unwrap_or(usize::MAX);
+            (*host).maximum_fragment_data = settings.maximum_fragment_data.unwrap_or(usize::MAX);
+            (*host).maximum_peer_fragment_data =
+                settings.maximum_peer_fragment_data.unwrap_or(usize::MAX);
+            (*host).maximum_packet_size = settings.maximum_packet_size;
+            (*host)
+                .outgoing_command_pool
+                .assume_init_mut()
+                .set_limit(settings.command_pool_limit);
+            (*host)
+                .incoming_command_pool
+                .assume_init_mut()
+                .set_limit(settings.command_pool_limit);
+            (*host).peer_memory_budget = settings.peer_memory_budget.unwrap_or(usize::MAX);
+            (*host).coalesce_delay =
+                u32::try_from(settings.coalesce_delay.as_millis()).unwrap_or(u32::MAX);
+            (*host).acknowledgement_mode = settings.acknowledgement_mode;
+            (*host).protocol_version = settings.protocol_version;
+            (*host).connection_migration = settings.connection_migration;
+            (*host).idle_timeout = settings
+                .idle_timeout
+                .map(|timeout| u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX));
+            (*host).missed_ping_limit = settings.missed_ping_limit;
+            (*host).unsequenced_window_size = settings.unsequenced_window_size;
+            for peer in &peers {
+                (*peer.0).missed_ping_limit = settings.missed_ping_limit;
+                (*peer.0)
+                    .unsequenced_window
+                    .assume_init_mut()
+                    .resize(settings.unsequenced_window_size as usize / 32, 0);
+            }
+            (*host).resend_event_threshold = settings.resend_event_threshold;
+            (*host).unsequenced_dedup_window = settings.unsequenced_dedup_window;
+            (*host).fast_retransmit = settings.fast_retransmit;
+            (*host).disconnect_on_drop = settings.disconnect_on_drop;
+            (*host).session_resumption = settings
+                .session_resumption
+                .map(|lifetime| u32::try_from(lifetime.as_millis()).unwrap_or(u32::MAX));
+            (*host).connect_approval = settings.connect_approval;
+            (*host).follow_redirects = settings.follow_redirects;
+            (*host).raw_datagram_rate = settings.raw_datagram_rate;
+            (*host).default_channel = settings.default_channel;
+            (*host).default_packet_kind = settings.default_packet_kind;
+            (*host).raw_datagram_credit = settings.raw_datagram_rate;
+            (*host).ping_payloads = settings.ping_payloads;
+            (*host).clock_sync = settings.clock_sync;
+            (*host).memory_budget_policy = settings.memory_budget_policy;
+            let rpc = (*host).rpc.assume_init_mut();
+            rpc.channel_id = settings.rpc_channel;
+            rpc.timeout = settings.rpc_timeout;
+            *(*host).channel_modes.assume_init_mut() = settings.channel_modes;
+            *(*host).channel_priorities.assume_init_mut() = settings.channel_priorities;
+            *(*host).channel_maximum_packet_sizes.assume_init_mut() =
+                settings.channel_maximum_packet_sizes;
+            *(*host).channel_drop_policies.assume_init_mut() = settings.channel_drop_policies;
+            (*host).fec.assume_init_mut().channel_groups = settings.channel_parity_groups;
+            *(*host).congestion_controller.assume_init_mut() = settings.congestion_controller;
+            #[cfg(feature = "crypto")]
+            {
+                (*host).connect_authentication = settings.connect_authentication;
+            }
             Ok(Self { host, peers })
         }
     }
@@ -146,6 +696,9 @@ impl<S: Socket> Host<S> {
     /// `data` is an integer value passed to the host upon connection, which can be anything.
     /// Retrieved with [`Event::Connect`].
     ///
+    /// The returned [`PendingConnection`] tracks the attempt, and its
+    /// [`PendingConnection::peer_id`] identifies the peer.
+    ///
     /// # Errors
     ///
     /// Returns [`NoAvailablePeers`] if all peer slots have been filled.
@@ -154,25 +707,143 @@ impl<S: Socket> Host<S> {
         address: S::Address,
         channel_count: usize,
         data: u32,
-    ) -> Result<&mut Peer<S>, NoAvailablePeers> {
+    ) -> Result<PendingConnection, NoAvailablePeers> {
         unsafe {
             let peer = enet_host_connect(self.host, address, channel_count, data);
             if !peer.is_null() {
-                Ok(self.peer_mut(self.peer_index(peer)))
+                Ok(PendingConnection::new(self.peer_index(peer)))
             } else {
                 Err(NoAvailablePeers)
             }
         }
     }
 
+    /// Initiates a connection like [`Host::connect`], also sending `payload` to the foreign host,
+    /// for data which doesn't fit in `data`, like authentication tokens or version strings.
+    /// Retrieved with [`Event::Connect`].
+    ///
+    /// The payload is sent alongside the connect command, and again each time it is resent, so it
+    /// doesn't cost an extra round trip. It requires [`ProtocolVersion::Extended`] on both hosts:
+    /// other hosts receive an empty payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConnectError::PayloadTooLarge`] if `payload` is longer than
+    /// [`PEER_MAXIMUM_CONNECT_PAYLOAD`](`crate::consts::PEER_MAXIMUM_CONNECT_PAYLOAD`) bytes, or
+    /// [`ConnectError::NoAvailablePeers`] if all peer slots have been filled.
+    pub fn connect_with_payload(
+        &mut self,
+        address: S::Address,
+        channel_count: usize,
+        data: u32,
+        payload: &[u8],
+    ) -> Result<PendingConnection, ConnectError> {
+        if payload.len() > PEER_MAXIMUM_CONNECT_PAYLOAD {
+            return Err(ConnectError::PayloadTooLarge);
+        }
+        let pending = self
+            .connect(address, channel_count, data)
+            .map_err(|NoAvailablePeers| ConnectError::NoAvailablePeers)?;
+        unsafe {
+            let peer = self.peers[pending.peer_id().index].0;
+            (*peer)
+                .connect_payload
+                .assume_init_mut()
+                .extend_from_slice(payload);
+            enet_peer_send_connect_payload(peer);
+        }
+        Ok(pending)
+    }
+
+    /// Connects the host to itself, for a local player on the same host as the server, as in a
+    /// listen server. Datagrams between the two peers are passed along in memory rather than
+    /// through the socket, and are handled by [`Host::service`] like any other.
+    ///
+    /// The connection uses two peer slots: the returned connection's peer, which acts as the
+    /// client, and the peer accepting it, which acts as the server's view of the local player.
+    /// Both generate the usual events, and both report `address` as their address, such as the
+    /// address the host's socket is bound to. See [`Peer::is_loopback`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NoAvailablePeers`] if all peer slots have been filled.
+    pub fn connect_loopback(
+        &mut self,
+        address: S::Address,
+        channel_count: usize,
+        data: u32,
+    ) -> Result<PendingConnection, NoAvailablePeers> {
+        let pending = self.connect(address, channel_count, data)?;
+        unsafe {
+            (*self.peers[pending.peer_id().index].0).loopback = true;
+        }
+        Ok(pending)
+    }
+
+    /// Initiates a connection to several addresses of the same foreign host, keeping whichever
+    /// completes the handshake first, as with Happy Eyeballs (RFC 8305). Useful when a hostname
+    /// resolves to both IPv6 and IPv4 addresses, and one of the address families is broken.
+    ///
+    /// `addresses` should be in order of preference, such as IPv6 and IPv4 addresses alternating,
+    /// starting with IPv6. The first is attempted immediately, and each of the others during
+    /// [`Host::service`], [`HOST_CONNECT_RACE_DELAY`](`crate::consts::HOST_CONNECT_RACE_DELAY`)
+    /// milliseconds after the one before it, or as soon as every attempt so far has failed. Each
+    /// attempt in progress uses a peer slot.
+    ///
+    /// Once an attempt connects and [`Event::Connect`] is returned for it, the others are
+    /// cancelled with [`Peer::disconnect_now`]. Failed attempts don't generate
+    /// [`Event::Disconnect`], unless they were the race's last.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NoAvailablePeers`] if all peer slots have been filled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `addresses` is empty.
+    pub fn connect_racing(
+        &mut self,
+        addresses: impl IntoIterator<Item = S::Address>,
+        channel_count: usize,
+        data: u32,
+    ) -> Result<RacingConnection, NoAvailablePeers> {
+        let mut addresses: VecDeque<S::Address> = addresses.into_iter().collect();
+        let address = addresses
+            .pop_front()
+            .expect("Expected at least one address to connect to.");
+        let pending = self.connect(address, channel_count, data)?;
+        let next_attempt = self.now() + Duration::from_millis(u64::from(HOST_CONNECT_RACE_DELAY));
+        Ok(self.races_mut().start(
+            pending.peer_id(),
+            addresses,
+            channel_count,
+            data,
+            next_attempt,
+        ))
+    }
+
     /// Checks for any queued events on the host and dispatches one if available.
-    pub fn check_events(&mut self) -> Option<Event<S>> {
+    pub fn check_events(&mut self) -> Option<Event<'_, S>> {
         unsafe {
+            self.expire_calls();
+            self.advance_races();
             let mut event: ENetEvent<S> = zeroed();
-            if enet_host_check_events(self.host, &mut event) {
-                Some(self.create_event(&event))
-            } else {
-                None
+            loop {
+                if let Some(reply) = self.rpc_mut().next_reply() {
+                    return Some(self.create_reply_event(reply));
+                }
+                if let Some(delivery) = self.fec_mut().next_delivery() {
+                    return Some(self.create_delivery_event(delivery));
+                }
+                if !enet_host_check_events(self.host, &raw mut event) {
+                    return None;
+                }
+                if !self.handle_rpc_event(&event)
+                    && !self.handle_fec_event(&event)
+                    && !self.handle_race_event(&event)
+                {
+                    return Some(self.create_event(&event));
+                }
             }
         }
     }
@@ -183,26 +854,150 @@ impl<S: Socket> Host<S> {
     ///
     /// # Errors
     ///
-    /// Returns any error reported by the socket implementation during [`Socket::receive`] or
-    /// [`Socket::send`].
-    pub fn service(&mut self) -> Result<Option<Event<S>>, S::Error> {
+    /// Returns a [`ServiceError`] if the socket implementation fails to receive or send, with the
+    /// [`ServiceStage`](`crate::error::ServiceStage`) that failed and the error it reported. The
+    /// host can be serviced again afterwards.
+    pub fn service(&mut self) -> Result<Option<Event<'_, S>>, ServiceError<S>> {
         unsafe {
+            self.expire_calls();
+            self.advance_races();
             let mut event: ENetEvent<S> = zeroed();
-            if enet_host_service(self.host, &mut event)? {
-                Ok(Some(self.create_event(&event)))
-            } else {
-                Ok(None)
+            loop {
+                if let Some(reply) = self.rpc_mut().next_reply() {
+                    return Ok(Some(self.create_reply_event(reply)));
+                }
+                if let Some(delivery) = self.fec_mut().next_delivery() {
+                    return Ok(Some(self.create_delivery_event(delivery)));
+                }
+                if !enet_host_service(self.host, &raw mut event)? {
+                    return Ok(None);
+                }
+                if !self.handle_rpc_event(&event)
+                    && !self.handle_fec_event(&event)
+                    && !self.handle_race_event(&event)
+                {
+                    return Ok(Some(self.create_event(&event)));
+                }
+            }
+        }
+    }
+
+    /// Service the host like [`Host::service`] until no events remain, appending them to
+    /// `events`, so the buffer can be reused from one call to the next.
+    ///
+    /// ```
+    /// # use std::convert::Infallible;
+    /// # use rusty_enet::{Host, HostSettings, ReadWrite};
+    /// # let mut host = Host::new(ReadWrite::<(), Infallible>::new(), HostSettings::default()).unwrap();
+    /// let mut events = Vec::new();
+    /// host.service_all(&mut events).unwrap();
+    /// for event in events.drain(..) {
+    ///     dbg!(event);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reported by the socket implementation, as with [`Host::service`]. Events
+    /// generated before the error are still appended.
+    pub fn service_all(&mut self, events: &mut Vec<EventNoRef>) -> Result<(), ServiceError<S>> {
+        while let Some(event) = self.service()? {
+            events.push(event.no_ref());
+        }
+        Ok(())
+    }
+
+    /// Service the host like [`Host::service`], but return after `max_events` events or
+    /// `max_datagrams` received datagrams, so a burst of traffic can't hold up the caller.
+    ///
+    /// Datagrams past the budget are left queued in the socket for the next call. Returns the
+    /// events generated, and whether the budget ran out, in which case more work may remain.
+    ///
+    /// ```
+    /// # use std::convert::Infallible;
+    /// # use rusty_enet::{Host, HostSettings, ReadWrite};
+    /// # let mut host = Host::new(ReadWrite::<(), Infallible>::new(), HostSettings::default()).unwrap();
+    /// // handle at most 64 events or 256 datagrams per frame
+    /// let service = host.service_budget(64, 256).unwrap();
+    /// for event in service.events {
+    ///     dbg!(event);
+    /// }
+    /// if service.work_remaining {
+    ///     // service again next frame, or after other work
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reported by the socket implementation, as with [`Host::service`]. Events
+    /// generated before the error are dropped.
+    pub fn service_budget(
+        &mut self,
+        max_events: usize,
+        max_datagrams: usize,
+    ) -> Result<BudgetedService, ServiceError<S>> {
+        unsafe {
+            (*self.host).datagram_budget = max_datagrams;
+        }
+        let mut events = Vec::new();
+        let result = loop {
+            if events.len() >= max_events {
+                break Ok(());
             }
+            match self.service() {
+                Ok(Some(event)) => events.push(event.no_ref()),
+                Ok(None) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+        let datagrams_exhausted = unsafe { (*self.host).datagram_budget == 0 };
+        unsafe {
+            (*self.host).datagram_budget = usize::MAX;
         }
+        result?;
+        Ok(BudgetedService {
+            work_remaining: events.len() >= max_events || datagrams_exhausted,
+            events,
+        })
     }
 
     /// Sends any queued packets on the host specified to its designated peers.
-    pub fn flush(&mut self) {
+    ///
+    /// Returns the errors for any datagrams the socket failed to send. A failure doesn't stop the
+    /// remaining peers from being sent to. Errors from flushes without a return value, such as
+    /// [`Peer::flush`], are returned here or by the next [`Host::service`].
+    pub fn flush(&mut self) -> Vec<ServiceError<S>> {
         unsafe {
             enet_host_flush(self.host);
+            (*self.host)
+                .send_errors
+                .assume_init_mut()
+                .drain(..)
+                .collect()
         }
     }
 
+    /// Disconnect every peer immediately with `data`, like [`Peer::disconnect_now`], and drop the
+    /// host, regardless of [`HostSettings::disconnect_on_drop`].
+    ///
+    /// Disconnections are sent on a best-effort basis, unreliably, in a single flush. To make sure
+    /// final messages arrive first, wait for [`Host::all_flushed`].
+    pub fn close(self, data: u32) {
+        unsafe { (*self.host).disconnect_on_drop = Some(data) }
+    }
+
+    /// Whether nothing is left to send to any peer, with every reliable packet acknowledged, see
+    /// [`Peer::has_queued_outgoing`].
+    ///
+    /// Useful for sending final messages before quitting: keep servicing the host until this
+    /// returns `true`, and it's safe to drop.
+    #[must_use]
+    pub fn all_flushed(&self) -> bool {
+        let datagrams_sent =
+            unsafe { (*self.host).outgoing_datagrams.assume_init_ref().is_empty() };
+        datagrams_sent && self.peers.iter().all(|peer| !peer.has_queued_outgoing())
+    }
+
     /// Get a reference to the underlying socket.
     #[must_use]
     pub fn socket(&self) -> &S {
@@ -220,57 +1015,56 @@ impl<S: Socket> Host<S> {
         self.peers.len()
     }
 
-    /// Get a reference to a single peer, if it exists.
+    /// Get a reference to a single peer, or [`None`] if the ID is out of bounds, or the peer's
+    /// slot has since been reused by another connection. See [`PeerID`].
     ///
     /// # Note
     ///
     /// Acquires the peer object, even if the peer is not in a connected state. See [`Peer::state`].
-    ///
-    /// # Panics
-    ///
-    /// Panics if the peer ID is outside the bounds of peers allocated for this host. Use
-    /// [`Host::get_peer`] for a non-panicking version.
     #[must_use]
-    pub fn peer(&self, peer: PeerID) -> &Peer<S> {
+    pub fn peer(&self, peer: PeerID) -> Option<&Peer<S>> {
         self.peers
-            .get(peer.0)
-            .expect("Expected the peer id to be in bounds.")
+            .get(peer.index)
+            .filter(|current| current.id() == peer)
     }
 
-    /// Get a reference to a single peer.
-    ///
-    /// # Note
-    ///
-    /// Acquires the peer object, even if the peer is not in a connected state. See [`Peer::state`].
+    /// Get a reference to a single peer, or [`None`] if the ID is out of bounds or stale.
+    #[deprecated(note = "use `Host::peer`, which returns `None` for invalid IDs")]
     #[must_use]
     pub fn get_peer(&self, peer: PeerID) -> Option<&Peer<S>> {
-        self.peers.get(peer.0)
+        self.peer(peer)
     }
 
-    /// Get a mutable reference to a single peer.
+    /// Get a mutable reference to a single peer, or [`None`] if the ID is out of bounds, or the
+    /// peer's slot has since been reused by another connection. See [`PeerID`].
     ///
     /// # Note
     ///
     /// Acquires the peer object, even if the peer is not in a connected state. See [`Peer::state`].
-    ///
-    /// # Panics
-    ///
-    /// Panics if the peer ID is outside the bounds of peers allocated for this host. Use
-    /// [`Host::get_peer_mut`] for a non-panicking version.
-    pub fn peer_mut(&mut self, peer: PeerID) -> &mut Peer<S> {
+    #[must_use]
+    pub fn peer_mut(&mut self, peer: PeerID) -> Option<&mut Peer<S>> {
         self.peers
-            .get_mut(peer.0)
-            .expect("Expected the peer id to be in bounds.")
+            .get_mut(peer.index)
+            .filter(|current| current.id() == peer)
     }
 
-    /// Get a mutable reference to a single peer, if it exists.
-    ///
-    /// # Note
-    ///
-    /// Acquires the peer object, even if the peer is not in a connected state. See [`Peer::state`].
+    /// Get a mutable reference to a single peer, or [`None`] if the ID is out of bounds or stale.
+    #[deprecated(note = "use `Host::peer_mut`, which returns `None` for invalid IDs")]
     #[must_use]
     pub fn get_peer_mut(&mut self, peer: PeerID) -> Option<&mut Peer<S>> {
-        self.peers.get_mut(peer.0)
+        self.peer_mut(peer)
+    }
+
+    /// Check whether a peer ID still refers to a peer of this host, so IDs kept across frames can
+    /// be checked before use. IDs are invalid if they are out of bounds, or the peer's slot has
+    /// since been reused by another connection, in which case [`Host::peer`] returns [`None`]. See
+    /// [`PeerID`].
+    ///
+    /// Peers which disconnected keep a valid ID until their slot is reused. Check
+    /// [`Peer::state`] to tell whether they are still connected.
+    #[must_use]
+    pub fn is_valid(&self, peer: PeerID) -> bool {
+        self.peer(peer).is_some()
     }
 
     /// Iterate over all peer objects.
@@ -307,6 +1101,100 @@ impl<S: Socket> Host<S> {
             .filter(|peer| peer.state() == PeerState::Connected)
     }
 
+    /// Queues a copy of `data` to be sent to a peer on the specified channel, without creating a
+    /// [`Packet`] first. See [`Peer::send_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// May return any of the [`PeerSendError`] variants on failure, including
+    /// [`PeerSendError::InvalidPeer`] if the peer ID is out of range or stale.
+    pub fn send_bytes(
+        &mut self,
+        peer: PeerID,
+        channel_id: u8,
+        data: &[u8],
+        kind: PacketKind,
+    ) -> Result<(), PeerSendError> {
+        self.peer_mut(peer)
+            .ok_or(PeerSendError::InvalidPeer)?
+            .send_bytes(channel_id, data, kind)
+    }
+
+    /// Queues many packets to be sent to any of this host's peers, as `(peer, channel_id, packet)`.
+    ///
+    /// The packets are grouped by peer and channel, keeping their order within each group, and
+    /// each group is queued like [`Peer::send_batch`], so each peer and channel is checked once.
+    /// Like any queued packets, those to the same peer are packed into as few datagrams as
+    /// possible during the next [`Host::service`] or [`Host::flush`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BatchSendError`] if any packet fails to queue, in which case the remaining
+    /// packets are not queued. Peer IDs which are out of range or stale fail with
+    /// [`PeerSendError::InvalidPeer`].
+    pub fn send_many(
+        &mut self,
+        packets: impl IntoIterator<Item = (PeerID, u8, Packet)>,
+    ) -> Result<(), BatchSendError> {
+        let packets: Vec<(PeerID, u8, Packet)> = packets.into_iter().collect();
+        // the first packet which can't be queued, and why
+        let mut failure = None;
+        let mut batches = BTreeMap::new();
+        for (index, (peer, channel_id, _)) in packets.iter().enumerate() {
+            let Some(peer) = self.peer_mut(*peer) else {
+                failure = Some((index, PeerSendError::InvalidPeer));
+                break;
+            };
+            batches
+                .entry((peer.id().index, *channel_id))
+                .or_insert_with(|| (peer.0, Vec::new()))
+                .1
+                .push(index);
+        }
+        let raw_packets = |batch: &[usize]| -> Vec<*mut ENetPacket> {
+            batch.iter().map(|&index| packets[index].2.packet).collect()
+        };
+        // every batch is checked before any is queued, so nothing after a failure is queued
+        for (&(_, channel_id), (peer, batch)) in &batches {
+            if let Err((position, error)) =
+                unsafe { enet_peer_check_batch(*peer, channel_id, &raw_packets(batch)) }
+            {
+                let index = batch[position];
+                if failure.is_none_or(|(failed, _)| index < failed) {
+                    failure = Some((index, error));
+                }
+            }
+        }
+        let limit = failure.map_or(packets.len(), |(index, _)| index);
+        for (&(_, channel_id), (peer, batch)) in &batches {
+            let count = batch.partition_point(|&index| index < limit);
+            if let Err((position, error)) =
+                unsafe { enet_peer_queue_batch(*peer, channel_id, &raw_packets(&batch[..count])) }
+            {
+                let index = batch[position];
+                if failure.is_none_or(|(failed, _)| index < failed) {
+                    failure = Some((index, error));
+                }
+            }
+        }
+        failure.map_or(Ok(()), |(sent, error)| Err(BatchSendError { sent, error }))
+    }
+
+    /// Register the handler for remote calls to `method_id` made with [`Peer::call`], replacing
+    /// any existing handler for that method.
+    ///
+    /// The handler is called during [`Host::service`] with the calling peer and the call's
+    /// payload, and its return value is sent back as the reply. Requires
+    /// [`HostSettings::rpc_channel`].
+    pub fn register_handler(
+        &mut self,
+        method_id: u16,
+        handler: impl FnMut(PeerID, &[u8]) -> Vec<u8> + Send + 'static,
+    ) {
+        self.rpc_mut()
+            .register_handler(method_id, Box::new(handler));
+    }
+
     /// Queues a packet to be sent to all peers.
     pub fn broadcast(&mut self, channel_id: u8, packet: &Packet) {
         unsafe {
@@ -314,6 +1202,81 @@ impl<S: Socket> Host<S> {
         }
     }
 
+    /// Send `data` as is to each of `addresses` through the host's socket, bypassing ENet's
+    /// protocol, such as for server list heartbeats or LAN announcements.
+    ///
+    /// Datagrams are sent immediately, up to
+    /// [`HostSettings::raw_datagram_rate`], and the rest are sent by later calls to
+    /// [`Host::service`]. Transforms added with [`Host::add_transform`] aren't applied, and
+    /// socket errors are returned by [`Host::service`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BadParameter`] if `data` is longer than [`MTU_MAX`](`crate::MTU_MAX`).
+    pub fn broadcast_raw(
+        &mut self,
+        addresses: &[S::Address],
+        data: &[u8],
+    ) -> Result<(), BadParameter> {
+        if data.len() > PROTOCOL_MAXIMUM_MTU {
+            return Err(BadParameter {
+                method: "Host::broadcast_raw",
+                parameter: "data",
+            });
+        }
+        unsafe {
+            let raw_datagrams = (*self.host).raw_datagrams.assume_init_mut();
+            raw_datagrams.extend(
+                addresses
+                    .iter()
+                    .map(|address| (address.clone(), data.to_vec())),
+            );
+            enet_host_send_raw_datagrams(self.host, enet_time_get(self.host));
+        }
+        Ok(())
+    }
+
+    /// Drop every datagram from the host at `address` for `duration`, including connection
+    /// requests, before they are parsed. Banning an address again replaces its ban.
+    ///
+    /// Bans cover every address on the same host, as compared by
+    /// [`Address::same_host`](`crate::Address::same_host`). Bans are looked up by
+    /// [`Address::host_key`](`crate::Address::host_key`), and addresses without a key are
+    /// compared against every such ban instead. Peers already connected from the
+    /// address aren't disconnected, and will time out unless disconnected with
+    /// [`Peer::disconnect_now`]. Expired bans are forgotten during [`Host::service`].
+    pub fn ban(&mut self, address: S::Address, duration: Duration) {
+        unsafe { enet_host_ban(self.host, address, duration) }
+    }
+
+    /// Lift a ban placed with [`Host::ban`], returning `false` if the address wasn't banned.
+    pub fn unban(&mut self, address: &S::Address) -> bool {
+        unsafe { enet_host_unban(self.host, address) }
+    }
+
+    /// Check if datagrams from `address` are dropped, due to a ban placed with [`Host::ban`]
+    /// which hasn't expired.
+    #[must_use]
+    pub fn is_banned(&self, address: &S::Address) -> bool {
+        unsafe { enet_host_is_banned(self.host, address) }
+    }
+
+    /// Set a callback to report the outcome of every incoming connection attempt to, along with
+    /// its source address, such as to feed tools which ban abusive addresses. See
+    /// [`ConnectOutcome`].
+    ///
+    /// The callback is called during [`Host::service`], [`Peer::accept`] and [`Peer::reject`],
+    /// and must not block for long.
+    #[allow(clippy::type_complexity)]
+    pub fn set_connect_audit(
+        &mut self,
+        audit: Option<Box<dyn FnMut(&S::Address, ConnectOutcome) + Send + Sync>>,
+    ) {
+        unsafe {
+            *(*self.host).connect_audit.assume_init_mut() = audit;
+        }
+    }
+
     /// Get the maximum allowed channels for future incoming connections.
     #[must_use]
     pub fn channel_limit(&self) -> usize {
@@ -421,6 +1384,51 @@ impl<S: Socket> Host<S> {
         Ok(())
     }
 
+    /// Check if this host uses a checksum to verify the integrity of packets. See
+    /// [`Host::set_checksum`].
+    #[must_use]
+    pub fn has_checksum(&self) -> bool {
+        unsafe { (*self.host).checksum.assume_init_ref().is_some() }
+    }
+
+    /// Set the checksum function to use when sending and receiving packets, or [`None`] to
+    /// disable checksums. Packets which fail verification are silently dropped.
+    ///
+    /// Use [`crc32`](`crate::crc32`) for compatibility with C ENet hosts using `enet_crc32`. Both
+    /// hosts must agree on the checksum function, as it changes the size of the protocol header.
+    #[allow(clippy::type_complexity)]
+    pub fn set_checksum(&mut self, checksum: Option<Box<dyn Fn(&[&[u8]]) -> u32 + Send + Sync>>) {
+        unsafe {
+            *(*self.host).checksum.assume_init_mut() = checksum;
+        }
+    }
+
+    /// Set the compressor to use when sending and receiving packets, or [`None`] to disable
+    /// compression. Both hosts must use the same kind of compressor, see
+    /// [`HostSettings::compressor`].
+    pub fn set_compressor(&mut self, compressor: Option<Box<dyn Compressor>>) {
+        unsafe {
+            enet_host_compress(self.host, compressor);
+        }
+    }
+
+    /// Add a transform to the end of this host's datagram pipeline. See [`PacketTransform`].
+    ///
+    /// Outgoing datagrams are encoded by each transform in the order they were added, and incoming
+    /// datagrams are decoded in reverse. Both hosts must use the same transforms in the same order.
+    pub fn add_transform(&mut self, transform: Box<dyn PacketTransform>) {
+        unsafe {
+            (*self.host).transforms.assume_init_mut().push(transform);
+        }
+    }
+
+    /// Remove all transforms added with [`Host::add_transform`].
+    pub fn clear_transforms(&mut self) {
+        unsafe {
+            (*self.host).transforms.assume_init_mut().clear();
+        }
+    }
+
     /// Get the time according to this host, as provided by
     /// [`HostSettings::time`](`crate::HostSettings::time`).
     #[must_use]
@@ -428,33 +1436,263 @@ impl<S: Socket> Host<S> {
         unsafe { (*self.host).time.assume_init_ref()() }
     }
 
+    /// The number of events waiting to be returned by [`Host::service`] without receiving any
+    /// more datagrams. If this keeps growing, events aren't being handled fast enough.
+    ///
+    /// This is an upper bound, as received packets can be consumed by the host, like remote
+    /// calls, or superseded by newer packets. See also [`Peer::incoming_waiting_bytes`].
+    #[must_use]
+    pub fn pending_events(&self) -> usize {
+        unsafe {
+            self.rpc().pending_replies()
+                + self.fec().pending_deliveries()
+                + enet_host_pending_events(self.host)
+        }
+    }
+
+    /// Get statistics for the pool of outgoing commands, one of which is held by each queued
+    /// packet until it is acknowledged. See [`HostSettings::command_pool_limit`].
+    #[must_use]
+    pub fn outgoing_command_pool_stats(&self) -> PoolStats {
+        unsafe { (*self.host).outgoing_command_pool.assume_init_ref().stats() }
+    }
+
+    /// Get statistics for the pool of incoming commands, one of which is held by each received
+    /// packet until it is dispatched. See [`HostSettings::command_pool_limit`].
+    #[must_use]
+    pub fn incoming_command_pool_stats(&self) -> PoolStats {
+        unsafe { (*self.host).incoming_command_pool.assume_init_ref().stats() }
+    }
+
     fn create_event<'a>(&'a mut self, event: &ENetEvent<S>) -> Event<'a, S> {
         match event.type_0 {
-            ENET_EVENT_TYPE_CONNECT => Event::Connect {
-                peer: self.peer_mut(self.peer_index(event.peer)),
-                data: event.data,
-            },
+            ENET_EVENT_TYPE_CONNECT => {
+                let peer = self.event_peer(event.peer);
+                Event::Connect {
+                    data: event.data,
+                    connect_data: peer.connect_data(),
+                    payload: take(unsafe { (*peer.0).connect_payload.assume_init_mut() }),
+                    channel_count: peer.channel_count(),
+                    address: peer
+                        .address()
+                        .expect("Connected peers should have an address."),
+                    peer,
+                }
+            }
+            ENET_EVENT_TYPE_CONNECT_REQUEST => {
+                let peer = self.event_peer(event.peer);
+                Event::ConnectRequest {
+                    data: event.data,
+                    payload: unsafe { (*peer.0).connect_payload.assume_init_ref() }.clone(),
+                    channel_count: peer.channel_count(),
+                    address: peer
+                        .address()
+                        .expect("Connecting peers should have an address."),
+                    peer,
+                }
+            }
             ENET_EVENT_TYPE_DISCONNECT => Event::Disconnect {
-                peer: self.peer_mut(self.peer_index(event.peer)),
+                peer: self.event_peer(event.peer),
                 data: event.data,
             },
             ENET_EVENT_TYPE_RECEIVE => Event::Receive {
-                peer: self.peer_mut(self.peer_index(event.peer)),
+                peer: self.event_peer(event.peer),
                 channel_id: event.channel_id,
-                packet: Packet::new_from_ptr(event.packet),
+                packet: Packet::from_received(event.packet),
+                received_at: event.time,
             },
+            ENET_EVENT_TYPE_ACKNOWLEDGE => Event::Acknowledge {
+                peer: self.event_peer(event.peer),
+                channel_id: event.channel_id,
+                tag: event.tag,
+                queued_at: event.time,
+            },
+            ENET_EVENT_TYPE_DELIVERY_FAILED => Event::DeliveryFailed {
+                peer: self.event_peer(event.peer),
+                channel_id: event.channel_id,
+                tag: event.tag,
+            },
+            ENET_EVENT_TYPE_PEER_IDLE => Event::PeerIdle {
+                peer: self.event_peer(event.peer),
+                idle_for: event.time,
+            },
+            ENET_EVENT_TYPE_PACKET_RESENT => Event::PacketResent {
+                peer: self.event_peer(event.peer),
+                channel_id: event.channel_id,
+                attempts: event.data,
+            },
+            ENET_EVENT_TYPE_PROTOCOL_VIOLATION => {
+                let (address, kind) = unsafe { (*self.host).protocol_violation.assume_init_mut() }
+                    .take()
+                    .expect("Protocol violation events should have a violation.");
+                Event::ProtocolViolation { address, kind }
+            }
             _ => unreachable!(),
         }
     }
 
-    fn peer_index(&self, peer: *const ENetPeer<S>) -> PeerID {
-        PeerID(unsafe { peer.offset_from((*self.host).peers) as usize })
+    /// Fail remote calls past their deadline, only reading the clock if any are pending.
+    fn expire_calls(&mut self) {
+        if self.rpc().has_pending() {
+            let now = self.now();
+            self.rpc_mut().expire(now);
+        }
+    }
+
+    fn rpc(&self) -> &RpcState {
+        unsafe { (*self.host).rpc.assume_init_ref() }
+    }
+
+    fn rpc_mut(&mut self) -> &mut RpcState {
+        unsafe { (*self.host).rpc.assume_init_mut() }
+    }
+
+    /// Handle remote calls and replies received on the RPC channel, returning `true` if the event
+    /// was consumed.
+    fn handle_rpc_event(&mut self, event: &ENetEvent<S>) -> bool {
+        let Some(rpc_channel) = self.rpc().channel_id else {
+            return false;
+        };
+        match event.type_0 {
+            ENET_EVENT_TYPE_RECEIVE if event.channel_id == rpc_channel => {
+                let peer = self.peer_index(event.peer);
+                let packet = Packet::from_received(event.packet);
+                if let Some(response) = self.rpc_mut().receive(peer, packet.data()) {
+                    if let Some(peer) = self.peer_mut(peer) {
+                        _ = peer.send(rpc_channel, &response);
+                    }
+                }
+                true
+            }
+            ENET_EVENT_TYPE_DISCONNECT => {
+                let peer = self.peer_index(event.peer);
+                self.rpc_mut().disconnect(peer);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn races(&self) -> &RaceState<S::Address> {
+        unsafe { (*self.host).races.assume_init_ref() }
+    }
+
+    fn races_mut(&mut self) -> &mut RaceState<S::Address> {
+        unsafe { (*self.host).races.assume_init_mut() }
+    }
+
+    /// Start the next attempt of each connection race which is due one, see
+    /// [`Host::connect_racing`], only reading the clock if any have addresses left.
+    fn advance_races(&mut self) {
+        // the races live in the ENet host, apart from the peer list borrowed to check them
+        let peers = &self.peers;
+        unsafe { (*self.host).races.assume_init_mut() }.retain(|peer| {
+            peers
+                .get(peer.index)
+                .is_some_and(|current| current.id() == peer)
+        });
+        if !self.races().has_pending() {
+            return;
+        }
+        let now = self.now();
+        let delay = Duration::from_millis(u64::from(HOST_CONNECT_RACE_DELAY));
+        while let Some(attempt) = self.races_mut().next_attempt(now, delay) {
+            let peer = unsafe {
+                enet_host_connect(
+                    self.host,
+                    attempt.address,
+                    attempt.channel_count,
+                    attempt.data,
+                )
+            };
+            if !peer.is_null() {
+                let peer = self.peer_index(peer);
+                self.races_mut().track(attempt.race, peer);
+            }
+        }
+    }
+
+    /// Cancel the other attempts of a connection race once one connects, returning `true` if the
+    /// event was consumed, as it is for attempts which failed while others remain.
+    fn handle_race_event(&mut self, event: &ENetEvent<S>) -> bool {
+        match event.type_0 {
+            ENET_EVENT_TYPE_CONNECT => {
+                let peer = self.peer_index(event.peer);
+                for attempt in self.races_mut().connect(peer) {
+                    if let Some(peer) = self.peer_mut(attempt) {
+                        peer.disconnect_now(0);
+                    }
+                }
+                false
+            }
+            ENET_EVENT_TYPE_DISCONNECT => {
+                let peer = self.peer_index(event.peer);
+                self.races_mut().disconnect(peer)
+            }
+            _ => false,
+        }
+    }
+
+    fn fec(&self) -> &FecState {
+        unsafe { (*self.host).fec.assume_init_ref() }
+    }
+
+    fn fec_mut(&mut self) -> &mut FecState {
+        unsafe { (*self.host).fec.assume_init_mut() }
+    }
+
+    /// Decode packets received on channels using forward error correction, returning `true` if
+    /// the event was consumed. Their data, and any packets recovered, are returned as
+    /// [`Event::Receive`] by [`Host::service`] afterwards.
+    fn handle_fec_event(&mut self, event: &ENetEvent<S>) -> bool {
+        if event.type_0 != ENET_EVENT_TYPE_RECEIVE
+            || self.fec().group_size(event.channel_id).is_none()
+        {
+            return false;
+        }
+        let peer = self.peer_index(event.peer);
+        let packet = Packet::from_received(event.packet);
+        self.fec_mut()
+            .receive(peer, event.channel_id, &packet, event.time);
+        true
+    }
+
+    fn create_delivery_event(&mut self, delivery: FecDelivery) -> Event<'_, S> {
+        Event::Receive {
+            peer: &mut self.peers[delivery.peer.index],
+            channel_id: delivery.channel_id,
+            packet: delivery.packet,
+            received_at: delivery.received_at,
+        }
+    }
+
+    fn create_reply_event(&mut self, reply: RpcReply) -> Event<'_, S> {
+        Event::Reply {
+            // the reply is for the slot the call was made to, even if its peer has since changed
+            peer: &mut self.peers[reply.peer.index],
+            call: reply.call,
+            result: reply.result,
+        }
+    }
+
+    fn peer_index(&self, peer: *mut ENetPeer<S>) -> PeerID {
+        unsafe { enet_peer_id(peer) }
+    }
+
+    fn event_peer(&mut self, peer: *mut ENetPeer<S>) -> &mut Peer<S> {
+        let index = unsafe { enet_peer_id(peer) }.index;
+        &mut self.peers[index]
     }
 }
 
 impl<S: Socket> Drop for Host<S> {
     fn drop(&mut self) {
-        unsafe { enet_host_destroy(self.host) }
+        unsafe {
+            if let Some(data) = (*self.host).disconnect_on_drop {
+                enet_host_disconnect_now(self.host, data);
+            }
+            enet_host_destroy(self.host);
+        }
     }
 }
 
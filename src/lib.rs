@@ -65,6 +65,13 @@
 //! packets in the sequence have not yet arrived, ENet will stall delivery of the higher sequence
 //! number packets until its predecessors have arrived.
 //!
+//! Unreliable packets may also be sent unsequenced, with
+//! [`PacketKind::Unreliable { sequenced: false }`](`PacketKind::Unreliable`). Unsequenced packets
+//! are delivered in whatever order they arrive, and are only filtered for duplicates. Each peer
+//! tracks the most recent unsequenced groups it has received in a sliding window of
+//! [`HostSettings::unsequenced_window_size`] groups, so an unsequenced packet arriving after more
+//! than that many newer ones is discarded. See [`Peer::incoming_unsequenced_group`].
+//!
 //! ## Channels
 //!
 //! Since ENet will stall delivery of reliable packets to ensure proper sequencing, and consequently
@@ -106,6 +113,21 @@
 //! capabilities. Further, ENet also provides a dynamic throttle that responds to deviations from
 //! normal network connections to rectify various types of network congestion by further limiting
 //! the volume of packets sent.
+//!
+//! ## Threading
+//!
+//! A [`Host`] is [`Send`] when its [`Socket`] is, and [`Sync`] when its socket is, so it can be
+//! moved to a dedicated network thread, or shared behind a lock. Everything stored on the host,
+//! like its [`Compressor`], [`PacketTransform`]s and [`CongestionController`], must be [`Send`]
+//! for this to hold. A host is never serviced from multiple threads at once, as
+//! [`Host::service`] and anything else touching peers requires `&mut self`.
+//!
+//! [`Packet`]s use atomic reference counts, so a packet can be built on one thread and sent to
+//! peers, or dropped, while the host's thread still holds references to it.
+//!
+//! The usual pattern is to own the host on its own thread, which calls [`Host::service`] in a
+//! loop, and to exchange packets and events with the rest of the program over channels.
+//! [`HostHandle`] does exactly this, with the `std` feature.
 
 #![no_std]
 #![cfg_attr(docsrs, feature(doc_cfg))]
@@ -141,35 +163,98 @@ extern crate std;
 extern crate alloc;
 
 mod address;
+mod allocator;
 mod c;
 mod compressor;
+mod congestion;
 mod crc32;
+#[cfg(feature = "crypto")]
+mod crypto;
 mod event;
+mod fec;
+#[cfg(feature = "std")]
+mod handle;
+#[cfg(feature = "std")]
+mod handler;
 mod host;
+#[cfg(feature = "std")]
+mod memory;
+#[cfg(feature = "serde")]
+mod message;
+#[cfg(feature = "noise")]
+mod noise;
 mod packet;
 mod peer;
+mod race;
+mod random;
 mod read_write;
+mod rpc;
+#[cfg(all(feature = "sharding", target_os = "linux"))]
+mod shard;
 mod socket;
+mod stream;
 mod time;
+mod transform;
 mod version;
 
 pub use address::*;
+pub use allocator::*;
 pub(crate) use c::*;
 pub use compressor::*;
+pub use congestion::*;
 pub use crc32::*;
+#[cfg(feature = "crypto")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+pub use crypto::*;
 pub use event::*;
+pub(crate) use fec::*;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use handle::*;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use handler::*;
 pub use host::*;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use memory::*;
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub use message::*;
+#[cfg(feature = "noise")]
+#[cfg_attr(docsrs, doc(cfg(feature = "noise")))]
+pub use noise::*;
 pub use packet::*;
 pub use peer::*;
+pub use race::*;
+pub use random::*;
 pub use read_write::*;
+pub use rpc::*;
+#[cfg(all(feature = "sharding", target_os = "linux"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "sharding", target_os = "linux"))))]
+pub use shard::*;
 pub use socket::*;
+pub use stream::*;
 pub use time::*;
+pub use transform::*;
 pub use version::*;
 
+#[cfg(feature = "bevy")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bevy")))]
+pub mod bevy;
+#[cfg(feature = "capi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "capi")))]
+pub mod capi;
 #[cfg(any(feature = "connected", doc))]
 #[cfg_attr(docsrs, doc(cfg(feature = "connected")))]
 pub mod connected;
 pub mod error;
+#[cfg(feature = "fuzzing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fuzzing")))]
+pub mod fuzzing;
+#[cfg(any(feature = "proptest", feature = "arbitrary"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "proptest", feature = "arbitrary"))))]
+pub mod testing;
 
 /// Constants provided by ENet.
 #[allow(missing_docs)]
@@ -180,7 +265,15 @@ pub mod consts;
 mod test;
 
 #[cfg(feature = "std")]
-pub(crate) use std::{boxed::Box, collections::VecDeque, vec::Vec};
+pub(crate) use std::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+    vec::Vec,
+};
 
 #[cfg(not(feature = "std"))]
-pub(crate) use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
+pub(crate) use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+    vec::Vec,
+};
@@ -0,0 +1,166 @@
+use core::convert::Infallible;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::{
+    error::{HostNewError, ServiceError},
+    Event, Host, HostSettings, PacketReceived, PeerID, Socket, SocketOptions, Vec, VecDeque,
+    MTU_MAX,
+};
+
+type Queue = Arc<Mutex<VecDeque<Vec<u8>>>>;
+
+/// A [`Socket`] connected in memory to the other socket of a pair, created with
+/// [`MemorySocket::pair`], for tests and examples which don't need a real network.
+///
+/// Datagrams are delivered as soon as they are sent, in order, and never lost. Each socket only
+/// reaches the other, so its address is `()`. The sockets can be moved to different threads.
+#[derive(Debug)]
+pub struct MemorySocket {
+    inbound: Queue,
+    outbound: Queue,
+}
+
+impl MemorySocket {
+    /// Create two sockets, each receiving the datagrams sent by the other.
+    #[must_use]
+    pub fn pair() -> (Self, Self) {
+        let first = Queue::default();
+        let second = Queue::default();
+        (
+            Self {
+                inbound: first.clone(),
+                outbound: second.clone(),
+            },
+            Self {
+                inbound: second,
+                outbound: first,
+            },
+        )
+    }
+}
+
+impl Socket for MemorySocket {
+    type Address = ();
+    type Error = Infallible;
+
+    fn init(&mut self, _socket_options: SocketOptions) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn send(&mut self, _address: (), buffer: &[u8]) -> Result<usize, Infallible> {
+        self.outbound
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push_back(buffer.to_vec());
+        Ok(buffer.len())
+    }
+
+    fn receive(
+        &mut self,
+        buffer: &mut [u8; MTU_MAX],
+    ) -> Result<Option<((), PacketReceived)>, Infallible> {
+        let inbound = self
+            .inbound
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_front();
+        Ok(inbound
+            .filter(|datagram| datagram.len() <= MTU_MAX)
+            .map(|datagram| {
+                buffer[..datagram.len()].copy_from_slice(&datagram);
+                ((), PacketReceived::Complete(datagram.len()))
+            }))
+    }
+}
+
+/// Two hosts connected to each other in memory, created with [`Host::create_pair`].
+#[derive(Debug)]
+pub struct HostPair {
+    /// The host which connected to [`HostPair::server`].
+    pub client: Host<MemorySocket>,
+    /// The client's peer for the server.
+    pub client_peer: PeerID,
+    /// The host which accepted the connection from [`HostPair::client`].
+    pub server: Host<MemorySocket>,
+    /// The server's peer for the client.
+    pub server_peer: PeerID,
+}
+
+impl Host<MemorySocket> {
+    /// Create two hosts connected over a pair of [`MemorySocket`]s, with a peer each already
+    /// connected to the other, to cut the setup out of tests and examples.
+    ///
+    /// `settings` is called once for each host. The client connects with as many channels as its
+    /// [`HostSettings::channel_limit`], and connection requests to servers using
+    /// [`HostSettings::connect_approval`] are accepted. The connect events are consumed.
+    ///
+    /// ```
+    /// use rusty_enet::{Event, Host, HostSettings, Packet};
+    ///
+    /// let mut pair = Host::create_pair(HostSettings::default).unwrap();
+    /// pair.client
+    ///     .peer_mut(pair.client_peer)
+    ///     .unwrap()
+    ///     .send(0, &Packet::reliable(b"hello"))
+    ///     .unwrap();
+    /// pair.client.flush();
+    /// let Some(Event::Receive { packet, .. }) = pair.server.service().unwrap() else {
+    ///     panic!("Expected the packet.");
+    /// };
+    /// assert_eq!(packet.data(), b"hello");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostNewError`] if either host could not be created with its settings.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the hosts fail to connect, which only happens if the settings prevent it.
+    pub fn create_pair(
+        settings: impl Fn() -> HostSettings,
+    ) -> Result<HostPair, HostNewError<MemorySocket>> {
+        let (client_socket, server_socket) = MemorySocket::pair();
+        let client_settings = settings();
+        let channel_count = client_settings.channel_limit;
+        let mut client = Host::new(client_socket, client_settings)?;
+        let mut server = Host::new(server_socket, settings())?;
+        _ = client.connect((), channel_count, 0);
+        let mut client_peer = None;
+        let mut server_peer = None;
+        for _ in 0..16 {
+            while let Some(event) = service(&mut client) {
+                if let Event::Connect { peer, .. } = event {
+                    client_peer = Some(peer.id());
+                }
+            }
+            while let Some(event) = service(&mut server) {
+                match event {
+                    Event::ConnectRequest {
+                        peer,
+                        channel_count,
+                        ..
+                    } => peer.accept(channel_count, 0),
+                    Event::Connect { peer, .. } => server_peer = Some(peer.id()),
+                    _ => {}
+                }
+            }
+            if let (Some(client_peer), Some(server_peer)) = (client_peer, server_peer) {
+                return Ok(HostPair {
+                    client,
+                    client_peer,
+                    server,
+                    server_peer,
+                });
+            }
+        }
+        panic!("Expected the hosts to connect in memory.");
+    }
+}
+
+fn service(host: &mut Host<MemorySocket>) -> Option<Event<'_, MemorySocket>> {
+    match host.service() {
+        Ok(event) => event,
+        Err(ServiceError { error, .. }) => match error {},
+    }
+}
@@ -0,0 +1,156 @@
+use core::fmt::Debug;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{PacketKind, Vec};
+
+/// An interface for encoding typed messages into packet data, used by
+/// [`Peer::send_serialized`](`crate::Peer::send_serialized`),
+/// [`Packet::deserialize`](`crate::Packet::deserialize`) and
+/// [`Event::deserialize`](`crate::Event::deserialize`).
+///
+/// Implementations are provided for `postcard` and `bincode`, behind features of the same name.
+/// Both peers must use the same codec.
+pub trait MessageCodec {
+    /// The error returned when a message fails to serialize or deserialize.
+    type Error: Debug;
+    /// Serialize a message into packet data.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MessageCodec::Error`] if the message could not be serialized.
+    fn serialize<T: Serialize + ?Sized>(message: &T) -> Result<Vec<u8>, Self::Error>;
+    /// Deserialize a message from packet data.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MessageCodec::Error`] if the data is not a valid message.
+    fn deserialize<'a, T: Deserialize<'a>>(data: &'a [u8]) -> Result<T, Self::Error>;
+}
+
+/// A [`MessageCodec`] using the compact `postcard` wire format.
+#[cfg(feature = "postcard")]
+#[cfg_attr(docsrs, doc(cfg(feature = "postcard")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl MessageCodec for Postcard {
+    type Error = postcard::Error;
+
+    fn serialize<T: Serialize + ?Sized>(message: &T) -> Result<Vec<u8>, Self::Error> {
+        postcard::to_allocvec(message)
+    }
+
+    fn deserialize<'a, T: Deserialize<'a>>(data: &'a [u8]) -> Result<T, Self::Error> {
+        postcard::from_bytes(data)
+    }
+}
+
+/// A [`MessageCodec`] using `bincode`'s default options.
+#[cfg(feature = "bincode")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bincode")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl MessageCodec for Bincode {
+    type Error = bincode::Error;
+
+    fn serialize<T: Serialize + ?Sized>(message: &T) -> Result<Vec<u8>, Self::Error> {
+        bincode::serialize(message)
+    }
+
+    fn deserialize<'a, T: Deserialize<'a>>(data: &'a [u8]) -> Result<T, Self::Error> {
+        bincode::deserialize(data)
+    }
+}
+
+/// A channel with a fixed message type and delivery mode, declared with
+/// [`channels!`](`crate::channels!`).
+///
+/// Messages are sent with [`Peer::send_typed`](`crate::Peer::send_typed`) and received with
+/// [`Event::receive_typed`](`crate::Event::receive_typed`), so sending the wrong message type on a
+/// channel is a compile error.
+pub trait TypedChannel {
+    /// The message type sent on this channel.
+    type Message;
+    /// The channel ID.
+    const ID: u8;
+    /// The kind of packet messages are sent as.
+    const KIND: PacketKind;
+}
+
+/// Declare [`TypedChannel`]s, each as a unit struct with a channel ID, delivery mode and message
+/// type.
+///
+/// The delivery mode is one of `Reliable`, `Unreliable`, `Unsequenced`, `AlwaysUnreliable` or
+/// `AlwaysUnsequenced`, matching the [`PacketKind`] variants. Channel IDs must be unique, which is
+/// checked at compile time.
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// pub struct ChatMessage {
+///     text: String,
+/// }
+///
+/// #[derive(Serialize, Deserialize)]
+/// pub struct Snapshot {
+///     positions: Vec<(f32, f32)>,
+/// }
+///
+/// rusty_enet::channels! {
+///     /// Chat messages, delivered reliably.
+///     pub Chat = 0 => Reliable<ChatMessage>,
+///     pub Snapshots = 1 => Unreliable<Snapshot>,
+/// }
+///
+/// use rusty_enet::TypedChannel;
+/// assert_eq!(Snapshots::ID, 1);
+/// assert_eq!(Chat::KIND, rusty_enet::PacketKind::Reliable);
+/// ```
+#[macro_export]
+macro_rules! channels {
+    (@kind Reliable) => {
+        $crate::PacketKind::Reliable
+    };
+    (@kind Unreliable) => {
+        $crate::PacketKind::Unreliable { sequenced: true }
+    };
+    (@kind Unsequenced) => {
+        $crate::PacketKind::Unreliable { sequenced: false }
+    };
+    (@kind AlwaysUnreliable) => {
+        $crate::PacketKind::AlwaysUnreliable { sequenced: true }
+    };
+    (@kind AlwaysUnsequenced) => {
+        $crate::PacketKind::AlwaysUnreliable { sequenced: false }
+    };
+    ($($(#[$meta:meta])* $vis:vis $name:ident = $id:literal => $mode:ident<$message:ty>),* $(,)?) => {
+        $(
+            $(#[$meta])*
+            #[derive(Debug, Clone, Copy)]
+            $vis struct $name;
+
+            impl $crate::TypedChannel for $name {
+                type Message = $message;
+                const ID: u8 = $id;
+                const KIND: $crate::PacketKind = $crate::channels!(@kind $mode);
+            }
+        )*
+        const _: () = {
+            let ids: &[u8] = &[$($id),*];
+            let mut i = 0;
+            while i < ids.len() {
+                let mut j = i + 1;
+                while j < ids.len() {
+                    assert!(ids[i] != ids[j], "Channel IDs must be unique.");
+                    j += 1;
+                }
+                i += 1;
+            }
+        };
+    };
+}
@@ -0,0 +1,820 @@
+use core::{
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+use std::{collections::HashMap, time::Instant};
+
+use blake2::{digest::Mac, Blake2sMac256};
+use snow::{Builder, HandshakeState, StatelessTransportState};
+
+use crate::{os_random, Box, PacketReceived, Socket, SocketOptions, Vec, MTU_MAX};
+
+/// Bytes added to every datagram sent through an established [`NoiseSocket`] session: a 1 byte
+/// message type, an 8 byte nonce and a 16 byte authentication tag.
+///
+/// Hosts using a [`NoiseSocket`] should keep their MTU at or below `MTU_MAX - NOISE_OVERHEAD`,
+/// see [`Host::set_mtu`](`crate::Host::set_mtu`).
+pub const NOISE_OVERHEAD: usize = 1 + 8 + TAG_SIZE;
+
+const TAG_SIZE: usize = 16;
+const HANDSHAKE: u8 = 0;
+const TRANSPORT: u8 = 1;
+const COOKIE: u8 = 2;
+const COOKIE_SIZE: usize = 8;
+const REPLAY_WINDOW_SIZE: u64 = 64;
+const MAXIMUM_SESSIONS: usize = 4096;
+const MAXIMUM_HANDSHAKES: usize = 256;
+/// Once this many handshakes are in progress, a new handshake must first echo a cookie proving
+/// it can receive datagrams at its address.
+const COOKIE_THRESHOLD: usize = MAXIMUM_HANDSHAKES / 4;
+const COOKIE_LIFETIME: Duration = Duration::from_secs(120);
+/// How long an established session must be idle before a new session may take its place when
+/// [`MAXIMUM_SESSIONS`] is reached.
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const MAXIMUM_PENDING_DATAGRAMS: usize = 8;
+
+type Authorizer<A> = Box<dyn FnMut(&A, &[u8; 32]) -> bool + Send>;
+
+/// The Noise handshake pattern used by a [`NoiseSocket`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoisePattern {
+    /// `Noise_XX_25519_ChaChaPoly_BLAKE2s`: both sides transmit their static public keys during
+    /// the handshake, so neither needs to know the other's key in advance. Takes 1.5 round trips.
+    XX,
+    /// `Noise_IK_25519_ChaChaPoly_BLAKE2s`: the connecting side must already know the static
+    /// public key of the remote host, see [`NoiseSocket::set_remote_public_key`]. Takes 1 round
+    /// trip.
+    IK,
+}
+
+impl NoisePattern {
+    fn parameters(self) -> &'static str {
+        match self {
+            NoisePattern::XX => "Noise_XX_25519_ChaChaPoly_BLAKE2s",
+            NoisePattern::IK => "Noise_IK_25519_ChaChaPoly_BLAKE2s",
+        }
+    }
+}
+
+/// A static X25519 keypair identifying a [`NoiseSocket`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct NoiseKeypair {
+    /// The private key, which must be kept secret.
+    pub private: [u8; 32],
+    /// The public key, which identifies this socket to remote hosts.
+    pub public: [u8; 32],
+}
+
+impl NoiseKeypair {
+    /// Generate a new random keypair.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system fails to provide randomness for the key.
+    #[must_use]
+    pub fn generate() -> Self {
+        let keypair = Builder::new(parameters(NoisePattern::XX))
+            .generate_keypair()
+            .expect("Expected the keypair to be generated.");
+        let mut private = [0; 32];
+        let mut public = [0; 32];
+        private.copy_from_slice(&keypair.private);
+        public.copy_from_slice(&keypair.public);
+        Self { private, public }
+    }
+}
+
+impl core::fmt::Debug for NoiseKeypair {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("NoiseKeypair")
+            .field("public", &self.public)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A [`Socket`] wrapper which performs a [Noise](https://noiseprotocol.org/) handshake with every
+/// remote address before any ENet traffic is exchanged.
+///
+/// The first datagram ENet sends to an address (its connect command) starts a handshake, and is
+/// held back until the handshake completes. Both sides authenticate with
+/// their static [`NoiseKeypair`], and every later datagram is encrypted with session keys unique
+/// to that pair of hosts. Datagrams which fail authentication, or which have already been
+/// received (replays), are silently dropped before ENet ever sees them.
+///
+/// Handshakes in progress are bounded separately from established sessions, and never take their
+/// place. Once many handshakes are in progress, a new one is answered with a cookie which the
+/// remote host must echo before any key agreement is performed, so spoofed handshakes cost
+/// little.
+///
+/// The handshake runs as its own exchange of datagrams ahead of ENet's connect and verify
+/// commands, rather than being carried inside them, which costs connections an extra 1 or 1.5
+/// round trips depending on the [`NoisePattern`]. Carrying it in the connect command would leave
+/// that command, and the peer slot it claims, unencrypted and unauthenticated, and would need the
+/// socket to parse ENet's protocol, while a [`Socket`] wrapper only sees whole datagrams.
+///
+/// Use [`NoiseSocket::set_authorizer`] to decide which remote static keys are allowed, and
+/// [`NoiseSocket::remote_public_key`] to identify the host behind a peer's address.
+///
+/// ```
+/// use std::net::UdpSocket;
+///
+/// use rusty_enet::{Host, HostSettings, NoiseKeypair, NoisePattern, NoiseSocket};
+///
+/// let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+/// let socket = NoiseSocket::new(socket, NoisePattern::XX, NoiseKeypair::generate());
+/// let host = Host::new(socket, HostSettings::default()).unwrap();
+/// ```
+pub struct NoiseSocket<S: Socket> {
+    socket: S,
+    pattern: NoisePattern,
+    keypair: NoiseKeypair,
+    remote_public_keys: HashMap<S::Address, [u8; 32]>,
+    authorizer: Option<Authorizer<S::Address>>,
+    /// Sessions whose handshake completed.
+    sessions: HashMap<S::Address, Transport>,
+    /// Handshakes in progress, including new handshakes with addresses which already have a
+    /// session.
+    handshakes: HashMap<S::Address, Handshake>,
+    next_handshake: u64,
+    /// The BLAKE2s key cookies are MACs with, secure random bytes chosen when the socket is
+    /// created.
+    cookie_key: [u8; 32],
+    created: Instant,
+    buffer: Box<[u8; MTU_MAX]>,
+}
+
+struct Handshake {
+    state: Box<HandshakeState>,
+    index: u8,
+    initiator: bool,
+    received: Vec<u8>,
+    sent: Vec<u8>,
+    pending: Vec<Vec<u8>>,
+    /// Orders handshakes by when they started, to evict the oldest.
+    order: u64,
+}
+
+struct Transport {
+    state: Box<StatelessTransportState>,
+    remote_public_key: [u8; 32],
+    initiator: bool,
+    received_handshake: Vec<u8>,
+    sent_handshake: Vec<u8>,
+    counter: u64,
+    highest: u64,
+    received: u64,
+    confirmed: bool,
+    last_active: Instant,
+}
+
+impl Transport {
+    fn accept_nonce(&mut self, counter: u64) -> bool {
+        if !self.confirmed || counter > self.highest {
+            let shift = counter.wrapping_sub(self.highest);
+            self.received = if !self.confirmed || shift >= REPLAY_WINDOW_SIZE {
+                1
+            } else {
+                (self.received << shift) | 1
+            };
+            self.highest = counter;
+            self.confirmed = true;
+            true
+        } else {
+            let offset = self.highest - counter;
+            if offset >= REPLAY_WINDOW_SIZE || self.received & (1 << offset) != 0 {
+                false
+            } else {
+                self.received |= 1 << offset;
+                true
+            }
+        }
+    }
+}
+
+/// Feeds the bytes an address hashes to into a MAC, as addresses only implement [`Hash`].
+struct MacHasher(Blake2sMac256);
+
+impl Hasher for MacHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        unreachable!("Expected the MAC to be finalized instead.")
+    }
+}
+
+fn parameters(pattern: NoisePattern) -> snow::params::NoiseParams {
+    pattern
+        .parameters()
+        .parse()
+        .expect("Expected valid Noise parameters.")
+}
+
+impl<S: Socket> NoiseSocket<S>
+where
+    S::Address: Hash + Eq,
+{
+    /// Wrap a socket, authenticating with `keypair` and handshaking with `pattern`.
+    ///
+    /// Both sides of a connection must use the same [`NoisePattern`].
+    #[must_use]
+    pub fn new(socket: S, pattern: NoisePattern, keypair: NoiseKeypair) -> Self {
+        Self {
+            socket,
+            pattern,
+            keypair,
+            remote_public_keys: HashMap::new(),
+            authorizer: None,
+            sessions: HashMap::new(),
+            handshakes: HashMap::new(),
+            next_handshake: 0,
+            cookie_key: {
+                let mut cookie_key = [0; 32];
+                os_random(&mut cookie_key);
+                cookie_key
+            },
+            created: Instant::now(),
+            buffer: Box::new([0; MTU_MAX]),
+        }
+    }
+
+    /// Get a reference to the wrapped socket.
+    #[must_use]
+    pub fn socket(&self) -> &S {
+        &self.socket
+    }
+
+    /// Get a mutable reference to the wrapped socket.
+    pub fn socket_mut(&mut self) -> &mut S {
+        &mut self.socket
+    }
+
+    /// Get the static public key of this socket.
+    #[must_use]
+    pub fn public_key(&self) -> [u8; 32] {
+        self.keypair.public
+    }
+
+    /// Set the static public key expected from the host at `address`.
+    ///
+    /// Required before connecting to `address` with [`NoisePattern::IK`]. With
+    /// [`NoisePattern::XX`], handshakes with a host presenting a different key are rejected.
+    pub fn set_remote_public_key(&mut self, address: S::Address, public_key: [u8; 32]) {
+        self.remote_public_keys.insert(address, public_key);
+    }
+
+    /// Set a function deciding whether a remote host, identified by its address and static public
+    /// key, may complete a handshake. Rejected hosts are never seen by ENet.
+    ///
+    /// All remote hosts are accepted by default.
+    pub fn set_authorizer(&mut self, authorizer: Option<Authorizer<S::Address>>) {
+        self.authorizer = authorizer;
+    }
+
+    /// Get the static public key of the host at `address`, if a handshake with it completed.
+    #[must_use]
+    pub fn remote_public_key(&self, address: &S::Address) -> Option<[u8; 32]> {
+        self.sessions
+            .get(address)
+            .map(|transport| transport.remote_public_key)
+    }
+
+    /// The cookie a handshake from `address` must echo while many handshakes are in progress.
+    fn cookie(&self, address: &S::Address, epoch: u64) -> [u8; COOKIE_SIZE] {
+        let mut mac = MacHasher(
+            <Blake2sMac256 as Mac>::new_from_slice(&self.cookie_key)
+                .expect("Expected BLAKE2s to accept a 32 byte key."),
+        );
+        (epoch, address).hash(&mut mac);
+        let mut cookie = [0; COOKIE_SIZE];
+        cookie.copy_from_slice(&mac.0.finalize().into_bytes()[..COOKIE_SIZE]);
+        cookie
+    }
+
+    fn cookie_epoch(&self) -> u64 {
+        self.created.elapsed().as_secs() / COOKIE_LIFETIME.as_secs()
+    }
+
+    fn is_valid_cookie(&self, address: &S::Address, cookie: &[u8]) -> bool {
+        let epoch = self.cookie_epoch();
+        [epoch, epoch.wrapping_sub(1)].into_iter().any(|epoch| {
+            let expected = self.cookie(address, epoch);
+            // compare without exiting early, so the cookie can't be guessed a byte at a time
+            expected
+                .iter()
+                .zip(cookie)
+                .fold(0, |difference, (a, b)| difference | (a ^ b))
+                == 0
+        })
+    }
+
+    fn build_handshake(&self, address: &S::Address, initiator: bool) -> Option<HandshakeState> {
+        let remote_public_key = self.remote_public_keys.get(address);
+        let builder =
+            Builder::new(parameters(self.pattern)).local_private_key(&self.keypair.private);
+        let builder = match (self.pattern, initiator, remote_public_key) {
+            (NoisePattern::IK, true, Some(remote_public_key)) => {
+                builder.remote_public_key(remote_public_key)
+            }
+            (NoisePattern::IK, true, None) => return None,
+            _ => builder,
+        };
+        if initiator {
+            builder.build_initiator().ok()
+        } else {
+            builder.build_responder().ok()
+        }
+    }
+
+    /// Track a new handshake with `address`, evicting the oldest handshake in progress if there
+    /// are too many. Established sessions are left alone.
+    fn start_handshake(&mut self, address: S::Address, state: HandshakeState, initiator: bool) {
+        if !self.handshakes.contains_key(&address) && self.handshakes.len() >= MAXIMUM_HANDSHAKES {
+            if let Some(oldest) = self
+                .handshakes
+                .iter()
+                .min_by_key(|(_, handshake)| handshake.order)
+                .map(|(address, _)| address.clone())
+            {
+                self.handshakes.remove(&oldest);
+            }
+        }
+        let order = self.next_handshake;
+        self.next_handshake += 1;
+        self.handshakes.insert(
+            address,
+            Handshake {
+                state: Box::new(state),
+                index: 0,
+                initiator,
+                received: Vec::new(),
+                sent: Vec::new(),
+                pending: Vec::new(),
+                order,
+            },
+        );
+    }
+
+    fn write_handshake(&mut self, address: &S::Address) -> Result<bool, S::Error> {
+        let Some(handshake) = self.handshakes.get_mut(address) else {
+            return Ok(false);
+        };
+        self.buffer[0] = HANDSHAKE;
+        self.buffer[1] = handshake.index;
+        // the first message has room for the cookie of a busy remote host
+        let header_size = if handshake.index == 0 {
+            self.buffer[2..2 + COOKIE_SIZE].fill(0);
+            2 + COOKIE_SIZE
+        } else {
+            2
+        };
+        let Ok(length) = handshake
+            .state
+            .write_message(&[], &mut self.buffer[header_size..])
+        else {
+            return Ok(false);
+        };
+        handshake.index += 1;
+        handshake.sent = self.buffer[..header_size + length].to_vec();
+        self.socket.send(address.clone(), &handshake.sent)?;
+        Ok(true)
+    }
+
+    fn finish_handshake(&mut self, address: &S::Address) -> Result<bool, S::Error> {
+        let Some(handshake) = self.handshakes.remove(address) else {
+            return Ok(false);
+        };
+        let mut remote_public_key = [0; 32];
+        match handshake.state.get_remote_static() {
+            Some(key) if key.len() == 32 => remote_public_key.copy_from_slice(key),
+            _ => return Ok(false),
+        }
+        let Ok(state) = handshake.state.into_stateless_transport_mode() else {
+            return Ok(false);
+        };
+        let authorized = self
+            .remote_public_keys
+            .get(address)
+            .is_none_or(|expected| *expected == remote_public_key)
+            && self
+                .authorizer
+                .as_mut()
+                .is_none_or(|authorizer| authorizer(address, &remote_public_key));
+        if !authorized {
+            return Ok(false);
+        }
+        if !self.sessions.contains_key(address) && self.sessions.len() >= MAXIMUM_SESSIONS {
+            let Some(idle) = self
+                .sessions
+                .iter()
+                .filter(|(_, transport)| transport.last_active.elapsed() >= SESSION_IDLE_TIMEOUT)
+                .min_by_key(|(_, transport)| transport.last_active)
+                .map(|(address, _)| address.clone())
+            else {
+                return Ok(false);
+            };
+            self.sessions.remove(&idle);
+        }
+        self.sessions.insert(
+            address.clone(),
+            Transport {
+                state: Box::new(state),
+                remote_public_key,
+                initiator: handshake.initiator,
+                received_handshake: handshake.received,
+                sent_handshake: handshake.sent,
+                counter: 0,
+                highest: 0,
+                received: 0,
+                confirmed: false,
+                last_active: Instant::now(),
+            },
+        );
+        for datagram in handshake.pending {
+            self.send_transport(address, &datagram)?;
+        }
+        Ok(true)
+    }
+
+    fn send_transport(&mut self, address: &S::Address, buffer: &[u8]) -> Result<usize, S::Error> {
+        let Some(transport) = self.sessions.get_mut(address) else {
+            return Ok(0);
+        };
+        if transport.initiator && !transport.confirmed && !transport.sent_handshake.is_empty() {
+            // our final handshake message may have been lost, so repeat it until the remote host
+            // proves it has a session by sending us something
+            self.socket
+                .send(address.clone(), &transport.sent_handshake)?;
+        }
+        let length = buffer.len() + NOISE_OVERHEAD;
+        if length > MTU_MAX {
+            return Ok(0);
+        }
+        let counter = transport.counter;
+        transport.counter += 1;
+        self.buffer[0] = TRANSPORT;
+        self.buffer[1..9].copy_from_slice(&counter.to_be_bytes());
+        let Ok(encrypted_length) =
+            transport
+                .state
+                .write_message(counter, buffer, &mut self.buffer[9..])
+        else {
+            return Ok(0);
+        };
+        let sent_length = self
+            .socket
+            .send(address.clone(), &self.buffer[..encrypted_length + 9])?;
+        Ok(sent_length.saturating_sub(NOISE_OVERHEAD))
+    }
+
+    fn receive_handshake(&mut self, address: &S::Address, length: usize) -> Result<(), S::Error> {
+        if length < 2 {
+            return Ok(());
+        }
+        let message = &self.buffer[..length];
+        if let Some(sent) = self
+            .handshakes
+            .get(address)
+            .filter(|handshake| handshake.received == message)
+            .map(|handshake| &handshake.sent)
+            .or_else(|| {
+                self.sessions
+                    .get(address)
+                    .filter(|transport| transport.received_handshake == message)
+                    .map(|transport| &transport.sent_handshake)
+            })
+        {
+            // the remote host did not receive our reply, so repeat it
+            self.socket.send(address.clone(), sent)?;
+            return Ok(());
+        }
+        let message_index = self.buffer[1];
+        let header_size = if message_index == 0 {
+            if length < 2 + COOKIE_SIZE {
+                return Ok(());
+            }
+            if let Some(handshake) = self.handshakes.get(address) {
+                if handshake.initiator && handshake.sent[..] > self.buffer[..length] {
+                    // both hosts started a handshake, the larger first message wins
+                    return Ok(());
+                }
+            }
+            if self.handshakes.len() >= COOKIE_THRESHOLD
+                && !self.is_valid_cookie(address, &self.buffer[2..2 + COOKIE_SIZE])
+            {
+                // prove the remote host can receive at its address before agreeing any keys
+                let mut reply = [0; 1 + COOKIE_SIZE];
+                reply[0] = COOKIE;
+                reply[1..].copy_from_slice(&self.cookie(address, self.cookie_epoch()));
+                self.socket.send(address.clone(), &reply)?;
+                return Ok(());
+            }
+            let Some(state) = self.build_handshake(address, false) else {
+                return Ok(());
+            };
+            self.start_handshake(address.clone(), state, false);
+            2 + COOKIE_SIZE
+        } else {
+            if self
+                .handshakes
+                .get(address)
+                .is_none_or(|handshake| handshake.index != message_index)
+            {
+                return Ok(());
+            }
+            2
+        };
+        let Some(handshake) = self.handshakes.get_mut(address) else {
+            return Ok(());
+        };
+        let mut payload = [0; MTU_MAX];
+        if handshake
+            .state
+            .read_message(&self.buffer[header_size..length], &mut payload)
+            .is_err()
+        {
+            self.handshakes.remove(address);
+            return Ok(());
+        }
+        handshake.index += 1;
+        handshake.received = self.buffer[..length].to_vec();
+        if !handshake.state.is_handshake_finished() {
+            self.write_handshake(address)?;
+        }
+        if self
+            .handshakes
+            .get(address)
+            .is_some_and(|handshake| handshake.state.is_handshake_finished())
+        {
+            self.finish_handshake(address)?;
+        }
+        Ok(())
+    }
+
+    fn receive_cookie(&mut self, address: &S::Address, length: usize) -> Result<(), S::Error> {
+        if length != 1 + COOKIE_SIZE {
+            return Ok(());
+        }
+        let Some(handshake) = self.handshakes.get_mut(address) else {
+            return Ok(());
+        };
+        if !handshake.initiator || handshake.index != 1 {
+            return Ok(());
+        }
+        handshake.sent[2..2 + COOKIE_SIZE].copy_from_slice(&self.buffer[1..length]);
+        self.socket.send(address.clone(), &handshake.sent)?;
+        Ok(())
+    }
+
+    fn receive_transport(
+        &mut self,
+        address: &S::Address,
+        length: usize,
+        buffer: &mut [u8; MTU_MAX],
+    ) -> Option<usize> {
+        if length < NOISE_OVERHEAD {
+            return None;
+        }
+        let transport = self.sessions.get_mut(address)?;
+        let mut counter = [0; 8];
+        counter.copy_from_slice(&self.buffer[1..9]);
+        let counter = u64::from_be_bytes(counter);
+        let payload_length = transport
+            .state
+            .read_message(counter, &self.buffer[9..length], buffer)
+            .ok()?;
+        if !transport.accept_nonce(counter) {
+            return None;
+        }
+        transport.last_active = Instant::now();
+        Some(payload_length)
+    }
+}
+
+impl<S: Socket> Socket for NoiseSocket<S>
+where
+    S::Address: Hash + Eq,
+{
+    type Address = S::Address;
+    type Error = S::Error;
+
+    fn init(&mut self, socket_options: SocketOptions) -> Result<(), Self::Error> {
+        self.socket.init(socket_options)
+    }
+
+    fn send(&mut self, address: Self::Address, buffer: &[u8]) -> Result<usize, Self::Error> {
+        if self.sessions.contains_key(&address) {
+            return self.send_transport(&address, buffer);
+        }
+        if let Some(handshake) = self.handshakes.get(&address) {
+            if handshake.initiator && handshake.index == 1 {
+                // ENet is retrying, so our first handshake message may have been lost
+                self.socket.send(address.clone(), &handshake.sent)?;
+            }
+        } else {
+            let Some(state) = self.build_handshake(&address, true) else {
+                return Ok(0);
+            };
+            self.start_handshake(address.clone(), state, true);
+            self.write_handshake(&address)?;
+        }
+        let Some(handshake) = self.handshakes.get_mut(&address) else {
+            return Ok(0);
+        };
+        if handshake.pending.len() >= MAXIMUM_PENDING_DATAGRAMS {
+            handshake.pending.remove(0);
+        }
+        handshake.pending.push(buffer.to_vec());
+        Ok(buffer.len())
+    }
+
+    fn receive_unreachable(&mut self) -> Result<Option<Self::Address>, Self::Error> {
+        self.socket.receive_unreachable()
+    }
+
+    fn receive(
+        &mut self,
+        buffer: &mut [u8; MTU_MAX],
+    ) -> Result<Option<(Self::Address, PacketReceived)>, Self::Error> {
+        loop {
+            let (address, received, length) = match self.socket.receive(&mut self.buffer)? {
+                Some((address, received)) => match received.length() {
+                    Some(length) => (address, received, length),
+                    None => return Ok(Some((address, PacketReceived::Partial))),
+                },
+                None => return Ok(None),
+            };
+            match self.buffer[..length].first() {
+                Some(&HANDSHAKE) => self.receive_handshake(&address, length)?,
+                Some(&COOKIE) => self.receive_cookie(&address, length)?,
+                Some(&TRANSPORT) => {
+                    if let Some(payload_length) = self.receive_transport(&address, length, buffer) {
+                        return Ok(Some((address, received.with_length(payload_length))));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<S: Socket + core::fmt::Debug> core::fmt::Debug for NoiseSocket<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("NoiseSocket")
+            .field("socket", &self.socket)
+            .field("pattern", &self.pattern)
+            .field("public_key", &self.keypair.public)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::Infallible;
+
+    use super::{
+        NoiseKeypair, NoisePattern, NoiseSocket, COOKIE, COOKIE_THRESHOLD, MAXIMUM_HANDSHAKES,
+    };
+    use crate::{Box, Event, Host, HostSettings, Packet, PeerState, ReadWrite, Socket, Vec};
+
+    type TestHost = Host<NoiseSocket<ReadWrite<usize, Infallible>>>;
+
+    fn create_host(pattern: NoisePattern, keypair: NoiseKeypair) -> TestHost {
+        let socket = NoiseSocket::new(ReadWrite::new(), pattern, keypair);
+        Host::new(socket, HostSettings::default()).unwrap()
+    }
+
+    /// Deliver datagrams between hosts, each addressed by its index, and return every received
+    /// packet. Datagrams to other addresses are dropped.
+    fn update(hosts: &mut [TestHost]) -> Vec<(usize, Vec<u8>)> {
+        let mut received = Vec::new();
+        for _ in 0..10 {
+            for (index, host) in hosts.iter_mut().enumerate() {
+                while let Some(event) = host.service().unwrap() {
+                    if let Event::Receive { packet, .. } = event {
+                        received.push((index, packet.data().to_vec()));
+                    }
+                }
+            }
+            for index in 0..hosts.len() {
+                while let Some((address, datagram)) = hosts[index].socket_mut().socket_mut().read()
+                {
+                    if let Some(host) = hosts.get_mut(address) {
+                        host.socket_mut().socket_mut().write(index, datagram);
+                    }
+                }
+            }
+        }
+        received
+    }
+
+    fn handshake(pattern: NoisePattern) {
+        let client_keypair = NoiseKeypair::generate();
+        let server_keypair = NoiseKeypair::generate();
+        let mut hosts = [
+            create_host(pattern, client_keypair.clone()),
+            create_host(pattern, server_keypair.clone()),
+        ];
+        hosts[0]
+            .socket_mut()
+            .set_remote_public_key(1, server_keypair.public);
+        let peer = hosts[0].connect(1, 1, 0).unwrap().peer_id();
+        update(&mut hosts);
+        assert_eq!(hosts[0].peer(peer).unwrap().state(), PeerState::Connected);
+        assert_eq!(
+            hosts[0].socket().remote_public_key(&1),
+            Some(server_keypair.public)
+        );
+        assert_eq!(
+            hosts[1].socket().remote_public_key(&0),
+            Some(client_keypair.public)
+        );
+
+        hosts[0]
+            .peer_mut(peer)
+            .unwrap()
+            .send(0, &Packet::reliable(&b"secret"[..]))
+            .unwrap();
+        hosts[0].flush();
+        let (_, datagram) = hosts[0].socket_mut().socket_mut().read().unwrap();
+        assert!(!datagram.windows(6).any(|window| window == b"secret"));
+        hosts[1].socket_mut().socket_mut().write(0, datagram);
+        assert_eq!(update(&mut hosts), [(1, b"secret".to_vec())]);
+    }
+
+    #[test]
+    fn noise_xx() {
+        handshake(NoisePattern::XX);
+    }
+
+    #[test]
+    fn noise_ik() {
+        handshake(NoisePattern::IK);
+    }
+
+    #[test]
+    fn noise_authorizer() {
+        let mut hosts = [
+            create_host(NoisePattern::XX, NoiseKeypair::generate()),
+            create_host(NoisePattern::XX, NoiseKeypair::generate()),
+        ];
+        hosts[1]
+            .socket_mut()
+            .set_authorizer(Some(Box::new(|_, _| false)));
+        let peer = hosts[0].connect(1, 1, 0).unwrap().peer_id();
+        update(&mut hosts);
+        assert_ne!(hosts[0].peer(peer).unwrap().state(), PeerState::Connected);
+        assert_eq!(hosts[1].socket().remote_public_key(&0), None);
+    }
+
+    #[test]
+    fn noise_handshake_flood() {
+        let mut hosts = [
+            create_host(NoisePattern::XX, NoiseKeypair::generate()),
+            create_host(NoisePattern::XX, NoiseKeypair::generate()),
+            create_host(NoisePattern::XX, NoiseKeypair::generate()),
+        ];
+        let peer = hosts[0].connect(1, 1, 0).unwrap().peer_id();
+        update(&mut hosts);
+        assert_eq!(hosts[0].peer(peer).unwrap().state(), PeerState::Connected);
+
+        // first handshake messages from many spoofed addresses
+        let mut attacker = NoiseSocket::new(
+            ReadWrite::<usize, Infallible>::new(),
+            NoisePattern::XX,
+            NoiseKeypair::generate(),
+        );
+        for address in 100..100 + MAXIMUM_HANDSHAKES * 2 {
+            attacker.send(1, &[0]).unwrap();
+            let (_, message) = attacker.socket_mut().read().unwrap();
+            attacker.handshakes.clear();
+            hosts[1].socket_mut().socket_mut().write(address, message);
+        }
+        while hosts[1].service().unwrap().is_some() {}
+        // only some start a handshake, the rest are asked to echo a cookie first
+        assert_eq!(hosts[1].socket().handshakes.len(), COOKIE_THRESHOLD);
+        let mut cookies = 0;
+        while let Some((_, datagram)) = hosts[1].socket_mut().socket_mut().read() {
+            if datagram[0] == COOKIE {
+                cookies += 1;
+            }
+        }
+        assert_eq!(cookies, MAXIMUM_HANDSHAKES * 2 - COOKIE_THRESHOLD);
+
+        // the established session is untouched
+        assert!(hosts[1].socket().remote_public_key(&0).is_some());
+        hosts[0]
+            .peer_mut(peer)
+            .unwrap()
+            .send(0, &Packet::reliable(&b"still here"[..]))
+            .unwrap();
+        assert_eq!(update(&mut hosts), [(1, b"still here".to_vec())]);
+
+        // a host which can receive the cookie still connects
+        let peer = hosts[2].connect(1, 1, 0).unwrap().peer_id();
+        update(&mut hosts);
+        assert_eq!(hosts[2].peer(peer).unwrap().state(), PeerState::Connected);
+        assert!(hosts[1].socket().remote_public_key(&2).is_some());
+    }
+}
@@ -1,8 +1,15 @@
-use core::fmt::Debug;
+use core::{fmt::Debug, ops::Deref, ptr::copy_nonoverlapping, sync::atomic::Ordering};
 
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+#[cfg(feature = "serde")]
+use crate::MessageCodec;
 use crate::{
-    enet_packet_create, enet_packet_destroy, ENetPacket, ENET_PACKET_FLAG_RELIABLE,
-    ENET_PACKET_FLAG_SENT, ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT, ENET_PACKET_FLAG_UNSEQUENCED,
+    enet_packet_acquire, enet_packet_create, enet_packet_destroy, enet_packet_reference_count,
+    enet_packet_release, uses_global_allocator, Box, ENetPacket, Vec, ENET_PACKET_FLAG_NO_ALLOCATE,
+    ENET_PACKET_FLAG_RELIABLE, ENET_PACKET_FLAG_SENT, ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT,
+    ENET_PACKET_FLAG_UNSEQUENCED,
 };
 
 /// Types of packets supported by ENet, used with [`Packet::new`].
@@ -18,10 +25,21 @@ pub enum PacketKind {
     /// [`PacketKind::AlwaysUnreliable`] instead.
     Unreliable {
         /// Should the packets be sequenced? Packets received out of order will be discarded.
+        ///
+        /// Unsequenced packets (`ENET_PACKET_FLAG_UNSEQUENCED`) are delivered in any order, see
+        /// [`Sequencing`](`crate#sequencing`) for how duplicates are filtered.
         sequenced: bool,
     },
-    /// An unreliable packet, with optional sequencing. Guaranteed to be unreliable, see
+    /// An unreliable packet, with optional sequencing, which is fragmented unreliably
+    /// (`ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT`) if it is too large to fit within the MTU. See
     /// [`PacketKind::Unreliable`].
+    ///
+    /// If any fragment is lost, the whole packet is discarded rather than retransmitted, which
+    /// suits large payloads that are quickly superseded, like voice frames or world snapshots.
+    ///
+    /// The only exception is when a channel has sent 65535 unreliable packets since its last
+    /// reliable packet. The next fragmented packet is then sent reliably, to restart the channel's
+    /// unreliable sequence numbers.
     AlwaysUnreliable {
         /// Should the packets be sequenced? Packets received out of order will be discarded.
         sequenced: bool,
@@ -32,6 +50,116 @@ pub enum PacketKind {
     Reliable,
 }
 
+/// The delivery flags of a [`Packet`], returned by [`Packet::flags`] and set with
+/// [`PacketBuilder::flags`].
+///
+/// The flags map directly to ENet's packet flags, and are a lower level alternative to
+/// [`PacketKind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PacketFlags {
+    /// The packet must be received by the target peer, and resent until it is delivered
+    /// (`ENET_PACKET_FLAG_RELIABLE`).
+    pub reliable: bool,
+    /// The packet will not be sequenced with other packets (`ENET_PACKET_FLAG_UNSEQUENCED`).
+    /// Ignored for reliable packets.
+    pub unsequenced: bool,
+    /// The packet will be fragmented unreliably if it exceeds the MTU
+    /// (`ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT`). See [`PacketKind::AlwaysUnreliable`].
+    pub unreliable_fragment: bool,
+}
+
+impl PacketFlags {
+    fn from_raw(flags: u32) -> Self {
+        Self {
+            reliable: flags & ENET_PACKET_FLAG_RELIABLE != 0,
+            unsequenced: flags & ENET_PACKET_FLAG_UNSEQUENCED != 0,
+            unreliable_fragment: flags & ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT != 0,
+        }
+    }
+
+    fn to_raw(self) -> u32 {
+        let mut flags = 0;
+        if self.reliable {
+            flags |= ENET_PACKET_FLAG_RELIABLE;
+        }
+        if self.unsequenced {
+            flags |= ENET_PACKET_FLAG_UNSEQUENCED;
+        }
+        if self.unreliable_fragment {
+            flags |= ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT;
+        }
+        flags
+    }
+}
+
+impl From<PacketKind> for PacketFlags {
+    fn from(kind: PacketKind) -> Self {
+        match kind {
+            PacketKind::Unreliable { sequenced } => Self {
+                unsequenced: !sequenced,
+                ..Self::default()
+            },
+            PacketKind::AlwaysUnreliable { sequenced } => Self {
+                unsequenced: !sequenced,
+                unreliable_fragment: true,
+                ..Self::default()
+            },
+            PacketKind::Reliable => Self {
+                reliable: true,
+                ..Self::default()
+            },
+        }
+    }
+}
+
+/// The delivery mode of a channel, configured with
+/// [`HostSettings::channel_modes`](`crate::HostSettings::channel_modes`).
+///
+/// A channel with a mode other than [`ChannelMode::Any`] only accepts packets of that mode, both
+/// when sending and receiving. ENet always delivers reliable packets in order, so there is no
+/// reliable unordered mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ChannelMode {
+    /// Packets of any kind may be sent on the channel.
+    #[default]
+    Any,
+    /// Only [`PacketKind::Reliable`] packets, which are delivered reliably and in order.
+    Reliable,
+    /// Only sequenced unreliable packets. Packets arriving after a newer packet are discarded.
+    Sequenced,
+    /// Only sequenced unreliable packets, keeping only the latest. Packets arriving after a newer
+    /// packet are discarded, and when several packets are waiting to be received, only the newest
+    /// is delivered. Suits state snapshots, where only the most recent state matters.
+    Latest,
+    /// Only unsequenced unreliable packets, which are delivered in any order.
+    Unsequenced,
+}
+
+impl ChannelMode {
+    /// Get the kind of packet sent by [`Peer::send_on`](`crate::Peer::send_on`) on a channel of
+    /// this mode. Channels without a mode send [`PacketKind::Reliable`] packets.
+    #[must_use]
+    pub fn packet_kind(self) -> PacketKind {
+        match self {
+            ChannelMode::Any | ChannelMode::Reliable => PacketKind::Reliable,
+            ChannelMode::Sequenced | ChannelMode::Latest => {
+                PacketKind::Unreliable { sequenced: true }
+            }
+            ChannelMode::Unsequenced => PacketKind::Unreliable { sequenced: false },
+        }
+    }
+
+    pub(crate) fn allows(self, flags: u32) -> bool {
+        let flags = PacketFlags::from_raw(flags);
+        match self {
+            ChannelMode::Any => true,
+            ChannelMode::Reliable => flags.reliable,
+            ChannelMode::Sequenced | ChannelMode::Latest => !flags.reliable && !flags.unsequenced,
+            ChannelMode::Unsequenced => !flags.reliable && flags.unsequenced,
+        }
+    }
+}
+
 /// An ENet data packet that may be sent to or received from a peer.
 ///
 /// See [`Fragmentation and Reassembly`](`crate#fragmentation-and-reassembly`).
@@ -53,29 +181,79 @@ impl Packet {
     /// - [`Packet::always_unreliable`]
     /// - [`Packet::always_unreliable_unsequenced`]
     /// - [`Packet::reliable`]
+    ///
+    /// Use [`PacketBuilder`] to set [`PacketFlags`] or a tag directly.
     #[must_use]
     pub fn new(data: &[u8], kind: PacketKind) -> Self {
-        let packet = unsafe {
-            enet_packet_create(
+        PacketBuilder::new(data).kind(kind).build()
+    }
+
+    /// Create a new packet from the concatenation of several slices, such as a header, body and
+    /// footer, with a single allocation.
+    ///
+    /// Accepts any slice of byte buffers, including `std::io::IoSlice`. See also
+    /// [`Peer::send_vectored`](`crate::Peer::send_vectored`).
+    #[must_use]
+    pub fn from_slices<T: Deref<Target = [u8]>>(slices: &[T], kind: PacketKind) -> Self {
+        let data_length = slices.iter().map(|slice| slice.len()).sum();
+        unsafe {
+            let packet = enet_packet_create(
+                core::ptr::null(),
+                data_length,
+                PacketFlags::from(kind).to_raw(),
+            );
+            let mut offset = 0;
+            for slice in slices.iter().filter(|slice| !slice.is_empty()) {
+                copy_nonoverlapping(slice.as_ptr(), (*packet).data.add(offset), slice.len());
+                offset += slice.len();
+            }
+            enet_packet_acquire(packet, 1);
+            Self { packet }
+        }
+    }
+
+    /// Create a new packet which shares `data` instead of copying it.
+    ///
+    /// Any owned buffer works, such as an `Arc<[u8]>`, a `Vec<u8>`, or a `bytes::Bytes`. `data`
+    /// is dropped once the packet and all of its clones have been dropped, and ENet is done
+    /// sending it.
+    ///
+    /// Cloning a [`Packet`] never copies its data, so a shared packet can be sent to many peers,
+    /// or passed to [`Host::broadcast`](`crate::Host::broadcast`), with a single buffer.
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use rusty_enet::{Packet, PacketKind};
+    ///
+    /// let payload: Arc<[u8]> = Arc::from(vec![0; 1 << 20]);
+    /// let packet = Packet::from_shared(payload.clone(), PacketKind::Reliable);
+    /// assert_eq!(packet.data().as_ptr(), payload.as_ptr());
+    /// assert_eq!(Arc::strong_count(&payload), 2);
+    /// drop(packet);
+    /// assert_eq!(Arc::strong_count(&payload), 1);
+    /// ```
+    #[must_use]
+    pub fn from_shared<T>(data: T, kind: PacketKind) -> Self
+    where
+        T: AsRef<[u8]> + Send + Sync + 'static,
+    {
+        unsafe fn free_owner<T>(owner: *mut u8) {
+            drop(Box::from_raw(owner.cast::<T>()));
+        }
+        let owner = Box::into_raw(Box::new(data));
+        unsafe {
+            let data = (*owner).as_ref();
+            let packet = enet_packet_create(
                 data.as_ptr(),
                 data.len(),
-                match kind {
-                    PacketKind::Unreliable { sequenced: true } => 0,
-                    PacketKind::Unreliable { sequenced: false } => ENET_PACKET_FLAG_UNSEQUENCED,
-                    PacketKind::AlwaysUnreliable { sequenced: true } => {
-                        ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT
-                    }
-                    PacketKind::AlwaysUnreliable { sequenced: false } => {
-                        ENET_PACKET_FLAG_UNRELIABLE_FRAGMENT | ENET_PACKET_FLAG_UNSEQUENCED
-                    }
-                    PacketKind::Reliable => ENET_PACKET_FLAG_RELIABLE,
-                },
-            )
-        };
-        unsafe {
-            (*packet).reference_count += 1;
+                PacketFlags::from(kind).to_raw() | ENET_PACKET_FLAG_NO_ALLOCATE,
+            );
+            (*packet).owner = owner.cast();
+            (*packet).free_owner = Some(free_owner::<T>);
+            enet_packet_acquire(packet, 1);
+            Self { packet }
         }
-        Self { packet }
     }
 
     /// Create a new unreliable packet with
@@ -115,7 +293,8 @@ impl Packet {
     /// Get this packet's [`PacketKind`].
     #[must_use]
     pub fn kind(&self) -> PacketKind {
-        let flags = unsafe { (*self.packet).flags | !ENET_PACKET_FLAG_SENT };
+        let flags =
+            unsafe { (*self.packet).flags.load(Ordering::Relaxed) & !ENET_PACKET_FLAG_SENT };
         let sequenced = flags & ENET_PACKET_FLAG_UNSEQUENCED == 0;
         if flags & ENET_PACKET_FLAG_RELIABLE != 0 {
             PacketKind::Reliable
@@ -126,24 +305,216 @@ impl Packet {
         }
     }
 
+    /// Take this packet's data as a [`Vec`].
+    ///
+    /// If this is the only reference to the packet, such as a packet just received with
+    /// [`Event::Receive`](`crate::Event::Receive`), its buffer is returned without copying.
+    /// Otherwise, or if a custom [`Allocator`](`crate::Allocator`) is installed, the data is
+    /// copied.
+    #[must_use]
+    pub fn into_vec(self) -> Vec<u8> {
+        unsafe {
+            let packet = self.packet;
+            if enet_packet_reference_count(packet) == 1
+                && uses_global_allocator()
+                && (*packet).flags.load(Ordering::Relaxed) & ENET_PACKET_FLAG_NO_ALLOCATE == 0
+                && !(*packet).data.is_null()
+            {
+                let data = Vec::from_raw_parts(
+                    (*packet).data,
+                    (*packet).data_length,
+                    (*packet).data_length,
+                );
+                (*packet).data = core::ptr::null_mut();
+                (*packet).data_length = 0;
+                data
+            } else {
+                self.data().to_vec()
+            }
+        }
+    }
+
+    /// Get this packet's [`PacketFlags`].
+    #[must_use]
+    pub fn flags(&self) -> PacketFlags {
+        PacketFlags::from_raw(unsafe { (*self.packet).flags.load(Ordering::Relaxed) })
+    }
+
+    /// Check if this packet is reliable. See [`PacketFlags::reliable`].
+    #[must_use]
+    pub fn is_reliable(&self) -> bool {
+        self.flags().reliable
+    }
+
+    /// Check if this packet is unsequenced. See [`PacketFlags::unsequenced`].
+    #[must_use]
+    pub fn is_unsequenced(&self) -> bool {
+        self.flags().unsequenced
+    }
+
+    /// Check if this packet is fragmented unreliably. See [`PacketFlags::unreliable_fragment`].
+    #[must_use]
+    pub fn is_unreliable_fragment(&self) -> bool {
+        self.flags().unreliable_fragment
+    }
+
+    /// Get the tag set with [`PacketBuilder::tag`], or [`None`] if this packet has no tag.
+    ///
+    /// Tags are local to the host which created the packet, and are never sent to peers.
+    /// Reliable packets with a tag generate [`Event::Acknowledge`](`crate::Event::Acknowledge`)
+    /// once delivered, or [`Event::DeliveryFailed`](`crate::Event::DeliveryFailed`) if the peer
+    /// disconnects first.
+    #[must_use]
+    pub fn tag(&self) -> Option<u64> {
+        unsafe { (*self.packet).tag }
+    }
+
+    /// Create a [`PacketBuilder`] with this packet's data, flags and tag, for sending a copy of it
+    /// with different delivery semantics.
+    #[must_use]
+    pub fn to_builder(&self) -> PacketBuilder<'_> {
+        PacketBuilder {
+            data: self.data(),
+            flags: self.flags(),
+            tag: self.tag(),
+        }
+    }
+
     /// Get the byte array contained in this packet.
     #[must_use]
     pub fn data(&self) -> &[u8] {
         unsafe { super::from_raw_parts_or_empty((*self.packet).data, (*self.packet).data_length) }
     }
 
-    pub(crate) fn new_from_ptr(packet: *mut ENetPacket) -> Self {
+    /// Deserialize this packet's data with the [`MessageCodec`] `C`, as sent by
+    /// [`Peer::send_serialized`](`crate::Peer::send_serialized`).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MessageCodec::Error`] if the data is not a valid `T`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn deserialize<'a, C: MessageCodec, T: Deserialize<'a>>(&'a self) -> Result<T, C::Error> {
+        C::deserialize(self.data())
+    }
+
+    /// Check if anything other than this handle references the packet, such as a peer's queued
+    /// outgoing commands.
+    pub(crate) fn is_shared(&self) -> bool {
+        unsafe { enet_packet_reference_count(self.packet) > 1 }
+    }
+
+    /// Wrap a packet returned by `enet_peer_receive`, taking over
+    /// the reference the peer's dispatch queue held, rather than acquiring a new one.
+    pub(crate) fn from_received(packet: *mut ENetPacket) -> Self {
+        Self { packet }
+    }
+}
+
+/// A builder for a [`Packet`], combining its data, [`PacketFlags`] and an optional tag.
+///
+/// ```
+/// use rusty_enet::{PacketBuilder, PacketKind};
+///
+/// let packet = PacketBuilder::new(b"hello")
+///     .kind(PacketKind::Reliable)
+///     .tag(7)
+///     .build();
+/// assert!(packet.is_reliable());
+/// assert_eq!(packet.tag(), Some(7));
+///
+/// // send a copy unreliably
+/// let copy = packet.to_builder().reliable(false).build();
+/// assert!(!copy.is_reliable());
+/// assert_eq!(copy.data(), b"hello");
+/// ```
+#[derive(Debug, Clone)]
+pub struct PacketBuilder<'a> {
+    data: &'a [u8],
+    flags: PacketFlags,
+    tag: Option<u64>,
+}
+
+impl<'a> PacketBuilder<'a> {
+    /// Start building a packet containing `data`, with
+    /// [`PacketKind::Unreliable { sequenced: true }`](`PacketKind::Unreliable`) and no tag.
+    #[must_use]
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            flags: PacketFlags::default(),
+            tag: None,
+        }
+    }
+
+    /// Set the packet's data.
+    #[must_use]
+    pub fn data(mut self, data: &'a [u8]) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Set all of the packet's flags from a [`PacketKind`].
+    #[must_use]
+    pub fn kind(mut self, kind: PacketKind) -> Self {
+        self.flags = kind.into();
+        self
+    }
+
+    /// Set all of the packet's flags.
+    #[must_use]
+    pub fn flags(mut self, flags: PacketFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Set [`PacketFlags::reliable`].
+    #[must_use]
+    pub fn reliable(mut self, reliable: bool) -> Self {
+        self.flags.reliable = reliable;
+        self
+    }
+
+    /// Set [`PacketFlags::unsequenced`].
+    #[must_use]
+    pub fn unsequenced(mut self, unsequenced: bool) -> Self {
+        self.flags.unsequenced = unsequenced;
+        self
+    }
+
+    /// Set [`PacketFlags::unreliable_fragment`].
+    #[must_use]
+    pub fn unreliable_fragment(mut self, unreliable_fragment: bool) -> Self {
+        self.flags.unreliable_fragment = unreliable_fragment;
+        self
+    }
+
+    /// Attach a tag to the packet, retrieved with [`Packet::tag`]. Reliable packets with a tag
+    /// report it back in [`Event::Acknowledge`](`crate::Event::Acknowledge`) or
+    /// [`Event::DeliveryFailed`](`crate::Event::DeliveryFailed`).
+    #[must_use]
+    pub fn tag(mut self, tag: u64) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Create the [`Packet`].
+    #[must_use]
+    pub fn build(self) -> Packet {
         unsafe {
-            (*packet).reference_count += 1;
+            let packet =
+                enet_packet_create(self.data.as_ptr(), self.data.len(), self.flags.to_raw());
+            (*packet).tag = self.tag;
+            enet_packet_acquire(packet, 1);
+            Packet { packet }
         }
-        Self { packet }
     }
 }
 
 impl Clone for Packet {
     fn clone(&self) -> Self {
         unsafe {
-            (*self.packet).reference_count += 1;
+            enet_packet_acquire(self.packet, 1);
         }
         Self {
             packet: self.packet,
@@ -154,14 +525,19 @@ impl Clone for Packet {
 impl Drop for Packet {
     fn drop(&mut self) {
         unsafe {
-            (*self.packet).reference_count -= 1;
-            if (*self.packet).reference_count == 0 {
+            if enet_packet_release(self.packet) {
                 enet_packet_destroy(self.packet);
             }
         }
     }
 }
 
+impl AsRef<[u8]> for Packet {
+    fn as_ref(&self) -> &[u8] {
+        self.data()
+    }
+}
+
 impl Debug for Packet {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let packet = unsafe { &(*self.packet) };
@@ -170,6 +546,44 @@ impl Debug for Packet {
             .field("dataLength", &packet.data_length)
             .field("flags", &packet.flags)
             .field("kind", &self.kind())
+            .field("tag", &packet.tag)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Packet, PacketKind};
+
+    #[test]
+    fn from_slices() {
+        let packet = Packet::from_slices(&[&[1, 2][..], &[], &[3]], PacketKind::Reliable);
+        assert_eq!(packet.data(), [1, 2, 3]);
+        assert_eq!(packet.kind(), PacketKind::Reliable);
+        let empty: [&[u8]; 2] = [&[], &[]];
+        assert!(Packet::from_slices(&empty, PacketKind::Reliable)
+            .data()
+            .is_empty());
+    }
+
+    #[test]
+    fn into_vec() {
+        let packet = Packet::reliable(&[1, 2, 3]);
+        let data = packet.data().as_ptr();
+        let vec = packet.into_vec();
+        assert_eq!(vec, [1, 2, 3]);
+        assert_eq!(vec.as_ptr(), data);
+
+        // shared packets are copied, leaving the other references intact
+        let packet = Packet::reliable(&[4, 5, 6]);
+        let clone = packet.clone();
+        let vec = packet.into_vec();
+        assert_eq!(vec, [4, 5, 6]);
+        assert_ne!(vec.as_ptr(), clone.data().as_ptr());
+        assert_eq!(clone.data(), [4, 5, 6]);
+
+        // received packets can be forwarded without copying
+        let forwarded = Packet::from_shared(clone.clone(), clone.kind());
+        assert_eq!(forwarded.data().as_ptr(), clone.data().as_ptr());
+    }
+}
@@ -1,27 +1,56 @@
-use core::{fmt::Debug, time::Duration};
+use core::{fmt::Debug, ops::Deref, time::Duration};
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
 
 use crate::{
-    consts::{PROTOCOL_MAXIMUM_MTU, PROTOCOL_MAXIMUM_PEER_ID, PROTOCOL_MINIMUM_MTU},
-    enet_peer_disconnect, enet_peer_disconnect_later, enet_peer_disconnect_now, enet_peer_ping,
-    enet_peer_ping_interval, enet_peer_reset, enet_peer_send, enet_peer_throttle_configure,
-    enet_peer_timeout,
-    error::{BadParameter, PeerSendError},
-    ENetPeer, Packet, Socket, ENET_PEER_STATE_ACKNOWLEDGING_CONNECT,
+    consts::{
+        PEER_MAXIMUM_CONNECT_PAYLOAD, PEER_MAXIMUM_PING_PAYLOAD, PROTOCOL_EXTENDED_MAXIMUM_PEER_ID,
+        PROTOCOL_MAXIMUM_MTU, PROTOCOL_MAXIMUM_PEER_ID, PROTOCOL_MAXIMUM_WINDOW_SIZE,
+        PROTOCOL_MINIMUM_MTU, PROTOCOL_MINIMUM_WINDOW_SIZE,
+    },
+    enet_host_audit_connect, enet_host_channel_mode, enet_list_size, enet_peer_accept,
+    enet_peer_add_path, enet_peer_coalesce_delay, enet_peer_disconnect, enet_peer_disconnect_later,
+    enet_peer_disconnect_now, enet_peer_flush, enet_peer_has_outgoing_commands, enet_peer_id,
+    enet_peer_maximum_packet_size, enet_peer_ping, enet_peer_ping_interval, enet_peer_redirect,
+    enet_peer_reset, enet_peer_send, enet_peer_send_batch, enet_peer_throttle_configure,
+    enet_peer_timeout, enet_peer_window_size,
+    error::{BadParameter, BatchSendError, PeerSendError},
+    Address, CallID, ChannelMode, ConnectOutcome, ENetPacket, ENetPeer, Host, Packet, PacketKind,
+    ProtocolVersion, Socket, Vec, ENET_PEER_STATE_ACKNOWLEDGING_CONNECT,
     ENET_PEER_STATE_ACKNOWLEDGING_DISCONNECT, ENET_PEER_STATE_CONNECTED,
     ENET_PEER_STATE_CONNECTING, ENET_PEER_STATE_CONNECTION_PENDING,
     ENET_PEER_STATE_CONNECTION_SUCCEEDED, ENET_PEER_STATE_DISCONNECTED,
     ENET_PEER_STATE_DISCONNECTING, ENET_PEER_STATE_DISCONNECT_LATER, ENET_PEER_STATE_ZOMBIE,
 };
+#[cfg(feature = "serde")]
+use crate::{error::SerializedSendError, MessageCodec, TypedChannel};
 
-/// A newtype around a `usize`, representing a unique identifier for a peer.
+/// A unique identifier for a peer, made of the index of its slot in the host and the slot's
+/// generation.
+///
+/// The generation changes whenever the slot is reused for a new connection, so IDs kept from an
+/// earlier connection are rejected by [`Host::peer`](`crate::Host::peer`), rather than
+/// addressing whichever peer took the slot next.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct PeerID(pub usize);
+pub struct PeerID {
+    /// The index of the peer's slot, between [`PeerID::MIN`] and [`PeerID::MAX`], or
+    /// [`PeerID::EXTENDED_MAX`] in hosts speaking
+    /// [`ProtocolVersion::ExtendedPeerIds`].
+    pub index: usize,
+    /// The generation of the peer's slot, which increases each time the slot is used for a new
+    /// connection. Slots which have never been used have a generation of `0`.
+    pub generation: u32,
+}
 
 impl PeerID {
-    /// The minimum valid value a [`PeerID`] can be.
+    /// The minimum valid index a [`PeerID`] can have.
     pub const MIN: usize = 0;
-    /// The maximum valid value a [`PeerID`] can be.
+    /// The maximum valid index a [`PeerID`] can have.
     pub const MAX: usize = PROTOCOL_MAXIMUM_PEER_ID as usize;
+    /// The maximum valid index a [`PeerID`] can have in hosts speaking
+    /// [`ProtocolVersion::ExtendedPeerIds`].
+    pub const EXTENDED_MAX: usize = PROTOCOL_EXTENDED_MAXIMUM_PEER_ID as usize;
 }
 
 /// The state of a [`Peer`].
@@ -40,19 +69,136 @@ pub enum PeerState {
     Zombie,
 }
 
+/// An outgoing connection attempt, returned by [`Host::connect`](`crate::Host::connect`).
+///
+/// Tracks the attempt separately from peers accepted by the host, and stays accurate after the
+/// peer slot is reused by another connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PendingConnection {
+    peer: PeerID,
+}
+
+/// The status of a [`PendingConnection`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// The connection is still being established.
+    Connecting,
+    /// The connection was established, and [`Event::Connect`](`crate::Event::Connect`) has been
+    /// returned for it.
+    Connected,
+    /// The connection timed out or was refused, or has since been closed.
+    Failed,
+}
+
+/// Network conditions simulated for a peer, set with [`Peer::simulate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PeerSimulation {
+    /// The chance, from `0` to `100`, of each datagram to or from the peer being dropped.
+    pub loss_percent: u8,
+    /// The delay added to each datagram sent to the peer.
+    pub min_latency: Duration,
+    /// The most extra delay added to each datagram sent to the peer, chosen at random for each,
+    /// so datagrams may arrive out of order.
+    pub jitter: Duration,
+}
+
+/// Statistics for one of a peer's channels, returned by [`Peer::channel_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChannelStats {
+    /// The number of packets queued to be sent on this channel.
+    pub packets_sent: u32,
+    /// The number of packets received on this channel and returned by
+    /// [`Host::service`](`crate::Host::service`).
+    pub packets_received: u32,
+    /// The number of times a reliable command on this channel was resent after its
+    /// acknowledgement timed out.
+    pub reliable_resends: u32,
+    /// The number of received commands waiting on this channel for earlier commands to arrive,
+    /// or for their remaining fragments.
+    pub incoming_queued: usize,
+    /// The sequence number the next reliable packet on this channel will be sent with.
+    pub next_reliable_sequence_number: u16,
+    /// The sequence number the next unreliable packet on this channel will be sent with.
+    pub next_unreliable_sequence_number: u16,
+}
+
+impl PendingConnection {
+    pub(crate) fn new(peer: PeerID) -> Self {
+        Self { peer }
+    }
+
+    /// The ID of the peer this connection uses.
+    #[must_use]
+    pub fn peer_id(&self) -> PeerID {
+        self.peer
+    }
+
+    /// Get the status of the connection on the host which started it.
+    #[must_use]
+    pub fn status<S: Socket>(&self, host: &Host<S>) -> ConnectionStatus {
+        let Some(peer) = host.peer(self.peer) else {
+            return ConnectionStatus::Failed;
+        };
+        match peer.state() {
+            PeerState::Connecting
+            | PeerState::AcknowledgingConnect
+            | PeerState::ConnectionPending
+            | PeerState::ConnectionSucceeded => ConnectionStatus::Connecting,
+            PeerState::Connected | PeerState::DisconnectLater => ConnectionStatus::Connected,
+            PeerState::Disconnected
+            | PeerState::Disconnecting
+            | PeerState::AcknowledgingDisconnect
+            | PeerState::Zombie => ConnectionStatus::Failed,
+        }
+    }
+}
+
 /// A peer, associated with a [`Host`](`crate::Host`), which may or may not be connected.
 ///
 /// To check on the connectivity of a peer, see [`Peer::state`].
+///
+/// Peers are owned by their host, and only handed out as references borrowing it, so a peer
+/// can't be used once the host is serviced, mutated or dropped:
+///
+/// ```compile_fail
+/// # use std::net::UdpSocket;
+/// # use rusty_enet::{Host, HostSettings, PeerID};
+/// # let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+/// let mut host = Host::new(socket, HostSettings::default()).unwrap();
+/// let peer = host
+///     .peer_mut(PeerID {
+///         index: 0,
+///         generation: 0,
+///     })
+///     .unwrap();
+/// host.service().unwrap();
+/// peer.ping();
+/// ```
+///
+/// To refer to a peer across calls to [`Host::service`](`crate::Host::service`), keep its
+/// [`PeerID`] and look it up again with [`Host::peer_mut`](`crate::Host::peer_mut`).
+///
+/// Peers of one host share its command pool and other state without locking, so they stay on the
+/// host's thread. Move the [`Host`](`crate::Host`) itself to use it from another thread:
+///
+/// ```compile_fail
+/// # use std::net::UdpSocket;
+/// # use rusty_enet::{Host, HostSettings};
+/// # let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+/// let mut host = Host::new(socket, HostSettings::default()).unwrap();
+/// std::thread::scope(|scope| {
+///     for peer in host.peers_mut() {
+///         scope.spawn(move || peer.ping());
+///     }
+/// });
+/// ```
 pub struct Peer<S: Socket>(pub(crate) *mut ENetPeer<S>);
 
-unsafe impl<S: Socket> Send for Peer<S> {}
-unsafe impl<S: Socket> Sync for Peer<S> {}
-
 impl<S: Socket> Peer<S> {
     /// Get the [`PeerID`] of this peer.
     #[must_use]
     pub fn id(&self) -> PeerID {
-        PeerID(unsafe { self.0.offset_from((*(*self.0).host).peers) as usize })
+        unsafe { enet_peer_id(self.0) }
     }
 
     /// Sends a ping request to a peer.
@@ -75,6 +221,298 @@ impl<S: Socket> Peer<S> {
         unsafe { enet_peer_send(self.0, channel_id, packet.packet) }
     }
 
+    /// Queues a copy of `data` to be sent to this peer on the specified channel, without creating
+    /// a [`Packet`] first.
+    ///
+    /// # Errors
+    ///
+    /// May return any of the [`PeerSendError`] variants on failure.
+    pub fn send_bytes(
+        &mut self,
+        channel_id: u8,
+        data: &[u8],
+        kind: PacketKind,
+    ) -> Result<(), PeerSendError> {
+        self.send(channel_id, &Packet::new(data, kind))
+    }
+
+    /// Queues a copy of `data` to be sent to this peer on its default channel, with its default
+    /// [`PacketKind`], set with [`Peer::set_defaults`] or
+    /// [`HostSettings::default_channel`](`crate::HostSettings::default_channel`) and
+    /// [`HostSettings::default_packet_kind`](`crate::HostSettings::default_packet_kind`).
+    ///
+    /// ```
+    /// # use std::convert::Infallible;
+    /// # use rusty_enet::{Host, HostSettings, PacketKind, ReadWrite};
+    /// # let mut host = Host::new(ReadWrite::<(), Infallible>::new(), HostSettings::default()).unwrap();
+    /// # let peer = host.connect((), 2, 0).unwrap().peer_id();
+    /// let peer = host.peer_mut(peer).unwrap();
+    /// // chat on the reliable channel 0, positions on the unreliable channel 1
+    /// peer.set_defaults(1, PacketKind::Unreliable { sequenced: true });
+    /// _ = peer.send_default(b"hello");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// May return any of the [`PeerSendError`] variants on failure.
+    pub fn send_default(&mut self, data: &[u8]) -> Result<(), PeerSendError> {
+        let (channel_id, kind) = self.defaults();
+        self.send_bytes(channel_id, data, kind)
+    }
+
+    /// The channel and kind of packets sent with [`Peer::send_default`].
+    #[must_use]
+    pub fn defaults(&self) -> (u8, PacketKind) {
+        unsafe {
+            (*self.0).defaults.unwrap_or((
+                (*(*self.0).host).default_channel,
+                (*(*self.0).host).default_packet_kind,
+            ))
+        }
+    }
+
+    /// The data the connection with this peer was started with, passed to
+    /// [`Host::connect`](`crate::Host::connect`) by whichever side connected. Both sides see the
+    /// same value, unlike the data of [`Event::Connect`](`crate::Event::Connect`), which the
+    /// connecting side receives from [`Peer::accept`].
+    #[must_use]
+    pub fn connect_data(&self) -> u32 {
+        unsafe { (*self.0).connect_data }
+    }
+
+    /// Set the channel and kind of packets sent to this peer with [`Peer::send_default`],
+    /// overriding the host's. The defaults are restored when the peer is reset.
+    pub fn set_defaults(&mut self, channel_id: u8, kind: PacketKind) {
+        unsafe {
+            (*self.0).defaults = Some((channel_id, kind));
+        }
+    }
+
+    /// Queues a packet to be sent to this peer on the specified channel, and immediately sends it
+    /// along with anything else queued for this peer, rather than waiting for the next
+    /// [`Host::service`](`crate::Host::service`). Useful for latency critical packets like player
+    /// input.
+    ///
+    /// # Errors
+    ///
+    /// May return any of the [`PeerSendError`] variants on failure.
+    pub fn send_now(&mut self, channel_id: u8, packet: &Packet) -> Result<(), PeerSendError> {
+        self.send(channel_id, packet)?;
+        self.flush();
+        Ok(())
+    }
+
+    /// Sends any packets queued for this peer, without sending those queued for other peers. See
+    /// [`Host::flush`](`crate::Host::flush`).
+    pub fn flush(&mut self) {
+        unsafe { enet_peer_flush(self.0) }
+    }
+
+    /// Whether any commands to this peer are queued, or sent reliably and not yet acknowledged,
+    /// matching ENet's `enet_peer_has_outgoing_commands`.
+    ///
+    /// Once this returns `false`, every reliable packet sent to the peer has been delivered. This
+    /// is the same condition [`Peer::disconnect_later`] waits for. See
+    /// [`Host::all_flushed`](`crate::Host::all_flushed`).
+    #[must_use]
+    pub fn has_queued_outgoing(&self) -> bool {
+        unsafe { enet_peer_has_outgoing_commands(self.0) != 0 }
+    }
+
+    /// Hold unreliable packets queued to this peer instead of sending them, while reliable packets
+    /// keep flowing, such as while a client is loading or backgrounded. Unpausing sends the held
+    /// packets during the next [`Host::service`](`crate::Host::service`) or
+    /// [`Host::flush`](`crate::Host::flush`).
+    ///
+    /// Held packets are still bounded by the channel's
+    /// [`UnreliableDropPolicy`](`crate::UnreliableDropPolicy`), so
+    /// [`UnreliableDropPolicy::KeepLatest`](`crate::UnreliableDropPolicy::KeepLatest`) channels
+    /// only hold the newest packet. Sequenced packets held while newer reliable packets are sent on
+    /// the same channel are discarded by the peer once sent, as they're out of date. Packets are
+    /// no longer held once the peer starts disconnecting.
+    pub fn pause_unreliable(&mut self, paused: bool) {
+        unsafe { (*self.0).unreliable_paused = paused }
+    }
+
+    /// Whether unreliable packets are being held. See [`Peer::pause_unreliable`].
+    #[must_use]
+    pub fn unreliable_paused(&self) -> bool {
+        unsafe { (*self.0).unreliable_paused }
+    }
+
+    /// Queues a copy of `data` to be sent to this peer on the specified channel, as the kind of
+    /// packet given by the channel's [`ChannelMode`]. See
+    /// [`HostSettings::channel_modes`](`crate::HostSettings::channel_modes`).
+    ///
+    /// # Errors
+    ///
+    /// May return any of the [`PeerSendError`] variants on failure.
+    pub fn send_on(&mut self, channel_id: u8, data: &[u8]) -> Result<(), PeerSendError> {
+        let kind = self.channel_mode(channel_id).packet_kind();
+        self.send(channel_id, &Packet::new(data, kind))
+    }
+
+    /// Get the delivery mode of a channel, configured with
+    /// [`HostSettings::channel_modes`](`crate::HostSettings::channel_modes`).
+    #[must_use]
+    pub fn channel_mode(&self, channel_id: u8) -> ChannelMode {
+        unsafe { enet_host_channel_mode((*self.0).host, channel_id) }
+    }
+
+    /// Queues several packets to be sent to this peer on the specified channel.
+    ///
+    /// Cheaper than calling [`Peer::send`] for each packet: the peer and channel are checked
+    /// once, and the commands for every packet are taken from the host's pool at once. All of
+    /// the packets are sent together during the next [`Host::service`] or [`Host::flush`], packed
+    /// into as few datagrams as possible.
+    ///
+    /// [`Host::service`]: `crate::Host::service`
+    /// [`Host::flush`]: `crate::Host::flush`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BatchSendError`] if any packet fails to queue, in which case the remaining
+    /// packets are not queued.
+    pub fn send_batch(
+        &mut self,
+        channel_id: u8,
+        packets: impl IntoIterator<Item = Packet>,
+    ) -> Result<(), BatchSendError> {
+        let packets: Vec<Packet> = packets.into_iter().collect();
+        let raw_packets: Vec<*mut ENetPacket> =
+            packets.iter().map(|packet| packet.packet).collect();
+        unsafe { enet_peer_send_batch(self.0, channel_id, &raw_packets) }
+            .map_err(|(sent, error)| BatchSendError { sent, error })
+    }
+
+    /// Queues a packet built from several slices to be sent to this peer on the specified
+    /// channel, without the caller concatenating them first.
+    ///
+    /// ```
+    /// # use std::{io::IoSlice, net::UdpSocket};
+    /// # use rusty_enet::{Host, HostSettings, PacketKind};
+    /// # let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let mut host = Host::new(socket, HostSettings::default()).unwrap();
+    /// # let peer = host.connect("127.0.0.1:1".parse().unwrap(), 1, 0).unwrap();
+    /// # let peer = host.peer_mut(peer.peer_id()).unwrap();
+    /// let header = [1, 2];
+    /// let body = b"hello";
+    /// let slices = [IoSlice::new(&header), IoSlice::new(body)];
+    /// // the peer is not connected yet
+    /// assert!(peer.send_vectored(0, &slices, PacketKind::Reliable).is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// May return any of the [`PeerSendError`] variants on failure.
+    pub fn send_vectored<T: Deref<Target = [u8]>>(
+        &mut self,
+        channel_id: u8,
+        slices: &[T],
+        kind: PacketKind,
+    ) -> Result<(), PeerSendError> {
+        self.send(channel_id, &Packet::from_slices(slices, kind))
+    }
+
+    /// Serialize `message` with the [`MessageCodec`] `C`, and queue it to be sent to this peer on
+    /// the specified channel. The receiver can decode it with [`Packet::deserialize`] or
+    /// [`Event::deserialize`](`crate::Event::deserialize`).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SerializedSendError::Serialize`] if the message fails to serialize, or
+    /// [`SerializedSendError::Send`] with any of the [`PeerSendError`] variants if it fails to
+    /// queue.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn send_serialized<C: MessageCodec, T: Serialize + ?Sized>(
+        &mut self,
+        channel_id: u8,
+        message: &T,
+        kind: PacketKind,
+    ) -> Result<(), SerializedSendError<C::Error>> {
+        let data = C::serialize(message).map_err(SerializedSendError::Serialize)?;
+        self.send(channel_id, &Packet::from_shared(data, kind))
+            .map_err(SerializedSendError::Send)
+    }
+
+    /// Serialize `message` with the [`MessageCodec`] `C`, and queue it to be sent to this peer on
+    /// the [`TypedChannel`] `T`, using the channel's delivery mode.
+    ///
+    /// # Errors
+    ///
+    /// See [`Peer::send_serialized`].
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn send_typed<T: TypedChannel, C: MessageCodec>(
+        &mut self,
+        message: &T::Message,
+    ) -> Result<(), SerializedSendError<C::Error>>
+    where
+        T::Message: Serialize,
+    {
+        self.send_serialized::<C, T::Message>(T::ID, message, T::KIND)
+    }
+
+    /// Call the method `method_id` registered on the peer with
+    /// [`Host::register_handler`](`crate::Host::register_handler`), sending `payload` reliably on
+    /// [`HostSettings::rpc_channel`](`crate::HostSettings::rpc_channel`).
+    ///
+    /// The returned [`CallID`] is reported back in [`Event::Reply`](`crate::Event::Reply`) once
+    /// the peer replies, or once the call fails because the peer disconnected, has no handler for
+    /// the method, or took longer than
+    /// [`HostSettings::rpc_timeout`](`crate::HostSettings::rpc_timeout`).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PeerSendError::InvalidChannel`] if the host has no RPC channel, or any of the
+    /// other [`PeerSendError`] variants if the call fails to queue.
+    pub fn call(&mut self, method_id: u16, payload: &[u8]) -> Result<CallID, PeerSendError> {
+        unsafe {
+            let host = (*self.0).host;
+            let rpc = (*host).rpc.assume_init_mut();
+            let Some(channel_id) = rpc.channel_id else {
+                return Err(PeerSendError::InvalidChannel);
+            };
+            let (call, packet) = rpc.request(method_id, payload);
+            self.send(channel_id, &packet)?;
+            let now = (*host).time.assume_init_ref()();
+            (*host).rpc.assume_init_mut().track(self.id(), call, now);
+            Ok(call)
+        }
+    }
+
+    /// Accept a connection request received with
+    /// [`Event::ConnectRequest`](`crate::Event::ConnectRequest`), with at most `channel_count` of
+    /// the channels the peer requested.
+    ///
+    /// An [`Event::Connect`](`crate::Event::Connect`) event will be generated by
+    /// [`Host::service`](`crate::Host::service`) once the peer has connected, and the connecting
+    /// host receives `data` with its own, if it speaks
+    /// [`ProtocolVersion::Extended`](`crate::ProtocolVersion::Extended`). Does nothing for peers
+    /// which aren't awaiting approval.
+    pub fn accept(&mut self, channel_count: usize, data: u32) {
+        unsafe { enet_peer_accept(self.0, channel_count, data) }
+    }
+
+    /// Reject a connection request received with
+    /// [`Event::ConnectRequest`](`crate::Event::ConnectRequest`), freeing the peer immediately.
+    ///
+    /// No [`Event::Disconnect`](`crate::Event::Disconnect`) event will be generated. The connecting
+    /// host receives an [`Event::Disconnect`](`crate::Event::Disconnect`) with `code` as its data,
+    /// on a best-effort basis, and otherwise times out. Does nothing for peers which aren't
+    /// awaiting approval.
+    pub fn reject(&mut self, code: u32) {
+        unsafe {
+            if (*self.0).pending_verify.is_some() {
+                if let Some(address) = (*self.0).address.assume_init_ref() {
+                    enet_host_audit_connect((*self.0).host, address, ConnectOutcome::Rejected);
+                }
+                enet_peer_disconnect_now(self.0, code);
+            }
+        }
+    }
+
     /// Request a disconnection from a peer.
     ///
     /// An [`Event::Disconnect`](`crate::Event::Disconnect`) event will be generated by
@@ -83,13 +521,57 @@ impl<S: Socket> Peer<S> {
         unsafe { enet_peer_disconnect(self.0, data) }
     }
 
+    /// Request a disconnection from a peer like [`Peer::disconnect`], telling it to connect to
+    /// `address` instead, such as to hand a client over from a lobby to a game server.
+    ///
+    /// Hosts using
+    /// [`HostSettings::follow_redirects`](`crate::HostSettings::follow_redirects`) connect to
+    /// `address` once the disconnection is complete, sending `data` as their connect data. The
+    /// peer also receives `data` with its [`Event::Disconnect`](`crate::Event::Disconnect`)
+    /// event, and hosts which don't follow redirects just disconnect.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BadParameter`] if `address` can't be encoded with [`Address::to_bytes`], or if
+    /// the peer isn't connected or doesn't speak
+    /// [`ProtocolVersion::Extended`](`crate::ProtocolVersion::Extended`).
+    pub fn redirect(&mut self, address: &S::Address, data: u32) -> Result<(), BadParameter> {
+        let Some(address) = address
+            .to_bytes()
+            .filter(|address| !address.is_empty() && address.len() <= PEER_MAXIMUM_CONNECT_PAYLOAD)
+        else {
+            return Err(BadParameter {
+                method: "Peer::redirect",
+                parameter: "address",
+            });
+        };
+        unsafe {
+            if (*self.0).protocol_version == ProtocolVersion::Enet
+                || !enet_peer_redirect(self.0, address, data)
+            {
+                return Err(BadParameter {
+                    method: "Peer::redirect",
+                    parameter: "self",
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// Force an immediate disconnection from a peer.
     ///
     /// No [`Event::Disconnect`](`crate::Event::Disconnect`) event will be generated. The foreign
     /// peer is not guaranteed to receive the disconnect notification, and is reset immediately upon
-    /// return from this function.
+    /// return from this function. Pending [`Peer::call`]s fail with
+    /// [`RpcError::Disconnected`](`crate::error::RpcError::Disconnected`).
     pub fn disconnect_now(&mut self, data: u32) {
-        unsafe { enet_peer_disconnect_now(self.0, data) }
+        unsafe {
+            enet_peer_disconnect_now(self.0, data);
+            (*(*self.0).host)
+                .rpc
+                .assume_init_mut()
+                .disconnect(self.id());
+        }
     }
 
     /// Request a disconnection from a peer, but only after all queued outgoing packets are sent.
@@ -103,10 +585,15 @@ impl<S: Socket> Peer<S> {
     /// Forcefully disconnects a peer.
     ///
     /// The foreign host represented by the peer is not notified of the disconnection and will
-    /// timeout on its connection to the local host.
+    /// timeout on its connection to the local host. Pending [`Peer::call`]s fail with
+    /// [`RpcError::Disconnected`](`crate::error::RpcError::Disconnected`).
     pub fn reset(&mut self) {
         unsafe {
             enet_peer_reset(self.0);
+            (*(*self.0).host)
+                .rpc
+                .assume_init_mut()
+                .disconnect(self.id());
         }
     }
 
@@ -124,24 +611,44 @@ impl<S: Socket> Peer<S> {
     /// - `limit` - the timeout limit; defaults to
     ///   [`PEER_TIMEOUT_LIMIT`](`crate::consts::PEER_TIMEOUT_LIMIT`) if 0
     /// - `minimum` - the timeout minimum; defaults to
-    ///   [`PEER_TIMEOUT_MINIMUM`](`crate::consts::PEER_TIMEOUT_MINIMUM`) if 0
+    ///   [`PEER_TIMEOUT_MINIMUM`](`crate::consts::PEER_TIMEOUT_MINIMUM`) milliseconds if zero
     /// - `maximum` - the timeout maximum; defaults to
-    ///   [`PEER_TIMEOUT_MAXIMUM`](`crate::consts::PEER_TIMEOUT_MAXIMUM`) if 0
+    ///   [`PEER_TIMEOUT_MAXIMUM`](`crate::consts::PEER_TIMEOUT_MAXIMUM`) milliseconds if zero
+    ///
+    /// Durations are rounded down to milliseconds.
+    pub fn set_timeout_durations(&mut self, limit: u32, minimum: Duration, maximum: Duration) {
+        let millis = |duration: Duration| u32::try_from(duration.as_millis()).unwrap_or(u32::MAX);
+        unsafe { enet_peer_timeout(self.0, limit, millis(minimum), millis(maximum)) }
+    }
+
+    /// Timeout parameters in milliseconds. See [`Peer::set_timeout_durations`].
+    #[deprecated(note = "use `Peer::set_timeout_durations`, which takes `Duration`s")]
     pub fn set_timeout(&mut self, limit: u32, minimum: u32, maximum: u32) {
         unsafe { enet_peer_timeout(self.0, limit, minimum, maximum) }
     }
 
-    /// Sets the interval at which pings will be sent to a peer in milliseconds.
+    /// Sets the interval at which pings will be sent to a peer, rounded down to milliseconds.
+    /// Defaults to [`PEER_PING_INTERVAL`](`crate::consts::PEER_PING_INTERVAL`) milliseconds if
+    /// zero.
     ///
     /// Pings are used both to monitor the liveness of the connection and also to dynamically adjust
     /// the throttle during periods of low traffic so that the throttle has reasonable
     /// responsiveness during traffic spikes.
     ///
     /// See [`Peer::ping`].
-    pub fn set_ping_interval(&mut self, ping_interval: u32) {
+    pub fn set_ping_interval(&mut self, ping_interval: Duration) {
+        let ping_interval = u32::try_from(ping_interval.as_millis()).unwrap_or(u32::MAX);
         unsafe { enet_peer_ping_interval(self.0, ping_interval) }
     }
 
+    /// Time the peer out once `missed_ping_limit` ping intervals pass without it acknowledging
+    /// anything, or [`None`] to only rely on [`Peer::set_timeout_durations`]. Reset to
+    /// [`HostSettings::missed_ping_limit`](`crate::HostSettings::missed_ping_limit`) when the
+    /// peer disconnects.
+    pub fn set_missed_ping_limit(&mut self, missed_ping_limit: Option<u32>) {
+        unsafe { (*self.0).missed_ping_limit = missed_ping_limit }
+    }
+
     /// Configure the peer's throttle parameters.
     ///
     /// Unreliable packets are dropped by ENet in response to the varying conditions of the
@@ -176,6 +683,57 @@ impl<S: Socket> Peer<S> {
         unsafe { enet_peer_throttle_configure(self.0, interval, acceleration, deceleration) }
     }
 
+    /// The current probability that unreliable packets are sent to this peer, as a ratio with
+    /// respect to [`PEER_PACKET_THROTTLE_SCALE`](`crate::consts::PEER_PACKET_THROTTLE_SCALE`).
+    /// Unreliable packets which aren't sent are dropped locally.
+    ///
+    /// Adjusted as the mean RTT changes, see [`Peer::set_throttle`], and never raised above
+    /// [`Peer::packet_throttle_limit`].
+    #[must_use]
+    pub fn packet_throttle(&self) -> u32 {
+        unsafe { (*self.0).packet_throttle }
+    }
+
+    /// The highest [`Peer::packet_throttle`] can rise to, as a ratio with respect to
+    /// [`PEER_PACKET_THROTTLE_SCALE`](`crate::consts::PEER_PACKET_THROTTLE_SCALE`).
+    ///
+    /// Recalculated from the bandwidth limits of both hosts every
+    /// [`HOST_BANDWIDTH_THROTTLE_INTERVAL`](`crate::consts::HOST_BANDWIDTH_THROTTLE_INTERVAL`)
+    /// milliseconds, and only below the scale while either limit is being exceeded.
+    #[must_use]
+    pub fn packet_throttle_limit(&self) -> u32 {
+        unsafe { (*self.0).packet_throttle_limit }
+    }
+
+    /// Whether the last bandwidth throttle limited [`Peer::packet_throttle_limit`] by this peer's
+    /// own incoming bandwidth, rather than by its share of the host's outgoing bandwidth.
+    #[must_use]
+    pub fn bandwidth_limited(&self) -> bool {
+        unsafe {
+            (*self.0).outgoing_bandwidth_throttle_epoch != 0
+                && (*self.0).outgoing_bandwidth_throttle_epoch
+                    == (*(*self.0).host).bandwidth_throttle_epoch
+        }
+    }
+
+    /// The throttle interval, in milliseconds. See [`Peer::set_throttle`].
+    #[must_use]
+    pub fn throttle_interval(&self) -> u32 {
+        unsafe { (*self.0).packet_throttle_interval }
+    }
+
+    /// The throttle acceleration. See [`Peer::set_throttle`].
+    #[must_use]
+    pub fn throttle_acceleration(&self) -> u32 {
+        unsafe { (*self.0).packet_throttle_acceleration }
+    }
+
+    /// The throttle deceleration. See [`Peer::set_throttle`].
+    #[must_use]
+    pub fn throttle_deceleration(&self) -> u32 {
+        unsafe { (*self.0).packet_throttle_deceleration }
+    }
+
     /// The maximum transmission unit of this peer. See [`Host::mtu`](`crate::Host::mtu`).
     #[must_use]
     pub fn mtu(&self) -> u16 {
@@ -202,6 +760,257 @@ impl<S: Socket> Peer<S> {
         Ok(())
     }
 
+    /// The maximum size of packets this peer may send to the local host, which is the smaller of
+    /// the limit set with [`Peer::set_maximum_packet_size`] and
+    /// [`HostSettings::maximum_packet_size`](`crate::HostSettings::maximum_packet_size`).
+    #[must_use]
+    pub fn maximum_packet_size(&self) -> usize {
+        unsafe { enet_peer_maximum_packet_size(self.0) }
+    }
+
+    /// Limit the size of packets this peer may send to the local host, or [`None`] to only use
+    /// [`HostSettings::maximum_packet_size`](`crate::HostSettings::maximum_packet_size`).
+    ///
+    /// Larger packets are rejected, fragmented ones before any memory is allocated for them, and
+    /// reported as
+    /// [`ProtocolViolationKind::PacketTooLarge`](`crate::ProtocolViolationKind::PacketTooLarge`).
+    /// The limit is cleared when the peer is reset.
+    pub fn set_maximum_packet_size(&mut self, maximum_packet_size: Option<usize>) {
+        unsafe {
+            (*self.0).maximum_packet_size = maximum_packet_size.unwrap_or(usize::MAX);
+        }
+    }
+
+    /// The maximum number of reliable bytes in flight to this peer at once, before scaling by the
+    /// packet throttle.
+    ///
+    /// This is the window size set with [`Peer::set_window_size`], or otherwise the window size
+    /// negotiated on connect, which is derived from the bandwidth limits of both hosts.
+    #[must_use]
+    pub fn window_size(&self) -> u32 {
+        unsafe { enet_peer_window_size(self.0) }
+    }
+
+    /// Override the window size negotiated on connect, or [`None`] to use it again. The window
+    /// size is clamped between
+    /// [`PROTOCOL_MINIMUM_WINDOW_SIZE`](`crate::consts::PROTOCOL_MINIMUM_WINDOW_SIZE`) and
+    /// [`PROTOCOL_MAXIMUM_WINDOW_SIZE`](`crate::consts::PROTOCOL_MAXIMUM_WINDOW_SIZE`).
+    ///
+    /// The negotiated window size is derived from the bandwidth limits of both hosts, which can
+    /// cap throughput on links with a high bandwidth-delay product. The override is cleared when
+    /// the peer is reset.
+    pub fn set_window_size(&mut self, window_size: Option<u32>) {
+        unsafe {
+            (*self.0).window_size_override = window_size.map(|window_size| {
+                window_size.clamp(PROTOCOL_MINIMUM_WINDOW_SIZE, PROTOCOL_MAXIMUM_WINDOW_SIZE)
+            });
+        }
+    }
+
+    /// How long packets queued for this peer may be held to coalesce into fewer datagrams, which
+    /// is the delay set with [`Peer::set_coalesce_delay`], or
+    /// [`HostSettings::coalesce_delay`](`crate::HostSettings::coalesce_delay`) if none is set.
+    #[must_use]
+    pub fn coalesce_delay(&self) -> Duration {
+        Duration::from_millis(u64::from(unsafe { enet_peer_coalesce_delay(self.0) }))
+    }
+
+    /// Set how long packets queued for this peer may be held to coalesce into fewer datagrams, or
+    /// [`None`] to use
+    /// [`HostSettings::coalesce_delay`](`crate::HostSettings::coalesce_delay`). The delay is
+    /// cleared when the peer is reset.
+    pub fn set_coalesce_delay(&mut self, coalesce_delay: Option<Duration>) {
+        unsafe {
+            (*self.0).coalesce_delay =
+                coalesce_delay.map(|delay| u32::try_from(delay.as_millis()).unwrap_or(u32::MAX));
+        }
+    }
+
+    /// The DSCP datagrams sent to this peer are marked with, set with [`Peer::set_dscp`].
+    #[must_use]
+    pub fn dscp(&self) -> Option<u8> {
+        unsafe { (*self.0).dscp }
+    }
+
+    /// Mark datagrams sent to this peer with a Differentiated Services Code Point, such as `46`
+    /// for Expedited Forwarding, so routers can prioritize latency sensitive peers over bulk
+    /// transfers, or [`None`] to send them unmarked. The DSCP is cleared when the peer is reset.
+    ///
+    /// Marked datagrams are sent one at a time with [`Socket::send_with_traffic_class`], rather
+    /// than batched, and are only marked by sockets which support it. Routers may ignore or
+    /// rewrite the marking.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BadParameter`] if `dscp` is greater than `63`.
+    pub fn set_dscp(&mut self, dscp: Option<u8>) -> Result<(), BadParameter> {
+        if dscp.is_some_and(|dscp| dscp > 63) {
+            return Err(BadParameter {
+                method: "Peer::set_dscp",
+                parameter: "dscp",
+            });
+        }
+        unsafe {
+            (*self.0).dscp = dscp;
+        }
+        Ok(())
+    }
+
+    /// Whether this peer is one of the two ends of a connection from the host to itself, made with
+    /// [`Host::connect_loopback`](`crate::Host::connect_loopback`).
+    #[must_use]
+    pub fn is_loopback(&self) -> bool {
+        unsafe { (*self.0).loopback }
+    }
+
+    /// Simulate a poor connection to this peer, for playtesting how the application copes with a
+    /// specific client's lag or packet loss, without a wrapper around the host's socket.
+    ///
+    /// Datagrams to and from the peer are dropped with a `loss_percent` chance, and datagrams to
+    /// the peer are held back for `min_latency`, plus up to `jitter` chosen at random for each.
+    /// Held datagrams are sent by the first [`Host::service`](`crate::Host::service`) after
+    /// their delay. Passing `0` and zero durations stops the simulation, which is also stopped
+    /// when the peer is reset.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BadParameter`] if `loss_percent` is greater than `100`.
+    pub fn simulate(
+        &mut self,
+        loss_percent: u8,
+        min_latency: Duration,
+        jitter: Duration,
+    ) -> Result<(), BadParameter> {
+        if loss_percent > 100 {
+            return Err(BadParameter {
+                method: "Peer::simulate",
+                parameter: "loss_percent",
+            });
+        }
+        let simulation = PeerSimulation {
+            loss_percent,
+            min_latency,
+            jitter,
+        };
+        unsafe {
+            (*self.0).simulation = (simulation != PeerSimulation::default()).then_some(simulation);
+        }
+        Ok(())
+    }
+
+    /// The network conditions simulated for this peer with [`Peer::simulate`], if any.
+    #[must_use]
+    pub fn simulation(&self) -> Option<PeerSimulation> {
+        unsafe { (*self.0).simulation }
+    }
+
+    /// Set a payload attached to every ping sent to this peer, such as a server load hint or a
+    /// presence flag, to avoid a separate keep-alive packet. The payload is only sent if
+    /// [`HostSettings::ping_payloads`](`crate::HostSettings::ping_payloads`) is enabled, and is
+    /// cleared when the peer is reset. An empty payload sends plain pings.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BadParameter`] if `payload` is longer than [`PEER_MAXIMUM_PING_PAYLOAD`].
+    pub fn set_ping_payload(&mut self, payload: &[u8]) -> Result<(), BadParameter> {
+        if payload.len() > PEER_MAXIMUM_PING_PAYLOAD {
+            return Err(BadParameter {
+                method: "Peer::set_ping_payload",
+                parameter: "payload",
+            });
+        }
+        unsafe {
+            let ping_payload = (*self.0).ping_payload.assume_init_mut();
+            ping_payload.clear();
+            ping_payload.extend_from_slice(payload);
+        }
+        Ok(())
+    }
+
+    /// The payload attached to the last ping received from this peer, or [`None`] if it hasn't
+    /// sent one. See [`Peer::set_ping_payload`].
+    #[must_use]
+    pub fn remote_ping_payload(&self) -> Option<&[u8]> {
+        unsafe { (*self.0).remote_ping_payload.assume_init_ref().as_deref() }
+    }
+
+    /// The estimated difference between this peer's clock and the local host's, in milliseconds,
+    /// or [`None`] if [`HostSettings::clock_sync`](`crate::HostSettings::clock_sync`) is disabled
+    /// or no ping has completed a round trip yet.
+    ///
+    /// Adding the offset to the local host's time, truncated to 32 bits of milliseconds like
+    /// [`Host::now`](`crate::Host::now`), gives the peer's time. Each ping's round trip is assumed
+    /// to be symmetric, and samples are smoothed the same way as the round trip time.
+    #[must_use]
+    pub fn estimated_clock_offset(&self) -> Option<i64> {
+        unsafe { (*self.0).clock_offset }
+    }
+
+    /// The variant of ENet's protocol spoken with this peer, negotiated while connecting. See
+    /// [`ProtocolVersion`] for more info.
+    ///
+    /// Connections start on [`ProtocolVersion::Enet`], and
+    /// connections made by the local host are upgraded shortly after connecting if both hosts
+    /// speak a later version.
+    #[must_use]
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        unsafe { (*self.0).protocol_version }
+    }
+
+    /// The ID of the session this connection belongs to, or [`None`] if it doesn't have one.
+    ///
+    /// Sessions are given to the connections accepted by hosts with
+    /// [`HostSettings::session_resumption`](`crate::HostSettings::session_resumption`) enabled,
+    /// once connected. IDs are unique to the host.
+    #[must_use]
+    pub fn session(&self) -> Option<u64> {
+        unsafe { (*self.0).session.map(|session| session.id) }
+    }
+
+    /// Whether this connection resumed a session which ended gracefully, keeping its
+    /// [`Peer::session`]. See
+    /// [`HostSettings::session_resumption`](`crate::HostSettings::session_resumption`).
+    #[must_use]
+    pub fn resumed(&self) -> bool {
+        unsafe { (*self.0).resumed }
+    }
+
+    /// The most recent unsequenced group received from this peer, which is the start of its
+    /// incoming unsequenced window.
+    ///
+    /// Unsequenced packets more than
+    /// [`HostSettings::unsequenced_window_size`](`crate::HostSettings::unsequenced_window_size`)
+    /// groups behind this are discarded, see [`Sequencing`](`crate#sequencing`).
+    #[must_use]
+    pub fn incoming_unsequenced_group(&self) -> u16 {
+        unsafe { (*self.0).incoming_unsequenced_group }
+    }
+
+    /// The unsequenced group of the last unsequenced packet sent to this peer.
+    #[must_use]
+    pub fn outgoing_unsequenced_group(&self) -> u16 {
+        unsafe { (*self.0).outgoing_unsequenced_group }
+    }
+
+    /// Bytes of received packets waiting to be returned by
+    /// [`Host::service`](`crate::Host::service`). Once this reaches
+    /// [`HOST_DEFAULT_MAXIMUM_WAITING_DATA`](`crate::consts::HOST_DEFAULT_MAXIMUM_WAITING_DATA`),
+    /// further packets from the peer are dropped.
+    #[must_use]
+    pub fn incoming_waiting_bytes(&self) -> usize {
+        unsafe { (*self.0).total_waiting_data }
+    }
+
+    /// Bytes currently held in this peer's queued incoming and outgoing commands, including
+    /// partially reassembled fragments and packets waiting to be sent or acknowledged.
+    ///
+    /// Bounded with
+    /// [`HostSettings::peer_memory_budget`](`crate::HostSettings::peer_memory_budget`).
+    #[must_use]
+    pub fn memory_usage(&self) -> usize {
+        unsafe { (*self.0).memory_usage }
+    }
+
     /// Get the current state of the peer.
     #[must_use]
     pub fn state(&self) -> PeerState {
@@ -264,12 +1073,51 @@ impl<S: Socket> Peer<S> {
         unsafe { (*self.0).packets_sent }
     }
 
+    /// Statistics for the channel with the ID specified, or [`None`] if the peer has no such
+    /// channel.
+    #[must_use]
+    pub fn channel_stats(&self, channel_id: u8) -> Option<ChannelStats> {
+        unsafe {
+            if usize::from(channel_id) >= (*self.0).channel_count {
+                return None;
+            }
+            let channel = (*self.0).channels.add(usize::from(channel_id));
+            Some(ChannelStats {
+                packets_sent: (*channel).packets_sent,
+                packets_received: (*channel).packets_received,
+                reliable_resends: (*channel).reliable_resends,
+                incoming_queued: enet_list_size(&raw mut (*channel).incoming_reliable_commands)
+                    + enet_list_size(&raw mut (*channel).incoming_unreliable_commands),
+                next_reliable_sequence_number: (*channel)
+                    .outgoing_reliable_sequence_number
+                    .wrapping_add(1),
+                next_unreliable_sequence_number: (*channel)
+                    .outgoing_unreliable_sequence_number
+                    .wrapping_add(1),
+            })
+        }
+    }
+
     /// Total number of packets lost.
     #[must_use]
     pub fn packets_lost(&self) -> u32 {
         unsafe { (*self.0).packets_lost }
     }
 
+    /// Total number of times reliable commands were resent after their acknowledgements timed
+    /// out. See also [`Peer::channel_stats`].
+    #[must_use]
+    pub fn reliable_resends(&self) -> u32 {
+        unsafe { (*self.0).reliable_resends }
+    }
+
+    /// Total number of unsequenced packets from this peer dropped as duplicates. See
+    /// [`HostSettings::unsequenced_dedup_window`](`crate::HostSettings::unsequenced_dedup_window`).
+    #[must_use]
+    pub fn unsequenced_duplicates(&self) -> u32 {
+        unsafe { (*self.0).unsequenced_duplicates }
+    }
+
     /// Mean packet loss of reliable packets as a ratio with respect to the constant
     /// [`PEER_PACKET_LOSS_SCALE`](crate::consts::PEER_PACKET_LOSS_SCALE).
     #[must_use]
@@ -289,6 +1137,30 @@ impl<S: Socket> Peer<S> {
         Duration::from_millis(u64::from(unsafe { (*self.0).ping_interval }))
     }
 
+    /// Timeout limit. See [`Peer::set_timeout_durations`].
+    #[must_use]
+    pub fn timeout_limit(&self) -> u32 {
+        unsafe { (*self.0).timeout_limit }
+    }
+
+    /// Timeout minimum. See [`Peer::set_timeout_durations`].
+    #[must_use]
+    pub fn timeout_minimum(&self) -> Duration {
+        Duration::from_millis(u64::from(unsafe { (*self.0).timeout_minimum }))
+    }
+
+    /// Timeout maximum. See [`Peer::set_timeout_durations`].
+    #[must_use]
+    pub fn timeout_maximum(&self) -> Duration {
+        Duration::from_millis(u64::from(unsafe { (*self.0).timeout_maximum }))
+    }
+
+    /// Missed ping limit. See [`Peer::set_missed_ping_limit`].
+    #[must_use]
+    pub fn missed_ping_limit(&self) -> Option<u32> {
+        unsafe { (*self.0).missed_ping_limit }
+    }
+
     /// Mean round trip time (RTT), between sending a reliable packet and receiving its
     /// acknowledgement.
     #[must_use]
@@ -302,6 +1174,13 @@ impl<S: Socket> Peer<S> {
         Duration::from_millis(u64::from(unsafe { (*self.0).round_trip_time_variance }))
     }
 
+    /// The number of datagrams sent to this peer which it reported were marked Congestion
+    /// Experienced by routers on the path, see [`HostSettings::ecn`](`crate::HostSettings::ecn`).
+    #[must_use]
+    pub fn congestion_marks(&self) -> u32 {
+        unsafe { (*self.0).outgoing_congestion_marks }
+    }
+
     /// Address of the remote peer, or [`None`] if this peer has never been connected.
     ///
     /// If the peer has disconnected, the previously connected peer's address will be returned.
@@ -311,13 +1190,70 @@ impl<S: Socket> Peer<S> {
     pub fn address(&self) -> Option<S::Address> {
         unsafe { (*self.0).address.assume_init_ref().clone() }
     }
+
+    /// The local address the last datagram from this peer was sent to, or [`None`] if the socket
+    /// doesn't supply it with
+    /// [`ReceiveMetadata::destination`](`crate::ReceiveMetadata::destination`).
+    ///
+    /// Useful for hosts with several addresses, to tell which one a peer reaches them at.
+    #[must_use]
+    pub fn local_address(&self) -> Option<S::Address> {
+        unsafe { (*self.0).local_address.assume_init_ref().clone() }
+    }
+
+    /// The TTL or hop limit of the last datagram received from this peer, or [`None`] if the
+    /// socket doesn't supply it with
+    /// [`ReceiveMetadata::hop_limit`](`crate::ReceiveMetadata::hop_limit`).
+    #[must_use]
+    pub fn hop_limit(&self) -> Option<u8> {
+        unsafe { (*self.0).hop_limit }
+    }
+
+    /// Add another address this peer can be reached at, such as the LAN and WAN addresses of
+    /// the same host, making the peer multihomed.
+    ///
+    /// Once a peer has more than one address, each is probed every second, and the peer moves to
+    /// the address with the fastest round trip, or to any address which answers if its current
+    /// address stops answering. [`Peer::address`] returns the address in use. The peer's host
+    /// must speak [`ProtocolVersion::Extended`] to answer probes, and if moving changes the
+    /// address it sees datagrams arriving from, it must enable
+    /// [`HostSettings::connection_migration`](`crate::HostSettings::connection_migration`).
+    ///
+    /// Addresses are forgotten when the peer disconnects.
+    pub fn add_address(&mut self, address: S::Address) {
+        unsafe { enet_peer_add_path(self.0, address) }
+    }
+
+    /// Stop probing one of the addresses added with [`Peer::add_address`]. If it's the address
+    /// in use, the peer moves to another address at the next probe.
+    pub fn remove_address(&mut self, address: &S::Address) {
+        unsafe {
+            (*self.0)
+                .paths
+                .assume_init_mut()
+                .retain(|path| !path.address.same(address));
+        }
+    }
+
+    /// The addresses this peer can be reached at, see [`Peer::add_address`], with the round trip
+    /// time measured by probing each, or [`None`] if the last probe wasn't answered.
+    pub fn addresses(&self) -> impl Iterator<Item = (&S::Address, Option<Duration>)> {
+        unsafe { (*self.0).paths.assume_init_ref() }
+            .iter()
+            .map(|path| {
+                (
+                    &path.address,
+                    path.round_trip_time
+                        .map(|round_trip_time| Duration::from_millis(u64::from(round_trip_time))),
+                )
+            })
+    }
 }
 
 impl<S: Socket> Debug for Peer<S> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let peer = unsafe { &(*self.0) };
         f.debug_struct("Peer")
-            .field("dispatchList", &core::ptr::addr_of!(peer.dispatch_list))
             .field("host", &peer.host)
             .field("outgoingPeerID", &peer.outgoing_peer_id)
             .field("incomingPeerID", &peer.incoming_peer_id)
@@ -410,7 +1346,9 @@ impl<S: Socket> Debug for Peer<S> {
             .field("reserved", &peer.reserved)
             .field("incomingUnsequencedGroup", &peer.incoming_unsequenced_group)
             .field("outgoingUnsequencedGroup", &peer.outgoing_unsequenced_group)
-            .field("unsequencedWindow", &peer.unsequenced_window)
+            .field("unsequencedWindow", unsafe {
+                peer.unsequenced_window.assume_init_ref()
+            })
             .field("eventData", &peer.event_data)
             .field("totalWaitingData", &peer.total_waiting_data)
             .finish()
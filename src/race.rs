@@ -0,0 +1,171 @@
+use core::{mem::take, time::Duration};
+
+use crate::{ConnectionStatus, Host, PeerID, PendingConnection, Socket, Vec, VecDeque};
+
+/// A connection racing several addresses of the same foreign host, returned by
+/// [`Host::connect_racing`](`crate::Host::connect_racing`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RacingConnection {
+    race: u32,
+}
+
+impl RacingConnection {
+    /// The ID of the peer whose attempt won the race, or [`None`] if none has connected yet.
+    #[must_use]
+    pub fn peer_id<S: Socket>(&self, host: &Host<S>) -> Option<PeerID> {
+        host.races().winner(self.race).flatten()
+    }
+
+    /// Get the status of the connection on the host which started it.
+    #[must_use]
+    pub fn status<S: Socket>(&self, host: &Host<S>) -> ConnectionStatus {
+        match host.races().winner(self.race) {
+            Some(Some(peer)) => PendingConnection::new(peer).status(host),
+            Some(None) => ConnectionStatus::Connecting,
+            None => ConnectionStatus::Failed,
+        }
+    }
+}
+
+struct Race<A> {
+    id: u32,
+    /// The addresses not attempted yet, in order of preference.
+    addresses: VecDeque<A>,
+    /// The peers of the attempts still in progress.
+    attempts: Vec<PeerID>,
+    winner: Option<PeerID>,
+    next_attempt: Duration,
+    channel_count: usize,
+    data: u32,
+}
+
+/// The next attempt of a race, taken with [`RaceState::next_attempt`].
+pub(crate) struct RaceAttempt<A> {
+    pub(crate) race: u32,
+    pub(crate) address: A,
+    pub(crate) channel_count: usize,
+    pub(crate) data: u32,
+}
+
+pub(crate) struct RaceState<A> {
+    next_race: u32,
+    races: Vec<Race<A>>,
+}
+
+impl<A> RaceState<A> {
+    pub(crate) fn new() -> Self {
+        Self {
+            next_race: 0,
+            races: Vec::new(),
+        }
+    }
+
+    /// Whether any race has addresses left to attempt.
+    pub(crate) fn has_pending(&self) -> bool {
+        self.races.iter().any(|race| !race.addresses.is_empty())
+    }
+
+    /// Track a race whose first attempt has been started, with the remaining `addresses`.
+    pub(crate) fn start(
+        &mut self,
+        peer: PeerID,
+        addresses: VecDeque<A>,
+        channel_count: usize,
+        data: u32,
+        next_attempt: Duration,
+    ) -> RacingConnection {
+        let id = self.next_race;
+        self.next_race = self.next_race.wrapping_add(1);
+        self.races.push(Race {
+            id,
+            addresses,
+            attempts: Vec::from([peer]),
+            winner: None,
+            next_attempt,
+            channel_count,
+            data,
+        });
+        RacingConnection { race: id }
+    }
+
+    /// The winner of a race, or [`None`] if the race failed or is no longer tracked.
+    fn winner(&self, race: u32) -> Option<Option<PeerID>> {
+        self.races
+            .iter()
+            .find(|existing| existing.id == race)
+            .map(|race| race.winner)
+    }
+
+    /// Take the next address of a race which is due another attempt, because the delay since its
+    /// last attempt has passed or all of its attempts so far have failed. The attempt started
+    /// should be tracked with [`RaceState::track`].
+    pub(crate) fn next_attempt(
+        &mut self,
+        now: Duration,
+        delay: Duration,
+    ) -> Option<RaceAttempt<A>> {
+        let race = self.races.iter_mut().find(|race| {
+            !race.addresses.is_empty() && (race.attempts.is_empty() || now >= race.next_attempt)
+        })?;
+        race.next_attempt = now + delay;
+        Some(RaceAttempt {
+            race: race.id,
+            address: race.addresses.pop_front()?,
+            channel_count: race.channel_count,
+            data: race.data,
+        })
+    }
+
+    pub(crate) fn track(&mut self, race: u32, peer: PeerID) {
+        if let Some(race) = self.races.iter_mut().find(|existing| existing.id == race) {
+            race.attempts.push(peer);
+        }
+    }
+
+    /// Make a peer which connected the winner of its race, returning the other attempts to be
+    /// cancelled.
+    pub(crate) fn connect(&mut self, peer: PeerID) -> Vec<PeerID> {
+        let Some(race) = self
+            .races
+            .iter_mut()
+            .find(|race| race.attempts.contains(&peer))
+        else {
+            return Vec::new();
+        };
+        race.winner = Some(peer);
+        race.addresses.clear();
+        race.attempts.retain(|attempt| *attempt != peer);
+        take(&mut race.attempts)
+    }
+
+    /// Handle a peer which disconnected, returning `true` if it was an attempt of a race which
+    /// hasn't failed yet, so the disconnection shouldn't be reported.
+    pub(crate) fn disconnect(&mut self, peer: PeerID) -> bool {
+        let Some(index) = self
+            .races
+            .iter()
+            .position(|race| race.winner == Some(peer) || race.attempts.contains(&peer))
+        else {
+            return false;
+        };
+        let race = &mut self.races[index];
+        race.attempts.retain(|attempt| *attempt != peer);
+        if race.winner.is_none() && !(race.attempts.is_empty() && race.addresses.is_empty()) {
+            return true;
+        }
+        self.races.remove(index);
+        false
+    }
+
+    /// Forget attempts and winners whose peer slot has been reused since, and races which have
+    /// failed.
+    pub(crate) fn retain(&mut self, current: impl Fn(PeerID) -> bool) {
+        self.races.retain_mut(|race| {
+            race.attempts.retain(|attempt| current(*attempt));
+            match race.winner {
+                Some(peer) => current(peer),
+                None => !race.attempts.is_empty() || !race.addresses.is_empty(),
+            }
+        });
+    }
+}
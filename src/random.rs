@@ -0,0 +1,18 @@
+/// Fill `bytes` from the operating system's random number generator, for use with
+/// [`HostSettings::secure_random`](`crate::HostSettings::secure_random`).
+///
+/// # Panics
+///
+/// Panics if the operating system's random number generator fails, or in a `no_std`
+/// environment without the `crypto` feature, where there is none to use.
+pub fn os_random(bytes: &mut [u8]) {
+    #[cfg(any(feature = "std", feature = "crypto"))]
+    {
+        getrandom::getrandom(bytes).expect("The system random number generator failed.");
+    }
+    #[cfg(not(any(feature = "std", feature = "crypto")))]
+    {
+        _ = bytes;
+        panic!("ENet host must be provided a custom secure random function in no_std environment.");
+    }
+}
@@ -29,6 +29,7 @@ use crate::{Address, PacketReceived, Socket, SocketOptions, Vec, MTU_MAX};
 pub struct ReadWrite<A: Address, E: SocketError> {
     inbound: VecDeque<(A, Vec<u8>)>,
     outbound: VecDeque<(A, Vec<u8>)>,
+    unreachable: VecDeque<A>,
     error: Option<E>,
 }
 
@@ -49,6 +50,12 @@ impl<A: Address, E: SocketError> ReadWrite<A, E> {
         self.outbound.pop_front()
     }
 
+    /// Report that packets can't be delivered to an address, such as after receiving an ICMP
+    /// port unreachable message. See [`Socket::receive_unreachable`].
+    pub fn write_unreachable(&mut self, address: A) {
+        self.unreachable.push_back(address);
+    }
+
     /// Send an error to the ENet host, which will bubble up as a receive error.
     pub fn error(&mut self, error: E) {
         self.error = Some(error);
@@ -60,6 +67,7 @@ impl<A: Address, E: SocketError> Default for ReadWrite<A, E> {
         Self {
             inbound: VecDeque::new(),
             outbound: VecDeque::new(),
+            unreachable: VecDeque::new(),
             error: None,
         }
     }
@@ -104,4 +112,8 @@ impl<A: Address + 'static, E: SocketError> Socket for ReadWrite<A, E> {
             Ok(None)
         }
     }
+
+    fn receive_unreachable(&mut self) -> Result<Option<A>, E> {
+        Ok(self.unreachable.pop_front())
+    }
 }
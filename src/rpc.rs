@@ -0,0 +1,176 @@
+use core::time::Duration;
+
+use crate::{error::RpcError, Box, Packet, PacketKind, PeerID, Vec, VecDeque};
+
+/// Identifies a remote call made with [`Peer::call`](`crate::Peer::call`), reported back in
+/// [`Event::Reply`](`crate::Event::Reply`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CallID(pub u32);
+
+const RPC_REQUEST: u8 = 0;
+const RPC_RESPONSE: u8 = 1;
+const RPC_UNKNOWN_METHOD: u8 = 2;
+const RPC_HEADER_SIZE: usize = 5;
+const RPC_REQUEST_HEADER_SIZE: usize = RPC_HEADER_SIZE + 2;
+
+pub(crate) type RpcHandler = Box<dyn FnMut(PeerID, &[u8]) -> Vec<u8> + Send>;
+
+struct PendingCall {
+    peer: PeerID,
+    call: CallID,
+    deadline: Duration,
+}
+
+pub(crate) struct RpcReply {
+    pub(crate) peer: PeerID,
+    pub(crate) call: CallID,
+    pub(crate) result: Result<Packet, RpcError>,
+}
+
+pub(crate) struct RpcState {
+    pub(crate) channel_id: Option<u8>,
+    pub(crate) timeout: Duration,
+    next_call: u32,
+    pending: Vec<PendingCall>,
+    handlers: Vec<(u16, RpcHandler)>,
+    replies: VecDeque<RpcReply>,
+}
+
+impl RpcState {
+    pub(crate) fn new() -> Self {
+        Self {
+            channel_id: None,
+            timeout: Duration::ZERO,
+            next_call: 0,
+            pending: Vec::new(),
+            handlers: Vec::new(),
+            replies: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn register_handler(&mut self, method_id: u16, handler: RpcHandler) {
+        if let Some((_, existing)) = self.handlers.iter_mut().find(|(id, _)| *id == method_id) {
+            *existing = handler;
+        } else {
+            self.handlers.push((method_id, handler));
+        }
+    }
+
+    /// Encode a request, which should be tracked with [`RpcState::track`] once it is queued.
+    pub(crate) fn request(&mut self, method_id: u16, payload: &[u8]) -> (CallID, Packet) {
+        let call = CallID(self.next_call);
+        self.next_call = self.next_call.wrapping_add(1);
+        let mut data = Vec::with_capacity(RPC_REQUEST_HEADER_SIZE + payload.len());
+        data.push(RPC_REQUEST);
+        data.extend_from_slice(&call.0.to_be_bytes());
+        data.extend_from_slice(&method_id.to_be_bytes());
+        data.extend_from_slice(payload);
+        (call, Packet::from_shared(data, PacketKind::Reliable))
+    }
+
+    pub(crate) fn track(&mut self, peer: PeerID, call: CallID, now: Duration) {
+        self.pending.push(PendingCall {
+            peer,
+            call,
+            deadline: now + self.timeout,
+        });
+    }
+
+    /// Handle a packet received on the RPC channel, returning a response to send back if it was a
+    /// request.
+    pub(crate) fn receive(&mut self, peer: PeerID, data: &[u8]) -> Option<Packet> {
+        if data.len() < RPC_HEADER_SIZE {
+            return None;
+        }
+        let call = CallID(u32::from_be_bytes([data[1], data[2], data[3], data[4]]));
+        match data[0] {
+            RPC_REQUEST if data.len() >= RPC_REQUEST_HEADER_SIZE => {
+                let method_id = u16::from_be_bytes([data[5], data[6]]);
+                let mut response = Vec::new();
+                if let Some((_, handler)) =
+                    self.handlers.iter_mut().find(|(id, _)| *id == method_id)
+                {
+                    let payload = handler(peer, &data[RPC_REQUEST_HEADER_SIZE..]);
+                    response.reserve_exact(RPC_HEADER_SIZE + payload.len());
+                    response.push(RPC_RESPONSE);
+                    response.extend_from_slice(&call.0.to_be_bytes());
+                    response.extend_from_slice(&payload);
+                } else {
+                    response.push(RPC_UNKNOWN_METHOD);
+                    response.extend_from_slice(&call.0.to_be_bytes());
+                }
+                Some(Packet::from_shared(response, PacketKind::Reliable))
+            }
+            RPC_RESPONSE => {
+                self.resolve(
+                    peer,
+                    call,
+                    Ok(Packet::new(&data[RPC_HEADER_SIZE..], PacketKind::Reliable)),
+                );
+                None
+            }
+            RPC_UNKNOWN_METHOD => {
+                self.resolve(peer, call, Err(RpcError::UnknownMethod));
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn resolve(&mut self, peer: PeerID, call: CallID, result: Result<Packet, RpcError>) {
+        if let Some(index) = self
+            .pending
+            .iter()
+            .position(|pending| pending.peer == peer && pending.call == call)
+        {
+            self.pending.swap_remove(index);
+            self.replies.push_back(RpcReply { peer, call, result });
+        }
+    }
+
+    pub(crate) fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Fail every call which has passed its deadline.
+    pub(crate) fn expire(&mut self, now: Duration) {
+        let replies = &mut self.replies;
+        self.pending.retain(|pending| {
+            if now >= pending.deadline {
+                replies.push_back(RpcReply {
+                    peer: pending.peer,
+                    call: pending.call,
+                    result: Err(RpcError::Timeout),
+                });
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Fail every call made to a peer which disconnected.
+    pub(crate) fn disconnect(&mut self, peer: PeerID) {
+        let replies = &mut self.replies;
+        self.pending.retain(|pending| {
+            if pending.peer == peer {
+                replies.push_back(RpcReply {
+                    peer: pending.peer,
+                    call: pending.call,
+                    result: Err(RpcError::Disconnected),
+                });
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    pub(crate) fn next_reply(&mut self) -> Option<RpcReply> {
+        self.replies.pop_front()
+    }
+
+    pub(crate) fn pending_replies(&self) -> usize {
+        self.replies.len()
+    }
+}
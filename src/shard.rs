@@ -0,0 +1,231 @@
+use core::time::Duration;
+use std::{
+    net::{SocketAddr, UdpSocket},
+    sync::{mpsc::Receiver, Arc},
+};
+
+use crate::{
+    error::{BadParameter, HostNewError, HostThreadStopped, PeerSendError, ServiceError},
+    reuse_port_socket, EventNoRef, Host, HostHandle, HostSettings, Packet, PeerID, Vec,
+};
+
+/// A peer of one of the shards of a [`ShardedHost`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShardPeer {
+    /// The index of the shard the peer is connected to.
+    pub shard: usize,
+    /// The peer's ID within its shard.
+    pub peer: PeerID,
+}
+
+/// A server split into several [`Host`]s, each running on its own thread, which share one
+/// address with `SO_REUSEPORT`. Created with [`ShardedHost::bind`].
+///
+/// Linux assigns each client to a shard by hashing its address, so all of a client's datagrams
+/// reach the same host, which owns the peer for the whole connection. Each shard's events arrive
+/// on its own receiver, so they can be handled by separate threads, while a [`ShardedHost`] can
+/// send to peers on any shard. Clients connect as they would to a single host.
+///
+/// The shard count must not change while clients are connected, as that changes which shard
+/// their datagrams are hashed to.
+///
+/// # Failed shards
+///
+/// Shards are chosen by the kernel's plain `SO_REUSEPORT` hash over the sockets bound to the
+/// address, with no steering program, so closing any one socket would reassign clients of every
+/// shard. To avoid that, the [`ShardedHost`] keeps each shard's socket bound until it and all of
+/// its clones are dropped, even after the shard's thread stops because [`Host::service`] failed.
+/// Datagrams from the failed shard's clients are then left unread until those clients time out,
+/// while clients of the other shards are unaffected.
+///
+/// ```no_run
+/// use std::thread;
+///
+/// use rusty_enet::{EventNoRef, HostSettings, ShardedHost};
+///
+/// let address = "0.0.0.0:6060".parse().unwrap();
+/// let (server, shards) = ShardedHost::bind(address, 4, HostSettings::default).unwrap();
+/// for events in shards {
+///     let server = server.clone();
+///     thread::spawn(move || {
+///         while let Ok(event) = events.recv() {
+///             if let EventNoRef::Receive { channel_id, packet, .. } = event.unwrap() {
+///                 // relay chat messages to the peers of every shard
+///                 server.broadcast(channel_id, &packet).unwrap();
+///             }
+///         }
+///     });
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShardedHost {
+    address: SocketAddr,
+    shards: Vec<HostHandle>,
+    // Keeps each shard's socket in the reuseport group if its host stops. See "Failed shards".
+    _sockets: Arc<[UdpSocket]>,
+}
+
+impl ShardedHost {
+    /// Bind `shard_count` hosts to `address`, each created with the settings returned by
+    /// `settings` and running on its own thread, as with [`HostHandle::spawn`].
+    ///
+    /// Returns the sharded host, along with the receiver for each shard's events, indexed by
+    /// shard.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostNewError::BadParameter`] if `shard_count` is `0`, or if the settings are
+    /// invalid, as with [`Host::new`].
+    ///
+    /// Returns [`HostNewError::FailedToInitializeSocket`] if a socket can't be bound with
+    /// [`reuse_port_socket`] or initialized.
+    #[allow(clippy::type_complexity)]
+    pub fn bind(
+        address: SocketAddr,
+        shard_count: usize,
+        settings: impl Fn() -> HostSettings,
+    ) -> Result<
+        (
+            ShardedHost,
+            Vec<Receiver<Result<EventNoRef, ServiceError<UdpSocket>>>>,
+        ),
+        HostNewError<UdpSocket>,
+    > {
+        Self::bind_with_poll_interval(
+            address,
+            shard_count,
+            settings,
+            HostHandle::DEFAULT_POLL_INTERVAL,
+        )
+    }
+
+    /// Bind `shard_count` hosts to `address`, like [`ShardedHost::bind`], waiting at most
+    /// `poll_interval` between calls to [`Host::service`] when no commands arrive.
+    ///
+    /// # Errors
+    ///
+    /// See [`ShardedHost::bind`].
+    #[allow(clippy::type_complexity)]
+    pub fn bind_with_poll_interval(
+        address: SocketAddr,
+        shard_count: usize,
+        settings: impl Fn() -> HostSettings,
+        poll_interval: Duration,
+    ) -> Result<
+        (
+            ShardedHost,
+            Vec<Receiver<Result<EventNoRef, ServiceError<UdpSocket>>>>,
+        ),
+        HostNewError<UdpSocket>,
+    > {
+        if shard_count == 0 {
+            return Err(HostNewError::BadParameter(BadParameter {
+                method: "ShardedHost::bind",
+                parameter: "shard_count",
+            }));
+        }
+        // bind the first socket alone, so every shard shares its port if `address` has port 0
+        let first = reuse_port_socket(address).map_err(HostNewError::FailedToInitializeSocket)?;
+        let address = first
+            .local_addr()
+            .map_err(HostNewError::FailedToInitializeSocket)?;
+        let mut sockets = vec![first];
+        for _ in 1..shard_count {
+            sockets
+                .push(reuse_port_socket(address).map_err(HostNewError::FailedToInitializeSocket)?);
+        }
+        let reserved = sockets
+            .iter()
+            .map(UdpSocket::try_clone)
+            .collect::<Result<Arc<[_]>, _>>()
+            .map_err(HostNewError::FailedToInitializeSocket)?;
+        let hosts = sockets
+            .into_iter()
+            .map(|socket| Host::new(socket, settings()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (shards, events) = hosts
+            .into_iter()
+            .map(|host| HostHandle::spawn_with_poll_interval(host, poll_interval))
+            .unzip();
+        Ok((
+            ShardedHost {
+                address,
+                shards,
+                _sockets: reserved,
+            },
+            events,
+        ))
+    }
+
+    /// Get the address every shard is bound to, with the port chosen by the system if `0` was
+    /// passed to [`ShardedHost::bind`].
+    #[must_use]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Get the number of shards.
+    #[must_use]
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Get the handle to one of the shard's hosts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the shard is out of bounds.
+    #[must_use]
+    pub fn shard(&self, shard: usize) -> &HostHandle {
+        &self.shards[shard]
+    }
+
+    /// Queue a packet to be sent to a peer of any shard. See [`HostHandle::send`], including for
+    /// the returned receiver.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostThreadStopped`] if the shard's thread has stopped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the peer's shard is out of bounds.
+    pub fn send(
+        &self,
+        peer: ShardPeer,
+        channel_id: u8,
+        packet: Packet,
+    ) -> Result<Receiver<Result<(), PeerSendError>>, HostThreadStopped> {
+        self.shards[peer.shard].send(peer.peer, channel_id, packet)
+    }
+
+    /// Request a disconnection from a peer of any shard. See [`HostHandle::disconnect`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostThreadStopped`] if the shard's thread has stopped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the peer's shard is out of bounds.
+    pub fn disconnect(&self, peer: ShardPeer, data: u32) -> Result<(), HostThreadStopped> {
+        self.shards[peer.shard].disconnect(peer.peer, data)
+    }
+
+    /// Queue a packet to be sent to the connected peers of every shard. See
+    /// [`HostHandle::broadcast`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostThreadStopped`] if any shard's thread has stopped, after queueing the packet
+    /// on the others.
+    pub fn broadcast(&self, channel_id: u8, packet: &Packet) -> Result<(), HostThreadStopped> {
+        let mut result = Ok(());
+        for shard in &self.shards {
+            if let Err(err) = shard.broadcast(channel_id, packet.clone()) {
+                result = Err(err);
+            }
+        }
+        result
+    }
+}
@@ -4,7 +4,23 @@ use std::{
     net::{SocketAddr, UdpSocket},
 };
 
-use crate::{consts::PROTOCOL_MAXIMUM_MTU, Address};
+use core::time::Duration;
+
+use crate::{consts::PROTOCOL_MAXIMUM_MTU, Address, Vec};
+
+#[cfg(all(feature = "mmsg", target_os = "linux"))]
+mod mmsg;
+#[cfg(all(feature = "offload", target_os = "linux"))]
+mod offload;
+#[cfg(all(feature = "sharding", target_os = "linux"))]
+mod reuse_port;
+
+#[cfg(all(feature = "offload", target_os = "linux"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "offload", target_os = "linux"))))]
+pub use offload::OffloadUdpSocket;
+#[cfg(all(feature = "sharding", target_os = "linux"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "sharding", target_os = "linux"))))]
+pub use reuse_port::reuse_port_socket;
 
 // This macro allows the same doc comment to apply to both variants.
 macro_rules! socket_error {
@@ -55,6 +71,44 @@ pub struct SocketOptions {
     pub receive_buffer: usize,
     /// Size of the send buffer desired by ENet.
     pub send_buffer: usize,
+    /// Whether ENet wants outgoing datagrams marked ECN-Capable Transport (`ECT(0)`), and
+    /// received datagrams marked Congestion Experienced (`CE`) returned as
+    /// [`PacketReceived::CongestionExperienced`]. Implementations which can't access the IP
+    /// header can ignore this. See [`HostSettings::ecn`](`crate::HostSettings::ecn`).
+    pub ecn: bool,
+}
+
+/// Ancillary data a [`Socket`] can supply along with each datagram it receives, with
+/// [`Socket::receive_batch_with_metadata`].
+///
+/// Every field is optional, so sockets only fill in what their platform provides, such as from
+/// control messages returned by `recvmsg`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiveMetadata<A> {
+    /// When the datagram was received, such as a kernel timestamp from `SO_TIMESTAMPNS`, on the
+    /// clock of [`HostSettings::time`](`crate::HostSettings::time`). Used in place of the time
+    /// the datagram was read from the socket, for round trip times and receive times.
+    pub timestamp: Option<Duration>,
+    /// The TTL (IPv4) or hop limit (IPv6) the datagram arrived with, see
+    /// [`Peer::hop_limit`](`crate::Peer::hop_limit`).
+    pub hop_limit: Option<u8>,
+    /// The ECN field of the datagram's IP header. Datagrams marked Congestion Experienced
+    /// (`0b11`) are handled like [`PacketReceived::CongestionExperienced`].
+    pub ecn: Option<u8>,
+    /// The local address the datagram was sent to, for hosts with several addresses, see
+    /// [`Peer::local_address`](`crate::Peer::local_address`).
+    pub destination: Option<A>,
+}
+
+impl<A> Default for ReceiveMetadata<A> {
+    fn default() -> Self {
+        Self {
+            timestamp: None,
+            hop_limit: None,
+            ecn: None,
+            destination: None,
+        }
+    }
 }
 
 /// A trait for implementing the underlying data transport layer ENet uses.
@@ -83,6 +137,25 @@ pub trait Socket: Sized {
     /// Try to send data. Should return the number of bytes successfully sent, or an error.
     fn send(&mut self, address: Self::Address, buffer: &[u8]) -> Result<usize, Self::Error>;
 
+    /// Try to send data like [`Socket::send`], with the IP header's traffic class (the IPv4 TOS
+    /// byte, or the IPv6 traffic class) set to `traffic_class`, such as with `sendmsg` ancillary
+    /// data.
+    ///
+    /// Used for peers with a DSCP set with [`Peer::set_dscp`](`crate::Peer::set_dscp`). The DSCP
+    /// is in the upper six bits, and the lower two bits are the ECN field, which is `ECT(0)` if
+    /// [`SocketOptions::ecn`] is set. The default implementation ignores the traffic class and
+    /// calls [`Socket::send`]. [`std::net::UdpSocket`] sets it on Linux with the `mmsg`
+    /// feature.
+    fn send_with_traffic_class(
+        &mut self,
+        address: Self::Address,
+        buffer: &[u8],
+        traffic_class: u8,
+    ) -> Result<usize, Self::Error> {
+        let _ = traffic_class;
+        self.send(address, buffer)
+    }
+
     /// Try to receive data from the socket into a buffer of size [`MTU_MAX`].
     ///
     /// A received packet should be written into the provided buffer. If a packet is received that
@@ -96,6 +169,84 @@ pub trait Socket: Sized {
         &mut self,
         buffer: &mut [u8; MTU_MAX],
     ) -> Result<Option<(Self::Address, PacketReceived)>, Self::Error>;
+
+    /// Try to receive an address which datagrams sent from this socket couldn't be delivered to,
+    /// such as from an ICMP port unreachable message, or `Ok(None)` if none are waiting.
+    ///
+    /// Connections still being made to the address fail immediately, rather than waiting to
+    /// time out. The default implementation never reports unreachable addresses.
+    /// [`std::net::UdpSocket`] reports them on Linux with the `mmsg` feature, using
+    /// `IP_RECVERR`.
+    fn receive_unreachable(&mut self) -> Result<Option<Self::Address>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Try to send several datagrams at once. Should return the total number of bytes
+    /// successfully sent, or an error.
+    ///
+    /// Called with up to
+    /// [`HostSettings::socket_batch_size`](`crate::HostSettings::socket_batch_size`) datagrams,
+    /// in the order they should be sent. The default implementation calls [`Socket::send`] for
+    /// each of them, but implementations can send them all at once, like with `sendmmsg` on
+    /// Linux.
+    fn send_batch(&mut self, datagrams: &[(Self::Address, Vec<u8>)]) -> Result<usize, Self::Error> {
+        let mut sent_length = 0;
+        for (address, datagram) in datagrams {
+            sent_length += self.send(address.clone(), datagram)?;
+        }
+        Ok(sent_length)
+    }
+
+    /// Try to receive several datagrams at once, one into each buffer, like with `recvmmsg` on
+    /// Linux.
+    ///
+    /// The address and size of each datagram received should be pushed to `received`, in the
+    /// order of the buffers they were written to. Receiving nothing means no datagrams are
+    /// waiting. Each buffer is treated like the one passed to [`Socket::receive`].
+    ///
+    /// If an error occurs after some datagrams were received, those should be returned instead,
+    /// leaving the error for the next call. The default implementation calls [`Socket::receive`]
+    /// until no datagram is waiting or every buffer is filled.
+    fn receive_batch(
+        &mut self,
+        buffers: &mut [[u8; MTU_MAX]],
+        received: &mut Vec<(Self::Address, PacketReceived)>,
+    ) -> Result<(), Self::Error> {
+        for buffer in buffers {
+            match self.receive(buffer) {
+                Ok(Some(datagram)) => received.push(datagram),
+                Ok(None) => break,
+                Err(err) if received.is_empty() => return Err(err),
+                Err(_) => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Try to receive several datagrams at once like [`Socket::receive_batch`], along with any
+    /// [`ReceiveMetadata`] the socket can supply for each of them.
+    ///
+    /// This is what [`Host`](`crate::Host`) calls to receive datagrams. The default
+    /// implementation calls [`Socket::receive_batch`], without any metadata, so only sockets
+    /// with access to ancillary data need to implement it.
+    fn receive_batch_with_metadata(
+        &mut self,
+        buffers: &mut [[u8; MTU_MAX]],
+        received: &mut Vec<(
+            Self::Address,
+            PacketReceived,
+            ReceiveMetadata<Self::Address>,
+        )>,
+    ) -> Result<(), Self::Error> {
+        let mut datagrams = Vec::new();
+        self.receive_batch(buffers, &mut datagrams)?;
+        received.extend(
+            datagrams
+                .into_iter()
+                .map(|(address, datagram)| (address, datagram, ReceiveMetadata::default())),
+        );
+        Ok(())
+    }
 }
 
 /// Return type of [`Socket::receive`], representing either a complete packet, or a partial
@@ -114,6 +265,34 @@ pub enum PacketReceived {
     Complete(usize),
     /// A partial packet was received.
     Partial,
+    /// A complete packet was received, which a router on the path marked Congestion Experienced
+    /// (`CE`) in its IP header's ECN field. The inner value is the size of the packet in bytes.
+    ///
+    /// Only returned by sockets initialized with [`SocketOptions::ecn`].
+    CongestionExperienced(usize),
+}
+
+impl PacketReceived {
+    /// The size of a complete packet, or [`None`] for a partial packet.
+    pub(crate) fn length(&self) -> Option<usize> {
+        match *self {
+            PacketReceived::Complete(length) | PacketReceived::CongestionExperienced(length) => {
+                Some(length)
+            }
+            PacketReceived::Partial => None,
+        }
+    }
+
+    /// The same kind of packet with a different size, for sockets wrapping another socket.
+    #[cfg_attr(not(any(feature = "crypto", feature = "noise")), allow(dead_code))]
+    pub(crate) fn with_length(&self, length: usize) -> PacketReceived {
+        match self {
+            PacketReceived::CongestionExperienced(_) => {
+                PacketReceived::CongestionExperienced(length)
+            }
+            _ => PacketReceived::Complete(length),
+        }
+    }
 }
 
 #[cfg(feature = "std")]
@@ -121,17 +300,32 @@ impl Socket for UdpSocket {
     type Address = SocketAddr;
     type Error = io::Error;
 
-    fn init(&mut self, _socket_options: SocketOptions) -> Result<(), io::Error> {
+    fn init(&mut self, socket_options: SocketOptions) -> Result<(), io::Error> {
         self.set_nonblocking(true)?;
         self.set_broadcast(true)?;
         // TODO: set receive_buffer and send_buffer (not supported by rust stdlib)
+        // ECN needs the IP header, which is only read when receiving with `recvmmsg`
+        #[cfg(all(feature = "mmsg", target_os = "linux"))]
+        mmsg::enable_errors(self)?;
+        #[cfg(all(feature = "mmsg", target_os = "linux"))]
+        if socket_options.ecn {
+            mmsg::enable_ecn(self)?;
+        }
+        #[cfg(not(all(feature = "mmsg", target_os = "linux")))]
+        let _ = socket_options;
         Ok(())
     }
 
     fn send(&mut self, address: SocketAddr, buffer: &[u8]) -> Result<usize, io::Error> {
         match self.send_to(buffer, address) {
             Ok(sent_length) => Ok(sent_length),
-            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(0),
+            // errors queued for earlier datagrams, see `Socket::receive_unreachable`
+            Err(err)
+                if err.kind() == ErrorKind::WouldBlock
+                    || err.kind() == ErrorKind::ConnectionRefused =>
+            {
+                Ok(0)
+            }
             Err(err) => Err(err),
         }
     }
@@ -145,8 +339,42 @@ impl Socket for UdpSocket {
                 // TODO: MSG_TRUNC? (not supported by rust stdlib)
                 Ok(Some((recv_addr, PacketReceived::Complete(recv_length))))
             }
-            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(err)
+                if err.kind() == ErrorKind::WouldBlock
+                    || err.kind() == ErrorKind::ConnectionRefused =>
+            {
+                Ok(None)
+            }
             Err(err) => Err(err),
         }
     }
+
+    #[cfg(all(feature = "mmsg", target_os = "linux"))]
+    fn send_with_traffic_class(
+        &mut self,
+        address: SocketAddr,
+        buffer: &[u8],
+        traffic_class: u8,
+    ) -> Result<usize, io::Error> {
+        mmsg::send_with_traffic_class(self, &address, buffer, traffic_class)
+    }
+
+    #[cfg(all(feature = "mmsg", target_os = "linux"))]
+    fn receive_unreachable(&mut self) -> Result<Option<SocketAddr>, io::Error> {
+        mmsg::receive_unreachable(self)
+    }
+
+    #[cfg(all(feature = "mmsg", target_os = "linux"))]
+    fn send_batch(&mut self, datagrams: &[(SocketAddr, Vec<u8>)]) -> Result<usize, io::Error> {
+        mmsg::send_batch(self, datagrams)
+    }
+
+    #[cfg(all(feature = "mmsg", target_os = "linux"))]
+    fn receive_batch(
+        &mut self,
+        buffers: &mut [[u8; MTU_MAX]],
+        received: &mut Vec<(SocketAddr, PacketReceived)>,
+    ) -> Result<(), io::Error> {
+        mmsg::receive_batch(self, buffers, received)
+    }
 }
@@ -0,0 +1,431 @@
+//! Batched [`UdpSocket`] I/O with Linux's `sendmmsg` and `recvmmsg`.
+
+use core::{
+    mem::{size_of, zeroed},
+    ptr,
+};
+use std::{
+    io::{self, ErrorKind},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, UdpSocket},
+    os::fd::AsRawFd,
+};
+
+use crate::{PacketReceived, Vec, MTU_MAX};
+
+pub(super) fn send_batch(
+    socket: &UdpSocket,
+    datagrams: &[(SocketAddr, Vec<u8>)],
+) -> Result<usize, io::Error> {
+    let mut addresses: Vec<libc::sockaddr_storage> = Vec::with_capacity(datagrams.len());
+    let mut iovecs: Vec<libc::iovec> = Vec::with_capacity(datagrams.len());
+    for (address, datagram) in datagrams {
+        addresses.push(to_sockaddr(address));
+        iovecs.push(libc::iovec {
+            iov_base: datagram.as_ptr().cast_mut().cast(),
+            iov_len: datagram.len(),
+        });
+    }
+    let mut messages: Vec<libc::mmsghdr> = datagrams
+        .iter()
+        .zip(addresses.iter_mut().zip(iovecs.iter_mut()))
+        .map(|((address, _), (storage, iovec))| {
+            let mut message: libc::mmsghdr = unsafe { zeroed() };
+            message.msg_hdr.msg_name = ptr::from_mut(storage).cast();
+            message.msg_hdr.msg_namelen = sockaddr_length(address);
+            message.msg_hdr.msg_iov = iovec;
+            message.msg_hdr.msg_iovlen = 1;
+            message
+        })
+        .collect();
+    let mut sent_length = 0;
+    send_messages(socket, &mut messages, &mut sent_length).map_err(|(_, err)| err)?;
+    Ok(sent_length)
+}
+
+/// Send a datagram with `sendmsg`, setting the traffic class of its IP header with ancillary
+/// data.
+pub(super) fn send_with_traffic_class(
+    socket: &UdpSocket,
+    address: &SocketAddr,
+    datagram: &[u8],
+    traffic_class: u8,
+) -> Result<usize, io::Error> {
+    let mut storage = to_sockaddr(address);
+    let mut iovec = libc::iovec {
+        iov_base: datagram.as_ptr().cast_mut().cast(),
+        iov_len: datagram.len(),
+    };
+    let mut control: [libc::cmsghdr; 2] = unsafe { zeroed() };
+    let mut header: libc::msghdr = unsafe { zeroed() };
+    header.msg_name = ptr::from_mut(&mut storage).cast();
+    header.msg_namelen = sockaddr_length(address);
+    header.msg_iov = &raw mut iovec;
+    header.msg_iovlen = 1;
+    header.msg_control = ptr::from_mut(&mut control).cast();
+    header.msg_controllen = unsafe { libc::CMSG_SPACE(socklen(size_of::<libc::c_int>())) } as usize;
+    let (level, kind) = match address {
+        SocketAddr::V4(_) => (libc::IPPROTO_IP, libc::IP_TOS),
+        SocketAddr::V6(_) => (libc::IPPROTO_IPV6, libc::IPV6_TCLASS),
+    };
+    unsafe {
+        let control = libc::CMSG_FIRSTHDR(&raw const header);
+        (*control).cmsg_level = level;
+        (*control).cmsg_type = kind;
+        (*control).cmsg_len = libc::CMSG_LEN(socklen(size_of::<libc::c_int>())) as usize;
+        libc::CMSG_DATA(control)
+            .cast::<libc::c_int>()
+            .write_unaligned(libc::c_int::from(traffic_class));
+    }
+    loop {
+        let sent = unsafe { libc::sendmsg(socket.as_raw_fd(), &raw const header, 0) };
+        if sent >= 0 {
+            return Ok(usize::try_from(sent).unwrap_or_default());
+        }
+        let err = io::Error::last_os_error();
+        match err.kind() {
+            // like `Socket::send`, datagrams which would block are dropped
+            ErrorKind::WouldBlock | ErrorKind::ConnectionRefused => return Ok(0),
+            ErrorKind::Interrupted => {}
+            _ => return Err(err),
+        }
+    }
+}
+
+/// Send `messages` with as few calls to `sendmmsg` as possible, adding the bytes sent to
+/// `sent_length`. On failure, returns the index of the first message which wasn't sent.
+pub(super) fn send_messages(
+    socket: &UdpSocket,
+    messages: &mut [libc::mmsghdr],
+    sent_length: &mut usize,
+) -> Result<(), (usize, io::Error)> {
+    let mut offset = 0;
+    while offset < messages.len() {
+        let remaining = &mut messages[offset..];
+        let sent = unsafe {
+            libc::sendmmsg(
+                socket.as_raw_fd(),
+                remaining.as_mut_ptr(),
+                u32::try_from(remaining.len()).unwrap_or(u32::MAX),
+                0,
+            )
+        };
+        if sent < 0 {
+            let err = io::Error::last_os_error();
+            match err.kind() {
+                // like `Socket::send`, datagrams which would block are dropped
+                ErrorKind::WouldBlock => return Ok(()),
+                // errors queued for earlier datagrams, see `receive_unreachable`
+                ErrorKind::Interrupted | ErrorKind::ConnectionRefused => continue,
+                _ => return Err((offset, err)),
+            }
+        }
+        let sent = usize::try_from(sent).unwrap_or_default();
+        *sent_length += remaining[..sent]
+            .iter()
+            .map(|message| message.msg_len as usize)
+            .sum::<usize>();
+        offset += sent.max(1);
+    }
+    Ok(())
+}
+
+/// The ECN codepoint for ECN-Capable Transport, `ECT(0)`.
+const ECN_CAPABLE: libc::c_int = 0b10;
+/// The ECN codepoint for Congestion Experienced, `CE`.
+const ECN_CONGESTION_EXPERIENCED: u8 = 0b11;
+
+/// Mark datagrams sent from the socket ECN-Capable Transport, and receive the traffic class of
+/// datagrams received, read by [`receive_batch`].
+pub(super) fn enable_ecn(socket: &UdpSocket) -> Result<(), io::Error> {
+    let set = |level, name, value: libc::c_int| {
+        let result = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                level,
+                name,
+                ptr::from_ref(&value).cast(),
+                socklen(size_of::<libc::c_int>()),
+            )
+        };
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    };
+    match socket.local_addr()? {
+        SocketAddr::V4(_) => {
+            set(libc::IPPROTO_IP, libc::IP_TOS, ECN_CAPABLE)?;
+            set(libc::IPPROTO_IP, libc::IP_RECVTOS, 1)?;
+        }
+        SocketAddr::V6(_) => {
+            set(libc::IPPROTO_IPV6, libc::IPV6_TCLASS, ECN_CAPABLE)?;
+            set(libc::IPPROTO_IPV6, libc::IPV6_RECVTCLASS, 1)?;
+            // dual stack sockets also send and receive IPv4 datagrams
+            _ = set(libc::IPPROTO_IP, libc::IP_TOS, ECN_CAPABLE);
+            _ = set(libc::IPPROTO_IP, libc::IP_RECVTOS, 1);
+        }
+    }
+    Ok(())
+}
+
+/// Queue ICMP errors for datagrams sent from the socket, read by [`receive_unreachable`].
+pub(super) fn enable_errors(socket: &UdpSocket) -> Result<(), io::Error> {
+    let enable: libc::c_int = 1;
+    let set = |level, name| unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            ptr::from_ref(&enable).cast(),
+            socklen(size_of::<libc::c_int>()),
+        ) == 0
+    };
+    let enabled = match socket.local_addr()? {
+        SocketAddr::V4(_) => set(libc::IPPROTO_IP, libc::IP_RECVERR),
+        // dual stack sockets also send IPv4 datagrams
+        SocketAddr::V6(_) => {
+            set(libc::IPPROTO_IP, libc::IP_RECVERR);
+            set(libc::IPPROTO_IPV6, libc::IPV6_RECVERR)
+        }
+    };
+    if enabled {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Read the socket's error queue until an error saying a destination is unreachable, returning
+/// the destination.
+pub(super) fn receive_unreachable(socket: &UdpSocket) -> Result<Option<SocketAddr>, io::Error> {
+    loop {
+        let mut storage: libc::sockaddr_storage = unsafe { zeroed() };
+        // the datagram which caused the error is returned too, but isn't needed
+        let mut buffer = [0_u8; 1];
+        let mut iovec = libc::iovec {
+            iov_base: buffer.as_mut_ptr().cast(),
+            iov_len: buffer.len(),
+        };
+        let mut control: [libc::cmsghdr; 8] = unsafe { zeroed() };
+        let mut header: libc::msghdr = unsafe { zeroed() };
+        header.msg_name = ptr::from_mut(&mut storage).cast();
+        header.msg_namelen = socklen(size_of::<libc::sockaddr_storage>());
+        header.msg_iov = &raw mut iovec;
+        header.msg_iovlen = 1;
+        header.msg_control = ptr::from_mut(&mut control).cast();
+        header.msg_controllen = size_of::<[libc::cmsghdr; 8]>();
+        let result = unsafe {
+            libc::recvmsg(
+                socket.as_raw_fd(),
+                &raw mut header,
+                libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT,
+            )
+        };
+        if result < 0 {
+            let err = io::Error::last_os_error();
+            match err.kind() {
+                ErrorKind::WouldBlock => return Ok(None),
+                ErrorKind::Interrupted => continue,
+                _ => return Err(err),
+            }
+        }
+        let mut unreachable = false;
+        unsafe {
+            let mut control = libc::CMSG_FIRSTHDR(&raw const header);
+            while !control.is_null() {
+                if matches!(
+                    ((*control).cmsg_level, (*control).cmsg_type),
+                    (libc::IPPROTO_IP, libc::IP_RECVERR) | (libc::IPPROTO_IPV6, libc::IPV6_RECVERR)
+                ) {
+                    let error = libc::CMSG_DATA(control)
+                        .cast::<libc::sock_extended_err>()
+                        .read_unaligned();
+                    unreachable |= matches!(
+                        i32::try_from(error.ee_errno),
+                        Ok(libc::ECONNREFUSED | libc::EHOSTUNREACH | libc::ENETUNREACH)
+                    );
+                }
+                control = libc::CMSG_NXTHDR(&raw const header, control);
+            }
+        }
+        if unreachable {
+            if let Some(address) = from_sockaddr(&storage) {
+                return Ok(Some(address));
+            }
+        }
+    }
+}
+
+/// Whether a received message carries a traffic class marked Congestion Experienced.
+unsafe fn congestion_experienced(header: &libc::msghdr) -> bool {
+    let mut control = libc::CMSG_FIRSTHDR(header);
+    while !control.is_null() {
+        let traffic_class = match ((*control).cmsg_level, (*control).cmsg_type) {
+            (libc::IPPROTO_IP, libc::IP_TOS) => Some(*libc::CMSG_DATA(control)),
+            (libc::IPPROTO_IPV6, libc::IPV6_TCLASS) => {
+                let traffic_class = libc::CMSG_DATA(control)
+                    .cast::<libc::c_int>()
+                    .read_unaligned();
+                u8::try_from(traffic_class & 0xff).ok()
+            }
+            _ => None,
+        };
+        if traffic_class.is_some_and(|traffic_class| {
+            traffic_class & ECN_CONGESTION_EXPERIENCED == ECN_CONGESTION_EXPERIENCED
+        }) {
+            return true;
+        }
+        control = libc::CMSG_NXTHDR(header, control);
+    }
+    false
+}
+
+pub(super) fn receive_batch(
+    socket: &UdpSocket,
+    buffers: &mut [[u8; MTU_MAX]],
+    received: &mut Vec<(SocketAddr, PacketReceived)>,
+) -> Result<(), io::Error> {
+    let mut addresses: Vec<libc::sockaddr_storage> =
+        (0..buffers.len()).map(|_| unsafe { zeroed() }).collect();
+    let mut iovecs: Vec<libc::iovec> = buffers
+        .iter_mut()
+        .map(|buffer| libc::iovec {
+            iov_base: buffer.as_mut_ptr().cast(),
+            iov_len: buffer.len(),
+        })
+        .collect();
+    // room for the traffic class, see `enable_ecn`
+    let mut controls: Vec<[libc::cmsghdr; 2]> =
+        (0..buffers.len()).map(|_| unsafe { zeroed() }).collect();
+    let mut messages: Vec<libc::mmsghdr> = addresses
+        .iter_mut()
+        .zip(iovecs.iter_mut())
+        .zip(controls.iter_mut())
+        .map(|((storage, iovec), control)| {
+            let mut message: libc::mmsghdr = unsafe { zeroed() };
+            message.msg_hdr.msg_name = ptr::from_mut(storage).cast();
+            message.msg_hdr.msg_namelen = socklen(size_of::<libc::sockaddr_storage>());
+            message.msg_hdr.msg_iov = iovec;
+            message.msg_hdr.msg_iovlen = 1;
+            message.msg_hdr.msg_control = ptr::from_mut(control).cast();
+            message.msg_hdr.msg_controllen = size_of::<[libc::cmsghdr; 2]>();
+            message
+        })
+        .collect();
+    let count = receive_messages(socket, &mut messages)?;
+    for (message, storage) in messages.iter().zip(&addresses).take(count) {
+        let Some(address) = from_sockaddr(storage) else {
+            continue;
+        };
+        if message.msg_hdr.msg_flags & libc::MSG_TRUNC != 0 {
+            received.push((address, PacketReceived::Partial));
+        } else if unsafe { congestion_experienced(&message.msg_hdr) } {
+            received.push((
+                address,
+                PacketReceived::CongestionExperienced(message.msg_len as usize),
+            ));
+        } else {
+            received.push((address, PacketReceived::Complete(message.msg_len as usize)));
+        }
+    }
+    Ok(())
+}
+
+/// Receive into `messages` with `recvmmsg`, returning the number of messages received.
+pub(super) fn receive_messages(
+    socket: &UdpSocket,
+    messages: &mut [libc::mmsghdr],
+) -> Result<usize, io::Error> {
+    loop {
+        let count = unsafe {
+            libc::recvmmsg(
+                socket.as_raw_fd(),
+                messages.as_mut_ptr(),
+                u32::try_from(messages.len()).unwrap_or(u32::MAX),
+                libc::MSG_DONTWAIT,
+                ptr::null_mut(),
+            )
+        };
+        if count >= 0 {
+            return Ok(usize::try_from(count).unwrap_or_default());
+        }
+        let err = io::Error::last_os_error();
+        match err.kind() {
+            ErrorKind::WouldBlock => return Ok(0),
+            ErrorKind::Interrupted | ErrorKind::ConnectionRefused => {}
+            _ => return Err(err),
+        }
+    }
+}
+
+pub(super) fn socklen(length: usize) -> libc::socklen_t {
+    libc::socklen_t::try_from(length).unwrap_or(libc::socklen_t::MAX)
+}
+
+pub(super) fn sockaddr_length(address: &SocketAddr) -> libc::socklen_t {
+    match address {
+        SocketAddr::V4(_) => socklen(size_of::<libc::sockaddr_in>()),
+        SocketAddr::V6(_) => socklen(size_of::<libc::sockaddr_in6>()),
+    }
+}
+
+pub(super) fn to_sockaddr(address: &SocketAddr) -> libc::sockaddr_storage {
+    let mut storage: libc::sockaddr_storage = unsafe { zeroed() };
+    match address {
+        SocketAddr::V4(address) => {
+            let sockaddr = libc::sockaddr_in {
+                sin_family: libc::AF_INET as libc::sa_family_t,
+                sin_port: address.port().to_be(),
+                sin_addr: libc::in_addr {
+                    s_addr: u32::from_ne_bytes(address.ip().octets()),
+                },
+                sin_zero: [0; 8],
+            };
+            unsafe {
+                ptr::from_mut(&mut storage)
+                    .cast::<libc::sockaddr_in>()
+                    .write(sockaddr);
+            }
+        }
+        SocketAddr::V6(address) => {
+            let sockaddr = libc::sockaddr_in6 {
+                sin6_family: libc::AF_INET6 as libc::sa_family_t,
+                sin6_port: address.port().to_be(),
+                sin6_flowinfo: address.flowinfo(),
+                sin6_addr: libc::in6_addr {
+                    s6_addr: address.ip().octets(),
+                },
+                sin6_scope_id: address.scope_id(),
+            };
+            unsafe {
+                ptr::from_mut(&mut storage)
+                    .cast::<libc::sockaddr_in6>()
+                    .write(sockaddr);
+            }
+        }
+    }
+    storage
+}
+
+pub(super) fn from_sockaddr(storage: &libc::sockaddr_storage) -> Option<SocketAddr> {
+    match i32::from(storage.ss_family) {
+        libc::AF_INET => {
+            let sockaddr = unsafe { &*ptr::from_ref(storage).cast::<libc::sockaddr_in>() };
+            Some(SocketAddr::V4(SocketAddrV4::new(
+                Ipv4Addr::from(sockaddr.sin_addr.s_addr.to_ne_bytes()),
+                u16::from_be(sockaddr.sin_port),
+            )))
+        }
+        libc::AF_INET6 => {
+            let sockaddr = unsafe { &*ptr::from_ref(storage).cast::<libc::sockaddr_in6>() };
+            Some(SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::from(sockaddr.sin6_addr.s6_addr),
+                u16::from_be(sockaddr.sin6_port),
+                sockaddr.sin6_flowinfo,
+                sockaddr.sin6_scope_id,
+            )))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,369 @@
+//! [`OffloadUdpSocket`], using Linux's UDP segmentation and receive offloads.
+
+use core::{
+    mem::{size_of, zeroed},
+    ops::Range,
+    ptr,
+};
+use std::{
+    collections::VecDeque,
+    io,
+    net::{SocketAddr, UdpSocket},
+    os::fd::AsRawFd,
+};
+
+use super::mmsg;
+use crate::{PacketReceived, Socket, SocketOptions, Vec, MTU_MAX};
+
+const UDP_SEGMENT: libc::c_int = 103;
+const UDP_GRO: libc::c_int = 104;
+/// The maximum number of segments the kernel accepts in one send.
+const MAXIMUM_SEGMENTS: usize = 64;
+/// The maximum size of one send or coalesced receive, leaving room for IP and UDP headers.
+const MAXIMUM_OFFLOAD_SIZE: usize = 65_000;
+/// The number of coalesced datagrams received at once.
+const RECEIVE_BUFFERS: usize = 4;
+
+/// A [`UdpSocket`] wrapper using UDP generic segmentation offload (GSO) and generic receive
+/// offload (GRO) on Linux, with the `offload` feature.
+///
+/// When sending a batch, consecutive datagrams to the same peer of the same size are handed to the
+/// kernel as one large buffer, split into datagrams by the kernel or network card. When receiving,
+/// the kernel may coalesce datagrams from the same peer, which are split again before being passed
+/// to the host. Combined with [`HostSettings::socket_batch_size`](`crate::HostSettings::socket_batch_size`),
+/// this greatly reduces the CPU cost of each datagram in bursts.
+///
+/// Offloads the kernel doesn't support are detected in [`Socket::init`] and left disabled, falling
+/// back to `sendmmsg` and `recvmmsg` alone.
+///
+/// ```no_run
+/// use std::net::UdpSocket;
+///
+/// use rusty_enet::{Host, HostSettings, OffloadUdpSocket};
+///
+/// let socket = OffloadUdpSocket::new(UdpSocket::bind("0.0.0.0:6060").unwrap());
+/// let host = Host::new(
+///     socket,
+///     HostSettings {
+///         socket_batch_size: 32,
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct OffloadUdpSocket {
+    socket: UdpSocket,
+    segmentation: bool,
+    coalescing: bool,
+    receive_buffers: Vec<u8>,
+    pending: VecDeque<(SocketAddr, Vec<u8>)>,
+}
+
+impl OffloadUdpSocket {
+    /// Wrap a socket, enabling offloads once it is passed to [`Host::new`](`crate::Host::new`).
+    #[must_use]
+    pub fn new(socket: UdpSocket) -> Self {
+        Self {
+            socket,
+            segmentation: false,
+            coalescing: false,
+            receive_buffers: Vec::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Get a reference to the underlying socket.
+    #[must_use]
+    pub fn get_ref(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    /// Unwrap the underlying socket.
+    #[must_use]
+    pub fn into_inner(self) -> UdpSocket {
+        self.socket
+    }
+
+    /// Is generic segmentation offload used to send datagrams?
+    #[must_use]
+    pub fn segmentation_offload(&self) -> bool {
+        self.segmentation
+    }
+
+    /// Is generic receive offload used to receive datagrams?
+    #[must_use]
+    pub fn receive_offload(&self) -> bool {
+        self.coalescing
+    }
+
+    /// Split datagrams into runs which can be sent as one segmented buffer: all to the same
+    /// address with the same size, except for a shorter last datagram.
+    fn segments(datagrams: &[(SocketAddr, Vec<u8>)]) -> Vec<Range<usize>> {
+        let mut runs = Vec::new();
+        let mut start = 0;
+        while start < datagrams.len() {
+            let (address, first) = &datagrams[start];
+            let mut end = start + 1;
+            let mut size = first.len();
+            while end < datagrams.len()
+                && end - start < MAXIMUM_SEGMENTS
+                && datagrams[end].0 == *address
+                && datagrams[end].1.len() <= first.len()
+                && size + datagrams[end].1.len() <= MAXIMUM_OFFLOAD_SIZE
+            {
+                size += datagrams[end].1.len();
+                end += 1;
+                if datagrams[end - 1].1.len() < first.len() {
+                    break;
+                }
+            }
+            runs.push(start..end);
+            start = end;
+        }
+        runs
+    }
+
+    fn push_received(
+        &mut self,
+        address: SocketAddr,
+        datagram: &[u8],
+        buffers: &mut [[u8; MTU_MAX]],
+        received: &mut Vec<(SocketAddr, PacketReceived)>,
+    ) {
+        if let Some(buffer) = buffers.get_mut(received.len()) {
+            if datagram.len() > MTU_MAX {
+                received.push((address, PacketReceived::Partial));
+            } else {
+                buffer[..datagram.len()].copy_from_slice(datagram);
+                received.push((address, PacketReceived::Complete(datagram.len())));
+            }
+        } else {
+            self.pending.push_back((address, datagram.to_vec()));
+        }
+    }
+}
+
+impl Socket for OffloadUdpSocket {
+    type Address = SocketAddr;
+    type Error = io::Error;
+
+    fn init(&mut self, socket_options: SocketOptions) -> Result<(), io::Error> {
+        // the control messages received are sized for GRO, so ECN isn't supported
+        self.socket.init(SocketOptions {
+            ecn: false,
+            ..socket_options
+        })?;
+        let fd = self.socket.as_raw_fd();
+        let mut segment_size: libc::c_int = 0;
+        let mut length = mmsg::socklen(size_of::<libc::c_int>());
+        self.segmentation = unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_UDP,
+                UDP_SEGMENT,
+                ptr::from_mut(&mut segment_size).cast(),
+                &raw mut length,
+            )
+        } == 0;
+        let enable: libc::c_int = 1;
+        self.coalescing = unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_UDP,
+                UDP_GRO,
+                ptr::from_ref(&enable).cast(),
+                mmsg::socklen(size_of::<libc::c_int>()),
+            )
+        } == 0;
+        if self.coalescing {
+            self.receive_buffers = vec![0; RECEIVE_BUFFERS * MAXIMUM_OFFLOAD_SIZE];
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, address: SocketAddr, buffer: &[u8]) -> Result<usize, io::Error> {
+        Socket::send(&mut self.socket, address, buffer)
+    }
+
+    fn receive(
+        &mut self,
+        buffer: &mut [u8; MTU_MAX],
+    ) -> Result<Option<(SocketAddr, PacketReceived)>, io::Error> {
+        let mut received = Vec::new();
+        self.receive_batch(core::slice::from_mut(buffer), &mut received)?;
+        Ok(received.pop())
+    }
+
+    fn send_with_traffic_class(
+        &mut self,
+        address: SocketAddr,
+        buffer: &[u8],
+        traffic_class: u8,
+    ) -> Result<usize, io::Error> {
+        mmsg::send_with_traffic_class(&self.socket, &address, buffer, traffic_class)
+    }
+
+    fn receive_unreachable(&mut self) -> Result<Option<SocketAddr>, io::Error> {
+        mmsg::receive_unreachable(&self.socket)
+    }
+
+    fn send_batch(&mut self, datagrams: &[(SocketAddr, Vec<u8>)]) -> Result<usize, io::Error> {
+        if !self.segmentation {
+            return mmsg::send_batch(&self.socket, datagrams);
+        }
+        let runs = Self::segments(datagrams);
+        let mut addresses: Vec<libc::sockaddr_storage> = runs
+            .iter()
+            .map(|run| mmsg::to_sockaddr(&datagrams[run.start].0))
+            .collect();
+        let mut iovecs: Vec<libc::iovec> = datagrams
+            .iter()
+            .map(|(_, datagram)| libc::iovec {
+                iov_base: datagram.as_ptr().cast_mut().cast(),
+                iov_len: datagram.len(),
+            })
+            .collect();
+        let mut controls: Vec<[libc::cmsghdr; 2]> =
+            runs.iter().map(|_| unsafe { zeroed() }).collect();
+        let mut messages: Vec<libc::mmsghdr> = Vec::with_capacity(runs.len());
+        for ((run, storage), control) in runs.iter().zip(&mut addresses).zip(&mut controls) {
+            let mut message: libc::mmsghdr = unsafe { zeroed() };
+            message.msg_hdr.msg_name = ptr::from_mut(storage).cast();
+            message.msg_hdr.msg_namelen = mmsg::sockaddr_length(&datagrams[run.start].0);
+            message.msg_hdr.msg_iov = iovecs[run.clone()].as_mut_ptr();
+            message.msg_hdr.msg_iovlen = run.len();
+            if run.len() > 1 {
+                let segment_size = u16::try_from(datagrams[run.start].1.len()).unwrap_or(u16::MAX);
+                unsafe {
+                    message.msg_hdr.msg_control = control.as_mut_ptr().cast();
+                    message.msg_hdr.msg_controllen =
+                        libc::CMSG_SPACE(size_of::<u16>() as libc::c_uint) as usize;
+                    let header = libc::CMSG_FIRSTHDR(&raw const message.msg_hdr);
+                    (*header).cmsg_level = libc::SOL_UDP;
+                    (*header).cmsg_type = UDP_SEGMENT;
+                    (*header).cmsg_len = libc::CMSG_LEN(size_of::<u16>() as libc::c_uint) as usize;
+                    libc::CMSG_DATA(header)
+                        .cast::<u16>()
+                        .write_unaligned(segment_size);
+                }
+            }
+            messages.push(message);
+        }
+        let mut sent_length = 0;
+        match mmsg::send_messages(&self.socket, &mut messages, &mut sent_length) {
+            Ok(()) => Ok(sent_length),
+            // the network device can't segment, so send the rest without offload from now on
+            Err((offset, err)) if err.raw_os_error() == Some(libc::EIO) => {
+                self.segmentation = false;
+                let start = runs[offset].start;
+                Ok(sent_length + mmsg::send_batch(&self.socket, &datagrams[start..])?)
+            }
+            Err((_, err)) => Err(err),
+        }
+    }
+
+    fn receive_batch(
+        &mut self,
+        buffers: &mut [[u8; MTU_MAX]],
+        received: &mut Vec<(SocketAddr, PacketReceived)>,
+    ) -> Result<(), io::Error> {
+        if !self.coalescing {
+            return mmsg::receive_batch(&self.socket, buffers, received);
+        }
+        while received.len() < buffers.len() {
+            let Some((address, datagram)) = self.pending.pop_front() else {
+                break;
+            };
+            self.push_received(address, &datagram, buffers, received);
+        }
+        if received.len() == buffers.len() {
+            return Ok(());
+        }
+        let mut receive_buffers = core::mem::take(&mut self.receive_buffers);
+        let result = self.receive_coalesced(&mut receive_buffers, buffers, received);
+        self.receive_buffers = receive_buffers;
+        match result {
+            Err(_) if !received.is_empty() => Ok(()),
+            result => result,
+        }
+    }
+}
+
+impl OffloadUdpSocket {
+    fn receive_coalesced(
+        &mut self,
+        receive_buffers: &mut [u8],
+        buffers: &mut [[u8; MTU_MAX]],
+        received: &mut Vec<(SocketAddr, PacketReceived)>,
+    ) -> Result<(), io::Error> {
+        let count = RECEIVE_BUFFERS.min(buffers.len() - received.len());
+        let mut addresses: Vec<libc::sockaddr_storage> =
+            (0..count).map(|_| unsafe { zeroed() }).collect();
+        let mut iovecs: Vec<libc::iovec> = receive_buffers
+            .chunks_exact_mut(MAXIMUM_OFFLOAD_SIZE)
+            .take(count)
+            .map(|buffer| libc::iovec {
+                iov_base: buffer.as_mut_ptr().cast(),
+                iov_len: buffer.len(),
+            })
+            .collect();
+        let mut controls: Vec<[libc::cmsghdr; 2]> =
+            (0..count).map(|_| unsafe { zeroed() }).collect();
+        let mut messages: Vec<libc::mmsghdr> = addresses
+            .iter_mut()
+            .zip(&mut iovecs)
+            .zip(&mut controls)
+            .map(|((storage, iovec), control)| {
+                let mut message: libc::mmsghdr = unsafe { zeroed() };
+                message.msg_hdr.msg_name = ptr::from_mut(storage).cast();
+                message.msg_hdr.msg_namelen = mmsg::socklen(size_of::<libc::sockaddr_storage>());
+                message.msg_hdr.msg_iov = iovec;
+                message.msg_hdr.msg_iovlen = 1;
+                message.msg_hdr.msg_control = control.as_mut_ptr().cast();
+                message.msg_hdr.msg_controllen = size_of::<[libc::cmsghdr; 2]>();
+                message
+            })
+            .collect();
+        let count = mmsg::receive_messages(&self.socket, &mut messages)?;
+        for ((message, storage), buffer) in messages
+            .iter()
+            .zip(&addresses)
+            .zip(receive_buffers.chunks_exact(MAXIMUM_OFFLOAD_SIZE))
+            .take(count)
+        {
+            let Some(address) = mmsg::from_sockaddr(storage) else {
+                continue;
+            };
+            let length = message.msg_len as usize;
+            if message.msg_hdr.msg_flags & libc::MSG_TRUNC != 0 || length > buffer.len() {
+                if received.len() < buffers.len() {
+                    received.push((address, PacketReceived::Partial));
+                }
+                continue;
+            }
+            let segment_size = Self::segment_size(&message.msg_hdr).unwrap_or(length);
+            for datagram in buffer[..length].chunks(segment_size.max(1)) {
+                self.push_received(address, datagram, buffers, received);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the size of the datagrams coalesced into a received message, if any were.
+    fn segment_size(header: &libc::msghdr) -> Option<usize> {
+        unsafe {
+            let mut control = libc::CMSG_FIRSTHDR(header);
+            while !control.is_null() {
+                if (*control).cmsg_level == libc::SOL_UDP && (*control).cmsg_type == UDP_GRO {
+                    let size = libc::CMSG_DATA(control)
+                        .cast::<libc::c_int>()
+                        .read_unaligned();
+                    return usize::try_from(size).ok();
+                }
+                control = libc::CMSG_NXTHDR(header, control);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,57 @@
+//! Binding [`UdpSocket`]s which share a port with `SO_REUSEPORT`.
+
+use core::{mem::size_of, ptr};
+use std::{
+    io,
+    net::{SocketAddr, UdpSocket},
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+};
+
+use super::mmsg;
+
+/// Bind a [`UdpSocket`] with `SO_REUSEPORT` set, so several sockets can bind the same address.
+///
+/// Linux spreads incoming datagrams over the sockets by hashing their source and destination
+/// addresses, so every datagram from one client reaches the same socket for as long as the set
+/// of sockets doesn't change. See [`ShardedHost`](`crate::ShardedHost`), which uses this to run a
+/// server across several threads.
+///
+/// # Errors
+///
+/// Returns an error if the socket can't be created, configured, or bound.
+pub fn reuse_port_socket(address: SocketAddr) -> io::Result<UdpSocket> {
+    let domain = match address {
+        SocketAddr::V4(_) => libc::AF_INET,
+        SocketAddr::V6(_) => libc::AF_INET6,
+    };
+    let fd = unsafe { libc::socket(domain, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    let enable: libc::c_int = 1;
+    if unsafe {
+        libc::setsockopt(
+            fd.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_REUSEPORT,
+            ptr::from_ref(&enable).cast(),
+            mmsg::socklen(size_of::<libc::c_int>()),
+        )
+    } != 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    let storage = mmsg::to_sockaddr(&address);
+    if unsafe {
+        libc::bind(
+            fd.as_raw_fd(),
+            ptr::from_ref(&storage).cast(),
+            mmsg::sockaddr_length(&address),
+        )
+    } != 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(UdpSocket::from(fd))
+}
@@ -0,0 +1,271 @@
+use core::fmt::Debug;
+
+use crate::{
+    error::PeerSendError, Box, Packet, PacketKind, Peer, PeerState, Socket, Vec, VecDeque,
+};
+
+const STREAM_HEADER_SIZE: usize = 8;
+
+/// The default [`PeerStream::chunk_size`], 16 KiB.
+pub const STREAM_DEFAULT_CHUNK_SIZE: usize = 16 * 1024;
+/// The default [`PeerStream::window`], 256 KiB.
+pub const STREAM_DEFAULT_WINDOW: usize = 256 * 1024;
+
+/// How far a [`PeerStream`] or [`StreamReceiver`] has progressed, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StreamProgress {
+    /// Bytes queued to the peer, or received from it.
+    pub sent: usize,
+    /// Bytes acknowledged by the peer. Always equal to [`StreamProgress::sent`] for a
+    /// [`StreamReceiver`].
+    pub acknowledged: usize,
+    /// Total size of the stream, or `0` if a [`StreamReceiver`] hasn't received any of it yet.
+    pub total: usize,
+}
+
+/// Sends an arbitrarily large buffer to a peer in reliable chunks, for transfers like map
+/// downloads or replays which would otherwise exceed
+/// [`HostSettings::maximum_packet_size`](`crate::HostSettings::maximum_packet_size`).
+///
+/// Only [`PeerStream::window`] bytes are in flight at once, so a large transfer doesn't flood the
+/// peer's queues or starve other channels. Call [`PeerStream::update`] regularly, such as after
+/// every [`Host::service`](`crate::Host::service`), to queue more chunks as earlier ones are
+/// acknowledged. The peer reassembles the stream with a [`StreamReceiver`].
+///
+/// The channel should be dedicated to the stream, as every packet received on it is treated as a
+/// chunk.
+///
+/// ```
+/// use rusty_enet::{PeerStream, StreamReceiver};
+///
+/// let stream = PeerStream::new(1, vec![0; 1024 * 1024])
+///     .chunk_size(8 * 1024)
+///     .on_progress(|progress| println!("sent {} of {}", progress.acknowledged, progress.total));
+/// let receiver = StreamReceiver::new();
+/// ```
+pub struct PeerStream {
+    channel_id: u8,
+    data: Vec<u8>,
+    chunk_size: usize,
+    window: usize,
+    offset: usize,
+    started: bool,
+    in_flight: VecDeque<(Packet, usize)>,
+    in_flight_bytes: usize,
+    progress: StreamProgress,
+    on_progress: Option<Box<dyn FnMut(StreamProgress)>>,
+}
+
+impl PeerStream {
+    /// Create a stream sending `data` on the specified channel, with
+    /// [`STREAM_DEFAULT_CHUNK_SIZE`] and [`STREAM_DEFAULT_WINDOW`].
+    #[must_use]
+    pub fn new(channel_id: u8, data: Vec<u8>) -> Self {
+        let total = data.len();
+        Self {
+            channel_id,
+            data,
+            chunk_size: STREAM_DEFAULT_CHUNK_SIZE,
+            window: STREAM_DEFAULT_WINDOW,
+            offset: 0,
+            started: false,
+            in_flight: VecDeque::new(),
+            in_flight_bytes: 0,
+            progress: StreamProgress {
+                sent: 0,
+                acknowledged: 0,
+                total,
+            },
+            on_progress: None,
+        }
+    }
+
+    /// Set the size of each chunk in bytes. Values of `0` are treated as `1`.
+    #[must_use]
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Set the maximum number of unacknowledged bytes in flight. At least one chunk is always
+    /// allowed in flight.
+    #[must_use]
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set a callback, called from [`PeerStream::update`] whenever the stream progresses.
+    #[must_use]
+    pub fn on_progress(mut self, on_progress: impl FnMut(StreamProgress) + 'static) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    /// Get the channel this stream sends on.
+    #[must_use]
+    pub fn channel_id(&self) -> u8 {
+        self.channel_id
+    }
+
+    /// Get how far this stream has progressed.
+    #[must_use]
+    pub fn progress(&self) -> StreamProgress {
+        self.progress
+    }
+
+    /// Check if every byte of the stream has been acknowledged by the peer.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.started && self.offset == self.data.len() && self.in_flight.is_empty()
+    }
+
+    /// Retire acknowledged chunks and queue more, up to the window, to `peer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PeerSendError::NotConnected`] if the peer disconnected before the stream
+    /// completed, or any of the other [`PeerSendError`] variants if a chunk fails to queue.
+    pub fn update<S: Socket>(
+        &mut self,
+        peer: &mut Peer<S>,
+    ) -> Result<StreamProgress, PeerSendError> {
+        if self.is_complete() {
+            return Ok(self.progress);
+        }
+        if peer.state() != PeerState::Connected {
+            return Err(PeerSendError::NotConnected);
+        }
+        let previous = self.progress;
+        // chunks are only referenced by the stream once the peer has acknowledged them
+        while let Some(&(ref chunk, length)) = self.in_flight.front() {
+            if chunk.is_shared() {
+                break;
+            }
+            self.in_flight.pop_front();
+            self.in_flight_bytes -= length;
+            self.progress.acknowledged += length;
+        }
+        while (!self.started || self.offset < self.data.len())
+            && (self.in_flight.is_empty() || self.in_flight_bytes < self.window)
+        {
+            let end = self.data.len().min(self.offset + self.chunk_size);
+            let chunk = if !self.started {
+                let mut chunk = Vec::with_capacity(STREAM_HEADER_SIZE + end);
+                chunk.extend_from_slice(&(self.data.len() as u64).to_be_bytes());
+                chunk.extend_from_slice(&self.data[..end]);
+                Packet::from_shared(chunk, PacketKind::Reliable)
+            } else {
+                Packet::new(&self.data[self.offset..end], PacketKind::Reliable)
+            };
+            peer.send(self.channel_id, &chunk)?;
+            self.in_flight_bytes += end - self.offset;
+            self.in_flight.push_back((chunk, end - self.offset));
+            self.progress.sent = end;
+            self.offset = end;
+            self.started = true;
+        }
+        if self.progress != previous {
+            if let Some(on_progress) = &mut self.on_progress {
+                on_progress(self.progress);
+            }
+        }
+        Ok(self.progress)
+    }
+}
+
+impl Debug for PeerStream {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PeerStream")
+            .field("channel_id", &self.channel_id)
+            .field("chunk_size", &self.chunk_size)
+            .field("window", &self.window)
+            .field("progress", &self.progress)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reassembles a stream sent by a [`PeerStream`], from the packets received on its channel.
+#[derive(Default)]
+pub struct StreamReceiver {
+    data: Vec<u8>,
+    total: Option<usize>,
+    on_progress: Option<Box<dyn FnMut(StreamProgress)>>,
+}
+
+impl StreamReceiver {
+    /// Create a receiver waiting for the start of a stream.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a callback, called from [`StreamReceiver::receive`] whenever the stream progresses.
+    #[must_use]
+    pub fn on_progress(mut self, on_progress: impl FnMut(StreamProgress) + 'static) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    /// Add a chunk received on the stream's channel, returning the stream's progress. Chunks
+    /// received after the stream completed, or malformed first chunks, are ignored.
+    pub fn receive(&mut self, chunk: &[u8]) -> StreamProgress {
+        match self.total {
+            Some(total) if self.data.len() < total => {
+                let length = chunk.len().min(total - self.data.len());
+                self.data.extend_from_slice(&chunk[..length]);
+            }
+            Some(_) => return self.progress(),
+            None if chunk.len() >= STREAM_HEADER_SIZE => {
+                let mut total = [0; STREAM_HEADER_SIZE];
+                total.copy_from_slice(&chunk[..STREAM_HEADER_SIZE]);
+                let total = usize::try_from(u64::from_be_bytes(total)).unwrap_or(usize::MAX);
+                let length = (chunk.len() - STREAM_HEADER_SIZE).min(total);
+                self.data
+                    .extend_from_slice(&chunk[STREAM_HEADER_SIZE..][..length]);
+                self.total = Some(total);
+            }
+            None => return self.progress(),
+        }
+        let progress = self.progress();
+        if let Some(on_progress) = &mut self.on_progress {
+            on_progress(progress);
+        }
+        progress
+    }
+
+    /// Get how far the stream has progressed.
+    #[must_use]
+    pub fn progress(&self) -> StreamProgress {
+        StreamProgress {
+            sent: self.data.len(),
+            acknowledged: self.data.len(),
+            total: self.total.unwrap_or(0),
+        }
+    }
+
+    /// Check if the whole stream has been received.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.total == Some(self.data.len())
+    }
+
+    /// Take the received data if the stream is complete, resetting the receiver for the next
+    /// stream on the channel.
+    pub fn take(&mut self) -> Option<Vec<u8>> {
+        if self.is_complete() {
+            self.total = None;
+            Some(core::mem::take(&mut self.data))
+        } else {
+            None
+        }
+    }
+}
+
+impl Debug for StreamReceiver {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StreamReceiver")
+            .field("progress", &self.progress())
+            .finish_non_exhaustive()
+    }
+}
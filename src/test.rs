@@ -1,4 +1,13 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
 use crate as enet;
+use crate::{Box, Vec};
 
 #[allow(dead_code)]
 mod network;
@@ -22,12 +31,22 @@ fn events() {
     assert_eq!(events.len(), 2);
     assert!(events[0].is_connect_and(|event| event.to == host1
         && event.from == host2
-        && event.peer == enet::PeerID(0)
-        && event.data == 0));
+        && event.peer
+            == enet::PeerID {
+                index: 0,
+                generation: 1,
+            }
+        && event.data == 0
+        && event.channel_count == 255));
     assert!(events[1].is_connect_and(|event| event.to == host2
         && event.from == host1
-        && event.peer == enet::PeerID(0)
-        && event.data == 5));
+        && event.peer
+            == enet::PeerID {
+                index: 0,
+                generation: 1,
+            }
+        && event.data == 5
+        && event.channel_count == 255));
 
     network.send(
         host1,
@@ -133,31 +152,500 @@ fn resend_reliable_packet() {
 }
 
 #[test]
-fn round_trip_time() {
+fn unsequenced() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    network.connect(host1, host2, 255, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+
+    for i in 0..3 {
+        let packet = enet::Packet::unreliable_unsequenced(&[i]);
+        assert_eq!(
+            packet.kind(),
+            enet::PacketKind::Unreliable { sequenced: false }
+        );
+        network.send(host1, host2, 0, &packet);
+    }
+    let events = network.update(10);
+    assert_eq!(events.len(), 3);
+    for (i, event) in events.iter().enumerate() {
+        assert!(event.is_receive_and(|event| event.packet.data() == [i as u8]));
+    }
+
+    let peer1 = network.resolve_peer(host1, host2);
+    let peer2 = network.resolve_peer(host2, host1);
+    assert_eq!(
+        network
+            .host(host1)
+            .peer(peer1)
+            .unwrap()
+            .outgoing_unsequenced_group(),
+        3
+    );
+    assert_eq!(
+        network
+            .host(host2)
+            .peer(peer2)
+            .unwrap()
+            .incoming_unsequenced_group(),
+        0
+    );
+}
+
+#[test]
+fn unsequenced_window_size() {
+    for unsequenced_window_size in [16, 1000, 32768] {
+        assert!(enet::Host::new(
+            enet::ReadWrite::<(), core::convert::Infallible>::new(),
+            enet::HostSettings {
+                unsequenced_window_size,
+                ..Default::default()
+            }
+        )
+        .is_err());
+    }
+
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    let host3 = network.create_host(enet::HostSettings {
+        unsequenced_window_size: 4096,
+        ..Default::default()
+    });
+    network.connect(host1, host2, 1, 0);
+    network.connect(host1, host3, 1, 0);
+    network.update(10);
+    for _ in 0..100 {
+        for _ in 0..20 {
+            let packet = enet::Packet::unreliable_unsequenced(&[0]);
+            network.send(host1, host2, 0, &packet);
+            network.send(host1, host3, 0, &packet);
+        }
+        network.update(1);
+    }
+    network.update(10);
+    // the window slides forward in steps of its size
+    let peer2 = network.resolve_peer(host2, host1);
+    let peer3 = network.resolve_peer(host3, host1);
+    assert_eq!(
+        network
+            .host(host2)
+            .peer(peer2)
+            .unwrap()
+            .incoming_unsequenced_group(),
+        1024
+    );
+    assert_eq!(
+        network
+            .host(host3)
+            .peer(peer3)
+            .unwrap()
+            .incoming_unsequenced_group(),
+        0
+    );
+}
+
+#[test]
+fn shared_packets() {
+    let mut network = Network::new();
+    let server = network.create_host(enet::HostSettings {
+        peer_limit: 2,
+        ..Default::default()
+    });
+    let client1 = network.create_host(enet::HostSettings::default());
+    let client2 = network.create_host(enet::HostSettings::default());
+    network.connect(client1, server, 255, 0);
+    network.connect(client2, server, 255, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 4);
+
+    let payload: Arc<[u8]> = Arc::from([7; 4000]);
+    let packet = enet::Packet::from_shared(payload.clone(), enet::PacketKind::Reliable);
+    network.host_mut(server).broadcast(0, &packet);
+    drop(packet);
+    assert_eq!(Arc::strong_count(&payload), 2);
+
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    assert!(events
+        .iter()
+        .all(|event| event.is_receive_and(|event| event.packet.data() == [7; 4000])));
+    assert_eq!(Arc::strong_count(&payload), 1);
+}
+
+#[test]
+fn batch_send() {
+    let mut network = Network::new();
+    let server = network.create_host(enet::HostSettings {
+        peer_limit: 2,
+        maximum_packet_size: 3000,
+        ..Default::default()
+    });
+    let client1 = network.create_host(enet::HostSettings::default());
+    let client2 = network.create_host(enet::HostSettings::default());
+    network.connect(client1, server, 2, 0);
+    network.connect(client2, server, 2, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 4);
+    let peer1 = network.resolve_peer(server, client1);
+    let peer2 = network.resolve_peer(server, client2);
+
+    network
+        .host_mut(server)
+        .peer_mut(peer1)
+        .unwrap()
+        .send_batch(0, (0..3).map(|i| enet::Packet::reliable(&[i])))
+        .unwrap();
+    let events = network.update(10);
+    assert_eq!(events.len(), 3);
+    for (i, event) in events.iter().enumerate() {
+        assert!(
+            event.is_receive_and(|event| event.to == client1 && event.packet.data() == [i as u8])
+        );
+    }
+
+    // packets are checked before any is queued, including fragmented ones
+    let result = network
+        .host_mut(server)
+        .peer_mut(peer1)
+        .unwrap()
+        .send_batch(
+            0,
+            [
+                enet::Packet::reliable(&[9; 2500]),
+                enet::Packet::reliable(&[10; 4000]),
+                enet::Packet::reliable(&[11]),
+            ],
+        );
+    assert_eq!(
+        result,
+        Err(enet::error::BatchSendError {
+            sent: 1,
+            error: enet::error::PeerSendError::PacketTooLarge,
+        })
+    );
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == [9; 2500]));
+
+    network
+        .host_mut(server)
+        .send_many([
+            (peer1, 0, enet::Packet::reliable(&[3])),
+            (peer2, 1, enet::Packet::reliable(&[4])),
+        ])
+        .unwrap();
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().any(
+        |event| event.is_receive_and(|event| event.to == client1 && event.packet.data() == [3])
+    ));
+    assert!(events
+        .iter()
+        .any(|event| event.is_receive_and(|event| event.to == client2
+            && event.channel_id == 1
+            && event.packet.data() == [4])));
+
+    // packets after a failure are not queued
+    let result = network.host_mut(server).send_many([
+        (peer1, 0, enet::Packet::reliable(&[5])),
+        (peer1, 2, enet::Packet::reliable(&[6])),
+        (peer1, 0, enet::Packet::reliable(&[7])),
+    ]);
+    assert_eq!(
+        result,
+        Err(enet::error::BatchSendError {
+            sent: 1,
+            error: enet::error::PeerSendError::InvalidChannel,
+        })
+    );
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == [5]));
+
+    // stale peer IDs are reported rather than panicking
+    let stale = enet::PeerID {
+        index: peer1.index,
+        generation: peer1.generation.wrapping_add(1),
+    };
+    let result = network
+        .host_mut(server)
+        .send_many([(stale, 0, enet::Packet::reliable(&[8]))]);
+    assert_eq!(
+        result,
+        Err(enet::error::BatchSendError {
+            sent: 0,
+            error: enet::error::PeerSendError::InvalidPeer,
+        })
+    );
+}
+
+#[test]
+fn send_bytes() {
+    let mut network = Network::new();
+    let server = network.create_host(enet::HostSettings::default());
+    let client = network.create_host(enet::HostSettings::default());
+    network.connect(client, server, 2, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+
+    let peer = network.resolve_peer(server, client);
+    network
+        .host_mut(server)
+        .peer_mut(peer)
+        .unwrap()
+        .send_bytes(0, &[1, 2], enet::PacketKind::Reliable)
+        .unwrap();
+    network
+        .host_mut(server)
+        .send_bytes(
+            peer,
+            1,
+            &[3],
+            enet::PacketKind::Unreliable { sequenced: true },
+        )
+        .unwrap();
+    assert_eq!(
+        network
+            .host_mut(server)
+            .send_bytes(peer, 2, &[4], enet::PacketKind::Reliable),
+        Err(enet::error::PeerSendError::InvalidChannel)
+    );
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    assert!(events[0].is_receive_and(|event| event.channel_id == 0
+        && event.packet.data() == [1, 2]
+        && event.packet.kind() == enet::PacketKind::Reliable));
+    assert!(events[1].is_receive_and(|event| event.channel_id == 1 && event.packet.data() == [3]));
+}
+
+#[test]
+fn packet_tags() {
+    let mut network = Network::new();
+    let server = network.create_host(enet::HostSettings::default());
+    let client = network.create_host(enet::HostSettings::default());
+    network.connect(client, server, 2, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+
+    let tagged = |data: &[u8], tag| {
+        enet::PacketBuilder::new(data)
+            .reliable(true)
+            .tag(tag)
+            .build()
+    };
+    network.send(server, client, 0, &tagged(&[1], 1));
+    network.send(server, client, 1, &tagged(&[2; 3000], 2));
+    network.send(server, client, 0, &enet::Packet::reliable(&[3]));
+    let unreliable = enet::PacketBuilder::new(&[4]).tag(4).build();
+    network.send(server, client, 0, &unreliable);
+    let events = network.update(10);
+    assert_eq!(events.len(), 6);
+    assert_eq!(events.iter().filter(|event| event.is_receive()).count(), 4);
+    // fragmented packets are only acknowledged once, and untagged or unreliable packets never are
+    assert!(events
+        .iter()
+        .any(|event| event.is_acknowledge_and(|event| event.to == server
+            && event.channel_id == 0
+            && event.tag == 1)));
+    assert!(events
+        .iter()
+        .any(|event| event.is_acknowledge_and(|event| event.to == server
+            && event.channel_id == 1
+            && event.tag == 2)));
+
+    // packets still queued when the peer is reset are reported as failed
+    network.send(server, client, 1, &tagged(&[5; 3000], 5));
+    let peer = network.resolve_peer(server, client);
+    network.host_mut(server).peer_mut(peer).unwrap().reset();
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_delivery_failed_and(|event| event.to == server
+        && event.channel_id == 1
+        && event.tag == 5));
+}
+
+#[test]
+fn rpc() {
+    let mut network = Network::new();
+    let settings = || enet::HostSettings {
+        rpc_channel: Some(1),
+        rpc_timeout: Duration::from_millis(100),
+        ..Default::default()
+    };
+    let server = network.create_host(settings());
+    let client = network.create_host(settings());
+    network.connect(client, server, 2, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+
+    network
+        .host_mut(server)
+        .register_handler(7, |_, payload| payload.iter().rev().copied().collect());
+    let peer = network.resolve_peer(client, server);
+    let call1 = network
+        .host_mut(client)
+        .peer_mut(peer)
+        .unwrap()
+        .call(7, &[1, 2, 3])
+        .unwrap();
+    let call2 = network
+        .host_mut(client)
+        .peer_mut(peer)
+        .unwrap()
+        .call(8, &[])
+        .unwrap();
+    assert_ne!(call1, call2);
+    // requests and replies are consumed by the hosts, only the results are reported
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    assert!(events[0].is_reply_and(|event| event.to == client
+        && event.call == call1
+        && event.result.is_ok_and(|packet| packet.data() == [3, 2, 1])));
+    assert!(events[1].is_reply_and(|event| event.to == client
+        && event.call == call2
+        && matches!(event.result, Err(enet::error::RpcError::UnknownMethod))));
+
+    // calls fail once the peer stops replying
+    network.conditions(client, server, NetworkConditions::disconnected());
+    let call = network
+        .host_mut(client)
+        .peer_mut(peer)
+        .unwrap()
+        .call(7, &[])
+        .unwrap();
+    let events = network.update(150);
+    assert_eq!(events.len(), 1);
+    assert!(events[0]
+        .is_reply_and(|event| event.call == call
+            && matches!(event.result, Err(enet::error::RpcError::Timeout))));
+
+    // and when the peer disconnects
+    let call = network
+        .host_mut(client)
+        .peer_mut(peer)
+        .unwrap()
+        .call(7, &[])
+        .unwrap();
+    network
+        .host_mut(client)
+        .peer_mut(peer)
+        .unwrap()
+        .disconnect_now(0);
+    let events = network.update(10);
+    assert!(events
+        .iter()
+        .any(|event| event.is_reply_and(|event| event.call == call
+            && matches!(event.result, Err(enet::error::RpcError::Disconnected)))));
+}
+
+#[test]
+fn peer_stream() {
+    let mut network = Network::new();
+    let server = network.create_host(enet::HostSettings::default());
+    let client = network.create_host(enet::HostSettings::default());
+    network.connect(client, server, 2, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    network.conditions(client, server, NetworkConditions::good());
+
+    let data: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+    let updates = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let mut stream = enet::PeerStream::new(1, data.clone())
+        .chunk_size(4096)
+        .window(16384)
+        .on_progress({
+            let updates = updates.clone();
+            move |progress| {
+                assert!(progress.acknowledged <= progress.sent);
+                assert!(progress.sent - progress.acknowledged <= 16384);
+                updates.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        });
+    let mut receiver = enet::StreamReceiver::new();
+    let peer = network.resolve_peer(server, client);
+    for _ in 0..1000 {
+        stream
+            .update(network.host_mut(server).peer_mut(peer).unwrap())
+            .unwrap();
+        for event in network.update(10) {
+            if let enet::EventNoRef::Receive {
+                channel_id, packet, ..
+            } = event.event()
+            {
+                assert_eq!(*channel_id, 1);
+                receiver.receive(packet.data());
+            }
+        }
+        if stream.is_complete() {
+            break;
+        }
+    }
+    assert!(stream.is_complete());
+    assert_eq!(stream.progress().acknowledged, data.len());
+    assert!(updates.load(std::sync::atomic::Ordering::Relaxed) > 2);
+    assert_eq!(receiver.take(), Some(data));
+    assert_eq!(receiver.progress().total, 0);
+}
+
+/// Connect two hosts speaking `protocol_version`, and return the round trip time measured under
+/// perfect, good and bad network conditions.
+fn measure_round_trip_times(protocol_version: enet::ProtocolVersion) -> [u128; 3] {
     let mut network = Network::new();
     let host1 = network.create_host(enet::HostSettings {
         peer_limit: 1,
+        protocol_version,
         ..Default::default()
     });
     let host2 = network.create_host(enet::HostSettings {
         peer_limit: 1,
+        protocol_version,
         ..Default::default()
     });
 
     network.connect(host1, host2, 255, 5);
     network.update(2);
 
-    network.conditions(host1, host2, NetworkConditions::perfect());
-    network.update(10000);
-    assert_eq!(network.round_trip_time(host1, host2).as_millis(), 1);
+    let mut round_trip_times = [0; 3];
+    for (conditions, round_trip_time) in [
+        NetworkConditions::perfect(),
+        NetworkConditions::good(),
+        NetworkConditions::bad(),
+    ]
+    .into_iter()
+    .zip(&mut round_trip_times)
+    {
+        network.conditions(host1, host2, conditions);
+        network.update(10000);
+        *round_trip_time = network.round_trip_time(host1, host2).as_millis();
+    }
+    round_trip_times
+}
 
-    network.conditions(host1, host2, NetworkConditions::good());
-    network.update(10000);
-    assert_eq!(network.round_trip_time(host1, host2).as_millis(), 90);
+#[test]
+fn round_trip_time() {
+    // ENet's own protocol keeps ENet's measurements
+    assert_eq!(
+        measure_round_trip_times(enet::ProtocolVersion::Enet),
+        [1, 90, 338]
+    );
+}
 
-    network.conditions(host1, host2, NetworkConditions::bad());
-    network.update(10000);
-    assert_eq!(network.round_trip_time(host1, host2).as_millis(), 338);
+#[test]
+fn round_trip_time_extended() {
+    // the version confirmation and session ticket sent on connecting shift the simulated losses
+    assert_eq!(
+        measure_round_trip_times(enet::ProtocolVersion::Extended),
+        [1, 84, 222]
+    );
 }
 
 #[test]
@@ -187,3 +675,4016 @@ fn timeout() {
     assert_eq!(events.len(), 1);
     assert!(events[0].is_disconnect_and(|event| event.from == 1));
 }
+
+#[test]
+fn range_coder() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        compressor: Some(Box::new(enet::RangeCoder::new())),
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        compressor: Some(Box::new(enet::RangeCoder::new())),
+        ..Default::default()
+    });
+
+    network.connect(host1, host2, 255, 5);
+    network.update(2);
+
+    let data = "compressible ".repeat(32);
+    network.send(host1, host2, 0, &enet::Packet::reliable(data.as_bytes()));
+    let events = network.update(1);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == data.as_bytes()));
+}
+
+#[test]
+fn checksum() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        checksum: Some(Box::new(enet::crc32)),
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+
+    network.connect(host1, host2, 255, 5);
+    let events = network.update(100);
+    assert!(events.is_empty());
+
+    network
+        .host_mut(host2)
+        .set_checksum(Some(Box::new(enet::crc32)));
+    let events = network.update(2000);
+    assert_eq!(events.len(), 2);
+    assert!(events[0].is_connect());
+    assert!(events[1].is_connect());
+
+    network.send(
+        host1,
+        host2,
+        0,
+        &enet::Packet::reliable("checked".as_bytes()),
+    );
+    let events = network.update(1);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == "checked".as_bytes()));
+}
+
+struct Xor(u8);
+
+impl enet::PacketTransform for Xor {
+    fn encode(&mut self, data: &mut Vec<u8>) -> bool {
+        data.iter_mut().for_each(|byte| *byte ^= self.0);
+        true
+    }
+
+    fn decode(&mut self, data: &mut Vec<u8>) -> bool {
+        self.encode(data)
+    }
+}
+
+struct Magic(u8);
+
+impl enet::PacketTransform for Magic {
+    fn encode(&mut self, data: &mut Vec<u8>) -> bool {
+        data.insert(0, self.0);
+        true
+    }
+
+    fn decode(&mut self, data: &mut Vec<u8>) -> bool {
+        if data.first() == Some(&self.0) {
+            data.remove(0);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[test]
+fn transforms() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    network.host_mut(host1).add_transform(Box::new(Magic(7)));
+    network.host_mut(host1).add_transform(Box::new(Xor(0x5a)));
+    network.host_mut(host2).add_transform(Box::new(Xor(0x5a)));
+    network.host_mut(host2).add_transform(Box::new(Magic(7)));
+
+    // transforms are applied in order, so mismatched pipelines can't communicate
+    network.connect(host1, host2, 255, 0);
+    let events = network.update(100);
+    assert!(events.is_empty());
+
+    network.host_mut(host2).clear_transforms();
+    network.host_mut(host2).add_transform(Box::new(Magic(7)));
+    network.host_mut(host2).add_transform(Box::new(Xor(0x5a)));
+    let events = network.update(2000);
+    assert_eq!(events.len(), 2);
+    assert!(events[0].is_connect());
+    assert!(events[1].is_connect());
+
+    network.send(
+        host1,
+        host2,
+        0,
+        &enet::Packet::reliable("transformed".as_bytes()),
+    );
+    let events = network.update(1);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == "transformed".as_bytes()));
+}
+
+#[test]
+fn strict_validation() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        strict_validation: true,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+
+    // valid traffic is unaffected
+    network.connect(host2, host1, 2, 0);
+    let events = network.update(100);
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().all(|event| event.is_connect()));
+    network.send(host2, host1, 1, &enet::Packet::reliable(&[1, 2, 3]));
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive());
+
+    // a connect command requesting 0 channels
+    let mut connect = vec![0x0f, 0xff, 2 | 0x80, 0xff, 0, 1];
+    connect.extend_from_slice(&[0; 44]);
+    let datagrams: [(&[u8], enet::ProtocolViolationKind); 5] = [
+        (&[0x00, 0x64], enet::ProtocolViolationKind::UnknownPeer),
+        (
+            &[0x0f, 0xff, 2],
+            enet::ProtocolViolationKind::TruncatedCommand,
+        ),
+        (
+            &[0x0f, 0xff, 13, 0, 0, 0],
+            enet::ProtocolViolationKind::UnknownCommand,
+        ),
+        (
+            &[0x0f, 0xff, 5, 0, 0, 0],
+            enet::ProtocolViolationKind::UnexpectedCommand,
+        ),
+        (&connect, enet::ProtocolViolationKind::InvalidChannelCount),
+    ];
+    for (datagram, expected_kind) in datagrams {
+        network.send_raw(host2, host1, datagram);
+        let events = network.update(1);
+        assert_eq!(events.len(), 1);
+        assert!(
+            events[0].is_protocol_violation_and(|event| event.from == host2
+                && event.to == host1
+                && event.kind == expected_kind)
+        );
+
+        // hosts without strict validation silently ignore the same datagrams
+        network.send_raw(host1, host2, datagram);
+        assert!(network.update(1).is_empty());
+    }
+}
+
+#[test]
+fn report_malformed() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        checksum: Some(Box::new(enet::crc32)),
+        report_malformed: true,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    network.conditions(host1, host2, NetworkConditions::perfect());
+
+    let datagrams: [(&[u8], enet::ProtocolViolationKind); 3] = [
+        (&[0x0f], enet::ProtocolViolationKind::TruncatedHeader),
+        (
+            &[0x00, 0x64, 0, 0, 0, 0],
+            enet::ProtocolViolationKind::UnknownPeer,
+        ),
+        (
+            &[0x0f, 0xff, 1, 2, 3, 4, 2, 0],
+            enet::ProtocolViolationKind::BadChecksum,
+        ),
+    ];
+    for (datagram, expected_kind) in datagrams {
+        network.send_raw(host2, host1, datagram);
+        let events = network.update(1);
+        assert_eq!(events.len(), 1);
+        assert!(
+            events[0].is_protocol_violation_and(|event| event.from == host2
+                && event.to == host1
+                && event.kind == expected_kind)
+        );
+    }
+
+    // connection attempts from hosts without a checksum are reported
+    network.connect(host2, host1, 255, 0);
+    let events = network.update(2);
+    assert!(!events.is_empty());
+    assert!(events.iter().all(|event| {
+        event.is_protocol_violation_and(|event| {
+            event.kind == enet::ProtocolViolationKind::BadChecksum
+        })
+    }));
+}
+
+#[test]
+fn crafted_length_fields() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        report_malformed: true,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    network.connect(host2, host1, 1, 0);
+    let events = network.update(100);
+    assert_eq!(events.len(), 2);
+
+    // a header claiming a sent time the datagram is too short to hold
+    network.send_raw(host2, host1, &[0x8f, 0xff]);
+    let events = network.update(1);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_protocol_violation_and(|event| {
+        event.kind == enet::ProtocolViolationKind::TruncatedHeader
+    }));
+
+    // a reliable send claiming 100 bytes of data with only 2 following, sent with every session
+    // ID, as only the connection's session ID is accepted
+    for session_id in 0..4_u8 {
+        network.send_raw(
+            host2,
+            host1,
+            &[session_id << 4, 0, 6 | 0x80, 0, 0, 1, 0, 100, 1, 2],
+        );
+    }
+    let events = network.update(1);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_protocol_violation_and(|event| {
+        event.kind == enet::ProtocolViolationKind::TruncatedCommand
+    }));
+
+    // the connection is unaffected
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[1, 2, 3]));
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == [1, 2, 3]));
+}
+
+#[cfg(feature = "fuzzing")]
+#[test]
+fn fuzzing_session() {
+    use enet::fuzzing::{handle_session, parse_datagram, record_session, FuzzState};
+
+    let session = record_session();
+    let mut state = FuzzState::default();
+    let events = handle_session(&mut state, &session);
+    assert!(matches!(events[0], enet::EventNoRef::Connect { .. }));
+    let received: Vec<usize> = events
+        .iter()
+        .filter_map(|event| match event {
+            enet::EventNoRef::Receive { packet, .. } => Some(packet.data().len()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(received, [8, 10, 11, 3000, 3000]);
+    assert!(matches!(
+        events.last(),
+        Some(enet::EventNoRef::Disconnect { .. })
+    ));
+
+    // every datagram of the session parses, and arbitrary prefixes of them are rejected safely
+    let mut remaining = session.as_slice();
+    while let [high, low, rest @ ..] = remaining {
+        let (datagram, rest) = rest.split_at(usize::from(u16::from_be_bytes([*high, *low])));
+        assert!(parse_datagram(datagram, false));
+        for length in 0..datagram.len() {
+            _ = parse_datagram(&datagram[..length], false);
+            _ = parse_datagram(&datagram[..length], true);
+        }
+        remaining = rest;
+    }
+}
+
+#[cfg(feature = "proptest")]
+proptest::proptest! {
+    #![proptest_config(proptest::prelude::ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn delivery_invariants(scenario in enet::testing::strategies::scenario()) {
+        let deliveries = scenario.run();
+        proptest::prop_assert_eq!(deliveries.check(), Ok(()));
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn delivery_invariants_arbitrary() {
+    use rand::{RngCore, SeedableRng};
+
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+    for _ in 0..16 {
+        let mut bytes = vec![0; 8192];
+        rng.fill_bytes(&mut bytes);
+        let mut unstructured = arbitrary::Unstructured::new(&bytes);
+        let scenario: enet::testing::Scenario = unstructured.arbitrary().unwrap();
+        assert_eq!(scenario.run().check(), Ok(()), "{scenario:?}");
+    }
+}
+
+#[cfg(feature = "capi")]
+#[test]
+fn capi() {
+    use core::ptr;
+
+    use enet::capi::*;
+
+    unsafe {
+        let mut address = ENetAddress::default();
+        assert_eq!(
+            enet_address_set_host_ip(&raw mut address, c"127.0.0.1".as_ptr()),
+            0
+        );
+        let server = enet_host_create(&raw const address, 2, 2, 0, 0);
+        let client = enet_host_create(ptr::null(), 1, 2, 0, 0);
+        (*server).checksum = Some(enet_crc32);
+        (*client).checksum = Some(enet_crc32);
+        let peer = enet_host_connect(client, &raw const (*server).address, 2, 42);
+        assert_eq!(peer, (*client).peers);
+        let mut event: ENetEvent = core::mem::zeroed();
+        let mut service = |host: *mut ENetHost| {
+            let mut events = Vec::new();
+            while enet_host_service(host, &raw mut event, 1) > 0 {
+                let data = if event.packet.is_null() {
+                    Vec::new()
+                } else {
+                    let packet = &*event.packet;
+                    let data =
+                        core::slice::from_raw_parts(packet.data, packet.data_length).to_vec();
+                    enet_packet_destroy(event.packet);
+                    data
+                };
+                events.push((event.kind, event.peer, event.data, data));
+            }
+            events
+        };
+        let mut server_events = Vec::new();
+        for _ in 0..100 {
+            server_events.extend(service(server));
+            if (*peer).state == 5 {
+                break;
+            }
+            service(client);
+        }
+        assert_eq!((*peer).state, 5);
+        assert_eq!(server_events.len(), 1);
+        assert_eq!(server_events[0].0, ENET_EVENT_TYPE_CONNECT);
+        assert_eq!(server_events[0].2, 42);
+        let server_peer = server_events[0].1;
+        assert_eq!(server_peer.offset_from((*server).peers), 0);
+        assert_eq!((*server).connected_peers, 1);
+
+        // a packet may be sent to several peers, and is destroyed once flushed
+        let packet = enet_packet_create(b"hello".as_ptr().cast(), 5, ENET_PACKET_FLAG_RELIABLE);
+        assert_eq!(enet_peer_send(peer, 0, packet), 0);
+        assert_eq!(enet_peer_send(peer, 1, packet), 0);
+        assert_eq!((*packet).reference_count, 2);
+        enet_host_flush(client);
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            received.extend(
+                service(server)
+                    .into_iter()
+                    .filter(|event| event.0 == ENET_EVENT_TYPE_RECEIVE)
+                    .map(|event| event.3),
+            );
+            if received.len() == 2 {
+                break;
+            }
+            service(client);
+        }
+        assert_eq!(received, [b"hello".to_vec(), b"hello".to_vec()]);
+
+        enet_peer_disconnect(server_peer, 7);
+        let mut client_events = Vec::new();
+        for _ in 0..100 {
+            client_events.extend(service(client));
+            service(server);
+            if (*server_peer).state == 0 {
+                break;
+            }
+        }
+        assert_eq!((*server_peer).state, 0);
+        assert!(client_events
+            .iter()
+            .any(|event| event.0 == ENET_EVENT_TYPE_DISCONNECT && event.2 == 7));
+        enet_host_destroy(client);
+        enet_host_destroy(server);
+    }
+}
+
+struct DropFragment {
+    seen: usize,
+    drop: usize,
+}
+
+impl enet::PacketTransform for DropFragment {
+    fn encode(&mut self, data: &mut Vec<u8>) -> bool {
+        if data.len() < 1000 {
+            return true;
+        }
+        self.seen += 1;
+        self.seen != self.drop + 1
+    }
+
+    fn decode(&mut self, _data: &mut Vec<u8>) -> bool {
+        true
+    }
+}
+
+#[test]
+fn fragment_limits() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        maximum_incomplete_fragments: Some(1),
+        maximum_fragment_data: Some(6000),
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    network.connect(host2, host1, 255, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    network
+        .host_mut(host2)
+        .add_transform(Box::new(DropFragment { seen: 0, drop: 2 }));
+
+    // the last fragment of the first packet is lost, leaving it incomplete
+    let first = [1; 4000];
+    network.send(host2, host1, 0, &enet::Packet::always_unreliable(&first));
+    let events = network.update(10);
+    assert!(events.is_empty());
+
+    // starting another fragmented packet drops the incomplete one to stay within the limits
+    let second = [2; 4000];
+    network.send(host2, host1, 0, &enet::Packet::always_unreliable(&second));
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == second));
+
+    // packets which can never fit are discarded
+    let third = [3; 8000];
+    network.send(host2, host1, 0, &enet::Packet::always_unreliable(&third));
+    let events = network.update(10);
+    assert!(events.is_empty());
+
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[4; 100]));
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == [4; 100]));
+}
+
+#[test]
+fn unreliable_fragments() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    network.connect(host2, host1, 255, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    network
+        .host_mut(host2)
+        .add_transform(Box::new(DropFragment { seen: 0, drop: 1 }));
+
+    // a lost fragment discards the whole packet
+    let packet = enet::Packet::always_unreliable(&[1; 4000]);
+    assert_eq!(
+        packet.kind(),
+        enet::PacketKind::AlwaysUnreliable { sequenced: true }
+    );
+    network.send(host2, host1, 0, &packet);
+    let events = network.update(1000);
+    assert!(events.is_empty());
+
+    // while large unreliable packets are promoted to reliable fragments, and retransmitted
+    network.host_mut(host2).clear_transforms();
+    network
+        .host_mut(host2)
+        .add_transform(Box::new(DropFragment { seen: 0, drop: 1 }));
+    network.send(host2, host1, 0, &enet::Packet::unreliable(&[2; 4000]));
+    let events = network.update(1000);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == [2; 4000]));
+}
+
+#[test]
+fn maximum_packet_size() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        report_malformed: true,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    network.connect(host2, host1, 255, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    let peer = network.resolve_peer(host1, host2);
+    network
+        .host_mut(host1)
+        .peer_mut(peer)
+        .unwrap()
+        .set_maximum_packet_size(Some(5000));
+    assert_eq!(
+        network
+            .host(host1)
+            .peer(peer)
+            .unwrap()
+            .maximum_packet_size(),
+        5000
+    );
+
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[1; 4000]));
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == [1; 4000]));
+
+    network.send(
+        host2,
+        host1,
+        0,
+        &enet::Packet::always_unreliable(&[2; 6000]),
+    );
+    let events = network.update(10);
+    assert!(!events.is_empty());
+    assert!(events.iter().all(|event| {
+        event.is_protocol_violation_and(|event| {
+            event.from == host2 && event.kind == enet::ProtocolViolationKind::PacketTooLarge
+        })
+    }));
+}
+
+#[test]
+fn channel_maximum_packet_sizes() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        report_malformed: true,
+        channel_maximum_packet_sizes: vec![100, 5000],
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    network.connect(host2, host1, 255, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+
+    // each channel has its own limit
+    network.send(host2, host1, 1, &enet::Packet::reliable(&[1; 4000]));
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == [1; 4000]));
+
+    for packet in [
+        enet::Packet::unreliable(&[2; 200]),
+        enet::Packet::new(&[3; 200], enet::PacketKind::Unreliable { sequenced: false }),
+        enet::Packet::always_unreliable(&[4; 4000]),
+    ] {
+        network.send(host2, host1, 0, &packet);
+        let events = network.update(10);
+        assert!(!events.is_empty());
+        assert!(events.iter().all(|event| {
+            event.is_protocol_violation_and(|event| {
+                event.from == host2 && event.kind == enet::ProtocolViolationKind::PacketTooLarge
+            })
+        }));
+    }
+
+    // channels past the end of the list only use the host and peer limits
+    network.send(host2, host1, 2, &enet::Packet::reliable(&[5; 8000]));
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == [5; 8000]));
+}
+
+#[test]
+fn channel_modes() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        report_malformed: true,
+        channel_modes: vec![enet::ChannelMode::Reliable, enet::ChannelMode::Sequenced],
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        channel_modes: vec![enet::ChannelMode::Reliable],
+        ..Default::default()
+    });
+    network.connect(host2, host1, 255, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    let peer1 = network.resolve_peer(host1, host2);
+    let peer2 = network.resolve_peer(host2, host1);
+
+    // the channel's mode is used without repeating it
+    let peer = network.host_mut(host2).peer_mut(peer2).unwrap();
+    assert_eq!(peer.channel_mode(0), enet::ChannelMode::Reliable);
+    assert_eq!(peer.channel_mode(1), enet::ChannelMode::Any);
+    peer.send_on(0, &[1, 2, 3]).unwrap();
+    assert_eq!(
+        peer.send(0, &enet::Packet::unreliable(&[4])),
+        Err(enet::error::PeerSendError::ChannelModeMismatch)
+    );
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.channel_id == 0
+        && event.packet.data() == [1, 2, 3]
+        && event.packet.kind() == enet::PacketKind::Reliable));
+
+    network
+        .host_mut(host1)
+        .peer_mut(peer1)
+        .unwrap()
+        .send_on(1, &[5])
+        .unwrap();
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.channel_id == 1
+        && event.packet.kind() == enet::PacketKind::Unreliable { sequenced: true }));
+
+    // mismatched packets from the peer are rejected
+    network.send(
+        host2,
+        host1,
+        1,
+        &enet::Packet::new(&[6], enet::PacketKind::Unreliable { sequenced: false }),
+    );
+    let events = network.update(10);
+    assert!(!events.is_empty());
+    assert!(events.iter().all(|event| {
+        event.is_protocol_violation_and(|event| {
+            event.from == host2 && event.kind == enet::ProtocolViolationKind::ChannelModeMismatch
+        })
+    }));
+}
+
+#[test]
+fn latest_channel_mode() {
+    let mut network = Network::new();
+    let settings = || enet::HostSettings {
+        peer_limit: 1,
+        channel_modes: vec![enet::ChannelMode::Latest],
+        ..Default::default()
+    };
+    let host1 = network.create_host(settings());
+    let host2 = network.create_host(settings());
+    network.connect(host2, host1, 2, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+
+    // only the newest of the packets waiting on the channel is delivered
+    for i in 0..5 {
+        network.send(host2, host1, 0, &enet::Packet::unreliable(&[i]));
+        network.send(host2, host1, 1, &enet::Packet::unreliable(&[i]));
+    }
+    let events = network.update(10);
+    assert_eq!(events.len(), 6);
+    let channel0 = events
+        .iter()
+        .filter(|event| event.is_receive_and(|event| event.channel_id == 0))
+        .collect::<Vec<_>>();
+    assert_eq!(channel0.len(), 1);
+    assert!(channel0[0].is_receive_and(|event| event.packet.data() == [4]));
+}
+
+#[test]
+fn channel_priorities() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        channel_priorities: vec![0, 10],
+        ..Default::default()
+    });
+    network.connect(host2, host1, 2, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+
+    // packets on higher priority channels skip ahead of those already queued
+    for _ in 0..10 {
+        network.send(host2, host1, 0, &enet::Packet::reliable(&[0; 1000]));
+    }
+    network.send(host2, host1, 1, &enet::Packet::reliable(&[1]));
+    let events = network.update(10);
+    assert_eq!(events.len(), 11);
+    assert!(events[0].is_receive_and(|event| event.channel_id == 1));
+    assert!(events[1..]
+        .iter()
+        .all(|event| event.is_receive_and(|event| event.channel_id == 0)));
+}
+
+#[test]
+fn unreliable_drop_policies() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        channel_drop_policies: vec![
+            enet::UnreliableDropPolicy::DropOldest(2),
+            enet::UnreliableDropPolicy::DropNewest(2),
+            enet::UnreliableDropPolicy::KeepLatest,
+        ],
+        ..Default::default()
+    });
+    network.connect(host2, host1, 4, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+
+    for i in 0..5 {
+        for channel_id in 0..4 {
+            network.send(host2, host1, channel_id, &enet::Packet::unreliable(&[i]));
+        }
+        // reliable packets are never dropped
+        network.send(host2, host1, 2, &enet::Packet::reliable(&[i]));
+    }
+    let events = network.update(10);
+    let received = |channel_id: u8| {
+        events
+            .iter()
+            .filter_map(|event| match event.event() {
+                enet::EventNoRef::Receive {
+                    channel_id: id,
+                    packet,
+                    ..
+                } if *id == channel_id && packet.kind() != enet::PacketKind::Reliable => {
+                    Some(packet.data()[0])
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(received(0), [3, 4]);
+    assert_eq!(received(1), [0, 1]);
+    assert_eq!(received(2), [4]);
+    assert_eq!(received(3), [0, 1, 2, 3, 4]);
+    assert_eq!(events.len(), 15);
+}
+
+#[test]
+fn send_now() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    let host3 = network.create_host(enet::HostSettings::default());
+    network.connect(host2, host1, 1, 0);
+    network.connect(host3, host1, 1, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 4);
+    let peer2 = network.resolve_peer(host1, host2);
+    let peer3 = network.resolve_peer(host1, host3);
+
+    // only the peer sent to immediately has its queue flushed
+    let host = network.host_mut(host1);
+    host.peer_mut(peer2)
+        .unwrap()
+        .send_now(0, &enet::Packet::unreliable(&[1; 100]))
+        .unwrap();
+    host.peer_mut(peer3)
+        .unwrap()
+        .send(0, &enet::Packet::unreliable(&[2; 100]))
+        .unwrap();
+    assert_eq!(host.peer(peer2).unwrap().memory_usage(), 0);
+    assert!(host.peer(peer3).unwrap().memory_usage() > 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+}
+
+#[test]
+fn coalesce_delay() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings {
+        coalesce_delay: Duration::from_millis(5),
+        ..Default::default()
+    });
+    network.connect(host2, host1, 1, 0);
+    let events = network.update(20);
+    assert_eq!(events.len(), 2);
+    let peer = network.resolve_peer(host2, host1);
+
+    // small packets are held until the delay passes
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[1]));
+    assert!(network.update(3).is_empty());
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[2]));
+    let events = network.update(5);
+    assert_eq!(events.len(), 2);
+
+    // or until they fill a datagram
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[3; 1000]));
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[4; 1000]));
+    let events = network.update(2);
+    assert!(!events.is_empty());
+
+    network
+        .host_mut(host2)
+        .peer_mut(peer)
+        .unwrap()
+        .set_coalesce_delay(Some(Duration::ZERO));
+    assert_eq!(
+        network.host(host2).peer(peer).unwrap().coalesce_delay(),
+        Duration::ZERO
+    );
+    network.update(10);
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[5]));
+    let events = network.update(2);
+    assert_eq!(events.len(), 1);
+}
+
+#[test]
+fn congestion_controller() {
+    use enet::CongestionController;
+
+    struct Counting(Arc<AtomicUsize>);
+
+    impl enet::CongestionController for Counting {
+        fn throttle(&mut self, _peer: enet::PeerID, sample: enet::ThrottleSample) -> u32 {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            sample.limit / 2
+        }
+    }
+
+    let samples = Arc::new(AtomicUsize::new(0));
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings {
+        congestion_controller: Box::new(Counting(samples.clone())),
+        ..Default::default()
+    });
+    network.connect(host2, host1, 1, 0);
+    network.update(10);
+    for _ in 0..5 {
+        network.send(host2, host1, 0, &enet::Packet::reliable(&[1]));
+        network.update(10);
+    }
+    assert!(samples.load(Ordering::Relaxed) > 0);
+
+    // the delay gradient slows down once queueing delay passes its target
+    let mut controller = enet::DelayGradient::new(Duration::from_millis(20));
+    let sample = |round_trip_time, throttle| enet::ThrottleSample {
+        round_trip_time: Duration::from_millis(round_trip_time),
+        last_round_trip_time: Duration::from_millis(50),
+        last_round_trip_time_variance: Duration::from_millis(5),
+        throttle,
+        limit: 32,
+        acceleration: 2,
+        deceleration: 2,
+    };
+    let peer = enet::PeerID {
+        index: 0,
+        generation: 0,
+    };
+    assert_eq!(controller.throttle(peer, sample(50, 20)), 22);
+    assert_eq!(controller.throttle(peer, sample(65, 20)), 22);
+    assert_eq!(controller.throttle(peer, sample(110, 20)), 14);
+    controller.reset(peer);
+    assert_eq!(controller.throttle(peer, sample(110, 20)), 22);
+}
+
+#[test]
+fn window_size() {
+    let mut network = Network::new();
+    let settings = || enet::HostSettings {
+        outgoing_bandwidth_limit: Some(10000),
+        ..Default::default()
+    };
+    let host1 = network.create_host(settings());
+    let host2 = network.create_host(settings());
+    network.connect(host2, host1, 1, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    let peer = network.resolve_peer(host2, host1);
+
+    // the negotiated window is derived from the bandwidth limits
+    let peer = network.host_mut(host2).peer_mut(peer).unwrap();
+    assert_eq!(
+        peer.window_size(),
+        enet::consts::PROTOCOL_MINIMUM_WINDOW_SIZE
+    );
+    peer.set_window_size(Some(u32::MAX));
+    assert_eq!(
+        peer.window_size(),
+        enet::consts::PROTOCOL_MAXIMUM_WINDOW_SIZE
+    );
+    peer.set_window_size(None);
+    assert_eq!(
+        peer.window_size(),
+        enet::consts::PROTOCOL_MINIMUM_WINDOW_SIZE
+    );
+}
+
+#[test]
+fn ping_payloads() {
+    let mut network = Network::new();
+    let settings = || enet::HostSettings {
+        strict_validation: true,
+        ping_payloads: true,
+        ..Default::default()
+    };
+    let host1 = network.create_host(settings());
+    let host2 = network.create_host(settings());
+    network.connect(host2, host1, 1, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    let peer1 = network.resolve_peer(host1, host2);
+    let peer2 = network.resolve_peer(host2, host1);
+
+    let peer = network.host_mut(host2).peer_mut(peer2).unwrap();
+    assert!(peer
+        .set_ping_payload(&[0; enet::consts::PEER_MAXIMUM_PING_PAYLOAD + 1])
+        .is_err());
+    peer.set_ping_payload(&[1, 2, 3]).unwrap();
+    peer.ping();
+    let events = network.update(10);
+    assert!(events.is_empty());
+    let peer = network.host_mut(host1).peer_mut(peer1).unwrap();
+    assert_eq!(peer.remote_ping_payload(), Some(&[1, 2, 3][..]));
+    assert_eq!(peer.state(), enet::PeerState::Connected);
+}
+
+#[test]
+fn clock_sync() {
+    let mut network = Network::new();
+    let settings = || enet::HostSettings {
+        strict_validation: true,
+        clock_sync: true,
+        ..Default::default()
+    };
+    let host1 = network.create_host(settings());
+    let host2 = network.create_host(settings());
+    network.connect(host2, host1, 1, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    let peer2 = network.resolve_peer(host2, host1);
+    assert_eq!(
+        network
+            .host(host2)
+            .peer(peer2)
+            .unwrap()
+            .estimated_clock_offset(),
+        None
+    );
+
+    network.host_mut(host2).peer_mut(peer2).unwrap().ping();
+    let events = network.update(10);
+    assert!(events.is_empty());
+    // both hosts share the network's clock
+    let offset = network
+        .host(host2)
+        .peer(peer2)
+        .unwrap()
+        .estimated_clock_offset();
+    assert!(offset.is_some_and(|offset| offset.abs() <= 1));
+}
+
+#[test]
+fn protocol_version_downgrade() {
+    for (version1, version2, expected) in [
+        (
+            enet::ProtocolVersion::Extended,
+            enet::ProtocolVersion::Extended,
+            enet::ProtocolVersion::Extended,
+        ),
+        (
+            enet::ProtocolVersion::Extended,
+            enet::ProtocolVersion::Enet,
+            enet::ProtocolVersion::Enet,
+        ),
+        (
+            enet::ProtocolVersion::Enet,
+            enet::ProtocolVersion::Extended,
+            enet::ProtocolVersion::Enet,
+        ),
+    ] {
+        let mut network = Network::new();
+        let settings = |protocol_version| enet::HostSettings {
+            strict_validation: true,
+            protocol_version,
+            ping_payloads: true,
+            ..Default::default()
+        };
+        let host1 = network.create_host(settings(version1));
+        let host2 = network.create_host(settings(version2));
+        network.connect(host2, host1, 1, 0);
+        let events = network.update(10);
+        assert_eq!(events.len(), 2);
+        let peer1 = network.resolve_peer(host1, host2);
+        let peer2 = network.resolve_peer(host2, host1);
+        assert_eq!(
+            network.host(host1).peer(peer1).unwrap().protocol_version(),
+            expected
+        );
+        assert_eq!(
+            network.host(host2).peer(peer2).unwrap().protocol_version(),
+            expected
+        );
+
+        // extensions are only sent when both hosts speak them
+        let peer = network.host_mut(host2).peer_mut(peer2).unwrap();
+        peer.set_ping_payload(&[1, 2, 3]).unwrap();
+        peer.ping();
+        let events = network.update(10);
+        assert!(events.is_empty());
+        let peer = network.host(host1).peer(peer1).unwrap();
+        assert_eq!(
+            peer.remote_ping_payload().is_some(),
+            expected == enet::ProtocolVersion::Extended
+        );
+        assert_eq!(peer.state(), enet::PeerState::Connected);
+    }
+}
+
+#[test]
+fn extended_peer_ids() {
+    let mut network = Network::new();
+    let settings = |peer_limit, protocol_version| enet::HostSettings {
+        peer_limit,
+        protocol_version,
+        strict_validation: true,
+        ..Default::default()
+    };
+    assert!(enet::Host::new(
+        enet::ReadWrite::<(), core::convert::Infallible>::new(),
+        settings(enet::PeerID::MAX + 1, enet::ProtocolVersion::Extended)
+    )
+    .is_err());
+    let server = network.create_host(settings(
+        enet::PeerID::MAX + 2,
+        enet::ProtocolVersion::ExtendedPeerIds,
+    ));
+    // fill every slot other hosts can address
+    let crowd = network.create_host(settings(
+        enet::PeerID::MAX - 1,
+        enet::ProtocolVersion::Extended,
+    ));
+    for _ in 0..enet::PeerID::MAX - 1 {
+        network.connect(crowd, server, 1, 0);
+    }
+    network.update(100);
+    assert_eq!(
+        network.host_mut(server).connected_peers().count(),
+        enet::PeerID::MAX - 1
+    );
+
+    let client = network.create_host(settings(1, enet::ProtocolVersion::Extended));
+    network.connect(client, server, 1, 0);
+    network.update(100);
+    assert_eq!(
+        network.host_mut(server).connected_peers().count(),
+        enet::PeerID::MAX - 1
+    );
+
+    let client = network.create_host(settings(1, enet::ProtocolVersion::ExtendedPeerIds));
+    network.connect(client, server, 2, 0);
+    network.update(10);
+    let peer = network.resolve_peer(server, client);
+    assert_eq!(peer.index, enet::PeerID::MAX + 1);
+    assert_eq!(
+        network.host(server).peer(peer).unwrap().protocol_version(),
+        enet::ProtocolVersion::ExtendedPeerIds
+    );
+    network.send(client, server, 1, &enet::Packet::reliable(&[1, 2, 3]));
+    network.send(server, client, 1, &enet::Packet::reliable(&[4, 5, 6]));
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().all(|event| matches!(
+        event.event(),
+        enet::EventNoRef::Receive { channel_id: 1, .. }
+    )));
+}
+
+#[test]
+fn event_timestamps() {
+    let mut network = Network::new();
+    let server = network.create_host(enet::HostSettings::default());
+    let client = network.create_host(enet::HostSettings::default());
+    network.connect(client, server, 1, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+
+    let queued_at = network.host(server).now();
+    let packet = enet::PacketBuilder::new(&[1; 3000])
+        .reliable(true)
+        .tag(1)
+        .build();
+    network.send(server, client, 0, &packet);
+    // timestamps don't depend on when the event is serviced
+    let events = network.update(10);
+    let serviced_at = network.host(server).now();
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().any(|event| event.is_receive_and(|event| {
+        event.received_at >= queued_at && event.received_at < serviced_at
+    })));
+    assert!(events.iter().any(|event| matches!(
+        event.event(),
+        enet::EventNoRef::Acknowledge { queued_at: time, .. } if *time == queued_at
+    )));
+}
+
+#[test]
+fn pending_connection() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    let pending = network.host_mut(host2).connect(host1, 1, 0).unwrap();
+    network.conditions(host1, host2, NetworkConditions::perfect());
+    assert_eq!(
+        pending.status(network.host(host2)),
+        enet::ConnectionStatus::Connecting
+    );
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        pending.status(network.host(host2)),
+        enet::ConnectionStatus::Connected
+    );
+    assert_eq!(pending.peer_id(), network.resolve_peer(host2, host1));
+
+    network.disconnect(host2, host1, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        pending.status(network.host(host2)),
+        enet::ConnectionStatus::Failed
+    );
+}
+
+#[test]
+fn stale_peer_ids() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings::default());
+    network.connect(host2, host1, 1, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    let old_peer = network.resolve_peer(host1, host2);
+
+    network.disconnect(host2, host1, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    // disconnected peers keep their ID until the slot is reused
+    assert!(network.host(host1).peer(old_peer).is_some());
+    assert!(network.host(host1).is_valid(old_peer));
+
+    network.connect(host2, host1, 1, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    let new_peer = network.resolve_peer(host1, host2);
+    assert_eq!(new_peer.index, old_peer.index);
+    assert_ne!(new_peer.generation, old_peer.generation);
+    assert!(network.host(host1).peer(old_peer).is_none());
+    assert!(network.host(host1).peer(new_peer).is_some());
+    assert!(!network.host(host1).is_valid(old_peer));
+    assert!(network.host(host1).is_valid(new_peer));
+    assert!(!network.host(host1).is_valid(enet::PeerID {
+        index: 1,
+        ..new_peer
+    }));
+}
+
+#[test]
+fn threading() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<enet::Host<std::net::UdpSocket>>();
+    assert_send_sync::<enet::Packet>();
+
+    let packet = enet::Packet::reliable(&[1, 2, 3]);
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let packet = packet.clone();
+            std::thread::spawn(move || {
+                for _ in 0..1000 {
+                    drop(packet.clone());
+                }
+                packet
+            })
+        })
+        .collect();
+    for thread in threads {
+        assert_eq!(thread.join().unwrap().data(), &[1, 2, 3]);
+    }
+    assert!(!packet.is_shared());
+}
+
+#[test]
+fn threading_shared_packet() {
+    // hosts on different threads mark the same packet as sent while another thread reads its flags
+    let packet = enet::Packet::reliable(&[1, 2, 3]);
+    let threads: Vec<_> = (0..2)
+        .map(|_| {
+            let packet = packet.clone();
+            std::thread::spawn(move || {
+                let mut network = Network::new();
+                let host1 = network.create_host(enet::HostSettings::default());
+                let host2 = network.create_host(enet::HostSettings::default());
+                network.connect(host1, host2, 1, 0);
+                network.update(10);
+                let mut received = 0;
+                for _ in 0..10 {
+                    network.send(host1, host2, 0, &packet);
+                    let events = network.update(1);
+                    received += events.iter().filter(|event| event.is_receive()).count();
+                }
+                let events = network.update(10);
+                received + events.iter().filter(|event| event.is_receive()).count()
+            })
+        })
+        .collect();
+    for _ in 0..1000 {
+        assert!(packet.flags().reliable);
+    }
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), 10);
+    }
+    assert!(!packet.is_shared());
+}
+
+#[test]
+fn host_handle() {
+    let server_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_address = server_socket.local_addr().unwrap();
+    let server = enet::Host::new(server_socket, enet::HostSettings::default()).unwrap();
+    let mut client = enet::Host::new(
+        std::net::UdpSocket::bind("127.0.0.1:0").unwrap(),
+        enet::HostSettings::default(),
+    )
+    .unwrap();
+    let client_peer = client.connect(server_address, 1, 0).unwrap().peer_id();
+    let (server, server_events) = enet::HostHandle::spawn(server);
+    let (client, client_events) = enet::HostHandle::spawn(client);
+    let timeout = Duration::from_secs(5);
+
+    let enet::EventNoRef::Connect { peer, .. } =
+        server_events.recv_timeout(timeout).unwrap().unwrap()
+    else {
+        panic!("expected a connect event");
+    };
+    assert!(matches!(
+        client_events.recv_timeout(timeout).unwrap().unwrap(),
+        enet::EventNoRef::Connect { peer, .. } if peer == client_peer
+    ));
+
+    let sent = client
+        .send(client_peer, 0, enet::Packet::reliable(&[1, 2, 3]))
+        .unwrap();
+    assert_eq!(sent.recv_timeout(timeout).unwrap(), Ok(()));
+    let sent = client
+        .send(client_peer, 1, enet::Packet::reliable(&[1, 2, 3]))
+        .unwrap();
+    assert_eq!(
+        sent.recv_timeout(timeout).unwrap(),
+        Err(enet::error::PeerSendError::InvalidChannel)
+    );
+    let enet::EventNoRef::Receive { packet, .. } =
+        server_events.recv_timeout(timeout).unwrap().unwrap()
+    else {
+        panic!("expected a receive event");
+    };
+    assert_eq!(packet.data(), &[1, 2, 3]);
+    server.broadcast(0, packet).unwrap();
+    assert!(matches!(
+        client_events.recv_timeout(timeout).unwrap().unwrap(),
+        enet::EventNoRef::Receive { packet, .. } if packet.data() == [1, 2, 3]
+    ));
+
+    server.clone().disconnect(peer, 5).unwrap();
+    assert!(matches!(
+        client_events.recv_timeout(timeout).unwrap().unwrap(),
+        enet::EventNoRef::Disconnect { data: 5, .. }
+    ));
+    drop(client);
+    assert!(client_events.recv_timeout(timeout).is_err());
+}
+
+/// Connect two hosts over UDP and send a burst of small packets and a large fragmented packet,
+/// enough to fill several socket batches.
+fn batched_transfer<S>(new_socket: impl Fn(std::net::UdpSocket) -> S)
+where
+    S: enet::Socket<Address = std::net::SocketAddr>,
+    S::Error: core::fmt::Debug,
+{
+    let settings = || enet::HostSettings {
+        socket_batch_size: 16,
+        ecn: true,
+        ..Default::default()
+    };
+    let server_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_address = server_socket.local_addr().unwrap();
+    let mut server = enet::Host::new(new_socket(server_socket), settings()).unwrap();
+    let client_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut client = enet::Host::new(new_socket(client_socket), settings()).unwrap();
+    let client_peer = client.connect(server_address, 2, 0).unwrap().peer_id();
+
+    let start = std::time::Instant::now();
+    let mut sent = false;
+    let mut received = Vec::new();
+    while received.len() < 41 && start.elapsed() < Duration::from_secs(5) {
+        while let Some(event) = client.service().unwrap() {
+            if let enet::Event::Connect { peer, .. } = event {
+                for i in 0..40_u8 {
+                    peer.send(i % 2, &enet::Packet::reliable(&[i; 600]))
+                        .unwrap();
+                }
+                peer.send(0, &enet::Packet::reliable(&[40; 30000])).unwrap();
+                sent = true;
+            }
+        }
+        while let Some(event) = server.service().unwrap() {
+            if let enet::Event::Receive { packet, .. } = event {
+                assert!(packet.data().iter().all(|&byte| byte == packet.data()[0]));
+                received.push(packet.data()[0]);
+            }
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert!(sent);
+    assert_eq!(
+        client.peer(client_peer).unwrap().state(),
+        enet::PeerState::Connected
+    );
+    received.sort_unstable();
+    assert_eq!(received, (0..41).collect::<Vec<_>>());
+}
+
+#[test]
+fn socket_batching() {
+    batched_transfer(|socket| socket);
+}
+
+#[cfg(all(feature = "offload", target_os = "linux"))]
+#[test]
+fn offload_socket() {
+    batched_transfer(enet::OffloadUdpSocket::new);
+}
+
+#[cfg(all(feature = "sharding", target_os = "linux"))]
+#[test]
+fn sharded_host() {
+    let (server, shards) = enet::ShardedHost::bind(
+        "127.0.0.1:0".parse().unwrap(),
+        2,
+        enet::HostSettings::default,
+    )
+    .unwrap();
+    let mut clients: Vec<_> = (0..4)
+        .map(|_| {
+            let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            let mut client = enet::Host::new(socket, enet::HostSettings::default()).unwrap();
+            let peer = client.connect(server.local_addr(), 1, 0).unwrap().peer_id();
+            (client, peer)
+        })
+        .collect();
+
+    // every client connects to exactly one shard, which receives its packets
+    let start = std::time::Instant::now();
+    let mut peers = Vec::new();
+    let mut received = 0;
+    while received < clients.len() && start.elapsed() < Duration::from_secs(5) {
+        for (client, _) in &mut clients {
+            while let Some(event) = client.service().unwrap() {
+                if let enet::Event::Connect { peer, .. } = event {
+                    peer.send(0, &enet::Packet::reliable(&[1])).unwrap();
+                }
+            }
+        }
+        for (shard, events) in shards.iter().enumerate() {
+            while let Ok(event) = events.try_recv() {
+                match event.unwrap() {
+                    enet::EventNoRef::Connect { peer, .. } => {
+                        peers.push(enet::ShardPeer { shard, peer });
+                    }
+                    enet::EventNoRef::Receive { packet, .. } => {
+                        assert_eq!(packet.data(), &[1]);
+                        received += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(peers.len(), clients.len());
+    assert_eq!(received, clients.len());
+
+    // any shard's peers can be sent to through the sharded host
+    for &peer in &peers {
+        server.send(peer, 0, enet::Packet::reliable(&[2])).unwrap();
+    }
+    let mut replies = 0;
+    while replies < clients.len() && start.elapsed() < Duration::from_secs(5) {
+        for (client, _) in &mut clients {
+            while let Some(event) = client.service().unwrap() {
+                if let enet::Event::Receive { packet, .. } = event {
+                    assert_eq!(packet.data(), &[2]);
+                    replies += 1;
+                }
+            }
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(replies, clients.len());
+    for (client, peer) in &clients {
+        assert_eq!(
+            client.peer(*peer).unwrap().state(),
+            enet::PeerState::Connected
+        );
+    }
+}
+
+#[cfg(feature = "bevy")]
+#[test]
+fn bevy_plugin() {
+    use bevy_app::{App, FixedPreUpdate};
+    use bevy_ecs::message::Messages;
+
+    use enet::bevy::{EnetEvent, EnetHost, EnetPlugin};
+
+    let server_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_address = server_socket.local_addr().unwrap();
+    let server = enet::Host::new(server_socket, enet::HostSettings::default()).unwrap();
+    let mut client = enet::Host::new(
+        std::net::UdpSocket::bind("127.0.0.1:0").unwrap(),
+        enet::HostSettings::default(),
+    )
+    .unwrap();
+    client.connect(server_address, 1, 7).unwrap();
+
+    let mut app = App::new();
+    app.add_plugins(EnetPlugin::new(server));
+    let mut connected = false;
+    for _ in 0..1000 {
+        while client.service().unwrap().is_some() {}
+        app.world_mut().run_schedule(FixedPreUpdate);
+        let mut messages = app.world_mut().resource_mut::<Messages<EnetEvent>>();
+        if messages
+            .drain()
+            .any(|EnetEvent(event)| matches!(event, enet::EventNoRef::Connect { data: 7, .. }))
+        {
+            connected = true;
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert!(connected);
+    assert_eq!(
+        app.world_mut()
+            .resource_mut::<EnetHost<std::net::UdpSocket>>()
+            .connected_peers()
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn command_pool() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        command_pool_limit: 4,
+        ..Default::default()
+    });
+    network.connect(host2, host1, 255, 0);
+    network.update(10);
+
+    let send_burst = |network: &mut Network| {
+        for _ in 0..8 {
+            network.send(host2, host1, 0, &enet::Packet::reliable(&[1; 100]));
+        }
+        assert_eq!(network.update(10).len(), 8);
+    };
+    send_burst(&mut network);
+    let outgoing = network.host(host2).outgoing_command_pool_stats();
+    let incoming = network.host(host1).incoming_command_pool_stats();
+    assert_eq!(outgoing.available, 4);
+    assert_eq!(outgoing.allocated, 4);
+
+    // later bursts of the same size reuse the pooled commands
+    send_burst(&mut network);
+    send_burst(&mut network);
+    let stats = network.host(host1).incoming_command_pool_stats();
+    assert_eq!(stats.allocations, incoming.allocations);
+    assert!(stats.reuses >= 16);
+    let stats = network.host(host2).outgoing_command_pool_stats();
+    assert_eq!(stats.allocated, 4);
+    assert!(stats.reuses >= 8);
+}
+
+#[test]
+fn allocator_fixed_after_use() {
+    static ALLOCATOR: enet::Allocator = enet::Allocator::GLOBAL;
+
+    let host = enet::Host::new(
+        enet::ReadWrite::<(), core::convert::Infallible>::new(),
+        enet::HostSettings::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        enet::set_allocator(&ALLOCATOR),
+        Err(enet::error::AllocatorAlreadySet)
+    );
+    drop(host);
+}
+
+#[test]
+fn service_budget() {
+    type Socket = enet::ReadWrite<std::net::SocketAddr, core::convert::Infallible>;
+    let address = std::net::SocketAddr::from(([127, 0, 0, 1], 1));
+    let mut server = enet::Host::new(Socket::new(), enet::HostSettings::default()).unwrap();
+    let mut client = enet::Host::new(Socket::new(), enet::HostSettings::default()).unwrap();
+    let peer = client.connect(address, 1, 0).unwrap().peer_id();
+    let transfer = |from: &mut enet::Host<Socket>, to: &mut enet::Host<Socket>| {
+        while let Some((_, data)) = from.socket_mut().read() {
+            to.socket_mut().write(address, data);
+        }
+    };
+    for _ in 0..4 {
+        while client.service().unwrap().is_some() {}
+        transfer(&mut client, &mut server);
+        while server.service().unwrap().is_some() {}
+        transfer(&mut server, &mut client);
+    }
+    assert_eq!(
+        client.peer(peer).unwrap().state(),
+        enet::PeerState::Connected
+    );
+
+    for i in 0..20 {
+        let peer = client.peer_mut(peer).unwrap();
+        peer.send(0, &enet::Packet::unreliable(&[i])).unwrap();
+        peer.flush();
+    }
+    transfer(&mut client, &mut server);
+
+    let service = server.service_budget(100, 5).unwrap();
+    assert_eq!(service.events.len(), 5);
+    assert!(service.work_remaining);
+    let service = server.service_budget(3, 100).unwrap();
+    assert_eq!(service.events.len(), 3);
+    assert!(service.work_remaining);
+    let service = server.service_budget(100, 100).unwrap();
+    assert_eq!(service.events.len(), 12);
+    assert!(!service.work_remaining);
+    assert!(matches!(
+        service.events.last(),
+        Some(enet::EventNoRef::Receive { packet, .. }) if packet.data() == [19]
+    ));
+}
+
+#[test]
+fn service_all() {
+    type Socket = enet::ReadWrite<std::net::SocketAddr, core::convert::Infallible>;
+    let address = std::net::SocketAddr::from(([127, 0, 0, 1], 1));
+    let mut server = enet::Host::new(Socket::new(), enet::HostSettings::default()).unwrap();
+    let mut client = enet::Host::new(Socket::new(), enet::HostSettings::default()).unwrap();
+    let peer = client.connect(address, 1, 0).unwrap().peer_id();
+    let transfer = |from: &mut enet::Host<Socket>, to: &mut enet::Host<Socket>| {
+        while let Some((_, data)) = from.socket_mut().read() {
+            to.socket_mut().write(address, data);
+        }
+    };
+    let mut events = Vec::new();
+    for _ in 0..4 {
+        client.service_all(&mut events).unwrap();
+        transfer(&mut client, &mut server);
+        server.service_all(&mut events).unwrap();
+        transfer(&mut server, &mut client);
+    }
+    assert_eq!(events.len(), 2);
+    assert!(events
+        .iter()
+        .all(|event| matches!(event, enet::EventNoRef::Connect { .. })));
+
+    // every queued event is drained, and appended to what the buffer already holds
+    for i in 0..10 {
+        client
+            .peer_mut(peer)
+            .unwrap()
+            .send(0, &enet::Packet::reliable(&[i]))
+            .unwrap();
+    }
+    client.flush();
+    transfer(&mut client, &mut server);
+    server.service_all(&mut events).unwrap();
+    assert_eq!(events.len(), 12);
+    assert!(matches!(
+        events.last(),
+        Some(enet::EventNoRef::Receive { packet, .. }) if packet.data() == [9]
+    ));
+}
+
+#[test]
+fn run_with_handler() {
+    type Socket = enet::ReadWrite<std::net::SocketAddr, core::convert::Infallible>;
+
+    /// Echoes packets back to the client, which it services while idle.
+    struct Echo {
+        client: enet::Host<Socket>,
+        address: std::net::SocketAddr,
+        connected: bool,
+        disconnected: bool,
+        replies: Vec<Vec<u8>>,
+    }
+
+    impl enet::EventHandler<Socket> for Echo {
+        fn on_connect(&mut self, _peer: &mut enet::Peer<Socket>, data: u32) {
+            assert_eq!(data, 7);
+            self.connected = true;
+        }
+
+        fn on_receive(
+            &mut self,
+            peer: &mut enet::Peer<Socket>,
+            channel_id: u8,
+            packet: enet::Packet,
+        ) {
+            peer.send(channel_id, &packet).unwrap();
+        }
+
+        fn on_disconnect(&mut self, _peer: &mut enet::Peer<Socket>, _data: u32) {
+            self.disconnected = true;
+        }
+
+        fn on_idle(&mut self, host: &mut enet::Host<Socket>) -> core::ops::ControlFlow<()> {
+            while let Some((_, data)) = host.socket_mut().read() {
+                self.client.socket_mut().write(self.address, data);
+            }
+            while let Some(event) = self.client.service().unwrap() {
+                match event {
+                    enet::Event::Connect { peer, .. } => {
+                        peer.send_default(b"hello").unwrap();
+                    }
+                    enet::Event::Receive { peer, packet, .. } => {
+                        self.replies.push(packet.data().to_vec());
+                        peer.disconnect(0);
+                    }
+                    _ => {}
+                }
+            }
+            while let Some((_, data)) = self.client.socket_mut().read() {
+                host.socket_mut().write(self.address, data);
+            }
+            if self.disconnected {
+                core::ops::ControlFlow::Break(())
+            } else {
+                core::ops::ControlFlow::Continue(())
+            }
+        }
+
+        fn poll_interval(&self) -> Duration {
+            Duration::ZERO
+        }
+    }
+
+    let address = std::net::SocketAddr::from(([127, 0, 0, 1], 1));
+    let mut server = enet::Host::new(Socket::new(), enet::HostSettings::default()).unwrap();
+    let mut client = enet::Host::new(Socket::new(), enet::HostSettings::default()).unwrap();
+    client.connect(address, 1, 7).unwrap();
+    let mut echo = Echo {
+        client,
+        address,
+        connected: false,
+        disconnected: false,
+        replies: Vec::new(),
+    };
+    server.run_with_handler(&mut echo).unwrap();
+    assert!(echo.connected);
+    assert_eq!(echo.replies, [b"hello".to_vec()]);
+}
+
+#[test]
+fn connection_migration() {
+    type Socket = enet::ReadWrite<std::net::SocketAddr, core::convert::Infallible>;
+    let server_address = std::net::SocketAddr::from(([127, 0, 0, 1], 1));
+    let old_address = std::net::SocketAddr::from(([127, 0, 0, 1], 2));
+    let new_address = std::net::SocketAddr::from(([127, 0, 0, 2], 2));
+    let settings = |connection_migration| enet::HostSettings {
+        connection_migration,
+        checksum: Some(Box::new(enet::crc32)),
+        ..Default::default()
+    };
+    for connection_migration in [false, true] {
+        let mut server = enet::Host::new(Socket::new(), settings(connection_migration)).unwrap();
+        let mut client = enet::Host::new(Socket::new(), settings(false)).unwrap();
+        let client_peer = client.connect(server_address, 1, 0).unwrap().peer_id();
+        // returns the packets the server received, and the addresses it sent datagrams to
+        let exchange =
+            |server: &mut enet::Host<Socket>, client: &mut enet::Host<Socket>, client_address| {
+                let mut received = Vec::new();
+                let mut destinations = Vec::new();
+                for _ in 0..4 {
+                    while client.service().unwrap().is_some() {}
+                    while let Some((_, data)) = client.socket_mut().read() {
+                        server.socket_mut().write(client_address, data);
+                    }
+                    while let Some(event) = server.service().unwrap() {
+                        if let enet::Event::Receive { packet, .. } = event {
+                            received.push(packet.data().to_vec());
+                        }
+                    }
+                    while let Some((address, data)) = server.socket_mut().read() {
+                        destinations.push(address);
+                        client.socket_mut().write(server_address, data);
+                    }
+                }
+                (received, destinations)
+            };
+        _ = exchange(&mut server, &mut client, old_address);
+        assert_eq!(
+            client.peer(client_peer).unwrap().state(),
+            enet::PeerState::Connected
+        );
+        let server_peer = server.connected_peers().next().unwrap().id();
+        assert_eq!(
+            server.peer(server_peer).unwrap().address(),
+            Some(old_address)
+        );
+
+        client
+            .peer_mut(client_peer)
+            .unwrap()
+            .send(0, &enet::Packet::reliable(&[1, 2, 3]))
+            .unwrap();
+        // the datagram from the new address is dropped, and answers the challenge next time
+        let (received, destinations) = exchange(&mut server, &mut client, new_address);
+        assert!(received.is_empty());
+        let server_peer = server.peer(server_peer).unwrap().id();
+        if connection_migration {
+            assert_eq!(destinations.first(), Some(&new_address));
+            assert_eq!(
+                server.peer(server_peer).unwrap().address(),
+                Some(new_address)
+            );
+            client
+                .peer_mut(client_peer)
+                .unwrap()
+                .send(0, &enet::Packet::unreliable_unsequenced(&[4, 5, 6]))
+                .unwrap();
+            let (received, _) = exchange(&mut server, &mut client, new_address);
+            assert_eq!(received, [vec![4, 5, 6]]);
+        } else {
+            assert!(!destinations.contains(&new_address));
+            assert_eq!(
+                server.peer(server_peer).unwrap().address(),
+                Some(old_address)
+            );
+        }
+        assert_eq!(
+            server.peer(server_peer).unwrap().state(),
+            enet::PeerState::Connected
+        );
+    }
+}
+
+#[test]
+fn session_resumption() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        strict_validation: true,
+        session_resumption: Some(Duration::from_secs(60)),
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        strict_validation: true,
+        ..Default::default()
+    });
+    let host3 = network.create_host(enet::HostSettings {
+        strict_validation: true,
+        ..Default::default()
+    });
+    let session = |network: &Network| {
+        let peer = network.host(host1).peer(network.resolve_peer(host1, host2));
+        (peer.unwrap().session(), peer.unwrap().resumed())
+    };
+    network.connect(host2, host1, 1, 0);
+    network.update(10);
+    let (id, resumed) = session(&network);
+    assert!(id.is_some());
+    assert!(!resumed);
+
+    // the session survives graceful disconnects from either side
+    network.disconnect(host2, host1, 0);
+    network.update(10);
+    network.connect(host2, host1, 1, 0);
+    network.update(10);
+    assert_eq!(session(&network), (id, true));
+    network.disconnect(host1, host2, 0);
+    network.update(10);
+    network.connect(host2, host1, 1, 0);
+    network.update(10);
+    assert_eq!(session(&network), (id, true));
+
+    // the ticket is resent along with the connect command when it's lost
+    network.disconnect(host2, host1, 0);
+    network.update(10);
+    network.connect(host2, host1, 1, 0);
+    network.conditions(host2, host1, NetworkConditions::disconnected());
+    network.update(10);
+    network.conditions(host2, host1, NetworkConditions::perfect());
+    network.update(2000);
+    assert_eq!(session(&network), (id, true));
+
+    // but not abrupt ones
+    let peer2 = network.resolve_peer(host2, host1);
+    network.host_mut(host2).peer_mut(peer2).unwrap().reset();
+    let peer1 = network.resolve_peer(host1, host2);
+    network.host_mut(host1).peer_mut(peer1).unwrap().reset();
+    network.connect(host2, host1, 1, 0);
+    network.update(10);
+    let (new_id, resumed) = session(&network);
+    assert_ne!(new_id, id);
+    assert!(!resumed);
+
+    // and hosts without a ticket start a new session
+    network.connect(host3, host1, 1, 0);
+    network.update(10);
+    let peer = network.host(host1).peer(network.resolve_peer(host1, host3));
+    assert!(peer.unwrap().session().is_some_and(|id| Some(id) != new_id));
+    assert!(!peer.unwrap().resumed());
+}
+
+#[test]
+fn connection_migration_wrong_answers() {
+    type Socket = enet::ReadWrite<std::net::SocketAddr, core::convert::Infallible>;
+    let server_address = std::net::SocketAddr::from(([127, 0, 0, 1], 1));
+    let old_address = std::net::SocketAddr::from(([127, 0, 0, 1], 2));
+    let new_address = std::net::SocketAddr::from(([127, 0, 0, 2], 2));
+    let clock = Arc::new(std::sync::atomic::AtomicU64::new(0));
+    let settings = |connection_migration| {
+        let clock = clock.clone();
+        enet::HostSettings {
+            connection_migration,
+            time: Box::new(move || Duration::from_millis(clock.load(Ordering::Relaxed))),
+            ..Default::default()
+        }
+    };
+    let mut server = enet::Host::new(Socket::new(), settings(true)).unwrap();
+    let mut client = enet::Host::new(Socket::new(), settings(false)).unwrap();
+    let client_peer = client.connect(server_address, 1, 0).unwrap().peer_id();
+    // delivers the client's datagrams from `client_address`, returning the server's datagrams
+    let step =
+        |server: &mut enet::Host<Socket>, client: &mut enet::Host<Socket>, client_address| {
+            clock.fetch_add(10, Ordering::Relaxed);
+            while client.service().unwrap().is_some() {}
+            while let Some((_, data)) = client.socket_mut().read() {
+                server.socket_mut().write(client_address, data);
+            }
+            while server.service().unwrap().is_some() {}
+            let mut datagrams = Vec::new();
+            while let Some((_, data)) = server.socket_mut().read() {
+                datagrams.push(data);
+            }
+            datagrams
+        };
+    for _ in 0..4 {
+        for data in step(&mut server, &mut client, old_address) {
+            client.socket_mut().write(server_address, data);
+        }
+    }
+    let server_peer = server.connected_peers().next().unwrap().id();
+    assert_eq!(
+        server.peer(server_peer).unwrap().address(),
+        Some(old_address)
+    );
+
+    // the first datagram from the new address is challenged, and the challenge held back
+    client
+        .peer_mut(client_peer)
+        .unwrap()
+        .send(0, &enet::Packet::reliable(&[1]))
+        .unwrap();
+    let challenge = step(&mut server, &mut client, new_address);
+    // wrong answers spend the challenge, so even the right answer is refused afterwards
+    let peer = client.peer_mut(client_peer).unwrap().0;
+    for guess in 0..enet::consts::PEER_MIGRATION_CHALLENGE_MAXIMUM_FAILURES {
+        let mut response = [guess; 12];
+        response[8..].copy_from_slice(&unsafe { (*peer).connect_id }.to_be_bytes());
+        unsafe {
+            crate::c::enet_peer_send_protocol_message(
+                peer,
+                crate::c::ENET_PROTOCOL_MESSAGE_MIGRATION_RESPONSE,
+                &response,
+            );
+        }
+    }
+    _ = step(&mut server, &mut client, new_address);
+    for data in challenge {
+        client.socket_mut().write(server_address, data);
+    }
+    _ = step(&mut server, &mut client, new_address);
+    assert_eq!(
+        server.peer(server_peer).unwrap().address(),
+        Some(old_address)
+    );
+
+    // until a new challenge is sent
+    clock.fetch_add(
+        u64::from(enet::consts::PEER_MIGRATION_CHALLENGE_INTERVAL),
+        Ordering::Relaxed,
+    );
+    client
+        .peer_mut(client_peer)
+        .unwrap()
+        .send(0, &enet::Packet::reliable(&[2]))
+        .unwrap();
+    for _ in 0..2 {
+        for data in step(&mut server, &mut client, new_address) {
+            client.socket_mut().write(server_address, data);
+        }
+    }
+    assert_eq!(
+        server.peer(server_peer).unwrap().address(),
+        Some(new_address)
+    );
+}
+
+#[test]
+fn session_resumption_forged_ticket() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        session_resumption: Some(Duration::from_secs(60)),
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings::default());
+    let host3 = network.create_host(enet::HostSettings::default());
+    network.connect(host2, host1, 1, 0);
+    network.update(10);
+    let peer = network.resolve_peer(host1, host2);
+    let id = network.host(host1).peer(peer).unwrap().session().unwrap();
+    network.disconnect(host2, host1, 0);
+    network.update(10);
+
+    // another host presenting a ticket for the session with an adjacent or guessed token is refused
+    network.connect(host3, host1, 1, 0);
+    network.update(10);
+    let peer = network.resolve_peer(host1, host3);
+    let raw_peer = network.host_mut(host1).peer_mut(peer).unwrap().0;
+    let token = unsafe {
+        (*(*raw_peer).host)
+            .resumable_sessions
+            .assume_init_ref()
+            .iter()
+            .find(|resumable| resumable.session.id == id)
+            .unwrap()
+            .session
+            .token
+    };
+    let ticket = |token: [u8; 8]| {
+        let mut ticket = [0; 16];
+        ticket[..8].copy_from_slice(&id.to_be_bytes());
+        ticket[8..].copy_from_slice(&token);
+        ticket
+    };
+    let adjacent = u64::from_be_bytes(token).wrapping_add(1).to_be_bytes();
+    for forged in [adjacent, [0; 8]] {
+        unsafe { crate::c::enet_peer_resume_session(raw_peer, &ticket(forged)) };
+        let peer = network.host(host1).peer(peer).unwrap();
+        assert!(!peer.resumed());
+        assert_ne!(peer.session(), Some(id));
+    }
+
+    // while the genuine ticket still resumes it
+    unsafe { crate::c::enet_peer_resume_session(raw_peer, &ticket(token)) };
+    let peer = network.host(host1).peer(peer).unwrap();
+    assert!(peer.resumed());
+    assert_eq!(peer.session(), Some(id));
+}
+
+#[test]
+fn multihoming() {
+    type Socket = enet::ReadWrite<std::net::SocketAddr, core::convert::Infallible>;
+    let client_address = std::net::SocketAddr::from(([127, 0, 0, 1], 2));
+    let lan_address = std::net::SocketAddr::from(([192, 168, 0, 1], 1));
+    let wan_address = std::net::SocketAddr::from(([203, 0, 113, 1], 1));
+    let clock = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
+    let settings = || {
+        let clock = clock.clone();
+        enet::HostSettings {
+            time: Box::new(move || {
+                Duration::from_millis(clock.load(std::sync::atomic::Ordering::Relaxed))
+            }),
+            ..Default::default()
+        }
+    };
+    let mut server = enet::Host::new(Socket::new(), settings()).unwrap();
+    let mut client = enet::Host::new(Socket::new(), settings()).unwrap();
+    let client_peer = client.connect(lan_address, 1, 0).unwrap().peer_id();
+    // datagrams sent to the LAN address are dropped once it's down, and the server answers
+    // along the path each datagram arrived on, returning the packets it received
+    let exchange =
+        |server: &mut enet::Host<Socket>, client: &mut enet::Host<Socket>, lan_down: bool| {
+            let mut received = Vec::new();
+            for _ in 0..10 {
+                clock.fetch_add(100, std::sync::atomic::Ordering::Relaxed);
+                while client.service().unwrap().is_some() {}
+                let mut datagrams = vec![(lan_address, None)];
+                while let Some((address, data)) = client.socket_mut().read() {
+                    if !(lan_down && address == lan_address) {
+                        datagrams.push((address, Some(data)));
+                    }
+                }
+                for (address, data) in datagrams {
+                    if let Some(data) = data {
+                        server.socket_mut().write(client_address, data);
+                    }
+                    while let Some(event) = server.service().unwrap() {
+                        if let enet::Event::Receive { packet, .. } = event {
+                            received.push(packet.data().to_vec());
+                        }
+                    }
+                    while let Some((_, data)) = server.socket_mut().read() {
+                        client.socket_mut().write(address, data);
+                    }
+                }
+            }
+            received
+        };
+    _ = exchange(&mut server, &mut client, false);
+    assert_eq!(
+        client.peer(client_peer).unwrap().state(),
+        enet::PeerState::Connected
+    );
+
+    client
+        .peer_mut(client_peer)
+        .unwrap()
+        .add_address(wan_address);
+    _ = exchange(&mut server, &mut client, false);
+    let peer = client.peer(client_peer).unwrap();
+    assert_eq!(peer.address(), Some(lan_address));
+    assert!(peer
+        .addresses()
+        .all(|(_, round_trip_time)| round_trip_time.is_some()));
+
+    // the peer moves to the WAN address once the LAN address stops answering
+    _ = exchange(&mut server, &mut client, true);
+    _ = exchange(&mut server, &mut client, true);
+    let peer = client.peer(client_peer).unwrap();
+    assert_eq!(peer.address(), Some(wan_address));
+    assert_eq!(
+        peer.addresses()
+            .map(|(address, round_trip_time)| (*address, round_trip_time.is_some()))
+            .collect::<Vec<_>>(),
+        [(lan_address, false), (wan_address, true)]
+    );
+    client
+        .peer_mut(client_peer)
+        .unwrap()
+        .send(0, &enet::Packet::reliable(&[1, 2, 3]))
+        .unwrap();
+    let received = exchange(&mut server, &mut client, true);
+    assert_eq!(received, [vec![1, 2, 3]]);
+    assert_eq!(
+        client.peer(client_peer).unwrap().state(),
+        enet::PeerState::Connected
+    );
+}
+
+#[test]
+fn ecn() {
+    type Socket = enet::ReadWrite<std::net::SocketAddr, core::convert::Infallible>;
+    /// Marks every datagram received Congestion Experienced.
+    struct CongestedSocket(Socket);
+    impl enet::Socket for CongestedSocket {
+        type Address = std::net::SocketAddr;
+        type Error = core::convert::Infallible;
+        fn init(&mut self, socket_options: enet::SocketOptions) -> Result<(), Self::Error> {
+            assert!(socket_options.ecn);
+            Ok(())
+        }
+        fn send(&mut self, address: Self::Address, buffer: &[u8]) -> Result<usize, Self::Error> {
+            self.0.send(address, buffer)
+        }
+        fn receive(
+            &mut self,
+            buffer: &mut [u8; enet::MTU_MAX],
+        ) -> Result<Option<(Self::Address, enet::PacketReceived)>, Self::Error> {
+            Ok(match self.0.receive(buffer)? {
+                Some((address, enet::PacketReceived::Complete(length))) => {
+                    Some((address, enet::PacketReceived::CongestionExperienced(length)))
+                }
+                received => received,
+            })
+        }
+    }
+    /// Counts the congestion signals for each peer.
+    struct Counter(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+    impl enet::CongestionController for Counter {
+        fn throttle(&mut self, peer: enet::PeerID, sample: enet::ThrottleSample) -> u32 {
+            enet::EnetThrottle.throttle(peer, sample)
+        }
+        fn congestion_experienced(&mut self, _: enet::PeerID, sample: enet::ThrottleSample) -> u32 {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            sample.throttle / 2
+        }
+    }
+    let server_address = std::net::SocketAddr::from(([127, 0, 0, 1], 1));
+    let client_address = std::net::SocketAddr::from(([127, 0, 0, 1], 2));
+    let signals = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let mut server = enet::Host::new(
+        CongestedSocket(Socket::new()),
+        enet::HostSettings {
+            ecn: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let mut client = enet::Host::new(
+        Socket::new(),
+        enet::HostSettings {
+            ecn: true,
+            congestion_controller: Box::new(Counter(signals.clone())),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let client_peer = client.connect(server_address, 1, 0).unwrap().peer_id();
+    for i in 0..20 {
+        if client.peer(client_peer).unwrap().state() == enet::PeerState::Connected {
+            client
+                .peer_mut(client_peer)
+                .unwrap()
+                .send(0, &enet::Packet::reliable(&[i]))
+                .unwrap();
+        }
+        while client.service().unwrap().is_some() {}
+        while let Some((_, data)) = client.socket_mut().read() {
+            server.socket_mut().0.write(client_address, data);
+        }
+        while server.service().unwrap().is_some() {}
+        while let Some((_, data)) = server.socket_mut().0.read() {
+            client.socket_mut().write(server_address, data);
+        }
+    }
+    // echoes are sent at most once per round trip, so not every mark causes a signal
+    let marks = client.peer(client_peer).unwrap().congestion_marks();
+    let signals = signals.load(std::sync::atomic::Ordering::Relaxed);
+    assert!(marks > 1);
+    assert!((1..20).contains(&signals));
+}
+
+#[test]
+fn unreachable_connect() {
+    type Socket = enet::ReadWrite<std::net::SocketAddr, core::convert::Infallible>;
+    let address = std::net::SocketAddr::from(([127, 0, 0, 1], 1));
+    let other_address = std::net::SocketAddr::from(([127, 0, 0, 1], 2));
+    let mut host = enet::Host::new(Socket::new(), enet::HostSettings::default()).unwrap();
+    let peer = host.connect(address, 1, 0).unwrap().peer_id();
+    let other_peer = host.connect(other_address, 1, 0).unwrap().peer_id();
+    assert!(host.service().unwrap().is_none());
+    host.socket_mut().write_unreachable(address);
+    let event = host.service().unwrap().map(enet::Event::no_ref);
+    assert!(matches!(
+        event,
+        Some(enet::EventNoRef::Disconnect { peer: disconnected, data: 0 }) if disconnected == peer
+    ));
+    assert_eq!(
+        host.peer(other_peer).unwrap().state(),
+        enet::PeerState::Connecting
+    );
+}
+
+#[cfg(all(feature = "mmsg", target_os = "linux"))]
+#[test]
+fn unreachable_udp_connect() {
+    let closed_address = std::net::UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut host = enet::Host::new(socket, enet::HostSettings::default()).unwrap();
+    let peer = host.connect(closed_address, 1, 0).unwrap().peer_id();
+    let start = std::time::Instant::now();
+    let event = loop {
+        if let Some(event) = host.service().unwrap() {
+            break event.no_ref();
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+        std::thread::sleep(Duration::from_millis(1));
+    };
+    assert!(matches!(
+        event,
+        enet::EventNoRef::Disconnect { peer: disconnected, data: 0 } if disconnected == peer
+    ));
+}
+
+#[test]
+fn all_flushed() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    network.connect(host1, host2, 1, 0);
+    network.update(10);
+    assert!(network.host(host1).all_flushed());
+
+    // reliable packets are queued until they're acknowledged
+    network.send(host1, host2, 0, &enet::Packet::reliable(&[1, 2, 3]));
+    let peer = network.resolve_peer(host1, host2);
+    assert!(network
+        .host(host1)
+        .peer(peer)
+        .unwrap()
+        .has_queued_outgoing());
+    network.host_mut(host1).flush();
+    assert!(network
+        .host(host1)
+        .peer(peer)
+        .unwrap()
+        .has_queued_outgoing());
+    assert!(!network.host(host1).all_flushed());
+    network.update(10);
+    assert!(!network
+        .host(host1)
+        .peer(peer)
+        .unwrap()
+        .has_queued_outgoing());
+    assert!(network.host(host1).all_flushed());
+}
+
+#[test]
+fn peer_idle() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        idle_timeout: Some(Duration::from_millis(1000)),
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings::default());
+    network.connect(host2, host1, 1, 0);
+    let idle_events = |events: &[Event]| {
+        events
+            .iter()
+            .filter(|event| event.is_peer_idle_and(|_| true))
+            .count()
+    };
+
+    // pings keep the peer connected, but don't count as activity
+    let events = network.update(900);
+    assert_eq!(idle_events(&events), 0);
+    let events = network.update(1500);
+    assert_eq!(idle_events(&events), 1);
+    assert!(events.iter().any(|event| event.is_peer_idle_and(|event| {
+        event.from == host2 && event.to == host1 && event.idle_for >= Duration::from_millis(1000)
+    })));
+
+    // sending a packet resets the timer
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[1, 2, 3]));
+    let events = network.update(900);
+    assert_eq!(idle_events(&events), 0);
+    let events = network.update(500);
+    assert_eq!(idle_events(&events), 1);
+}
+
+#[test]
+fn missed_ping_limit() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        missed_ping_limit: Some(4),
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings::default());
+    network.connect(host2, host1, 1, 0);
+    network.update(1000);
+    let peer = network.resolve_peer(host1, host2);
+    assert_eq!(
+        network.host(host1).peer(peer).unwrap().missed_ping_limit(),
+        Some(4)
+    );
+
+    // 4 missed ping intervals take 2 seconds from the last acknowledgement, well before the
+    // reliable command timeout
+    network.conditions(host1, host2, NetworkConditions::disconnected());
+    let events = network.update(1400);
+    assert!(!events.iter().any(Event::is_disconnect));
+    let events = network.update(700);
+    assert!(events
+        .iter()
+        .any(|event| event.is_disconnect_and(|event| event.from == host2 && event.to == host1)));
+}
+
+#[test]
+fn timeout_durations() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    network.connect(host1, host2, 1, 0);
+    network.update(10);
+    let peer = network.resolve_peer(host1, host2);
+    let peer = network.host_mut(host1).peer_mut(peer).unwrap();
+    peer.set_timeout_durations(8, Duration::from_secs(2), Duration::from_secs(10));
+    peer.set_ping_interval(Duration::from_millis(250));
+    assert_eq!(peer.timeout_limit(), 8);
+    assert_eq!(peer.timeout_minimum(), Duration::from_secs(2));
+    assert_eq!(peer.timeout_maximum(), Duration::from_secs(10));
+    assert_eq!(peer.ping_interval(), Duration::from_millis(250));
+
+    // zero restores the defaults
+    peer.set_timeout_durations(0, Duration::ZERO, Duration::ZERO);
+    peer.set_ping_interval(Duration::ZERO);
+    assert_eq!(peer.timeout_limit(), enet::consts::PEER_TIMEOUT_LIMIT);
+    assert_eq!(
+        peer.timeout_minimum(),
+        Duration::from_millis(enet::consts::PEER_TIMEOUT_MINIMUM.into())
+    );
+    assert_eq!(
+        peer.timeout_maximum(),
+        Duration::from_millis(enet::consts::PEER_TIMEOUT_MAXIMUM.into())
+    );
+    assert_eq!(
+        peer.ping_interval(),
+        Duration::from_millis(enet::consts::PEER_PING_INTERVAL.into())
+    );
+}
+
+#[test]
+fn error_sources() {
+    use std::{error::Error, string::ToString};
+    /// Fails to initialize.
+    struct BrokenSocket;
+    impl enet::Socket for BrokenSocket {
+        type Address = std::net::SocketAddr;
+        type Error = std::io::Error;
+        fn init(&mut self, _: enet::SocketOptions) -> Result<(), Self::Error> {
+            Err(std::io::Error::other("broken"))
+        }
+        fn send(&mut self, _: Self::Address, _: &[u8]) -> Result<usize, Self::Error> {
+            unreachable!()
+        }
+        fn receive(
+            &mut self,
+            _: &mut [u8; enet::MTU_MAX],
+        ) -> Result<Option<(Self::Address, enet::PacketReceived)>, Self::Error> {
+            unreachable!()
+        }
+    }
+    let Err(err) = enet::Host::new(BrokenSocket, enet::HostSettings::default()) else {
+        panic!("Expected the socket to fail to initialize.");
+    };
+    assert_eq!(err.source().unwrap().to_string(), "broken");
+
+    let err = enet::error::BatchSendError {
+        sent: 1,
+        error: enet::error::PeerSendError::InvalidChannel,
+    };
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        enet::error::PeerSendError::InvalidChannel.to_string()
+    );
+}
+
+#[test]
+fn service_error() {
+    /// Fails to send to port 1, and to receive after `fail_receive` is set.
+    struct FlakySocket {
+        fail_receive: bool,
+    }
+    impl enet::Socket for FlakySocket {
+        type Address = std::net::SocketAddr;
+        type Error = std::io::Error;
+        fn init(&mut self, _: enet::SocketOptions) -> Result<(), Self::Error> {
+            Ok(())
+        }
+        fn send(&mut self, address: Self::Address, buffer: &[u8]) -> Result<usize, Self::Error> {
+            if address.port() == 1 {
+                Err(std::io::Error::other("send"))
+            } else {
+                Ok(buffer.len())
+            }
+        }
+        fn receive(
+            &mut self,
+            _: &mut [u8; enet::MTU_MAX],
+        ) -> Result<Option<(Self::Address, enet::PacketReceived)>, Self::Error> {
+            if self.fail_receive {
+                Err(std::io::Error::other("receive"))
+            } else {
+                Ok(None)
+            }
+        }
+    }
+    let address = std::net::SocketAddr::from(([127, 0, 0, 1], 1));
+    let mut host = enet::Host::new(
+        FlakySocket {
+            fail_receive: false,
+        },
+        enet::HostSettings::default(),
+    )
+    .unwrap();
+    host.connect(address, 1, 0).unwrap();
+    let Err(err) = host.service() else {
+        panic!("Expected sending to fail.");
+    };
+    assert_eq!(err.stage, enet::error::ServiceStage::Send);
+    assert_eq!(err.address, Some(address));
+    assert_eq!(err.error.kind(), std::io::ErrorKind::Other);
+
+    host.socket_mut().fail_receive = true;
+    let Err(err) = host.service() else {
+        panic!("Expected receiving to fail.");
+    };
+    assert_eq!(err.stage, enet::error::ServiceStage::Receive);
+    assert_eq!(err.address, None);
+}
+
+#[test]
+fn flush_errors() {
+    /// Fails to send to port 1, and records the ports it sent to.
+    struct FailingSocket {
+        sent_to: Vec<u16>,
+    }
+    impl enet::Socket for FailingSocket {
+        type Address = std::net::SocketAddr;
+        type Error = std::io::Error;
+        fn init(&mut self, _: enet::SocketOptions) -> Result<(), Self::Error> {
+            Ok(())
+        }
+        fn send(&mut self, address: Self::Address, buffer: &[u8]) -> Result<usize, Self::Error> {
+            if address.port() == 1 {
+                Err(std::io::Error::other("send"))
+            } else {
+                self.sent_to.push(address.port());
+                Ok(buffer.len())
+            }
+        }
+        fn receive(
+            &mut self,
+            _: &mut [u8; enet::MTU_MAX],
+        ) -> Result<Option<(Self::Address, enet::PacketReceived)>, Self::Error> {
+            Ok(None)
+        }
+    }
+    let failing = std::net::SocketAddr::from(([127, 0, 0, 1], 1));
+    let working = std::net::SocketAddr::from(([127, 0, 0, 1], 2));
+    let mut host = enet::Host::new(
+        FailingSocket {
+            sent_to: Vec::new(),
+        },
+        enet::HostSettings::default(),
+    )
+    .unwrap();
+    host.connect(failing, 1, 0).unwrap();
+    host.connect(working, 1, 0).unwrap();
+    let errors = host.flush();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].stage, enet::error::ServiceStage::Send);
+    assert_eq!(errors[0].address, Some(failing));
+    assert_eq!(host.socket().sent_to, [2]);
+    assert!(host.flush().is_empty());
+}
+
+#[test]
+fn channel_stats() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    network.connect(host1, host2, 2, 0);
+    network.update(10);
+    for _ in 0..3 {
+        network.send(host1, host2, 1, &enet::Packet::reliable(&[1, 2, 3]));
+    }
+    network.send(host1, host2, 1, &enet::Packet::unreliable(&[4]));
+    network.update(10);
+    let peer1 = network.resolve_peer(host1, host2);
+    let peer2 = network.resolve_peer(host2, host1);
+    let stats = network
+        .host(host1)
+        .peer(peer1)
+        .unwrap()
+        .channel_stats(1)
+        .unwrap();
+    assert_eq!(stats.packets_sent, 4);
+    assert_eq!(stats.reliable_resends, 0);
+    assert_eq!(stats.next_reliable_sequence_number, 4);
+    assert_eq!(stats.next_unreliable_sequence_number, 2);
+    let stats = network
+        .host(host2)
+        .peer(peer2)
+        .unwrap()
+        .channel_stats(1)
+        .unwrap();
+    assert_eq!(stats.packets_received, 4);
+    assert_eq!(stats.incoming_queued, 0);
+    assert_eq!(
+        network.host(host2).peer(peer2).unwrap().channel_stats(0),
+        Some(enet::ChannelStats {
+            next_reliable_sequence_number: 1,
+            next_unreliable_sequence_number: 1,
+            ..Default::default()
+        })
+    );
+    assert_eq!(
+        network.host(host1).peer(peer1).unwrap().channel_stats(2),
+        None
+    );
+
+    network.conditions(host1, host2, NetworkConditions::disconnected());
+    network.send(host1, host2, 1, &enet::Packet::reliable(&[5]));
+    network.update(1000);
+    let stats = network
+        .host(host1)
+        .peer(peer1)
+        .unwrap()
+        .channel_stats(1)
+        .unwrap();
+    assert!(stats.reliable_resends > 0);
+    assert_eq!(
+        network
+            .host(host1)
+            .peer(peer1)
+            .unwrap()
+            .channel_stats(0)
+            .unwrap()
+            .reliable_resends,
+        0
+    );
+}
+
+#[test]
+fn packet_resent() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        resend_event_threshold: Some(1),
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings::default());
+    network.connect(host1, host2, 2, 0);
+    network.update(10);
+    let peer = network.resolve_peer(host1, host2);
+    assert_eq!(
+        network.host(host1).peer(peer).unwrap().reliable_resends(),
+        0
+    );
+
+    network.conditions(host1, host2, NetworkConditions::disconnected());
+    network.send(host1, host2, 1, &enet::Packet::reliable(&[0; 4000]));
+    let events = network.update(4000);
+    let resends = network.host(host1).peer(peer).unwrap().reliable_resends();
+    assert!(resends >= 2);
+    // the first resend is under the threshold, and fragments are reported once per resend
+    let resent = events
+        .iter()
+        .filter(|event| event.is_packet_resent_and(|_| true))
+        .count();
+    assert!(resent > 0);
+    assert!(!events
+        .iter()
+        .any(|event| event.is_packet_resent_and(|event| {
+            event.from != host2 || event.to != host1 || event.channel_id != 1 || event.attempts < 2
+        })));
+    for attempts in 2..2 + resent as u32 {
+        assert!(events
+            .iter()
+            .any(|event| event.is_packet_resent_and(|event| event.attempts == attempts)));
+    }
+}
+
+#[test]
+fn throttle_status() {
+    let mut network = Network::new();
+    // the remote host only limits its incoming bandwidth for peers with an outgoing limit
+    let host1 = network.create_host(enet::HostSettings {
+        outgoing_bandwidth_limit: Some(100_000),
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        incoming_bandwidth_limit: Some(2000),
+        ..Default::default()
+    });
+    network.connect(host1, host2, 1, 0);
+    network.update(10);
+    let peer = network.resolve_peer(host1, host2);
+    let status = |network: &Network| {
+        let peer = network.host(host1).peer(peer).unwrap();
+        (
+            peer.packet_throttle(),
+            peer.packet_throttle_limit(),
+            peer.bandwidth_limited(),
+        )
+    };
+    assert_eq!(
+        status(&network),
+        (
+            enet::consts::PEER_DEFAULT_PACKET_THROTTLE,
+            enet::consts::PEER_PACKET_THROTTLE_SCALE,
+            false
+        )
+    );
+    assert_eq!(
+        network.host(host1).peer(peer).unwrap().throttle_interval(),
+        enet::consts::PEER_PACKET_THROTTLE_INTERVAL
+    );
+
+    for _ in 0..25 {
+        for _ in 0..10 {
+            network.send(host1, host2, 0, &enet::Packet::unreliable(&[0; 500]));
+        }
+        network.update(100);
+    }
+    let (throttle, limit, bandwidth_limited) = status(&network);
+    assert!(bandwidth_limited);
+    assert!(limit < enet::consts::PEER_PACKET_THROTTLE_SCALE);
+    assert!(throttle <= limit);
+}
+
+#[test]
+fn pending_events() {
+    let socket1 = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let socket2 = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address2 = socket2.local_addr().unwrap();
+    let mut host1 = enet::Host::new(socket1, enet::HostSettings::default()).unwrap();
+    let mut host2 = enet::Host::new(socket2, enet::HostSettings::default()).unwrap();
+    let peer1 = host1.connect(address2, 1, 0).unwrap().peer_id();
+    let mut peer2 = None;
+    let start = std::time::Instant::now();
+    while !host1.peer(peer1).unwrap().connected() || peer2.is_none() {
+        _ = host1.service().unwrap();
+        if let Some(enet::Event::Connect { peer, .. }) = host2.service().unwrap() {
+            peer2 = Some(peer.id());
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    let peer2 = peer2.unwrap();
+    assert_eq!(host2.pending_events(), 0);
+
+    for _ in 0..3 {
+        host1
+            .peer_mut(peer1)
+            .unwrap()
+            .send(0, &enet::Packet::reliable(&[0; 100]))
+            .unwrap();
+    }
+    host1.flush();
+    let start = std::time::Instant::now();
+    while !matches!(host2.service().unwrap(), Some(enet::Event::Receive { .. })) {
+        assert!(start.elapsed() < Duration::from_secs(1));
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(host2.pending_events(), 2);
+    assert_eq!(host2.peer(peer2).unwrap().incoming_waiting_bytes(), 200);
+    while host2.service().unwrap().is_some() {}
+    assert_eq!(host2.pending_events(), 0);
+    assert_eq!(host2.peer(peer2).unwrap().incoming_waiting_bytes(), 0);
+}
+
+#[test]
+fn pause_unreliable() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    network.connect(host1, host2, 1, 0);
+    network.update(10);
+    let peer = network.resolve_peer(host1, host2);
+    network
+        .host_mut(host1)
+        .peer_mut(peer)
+        .unwrap()
+        .pause_unreliable(true);
+    assert!(network.host(host1).peer(peer).unwrap().unreliable_paused());
+
+    network.send(host1, host2, 0, &enet::Packet::unreliable_unsequenced(&[1]));
+    network.send(host1, host2, 0, &enet::Packet::reliable(&[2]));
+    let events = network.update(100);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == [2]));
+    assert!(network
+        .host(host1)
+        .peer(peer)
+        .unwrap()
+        .has_queued_outgoing());
+
+    network
+        .host_mut(host1)
+        .peer_mut(peer)
+        .unwrap()
+        .pause_unreliable(false);
+    let events = network.update(100);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == [1]));
+}
+
+#[test]
+fn acknowledgement_mode() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings {
+        acknowledgement_mode: enet::AcknowledgementMode::Delayed(Duration::from_millis(50)),
+        ..Default::default()
+    });
+    network.connect(host1, host2, 1, 0);
+    let events = network.update(20);
+    assert_eq!(events.len(), 2);
+    let is_acknowledge =
+        |event: &Event| matches!(event.event(), enet::EventNoRef::Acknowledge { .. });
+
+    // acknowledgements are held until the delay passes
+    let packet = enet::PacketBuilder::new(&[1]).reliable(true).tag(1).build();
+    network.send(host1, host2, 0, &packet);
+    let events = network.update(20);
+    assert!(events.iter().any(|event| event.is_receive_and(|_| true)));
+    assert!(!events.iter().any(is_acknowledge));
+    let events = network.update(50);
+    assert_eq!(
+        events.iter().filter(|event| is_acknowledge(event)).count(),
+        1
+    );
+
+    // or until something else is sent to the peer
+    network.send(host1, host2, 0, &packet);
+    network.update(5);
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[2]));
+    let events = network.update(5);
+    assert_eq!(
+        events.iter().filter(|event| is_acknowledge(event)).count(),
+        1
+    );
+}
+
+#[test]
+fn fast_retransmit() {
+    fn frames_until_resent(fast_retransmit: Option<u32>) -> usize {
+        let mut network = Network::new();
+        let host1 = network.create_host(enet::HostSettings {
+            fast_retransmit,
+            ..Default::default()
+        });
+        let host2 = network.create_host(enet::HostSettings::default());
+        network.connect(host1, host2, 1, 0);
+        let events = network.update(20);
+        assert_eq!(events.len(), 2);
+
+        network.conditions(host1, host2, NetworkConditions::disconnected());
+        network.send(host1, host2, 0, &enet::Packet::reliable(&[0]));
+        network.update(1);
+        network.conditions(host1, host2, NetworkConditions::perfect());
+        for frame in 1..=1000 {
+            if frame <= 3 {
+                network.send(host1, host2, 0, &enet::Packet::reliable(&[frame as u8]));
+            }
+            let events = network.update(1);
+            if events.iter().any(|event| event.is_receive_and(|_| true)) {
+                return frame;
+            }
+        }
+        panic!("The lost packet was never resent.");
+    }
+
+    // three later packets are acknowledged within a few frames, long before the resend timeout
+    assert!(frames_until_resent(Some(3)) < 10);
+    assert!(frames_until_resent(None) > 100);
+}
+
+#[test]
+fn channel_parity_groups() {
+    let mut network = Network::new();
+    let settings = || enet::HostSettings {
+        channel_parity_groups: vec![0, 4],
+        ..Default::default()
+    };
+    let host1 = network.create_host(settings());
+    let host2 = network.create_host(settings());
+    network.connect(host1, host2, 2, 0);
+    let events = network.update(20);
+    assert_eq!(events.len(), 2);
+    let received = |events: &[Event]| {
+        let mut received = events
+            .iter()
+            .filter_map(|event| match event.event() {
+                enet::EventNoRef::Receive {
+                    channel_id: 1,
+                    packet,
+                    ..
+                } => Some(packet.data().to_vec()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        received.sort();
+        received
+    };
+
+    // a lost packet is rebuilt from the rest of its group
+    let unreliable = |data: &[u8]| enet::Packet::unreliable_unsequenced(data);
+    network.send(host1, host2, 1, &unreliable(&[1]));
+    let mut events = network.update(1);
+    network.conditions(host1, host2, NetworkConditions::disconnected());
+    network.send(host1, host2, 1, &unreliable(&[2, 2, 2]));
+    events.extend(network.update(1));
+    network.conditions(host1, host2, NetworkConditions::perfect());
+    network.send(host1, host2, 1, &unreliable(&[3; 100]));
+    network.send(host1, host2, 1, &unreliable(&[]));
+    events.extend(network.update(5));
+    assert_eq!(
+        received(&events),
+        [vec![], vec![1], vec![2, 2, 2], vec![3; 100]]
+    );
+
+    // reliable packets pass through untouched
+    network.send(host1, host2, 1, &enet::Packet::reliable(&[4]));
+    let events = network.update(5);
+    assert_eq!(received(&events), [vec![4]]);
+}
+
+#[test]
+fn unsequenced_dedup_window() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings {
+        unsequenced_dedup_window: 2,
+        ..Default::default()
+    });
+    network.connect(host1, host2, 2, 0);
+    let events = network.update(20);
+    assert_eq!(events.len(), 2);
+    let peer = network.resolve_peer(host2, host1);
+
+    let unsequenced = |data: &[u8]| enet::Packet::unreliable_unsequenced(data);
+    network.send(host1, host2, 0, &unsequenced(&[1]));
+    network.send(host1, host2, 0, &unsequenced(&[1]));
+    network.send(host1, host2, 1, &unsequenced(&[1]));
+    network.send(host1, host2, 0, &unsequenced(&[2]));
+    network.send(host1, host2, 0, &unsequenced(&[3]));
+    // only the last two packets are remembered
+    network.send(host1, host2, 0, &unsequenced(&[1]));
+    network.send(host1, host2, 0, &enet::Packet::unreliable(&[1]));
+    let events = network.update(5);
+    assert_eq!(events.len(), 6);
+    assert_eq!(
+        network
+            .host(host2)
+            .peer(peer)
+            .unwrap()
+            .unsequenced_duplicates(),
+        1
+    );
+}
+
+#[test]
+fn connect_payload() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    assert_eq!(
+        network.host_mut(host1).connect_with_payload(
+            host2,
+            1,
+            0,
+            &[0; enet::consts::PEER_MAXIMUM_CONNECT_PAYLOAD + 1]
+        ),
+        Err(enet::error::ConnectError::PayloadTooLarge)
+    );
+
+    // the payload is resent along with the connect command
+    network.conditions(host1, host2, NetworkConditions::disconnected());
+    network
+        .host_mut(host1)
+        .connect_with_payload(host2, 1, 7, b"token")
+        .unwrap();
+    assert!(network.update(100).is_empty());
+    network.conditions(host1, host2, NetworkConditions::perfect());
+    let events = network.update(2000);
+    let payloads = events
+        .iter()
+        .filter_map(|event| match event.event() {
+            enet::EventNoRef::Connect { data, payload, .. } => Some((*data, payload.clone())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(payloads.len(), 2);
+    assert!(payloads.contains(&(7, b"token".to_vec())));
+    assert!(payloads.contains(&(0, Vec::new())));
+}
+
+#[test]
+fn connect_approval() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings {
+        connect_approval: true,
+        ..Default::default()
+    });
+    let host3 = network.create_host(enet::HostSettings::default());
+    let request = |events: &[Event]| {
+        events.iter().find_map(|event| match event.event() {
+            enet::EventNoRef::ConnectRequest {
+                peer,
+                data,
+                payload,
+                channel_count,
+            } => Some((*peer, *data, payload.clone(), *channel_count)),
+            _ => None,
+        })
+    };
+    let connections = |events: &[Event]| {
+        events
+            .iter()
+            .filter_map(|event| match event.event() {
+                enet::EventNoRef::Connect {
+                    data,
+                    payload,
+                    channel_count,
+                    ..
+                } => Some((event.to(), *data, payload.clone(), *channel_count)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // requests wait for the application, while the connecting host keeps resending them
+    network.conditions(host1, host2, NetworkConditions::perfect());
+    network
+        .host_mut(host1)
+        .connect_with_payload(host2, 4, 7, b"token")
+        .unwrap();
+    let events = network.update(100);
+    let (peer, data, payload, channel_count) = request(&events).unwrap();
+    assert_eq!(
+        (data, payload.as_slice(), channel_count),
+        (7, &b"token"[..], 4)
+    );
+    assert!(connections(&events).is_empty());
+    assert!(connections(&network.update(1000)).is_empty());
+
+    // accepted peers connect with the accepted channels, and the data is resent along with the
+    // verify connect command
+    network.conditions(host1, host2, NetworkConditions::disconnected());
+    network.host_mut(host2).peer_mut(peer).unwrap().accept(2, 9);
+    assert!(network.update(100).is_empty());
+    network.conditions(host1, host2, NetworkConditions::perfect());
+    let mut connected = connections(&network.update(2000));
+    connected.sort();
+    assert_eq!(
+        connected,
+        [(host1, 9, Vec::new(), 2), (host2, 7, b"token".to_vec(), 2)]
+    );
+
+    // rejected peers are disconnected with the code
+    network.conditions(host3, host2, NetworkConditions::perfect());
+    network.host_mut(host3).connect(host2, 1, 0).unwrap();
+    let (peer, ..) = request(&network.update(100)).unwrap();
+    network.host_mut(host2).peer_mut(peer).unwrap().reject(5);
+    let events = network.update(100);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_disconnect_and(|event| event.to == host3 && event.data == 5));
+}
+
+#[test]
+fn redirect() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        follow_redirects: true,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings::default());
+    let host3 = network.create_host(enet::HostSettings::default());
+    network.connect(host1, host2, 1, 0);
+    network.update(1000);
+    network.conditions(host1, host3, NetworkConditions::perfect());
+
+    // the redirected peer disconnects with the data, and connects to the new address with it
+    let peer = network.resolve_peer(host2, host1);
+    let peer = network.host_mut(host2).peer_mut(peer).unwrap();
+    peer.redirect(&host3, 4).unwrap();
+    assert_eq!(
+        peer.redirect(&host3, 4),
+        Err(enet::error::BadParameter {
+            method: "Peer::redirect",
+            parameter: "self",
+        })
+    );
+    let events = network.update(1000);
+    assert!(events
+        .iter()
+        .any(|event| event.is_disconnect_and(|event| event.to == host1 && event.data == 4)));
+    assert!(events.iter().any(|event| event.to() == host3
+        && matches!(event.event(), enet::EventNoRef::Connect { data: 4, .. })));
+}
+
+#[test]
+fn redirect_ignored() {
+    let mut network = Network::new();
+    let settings = || enet::HostSettings {
+        follow_redirects: true,
+        ..Default::default()
+    };
+    let host1 = network.create_host(settings());
+    let host2 = network.create_host(settings());
+    let host3 = network.create_host(settings());
+    network.conditions(host1, host3, NetworkConditions::perfect());
+    network.conditions(host2, host3, NetworkConditions::perfect());
+    let connected_to_host3 = |events: &[Event]| {
+        events.iter().any(|event| {
+            event.to() == host3 && matches!(event.event(), enet::EventNoRef::Connect { .. })
+        })
+    };
+
+    // peers which connected to this host can't redirect it
+    network.connect(host1, host2, 1, 0);
+    network.update(1000);
+    let peer = network.resolve_peer(host1, host2);
+    network
+        .host_mut(host1)
+        .peer_mut(peer)
+        .unwrap()
+        .redirect(&host3, 4)
+        .unwrap();
+    let events = network.update(1000);
+    assert!(events
+        .iter()
+        .any(|event| event.is_disconnect_and(|event| event.to == host2 && event.data == 4)));
+    assert!(!connected_to_host3(&events));
+
+    // and a redirect only applies to the disconnect right after it
+    network.connect(host1, host2, 1, 0);
+    network.update(1000);
+    let peer = network.resolve_peer(host2, host1);
+    let raw_peer = network.host_mut(host2).peer_mut(peer).unwrap().0;
+    unsafe {
+        crate::c::enet_peer_send_protocol_message(
+            raw_peer,
+            crate::c::ENET_PROTOCOL_MESSAGE_REDIRECT,
+            &enet::Address::to_bytes(&host3).unwrap(),
+        );
+    }
+    network.update(1000);
+    network.disconnect(host2, host1, 5);
+    let events = network.update(1000);
+    assert!(events
+        .iter()
+        .any(|event| event.is_disconnect_and(|event| event.to == host1 && event.data == 5)));
+    assert!(!connected_to_host3(&events));
+}
+
+#[test]
+fn ban() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    let host3 = network.create_host(enet::HostSettings::default());
+
+    // datagrams from banned addresses are dropped, including connection requests
+    network
+        .host_mut(host2)
+        .ban(host1, Duration::from_millis(2000));
+    assert!(network.host_mut(host2).is_banned(&host1));
+    assert!(!network.host_mut(host2).is_banned(&host3));
+    network.connect(host1, host2, 1, 0);
+    assert!(network.update(1000).is_empty());
+
+    // bans expire, and the connecting host's retries get through
+    let events = network.update(3000);
+    assert!(!network.host_mut(host2).is_banned(&host1));
+    assert!(events
+        .iter()
+        .any(|event| event.to() == host2
+            && matches!(event.event(), enet::EventNoRef::Connect { .. })));
+
+    // bans can be lifted early
+    network.host_mut(host2).ban(host3, Duration::from_secs(60));
+    assert!(network.host_mut(host2).unban(&host3));
+    assert!(!network.host_mut(host2).unban(&host3));
+    network.connect(host3, host2, 1, 0);
+    assert_eq!(network.update(100).len(), 2);
+}
+
+#[test]
+fn connect_audit() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    let host3 = network.create_host(enet::HostSettings::default());
+    let outcomes = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let audit = outcomes.clone();
+    network
+        .host_mut(host2)
+        .set_connect_audit(Some(Box::new(move |address, outcome| {
+            audit.lock().unwrap().push((*address, outcome));
+        })));
+    let take = || core::mem::take(&mut *outcomes.lock().unwrap());
+
+    network.connect(host1, host2, 1, 0);
+    network.update(100);
+    assert_eq!(take(), [(host1, enet::ConnectOutcome::Accepted)]);
+
+    // every retry is reported
+    network.connect(host3, host2, 1, 0);
+    network.update(1000);
+    let attempts = take();
+    assert!(attempts.len() > 1);
+    assert!(attempts
+        .iter()
+        .all(|outcome| *outcome == (host3, enet::ConnectOutcome::PeerLimit)));
+
+    network.host_mut(host2).ban(host3, Duration::from_secs(60));
+    network.update(1000);
+    assert!(take().contains(&(host3, enet::ConnectOutcome::Banned)));
+
+    // datagrams from banned peers which aren't connection requests aren't reported
+    network.host_mut(host2).ban(host1, Duration::from_secs(60));
+    network
+        .host_mut(host1)
+        .peer_mut(enet::PeerID {
+            index: 0,
+            generation: 1,
+        })
+        .unwrap()
+        .send(0, &enet::Packet::reliable(&[0]))
+        .unwrap();
+    network.update(1000);
+    assert!(!take().contains(&(host1, enet::ConnectOutcome::Banned)));
+}
+
+#[test]
+fn broadcast_raw() {
+    let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    let address = receiver.local_addr().unwrap();
+    let received = || {
+        let mut count = 0;
+        let mut buffer = [0; 16];
+        while let Ok(length) = receiver.recv(&mut buffer) {
+            assert_eq!(&buffer[..length], b"hello");
+            count += 1;
+        }
+        count
+    };
+    let clock = Arc::new(AtomicUsize::new(0));
+    let time = clock.clone();
+    let mut host = enet::Host::new(
+        std::net::UdpSocket::bind("127.0.0.1:0").unwrap(),
+        enet::HostSettings {
+            raw_datagram_rate: 2,
+            time: Box::new(move || Duration::from_millis(time.load(Ordering::Relaxed) as u64)),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    // datagrams over the rate limit wait for later services
+    host.broadcast_raw(&[address; 3], b"hello").unwrap();
+    assert_eq!(received(), 2);
+    clock.store(499, Ordering::Relaxed);
+    assert!(host.service().unwrap().is_none());
+    assert_eq!(received(), 0);
+    clock.store(500, Ordering::Relaxed);
+    assert!(host.service().unwrap().is_none());
+    assert_eq!(received(), 1);
+
+    assert_eq!(
+        host.broadcast_raw(&[address], &[0; enet::MTU_MAX + 1]),
+        Err(enet::error::BadParameter {
+            method: "Host::broadcast_raw",
+            parameter: "data",
+        })
+    );
+}
+
+#[cfg(all(feature = "mmsg", target_os = "linux"))]
+#[test]
+fn dscp_udp() {
+    use std::os::fd::AsRawFd;
+
+    let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+    let enable: libc::c_int = 1;
+    assert_eq!(
+        unsafe {
+            libc::setsockopt(
+                receiver.as_raw_fd(),
+                libc::IPPROTO_IP,
+                libc::IP_RECVTOS,
+                core::ptr::from_ref(&enable).cast(),
+                core::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        },
+        0
+    );
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut host = enet::Host::new(socket, enet::HostSettings::default()).unwrap();
+    let peer = host
+        .connect(receiver.local_addr().unwrap(), 1, 0)
+        .unwrap()
+        .peer_id();
+    assert_eq!(
+        host.peer_mut(peer).unwrap().set_dscp(Some(64)),
+        Err(enet::error::BadParameter {
+            method: "Peer::set_dscp",
+            parameter: "dscp",
+        })
+    );
+    host.peer_mut(peer).unwrap().set_dscp(Some(46)).unwrap();
+    host.flush();
+
+    // the connect command arrives marked Expedited Forwarding
+    let mut buffer = [0_u8; enet::MTU_MAX];
+    let mut iovec = libc::iovec {
+        iov_base: buffer.as_mut_ptr().cast(),
+        iov_len: buffer.len(),
+    };
+    let mut control: [libc::cmsghdr; 2] = unsafe { core::mem::zeroed() };
+    let mut header: libc::msghdr = unsafe { core::mem::zeroed() };
+    header.msg_iov = &raw mut iovec;
+    header.msg_iovlen = 1;
+    header.msg_control = core::ptr::from_mut(&mut control).cast();
+    header.msg_controllen = core::mem::size_of::<[libc::cmsghdr; 2]>();
+    assert!(unsafe { libc::recvmsg(receiver.as_raw_fd(), &raw mut header, 0) } > 0);
+    let control = unsafe { libc::CMSG_FIRSTHDR(&raw const header) };
+    assert!(!control.is_null());
+    assert_eq!(
+        unsafe { ((*control).cmsg_level, (*control).cmsg_type) },
+        (libc::IPPROTO_IP, libc::IP_TOS)
+    );
+    assert_eq!(unsafe { *libc::CMSG_DATA(control) }, 46 << 2);
+}
+
+#[test]
+fn receive_metadata() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    network.host_mut(host2).socket_mut().metadata = enet::ReceiveMetadata {
+        hop_limit: Some(60),
+        destination: Some(7),
+        ..Default::default()
+    };
+    network.connect(host1, host2, 1, 0);
+    network.update(100);
+
+    // metadata is recorded for the peer when the socket supplies it
+    let peer = network.resolve_peer(host2, host1);
+    let peer = network.host(host2).peer(peer).unwrap();
+    assert_eq!(
+        (peer.hop_limit(), peer.local_address()),
+        (Some(60), Some(7))
+    );
+    let peer = network.resolve_peer(host1, host2);
+    let peer = network.host(host1).peer(peer).unwrap();
+    assert_eq!((peer.hop_limit(), peer.local_address()), (None, None));
+}
+
+#[test]
+fn connect_racing() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    let host3 = network.create_host(enet::HostSettings::default());
+    network.conditions(host1, host3, NetworkConditions::perfect());
+
+    // the preferred address doesn't answer, so the next is attempted after the delay and wins
+    let race = network
+        .host_mut(host1)
+        .connect_racing([host2, host3], 1, 5)
+        .unwrap();
+    assert!(network.update(200).is_empty());
+    assert_eq!(
+        race.status(network.host(host1)),
+        enet::ConnectionStatus::Connecting
+    );
+    let events = network.update(100);
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().any(|event| event.to() == host3
+        && matches!(event.event(), enet::EventNoRef::Connect { data: 5, .. })));
+    let peer = race.peer_id(network.host(host1)).unwrap();
+    assert_eq!(
+        network.host(host1).peer(peer).unwrap().address(),
+        Some(host3)
+    );
+    assert_eq!(
+        race.status(network.host(host1)),
+        enet::ConnectionStatus::Connected
+    );
+
+    // the cancelled attempt never reports a disconnection
+    assert!(network.update(10000).is_empty());
+
+    // when every attempt fails, only the last reports it
+    let race = network
+        .host_mut(host1)
+        .connect_racing([host2, host2], 1, 0)
+        .unwrap();
+    let events = network.update(40000);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_disconnect_and(|event| event.to == host1));
+    assert_eq!(
+        race.status(network.host(host1)),
+        enet::ConnectionStatus::Failed
+    );
+    assert_eq!(race.peer_id(network.host(host1)), None);
+}
+
+#[test]
+fn send_default() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        channel_limit: 2,
+        default_channel: 1,
+        default_packet_kind: enet::PacketKind::Unreliable { sequenced: false },
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        channel_limit: 2,
+        ..Default::default()
+    });
+    network.connect(host1, host2, 2, 0);
+    network.update(100);
+
+    // packets use the host's defaults
+    let peer = network.resolve_peer(host1, host2);
+    network
+        .host_mut(host1)
+        .peer_mut(peer)
+        .unwrap()
+        .send_default(b"hello")
+        .unwrap();
+    let events = network.update(100);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.to == host2
+        && event.channel_id == 1
+        && event.packet.data() == b"hello"
+        && event.packet.kind() == enet::PacketKind::Unreliable { sequenced: false }));
+
+    // unless the peer overrides them
+    let peer = network.host_mut(host1).peer_mut(peer).unwrap();
+    peer.set_defaults(0, enet::PacketKind::Reliable);
+    assert_eq!(peer.defaults(), (0, enet::PacketKind::Reliable));
+    peer.send_default(b"world").unwrap();
+    let events = network.update(100);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.channel_id == 0
+        && event.packet.data() == b"world"
+        && event.packet.kind() == enet::PacketKind::Reliable));
+}
+
+#[test]
+fn connect_data() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    let host3 = network.create_host(enet::HostSettings {
+        connect_approval: true,
+        ..Default::default()
+    });
+
+    // the connect data reaches the remote's connect event, and is echoed back locally
+    network.connect(host1, host2, 1, 42);
+    let events = network.update(100);
+    assert_eq!(events.len(), 2);
+    assert!(events
+        .iter()
+        .any(|event| event.is_connect_and(|event| event.to == host2
+            && event.data == 42
+            && event.connect_data == 42)));
+    assert!(events.iter().any(|event| event
+        .is_connect_and(|event| event.to == host1 && event.data == 0 && event.connect_data == 42)));
+    let peer1 = network.resolve_peer(host1, host2);
+    let peer2 = network.resolve_peer(host2, host1);
+    assert_eq!(network.host(host1).peer(peer1).unwrap().connect_data(), 42);
+    assert_eq!(network.host(host2).peer(peer2).unwrap().connect_data(), 42);
+
+    // both sides see the connect data, even when the connecting side receives accept data
+    network.connect(host1, host3, 1, 5);
+    let events = network.update(100);
+    let enet::EventNoRef::ConnectRequest { peer, .. } = events[0].event() else {
+        panic!("Expected a connect request.");
+    };
+    network
+        .host_mut(host3)
+        .peer_mut(*peer)
+        .unwrap()
+        .accept(1, 9);
+    let events = network.update(100);
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().any(|event| event
+        .is_connect_and(|event| event.to == host3 && event.data == 5 && event.connect_data == 5)));
+    assert!(events.iter().any(|event| event
+        .is_connect_and(|event| event.to == host1 && event.data == 9 && event.connect_data == 5)));
+}
+
+#[test]
+fn simulate() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings::default());
+    let host2 = network.create_host(enet::HostSettings::default());
+    network.connect(host1, host2, 1, 0);
+    network.update(1000);
+    let peer1 = network.resolve_peer(host1, host2);
+    let peer2 = network.resolve_peer(host2, host1);
+
+    // datagrams to the peer are held back for the simulated latency
+    let peer = network.host_mut(host1).peer_mut(peer1).unwrap();
+    peer.simulate(0, Duration::from_millis(100), Duration::ZERO)
+        .unwrap();
+    assert_eq!(
+        peer.simulate(101, Duration::ZERO, Duration::ZERO),
+        Err(enet::error::BadParameter {
+            method: "Peer::simulate",
+            parameter: "loss_percent",
+        })
+    );
+    assert_eq!(
+        peer.simulation(),
+        Some(enet::PeerSimulation {
+            loss_percent: 0,
+            min_latency: Duration::from_millis(100),
+            jitter: Duration::ZERO,
+        })
+    );
+    network.send(host1, host2, 0, &enet::Packet::reliable(b"late"));
+    assert!(network.update(90).is_empty());
+    let events = network.update(20);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.to == host2 && event.packet.data() == b"late"));
+
+    // datagrams from the peer are dropped at the simulated loss rate
+    let peer = network.host_mut(host1).peer_mut(peer1).unwrap();
+    peer.simulate(100, Duration::ZERO, Duration::ZERO).unwrap();
+    network
+        .host_mut(host2)
+        .peer_mut(peer2)
+        .unwrap()
+        .send(0, &enet::Packet::reliable(b"lost"))
+        .unwrap();
+    assert!(network.update(500).is_empty());
+
+    // and arrive once the simulation stops
+    let peer = network.host_mut(host1).peer_mut(peer1).unwrap();
+    peer.simulate(0, Duration::ZERO, Duration::ZERO).unwrap();
+    assert_eq!(peer.simulation(), None);
+    let events = network.update(2000);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.to == host1 && event.packet.data() == b"lost"));
+}
+
+#[test]
+fn connect_loopback() {
+    let mut network = Network::new();
+    let host = network.create_host(enet::HostSettings::default());
+
+    // both ends connect without the socket, which drops datagrams the host sends to itself
+    let client = network
+        .host_mut(host)
+        .connect_loopback(host, 1, 3)
+        .unwrap()
+        .peer_id();
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    let server = events
+        .iter()
+        .find_map(|event| match event.event() {
+            enet::EventNoRef::Connect { peer, data: 3, .. } => Some(*peer),
+            _ => None,
+        })
+        .unwrap();
+    assert_ne!(server, client);
+    assert!(events.iter().any(|event| matches!(
+        event.event(),
+        enet::EventNoRef::Connect { peer, data: 0, .. } if *peer == client
+    )));
+    assert!(network.host(host).peer(client).unwrap().is_loopback());
+    assert!(network.host(host).peer(server).unwrap().is_loopback());
+    assert_eq!(
+        network.host(host).peer(server).unwrap().address(),
+        Some(host)
+    );
+
+    // packets are delivered to the other end as usual
+    network
+        .host_mut(host)
+        .peer_mut(client)
+        .unwrap()
+        .send(0, &enet::Packet::reliable(b"local"))
+        .unwrap();
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(
+        events[0].is_receive_and(|event| event.peer == server && event.packet.data() == b"local")
+    );
+
+    network
+        .host_mut(host)
+        .peer_mut(client)
+        .unwrap()
+        .disconnect(4);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    assert!(events
+        .iter()
+        .any(|event| event.is_disconnect_and(|event| event.peer == server && event.data == 4)));
+}
+
+#[test]
+fn create_pair() {
+    let mut pair = enet::Host::create_pair(|| enet::HostSettings {
+        channel_limit: 2,
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(
+        pair.client.peer(pair.client_peer).unwrap().state(),
+        enet::PeerState::Connected
+    );
+    assert_eq!(
+        pair.server.peer(pair.server_peer).unwrap().state(),
+        enet::PeerState::Connected
+    );
+    assert_eq!(
+        pair.client.peer(pair.client_peer).unwrap().channel_count(),
+        2
+    );
+    pair.client
+        .peer_mut(pair.client_peer)
+        .unwrap()
+        .send(1, &enet::Packet::reliable(&[1, 2, 3]))
+        .unwrap();
+    pair.client.flush();
+    assert!(matches!(
+        pair.server.service().unwrap(),
+        Some(enet::Event::Receive { channel_id: 1, packet, .. }) if packet.data() == [1, 2, 3]
+    ));
+    pair.server
+        .peer_mut(pair.server_peer)
+        .unwrap()
+        .send(0, &enet::Packet::reliable(&[4]))
+        .unwrap();
+    pair.server.flush();
+    assert!(matches!(
+        pair.client.service().unwrap(),
+        Some(enet::Event::Receive { packet, .. }) if packet.data() == [4]
+    ));
+}
+
+#[test]
+fn header_obfuscation() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    for host in [host1, host2] {
+        network
+            .host_mut(host)
+            .add_transform(Box::new(enet::HeaderObfuscation::new(42)));
+    }
+
+    network.connect(host1, host2, 255, 0);
+    let events = network.update(100);
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().all(|event| event.is_connect()));
+
+    network.send(
+        host1,
+        host2,
+        0,
+        &enet::Packet::reliable("obfuscated".as_bytes()),
+    );
+    let events = network.update(1);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == "obfuscated".as_bytes()));
+}
+
+#[test]
+fn peer_fragment_limit() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 2,
+        maximum_peer_fragment_data: Some(3000),
+        report_malformed: true,
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    let host3 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    network.connect(host2, host1, 255, 0);
+    network.connect(host3, host1, 255, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 4);
+
+    // a peer reassembling more reliable data than its limit is disconnected
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[1; 4000]));
+    let events = network.update(10);
+    assert!(events
+        .iter()
+        .any(|event| event.is_protocol_violation_and(|event| {
+            event.from == host2 && event.kind == enet::ProtocolViolationKind::FragmentDataExceeded
+        })));
+    assert!(events
+        .iter()
+        .any(|event| event.is_disconnect_and(|event| event.from == host2 && event.to == host1)));
+
+    // without affecting the other peers
+    network.send(host3, host1, 0, &enet::Packet::reliable(&[2; 2500]));
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_receive_and(|event| event.packet.data() == [2; 2500]));
+}
+
+#[test]
+fn close() {
+    fn connected_pair(
+        settings: enet::HostSettings,
+    ) -> (
+        enet::Host<std::net::UdpSocket>,
+        enet::Host<std::net::UdpSocket>,
+    ) {
+        let socket1 = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket2 = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address2 = socket2.local_addr().unwrap();
+        let mut host1 = enet::Host::new(socket1, settings).unwrap();
+        let mut host2 = enet::Host::new(socket2, enet::HostSettings::default()).unwrap();
+        host1.connect(address2, 1, 0).unwrap();
+        let (mut connected1, mut connected2) = (false, false);
+        let start = std::time::Instant::now();
+        while !connected1 || !connected2 {
+            connected1 |= matches!(host1.service().unwrap(), Some(enet::Event::Connect { .. }));
+            connected2 |= matches!(host2.service().unwrap(), Some(enet::Event::Connect { .. }));
+            assert!(start.elapsed() < Duration::from_secs(1));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        (host1, host2)
+    }
+    fn wait_for_disconnect(host: &mut enet::Host<std::net::UdpSocket>) -> u32 {
+        let start = std::time::Instant::now();
+        loop {
+            if let Some(enet::Event::Disconnect { data, .. }) = host.service().unwrap() {
+                return data;
+            }
+            assert!(start.elapsed() < Duration::from_secs(1));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    let (host1, mut host2) = connected_pair(enet::HostSettings::default());
+    host1.close(5);
+    assert_eq!(wait_for_disconnect(&mut host2), 5);
+
+    let (host1, mut host2) = connected_pair(enet::HostSettings {
+        disconnect_on_drop: Some(6),
+        ..Default::default()
+    });
+    drop(host1);
+    assert_eq!(wait_for_disconnect(&mut host2), 6);
+}
+
+#[test]
+fn memory_budget() {
+    let mut network = Network::new();
+    let host1 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        peer_memory_budget: Some(20000),
+        ..Default::default()
+    });
+    let host2 = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        peer_memory_budget: Some(10000),
+        memory_budget_policy: enet::MemoryBudgetPolicy::Throttle,
+        ..Default::default()
+    });
+    network.connect(host2, host1, 255, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    let peer1 = network.resolve_peer(host1, host2);
+    let peer2 = network.resolve_peer(host2, host1);
+
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[1; 4000]));
+    assert!(network.host(host2).peer(peer2).unwrap().memory_usage() > 4000);
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+    assert_eq!(network.host(host1).peer(peer1).unwrap().memory_usage(), 0);
+    assert_eq!(network.host(host2).peer(peer2).unwrap().memory_usage(), 0);
+
+    // throttled peers refuse new packets until their queues drain
+    let peer = network.host_mut(host2).peer_mut(peer2).unwrap();
+    for _ in 0..3 {
+        peer.send(0, &enet::Packet::reliable(&[2; 4000])).unwrap();
+    }
+    assert_eq!(
+        peer.send(0, &enet::Packet::reliable(&[2; 4000])),
+        Err(enet::error::PeerSendError::MemoryBudgetExceeded)
+    );
+    let events = network.update(10);
+    assert_eq!(events.len(), 3);
+    network.send(host2, host1, 0, &enet::Packet::reliable(&[3; 4000]));
+    let events = network.update(10);
+    assert_eq!(events.len(), 1);
+
+    // other peers are disconnected
+    for _ in 0..6 {
+        network.send(host1, host2, 0, &enet::Packet::reliable(&[4; 4000]));
+    }
+    let events = network.update(10);
+    assert!(events
+        .iter()
+        .any(|event| event.is_disconnect_and(|event| event.to == host1)));
+}
+
+#[cfg(all(feature = "postcard", feature = "bincode"))]
+#[test]
+fn serialized_messages() {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Chat<'a> {
+        from: u32,
+        text: &'a str,
+    }
+
+    let mut network = Network::new();
+    let server = network.create_host(enet::HostSettings::default());
+    let client = network.create_host(enet::HostSettings::default());
+    network.connect(client, server, 1, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+
+    let peer = network.resolve_peer(server, client);
+    let message = Chat {
+        from: 7,
+        text: "hello",
+    };
+    let peer = network.host_mut(server).peer_mut(peer).unwrap();
+    peer.send_serialized::<enet::Postcard, _>(0, &message, enet::PacketKind::Reliable)
+        .unwrap();
+    peer.send_serialized::<enet::Bincode, _>(0, &message, enet::PacketKind::Reliable)
+        .unwrap();
+    assert!(matches!(
+        peer.send_serialized::<enet::Postcard, _>(1, &message, enet::PacketKind::Reliable),
+        Err(enet::error::SerializedSendError::Send(
+            enet::error::PeerSendError::InvalidChannel
+        ))
+    ));
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    assert!(
+        events[0].is_receive_and(|event| event.packet.deserialize::<enet::Postcard, Chat>()
+            == Ok(Chat {
+                from: 7,
+                text: "hello",
+            }))
+    );
+    assert!(events[1].is_receive_and(|event| event
+        .packet
+        .deserialize::<enet::Bincode, Chat>()
+        .is_ok_and(|chat| chat.text == "hello")));
+}
+
+#[cfg(feature = "postcard")]
+#[test]
+fn typed_channels() {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Chat(std::string::String);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Position(i32, i32);
+
+    enet::channels! {
+        ChatChannel = 0 => Reliable<Chat>,
+        PositionChannel = 1 => Unsequenced<Position>,
+    }
+
+    let mut network = Network::new();
+    let server = network.create_host(enet::HostSettings::default());
+    let client = network.create_host(enet::HostSettings::default());
+    network.connect(client, server, 2, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+
+    let peer = network.resolve_peer(server, client);
+    let peer = network.host_mut(server).peer_mut(peer).unwrap();
+    peer.send_typed::<ChatChannel, enet::Postcard>(&Chat("hi".into()))
+        .unwrap();
+    peer.send_typed::<PositionChannel, enet::Postcard>(&Position(1, -1))
+        .unwrap();
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    assert!(events[0].is_receive_and(
+        |event| event.channel_id == 0 && event.packet.kind() == enet::PacketKind::Reliable
+    ));
+    assert!(events[1].is_receive_and(|event| event.channel_id == 1
+        && event.packet.kind() == enet::PacketKind::Unreliable { sequenced: false }));
+    assert_eq!(
+        events[0]
+            .event()
+            .receive_typed::<ChatChannel, enet::Postcard>(),
+        Some(Ok(Chat("hi".into())))
+    );
+    assert_eq!(
+        events[1]
+            .event()
+            .receive_typed::<PositionChannel, enet::Postcard>(),
+        Some(Ok(Position(1, -1)))
+    );
+    // messages on other channels are ignored
+    assert!(events[0]
+        .event()
+        .receive_typed::<PositionChannel, enet::Postcard>()
+        .is_none());
+}
+
+#[cfg(feature = "crypto")]
+#[test]
+fn connect_authentication() {
+    let mut network = Network::new();
+    let server = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        connect_authentication: Some(enet::ConnectAuthentication::new([1; 32])),
+        ..Default::default()
+    });
+    let unauthenticated = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        ..Default::default()
+    });
+    let wrong_key = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        connect_authentication: Some(enet::ConnectAuthentication::new([2; 32])),
+        ..Default::default()
+    });
+    let client = network.create_host(enet::HostSettings {
+        peer_limit: 1,
+        connect_authentication: Some(enet::ConnectAuthentication::new([1; 32])),
+        ..Default::default()
+    });
+
+    network.connect(unauthenticated, server, 255, 0);
+    network.connect(wrong_key, server, 255, 0);
+    let events = network.update(100);
+    assert!(events.is_empty());
+
+    network.connect(client, server, 255, 0);
+    let events = network.update(10);
+    assert_eq!(events.len(), 2);
+    assert!(events
+        .iter()
+        .any(|event| event.is_connect_and(|event| event.to == server && event.from == client)));
+    assert!(events
+        .iter()
+        .any(|event| event.is_connect_and(|event| event.to == client && event.from == server)));
+}
+
+#[cfg(feature = "connected")]
+#[test]
+fn connected_slot_reuse() {
+    use enet::connected;
+    type Connection = enet::ReadWrite<(), std::io::Error>;
+
+    // move datagrams between each connection of the server and its counterpart on the client
+    fn pump(
+        server: &mut connected::Host<Connection>,
+        client: &mut connected::Host<Connection>,
+        connections: usize,
+    ) -> Vec<(bool, connected::EventNoRef)> {
+        let mut events = Vec::new();
+        for _ in 0..20 {
+            while let Some(event) = server.service() {
+                events.push((true, event.no_ref()));
+            }
+            while let Some(event) = client.service() {
+                events.push((false, event.no_ref()));
+            }
+            for index in 0..connections {
+                let id = connected::ConnectionID(index);
+                let mut to_client = Vec::new();
+                if let Some(connection) = server.peer_mut(id).connection_mut() {
+                    while let Some(((), datagram)) = connection.read() {
+                        to_client.push(datagram);
+                    }
+                }
+                let mut to_server = Vec::new();
+                if let Some(connection) = client.peer_mut(id).connection_mut() {
+                    while let Some(((), datagram)) = connection.read() {
+                        to_server.push(datagram);
+                    }
+                    for datagram in to_client {
+                        connection.write((), datagram);
+                    }
+                }
+                if let Some(connection) = server.peer_mut(id).connection_mut() {
+                    for datagram in to_server {
+                        connection.write((), datagram);
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    let settings = || enet::HostSettings {
+        peer_limit: 2,
+        ..Default::default()
+    };
+    let mut server = connected::Host::<Connection>::new(settings()).unwrap();
+    let mut client = connected::Host::<Connection>::new(settings()).unwrap();
+    let receiver = || connected::ConnectionKind::Receiver {
+        connection: Connection::new(),
+        timeout: Duration::from_secs(60),
+    };
+    let initiator = || connected::ConnectionKind::Initiator {
+        connection: Connection::new(),
+        channel_count: 1,
+        data: 0,
+    };
+
+    let old = server.add_connection(receiver()).unwrap().id();
+    client.add_connection(initiator()).unwrap();
+    let events = pump(&mut server, &mut client, 1);
+    assert!(events
+        .iter()
+        .any(|(to_server, event)| *to_server
+            && matches!(event, connected::EventNoRef::Connect { .. })));
+    assert!(server.peer(old).connected());
+
+    client.peer_mut(old).disconnect(0);
+    let events = pump(&mut server, &mut client, 1);
+    assert!(events.iter().any(|(to_server, event)| *to_server
+        && matches!(event, connected::EventNoRef::Disconnect { peer, .. } if *peer == old)));
+
+    // the old connections are still draining, so the new ones take the second connection slots,
+    // but the server reuses its first ENet peer slot
+    let new = server.add_connection(receiver()).unwrap().id();
+    assert_ne!(new, old);
+    client.add_connection(initiator()).unwrap();
+    pump(&mut server, &mut client, 2);
+    assert!(server.peer(new).connected());
+    assert!(!server.peer(old).connected());
+    assert_eq!(server.peer(old).round_trip_time(), Duration::ZERO);
+    assert_eq!(server.connected_peers().count(), 1);
+    assert_eq!(server.connected_peers_mut().count(), 1);
+}
@@ -16,6 +16,8 @@ use crate::{Box, Vec};
 pub struct Socket {
     sender: mpsc::Sender<(usize, Vec<u8>)>,
     receiver: mpsc::Receiver<(usize, Vec<u8>)>,
+    /// Supplied with every datagram received.
+    pub metadata: enet::ReceiveMetadata<usize>,
 }
 
 impl Socket {
@@ -26,10 +28,12 @@ impl Socket {
             Socket {
                 sender: sender1,
                 receiver: receiver1,
+                metadata: enet::ReceiveMetadata::default(),
             },
             Socket {
                 sender: sender2,
                 receiver: receiver2,
+                metadata: enet::ReceiveMetadata::default(),
             },
         )
     }
@@ -77,6 +81,23 @@ impl enet::Socket for Socket {
             Ok(None)
         }
     }
+
+    fn receive_batch_with_metadata(
+        &mut self,
+        buffers: &mut [[u8; enet::MTU_MAX]],
+        received: &mut Vec<(
+            Self::Address,
+            enet::PacketReceived,
+            enet::ReceiveMetadata<Self::Address>,
+        )>,
+    ) -> Result<(), Self::Error> {
+        let mut datagrams = Vec::new();
+        self.receive_batch(buffers, &mut datagrams)?;
+        for (address, datagram) in datagrams {
+            received.push((address, datagram, self.metadata.clone()));
+        }
+        Ok(())
+    }
 }
 
 impl enet::Address for usize {
@@ -93,7 +114,19 @@ impl enet::Address for usize {
     }
 
     fn port(&self) -> u16 {
-        self.port()
+        0
+    }
+
+    fn host_key(&self) -> Option<u128> {
+        Some(*self as u128)
+    }
+
+    fn to_bytes(&self) -> Option<Vec<u8>> {
+        Some(self.to_be_bytes().to_vec())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(usize::from_be_bytes(bytes.try_into().ok()?))
     }
 }
 
@@ -224,6 +257,17 @@ impl Network {
                             enet::Event::Receive { peer, .. } => {
                                 peer_index = peer.address().unwrap();
                             }
+                            enet::Event::ConnectRequest { peer, .. }
+                            | enet::Event::Acknowledge { peer, .. }
+                            | enet::Event::DeliveryFailed { peer, .. }
+                            | enet::Event::Reply { peer, .. }
+                            | enet::Event::PeerIdle { peer, .. }
+                            | enet::Event::PacketResent { peer, .. } => {
+                                peer_index = peer.address().unwrap();
+                            }
+                            enet::Event::ProtocolViolation { address, .. } => {
+                                peer_index = *address;
+                            }
                         }
                         events.push(Event {
                             from: peer_index,
@@ -253,6 +297,14 @@ impl Network {
         index
     }
 
+    pub fn host(&self, host: usize) -> &enet::Host<Socket> {
+        &self.hosts[host]
+    }
+
+    pub fn host_mut(&mut self, host: usize) -> &mut enet::Host<Socket> {
+        &mut self.hosts[host]
+    }
+
     pub fn resolve_peer(&self, from: usize, to: usize) -> enet::PeerID {
         self.connections[&(from, to)]
     }
@@ -269,17 +321,23 @@ impl Network {
 
     pub fn disconnect(&mut self, from: usize, to: usize, data: u32) {
         let peer = self.resolve_peer(from, to);
-        self.hosts[from].peer_mut(peer).disconnect(data);
+        self.hosts[from].peer_mut(peer).unwrap().disconnect(data);
     }
 
     pub fn disconnect_later(&mut self, from: usize, to: usize, data: u32) {
         let peer = self.resolve_peer(from, to);
-        self.hosts[from].peer_mut(peer).disconnect_later(data);
+        self.hosts[from]
+            .peer_mut(peer)
+            .unwrap()
+            .disconnect_later(data);
     }
 
     pub fn disconnect_now(&mut self, from: usize, to: usize, data: u32) {
         let peer = self.resolve_peer(from, to);
-        self.hosts[from].peer_mut(peer).disconnect_now(data);
+        self.hosts[from]
+            .peer_mut(peer)
+            .unwrap()
+            .disconnect_now(data);
         self.conditions.remove(&(from, to));
     }
 
@@ -287,13 +345,18 @@ impl Network {
         let peer = self.resolve_peer(from, to);
         self.hosts[from]
             .peer_mut(peer)
+            .unwrap()
             .send(channel_id, packet)
             .unwrap();
     }
 
+    pub fn send_raw(&mut self, from: usize, to: usize, data: &[u8]) {
+        self.sockets[to].send(from, data);
+    }
+
     pub fn round_trip_time(&self, from: usize, to: usize) -> Duration {
         let peer = self.resolve_peer(from, to);
-        self.hosts[from].peer(peer).round_trip_time()
+        self.hosts[from].peer(peer).unwrap().round_trip_time()
     }
 }
 
@@ -335,6 +398,8 @@ pub struct EventConnect {
     pub to: usize,
     pub peer: enet::PeerID,
     pub data: u32,
+    pub connect_data: u32,
+    pub channel_count: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -345,6 +410,48 @@ pub struct EventDisconnect {
     pub data: u32,
 }
 
+#[derive(Debug, Clone)]
+pub struct EventProtocolViolation {
+    pub from: usize,
+    pub to: usize,
+    pub kind: enet::ProtocolViolationKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventTag {
+    pub from: usize,
+    pub to: usize,
+    pub peer: enet::PeerID,
+    pub channel_id: u8,
+    pub tag: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventReply {
+    pub from: usize,
+    pub to: usize,
+    pub peer: enet::PeerID,
+    pub call: enet::CallID,
+    pub result: Result<enet::Packet, enet::error::RpcError>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventPeerIdle {
+    pub from: usize,
+    pub to: usize,
+    pub peer: enet::PeerID,
+    pub idle_for: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventPacketResent {
+    pub from: usize,
+    pub to: usize,
+    pub peer: enet::PeerID,
+    pub channel_id: u8,
+    pub attempts: u32,
+}
+
 #[derive(Debug, Clone)]
 pub struct EventReceive {
     pub from: usize,
@@ -352,6 +459,7 @@ pub struct EventReceive {
     pub peer: enet::PeerID,
     pub channel_id: u8,
     pub packet: enet::Packet,
+    pub received_at: Duration,
 }
 
 impl Event {
@@ -363,17 +471,30 @@ impl Event {
         self.to
     }
 
+    pub const fn event(&self) -> &enet::EventNoRef {
+        &self.event
+    }
+
     pub const fn is_connect(&self) -> bool {
         matches!(&self.event, enet::EventNoRef::Connect { .. })
     }
 
     pub fn is_connect_and(&self, and: impl Fn(EventConnect) -> bool) -> bool {
-        if let enet::EventNoRef::Connect { peer, data } = &self.event {
+        if let enet::EventNoRef::Connect {
+            peer,
+            data,
+            connect_data,
+            channel_count,
+            ..
+        } = &self.event
+        {
             and(EventConnect {
                 from: self.from,
                 to: self.to,
                 peer: *peer,
                 data: *data,
+                connect_data: *connect_data,
+                channel_count: *channel_count,
             })
         } else {
             false
@@ -406,6 +527,7 @@ impl Event {
             peer,
             channel_id,
             packet,
+            received_at,
         } = &self.event
         {
             and(EventReceive {
@@ -414,6 +536,108 @@ impl Event {
                 peer: *peer,
                 channel_id: *channel_id,
                 packet: packet.clone(),
+                received_at: *received_at,
+            })
+        } else {
+            false
+        }
+    }
+
+    pub const fn is_protocol_violation(&self) -> bool {
+        matches!(&self.event, enet::EventNoRef::ProtocolViolation { .. })
+    }
+
+    pub fn is_protocol_violation_and(&self, and: impl Fn(EventProtocolViolation) -> bool) -> bool {
+        if let enet::EventNoRef::ProtocolViolation { kind } = &self.event {
+            and(EventProtocolViolation {
+                from: self.from,
+                to: self.to,
+                kind: *kind,
+            })
+        } else {
+            false
+        }
+    }
+
+    pub fn is_acknowledge_and(&self, and: impl Fn(EventTag) -> bool) -> bool {
+        if let enet::EventNoRef::Acknowledge {
+            peer,
+            channel_id,
+            tag,
+            ..
+        } = &self.event
+        {
+            and(EventTag {
+                from: self.from,
+                to: self.to,
+                peer: *peer,
+                channel_id: *channel_id,
+                tag: *tag,
+            })
+        } else {
+            false
+        }
+    }
+
+    pub fn is_delivery_failed_and(&self, and: impl Fn(EventTag) -> bool) -> bool {
+        if let enet::EventNoRef::DeliveryFailed {
+            peer,
+            channel_id,
+            tag,
+        } = &self.event
+        {
+            and(EventTag {
+                from: self.from,
+                to: self.to,
+                peer: *peer,
+                channel_id: *channel_id,
+                tag: *tag,
+            })
+        } else {
+            false
+        }
+    }
+
+    pub fn is_peer_idle_and(&self, and: impl Fn(EventPeerIdle) -> bool) -> bool {
+        if let enet::EventNoRef::PeerIdle { peer, idle_for } = &self.event {
+            and(EventPeerIdle {
+                from: self.from,
+                to: self.to,
+                peer: *peer,
+                idle_for: *idle_for,
+            })
+        } else {
+            false
+        }
+    }
+
+    pub fn is_packet_resent_and(&self, and: impl Fn(EventPacketResent) -> bool) -> bool {
+        if let enet::EventNoRef::PacketResent {
+            peer,
+            channel_id,
+            attempts,
+        } = &self.event
+        {
+            and(EventPacketResent {
+                from: self.from,
+                to: self.to,
+                peer: *peer,
+                channel_id: *channel_id,
+                attempts: *attempts,
+            })
+        } else {
+            false
+        }
+    }
+
+    pub fn is_reply_and(&self, and: impl Fn(EventReply) -> bool) -> bool {
+        if let enet::EventNoRef::Reply { peer, call, result } = &self.event {
+            and(EventReply {
+                from: self.from,
+                to: self.to,
+                peer: *peer,
+                call: *call,
+                result: result.clone(),
             })
         } else {
             false
@@ -0,0 +1,675 @@
+//! Property testing support, enabled with the `proptest` and `arbitrary` features.
+//!
+//! A [`Scenario`] is a sequence of [`Action`]s taken by a client connected to a server, with a
+//! [`NetworkSchedule`] deciding the fate of each datagram between them. [`run_scenario`] plays
+//! it out between two in-memory hosts on a simulated clock, then lets the network settle, and
+//! [`Deliveries::check`] verifies ENet's delivery guarantees held:
+//!
+//! - Every packet arrives at most once, on the channel it was sent on, with its data intact.
+//! - Every reliable packet arrives.
+//! - Packets which aren't unsequenced arrive in the order they were sent on their channel.
+//!
+//! With the `proptest` feature, every type implements
+//! [`proptest::arbitrary::Arbitrary`](https://docs.rs/proptest/latest/proptest/arbitrary/trait.Arbitrary.html),
+//! and with the `arbitrary` feature, [`arbitrary::Arbitrary`](https://docs.rs/arbitrary).
+//!
+//! ```
+//! use rusty_enet::{
+//!     testing::{run_scenario, Action, NetworkSchedule, PacketSpec},
+//!     PacketKind,
+//! };
+//!
+//! let actions = [
+//!     Action::Send(PacketSpec::new(0, PacketKind::Reliable, b"hello")),
+//!     Action::Advance(100),
+//! ];
+//! let deliveries = run_scenario(&actions, &NetworkSchedule::lossy(3));
+//! deliveries.check().unwrap();
+//! ```
+
+use core::{convert::Infallible, fmt, time::Duration};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use crate::{Box, EventNoRef, Host, HostSettings, Packet, PacketKind, PeerID, ReadWrite, Vec};
+
+/// The number of channels connected in a scenario. Channel IDs are taken modulo this.
+pub const SCENARIO_CHANNELS: u8 = 4;
+
+/// The maximum length of the data generated for a [`PacketSpec`], enough for several fragments.
+pub const MAXIMUM_SPEC_DATA: usize = 4096;
+
+const STEP_MILLIS: u64 = 10;
+const SETTLE_STEPS: usize = 6000;
+
+/// A packet to send in a [`Scenario`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketSpec {
+    /// The channel to send on, modulo [`SCENARIO_CHANNELS`].
+    pub channel_id: u8,
+    /// The kind of packet.
+    pub kind: PacketKind,
+    /// The packet's data. The packet's index in the scenario is prepended when it is sent.
+    pub data: Vec<u8>,
+}
+
+impl PacketSpec {
+    /// Create a packet spec.
+    #[must_use]
+    pub fn new(channel_id: u8, kind: PacketKind, data: &[u8]) -> Self {
+        Self {
+            channel_id,
+            kind,
+            data: data.to_vec(),
+        }
+    }
+}
+
+/// A step of a [`Scenario`], taken by the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Queue a packet to the server.
+    Send(PacketSpec),
+    /// Flush queued packets, see [`Host::flush`].
+    Flush,
+    /// Service both hosts and exchange datagrams for this many milliseconds, in 10 millisecond
+    /// steps.
+    Advance(u16),
+}
+
+/// What happens to a datagram in transit, decided by a [`NetworkSchedule`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatagramFate {
+    /// The datagram arrives on the next step.
+    Deliver,
+    /// The datagram is lost.
+    Drop,
+    /// The datagram arrives twice.
+    Duplicate,
+    /// The datagram arrives after this many extra steps, reordering it with later datagrams.
+    Delay(u8),
+}
+
+/// The fates of datagrams sent during a [`Scenario`], in either direction, in the order they are
+/// sent. Once every fate has been used, datagrams are delivered normally.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkSchedule {
+    /// The fate of each datagram.
+    pub fates: Vec<DatagramFate>,
+}
+
+impl NetworkSchedule {
+    /// A schedule delivering every datagram.
+    #[must_use]
+    pub fn perfect() -> Self {
+        Self::default()
+    }
+
+    /// A schedule dropping every `n`th of the first 256 datagrams.
+    #[must_use]
+    pub fn lossy(n: usize) -> Self {
+        Self {
+            fates: (1..=256)
+                .map(|index| {
+                    if index % n.max(1) == 0 {
+                        DatagramFate::Drop
+                    } else {
+                        DatagramFate::Deliver
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A sequence of actions and the network they run on. See the [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scenario {
+    /// The actions taken by the client.
+    pub actions: Vec<Action>,
+    /// The fates of datagrams while the actions run.
+    pub schedule: NetworkSchedule,
+}
+
+impl Scenario {
+    /// Run the scenario with [`run_scenario`].
+    #[must_use]
+    pub fn run(&self) -> Deliveries {
+        run_scenario(&self.actions, &self.schedule)
+    }
+}
+
+/// A packet sent by the client, recorded by [`run_scenario`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentPacket {
+    /// The channel the packet was sent on.
+    pub channel_id: u8,
+    /// The kind of packet.
+    pub kind: PacketKind,
+    /// The packet's data, without its index.
+    pub data: Vec<u8>,
+}
+
+/// A packet received by the server, recorded by [`run_scenario`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedPacket {
+    /// The channel the packet was received on.
+    pub channel_id: u8,
+    /// The packet's data, including its index.
+    pub data: Vec<u8>,
+}
+
+/// The packets sent and received while running a scenario, returned by [`run_scenario`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deliveries {
+    /// The packets sent, indexed by the index prepended to their data.
+    pub sent: Vec<SentPacket>,
+    /// The packets received, in the order they were received.
+    pub received: Vec<ReceivedPacket>,
+    /// Whether either host saw a disconnection.
+    pub disconnected: bool,
+}
+
+/// A delivery guarantee which didn't hold, returned by [`Deliveries::check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// A packet was received whose index or data doesn't match any sent packet.
+    Corrupted {
+        /// The position of the packet in [`Deliveries::received`].
+        position: usize,
+    },
+    /// A packet was received on a different channel than it was sent on.
+    WrongChannel {
+        /// The index of the packet.
+        index: usize,
+    },
+    /// A packet was received more than once.
+    Duplicate {
+        /// The index of the packet.
+        index: usize,
+    },
+    /// A packet which isn't unsequenced was received after a packet sent later on its channel.
+    OutOfOrder {
+        /// The index of the packet.
+        index: usize,
+    },
+    /// A reliable packet was never received.
+    Lost {
+        /// The index of the packet.
+        index: usize,
+    },
+    /// The connection was lost during the scenario.
+    Disconnected,
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Corrupted { position } => write!(f, "received packet {position} is corrupted"),
+            Self::WrongChannel { index } => {
+                write!(f, "packet {index} arrived on the wrong channel")
+            }
+            Self::Duplicate { index } => write!(f, "packet {index} arrived more than once"),
+            Self::OutOfOrder { index } => write!(f, "packet {index} arrived out of order"),
+            Self::Lost { index } => write!(f, "reliable packet {index} was lost"),
+            Self::Disconnected => f.write_str("the connection was lost"),
+        }
+    }
+}
+
+impl std::error::Error for InvariantViolation {}
+
+impl Deliveries {
+    /// Check ENet's delivery guarantees held. See the [module documentation](self).
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`InvariantViolation`] found.
+    pub fn check(&self) -> Result<(), InvariantViolation> {
+        if self.disconnected {
+            return Err(InvariantViolation::Disconnected);
+        }
+        let mut seen = vec![false; self.sent.len()];
+        let mut last_sequenced = [None; SCENARIO_CHANNELS as usize];
+        for (position, received) in self.received.iter().enumerate() {
+            let index = received
+                .data
+                .first_chunk()
+                .map(|index| u32::from_be_bytes(*index) as usize)
+                .filter(|index| {
+                    self.sent
+                        .get(*index)
+                        .is_some_and(|sent| sent.data == received.data[4..])
+                })
+                .ok_or(InvariantViolation::Corrupted { position })?;
+            let sent = &self.sent[index];
+            if sent.channel_id != received.channel_id {
+                return Err(InvariantViolation::WrongChannel { index });
+            }
+            if seen[index] {
+                return Err(InvariantViolation::Duplicate { index });
+            }
+            seen[index] = true;
+            if !is_unsequenced(sent.kind) {
+                let last = &mut last_sequenced[usize::from(sent.channel_id)];
+                if last.is_some_and(|last| last > index) {
+                    return Err(InvariantViolation::OutOfOrder { index });
+                }
+                *last = Some(index);
+            }
+        }
+        if let Some(index) = self
+            .sent
+            .iter()
+            .zip(&seen)
+            .position(|(sent, seen)| sent.kind == PacketKind::Reliable && !seen)
+        {
+            return Err(InvariantViolation::Lost { index });
+        }
+        Ok(())
+    }
+}
+
+fn is_unsequenced(kind: PacketKind) -> bool {
+    matches!(
+        kind,
+        PacketKind::Unreliable { sequenced: false }
+            | PacketKind::AlwaysUnreliable { sequenced: false }
+    )
+}
+
+type ScenarioSocket = ReadWrite<SocketAddr, Infallible>;
+
+struct InFlight {
+    arrival: usize,
+    to_server: bool,
+    data: Vec<u8>,
+}
+
+struct Simulation<'a> {
+    time: Arc<AtomicU64>,
+    client: Host<ScenarioSocket>,
+    server: Host<ScenarioSocket>,
+    step: usize,
+    in_flight: Vec<InFlight>,
+    fates: core::slice::Iter<'a, DatagramFate>,
+    deliveries: Deliveries,
+    connected: usize,
+}
+
+impl Simulation<'_> {
+    fn client_address() -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 1))
+    }
+
+    fn server_address() -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 2))
+    }
+
+    fn host(time: &Arc<AtomicU64>, seed: u32) -> Host<ScenarioSocket> {
+        let time = time.clone();
+        Host::new(
+            ScenarioSocket::new(),
+            HostSettings {
+                peer_limit: 1,
+                time: Box::new(move || Duration::from_millis(time.load(Ordering::Relaxed))),
+                seed: Some(seed),
+                ..HostSettings::default()
+            },
+        )
+        .expect("Scenario host settings should be valid.")
+    }
+
+    fn advance(&mut self) {
+        self.step += 1;
+        self.time.fetch_add(STEP_MILLIS, Ordering::Relaxed);
+        let (arrived, in_flight) = core::mem::take(&mut self.in_flight)
+            .into_iter()
+            .partition(|datagram| datagram.arrival <= self.step);
+        self.in_flight = in_flight;
+        for datagram in arrived {
+            if datagram.to_server {
+                self.server
+                    .socket_mut()
+                    .write(Self::client_address(), datagram.data);
+            } else {
+                self.client
+                    .socket_mut()
+                    .write(Self::server_address(), datagram.data);
+            }
+        }
+        while let Some(event) = self.client.service().unwrap() {
+            let event = event.no_ref();
+            self.handle_event(event, false);
+        }
+        while let Some(event) = self.server.service().unwrap() {
+            let event = event.no_ref();
+            self.handle_event(event, true);
+        }
+        self.send_datagrams(true);
+        self.send_datagrams(false);
+    }
+
+    fn handle_event(&mut self, event: EventNoRef, server: bool) {
+        match event {
+            EventNoRef::Connect { .. } => self.connected += 1,
+            EventNoRef::Disconnect { .. } => self.deliveries.disconnected = true,
+            EventNoRef::Receive {
+                channel_id, packet, ..
+            } if server => self.deliveries.received.push(ReceivedPacket {
+                channel_id,
+                data: packet.data().to_vec(),
+            }),
+            _ => {}
+        }
+    }
+
+    fn send_datagrams(&mut self, to_server: bool) {
+        let host = if to_server {
+            &mut self.client
+        } else {
+            &mut self.server
+        };
+        while let Some((_, data)) = host.socket_mut().read() {
+            let fate = self.fates.next().copied().unwrap_or(DatagramFate::Deliver);
+            let arrival = self.step + 1;
+            match fate {
+                DatagramFate::Deliver => {}
+                DatagramFate::Drop => continue,
+                DatagramFate::Duplicate => self.in_flight.push(InFlight {
+                    arrival,
+                    to_server,
+                    data: data.clone(),
+                }),
+                DatagramFate::Delay(steps) => {
+                    self.in_flight.push(InFlight {
+                        arrival: arrival + usize::from(steps),
+                        to_server,
+                        data,
+                    });
+                    continue;
+                }
+            }
+            self.in_flight.push(InFlight {
+                arrival,
+                to_server,
+                data,
+            });
+        }
+    }
+
+    fn reliable_outstanding(&self) -> bool {
+        let reliable = self
+            .deliveries
+            .sent
+            .iter()
+            .filter(|sent| sent.kind == PacketKind::Reliable)
+            .count();
+        let received = self
+            .deliveries
+            .received
+            .iter()
+            .filter(|received| {
+                received
+                    .data
+                    .first_chunk()
+                    .and_then(|index| {
+                        self.deliveries
+                            .sent
+                            .get(u32::from_be_bytes(*index) as usize)
+                    })
+                    .is_some_and(|sent| sent.kind == PacketKind::Reliable)
+            })
+            .count();
+        received < reliable
+    }
+}
+
+/// Run a scenario between two in-memory hosts, returning the packets sent and received. See the
+/// [module documentation](self).
+///
+/// The client connects with a perfect network first. After the actions run, datagrams are
+/// delivered normally for up to a minute of simulated time, until every reliable packet arrives.
+///
+/// # Panics
+///
+/// Panics if the hosts fail to connect, which would be a bug.
+#[must_use]
+pub fn run_scenario(actions: &[Action], schedule: &NetworkSchedule) -> Deliveries {
+    let time = Arc::new(AtomicU64::new(1000));
+    let mut simulation = Simulation {
+        client: Simulation::host(&time, 1),
+        server: Simulation::host(&time, 2),
+        time,
+        step: 0,
+        in_flight: Vec::new(),
+        fates: [].iter(),
+        deliveries: Deliveries::default(),
+        connected: 0,
+    };
+    let peer: PeerID = simulation
+        .client
+        .connect(
+            Simulation::server_address(),
+            usize::from(SCENARIO_CHANNELS),
+            0,
+        )
+        .unwrap()
+        .peer_id();
+    while simulation.connected < 2 {
+        assert!(simulation.step < 1000, "Scenario hosts failed to connect.");
+        simulation.advance();
+    }
+    simulation.fates = schedule.fates.iter();
+    for action in actions {
+        match action {
+            Action::Send(spec) => {
+                let channel_id = spec.channel_id % SCENARIO_CHANNELS;
+                let index = u32::try_from(simulation.deliveries.sent.len()).unwrap();
+                let mut data = index.to_be_bytes().to_vec();
+                data.extend_from_slice(&spec.data);
+                let packet = Packet::new(&data, spec.kind);
+                if simulation
+                    .client
+                    .peer_mut(peer)
+                    .unwrap()
+                    .send(channel_id, &packet)
+                    .is_ok()
+                {
+                    simulation.deliveries.sent.push(SentPacket {
+                        channel_id,
+                        kind: spec.kind,
+                        data: spec.data.clone(),
+                    });
+                }
+            }
+            Action::Flush => {
+                simulation.client.flush();
+            }
+            Action::Advance(millis) => {
+                for _ in 0..u64::from(*millis).div_ceil(STEP_MILLIS) {
+                    simulation.advance();
+                }
+            }
+        }
+    }
+    simulation.fates = [].iter();
+    for _ in 0..SETTLE_STEPS {
+        if !simulation.reliable_outstanding() && simulation.in_flight.is_empty() {
+            break;
+        }
+        simulation.advance();
+    }
+    // deliver anything still queued
+    for _ in 0..10 {
+        simulation.advance();
+    }
+    simulation.deliveries
+}
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls {
+    use arbitrary::{Arbitrary, Result, Unstructured};
+
+    use super::{Action, DatagramFate, NetworkSchedule, PacketSpec, Scenario, MAXIMUM_SPEC_DATA};
+    use crate::PacketKind;
+
+    impl<'a> Arbitrary<'a> for PacketSpec {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            let channel_id = u.arbitrary()?;
+            let kind = match u.int_in_range(0..=4)? {
+                0 => PacketKind::Reliable,
+                1 => PacketKind::Unreliable { sequenced: true },
+                2 => PacketKind::Unreliable { sequenced: false },
+                3 => PacketKind::AlwaysUnreliable { sequenced: true },
+                _ => PacketKind::AlwaysUnreliable { sequenced: false },
+            };
+            // mostly small packets, so the input lasts for more of them
+            let maximum = if u.ratio(1, 5)? {
+                MAXIMUM_SPEC_DATA
+            } else {
+                64
+            };
+            let length = u.int_in_range(0..=maximum)?;
+            let data = u.bytes(length.min(u.len()))?.to_vec();
+            Ok(Self {
+                channel_id,
+                kind,
+                data,
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for Action {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(match u.int_in_range(0..=3)? {
+                0 | 1 => Action::Send(u.arbitrary()?),
+                2 => Action::Flush,
+                _ => Action::Advance(u.int_in_range(0..=500)?),
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for DatagramFate {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(match u.int_in_range(0..=5)? {
+                0..=2 => DatagramFate::Deliver,
+                3 => DatagramFate::Drop,
+                4 => DatagramFate::Duplicate,
+                _ => DatagramFate::Delay(u.int_in_range(1..=20)?),
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for NetworkSchedule {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            let length = u.int_in_range(0..=256)?;
+            let fates = (0..length).map(|_| u.arbitrary()).collect::<Result<_>>()?;
+            Ok(Self { fates })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for Scenario {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            // the schedule first, so it isn't starved by the packets' data
+            let schedule = u.arbitrary()?;
+            let length = u.int_in_range(0..=64)?;
+            let actions = (0..length).map(|_| u.arbitrary()).collect::<Result<_>>()?;
+            Ok(Self { actions, schedule })
+        }
+    }
+}
+
+/// [`proptest`](https://docs.rs/proptest) strategies for scenarios, enabled with the `proptest`
+/// feature. Each type also implements `proptest::arbitrary::Arbitrary` with these strategies.
+#[cfg(feature = "proptest")]
+pub mod strategies {
+    use proptest::{
+        arbitrary::Arbitrary,
+        collection::vec,
+        prelude::{any, prop_oneof, BoxedStrategy, Just, Strategy},
+    };
+
+    use super::{Action, DatagramFate, NetworkSchedule, PacketSpec, Scenario, MAXIMUM_SPEC_DATA};
+    use crate::PacketKind;
+
+    /// Any [`PacketKind`].
+    pub fn packet_kind() -> impl Strategy<Value = PacketKind> {
+        prop_oneof![
+            Just(PacketKind::Reliable),
+            any::<bool>().prop_map(|sequenced| PacketKind::Unreliable { sequenced }),
+            any::<bool>().prop_map(|sequenced| PacketKind::AlwaysUnreliable { sequenced }),
+        ]
+    }
+
+    /// A [`PacketSpec`] with up to [`MAXIMUM_SPEC_DATA`] bytes of data, mostly small.
+    pub fn packet_spec() -> impl Strategy<Value = PacketSpec> {
+        let data = prop_oneof![
+            4 => vec(any::<u8>(), 0..64),
+            1 => vec(any::<u8>(), 0..=MAXIMUM_SPEC_DATA),
+        ];
+        (any::<u8>(), packet_kind(), data).prop_map(|(channel_id, kind, data)| PacketSpec {
+            channel_id,
+            kind,
+            data,
+        })
+    }
+
+    /// An [`Action`], sending packets most often.
+    pub fn action() -> impl Strategy<Value = Action> {
+        prop_oneof![
+            2 => packet_spec().prop_map(Action::Send),
+            1 => Just(Action::Flush),
+            1 => (0..=500_u16).prop_map(Action::Advance),
+        ]
+    }
+
+    /// A [`DatagramFate`], delivering datagrams most often.
+    pub fn datagram_fate() -> impl Strategy<Value = DatagramFate> {
+        prop_oneof![
+            3 => Just(DatagramFate::Deliver),
+            1 => Just(DatagramFate::Drop),
+            1 => Just(DatagramFate::Duplicate),
+            1 => (1..=20_u8).prop_map(DatagramFate::Delay),
+        ]
+    }
+
+    /// A [`NetworkSchedule`] of up to 256 fates.
+    pub fn network_schedule() -> impl Strategy<Value = NetworkSchedule> {
+        vec(datagram_fate(), 0..=256).prop_map(|fates| NetworkSchedule { fates })
+    }
+
+    /// A [`Scenario`] of up to 64 actions.
+    pub fn scenario() -> impl Strategy<Value = Scenario> {
+        (vec(action(), 0..=64), network_schedule())
+            .prop_map(|(actions, schedule)| Scenario { actions, schedule })
+    }
+
+    macro_rules! impl_arbitrary {
+        ($($ty:ty => $strategy:ident),* $(,)?) => {
+            $(
+                impl Arbitrary for $ty {
+                    type Parameters = ();
+                    type Strategy = BoxedStrategy<Self>;
+
+                    fn arbitrary_with((): ()) -> Self::Strategy {
+                        $strategy().boxed()
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_arbitrary! {
+        PacketSpec => packet_spec,
+        Action => action,
+        DatagramFate => datagram_fate,
+        NetworkSchedule => network_schedule,
+        Scenario => scenario,
+    }
+}
@@ -0,0 +1,227 @@
+use crate::Vec;
+
+/// An interface for transforming whole ENet datagrams, added with
+/// [`Host::add_transform`](`crate::Host::add_transform`).
+///
+/// Transforms run after compression and checksums, so they see exactly the bytes ENet would
+/// otherwise hand to the socket. Outgoing datagrams pass through every transform in the order they
+/// were added, and incoming datagrams pass through them in reverse, so each
+/// [`PacketTransform::decode`] must undo its own [`PacketTransform::encode`].
+///
+/// This is useful for stacking obfuscation, custom framing, or anything else which needs to see
+/// the complete datagram without implementing a [`Socket`](`crate::Socket`) wrapper.
+pub trait PacketTransform: Send {
+    /// Transform an outgoing datagram in place. Return `false` to drop the datagram.
+    fn encode(&mut self, data: &mut Vec<u8>) -> bool;
+    /// Reverse [`PacketTransform::encode`] on an incoming datagram in place. Return `false` to
+    /// drop the datagram.
+    fn decode(&mut self, data: &mut Vec<u8>) -> bool;
+    /// Transform an outgoing datagram for a connection both hosts know the connect ID of. This
+    /// is every datagram sent to a peer, except connect commands which haven't been answered yet.
+    ///
+    /// Defaults to [`PacketTransform::encode`].
+    fn encode_connection(&mut self, data: &mut Vec<u8>, connect_id: u32) -> bool {
+        _ = connect_id;
+        self.encode(data)
+    }
+    /// Reverse [`PacketTransform::encode`] or [`PacketTransform::encode_connection`] on an
+    /// incoming datagram in place. `connect_id` looks up the connect ID of the peer at the
+    /// datagram's address, or returns [`None`] if there is no such peer.
+    ///
+    /// Defaults to [`PacketTransform::decode`].
+    fn decode_connection(
+        &mut self,
+        data: &mut Vec<u8>,
+        connect_id: &mut dyn FnMut() -> Option<u32>,
+    ) -> bool {
+        _ = connect_id;
+        self.decode(data)
+    }
+}
+
+/// Bytes added to every datagram by [`HeaderObfuscation`].
+pub const HEADER_OBFUSCATION_OVERHEAD: usize = 5;
+
+const SALT_SIZE: usize = 4;
+const HOST_KEY: u8 = 0;
+const CONNECTION_KEY: u8 = 1;
+
+/// A [`PacketTransform`] which scrambles every datagram, including the ENet protocol and command
+/// headers, so traffic is not trivially fingerprintable as ENet.
+///
+/// Each datagram is prefixed with a 4 byte salt, and the rest is XORed with a keystream derived
+/// from the salt and a key. Connect commands which haven't been answered yet use the key given to
+/// [`HeaderObfuscation::new`], and every later datagram uses a key derived from it and the
+/// connection's connect ID, so no two connections share a keystream. One obfuscated byte records
+/// which kind of key was used. Both hosts must use the same key.
+///
+/// This is obfuscation, not security: the keystream is not cryptographically strong and anyone
+/// who knows the key can reverse it. Use `EncryptedSocket` (with the `crypto` feature) for
+/// confidentiality.
+///
+/// ```
+/// use std::net::UdpSocket;
+///
+/// use rusty_enet::{HeaderObfuscation, Host, HostSettings};
+///
+/// let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+/// let mut host = Host::new(socket, HostSettings::default()).unwrap();
+/// host.add_transform(Box::new(HeaderObfuscation::new(0x1234_5678)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HeaderObfuscation {
+    key: u64,
+    counter: u64,
+}
+
+impl HeaderObfuscation {
+    /// Create a header obfuscation transform using `key`.
+    #[must_use]
+    pub fn new(key: u64) -> Self {
+        Self { key, counter: 0 }
+    }
+
+    fn connection_key(&self, connect_id: u32) -> u64 {
+        let mut state = self.key ^ (u64::from(connect_id) << 32 | u64::from(connect_id));
+        split_mix(&mut state)
+    }
+
+    fn obfuscate(&mut self, data: &mut Vec<u8>, kind: u8, key: u64) {
+        let mut state = self.key ^ self.counter;
+        self.counter = self.counter.wrapping_add(1);
+        let salt = (split_mix(&mut state) as u32).to_be_bytes();
+        let mut state = self.key ^ u64::from(u32::from_be_bytes(salt));
+        let kind = kind ^ split_mix(&mut state) as u8;
+        apply(key, salt, data);
+        data.splice(0..0, salt.into_iter().chain([kind]));
+    }
+
+    /// Remove the salt and key kind from an obfuscated datagram.
+    fn deobfuscate(&self, data: &mut Vec<u8>) -> Option<([u8; SALT_SIZE], u8)> {
+        if data.len() < HEADER_OBFUSCATION_OVERHEAD {
+            return None;
+        }
+        let mut salt = [0; SALT_SIZE];
+        salt.copy_from_slice(&data[..SALT_SIZE]);
+        let mut state = self.key ^ u64::from(u32::from_be_bytes(salt));
+        let kind = data[SALT_SIZE] ^ split_mix(&mut state) as u8;
+        data.drain(..HEADER_OBFUSCATION_OVERHEAD);
+        Some((salt, kind))
+    }
+}
+
+fn apply(key: u64, salt: [u8; SALT_SIZE], data: &mut [u8]) {
+    let mut state = key ^ u64::from(u32::from_be_bytes(salt)).rotate_left(32);
+    for chunk in data.chunks_mut(8) {
+        let keystream = split_mix(&mut state).to_le_bytes();
+        for (byte, key) in chunk.iter_mut().zip(keystream) {
+            *byte ^= key;
+        }
+    }
+}
+
+impl PacketTransform for HeaderObfuscation {
+    fn encode(&mut self, data: &mut Vec<u8>) -> bool {
+        self.obfuscate(data, HOST_KEY, self.key);
+        true
+    }
+
+    fn decode(&mut self, data: &mut Vec<u8>) -> bool {
+        self.decode_connection(data, &mut || None)
+    }
+
+    fn encode_connection(&mut self, data: &mut Vec<u8>, connect_id: u32) -> bool {
+        self.obfuscate(data, CONNECTION_KEY, self.connection_key(connect_id));
+        true
+    }
+
+    fn decode_connection(
+        &mut self,
+        data: &mut Vec<u8>,
+        connect_id: &mut dyn FnMut() -> Option<u32>,
+    ) -> bool {
+        let (salt, key) = match self.deobfuscate(data) {
+            Some((salt, HOST_KEY)) => (salt, self.key),
+            Some((salt, CONNECTION_KEY)) => match connect_id() {
+                Some(connect_id) => (salt, self.connection_key(connect_id)),
+                None => return false,
+            },
+            _ => return false,
+        };
+        apply(key, salt, data);
+        true
+    }
+}
+
+fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HeaderObfuscation, PacketTransform, HEADER_OBFUSCATION_OVERHEAD};
+
+    #[test]
+    fn header_obfuscation_round_trip() {
+        let mut sender = HeaderObfuscation::new(42);
+        let mut receiver = HeaderObfuscation::new(42);
+        let original: crate::Vec<u8> = (0..64).collect();
+
+        let mut first = original.clone();
+        assert!(sender.encode(&mut first));
+        assert_eq!(first.len(), original.len() + HEADER_OBFUSCATION_OVERHEAD);
+        // every command header is hidden, not just the first
+        assert_ne!(first[HEADER_OBFUSCATION_OVERHEAD..][..16], original[..16]);
+        assert_ne!(first[HEADER_OBFUSCATION_OVERHEAD + 16..], original[16..]);
+
+        // the same datagram looks different every time it is sent
+        let mut second = original.clone();
+        assert!(sender.encode(&mut second));
+        assert_ne!(first, second);
+
+        assert!(receiver.decode(&mut first));
+        assert!(receiver.decode(&mut second));
+        assert_eq!(first, original);
+        assert_eq!(second, original);
+
+        // short datagrams are obfuscated too, and truncated ones are dropped
+        let mut short = crate::Vec::from([1, 2]);
+        assert!(sender.encode(&mut short));
+        assert!(receiver.decode(&mut short));
+        assert_eq!(short, [1, 2]);
+        assert!(!receiver.decode(&mut crate::Vec::from([1, 2])));
+    }
+
+    #[test]
+    fn header_obfuscation_connection_key() {
+        let mut sender = HeaderObfuscation::new(42);
+        let mut receiver = HeaderObfuscation::new(42);
+        let original: crate::Vec<u8> = (0..64).collect();
+
+        let mut connection = original.clone();
+        assert!(sender.encode_connection(&mut connection, 7));
+        let mut other = original.clone();
+        assert!(sender.encode_connection(&mut other, 8));
+
+        // each connection needs its own connect ID to be decoded
+        let mut wrong = connection.clone();
+        assert!(receiver.decode_connection(&mut wrong, &mut || Some(8)));
+        assert_ne!(wrong, original);
+        let mut unknown = connection.clone();
+        assert!(!receiver.decode(&mut unknown));
+        assert!(receiver.decode_connection(&mut connection, &mut || Some(7)));
+        assert_eq!(connection, original);
+        assert!(receiver.decode_connection(&mut other, &mut || Some(8)));
+        assert_eq!(other, original);
+
+        // datagrams before the connect ID is known never need a lookup
+        let mut connect = original.clone();
+        assert!(sender.encode(&mut connect));
+        assert!(receiver.decode_connection(&mut connect, &mut || unreachable!()));
+        assert_eq!(connect, original);
+    }
+}
@@ -20,3 +20,61 @@ impl Version {
         }
     }
 }
+
+/// The variant of ENet's 1.3.x protocol a host speaks, configured with
+/// [`HostSettings::protocol_version`](`crate::HostSettings::protocol_version`).
+///
+/// Each connection speaks the lower of both hosts' versions. A host speaking
+/// [`ProtocolVersion::Extended`] marks the session ID of its connection requests, which other
+/// hosts treat as any other session ID. A host accepting a marked request also speaking
+/// [`ProtocolVersion::Extended`] confirms it once connected, and the connection is upgraded. Any
+/// other connection stays on [`ProtocolVersion::Enet`], so extensions are never sent to hosts
+/// which would reject them. Get a connection's version with
+/// [`Peer::protocol_version`](`crate::Peer::protocol_version`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ProtocolVersion {
+    /// The protocol exactly as C ENet 1.3.x speaks it.
+    Enet = 0,
+    /// ENet's protocol, with rusty_enet's extensions sent on the protocol's reserved channel,
+    /// such as [`HostSettings::ping_payloads`](`crate::HostSettings::ping_payloads`) and
+    /// [`HostSettings::clock_sync`](`crate::HostSettings::clock_sync`).
+    #[default]
+    Extended = 1,
+    /// [`ProtocolVersion::Extended`], with peer IDs past
+    /// [`PROTOCOL_MAXIMUM_PEER_ID`](`crate::consts::PROTOCOL_MAXIMUM_PEER_ID`), allowing a
+    /// [`HostSettings::peer_limit`](`crate::HostSettings::peer_limit`) up to
+    /// [`PROTOCOL_EXTENDED_MAXIMUM_PEER_ID`](`crate::consts::PROTOCOL_EXTENDED_MAXIMUM_PEER_ID`).
+    ///
+    /// Datagrams to these peers carry their full ID after the header. Hosts with more peers than
+    /// [`PROTOCOL_MAXIMUM_PEER_ID`](`crate::consts::PROTOCOL_MAXIMUM_PEER_ID`) leave the two slots
+    /// below it unused, and only give slots past it to incoming connections which negotiated
+    /// this version, so the remaining slots go to other hosts and outgoing connections.
+    ExtendedPeerIds = 2,
+}
+
+impl ProtocolVersion {
+    pub(crate) fn from_u8(version: u8) -> Option<ProtocolVersion> {
+        match version {
+            0 => Some(ProtocolVersion::Enet),
+            1 => Some(ProtocolVersion::Extended),
+            2 => Some(ProtocolVersion::ExtendedPeerIds),
+            _ => None,
+        }
+    }
+
+    /// Mark the outgoing session ID of a connection request with this version. C ENet only sends
+    /// `0xFF` or IDs within the header's session mask, and any other value is taken modulo the
+    /// mask, so the mark is ignored by other hosts.
+    pub(crate) fn mark_session_id(self, session_id: u8) -> u8 {
+        0x80 | (self as u8) << 2 | session_id & 3
+    }
+
+    /// The version a connection request's outgoing session ID was marked with, or
+    /// [`ProtocolVersion::Enet`] if it isn't marked.
+    pub(crate) fn from_session_id(session_id: u8) -> ProtocolVersion {
+        if session_id & 0x80 == 0 || session_id == 0xFF {
+            return ProtocolVersion::Enet;
+        }
+        ProtocolVersion::from_u8(session_id >> 2 & 0x1F).unwrap_or(ProtocolVersion::Enet)
+    }
+}